@@ -1,6 +1,17 @@
-//! Minimal library to generate `asciicast(v2)`.
+//! Minimal library to generate `asciicast(v2)` and `asciicast(v3)`.
 
+use std::collections::BTreeMap;
 use std::io::{self, Write};
+use std::ops::{Bound, RangeBounds};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "vt100")]
+mod screen;
+
+#[cfg(feature = "vt100")]
+pub use self::screen::Screen;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Asciicast {
@@ -9,19 +20,27 @@ pub struct Asciicast {
 }
 
 impl Asciicast {
-    const VERSION: u8 = 2;
-
     pub fn new(width: u16, height: u16, events: impl IntoIterator<Item = Event>) -> Self {
-        Asciicast {
-            header: Header { version: Self::VERSION, width, height },
-            events: events.into_iter().collect(),
-        }
+        Self::with_header(Header::new(width, height), events)
     }
 
-    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
-        self.header.write_to(&mut w)?;
+    pub fn with_header(header: Header, events: impl IntoIterator<Item = Event>) -> Self {
+        Asciicast { header, events: events.into_iter().collect() }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Write the recording in the format of the header's version.
+    pub fn write_to(&self, w: impl Write) -> io::Result<()> {
+        let mut writer = AsciicastWriter::new(w, &self.header)?;
         for event in &self.events {
-            event.write_to(&mut w)?;
+            writer.write_event(event)?;
         }
         Ok(())
     }
@@ -29,20 +48,45 @@ impl Asciicast {
     pub fn read_from(mut r: impl io::BufRead) -> io::Result<Self> {
         let mut header = String::new();
         r.read_line(&mut header)?;
-        let header: Header = serde_json::from_str(&header)?;
+        let header = Header::parse(&header)?;
 
         let mut events = vec![];
         let mut line = String::new();
+        let mut prev_us = 0;
         loop {
             line.clear();
             if r.read_line(&mut line)? == 0 {
                 break;
             };
 
-            let (time_us, code, data): (f64, &str, String) = serde_json::from_str(&line)?;
-            let time_us = (time_us * 1_000_000.0) as u64;
+            // v3 allows comments and blank lines between events.
+            if header.version == Version::V3 && (line.starts_with('#') || line.trim().is_empty()) {
+                continue;
+            }
+
+            let (time, code, data): (f64, &str, String) = serde_json::from_str(&line)?;
+            let mut time_us = (time * 1_000_000.0).round() as u64;
+            if header.version == Version::V3 {
+                time_us += prev_us;
+            }
+            prev_us = time_us;
+
             let kind = match code {
                 "o" => EventKind::Output(data),
+                "i" => EventKind::Input(data),
+                "m" => EventKind::Marker(data),
+                "r" => match data.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+                    Some((Ok(width), Ok(height))) => EventKind::Resize { width, height },
+                    _ => {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid resize"));
+                    }
+                },
+                "x" if header.version == Version::V3 => match data.parse() {
+                    Ok(status) => EventKind::Exit(status),
+                    Err(_) => {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid exit"));
+                    }
+                },
                 _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown event code")),
             };
             events.push(Event { time_us, kind });
@@ -50,23 +94,337 @@ impl Asciicast {
 
         Ok(Asciicast { header, events })
     }
+
+    /// Shorten the pauses in the recording to at most `max`, e.g. to cut the time spent thinking
+    /// out of a demo. Unlike the header's `idle_time_limit` this changes the recorded timing.
+    pub fn limit_idle_time(mut self, max: Duration) -> Self {
+        let max = max.as_micros() as u64;
+        let end_us = self.end_us();
+        let (mut prev_us, mut removed) = (0, 0);
+        for event in &mut self.events {
+            removed += event.time_us.saturating_sub(prev_us).saturating_sub(max);
+            prev_us = event.time_us;
+            event.time_us -= removed;
+        }
+
+        if let Some(duration) = &mut self.header.duration {
+            removed += end_us.saturating_sub(prev_us).saturating_sub(max);
+            *duration = Duration::from_micros(end_us - removed);
+        }
+        self
+    }
+
+    /// Keep the part of the recording within `range`, shifted to start at zero.
+    /// The output and resizes before the range are kept at the start so that the screen is as it
+    /// was at the start of the range.
+    pub fn trim(mut self, range: impl RangeBounds<Duration>) -> Self {
+        let bound = |bound: Bound<&Duration>| match bound {
+            Bound::Included(time) | Bound::Excluded(time) => Some(time.as_micros() as u64),
+            Bound::Unbounded => None,
+        };
+        let start_us = bound(range.start_bound()).unwrap_or(0);
+        let end_us = self.end_us().min(bound(range.end_bound()).unwrap_or(u64::MAX));
+
+        self.events.retain_mut(|event| {
+            if range.contains(&Duration::from_micros(event.time_us)) {
+                event.time_us -= start_us;
+                true
+            } else if event.time_us <= start_us {
+                event.time_us = 0;
+                matches!(event.kind, EventKind::Output(_) | EventKind::Resize { .. })
+            } else {
+                false
+            }
+        });
+
+        if let Some(duration) = &mut self.header.duration {
+            *duration = Duration::from_micros(end_us.saturating_sub(start_us));
+        }
+        self
+    }
+
+    /// Append `other` to the end of the recording, keeping this recording's header.
+    /// A resize is inserted if `other` was recorded at a different size, and the exit events of
+    /// this recording are dropped as it no longer ends there.
+    pub fn concat(mut self, other: Asciicast) -> Self {
+        let offset = self.end_us();
+        let other_end_us = other.end_us();
+        self.events.retain(|event| !matches!(event.kind, EventKind::Exit(_)));
+
+        let size = self.events.iter().rev().find_map(|event| match event.kind {
+            EventKind::Resize { width, height } => Some((width, height)),
+            _ => None,
+        });
+        let (width, height) = (other.header.width, other.header.height);
+        if size.unwrap_or((self.header.width, self.header.height)) != (width, height) {
+            self.events.push(Event { time_us: offset, kind: EventKind::Resize { width, height } });
+        }
+
+        self.events.extend(
+            other
+                .events
+                .into_iter()
+                .map(|event| Event { time_us: event.time_us + offset, ..event }),
+        );
+
+        if let Some(duration) = &mut self.header.duration {
+            *duration = Duration::from_micros(offset + other_end_us);
+        }
+        self
+    }
+
+    /// The time the recording ends, which is the header's `duration` if it's after the last event.
+    fn end_us(&self) -> u64 {
+        let last_us = self.events.last().map_or(0, |event| event.time_us);
+        self.header.duration.map_or(last_us, |duration| last_us.max(duration.as_micros() as u64))
+    }
+
+    /// Write the output of the recording to `sink` with the recorded timing, `speed` times as fast.
+    /// Pauses are capped by the header's `idle_time_limit`, an infinite speed plays without pauses.
+    pub fn play(&self, speed: f64, mut sink: impl Write) -> io::Result<()> {
+        assert!(speed > 0.0, "playback speed must be positive");
+        let start = Instant::now();
+        let mut at = Duration::ZERO;
+        let mut prev_us = 0;
+        for event in &self.events {
+            let mut delay = Duration::from_micros(event.time_us.saturating_sub(prev_us));
+            if let Some(limit) = self.header.idle_time_limit {
+                delay = delay.min(limit);
+            }
+            prev_us = event.time_us;
+
+            // Sleep until the event is due rather than for the delay so the time spent writing
+            // doesn't accumulate over the recording.
+            at += delay.div_f64(speed);
+            if let Some(remaining) = at.checked_sub(start.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+
+            if let EventKind::Output(output) = &event.kind {
+                sink.write_all(output.as_bytes())?;
+                sink.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a recording incrementally as the events happen.
+/// Each event is flushed once written so the recording is usable even if it's never finished.
+pub struct AsciicastWriter<W: Write> {
+    w: W,
+    version: Version,
+    start: Instant,
+    prev_us: u64,
+}
+
+impl<W: Write> AsciicastWriter<W> {
+    /// Write the header, the recording starts now.
+    pub fn new(mut w: W, header: &Header) -> io::Result<Self> {
+        header.write_to(&mut w)?;
+        w.flush()?;
+        Ok(Self { w, version: header.version, start: Instant::now(), prev_us: 0 })
+    }
+
+    /// Write an event at the given time since the start of the recording.
+    /// Events must be written in order of their time.
+    pub fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        // Event times are absolute in v2 and relative to the previous event in v3.
+        let time_us = match self.version {
+            Version::V2 => event.time_us,
+            Version::V3 => event.time_us.saturating_sub(self.prev_us),
+        };
+        self.prev_us = event.time_us;
+        event.write_to(&mut self.w, self.version, time_us)?;
+        self.w.flush()
+    }
+
+    /// Write an event that happens now.
+    pub fn record(&mut self, kind: EventKind) -> io::Result<()> {
+        let time_us = (self.start.elapsed().as_micros() as u64).max(self.prev_us);
+        self.write_event(&Event { time_us, kind })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    #[default]
+    V2,
+    V3,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
-    pub version: u8,
+    pub version: Version,
     pub width: u16,
     pub height: u16,
+    /// The unix timestamp of the start of the recording in seconds.
+    pub timestamp: Option<u64>,
+    /// The duration of the recording, only written in v2 headers.
+    pub duration: Option<Duration>,
+    /// The maximum time between events when played back.
+    pub idle_time_limit: Option<Duration>,
+    pub command: Option<String>,
+    pub title: Option<String>,
+    /// The environment of the recording, usually `SHELL` and `TERM`.
+    pub env: BTreeMap<String, String>,
+    pub theme: Option<Theme>,
+}
+
+/// The colors of the recorded terminal as `#rrggbb`. The palette is 8 or 16 colors separated by `:`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Theme {
+    pub fg: String,
+    pub bg: String,
+    pub palette: String,
 }
 
 impl Header {
+    pub fn new(width: u16, height: u16) -> Self {
+        Header {
+            version: Version::default(),
+            width,
+            height,
+            timestamp: None,
+            duration: None,
+            idle_time_limit: None,
+            command: None,
+            title: None,
+            env: BTreeMap::new(),
+            theme: None,
+        }
+    }
+
     pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
-        writeln!(
-            w,
-            r#"{{"version": {}, "width": {}, "height": {}}}"#,
-            self.version, self.width, self.height
-        )
+        let seconds = |duration: Option<Duration>| duration.map(|d| d.as_secs_f64());
+        match self.version {
+            Version::V2 => serde_json::to_writer(
+                &mut w,
+                &HeaderV2 {
+                    version: 2,
+                    width: self.width,
+                    height: self.height,
+                    timestamp: self.timestamp,
+                    duration: seconds(self.duration),
+                    idle_time_limit: seconds(self.idle_time_limit),
+                    command: self.command.clone(),
+                    title: self.title.clone(),
+                    env: self.env.clone(),
+                    theme: self.theme.clone(),
+                },
+            )?,
+            Version::V3 => serde_json::to_writer(
+                &mut w,
+                &HeaderV3 {
+                    version: 3,
+                    term: Term { cols: self.width, rows: self.height, theme: self.theme.clone() },
+                    timestamp: self.timestamp,
+                    idle_time_limit: seconds(self.idle_time_limit),
+                    command: self.command.clone(),
+                    title: self.title.clone(),
+                    env: self.env.clone(),
+                },
+            )?,
+        }
+        writeln!(w)
     }
+
+    fn parse(s: &str) -> io::Result<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u8,
+        }
+
+        let duration = |seconds: Option<f64>| {
+            seconds.map(Duration::try_from_secs_f64).transpose().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid duration in header")
+            })
+        };
+
+        let Versioned { version } = serde_json::from_str(s)?;
+        match version {
+            2 => {
+                let header: HeaderV2 = serde_json::from_str(s)?;
+                Ok(Header {
+                    version: Version::V2,
+                    width: header.width,
+                    height: header.height,
+                    timestamp: header.timestamp,
+                    duration: duration(header.duration)?,
+                    idle_time_limit: duration(header.idle_time_limit)?,
+                    command: header.command,
+                    title: header.title,
+                    env: header.env,
+                    theme: header.theme,
+                })
+            }
+            3 => {
+                let header: HeaderV3 = serde_json::from_str(s)?;
+                Ok(Header {
+                    version: Version::V3,
+                    width: header.term.cols,
+                    height: header.term.rows,
+                    timestamp: header.timestamp,
+                    duration: None,
+                    idle_time_limit: duration(header.idle_time_limit)?,
+                    command: header.command,
+                    title: header.title,
+                    env: header.env,
+                    theme: header.term.theme,
+                })
+            }
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported asciicast version")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HeaderV2 {
+    version: u8,
+    width: u16,
+    height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    theme: Option<Theme>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HeaderV3 {
+    version: u8,
+    term: Term,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Term {
+    cols: u16,
+    rows: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    theme: Option<Theme>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -77,16 +435,311 @@ pub struct Event {
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum EventKind {
+    /// Data written to the terminal.
     Output(String),
+    /// Data read from the terminal, e.g. key presses.
+    Input(String),
+    /// A marker with an optional label for navigating the recording.
+    Marker(String),
+    /// The terminal was resized to `width` columns and `height` rows.
+    Resize { width: u16, height: u16 },
+    /// The recorded process exited with the status, only supported by v3.
+    Exit(i32),
 }
 
 impl Event {
-    fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+    fn write_to(&self, mut w: impl Write, version: Version, time_us: u64) -> io::Result<()> {
+        let owned;
         let (code, data) = match &self.kind {
             EventKind::Output(output) => ("o", output),
+            EventKind::Input(input) => ("i", input),
+            EventKind::Marker(label) => ("m", label),
+            EventKind::Resize { width, height } => {
+                owned = format!("{width}x{height}");
+                ("r", &owned)
+            }
+            EventKind::Exit(status) => {
+                if version == Version::V2 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "exit events are not supported by asciicast v2",
+                    ));
+                }
+                owned = status.to_string();
+                ("x", &owned)
+            }
         };
 
-        serde_json::to_writer(&mut w, &(self.time_us as f64 / 1_000_000.0, code, data))?;
+        serde_json::to_writer(&mut w, &(time_us as f64 / 1_000_000.0, code, data))?;
         writeln!(w)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let cast = Asciicast::new(
+            80,
+            24,
+            [
+                Event { time_us: 0, kind: EventKind::Output("a\"b\r\n".into()) },
+                Event { time_us: 250_000, kind: EventKind::Input("\u{1b}:q".into()) },
+                Event { time_us: 500_000, kind: EventKind::Marker("snapshot".into()) },
+                Event { time_us: 1_500_000, kind: EventKind::Resize { width: 120, height: 40 } },
+            ],
+        );
+
+        let mut buf = vec![];
+        cast.write_to(&mut buf).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            s,
+            concat!(
+                "{\"version\":2,\"width\":80,\"height\":24}\n",
+                "[0.0,\"o\",\"a\\\"b\\r\\n\"]\n",
+                "[0.25,\"i\",\"\\u001b:q\"]\n",
+                "[0.5,\"m\",\"snapshot\"]\n",
+                "[1.5,\"r\",\"120x40\"]\n",
+            )
+        );
+        assert_eq!(Asciicast::read_from(s.as_bytes()).unwrap(), cast);
+    }
+
+    #[test]
+    fn roundtrip_v3() {
+        let header = Header {
+            version: Version::V3,
+            timestamp: Some(1_700_000_000),
+            idle_time_limit: Some(Duration::from_millis(2500)),
+            command: Some("zi".into()),
+            title: Some("demo".into()),
+            env: BTreeMap::from([("TERM".into(), "xterm-256color".into())]),
+            theme: Some(Theme {
+                fg: "#ffffff".into(),
+                bg: "#000000".into(),
+                palette: "#000000:#ffffff".into(),
+            }),
+            ..Header::new(80, 24)
+        };
+        let cast = Asciicast::with_header(
+            header,
+            [
+                Event { time_us: 500_000, kind: EventKind::Output("a".into()) },
+                Event { time_us: 1_250_000, kind: EventKind::Marker("".into()) },
+                Event { time_us: 2_000_000, kind: EventKind::Exit(0) },
+            ],
+        );
+
+        let mut buf = vec![];
+        cast.write_to(&mut buf).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            s,
+            concat!(
+                r##"{"version":3,"term":{"cols":80,"rows":24,"theme":{"fg":"#ffffff","bg":"#000000","palette":"#000000:#ffffff"}},"##,
+                r##""timestamp":1700000000,"idle_time_limit":2.5,"command":"zi","title":"demo","env":{"TERM":"xterm-256color"}}"##,
+                "\n[0.5,\"o\",\"a\"]\n",
+                "[0.75,\"m\",\"\"]\n",
+                "[0.75,\"x\",\"0\"]\n",
+            )
+        );
+        assert_eq!(Asciicast::read_from(s.as_bytes()).unwrap(), cast);
+
+        // Comments and blank lines are allowed between v3 events.
+        let s = s.replace("\n[0.75,\"m\"", "\n# comment\n\n[0.75,\"m\"");
+        assert_eq!(Asciicast::read_from(s.as_bytes()).unwrap(), cast);
+
+        // Exit events can't be written as v2.
+        let cast = Asciicast::new(80, 24, [Event { time_us: 0, kind: EventKind::Exit(1) }]);
+        let err = cast.write_to(vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn writer() {
+        for version in [Version::V2, Version::V3] {
+            let header = Header { version, ..Header::new(80, 24) };
+            let mut writer = AsciicastWriter::new(vec![], &header).unwrap();
+            writer.record(EventKind::Output("a".into())).unwrap();
+            writer.record(EventKind::Resize { width: 100, height: 30 }).unwrap();
+            writer.record(EventKind::Output("b".into())).unwrap();
+
+            let cast = Asciicast::read_from(&writer.into_inner()[..]).unwrap();
+            assert_eq!(cast.header(), &header);
+            let kinds = cast.events().iter().map(|event| &event.kind).collect::<Vec<_>>();
+            assert_eq!(
+                kinds,
+                [
+                    &EventKind::Output("a".into()),
+                    &EventKind::Resize { width: 100, height: 30 },
+                    &EventKind::Output("b".into()),
+                ]
+            );
+            assert!(cast.events().is_sorted_by_key(|event| event.time_us));
+        }
+    }
+
+    #[test]
+    fn read_v2_header() {
+        let s = r#"{"version": 2, "width": 100, "height": 50, "duration": 1.5, "env": {"SHELL": "/bin/sh"}}"#;
+        let cast = Asciicast::read_from(s.as_bytes()).unwrap();
+        assert_eq!(
+            cast.header(),
+            &Header {
+                duration: Some(Duration::from_millis(1500)),
+                env: BTreeMap::from([("SHELL".into(), "/bin/sh".into())]),
+                ..Header::new(100, 50)
+            }
+        );
+        assert!(cast.events().is_empty());
+    }
+
+    #[test]
+    fn play() {
+        let cast = Asciicast::with_header(
+            Header { idle_time_limit: Some(Duration::from_millis(10)), ..Header::new(80, 24) },
+            [
+                Event { time_us: 0, kind: EventKind::Output("a".into()) },
+                Event { time_us: 5_000, kind: EventKind::Input("x".into()) },
+                Event { time_us: 60_000_000, kind: EventKind::Output("b".into()) },
+            ],
+        );
+
+        let mut out = vec![];
+        let start = Instant::now();
+        cast.play(1.0, &mut out).unwrap();
+        assert_eq!(out, b"ab");
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let mut out = vec![];
+        cast.play(f64::INFINITY, &mut out).unwrap();
+        assert_eq!(out, b"ab");
+    }
+
+    fn output(time_us: u64, output: &str) -> Event {
+        Event { time_us, kind: EventKind::Output(output.into()) }
+    }
+
+    #[test]
+    fn limit_idle_time() {
+        let header = Header { duration: Some(Duration::from_secs(20)), ..Header::new(80, 24) };
+        let cast = Asciicast::with_header(
+            header.clone(),
+            [output(3_000_000, "a"), output(3_500_000, "b"), output(10_000_000, "c")],
+        )
+        .limit_idle_time(Duration::from_secs(1));
+
+        assert_eq!(
+            cast,
+            Asciicast::with_header(
+                Header { duration: Some(Duration::from_millis(3500)), ..header },
+                [output(1_000_000, "a"), output(1_500_000, "b"), output(2_500_000, "c")],
+            )
+        );
+    }
+
+    #[test]
+    fn trim() {
+        let cast = || {
+            Asciicast::new(
+                80,
+                24,
+                [
+                    output(0, "a"),
+                    Event { time_us: 1_000_000, kind: EventKind::Input("x".into()) },
+                    Event {
+                        time_us: 1_500_000,
+                        kind: EventKind::Resize { width: 100, height: 30 },
+                    },
+                    output(2_000_000, "b"),
+                    output(3_000_000, "c"),
+                    output(4_000_000, "d"),
+                ],
+            )
+        };
+
+        assert_eq!(
+            cast().trim(Duration::from_secs(2)..Duration::from_secs(4)),
+            Asciicast::new(
+                80,
+                24,
+                [
+                    output(0, "a"),
+                    Event { time_us: 0, kind: EventKind::Resize { width: 100, height: 30 } },
+                    output(0, "b"),
+                    output(1_000_000, "c"),
+                ]
+            )
+        );
+        assert_eq!(cast().trim(..), cast());
+        assert_eq!(cast().trim(..=Duration::from_secs(3)).events().len(), 5);
+    }
+
+    #[test]
+    fn concat() {
+        let header = Header { duration: Some(Duration::from_secs(2)), ..Header::new(80, 24) };
+        let cast = Asciicast::with_header(
+            header.clone(),
+            [output(1_000_000, "a"), Event { time_us: 1_500_000, kind: EventKind::Exit(0) }],
+        )
+        .concat(Asciicast::new(100, 30, [output(0, "b"), output(500_000, "c")]));
+
+        assert_eq!(
+            cast,
+            Asciicast::with_header(
+                Header { duration: Some(Duration::from_millis(2500)), ..header },
+                [
+                    output(1_000_000, "a"),
+                    Event {
+                        time_us: 2_000_000,
+                        kind: EventKind::Resize { width: 100, height: 30 }
+                    },
+                    output(2_000_000, "b"),
+                    output(2_500_000, "c"),
+                ]
+            )
+        );
+    }
+
+    #[cfg(feature = "vt100")]
+    #[test]
+    fn screen() {
+        let cast = |outputs: &[&str]| {
+            Asciicast::new(
+                10,
+                3,
+                outputs
+                    .iter()
+                    .map(|&output| Event { time_us: 0, kind: EventKind::Output(output.into()) }),
+            )
+        };
+
+        let screen = cast(&["ab\r\ncd"]).screen();
+        assert_eq!(screen.rows, ["ab", "cd", ""]);
+        assert_eq!(screen.cursor, (1, 2));
+        assert_eq!(screen.to_string(), "ab\ncd\n\n");
+
+        // The same screen drawn in a different order.
+        assert_eq!(cast(&["\x1b[2;1Hcd", "\x1b[1;1Hab\x1b[2;3H"]).screen(), screen);
+        assert_ne!(cast(&["ab\r\n\x1b[1mcd"]).screen(), screen);
+
+        let mut resized = cast(&["ab"]);
+        resized.events.push(Event { time_us: 0, kind: EventKind::Resize { width: 4, height: 2 } });
+        let screen = resized.screen();
+        assert_eq!((screen.width, screen.height), (4, 2));
+        assert_eq!(screen.rows, ["ab", ""]);
+    }
+
+    #[test]
+    fn invalid_events() {
+        let header = "{\"version\": 2, \"width\": 80, \"height\": 24}\n";
+        for event in ["[0.0,\"x\",\"\"]", "[0.0,\"r\",\"80\"]", "[0.0,\"r\",\"ax24\"]"] {
+            let err = Asciicast::read_from(format!("{header}{event}\n").as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{event}");
+        }
+    }
+}
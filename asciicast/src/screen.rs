@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::{Asciicast, EventKind};
+
+/// The state of the terminal after playing a recording.
+/// Recordings that draw the same screen with different escape sequences have equal screens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    pub width: u16,
+    pub height: u16,
+    /// The text of each row without trailing whitespace.
+    pub rows: Vec<String>,
+    /// The 0-indexed `(row, col)` of the cursor.
+    pub cursor: (u16, u16),
+    /// The escape sequences to draw the screen from scratch including colors and attributes.
+    /// These are generated from the screen so they don't depend on how the screen was drawn.
+    formatted: Vec<u8>,
+}
+
+impl Screen {
+    fn new(screen: &vt100::Screen) -> Self {
+        let (height, width) = screen.size();
+        Self {
+            width,
+            height,
+            rows: screen.rows(0, width).map(|row| row.trim_end().to_string()).collect(),
+            cursor: screen.cursor_position(),
+            formatted: screen.contents_formatted(),
+        }
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            writeln!(f, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Asciicast {
+    /// Feed the output of the recording through a terminal emulator, following any resizes.
+    pub fn screen(&self) -> Screen {
+        let mut parser = vt100::Parser::new(self.header.height, self.header.width, 0);
+        for event in &self.events {
+            match &event.kind {
+                EventKind::Output(output) => parser.process(output.as_bytes()),
+                EventKind::Resize { width, height } => parser.set_size(*height, *width),
+                EventKind::Input(_) | EventKind::Marker(_) | EventKind::Exit(_) => {}
+            }
+        }
+        Screen::new(parser.screen())
+    }
+}
@@ -1,5 +1,6 @@
 use api::command::{self, Arity, Command, CommandError, CommandFlags};
 use api::dependency;
+use api::events;
 use api::lifecycle::{self, InitializeResult};
 use bindings::zi::api::editor::*;
 
@@ -41,7 +42,10 @@ impl lifecycle::Guest for Component {
         test_insert_delete_undo_redo();
         test_buffer_apis();
         test_view_scroll_align_focus();
+        test_mappings();
+        test_events();
         test_mode();
+        test_ui();
 
         InitializeResult {
             commands: vec![Command {
@@ -95,6 +99,32 @@ fn test_buffer_apis() {
 
     let lines = buf.len_lines();
     assert!(lines >= 1);
+
+    let id = buf.get_id();
+    assert_eq!(get_buffer(id).map(|buf| buf.get_id()), Some(id));
+    assert!(get_buffers().iter().any(|buf| buf.get_id() == id));
+
+    let start = Point { line: 0, col: 0 };
+    let text = buf.get_text();
+    buf.subscribe_changes();
+    buf.edit(&[Delta { start, end: start, text: "xyz".into() }]).unwrap();
+    assert_eq!(buf.get_text(), format!("xyz{text}"));
+    assert_eq!(buf.get_slice(start, Point { line: 0, col: 3 }).unwrap(), "xyz");
+    buf.edit(&[Delta { start, end: Point { line: 0, col: 3 }, text: "".into() }]).unwrap();
+    assert_eq!(buf.get_text(), text);
+    buf.unsubscribe_changes();
+
+    let overlapping = [
+        Delta { start, end: Point { line: 0, col: 2 }, text: "a".into() },
+        Delta {
+            start: Point { line: 0, col: 1 },
+            end: Point { line: 0, col: 3 },
+            text: "b".into(),
+        },
+    ];
+    assert_eq!(buf.edit(&overlapping), Err(EditError::InvalidRange));
+    let out_of_bounds = Point { line: lines + 1, col: 0 };
+    assert_eq!(buf.get_slice(start, out_of_bounds), Err(EditError::InvalidRange));
 }
 
 fn test_view_scroll_align_focus() {
@@ -107,6 +137,29 @@ fn test_view_scroll_align_focus() {
     view.focus();
 }
 
+fn test_mappings() {
+    map(&[Mode::Normal], "Q", "foo").unwrap();
+    assert!(map(&[Mode::Normal], "dd", "foo").is_err());
+    unmap(&[Mode::Normal], "Q").unwrap();
+
+    map_sub_mode("test", "x", "foo").unwrap();
+    assert!(enter_sub_mode("unknown").is_err());
+    enter_sub_mode("test").unwrap();
+    // Changing mode leaves the sub-mode.
+    set_mode(Mode::Normal);
+    unmap_sub_mode("test", "x").unwrap();
+    assert!(enter_sub_mode("test").is_err());
+}
+
+fn test_events() {
+    let buf = get_active_view().get_buffer();
+    let subscription = subscribe(EventKind::CursorMove, Some(buf.get_id()), None, None);
+    unsubscribe(subscription);
+
+    let subscription = subscribe(EventKind::ModeChange, None, Some("rust"), Some(Mode::Insert));
+    assert_ne!(subscribe(EventKind::BufferWrite, None, None, None), subscription);
+}
+
 fn test_mode() {
     assert_eq!(get_mode(), Mode::Normal);
     set_mode(Mode::Insert);
@@ -115,6 +168,51 @@ fn test_mode() {
     assert_eq!(get_mode(), Mode::Normal);
 }
 
+fn test_ui() {
+    let config = FloatConfig {
+        anchor: FloatAnchor::Cursor(Offset { x: 0, y: 1 }),
+        width: 10,
+        height: 1,
+        border: true,
+        focusable: false,
+    };
+    let float = open_float("hello", config);
+    assert!(float.get_buffer().get_flags().contains(BufferFlags::READONLY));
+    assert_eq!(float.get_buffer().get_text(), "hello");
+    float.close();
+
+    let picker = open_picker("test").unwrap();
+    assert!(open_picker("test").is_err());
+    push_picker_items(picker, &[PickerItem { id: 1, text: "a".into() }]).unwrap();
+    assert!(push_picker_items(picker + 1, &[]).is_err());
+    // Closing the picker restores the mode.
+    get_active_view().close();
+    assert_eq!(get_mode(), Mode::Normal);
+
+    let buf = get_active_view().get_buffer();
+    let ns = create_namespace("test");
+    assert_eq!(create_namespace("test"), ns);
+    buf.set_sign(ns, 0, 'x', "Error").unwrap();
+    buf.set_virtual_text(ns, 0, "virtual text", "Comment").unwrap();
+    let out_of_bounds = buf.len_lines() + 1;
+    assert_eq!(buf.set_sign(ns, out_of_bounds, 'x', "Error"), Err(EditError::InvalidRange));
+    buf.clear_namespace(ns, 0, buf.len_lines());
+}
+
+impl events::Guest for Component {
+    fn on_buffer_change(_buf: BufferId, _changes: Vec<events::Change>) {}
+
+    fn on_buffer_open(_buf: BufferId) {}
+
+    fn on_buffer_write(_buf: BufferId) {}
+
+    fn on_cursor_move(_buf: BufferId, _cursor: Point) {}
+
+    fn on_mode_change(_from: Mode, _to: Mode) {}
+
+    fn on_picker_confirm(_picker: PickerId, _item: u64) {}
+}
+
 impl dependency::Guest for Component {
     fn get_name() -> String {
         "test".into()
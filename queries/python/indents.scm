@@ -0,0 +1,28 @@
+[
+  (function_definition)
+  (class_definition)
+  (if_statement)
+  (for_statement)
+  (while_statement)
+  (try_statement)
+  (with_statement)
+  (match_statement)
+  (case_clause)
+  (argument_list)
+  (parameters)
+  (list)
+  (tuple)
+  (set)
+  (dictionary)
+] @indent
+
+(elif_clause "elif" @outdent)
+(else_clause "else" @outdent)
+(except_clause "except" @outdent)
+(finally_clause "finally" @outdent)
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
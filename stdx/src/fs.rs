@@ -0,0 +1,197 @@
+#[cfg(unix)]
+use std::ffi::{OsStr, OsString};
+#[cfg(unix)]
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+/// Move `path` to the user's trash so it can be restored later.
+/// This follows the freedesktop.org trash specification on unix and uses `~/.Trash` on macOS.
+pub fn trash(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = std::path::absolute(path)?;
+    trash_absolute(&path)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn trash_absolute(path: &Path) -> io::Result<()> {
+    use std::io::Write;
+
+    let data = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home()?.join(".local/share"),
+    };
+
+    let files = data.join("Trash/files");
+    let info = data.join("Trash/info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let name = file_name(path)?;
+    for n in 0.. {
+        let name = suffixed(name, n);
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        let info_path = info.join(info_name);
+
+        // Creating the info file atomically reserves the name.
+        let mut f = match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(f) => f,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        let dst = files.join(&name);
+        if dst.symlink_metadata().is_ok() {
+            drop(f);
+            fs::remove_file(&info_path)?;
+            continue;
+        }
+
+        let res = write!(
+            f,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(path.as_os_str()),
+            deletion_date()
+        )
+        .and_then(|()| move_path(path, &dst));
+
+        if res.is_err() {
+            let _ = fs::remove_file(&info_path);
+        }
+        return res;
+    }
+
+    unreachable!()
+}
+
+#[cfg(target_os = "macos")]
+fn trash_absolute(path: &Path) -> io::Result<()> {
+    let dir = home()?.join(".Trash");
+    let name = file_name(path)?;
+    for n in 0.. {
+        let dst = dir.join(suffixed(name, n));
+        if dst.symlink_metadata().is_err() {
+            return move_path(path, &dst);
+        }
+    }
+
+    unreachable!()
+}
+
+/// Rename `from` to `to`, falling back to copying and removing `from` if they are on different
+/// devices, as is common for the trash of a mounted drive.
+#[cfg(unix)]
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(err) = copy_recursive(from, to) {
+                // Don't leave a partial copy behind, the original is still intact.
+                let _ = remove_recursive(to);
+                return Err(err);
+            }
+            remove_recursive(from)
+        }
+        res => res,
+    }
+}
+
+/// Copy a file, symlink or directory tree without following symlinks.
+#[cfg(unix)]
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)
+    } else if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, metadata.permissions())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn remove_recursive(path: &Path) -> io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(not(unix))]
+fn trash_absolute(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "trash is not supported on this platform"))
+}
+
+#[cfg(unix)]
+fn home() -> io::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "$HOME is not set"))
+}
+
+#[cfg(unix)]
+fn file_name(path: &Path) -> io::Result<&OsStr> {
+    path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("cannot trash `{}`", path.display()))
+    })
+}
+
+/// `name`, `name.1`, `name.2`, ...
+#[cfg(unix)]
+fn suffixed(name: &OsStr, n: usize) -> OsString {
+    let mut name = name.to_os_string();
+    if n > 0 {
+        name.push(format!(".{n}"));
+    }
+    name
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn percent_encode(s: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~/".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+/// The current time formatted as `YYYY-MM-DDThh:mm:ss`.
+/// The specification asks for local time, but we don't have timezone information so this is UTC.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn deletion_date() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
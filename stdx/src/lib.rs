@@ -1,5 +1,6 @@
 pub mod bomb;
 pub mod bound;
+pub mod fs;
 pub mod iter;
 pub mod merge;
 pub mod path;
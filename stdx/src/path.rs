@@ -66,6 +66,13 @@ impl fmt::Debug for Display {
     }
 }
 
+impl AsRef<Path> for Relative {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
 impl AsRef<Path> for Display {
     #[inline]
     fn as_ref(&self) -> &Path {
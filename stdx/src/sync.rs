@@ -1,9 +1,11 @@
 use std::sync::Arc;
 use std::sync::atomic::{self, AtomicBool};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Default)]
 pub struct Cancel {
     cancel: Arc<AtomicBool>,
+    deadline: Option<Instant>,
 }
 
 impl Cancel {
@@ -12,6 +14,12 @@ impl Cancel {
         Self::default()
     }
 
+    /// A token that is also cancelled once `timeout` has elapsed.
+    #[inline]
+    pub fn after(timeout: Duration) -> Self {
+        Self { deadline: Some(Instant::now() + timeout), ..Self::default() }
+    }
+
     #[inline]
     pub fn cancel(&self) {
         self.cancel.store(true, atomic::Ordering::Relaxed);
@@ -20,5 +28,6 @@ impl Cancel {
     #[inline]
     pub fn is_cancelled(&self) -> bool {
         self.cancel.load(atomic::Ordering::Relaxed)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
     }
 }
@@ -3,4 +3,77 @@ pub struct CompletionItem {
     pub label: String,
     pub filter_text: Option<String>,
     pub insert_text: Option<String>,
+    /// The text used to order items that match the query equally well.
+    pub sort_text: Option<String>,
+    pub kind: Option<CompletionItemKind>,
+    /// Additional information about the item, such as its type.
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+    pub deprecated: bool,
+    /// Whether the insert text is a snippet with tabstops, e.g. `fn ${1:name}($2)`.
+    pub snippet: bool,
+    /// Opaque data the provider of the item uses to resolve it.
+    pub data: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum CompletionItemKind {
+    Text,
+    Method,
+    Function,
+    Constructor,
+    Field,
+    Variable,
+    Class,
+    Interface,
+    Module,
+    Property,
+    Unit,
+    Value,
+    Enum,
+    Keyword,
+    Snippet,
+    Color,
+    File,
+    Reference,
+    Folder,
+    EnumMember,
+    Constant,
+    Struct,
+    Event,
+    Operator,
+    TypeParameter,
+}
+
+impl CompletionItemKind {
+    /// A short symbol for the kind displayed next to the items in the completion menu.
+    pub fn icon(self) -> &'static str {
+        match self {
+            CompletionItemKind::Text => "txt",
+            CompletionItemKind::Method => "fn",
+            CompletionItemKind::Function => "fn",
+            CompletionItemKind::Constructor => "new",
+            CompletionItemKind::Field => "fld",
+            CompletionItemKind::Variable => "var",
+            CompletionItemKind::Class => "cls",
+            CompletionItemKind::Interface => "trt",
+            CompletionItemKind::Module => "mod",
+            CompletionItemKind::Property => "prp",
+            CompletionItemKind::Unit => "unt",
+            CompletionItemKind::Value => "val",
+            CompletionItemKind::Enum => "enm",
+            CompletionItemKind::Keyword => "kw",
+            CompletionItemKind::Snippet => "snp",
+            CompletionItemKind::Color => "clr",
+            CompletionItemKind::File => "fil",
+            CompletionItemKind::Reference => "ref",
+            CompletionItemKind::Folder => "dir",
+            CompletionItemKind::EnumMember => "var",
+            CompletionItemKind::Constant => "cst",
+            CompletionItemKind::Struct => "st",
+            CompletionItemKind::Event => "evt",
+            CompletionItemKind::Operator => "op",
+            CompletionItemKind::TypeParameter => "ty",
+        }
+    }
 }
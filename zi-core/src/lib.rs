@@ -4,7 +4,7 @@ pub mod style;
 
 use std::fmt;
 
-pub use self::completion::CompletionItem;
+pub use self::completion::{CompletionItem, CompletionItemKind};
 pub use self::position::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -41,6 +41,14 @@ pub enum Operator {
     Delete,
     Change,
     Yank,
+    Filter,
+    Fold,
+    Reindent,
+    Surround,
+    Comment,
+    Lowercase,
+    Uppercase,
+    ToggleCase,
 }
 
 slotmap::new_key_type! {
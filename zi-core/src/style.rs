@@ -33,9 +33,10 @@ impl FromStr for Style {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut style = Style::none();
         for part in s.split_whitespace() {
-            let (key, value) = part
-                .split_once('=')
-                .ok_or_else(|| anyhow::anyhow!("invalid style part: {part}"))?;
+            let Some((key, value)) = part.split_once('=') else {
+                style.modifier |= parse_modifier(part)?;
+                continue;
+            };
 
             style = match key {
                 "fg" => style.with_fg(value.parse()?),
@@ -79,6 +80,22 @@ impl From<Style> for tui::Style {
 
 pub type Modifier = tui::Modifier;
 
+/// Parse the name of a modifier as used by the [`modifier!`] macro.
+pub fn parse_modifier(name: &str) -> anyhow::Result<Modifier> {
+    Ok(match name {
+        "bold" => Modifier::BOLD,
+        "dim" => Modifier::DIM,
+        "italic" => Modifier::ITALIC,
+        "underline" => Modifier::UNDERLINED,
+        "slow_blink" => Modifier::SLOW_BLINK,
+        "rapid_blink" => Modifier::RAPID_BLINK,
+        "reverse" => Modifier::REVERSED,
+        "hidden" => Modifier::HIDDEN,
+        "crossed_out" => Modifier::CROSSED_OUT,
+        _ => anyhow::bail!("unknown modifier `{name}`"),
+    })
+}
+
 impl Merge for Style {
     #[inline]
     fn merge(self, other: Self) -> Self {
@@ -226,4 +243,15 @@ mod tests {
 
         assert_eq!(style!(bold dim), Style::none().with_modifier(Modifier::BOLD | Modifier::DIM));
     }
+
+    #[test]
+    fn parse_style() {
+        assert_eq!("fg=#112233 bold".parse::<Style>().unwrap(), style!(fg=0x11223300 bold));
+        assert_eq!(
+            "bg=#445566 italic underline".parse::<Style>().unwrap(),
+            style!(bg=0x44556600 italic underline)
+        );
+        assert!("shiny".parse::<Style>().is_err());
+        assert!("fg=red".parse::<Style>().is_err());
+    }
 }
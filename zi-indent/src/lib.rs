@@ -23,6 +23,22 @@ pub fn indent(config: Config, text: &(impl Text + ?Sized), line_idx: usize) -> I
     Indent::Bytes(indent)
 }
 
+/// The indentation of an existing line, this is [`indent`] outdented by a level if the line
+/// starts with a closing bracket.
+pub fn reindent(config: Config, text: &(impl Text + ?Sized), line_idx: usize) -> Indent {
+    let Indent::Bytes(indent) = indent(config, text, line_idx);
+    let closes = text
+        .line(line_idx)
+        .and_then(|line| line.chars().find(|c| !c.is_whitespace()))
+        .is_some_and(|c| matches!(c, '}' | ')' | ']'));
+    if closes {
+        return Indent::Bytes(indent.saturating_sub(config.tab_width as usize));
+    }
+
+    Indent::Bytes(indent)
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct Config {
     pub tab_width: u8,
 }
@@ -1,4 +1,4 @@
-use zi_indent::{Config, Indent, Text, indent};
+use zi_indent::{Config, Indent, Text, indent, reindent};
 
 #[track_caller]
 fn check(text: impl Text, line_idx: usize, expected: Indent) {
@@ -24,3 +24,13 @@ fn indent_smoke() {
         Indent::Bytes(4),
     );
 }
+
+#[test]
+fn reindent_smoke() {
+    let text = r#"fn main() {
+let foo = 42;
+        }"#;
+    assert_eq!(reindent(Config::default(), text, 0), Indent::Bytes(0));
+    assert_eq!(reindent(Config::default(), text, 1), Indent::Bytes(4));
+    assert_eq!(reindent(Config::default(), "fn main() {\n    foo\n}", 2), Indent::Bytes(0));
+}
@@ -7,7 +7,45 @@ use chumsky::Parser;
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Event {
     Key(KeyEvent),
+    /// A key was released, only reported by terminals supporting the kitty keyboard protocol.
+    KeyRelease(KeyEvent),
     Resize(u16, u16),
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal (via bracketed paste), to be inserted as is.
+    Paste(String),
+    /// Text typed as a whole, e.g. committed by an input method or a character made up of several
+    /// codepoints, see [`compose`].
+    Text(String),
+}
+
+/// Merge runs of character key presses that were read together into a single text event if they
+/// include a non-ASCII character. Terminals deliver text committed by an input method as a burst
+/// of key presses, one per codepoint, which would otherwise split up composed characters.
+/// Plain ASCII is left alone as it may well be quickly typed keys.
+pub fn compose(events: impl IntoIterator<Item = Event>) -> Vec<Event> {
+    fn flush(out: &mut Vec<Event>, run: &mut Vec<KeyEvent>) {
+        let text = run.iter().filter_map(KeyEvent::char).collect::<String>();
+        if run.len() > 1 && !text.is_ascii() {
+            out.push(Event::Text(text));
+            run.clear();
+        } else {
+            out.extend(run.drain(..).map(Event::Key));
+        }
+    }
+
+    let mut out = vec![];
+    let mut run = vec![];
+    for event in events {
+        match event {
+            Event::Key(key) if key.char().is_some() => run.push(key),
+            event => {
+                flush(&mut out, &mut run);
+                out.push(event);
+            }
+        }
+    }
+    flush(&mut out, &mut run);
+    out
 }
 
 #[cfg(feature = "crossterm")]
@@ -16,19 +54,26 @@ impl TryFrom<crossterm::event::Event> for Event {
 
     fn try_from(event: crossterm::event::Event) -> Result<Self, Self::Error> {
         match event {
-            crossterm::event::Event::Key(event) => match event.code {
-                // weird crossterm case, we just convert this to `<S-Tab>`
-                crossterm::event::KeyCode::BackTab => Ok(Event::Key(KeyEvent::new(
-                    KeyCode::Tab,
-                    KeyModifiers::try_from(event.modifiers)? | KeyModifiers::SHIFT,
-                ))),
-                _ => Ok(Event::Key(KeyEvent::new(
-                    event.code.try_into()?,
-                    event.modifiers.try_into()?,
-                ))),
-            },
+            crossterm::event::Event::Key(event) => {
+                let key = match event.code {
+                    // weird crossterm case, we just convert this to `<S-Tab>`
+                    crossterm::event::KeyCode::BackTab => KeyEvent::new(
+                        KeyCode::Tab,
+                        KeyModifiers::try_from(event.modifiers)? | KeyModifiers::SHIFT,
+                    ),
+                    _ => KeyEvent::new(event.code.try_into()?, event.modifiers.try_into()?),
+                };
+
+                match event.kind {
+                    crossterm::event::KeyEventKind::Release => Ok(Event::KeyRelease(key)),
+                    crossterm::event::KeyEventKind::Press
+                    | crossterm::event::KeyEventKind::Repeat => Ok(Event::Key(key)),
+                }
+            }
 
             crossterm::event::Event::Resize(width, height) => Ok(Event::Resize(width, height)),
+            crossterm::event::Event::Mouse(event) => Ok(Event::Mouse(event.try_into()?)),
+            crossterm::event::Event::Paste(text) => Ok(Event::Paste(text)),
             _ => Err(()),
         }
     }
@@ -119,6 +164,10 @@ bitflags::bitflags! {
         const SHIFT = 0b0000_0001;
         const CONTROL = 0b0000_0010;
         const ALT = 0b0000_0100;
+        /// The super (or command, windows) key, only reported with the kitty keyboard protocol.
+        const SUPER = 0b0000_1000;
+        /// Only reported with the kitty keyboard protocol.
+        const HYPER = 0b0001_0000;
     }
 }
 
@@ -127,16 +176,101 @@ impl TryFrom<crossterm::event::KeyModifiers> for KeyModifiers {
     type Error = ();
 
     fn try_from(modifiers: crossterm::event::KeyModifiers) -> Result<Self, Self::Error> {
-        Ok(match modifiers {
-            crossterm::event::KeyModifiers::NONE => KeyModifiers::NONE,
-            crossterm::event::KeyModifiers::SHIFT => KeyModifiers::SHIFT,
-            crossterm::event::KeyModifiers::CONTROL => KeyModifiers::CONTROL,
-            crossterm::event::KeyModifiers::ALT => KeyModifiers::ALT,
-            _ => return Err(()),
+        use crossterm::event::KeyModifiers as Modifiers;
+
+        if modifiers.contains(Modifiers::META) {
+            return Err(());
+        }
+
+        Ok([
+            (Modifiers::SHIFT, KeyModifiers::SHIFT),
+            (Modifiers::CONTROL, KeyModifiers::CONTROL),
+            (Modifiers::ALT, KeyModifiers::ALT),
+            (Modifiers::SUPER, KeyModifiers::SUPER),
+            (Modifiers::HYPER, KeyModifiers::HYPER),
+        ]
+        .into_iter()
+        .filter(|&(from, _)| modifiers.contains(from))
+        .fold(KeyModifiers::NONE, |acc, (_, to)| acc | to))
+    }
+}
+
+/// A mouse event at a cell of the screen, with `(0, 0)` the top left cell.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub column: u16,
+    pub row: u16,
+    pub modifiers: KeyModifiers,
+}
+
+impl MouseEvent {
+    pub fn new(kind: MouseEventKind, column: u16, row: u16) -> Self {
+        Self { kind, column, row, modifiers: KeyModifiers::NONE }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum MouseEventKind {
+    Down(MouseButton),
+    Up(MouseButton),
+    /// The mouse moved with the button held down.
+    Drag(MouseButton),
+    Moved,
+    ScrollDown,
+    ScrollUp,
+    ScrollLeft,
+    ScrollRight,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[cfg(feature = "crossterm")]
+impl TryFrom<crossterm::event::MouseEvent> for MouseEvent {
+    type Error = ();
+
+    fn try_from(event: crossterm::event::MouseEvent) -> Result<Self, Self::Error> {
+        use crossterm::event::MouseEventKind as Kind;
+
+        let kind = match event.kind {
+            Kind::Down(button) => MouseEventKind::Down(button.into()),
+            Kind::Up(button) => MouseEventKind::Up(button.into()),
+            Kind::Drag(button) => MouseEventKind::Drag(button.into()),
+            Kind::Moved => MouseEventKind::Moved,
+            Kind::ScrollDown => MouseEventKind::ScrollDown,
+            Kind::ScrollUp => MouseEventKind::ScrollUp,
+            Kind::ScrollLeft => MouseEventKind::ScrollLeft,
+            Kind::ScrollRight => MouseEventKind::ScrollRight,
+        };
+
+        Ok(MouseEvent {
+            kind,
+            column: event.column,
+            row: event.row,
+            modifiers: event.modifiers.try_into()?,
         })
     }
 }
 
+#[cfg(feature = "crossterm")]
+impl From<crossterm::event::MouseButton> for MouseButton {
+    fn from(button: crossterm::event::MouseButton) -> Self {
+        match button {
+            crossterm::event::MouseButton::Left => MouseButton::Left,
+            crossterm::event::MouseButton::Right => MouseButton::Right,
+            crossterm::event::MouseButton::Middle => MouseButton::Middle,
+        }
+    }
+}
+
 impl From<KeyEvent> for Event {
     #[inline]
     fn from(v: KeyEvent) -> Self {
@@ -214,6 +348,8 @@ impl fmt::Display for KeyEvent {
                         KeyModifiers::CONTROL => "C",
                         KeyModifiers::SHIFT => "S",
                         KeyModifiers::ALT => "A",
+                        KeyModifiers::SUPER => "D",
+                        KeyModifiers::HYPER => "H",
                         _ => unreachable!("missing modifier case in fmt::Display for KeyEvent"),
                     }
                 )?;
@@ -251,6 +387,15 @@ impl KeyEvent {
     pub fn modifiers(&self) -> KeyModifiers {
         self.modifiers
     }
+
+    /// The character typed by the key, if it is a character without modifiers other than shift.
+    #[inline]
+    pub fn char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if (self.modifiers - KeyModifiers::SHIFT).is_empty() => Some(c),
+            _ => None,
+        }
+    }
 }
 
 impl From<KeyCode> for KeyEvent {
@@ -381,6 +526,8 @@ fn key_event() -> impl Parser<char, KeyEvent, Error = chumsky::error::Simple<cha
         kw("c").to(KeyModifiers::CONTROL),
         kw("s").to(KeyModifiers::SHIFT),
         kw("a").to(KeyModifiers::ALT),
+        kw("d").to(KeyModifiers::SUPER),
+        kw("h").to(KeyModifiers::HYPER),
     ));
 
     let modifiers = modifier
@@ -30,9 +30,30 @@ pub(crate) fn capabilities() -> lsp_types::ClientCapabilities {
             semantic_tokens: Some(lsp_types::SemanticTokensWorkspaceClientCapabilities {
                 refresh_support: None,
             }),
+            file_operations: Some(lsp_types::WorkspaceFileOperationsClientCapabilities {
+                will_rename: Some(true),
+                ..Default::default()
+            }),
             ..Default::default()
         }),
         text_document: Some(lsp_types::TextDocumentClientCapabilities {
+            completion: Some(lsp_types::CompletionClientCapabilities {
+                completion_item: Some(lsp_types::CompletionItemCapability {
+                    documentation_format: Some(vec![
+                        lsp_types::MarkupKind::PlainText,
+                        lsp_types::MarkupKind::Markdown,
+                    ]),
+                    deprecated_support: Some(true),
+                    tag_support: Some(lsp_types::TagSupport {
+                        value_set: vec![lsp_types::CompletionItemTag::DEPRECATED],
+                    }),
+                    resolve_support: Some(lsp_types::CompletionItemCapabilityResolveSupport {
+                        properties: vec!["documentation".to_string(), "detail".to_string()],
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
             declaration: GOTO_CAPABILITY,
             definition: GOTO_CAPABILITY,
             type_definition: GOTO_CAPABILITY,
@@ -90,7 +111,7 @@ pub(crate) fn capabilities() -> lsp_types::ClientCapabilities {
                 // There are in order of preference
                 lsp_types::PositionEncodingKind::UTF8,
                 lsp_types::PositionEncodingKind::UTF16,
-                // No support for UTF32 yet
+                lsp_types::PositionEncodingKind::UTF32,
             ]),
             ..Default::default()
         }),
@@ -252,7 +273,10 @@ impl async_lsp::LanguageClient for LanguageClient {
         &mut self,
         params: <lsp_notification!("window/showMessage") as Notification>::Params,
     ) -> Self::NotifyResult {
-        let _ = params;
+        self.0.send(move |editor| {
+            editor.notify(from_proto::message_level(params.typ), params.message);
+            Ok(())
+        });
         ControlFlow::Continue(())
     }
 
@@ -261,9 +285,7 @@ impl async_lsp::LanguageClient for LanguageClient {
         params: <lsp_notification!("window/logMessage") as Notification>::Params,
     ) -> Self::NotifyResult {
         self.0.send(move |editor| {
-            tracing::info!("received log message");
-            // TODO there are multiple levels of log messages
-            editor.set_error(params.message);
+            editor.log(from_proto::message_level(params.typ), params.message);
             Ok(())
         });
         ControlFlow::Continue(())
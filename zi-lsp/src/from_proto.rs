@@ -5,7 +5,7 @@
 
 use async_lsp::lsp_types;
 use zi::lstypes::Severity;
-use zi::{Delta, Deltas, Point, PointRange, Text, lstypes};
+use zi::{Delta, Deltas, MessageLevel, Point, PointRange, Text, lstypes};
 
 pub fn goto_definition(
     encoding: lstypes::PositionEncoding,
@@ -63,6 +63,60 @@ pub fn deltas(
     if deltas.len() < n { None } else { Some(deltas) }
 }
 
+/// Convert a workspace edit, the edits are not decoded as the documents may not be open.
+/// Resource operations (creating, renaming, and deleting files) are not supported and are skipped.
+pub fn workspace_edit(
+    encoding: lstypes::PositionEncoding,
+    edit: lsp_types::WorkspaceEdit,
+) -> lstypes::WorkspaceEdit {
+    let text_edits = |edits: Vec<lsp_types::TextEdit>| {
+        edits
+            .into_iter()
+            .map(|edit| lstypes::TextEdit {
+                range: encoded_range(encoding, edit.range),
+                new_text: edit.new_text,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let document_edit = |edit: lsp_types::TextDocumentEdit| {
+        let edits = edit
+            .edits
+            .into_iter()
+            .map(|edit| match edit {
+                lsp_types::OneOf::Left(edit) => edit,
+                lsp_types::OneOf::Right(edit) => edit.text_edit,
+            })
+            .collect();
+        (edit.text_document.uri, text_edits(edits))
+    };
+
+    // `document_changes` takes precedence over `changes` if both are present.
+    let changes = match edit.document_changes {
+        Some(lsp_types::DocumentChanges::Edits(edits)) => {
+            edits.into_iter().map(document_edit).collect()
+        }
+        Some(lsp_types::DocumentChanges::Operations(ops)) => ops
+            .into_iter()
+            .filter_map(|op| match op {
+                lsp_types::DocumentChangeOperation::Edit(edit) => Some(document_edit(edit)),
+                lsp_types::DocumentChangeOperation::Op(op) => {
+                    tracing::warn!(?op, "ignoring unsupported resource operation");
+                    None
+                }
+            })
+            .collect(),
+        None => edit
+            .changes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(url, edits)| (url, text_edits(edits)))
+            .collect(),
+    };
+
+    lstypes::WorkspaceEdit { changes }
+}
+
 pub fn range(
     encoding: lstypes::PositionEncoding,
     text: &(impl Text + ?Sized),
@@ -76,25 +130,7 @@ pub fn point(
     text: &(impl Text + ?Sized),
     point: lsp_types::Position,
 ) -> Option<Point> {
-    if point.line as usize > text.len_lines() {
-        return None;
-    }
-
-    match encoding {
-        lstypes::PositionEncoding::Utf8 => {
-            Some(Point::new(point.line as usize, point.character as usize))
-        }
-        lstypes::PositionEncoding::Utf16 => {
-            let line_start_byte = text.line_to_byte(point.line as usize);
-            let line_start_cu = text.byte_to_utf16_cu(line_start_byte);
-            if line_start_cu + point.character as usize > text.len_utf16_cu() {
-                return None;
-            }
-
-            let byte = text.utf16_cu_to_byte(line_start_cu + point.character as usize);
-            Some(text.byte_to_point(byte))
-        }
-    }
+    encoding.decode(text, Point::new(point.line as usize, point.character as usize))
 }
 
 pub fn diagnostics(
@@ -122,6 +158,14 @@ pub fn diagnostic(
     })
 }
 
+pub fn message_level(typ: lsp_types::MessageType) -> MessageLevel {
+    match typ {
+        lsp_types::MessageType::ERROR => MessageLevel::Error,
+        lsp_types::MessageType::WARNING => MessageLevel::Warning,
+        _ => MessageLevel::Info,
+    }
+}
+
 fn encoded_range(
     encoding: lstypes::PositionEncoding,
     range: lsp_types::Range,
@@ -148,15 +192,69 @@ pub fn completion_response(
     }
 }
 
+#[expect(deprecated)]
 pub fn completion_item(
     _encoding: lstypes::PositionEncoding,
     _text: &(impl Text + ?Sized),
     item: lsp_types::CompletionItem,
 ) -> Option<lstypes::CompletionItem> {
+    // Keep the original item around as it must be sent back as is to resolve it.
+    let data = serde_json::to_string(&item).ok();
+    let deprecated = item.deprecated == Some(true)
+        || item
+            .tags
+            .as_ref()
+            .is_some_and(|tags| tags.contains(&lsp_types::CompletionItemTag::DEPRECATED));
     Some(lstypes::CompletionItem {
         label: item.label,
         insert_text: item.insert_text,
         filter_text: item.filter_text,
+        sort_text: item.sort_text,
+        kind: item.kind.and_then(completion_item_kind),
+        detail: item.detail,
+        documentation: item.documentation.map(|doc| match doc {
+            lsp_types::Documentation::String(doc) => doc,
+            lsp_types::Documentation::MarkupContent(content) => content.value,
+        }),
+        deprecated,
+        snippet: item.insert_text_format == Some(lsp_types::InsertTextFormat::SNIPPET),
+        data,
+    })
+}
+
+fn completion_item_kind(
+    kind: lsp_types::CompletionItemKind,
+) -> Option<lstypes::CompletionItemKind> {
+    use lsp_types::CompletionItemKind as K;
+    use lstypes::CompletionItemKind as Kind;
+
+    Some(match kind {
+        K::TEXT => Kind::Text,
+        K::METHOD => Kind::Method,
+        K::FUNCTION => Kind::Function,
+        K::CONSTRUCTOR => Kind::Constructor,
+        K::FIELD => Kind::Field,
+        K::VARIABLE => Kind::Variable,
+        K::CLASS => Kind::Class,
+        K::INTERFACE => Kind::Interface,
+        K::MODULE => Kind::Module,
+        K::PROPERTY => Kind::Property,
+        K::UNIT => Kind::Unit,
+        K::VALUE => Kind::Value,
+        K::ENUM => Kind::Enum,
+        K::KEYWORD => Kind::Keyword,
+        K::SNIPPET => Kind::Snippet,
+        K::COLOR => Kind::Color,
+        K::FILE => Kind::File,
+        K::REFERENCE => Kind::Reference,
+        K::FOLDER => Kind::Folder,
+        K::ENUM_MEMBER => Kind::EnumMember,
+        K::CONSTANT => Kind::Constant,
+        K::STRUCT => Kind::Struct,
+        K::EVENT => Kind::Event,
+        K::OPERATOR => Kind::Operator,
+        K::TYPE_PARAMETER => Kind::TypeParameter,
+        _ => return None,
     })
 }
 
@@ -183,10 +281,11 @@ pub fn semantic_tokens(
             let hl = semantic_tt_to_highlight(&legend.token_types[token.token_type as usize])
                 .map(|name| theme.highlight_id_by_name(name))?;
 
-            let point = point(encoding, text, lsp_types::Position::new(line, char))?;
-            let start = text.point_to_byte(point);
-            // TODO need to convert this length to the right encoding too...
-            Some(zi::Mark::builder(start).width(token.length as usize).hl(hl))
+            // Tokens don't span lines so the length is in code units of the same line.
+            let start = point(encoding, text, lsp_types::Position::new(line, char))?;
+            let end = point(encoding, text, lsp_types::Position::new(line, char + token.length))?;
+            let start = text.point_to_byte(start);
+            Some(zi::Mark::builder(start).width(text.point_to_byte(end) - start).hl(hl))
         })
         .collect::<Vec<_>>()
 }
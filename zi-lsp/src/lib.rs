@@ -105,6 +105,10 @@ impl zi::LanguageServiceConfig for LanguageServerConfig {
             start(LanguageClient::new(client.clone()), cwd, &self.command, &self.args[..])?;
         Ok((Box::new(LanguageService::new(client, server)), Box::pin(fut.map_err(Into::into))))
     }
+
+    fn command(&self) -> Option<&OsStr> {
+        Some(&self.command)
+    }
 }
 
 trait EditorExt {
@@ -79,6 +79,9 @@ impl LanguageService {
                 enc if *enc == lsp_types::PositionEncodingKind::UTF16 => {
                     lstypes::PositionEncoding::Utf16
                 }
+                enc if *enc == lsp_types::PositionEncodingKind::UTF32 => {
+                    lstypes::PositionEncoding::Utf32
+                }
                 _ => {
                     tracing::warn!("server returned unknown position encoding: {encoding:?}",);
                     lstypes::PositionEncoding::default()
@@ -138,6 +141,18 @@ impl zi::LanguageService for LanguageService {
         Some(())
     }
 
+    fn completion_resolve_capabilities(&self) -> Option<()> {
+        self.capabilities().completion_provider.as_ref()?.resolve_provider?.then_some(())
+    }
+
+    fn completion_trigger_characters(&self) -> Vec<char> {
+        self.capabilities()
+            .completion_provider
+            .as_ref()
+            .and_then(|provider| provider.trigger_characters.as_ref())
+            .map_or_else(Vec::new, |chars| chars.iter().filter_map(|c| c.chars().next()).collect())
+    }
+
     fn reference_capabilities(&self) -> Option<()> {
         self.capabilities().references_provider.as_ref()?;
         Some(())
@@ -168,6 +183,12 @@ impl zi::LanguageService for LanguageService {
         Some(())
     }
 
+    fn will_rename_files_capabilities(&self) -> Option<()> {
+        let workspace = self.capabilities().workspace.as_ref()?;
+        workspace.file_operations.as_ref()?.will_rename.as_ref()?;
+        Some(())
+    }
+
     fn initialize(&mut self, params: lstypes::InitializeParams) -> ResponseFuture<()> {
         let caps = Arc::clone(&self.capabilities);
         #[expect(deprecated)]
@@ -481,6 +502,26 @@ impl zi::LanguageService for LanguageService {
             .boxed()
     }
 
+    fn resolve_completion_item(
+        &mut self,
+        item: lstypes::CompletionItem,
+    ) -> ResponseFuture<lstypes::CompletionItem> {
+        let enc = self.position_encoding();
+        let Some(lsp_item) = to_proto::completion_item(&item) else {
+            return Box::pin(async move { Ok(item) });
+        };
+
+        self.server
+            .completion_item_resolve(lsp_item)
+            .map(move |res| {
+                res.map(|resolved| {
+                    from_proto::completion_item(enc, &Rope::new(), resolved).unwrap_or(item)
+                })
+            })
+            .map_err(Into::into)
+            .boxed()
+    }
+
     fn semantic_tokens_full(
         &mut self,
         theme: Setting<Theme>,
@@ -582,6 +623,27 @@ impl zi::LanguageService for LanguageService {
             .boxed()
     }
 
+    fn will_rename_files(
+        &mut self,
+        params: lstypes::RenameFilesParams,
+    ) -> ResponseFuture<Option<lstypes::WorkspaceEdit>> {
+        let enc = self.position_encoding();
+        self.server
+            .will_rename_files(lsp_types::RenameFilesParams {
+                files: params
+                    .files
+                    .into_iter()
+                    .map(|file| lsp_types::FileRename {
+                        old_uri: file.old_url.to_string(),
+                        new_uri: file.new_url.to_string(),
+                    })
+                    .collect(),
+            })
+            .map_ok(move |edit| edit.map(|edit| from_proto::workspace_edit(enc, edit)))
+            .map_err(Into::into)
+            .boxed()
+    }
+
     fn shutdown(&mut self) -> ResponseFuture<()> {
         self.server.shutdown(()).map_err(Into::into).boxed()
     }
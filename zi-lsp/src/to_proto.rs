@@ -48,15 +48,8 @@ pub fn byte(
     text: &(impl Text + ?Sized),
     byte: usize,
 ) -> lsp_types::Position {
-    match encoding {
-        lstypes::PositionEncoding::Utf8 => point(encoding, text, text.byte_to_point(byte)),
-        lstypes::PositionEncoding::Utf16 => {
-            let line = text.byte_to_line(byte);
-            let line_start = text.byte_to_utf16_cu(text.line_to_byte(line));
-            let col = text.byte_to_utf16_cu(byte) - line_start;
-            lsp_types::Position::new(line as u32, col as u32)
-        }
-    }
+    let point = encoding.encode(text, byte);
+    lsp_types::Position::new(point.line() as u32, point.col() as u32)
 }
 
 pub fn point(
@@ -64,12 +57,7 @@ pub fn point(
     text: &(impl Text + ?Sized),
     point: Point,
 ) -> lsp_types::Position {
-    match encoding {
-        lstypes::PositionEncoding::Utf8 => {
-            lsp_types::Position::new(point.line() as u32, point.col() as u32)
-        }
-        lstypes::PositionEncoding::Utf16 => byte(encoding, text, text.point_to_byte(point)),
-    }
+    byte(encoding, text, text.point_to_byte(point))
 }
 
 pub fn document_position(
@@ -82,3 +70,8 @@ pub fn document_position(
         position: point(encoding, &text, params.point),
     }
 }
+
+/// The original item the completion item was converted from, see `from_proto::completion_item`.
+pub fn completion_item(item: &lstypes::CompletionItem) -> Option<lsp_types::CompletionItem> {
+    serde_json::from_str(item.data.as_deref()?).ok()
+}
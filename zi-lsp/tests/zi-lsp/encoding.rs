@@ -0,0 +1,71 @@
+use zi::lstypes::PositionEncoding::{self, Utf8, Utf16, Utf32};
+use zi::{TextBase, TextMut};
+use zi_lsp::{from_proto, to_proto};
+
+use super::*;
+
+#[test]
+fn position_encoding_matrix() {
+    // An emoji outside the BMP and a character with combining marks.
+    let text = "a😀b\ne\u{301}\u{303}x\n";
+
+    // The byte of each character boundary and its position in utf-8, utf-16, and utf-32.
+    let cases = [
+        (0, lsp_pos!(0:0), lsp_pos!(0:0), lsp_pos!(0:0)),
+        (1, lsp_pos!(0:1), lsp_pos!(0:1), lsp_pos!(0:1)),
+        (5, lsp_pos!(0:5), lsp_pos!(0:3), lsp_pos!(0:2)),
+        (6, lsp_pos!(0:6), lsp_pos!(0:4), lsp_pos!(0:3)),
+        (7, lsp_pos!(1:0), lsp_pos!(1:0), lsp_pos!(1:0)),
+        (8, lsp_pos!(1:1), lsp_pos!(1:1), lsp_pos!(1:1)),
+        (10, lsp_pos!(1:3), lsp_pos!(1:2), lsp_pos!(1:2)),
+        (12, lsp_pos!(1:5), lsp_pos!(1:3), lsp_pos!(1:3)),
+        (13, lsp_pos!(1:6), lsp_pos!(1:4), lsp_pos!(1:4)),
+        (14, lsp_pos!(2:0), lsp_pos!(2:0), lsp_pos!(2:0)),
+    ];
+
+    for (byte, utf8, utf16, utf32) in cases {
+        for (encoding, pos) in [(Utf8, utf8), (Utf16, utf16), (Utf32, utf32)] {
+            assert_eq!(to_proto::byte(encoding, text, byte), pos, "{encoding:?} byte {byte}");
+            let point = from_proto::point(encoding, text, pos).unwrap();
+            assert_eq!(text.point_to_byte(point), byte, "{encoding:?} {pos:?}");
+        }
+    }
+
+    // Positions within a character are rounded down to the start of the character, and positions
+    // past the end of a line are clamped to the end of the line.
+    let check = |encoding: PositionEncoding, pos: lsp_types::Position, byte: Option<usize>| {
+        let point = from_proto::point(encoding, text, pos);
+        assert_eq!(point.map(|point| text.point_to_byte(point)), byte, "{encoding:?} {pos:?}");
+    };
+
+    check(Utf8, lsp_pos!(0:3), Some(1));
+    check(Utf16, lsp_pos!(0:2), Some(1));
+    check(Utf8, lsp_pos!(1:2), Some(8));
+    check(Utf8, lsp_pos!(0:100), Some(6));
+    check(Utf16, lsp_pos!(0:100), Some(6));
+    check(Utf32, lsp_pos!(1:100), Some(13));
+    check(Utf32, lsp_pos!(2:1), Some(14));
+    check(Utf16, lsp_pos!(3:0), None);
+}
+
+#[test]
+fn position_encoding_edits() {
+    let text = "😀x\ne\u{301}y\n";
+    for (encoding, ranges) in [
+        (Utf8, [lsp_range!(0:4..0:5), lsp_range!(1:3..1:4)]),
+        (Utf16, [lsp_range!(0:2..0:3), lsp_range!(1:2..1:3)]),
+        (Utf32, [lsp_range!(0:1..0:2), lsp_range!(1:2..1:3)]),
+    ] {
+        let edits = ranges.map(|range| lsp_types::TextEdit { range, new_text: "z".to_string() });
+        let deltas = from_proto::deltas(encoding, text, edits).unwrap();
+        let mut s = text.to_string();
+        s.edit(&deltas);
+        assert_eq!(s, "😀z\ne\u{301}z\n", "{encoding:?}");
+
+        // The change events are ordered by start descending.
+        let changes = to_proto::deltas(encoding, text, &deltas);
+        let mut actual = changes.iter().map(|change| change.range.unwrap()).collect::<Vec<_>>();
+        actual.reverse();
+        assert_eq!(actual, ranges, "{encoding:?}");
+    }
+}
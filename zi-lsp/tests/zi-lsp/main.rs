@@ -15,6 +15,7 @@ use zi_test::{TestContext, new};
 
 mod definition;
 mod diagnostics;
+mod encoding;
 mod format;
 mod sync;
 
@@ -190,6 +190,51 @@ async fn lsp_changes_incremental_utf16() -> zi::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn lsp_changes_incremental_utf32() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let expected_events = ExpectedSequence::new([
+        vec![lsp_change_event!("\n")],
+        vec![lsp_change_event!(0:0..0:0 => "😀e\u{301}")],
+        // This would be 0:7 if utf-8 and 0:4 if utf-16
+        vec![lsp_change_event!(0:3..0:3 => "z")],
+    ]);
+
+    cx.setup_lang_server(zi::filetype!(text), "test-server", (), |builder| {
+        builder
+            .request::<request::Initialize, _>(move |_, _params| async {
+                Ok(lsp_types::InitializeResult {
+                    capabilities: lsp_types::ServerCapabilities {
+                        position_encoding: Some(lsp_types::PositionEncodingKind::UTF32),
+                        text_document_sync: Some(lsp_types::TextDocumentSyncCapability::Kind(
+                            lsp_types::TextDocumentSyncKind::INCREMENTAL,
+                        )),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+            })
+            .notification::<notification::DidChangeTextDocument>(move |_, params| {
+                expected_events.assert_eq(&params.content_changes);
+                Ok(())
+            })
+    })
+    .await;
+
+    let buf = cx.open_tmp("", zi::OpenFlags::SPAWN_LANGUAGE_SERVICES).await?;
+
+    cx.with(move |editor| {
+        editor.edit(buf, &deltas![0..0 => "😀e\u{301}"])?;
+        editor.edit(buf, &deltas![7..7 => "z"])
+    })
+    .await?;
+
+    cx.cleanup().await;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn lsp_changes_incremental_utf8_random() -> zi::Result<()> {
     let cx = new("").await;
@@ -12,17 +12,16 @@ fn main() {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Id(usize);
 
-impl From<Id> for u32 {
+impl From<Id> for u64 {
     #[inline]
-    fn from(id: Id) -> u32 {
-        debug_assert!(id.0 < u32::MAX as usize);
-        id.0 as u32
+    fn from(id: Id) -> u64 {
+        id.0 as u64
     }
 }
 
-impl From<u32> for Id {
+impl From<u64> for Id {
     #[inline]
-    fn from(id: u32) -> Id {
+    fn from(id: u64) -> Id {
         Id(id as usize)
     }
 }
@@ -98,3 +97,55 @@ fn bench_marktree_get<const LEAF_SIZE: usize>(bencher: Bencher<'_, '_>) {
         });
     });
 }
+
+#[divan::bench(consts = LEAF_SIZES, args = [false, true])]
+fn bench_marktree_shift_many<const LEAF_SIZE: usize>(bencher: Bencher<'_, '_>, batched: bool) {
+    let edits = (0..1000).map(|i| (i * 100..i * 100 + 10, 5)).collect::<Vec<_>>();
+    bencher
+        .with_inputs(|| {
+            MarkTree::<Id, LEAF_SIZE>::build(
+                100_000,
+                (0..20_000).map(|i| (Id(i), MarkBuilder::new(i * 5).width(2))),
+            )
+        })
+        .bench_local_values(|mut tree| {
+            if batched {
+                tree.shift_many(edits.iter().cloned());
+            } else {
+                edits.iter().rev().for_each(|(range, by)| tree.shift(range.clone(), *by));
+            }
+            tree
+        })
+}
+
+#[divan::bench(consts = LEAF_SIZES, args = [false, true])]
+fn bench_marktree_after_edits<const LEAF_SIZE: usize>(bencher: Bencher<'_, '_>, compact: bool) {
+    let n = 100_000;
+    let mut tree = MarkTree::<Id, LEAF_SIZE>::build(
+        n,
+        (0..10_000).map(|i| (Id(i), MarkBuilder::new(i * 10).width(4))),
+    );
+
+    // Steady state after many small edits that keep the length constant.
+    let mut seed = 0x2545f491u64;
+    for _ in 0..1_000_000 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let at = (seed >> 33) as usize % (n - 1);
+        if seed & 1 == 0 {
+            tree.shift(at..at, 1);
+            tree.shift(at + 1..at + 2, 0);
+        } else {
+            tree.shift(at..at + 1, 0);
+            tree.shift(at..at, 1);
+        }
+    }
+
+    if compact {
+        tree.compact();
+    }
+
+    bencher.bench_local(move || {
+        (0..10_000).for_each(|i| assert!(tree.get(Id(i)).is_some()));
+        tree.range(..).count()
+    });
+}
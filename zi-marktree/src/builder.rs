@@ -3,11 +3,12 @@ use std::iter;
 use std::marker::PhantomData;
 
 use arrayvec::ArrayVec;
-use crop::tree::TreeBuilder;
+use crop::tree::{Tree, TreeBuilder};
+use rustc_hash::FxHashMap;
 use tinyset::SetU64;
 
 use crate::key::{Flags, Key};
-use crate::{Bias, Extent, Inserter, Leaf, MarkTree, MarkTreeId};
+use crate::{ARITY, Bias, Extent, Inserter, Invalidation, Leaf, MarkTree, MarkTreeId};
 
 #[derive(Debug, Clone, Copy)]
 pub struct MarkBuilder {
@@ -15,15 +16,35 @@ pub struct MarkBuilder {
     pub(super) width: usize,
     pub(super) start_flags: Flags,
     pub(super) end_flags: Flags,
+    pub(super) invalidation: Invalidation,
 }
 
 impl MarkBuilder {
     pub fn new(at: usize) -> Self {
-        Self { at, width: 0, start_flags: Flags::empty(), end_flags: Flags::END }
+        Self {
+            at,
+            width: 0,
+            start_flags: Flags::empty(),
+            end_flags: Flags::END,
+            invalidation: Invalidation::Collapse,
+        }
+    }
+
+    pub fn insert<Id: MarkTreeId, const N: usize, T: Default>(
+        self,
+        tree: &mut MarkTree<Id, N, T>,
+        id: Id,
+    ) {
+        self.insert_with_payload(tree, id, T::default())
     }
 
-    pub fn insert<Id: MarkTreeId, const N: usize>(self, tree: &mut MarkTree<Id, N>, id: Id) {
-        drop(Inserter { tree, id, builder: self })
+    pub fn insert_with_payload<Id: MarkTreeId, const N: usize, T>(
+        self,
+        tree: &mut MarkTree<Id, N, T>,
+        id: Id,
+        payload: T,
+    ) {
+        drop(Inserter { tree, id, builder: self, payload: Some(payload) })
     }
 
     pub fn width(mut self, width: usize) -> Self {
@@ -53,20 +74,49 @@ impl MarkBuilder {
         }
         self
     }
+
+    /// What happens to the mark when its range is deleted, see [`Invalidation`].
+    pub fn invalidation(mut self, invalidation: Invalidation) -> Self {
+        self.invalidation = invalidation;
+        self
+    }
 }
 
-impl<Id: MarkTreeId, const N: usize> MarkTree<Id, N> {
-    /// Fast construction of a mark tree from a iterator of `(id, mark)` pairs.
+impl<Id: MarkTreeId, const N: usize, T> MarkTree<Id, N, T> {
+    /// Fast construction of a mark tree from a iterator of `(id, mark)` pairs with default
+    /// payloads.
     /// The `len` must be larger than any position of the marks.
-    pub fn build<T: IntoIterator<Item = (Id, MarkBuilder)>>(len: usize, iter: T) -> Self {
+    pub fn build(len: usize, iter: impl IntoIterator<Item = (Id, MarkBuilder)>) -> Self
+    where
+        T: Default,
+    {
+        Self::build_with_payloads(
+            len,
+            iter.into_iter().map(|(id, builder)| (id, builder, T::default())),
+        )
+    }
+
+    /// Fast construction of a mark tree from a iterator of `(id, mark, payload)` triples.
+    /// The `len` must be larger than any position of the marks.
+    pub fn build_with_payloads(
+        len: usize,
+        iter: impl IntoIterator<Item = (Id, MarkBuilder, T)>,
+    ) -> Self {
         let mut iter = iter.into_iter().peekable();
         if iter.peek().is_none() {
             return Self::new(len);
         }
 
         let mut map = BTreeMap::new();
-        for (id, builder) in iter {
+        let mut payloads = FxHashMap::default();
+        let mut invalidations = FxHashMap::default();
+        for (id, builder, payload) in iter {
             let id = id.into();
+            payloads.insert(id, payload);
+            if builder.invalidation != Invalidation::Collapse {
+                invalidations.insert(id, builder);
+            }
+
             let start_key = Key::new(id, builder.start_flags);
             map.entry(builder.at).or_insert_with(SetU64::new).insert(start_key.into_raw());
 
@@ -78,46 +128,53 @@ impl<Id: MarkTreeId, const N: usize> MarkTree<Id, N> {
             }
         }
 
-        let mut extents = vec![];
-        let (&offset, _) = map.first_key_value().expect("insertions is non-empty");
-        if offset > 0 {
-            extents.push(Extent { length: offset, keys: SetU64::new() });
-        }
+        let tree = Self {
+            tree: build_tree(len, map),
+            payloads,
+            invalidations,
+            invalidated: vec![],
+            _id: PhantomData,
+        };
 
-        let next_offsets = map
-            .iter()
-            .map(|(&offset, _)| offset)
-            .skip(1)
-            .chain(iter::once(len))
-            .collect::<Vec<_>>();
-
-        assert_eq!(next_offsets.len(), map.len());
-        extents.extend(map.into_iter().zip(next_offsets).map(
-            |((start_offset, keys), end_offset)| {
-                // TODO this assertion can fail if `len` is larger than the largest offset
-                debug_assert!(
-                    start_offset <= end_offset,
-                    "start_offset: {start_offset}, end_offset: {end_offset}",
-                );
-                Extent { length: end_offset - start_offset, keys }
-            },
-        ));
-
-        let mut builder = TreeBuilder::new();
-        let mut chunks = extents.into_iter().array_chunks::<N>();
-        for leaf in chunks.by_ref().map(ArrayVec::from).map(Leaf::from) {
-            builder.append(leaf);
-        }
+        assert_eq!(tree.len(), len);
 
-        let array = ArrayVec::from_iter(chunks.into_remainder());
-        if !array.is_empty() {
-            builder.append(Leaf::from(array));
-        }
+        tree
+    }
+}
 
-        let tree = Self { tree: builder.build(), _id: PhantomData };
+/// Build the tree from the keys at each offset, `keys` must be non-empty.
+pub(super) fn build_tree<const N: usize>(
+    len: usize,
+    keys: BTreeMap<usize, SetU64>,
+) -> Tree<ARITY, Leaf<N>> {
+    let mut extents = vec![];
+    let (&offset, _) = keys.first_key_value().expect("keys is non-empty");
+    if offset > 0 {
+        extents.push(Extent { length: offset, keys: SetU64::new() });
+    }
 
-        assert_eq!(tree.len(), len);
+    let next_offsets = keys.keys().copied().skip(1).chain(iter::once(len)).collect::<Vec<_>>();
+
+    assert_eq!(next_offsets.len(), keys.len());
+    extents.extend(keys.into_iter().zip(next_offsets).map(|((start_offset, keys), end_offset)| {
+        // TODO this assertion can fail if `len` is larger than the largest offset
+        debug_assert!(
+            start_offset <= end_offset,
+            "start_offset: {start_offset}, end_offset: {end_offset}",
+        );
+        Extent { length: end_offset - start_offset, keys }
+    }));
+
+    let mut builder = TreeBuilder::new();
+    let mut chunks = extents.into_iter().array_chunks::<N>();
+    for leaf in chunks.by_ref().map(ArrayVec::from).map(Leaf::from) {
+        builder.append(leaf);
+    }
 
-        tree
+    let array = ArrayVec::from_iter(chunks.into_remainder());
+    if !array.is_empty() {
+        builder.append(Leaf::from(array));
     }
+
+    builder.build()
 }
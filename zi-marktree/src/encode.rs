@@ -0,0 +1,202 @@
+//! A compact binary format for the items of a `MarkTree` (without payloads).
+//!
+//! The format is a header of the magic bytes, a version byte, the length of the tree and the
+//! number of items, followed by a record per item ordered by start. Each record is prefixed with
+//! its length so later versions can append fields that older versions skip.
+//! All integers are LEB128 varints, starts are encoded relative to the previous start.
+
+use std::fmt;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::key::Flags;
+use crate::{Bias, Invalidation, MarkBuilder, MarkTree, MarkTreeId};
+
+const MAGIC: &[u8; 3] = b"zmt";
+const VERSION: u8 = 1;
+
+const START_BIAS_LEFT: u8 = 1 << 0;
+const END_BIAS_LEFT: u8 = 1 << 1;
+const INVALIDATION_SHIFT: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(&'static str);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid marktree encoding: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+struct Record {
+    id: u64,
+    start: usize,
+    end: usize,
+    flags: u8,
+}
+
+impl<Id: MarkTreeId, const N: usize, T> MarkTree<Id, N, T> {
+    /// Encode the ids, ranges, biases, and invalidation policies of the items, see
+    /// [`MarkTree::decode`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut records = Vec::<Record>::new();
+        let mut indices = FxHashMap::default();
+        // The end key of a collapsed range needn't come after its start key, so the ends are
+        // filled in once every start is known.
+        let mut ends = vec![];
+        for (offset, key) in self.keys() {
+            let left = key.flags().contains(Flags::BIAS_LEFT);
+            if key.flags().contains(Flags::END) {
+                ends.push((key.id(), offset, left));
+                continue;
+            }
+
+            let invalidation = match self.invalidations.get(&key.id()) {
+                Some(builder) => builder.invalidation,
+                None => Invalidation::Collapse,
+            };
+
+            let mut flags = (invalidation as u8) << INVALIDATION_SHIFT;
+            if left {
+                flags |= START_BIAS_LEFT;
+            }
+
+            indices.insert(key.id(), records.len());
+            records.push(Record { id: key.id(), start: offset, end: offset, flags });
+        }
+
+        for (id, offset, left) in ends {
+            let record = &mut records[indices[&id]];
+            record.end = offset;
+            if left {
+                record.flags |= END_BIAS_LEFT;
+            }
+        }
+
+        let mut buf = MAGIC.to_vec();
+        buf.push(VERSION);
+        write_varint(&mut buf, self.len() as u64);
+        write_varint(&mut buf, records.len() as u64);
+
+        let mut prev_start = 0;
+        let mut record_buf = vec![];
+        for record in records {
+            record_buf.clear();
+            write_varint(&mut record_buf, record.id);
+            write_varint(&mut record_buf, (record.start - prev_start) as u64);
+            write_varint(&mut record_buf, (record.end - record.start) as u64);
+            record_buf.push(record.flags);
+            prev_start = record.start;
+
+            write_varint(&mut buf, record_buf.len() as u64);
+            buf.extend_from_slice(&record_buf);
+        }
+
+        buf
+    }
+
+    /// Decode the items encoded by [`MarkTree::encode`], each item gets a default payload.
+    pub fn decode(mut bytes: &[u8]) -> Result<Self, DecodeError>
+    where
+        T: Default,
+    {
+        let bytes = &mut bytes;
+        if take(bytes, MAGIC.len())? != MAGIC {
+            return Err(DecodeError("missing magic bytes"));
+        }
+
+        // Versions are backwards compatible, newer versions only append fields to records.
+        if take(bytes, 1)?[0] < 1 {
+            return Err(DecodeError("unknown version"));
+        }
+
+        let len = read_usize(bytes)?;
+        let count = read_usize(bytes)?;
+
+        let mut items = Vec::with_capacity(count.min(bytes.len()));
+        let mut ids = FxHashSet::default();
+        let mut start = 0;
+        for _ in 0..count {
+            let record_len = read_usize(bytes)?;
+            let record = &mut take(bytes, record_len)?;
+
+            let id = read_varint(record)?;
+            if id >= 1 << 56 {
+                return Err(DecodeError("id out of range"));
+            }
+            start = start
+                .checked_add(read_usize(record)?)
+                .ok_or(DecodeError("position out of range"))?;
+            let width = read_usize(record)?;
+            let flags = take(record, 1)?[0];
+
+            if !ids.insert(id) {
+                return Err(DecodeError("duplicate id"));
+            }
+
+            if start.checked_add(width).is_none_or(|end| end > len) {
+                return Err(DecodeError("item out of bounds"));
+            }
+
+            let bias = |mask| if flags & mask != 0 { Bias::Left } else { Bias::Right };
+            let invalidation = match (flags >> INVALIDATION_SHIFT) & 0b11 {
+                0 => Invalidation::Collapse,
+                1 => Invalidation::Delete,
+                2 => Invalidation::PinLeft,
+                _ => Invalidation::PinRight,
+            };
+
+            let builder = MarkBuilder::new(start)
+                .width(width)
+                .start_bias(bias(START_BIAS_LEFT))
+                .end_bias(bias(END_BIAS_LEFT))
+                .invalidation(invalidation);
+            items.push((Id::from(id), builder));
+        }
+
+        if !bytes.is_empty() {
+            return Err(DecodeError("trailing bytes"));
+        }
+
+        Ok(Self::build(len, items))
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take(bytes, 1)?[0];
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(DecodeError("varint too long"))
+}
+
+fn read_usize(bytes: &mut &[u8]) -> Result<usize, DecodeError> {
+    usize::try_from(read_varint(bytes)?).map_err(|_| DecodeError("integer out of range"))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], DecodeError> {
+    if bytes.len() < n {
+        return Err(DecodeError("unexpected end of input"));
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
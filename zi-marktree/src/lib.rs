@@ -7,16 +7,22 @@
 
 mod bitbag;
 mod builder;
+mod encode;
+mod snapshot;
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::marker::PhantomData;
 use std::ops::{Add, AddAssign, Range, RangeBounds, Sub, SubAssign};
-use std::{cmp, fmt, iter};
+use std::{cmp, fmt, iter, mem};
 
 use arrayvec::ArrayVec;
+use croaring::Treemap;
+// The generalized sum tree lives in our fork of `crop` (see the `[patch]` in the workspace
+// manifest) rather than in a crate of its own. `Leaf` below implements its traits.
 use crop::tree::{
     Arc, AsSlice, BalancedLeaf, BaseMeasured, Metric, Node, ReplaceableLeaf, Summarize, Tree,
 };
+use rustc_hash::FxHashMap;
 use smallvec::{SmallVec, smallvec};
 use stdx::iter::ExactChain;
 use stdx::range::RangeExt;
@@ -24,10 +30,54 @@ use tinyset::SetU64;
 
 use self::bitbag::Bitbag;
 pub use self::builder::MarkBuilder;
+pub use self::encode::DecodeError;
 use self::extent_builder::ExtentBuilder;
 use self::key::{Flags, Key};
+pub use self::snapshot::Snapshot;
 
-pub trait MarkTreeId: Copy + Eq + From<u32> + Into<u32> + fmt::Debug + 'static {}
+/// The id of an item, a `u64` of which the lower 56 bits can be used, see [`TaggedId`].
+pub trait MarkTreeId: Copy + Eq + From<u64> + Into<u64> + fmt::Debug + 'static {}
+
+/// A 48-bit id with an 8-bit tag, e.g. a namespace, so ids only have to be unique per tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaggedId(u64);
+
+impl TaggedId {
+    pub const MAX_ID: u64 = (1 << 48) - 1;
+
+    #[inline]
+    #[track_caller]
+    pub fn new(tag: u8, id: u64) -> Self {
+        assert!(id <= Self::MAX_ID, "id {id} is too large");
+        Self((tag as u64) << 48 | id)
+    }
+
+    #[inline]
+    pub fn tag(self) -> u8 {
+        (self.0 >> 48) as u8
+    }
+
+    #[inline]
+    pub fn id(self) -> u64 {
+        self.0 & Self::MAX_ID
+    }
+}
+
+impl From<TaggedId> for u64 {
+    #[inline]
+    fn from(id: TaggedId) -> u64 {
+        id.0
+    }
+}
+
+impl From<u64> for TaggedId {
+    #[inline]
+    fn from(raw: u64) -> TaggedId {
+        TaggedId(raw)
+    }
+}
+
+impl MarkTreeId for TaggedId {}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Bias {
@@ -35,6 +85,23 @@ pub enum Bias {
     Right,
 }
 
+/// What happens to an item when a shift deletes the entire range of the item.
+/// An empty item is only invalidated if it is strictly within the deleted range.
+/// Only items with a policy other than the default are tracked, so only those are reported by
+/// [`MarkTree::invalidated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Invalidation {
+    /// Keep the item, collapsed at the edit according to its biases.
+    #[default]
+    Collapse,
+    /// Delete the item (and its payload).
+    Delete,
+    /// Move the item to an empty range at the start of the edit.
+    PinLeft,
+    /// Move the item to an empty range at the end of the edit.
+    PinRight,
+}
+
 // Small to make it easier to debug tests.
 #[cfg(debug_assertions)]
 const ARITY: usize = 4;
@@ -57,22 +124,39 @@ const ARITY: usize = 7;
 //
 // Plenty of optimizations available. The implementation is fairly naive.
 //  - avoid recreating bitmaps and arrays from scratch all the time
-pub struct MarkTree<Id: MarkTreeId, const N: usize> {
+//
+/// Each item also has a payload of type `T`, e.g. the highlight of a mark, which is returned
+/// together with its range by [`MarkTree::range_with_payload`].
+pub struct MarkTree<Id: MarkTreeId, const N: usize, T = ()> {
     tree: Tree<ARITY, Leaf<N>>,
+    /// The payloads are kept out of the tree as they're unaffected by shifts.
+    /// Invariant: there is a payload for every id in the tree.
+    payloads: FxHashMap<u64, T>,
+    /// The builders of the items that don't use the default `Invalidation::Collapse` to reinsert
+    /// them when pinned.
+    invalidations: FxHashMap<u64, MarkBuilder>,
+    /// The items that were invalidated by the last shift.
+    invalidated: Vec<Id>,
     _id: PhantomData<Id>,
 }
 
-impl<Id: MarkTreeId, const N: usize> fmt::Debug for MarkTree<Id, N> {
+impl<Id: MarkTreeId, const N: usize, T> fmt::Debug for MarkTree<Id, N, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.tree.fmt(f)
     }
 }
 
-impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
+impl<const N: usize, Id: MarkTreeId, T> MarkTree<Id, N, T> {
     /// Creates a new `MarkTree` appropriate for a text of length `n`.
     pub fn new(n: usize) -> Self {
         // assert!(n > 0, "MarkTree must have a non-zero length");
-        let mut this = Self { tree: Tree::default(), _id: PhantomData };
+        let mut this = Self {
+            tree: Tree::default(),
+            payloads: Default::default(),
+            invalidations: Default::default(),
+            invalidated: vec![],
+            _id: PhantomData,
+        };
         this.replace(0..0, Replacement::Gap(n));
         this
     }
@@ -95,18 +179,29 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
         Some(start..end)
     }
 
-    fn get_left(&self, id: u32) -> Option<usize> {
+    /// The payload of the item.
+    #[inline]
+    pub fn payload(&self, id: impl Into<Id>) -> Option<&T> {
+        self.payloads.get(&id.into().into())
+    }
+
+    #[inline]
+    pub fn payload_mut(&mut self, id: impl Into<Id>) -> Option<&mut T> {
+        self.payloads.get_mut(&id.into().into())
+    }
+
+    fn get_left(&self, id: u64) -> Option<usize> {
         let (offset, leaf) = self.find_left_leaf(id)?;
         leaf.get_left(id).map(|byte| offset + byte)
     }
 
-    fn get_right(&self, id: u32) -> Option<usize> {
+    fn get_right(&self, id: u64) -> Option<usize> {
         let (offset, leaf) = self.find_right_leaf(id)?;
         leaf.get_right(id).map(|byte| offset - byte)
     }
 
     /// Return the `(offset, leaf)` pair of the leftmost leaf that contains the given `id`.
-    fn find_left_leaf(&self, id: u32) -> Option<(usize, &Leaf<N>)> {
+    fn find_left_leaf(&self, id: u64) -> Option<(usize, &Leaf<N>)> {
         // Need to do a manual traversal to make use of the bitmaps.
         let mut node = self.tree.root().as_ref();
         if !node.summary().ids.contains(id) {
@@ -139,7 +234,7 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
     }
 
     /// Return the `(end_offset, leaf)` pair of the rightmost leaf that contains the given `id`.
-    fn find_right_leaf(&self, id: u32) -> Option<(usize, &Leaf<N>)> {
+    fn find_right_leaf(&self, id: u64) -> Option<(usize, &Leaf<N>)> {
         let mut node = self.tree.root().as_ref();
         if !node.summary().ids.contains(id) {
             return None;
@@ -171,11 +266,180 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
         }
     }
 
+    /// Replace the bytes in `range` with `by` bytes.
+    /// The items whose range is entirely deleted are handled according to their [`Invalidation`]
+    /// and those that don't collapse are available in [`MarkTree::invalidated`] until the next
+    /// shift.
     pub fn shift(&mut self, range: impl RangeBounds<usize>, by: usize) {
-        self.replace(range, Replacement::Gap(by));
+        let (start, end) = range_bounds_to_start_end(range, 0, self.len());
+        let invalidated = self.covered(start..end);
+
+        let mut pinned = vec![];
+        for &id in &invalidated {
+            let builder = self.invalidations[&id.into()];
+            match builder.invalidation {
+                Invalidation::Collapse => unreachable!("collapsing items are not stored"),
+                Invalidation::Delete => {
+                    self.remove(id);
+                }
+                Invalidation::PinLeft => pinned.push((id, MarkBuilder { at: start, ..builder })),
+                Invalidation::PinRight => {
+                    pinned.push((id, MarkBuilder { at: start + by, ..builder }))
+                }
+            }
+        }
+
+        self.replace(start..end, Replacement::Gap(by));
+        for (id, builder) in pinned {
+            self.insert_keys(id.into(), builder.width(0));
+        }
+
+        self.invalidated = invalidated;
+        self.tree.assert_invariants();
+    }
+
+    /// Applies many shifts at once, the `(range, by)` edits are as in [`MarkTree::shift`] but are all
+    /// in terms of the positions before any edit, so they must be disjoint (like `Deltas`).
+    /// This is equivalent to shifting by each edit from last to first.
+    ///
+    /// The edits are applied to the leaves in a single descent of the tree. Only the edits that
+    /// touch the boundary of a leaf, or that would leave a leaf too full or too empty, go through
+    /// [`MarkTree::shift`]'s rebalancing replacement afterwards.
+    pub fn shift_many(&mut self, edits: impl IntoIterator<Item = (Range<usize>, usize)>) {
+        let mut edits = edits.into_iter().collect::<SmallVec<_, 4>>();
+        edits.sort_by_key(|(range, _)| range.start);
+        for pair in edits.windows(2) {
+            let ((a, _), (b, _)) = (&pair[0], &pair[1]);
+            assert!(a.end < b.start, "edits must be disjoint: {a:?} and {b:?}");
+        }
+
+        let mut invalidated = vec![];
+        let mut pinned = vec![];
+        // The change in length caused by the preceding edits.
+        let mut delta = 0isize;
+        for (range, by) in &edits {
+            let covered = self.covered(range.clone());
+            for &id in &covered {
+                let builder = self.invalidations[&id.into()];
+                let start = range.start.checked_add_signed(delta).unwrap();
+                match builder.invalidation {
+                    Invalidation::Collapse => unreachable!("collapsing items are not stored"),
+                    Invalidation::Delete => {
+                        self.remove(id);
+                    }
+                    Invalidation::PinLeft => {
+                        pinned.push((id, MarkBuilder { at: start, ..builder }))
+                    }
+                    Invalidation::PinRight => {
+                        pinned.push((id, MarkBuilder { at: start + by, ..builder }))
+                    }
+                }
+            }
+
+            invalidated.extend(covered);
+            delta += *by as isize - range.len() as isize;
+        }
+
+        let mut deferred = vec![];
+        if !edits.is_empty() {
+            shift_leaves(self.tree.root_mut(), 0, &edits, &mut deferred);
+        }
+
+        // The deferred edits are applied from last to first, so each one is only offset by the
+        // edits before it that were already applied to the leaves.
+        let mut replacements = vec![];
+        let mut deferred = deferred.into_iter().peekable();
+        let mut delta = 0isize;
+        for (range, by) in &edits {
+            if deferred.next_if(|(r, _)| r == range).is_some() {
+                let start = range.start.checked_add_signed(delta).unwrap();
+                replacements.push((start..start + range.len(), *by));
+            } else {
+                delta += *by as isize - range.len() as isize;
+            }
+        }
+
+        for (range, by) in replacements.into_iter().rev() {
+            self.replace(range, Replacement::Gap(by));
+        }
+
+        for (id, builder) in pinned {
+            self.insert_keys(id.into(), builder.width(0));
+        }
+
+        self.invalidated = invalidated;
+        self.tree.assert_invariants();
+    }
+
+    /// Rebuild the tree to remove the fragmentation left behind by edits.
+    /// The positions of the items are unchanged.
+    pub fn compact(&mut self) {
+        let keys = self.keys().fold(BTreeMap::<usize, SetU64>::new(), |mut keys, (offset, key)| {
+            keys.entry(offset).or_default().insert(key.into_raw());
+            keys
+        });
+
+        let len = self.len();
+        self.tree =
+            if keys.is_empty() { Self::new(len).tree } else { builder::build_tree(len, keys) };
         self.tree.assert_invariants();
     }
 
+    /// Returns an iterator over all the keys and their positions in order.
+    fn keys(&self) -> impl Iterator<Item = (usize, Key)> + '_ {
+        // The leaves are all at the same depth, so they are visited in order.
+        let mut q = VecDeque::from([(0, self.tree.root().as_ref())]);
+
+        iter::from_coroutine(
+            #[coroutine]
+            move || {
+                while let Some((mut offset, node)) = q.pop_front() {
+                    match node {
+                        Node::Internal(inode) => {
+                            for child in inode.children().iter() {
+                                q.push_back((offset, child.as_ref()));
+                                offset += child.summary().bytes;
+                            }
+                        }
+                        Node::Leaf(leaf) => {
+                            for extent in leaf.as_slice().extents {
+                                for key in extent.keys() {
+                                    yield (offset, key);
+                                }
+                                offset += extent.len();
+                            }
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// The items with an [`Invalidation`] policy other than `Collapse` whose range was entirely
+    /// deleted by the last shift, including the deleted items.
+    #[inline]
+    pub fn invalidated(&self) -> &[Id] {
+        &self.invalidated
+    }
+
+    /// The items with an invalidation policy that would be invalidated by deleting `range`.
+    fn covered(&self, range: Range<usize>) -> Vec<Id> {
+        // Collapsing items aren't tracked, so edits only pay for the query if there are any others.
+        if range.is_empty() || self.invalidations.is_empty() {
+            return vec![];
+        }
+
+        self.range(range.start..=range.end)
+            .filter(|(item, id)| {
+                range.start <= item.start
+                    && item.end <= range.end
+                    && (!item.is_empty() || (range.start < item.start && item.end < range.end))
+                    && self.invalidations.contains_key(&(*id).into())
+            })
+            .map(|(_, id)| id)
+            .collect()
+    }
+
     /// Returns an iterator over the items whose start point is in the given range.
     pub fn range(
         &self,
@@ -237,13 +501,172 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
         )
     }
 
-    /// Inserts an item based on its byte position.
-    /// This does not affect `self.len()`.
-    pub fn insert(&mut self, at: usize, id: Id) -> Inserter<'_, Id, N> {
-        Inserter { tree: self, id, builder: MarkBuilder::new(at) }
+    /// Returns an iterator over the items whose start point is in the given range in reverse order
+    /// of [`MarkTree::range`]. This is lazy, e.g. `range_rev(..byte).next()` is the last item
+    /// before `byte`.
+    pub fn range_rev(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, Id)> + '_ {
+        let (start, end) = range_bounds_to_start_end(range, 0, self.len());
+        let mut stack = vec![(0, self.tree.root().as_ref())];
+
+        iter::from_coroutine(
+            #[coroutine]
+            move || {
+                while let Some((mut offset, node)) = stack.pop() {
+                    match node {
+                        Node::Internal(inode) => {
+                            // Push the children in order so the last child is explored first.
+                            for child in inode.children().iter() {
+                                let summary = child.summary();
+                                if (offset..offset + summary.bytes).intersects(&(start..end)) {
+                                    stack.push((offset, child.as_ref()));
+                                }
+
+                                offset += summary.bytes;
+                            }
+                        }
+                        Node::Leaf(leaf) => {
+                            offset += leaf.summary().bytes;
+                            for extent in leaf.as_slice().extents.iter().rev() {
+                                offset -= extent.len();
+                                if offset >= end {
+                                    continue;
+                                }
+
+                                if offset < start {
+                                    break;
+                                }
+
+                                let keys = extent.keys().collect::<SmallVec<Key, 4>>();
+                                for key in keys.into_iter().rev() {
+                                    let flags = key.flags();
+                                    if flags.contains(Flags::END) {
+                                        continue;
+                                    }
+
+                                    if flags.contains(Flags::RANGE) {
+                                        let end = self
+                                            .get_right(key.id())
+                                            .expect("we should at least find the current key");
+                                        yield (offset..end, key.id().into());
+                                    } else {
+                                        yield (offset..offset, key.id().into());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+        )
     }
 
-    pub fn drain(&mut self, range: impl RangeBounds<usize>) -> Drain<'_, Id, N>
+    /// Returns a cursor positioned before the items that start at `byte`.
+    pub fn cursor(&self, byte: usize) -> Cursor<'_, Id, N, T> {
+        Cursor::new(self, byte)
+    }
+
+    /// Returns an iterator over the items that intersect the given range, i.e. the items that
+    /// start in the range as with [`MarkTree::range`], preceded by the range items that start
+    /// before the range and end after its start.
+    /// Finding the latter only looks at the ranges that cross the boundaries of the nodes on the
+    /// way down to the start of the range, not at all the items before it.
+    pub fn overlapping(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, Id)> + '_ {
+        let (start, end) = range_bounds_to_start_end(range, 0, self.len());
+        // A range item contains `start` if only one of its keys is before it.
+        let mut straddling = self
+            .open_before(start)
+            .iter()
+            .filter_map(|id| {
+                let range = self.get(Id::from(id)).expect("id is in the tree");
+                (range.start < start && start < range.end).then_some((range, id.into()))
+            })
+            .collect::<Vec<_>>();
+        straddling.sort_by_key(|(range, _)| range.start);
+        straddling.into_iter().chain(self.range(start..end))
+    }
+
+    /// Returns an iterator over the items that contain the byte, including empty items at the
+    /// byte.
+    pub fn at(&self, byte: usize) -> impl Iterator<Item = (Range<usize>, Id)> + '_ {
+        self.overlapping(byte..=byte)
+    }
+
+    /// The range items with exactly one key before `byte`.
+    /// The nodes entirely before `byte` contribute the ranges that cross their boundary, ranges
+    /// with both keys in them cancel out.
+    fn open_before(&self, byte: usize) -> Treemap {
+        let mut open = Treemap::new();
+        let mut node = self.tree.root().as_ref();
+        let mut offset = 0;
+        loop {
+            match node {
+                Node::Internal(inode) => {
+                    let mut next = None;
+                    for child in inode.children() {
+                        let summary = child.summary();
+                        if offset >= byte {
+                            break;
+                        }
+
+                        // Keys can be at the very end of a node, so only use the summary if the
+                        // entire node is before the byte.
+                        if offset + summary.bytes < byte {
+                            open.xor_inplace(&summary.open);
+                            offset += summary.bytes;
+                        } else {
+                            next = Some(child);
+                            break;
+                        }
+                    }
+
+                    match next {
+                        Some(child) => node = child.as_ref(),
+                        None => return open,
+                    }
+                }
+                Node::Leaf(leaf) => {
+                    for extent in leaf.as_slice().extents {
+                        if offset >= byte {
+                            break;
+                        }
+
+                        extent.keys().for_each(|key| toggle_open(&mut open, key));
+                        offset += extent.len();
+                    }
+                    return open;
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the items whose start point is in the given range along with their
+    /// payloads.
+    pub fn range_with_payload(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, Id, &T)> + '_ {
+        self.range(range).map(|(range, id)| {
+            let payload = self.payloads.get(&id.into()).expect("every item has a payload");
+            (range, id, payload)
+        })
+    }
+
+    /// Inserts an item based on its byte position with a default payload, see
+    /// [`Inserter::payload`]. This does not affect `self.len()`.
+    pub fn insert(&mut self, at: usize, id: Id) -> Inserter<'_, Id, N, T>
+    where
+        T: Default,
+    {
+        Inserter { tree: self, id, builder: MarkBuilder::new(at), payload: Some(T::default()) }
+    }
+
+    pub fn drain(&mut self, range: impl RangeBounds<usize>) -> Drain<'_, Id, N, T>
     where
         Self: Sized,
     {
@@ -252,10 +675,23 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
     }
 
     pub fn delete(&mut self, id: impl Into<Id>) -> Option<Range<usize>> {
+        self.remove(id).map(|(range, _)| range)
+    }
+
+    /// Delete the item, returning its range and payload.
+    pub fn remove(&mut self, id: impl Into<Id>) -> Option<(Range<usize>, T)> {
+        let id = id.into().into();
+        let range = self.delete_keys(id)?;
+        self.invalidations.remove(&id);
+        let payload = self.payloads.remove(&id).expect("every item has a payload");
+        Some((range, payload))
+    }
+
+    fn delete_keys(&mut self, id: u64) -> Option<Range<usize>> {
         fn del<const N: usize>(
             node: &mut Arc<Node<ARITY, Leaf<N>>>,
             mut offset: usize,
-            id: u32,
+            id: u64,
         ) -> usize {
             match Arc::make_mut(node) {
                 Node::Internal(inode) => {
@@ -277,7 +713,6 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
             }
         }
 
-        let id = id.into().into();
         let root = self.tree.root_mut();
         if !root.summary().ids.contains(id) {
             return None;
@@ -294,6 +729,38 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
         Some(range)
     }
 
+    /// Insert the keys of the item, replacing the existing keys if any.
+    fn insert_keys(&mut self, id: u64, builder: MarkBuilder) {
+        let at = builder.at;
+        let n = self.len();
+
+        if self.tree.summary().ids.contains(id) {
+            self.delete_keys(id).unwrap();
+        }
+
+        assert!(
+            at + builder.width <= self.len(),
+            "range {at}..{} out of bounds of marktree of length {}",
+            builder.at + builder.width,
+            self.len(),
+        );
+
+        self.replace(at..at, Replacement::Key(Key::new(id, builder.start_flags)));
+        assert_eq!(self.len(), n, "first insertion should not change the length of the tree");
+
+        if builder.start_flags.contains(Flags::RANGE) {
+            assert!(builder.end_flags.contains(Flags::RANGE | Flags::END));
+            let at = at + builder.width;
+            self.replace(at..at, Replacement::Key(Key::new(id, builder.end_flags)));
+            assert_eq!(self.len(), n, "second insertion should not change the length of the tree");
+        }
+
+        match builder.invalidation {
+            Invalidation::Collapse => self.invalidations.remove(&id),
+            _ => self.invalidations.insert(id, builder),
+        };
+    }
+
     fn replace(&mut self, range: impl RangeBounds<usize>, replace_with: Replacement) {
         let initial_len = self.len();
         let k = replace_with.width();
@@ -333,15 +800,72 @@ impl<const N: usize, Id: MarkTreeId> MarkTree<Id, N> {
     }
 }
 
+/// Apply the sorted and disjoint edits, which are relative to the start of the tree, to the leaves
+/// of `node` at `offset` that contain them. The edits that can't be applied within a single leaf
+/// without overfilling or underfilling it are added to `deferred` in order.
+fn shift_leaves<const N: usize>(
+    node: &mut Arc<Node<ARITY, Leaf<N>>>,
+    mut offset: usize,
+    mut edits: &[(Range<usize>, usize)],
+    deferred: &mut Vec<(Range<usize>, usize)>,
+) {
+    match Arc::make_mut(node) {
+        Node::Internal(inode) => {
+            for i in 0..inode.children().len() {
+                let bytes = inode.child(i).summary().bytes;
+                // Keys can be at either end of a node, so edits touching a boundary may move keys
+                // of the neighbouring node too.
+                let n = edits.partition_point(|(range, _)| range.start <= offset);
+                deferred.extend_from_slice(&edits[..n]);
+                edits = &edits[n..];
+
+                let n = edits.partition_point(|(range, _)| range.end < offset + bytes);
+                if n > 0 {
+                    inode.with_child_mut(i, |child| {
+                        shift_leaves(child, offset, &edits[..n], deferred)
+                    });
+                }
+                edits = &edits[n..];
+                offset += bytes;
+            }
+
+            deferred.extend_from_slice(edits);
+        }
+        Node::Leaf(leaf) => {
+            let (value, summary) = (leaf.value.clone(), leaf.summary.clone());
+            // From last to first, so the positions of the remaining edits are unaffected.
+            let overflowed = edits.iter().rev().any(|(range, by)| {
+                let range = range.start - offset..range.end - offset;
+                leaf.value.shift(&mut leaf.summary, range, *by).is_some()
+            });
+
+            let n = leaf.value.extents.len();
+            if overflowed || (n < N / 2 && n < value.extents.len()) {
+                leaf.value = value;
+                leaf.summary = summary;
+                deferred.extend_from_slice(edits);
+            }
+        }
+    }
+}
+
 /// A builder for inserting an item into a `MarkTree`, inserts on drop.
 #[derive(Debug)]
-pub struct Inserter<'a, Id: MarkTreeId, const N: usize> {
-    tree: &'a mut MarkTree<Id, N>,
+pub struct Inserter<'a, Id: MarkTreeId, const N: usize, T = ()> {
+    tree: &'a mut MarkTree<Id, N, T>,
     id: Id,
     builder: MarkBuilder,
+    /// Only `None` once inserted.
+    payload: Option<T>,
 }
 
-impl<Id: MarkTreeId, const N: usize> Inserter<'_, Id, N> {
+impl<Id: MarkTreeId, const N: usize, T> Inserter<'_, Id, N, T> {
+    /// Set the payload of the item, replacing the payload of an existing item with the same id.
+    pub fn payload(mut self, payload: T) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
     pub fn start_bias(mut self, bias: Bias) -> Self {
         self.builder = self.builder.start_bias(bias);
         self
@@ -356,40 +880,202 @@ impl<Id: MarkTreeId, const N: usize> Inserter<'_, Id, N> {
         self.builder = self.builder.width(width);
         self
     }
+
+    pub fn invalidation(mut self, invalidation: Invalidation) -> Self {
+        self.builder = self.builder.invalidation(invalidation);
+        self
+    }
 }
 
-impl<Id: MarkTreeId, const N: usize> Drop for Inserter<'_, Id, N> {
+impl<Id: MarkTreeId, const N: usize, T> Drop for Inserter<'_, Id, N, T> {
     fn drop(&mut self) {
         let id = self.id.into();
-        let at = self.builder.at;
-        let n = self.tree.len();
+        self.tree.insert_keys(id, self.builder);
+        let payload = self.payload.take().expect("inserted only once");
+        self.tree.payloads.insert(id, payload);
+        self.tree.assert_invariants();
+    }
+}
 
-        if self.tree.tree.summary().ids.contains(id) {
-            self.tree.delete(id).unwrap();
+/// A cursor over the items of a `MarkTree` in the order of [`MarkTree::range`] that can move in
+/// either direction. The cursor keeps its path to the current leaf, so each step only moves to
+/// the neighbouring extent or leaf.
+pub struct Cursor<'a, Id: MarkTreeId, const N: usize, T = ()> {
+    tree: &'a MarkTree<Id, N, T>,
+    /// The path to the current leaf, each frame is the children of a node along with the index and
+    /// offset of the child on the path.
+    stack: Vec<(&'a [Arc<Node<ARITY, Leaf<N>>>], usize, usize)>,
+    /// The extents of the current leaf, the cursor is at the `extent`th one which is at `offset`.
+    /// `extent` is one past the last extent at the end of the leaf.
+    extents: &'a [Extent],
+    extent: usize,
+    offset: usize,
+    /// The start keys of the current extent, the cursor is before the `index`th one.
+    keys: SmallVec<Key, 4>,
+    index: usize,
+}
+
+impl<'a, Id: MarkTreeId, const N: usize, T> Cursor<'a, Id, N, T> {
+    fn new(tree: &'a MarkTree<Id, N, T>, byte: usize) -> Self {
+        let mut cursor = Self {
+            tree,
+            stack: vec![],
+            extents: &[],
+            extent: 0,
+            offset: 0,
+            keys: SmallVec::new(),
+            index: 0,
+        };
+        cursor.seek(byte);
+        cursor
+    }
+
+    /// Move the cursor before the items that start at `byte`.
+    pub fn seek(&mut self, byte: usize) {
+        self.stack.clear();
+        let mut node = self.tree.tree.root();
+        let mut offset = 0;
+        // Descend into the first child that can contain keys at `byte`, keys can be at the very
+        // end of a node.
+        while let Node::Internal(inode) = node.as_ref() {
+            let children = inode.children();
+            let mut idx = 0;
+            while idx + 1 < children.len() && offset + children[idx].summary().bytes < byte {
+                offset += children[idx].summary().bytes;
+                idx += 1;
+            }
+            self.stack.push((children, idx, offset));
+            node = &children[idx];
         }
 
-        assert!(
-            at + self.builder.width <= self.tree.len(),
-            "range {at}..{} out of bounds of marktree of length {}",
-            self.builder.at + self.builder.width,
-            self.tree.len(),
-        );
+        self.enter_leaf(node, offset);
+        while self.extent < self.extents.len() && self.offset < byte {
+            self.offset += self.extents[self.extent].len();
+            self.extent += 1;
+        }
+        self.load_keys();
+        self.index = 0;
+    }
+
+    /// Returns the item after the cursor and moves the cursor past it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(Range<usize>, Id)> {
+        loop {
+            if let Some(&key) = self.keys.get(self.index) {
+                self.index += 1;
+                return Some(self.item(key));
+            }
 
-        self.tree.replace(at..at, Replacement::Key(Key::new(id, self.builder.start_flags)));
-        assert_eq!(self.tree.len(), n, "first insertion should not change the length of the tree");
-
-        if self.builder.start_flags.contains(Flags::RANGE) {
-            assert!(self.builder.end_flags.contains(Flags::RANGE | Flags::END));
-            let at = at + self.builder.width;
-            self.tree.replace(at..at, Replacement::Key(Key::new(id, self.builder.end_flags)));
-            assert_eq!(
-                self.tree.len(),
-                n,
-                "second insertion should not change the length of the tree"
-            );
+            if self.extent + 1 < self.extents.len() {
+                self.offset += self.extents[self.extent].len();
+                self.extent += 1;
+            } else if !self.next_leaf() {
+                return None;
+            }
+            self.load_keys();
+            self.index = 0;
         }
+    }
 
-        self.tree.assert_invariants();
+    /// Returns the item before the cursor and moves the cursor before it.
+    pub fn prev(&mut self) -> Option<(Range<usize>, Id)> {
+        loop {
+            if self.index > 0 {
+                self.index -= 1;
+                return Some(self.item(self.keys[self.index]));
+            }
+
+            if self.extent > 0 {
+                self.extent -= 1;
+                self.offset -= self.extents[self.extent].len();
+            } else if !self.prev_leaf() {
+                return None;
+            }
+            self.load_keys();
+            self.index = self.keys.len();
+        }
+    }
+
+    fn item(&self, key: Key) -> (Range<usize>, Id) {
+        if key.flags().contains(Flags::RANGE) {
+            let end =
+                self.tree.get_right(key.id()).expect("we should at least find the current key");
+            (self.offset..end, key.id().into())
+        } else {
+            (self.offset..self.offset, key.id().into())
+        }
+    }
+
+    fn load_keys(&mut self) {
+        self.keys.clear();
+        if let Some(extent) = self.extents.get(self.extent) {
+            self.keys.extend(extent.keys().filter(|key| !key.flags().contains(Flags::END)));
+        }
+    }
+
+    /// Position the cursor at the first extent of the leaf `node` which is at `offset`.
+    fn enter_leaf(&mut self, node: &'a Node<ARITY, Leaf<N>>, offset: usize) {
+        let Node::Leaf(leaf) = node else { unreachable!("expected a leaf") };
+        self.extents = leaf.as_slice().extents;
+        self.extent = 0;
+        self.offset = offset;
+    }
+
+    /// Move to the first extent of the next leaf, returns `false` if this is the last leaf.
+    fn next_leaf(&mut self) -> bool {
+        let Some(depth) =
+            self.stack.iter().rposition(|(children, idx, _)| idx + 1 < children.len())
+        else {
+            return false;
+        };
+
+        self.stack.truncate(depth + 1);
+        let frame = self.stack.last_mut().unwrap();
+        frame.2 += frame.0[frame.1].summary().bytes;
+        frame.1 += 1;
+        let (children, idx, mut offset) = *frame;
+        let mut node = &children[idx];
+        while let Node::Internal(inode) = node.as_ref() {
+            self.stack.push((inode.children(), 0, offset));
+            node = &inode.children()[0];
+        }
+        self.enter_leaf(node, offset);
+        true
+    }
+
+    /// Move past the last extent of the previous leaf, returns `false` if this is the first leaf.
+    fn prev_leaf(&mut self) -> bool {
+        let Some(depth) = self.stack.iter().rposition(|&(_, idx, _)| idx > 0) else {
+            return false;
+        };
+
+        self.stack.truncate(depth + 1);
+        let frame = self.stack.last_mut().unwrap();
+        frame.1 -= 1;
+        frame.2 -= frame.0[frame.1].summary().bytes;
+        let (children, idx, mut offset) = *frame;
+        let mut node = &children[idx];
+        while let Node::Internal(inode) = node.as_ref() {
+            let children = inode.children();
+            let (last, rest) = children.split_last().expect("internal nodes have children");
+            offset += rest.iter().map(|child| child.summary().bytes).sum::<usize>();
+            self.stack.push((children, rest.len(), offset));
+            node = last;
+        }
+        self.enter_leaf(node, offset);
+        self.offset += node.summary().bytes;
+        self.extent = self.extents.len();
+        true
+    }
+}
+
+impl<Id: MarkTreeId, const N: usize, T> fmt::Debug for Cursor<'_, Id, N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("offset", &self.offset)
+            .field("keys", &self.keys)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
     }
 }
 
@@ -410,12 +1096,12 @@ impl Replacement {
     }
 }
 
-pub struct Drain<'a, Id: MarkTreeId, const N: usize> {
-    tree: &'a mut MarkTree<Id, N>,
+pub struct Drain<'a, Id: MarkTreeId, const N: usize, T = ()> {
+    tree: &'a mut MarkTree<Id, N, T>,
     ids: std::vec::IntoIter<Id>,
 }
 
-impl<Id: MarkTreeId, const N: usize> Drop for Drain<'_, Id, N> {
+impl<Id: MarkTreeId, const N: usize, T> Drop for Drain<'_, Id, N, T> {
     fn drop(&mut self) {
         for id in self.ids.by_ref() {
             self.tree.delete(id).unwrap();
@@ -423,7 +1109,7 @@ impl<Id: MarkTreeId, const N: usize> Drop for Drain<'_, Id, N> {
     }
 }
 
-impl<Id: MarkTreeId, const N: usize> Iterator for Drain<'_, Id, N> {
+impl<Id: MarkTreeId, const N: usize, T> Iterator for Drain<'_, Id, N, T> {
     type Item = (Range<usize>, Id);
 
     #[inline]
@@ -461,8 +1147,8 @@ impl Extent {
         Self { length, keys: keys.into_iter().map(Key::into_raw).collect() }
     }
 
-    fn ids(&self) -> impl Iterator<Item = u32> + '_ {
-        self.keys.iter().map(|key| key as u32)
+    fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.keys.iter().map(|key| Key::from_raw(key).id())
     }
 
     fn keys(&self) -> impl Iterator<Item = Key> + '_ {
@@ -488,22 +1174,22 @@ impl<const N: usize> fmt::Debug for Leaf<N> {
 
 impl<const N: usize> Leaf<N> {
     #[inline]
-    fn get_left(&self, id: u32) -> Option<usize> {
+    fn get_left(&self, id: u64) -> Option<usize> {
         self.as_slice().get_left(id)
     }
 
     #[inline]
-    fn get_right(&self, id: u32) -> Option<usize> {
+    fn get_right(&self, id: u64) -> Option<usize> {
         self.as_slice().get_right(id)
     }
 
-    fn delete(&mut self, summary: &mut Summary, id: u32) -> Option<usize> {
+    fn delete(&mut self, summary: &mut Summary, id: u64) -> Option<usize> {
         let mut offset = 0;
 
         for extent in &mut self.extents {
-            if extent.keys.remove(id as u64) {
+            if extent.keys.remove(id) {
                 // Fast path if the flags are empty.
-                assert!(summary.ids.remove(id).is_some());
+                summary.remove_key(Key::from_raw(id));
                 return Some(offset);
             } else {
                 // Otherwise, we have to linearly scan the map to find the id since the keys contain the flags too.
@@ -513,7 +1199,7 @@ impl<const N: usize> Leaf<N> {
                     if key.id() == id {
                         drop(iter);
                         assert!(extent.keys.remove(key.into_raw()));
-                        assert!(summary.ids.remove(id).is_some());
+                        summary.remove_key(key);
                         return Some(offset);
                     }
                 }
@@ -652,7 +1338,7 @@ impl<const N: usize> Leaf<N> {
                 // There is an existing extent that exactly matches the insertion point.
                 // Just add the new key to the extent and return.
                 self.extents[i].keys.insert(key.into_raw());
-                summary.ids.insert(key.id());
+                summary.insert_key(key);
                 break;
             }
 
@@ -663,9 +1349,7 @@ impl<const N: usize> Leaf<N> {
                 self.extents[i].length -= rem;
 
                 match self.extents.try_insert(i + 1, Extent::new(rem, [key])) {
-                    Ok(()) => {
-                        summary.ids.insert(key.id());
-                    }
+                    Ok(()) => summary.insert_key(key),
                     Err(err) => {
                         if i + 1 == N {
                             summary.bytes -= rem;
@@ -683,7 +1367,7 @@ impl<const N: usize> Leaf<N> {
 
                         let extent = err.element();
                         for key in extent.keys() {
-                            summary.ids.insert(key.id());
+                            summary.insert_key(key);
                         }
                         self.extents.insert(i + 1, extent);
 
@@ -773,7 +1457,7 @@ mod key {
 
     bitflags::bitflags! {
         #[derive(Clone, Copy, PartialEq, Eq)]
-        pub struct Flags: u32 {
+        pub struct Flags: u8 {
             const BIAS_LEFT = 1 << 0;
             // If the key is part of a range pair.
             const RANGE = 1 << 1;
@@ -788,8 +1472,7 @@ mod key {
         }
     }
 
-    /// Key encodes the 32-bit id and 32-bit flags.
-    // We don't nearly need 32-bits of flag space, but we're keeping the id small to allow for optimizations.
+    /// Key encodes the 56-bit id (48-bit id and 8-bit tag of a `TaggedId`) and 8-bit flags.
     #[derive(Clone, Copy)]
     pub(super) struct Key(u64);
 
@@ -800,21 +1483,24 @@ mod key {
     }
 
     impl Key {
-        const FLAG_BITS: usize = 32;
+        const FLAG_BITS: usize = 8;
         const ID_BITS: usize = mem::size_of::<u64>() * 8 - Self::FLAG_BITS;
+        const ID_MASK: u64 = (1 << Self::ID_BITS) - 1;
 
-        pub fn new(id: u32, flag: Flags) -> Self {
-            Self(id as u64 | ((flag.bits() as u64) << 32))
+        #[track_caller]
+        pub fn new(id: u64, flag: Flags) -> Self {
+            assert!(id <= Self::ID_MASK, "id {id} is too large, ids must fit in 56 bits");
+            Self(id | ((flag.bits() as u64) << Self::ID_BITS))
         }
 
         #[inline]
-        pub fn id(self) -> u32 {
-            self.0 as u32
+        pub fn id(self) -> u64 {
+            self.0 & Self::ID_MASK
         }
 
         #[inline]
         pub fn flags(self) -> Flags {
-            Flags::from_bits((self.0 >> Self::ID_BITS) as u32).unwrap()
+            Flags::from_bits((self.0 >> Self::ID_BITS) as u8).unwrap()
         }
 
         #[inline]
@@ -866,14 +1552,28 @@ impl<const N: usize> ReplaceableLeaf<ByteMetric> for Leaf<N> {
 impl<const N: usize> BalancedLeaf for Leaf<N> {
     #[inline]
     fn is_underfilled(&self, _summary: &Self::Summary) -> bool {
-        false
+        self.extents.len() < N / 2
     }
 
-    #[inline]
     fn balance_leaves(
-        _left: (&mut Self, &mut Self::Summary),
-        _right: (&mut Self, &mut Self::Summary),
+        (left, left_summary): (&mut Self, &mut Self::Summary),
+        (right, right_summary): (&mut Self, &mut Self::Summary),
     ) {
+        let (l, r) = (left.extents.len(), right.extents.len());
+        if l + r <= N {
+            // Merge the right leaf into the left one, leaving it empty.
+            left.extents.extend(right.extents.drain(..));
+        } else if l < r {
+            // Otherwise, even out the extents by moving some over to the smaller leaf.
+            left.extents.extend(right.extents.drain(..(r - l) / 2));
+        } else {
+            let moved = left.extents.drain(l - (l - r) / 2..).collect::<ArrayVec<_, N>>();
+            let rest = mem::take(&mut right.extents);
+            right.extents = moved.into_iter().chain(rest).collect();
+        }
+
+        *left_summary = left.summarize();
+        *right_summary = right.summarize();
     }
 }
 
@@ -923,10 +1623,10 @@ impl Default for LeafSlice<'_> {
 impl LeafSlice<'_> {
     /// Return the item with the given `id` if it exists.
     /// The item `byte` is relative to the start of the leaf node.
-    fn get_left(&self, id: u32) -> Option<usize> {
+    fn get_left(&self, id: u64) -> Option<usize> {
         let mut offset = 0;
         for extent in self.extents {
-            if extent.keys.contains(id as u64) {
+            if extent.keys.contains(id) {
                 // Fast path if the flags are empty.
                 return Some(offset);
             } else {
@@ -947,11 +1647,11 @@ impl LeafSlice<'_> {
 
     /// Return the item with the given `id` if it exists.
     /// The item `byte` is the distance to the end of the leaf node.
-    fn get_right(&self, id: u32) -> Option<usize> {
+    fn get_right(&self, id: u64) -> Option<usize> {
         let mut offset = 0;
 
         for extent in self.extents.iter().rev() {
-            if extent.keys.contains(id as u64) {
+            if extent.keys.contains(id) {
                 // Fast path if the flags are empty.
                 return Some(offset + extent.len());
             } else {
@@ -976,9 +1676,12 @@ impl Summarize for LeafSlice<'_> {
 
     #[inline]
     fn summarize(&self) -> Self::Summary {
+        let mut open = Treemap::new();
+        self.extents.iter().flat_map(Extent::keys).for_each(|key| toggle_open(&mut open, key));
         Summary {
             bytes: self.extents.iter().map(|extent| extent.len()).sum(),
             ids: FromIterator::from_iter(self.extents.iter().flat_map(|extent| extent.ids())),
+            open,
         }
     }
 }
@@ -1019,7 +1722,7 @@ impl AddAssign<&Extent> for Summary {
         self.bytes += rhs.len();
         for key in rhs.keys() {
             // TODO probably is a faster way to do this
-            self.ids.insert(key.id());
+            self.insert_key(key);
         }
     }
 }
@@ -1030,7 +1733,7 @@ impl SubAssign<&Extent> for Summary {
         self.bytes -= rhs.len();
         for key in rhs.keys() {
             // TODO probably is a faster way to do this
-            assert!(self.ids.remove(key.id()).is_some());
+            self.remove_key(key);
         }
     }
 }
@@ -1047,6 +1750,8 @@ impl AddAssign<&Self> for Summary {
     fn add_assign(&mut self, rhs: &Self) {
         self.bytes += rhs.bytes;
         self.ids |= &rhs.ids;
+        // A range with a key on either side no longer crosses the boundary.
+        self.open.xor_inplace(&rhs.open);
     }
 }
 
@@ -1055,17 +1760,54 @@ impl SubAssign<&Self> for Summary {
     fn sub_assign(&mut self, rhs: &Self) {
         self.bytes -= rhs.bytes;
         self.ids -= &rhs.ids;
+        self.open.xor_inplace(&rhs.open);
     }
 }
 
-#[derive(Default, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 struct Summary {
     /// This needs to be a `bag` not a `set` otherwise the `Sub` operation and `Add` operation will
     /// not be inverses of each other and `crop` assumptions break.
     ids: Bitbag,
+    /// The range items with exactly one key in the node, i.e. the ranges that cross its boundary.
+    /// These let [`MarkTree::overlapping`] skip the nodes that are entirely before the query.
+    open: Treemap,
     bytes: usize,
 }
 
+impl Summary {
+    #[inline]
+    fn insert_key(&mut self, key: Key) {
+        self.ids.insert(key.id());
+        toggle_open(&mut self.open, key);
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: Key) {
+        assert!(self.ids.remove(key.id()).is_some());
+        toggle_open(&mut self.open, key);
+    }
+}
+
+impl Default for Summary {
+    #[inline]
+    fn default() -> Self {
+        Self { ids: Bitbag::default(), open: Treemap::new(), bytes: 0 }
+    }
+}
+
+/// Add the id of a range key to the ids of the ranges with one key in a node, or remove it if the
+/// other key is already there.
+#[inline]
+fn toggle_open(open: &mut Treemap, key: Key) {
+    if !key.flags().contains(Flags::RANGE) {
+        return;
+    }
+
+    let id = key.id();
+    if open.contains(id) { open.remove(id) } else { open.add(id) }
+}
+
 impl fmt::Debug for Summary {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("")
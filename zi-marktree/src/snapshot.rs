@@ -0,0 +1,143 @@
+use std::marker::PhantomData;
+use std::ops::{Range, RangeBounds};
+
+use croaring::Treemap;
+use crop::tree::{Arc, Node};
+
+use crate::{ARITY, Leaf, MarkTree, MarkTreeId};
+
+/// A copy-on-write snapshot of the positions of the items of a `MarkTree`, see
+/// [`MarkTree::snapshot`]. The payloads are not part of the snapshot.
+pub struct Snapshot<Id: MarkTreeId, const N: usize> {
+    /// The payloads of this tree are always empty.
+    tree: MarkTree<Id, N>,
+}
+
+impl<Id: MarkTreeId, const N: usize> Clone for Snapshot<Id, N> {
+    fn clone(&self) -> Self {
+        Self { tree: self.tree.positions() }
+    }
+}
+
+impl<Id: MarkTreeId, const N: usize> std::fmt::Debug for Snapshot<Id, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.tree.fmt(f)
+    }
+}
+
+impl<Id: MarkTreeId, const N: usize> Snapshot<Id, N> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, id: impl Into<Id>) -> Option<Range<usize>> {
+        self.tree.get(id)
+    }
+
+    pub fn range(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, Id)> + '_ {
+        self.tree.range(range)
+    }
+
+    /// Returns the items whose range differs between the snapshots as `(id, before, after)` where
+    /// `self` is before and `other` is after, `None` if the item doesn't exist in that snapshot.
+    /// The subtrees the snapshots still share are skipped.
+    pub fn diff(&self, other: &Self) -> Vec<(Id, Option<Range<usize>>, Option<Range<usize>>)> {
+        let mut ids = Treemap::new();
+        changed_ids::<N>(self.tree.tree.root(), 0, other.tree.tree.root(), 0, &mut ids);
+        ids.iter()
+            .map(Id::from)
+            .filter_map(|id| {
+                let (before, after) = (self.get(id), other.get(id));
+                (before != after).then_some((id, before, after))
+            })
+            .collect()
+    }
+}
+
+/// Collect the ids that may have a different position in the two nodes at the given offsets.
+fn changed_ids<const N: usize>(
+    a: &Arc<Node<ARITY, Leaf<N>>>,
+    a_offset: usize,
+    b: &Arc<Node<ARITY, Leaf<N>>>,
+    b_offset: usize,
+    ids: &mut Treemap,
+) {
+    if a_offset == b_offset && Arc::ptr_eq(a, b) {
+        return;
+    }
+
+    if let (Node::Internal(x), Node::Internal(y)) = (a.as_ref(), b.as_ref()) {
+        let lens = |children: &[Arc<Node<ARITY, Leaf<N>>>]| {
+            children.iter().map(|child| child.summary().bytes).collect::<Vec<_>>()
+        };
+
+        // Only compare the children pairwise if they line up, otherwise every id is a candidate.
+        if a_offset == b_offset && lens(x.children()) == lens(y.children()) {
+            let mut offset = a_offset;
+            for (a, b) in x.children().iter().zip(y.children()) {
+                changed_ids(a, offset, b, offset, ids);
+                offset += a.summary().bytes;
+            }
+            return;
+        }
+    }
+
+    for node in [a, b] {
+        for id in node.summary().ids.values() {
+            ids.add(id);
+        }
+    }
+}
+
+impl<Id: MarkTreeId, const N: usize, T> MarkTree<Id, N, T> {
+    /// Take a snapshot of the positions of the items. This is cheap as the tree is shared with the
+    /// snapshot until either is modified.
+    pub fn snapshot(&self) -> Snapshot<Id, N> {
+        Snapshot { tree: self.positions() }
+    }
+
+    /// Restore the positions of the items to those of the snapshot, e.g. on undo.
+    /// The items created since the snapshot are deleted, as are the items that were deleted since
+    /// the snapshot as their payloads are gone.
+    pub fn restore(&mut self, snapshot: &Snapshot<Id, N>) {
+        self.tree = snapshot.tree.tree.clone();
+
+        let deleted = self
+            .tree
+            .summary()
+            .ids
+            .values()
+            .filter(|id| !self.payloads.contains_key(id))
+            .collect::<Vec<_>>();
+        for id in deleted {
+            self.delete_keys(id).expect("id is in the tree");
+        }
+
+        let ids = &self.tree.summary().ids;
+        self.payloads.retain(|id, _| ids.contains(*id));
+        self.invalidations.retain(|id, _| ids.contains(*id));
+        self.invalidated.clear();
+        self.tree.assert_invariants();
+    }
+
+    /// A copy of the tree without the payloads.
+    fn positions(&self) -> MarkTree<Id, N> {
+        MarkTree {
+            tree: self.tree.clone(),
+            payloads: Default::default(),
+            invalidations: Default::default(),
+            invalidated: vec![],
+            _id: PhantomData,
+        }
+    }
+}
@@ -8,22 +8,21 @@ use std::{fmt, iter};
 use proptest::collection::vec;
 use proptest::strategy::{BoxedStrategy, Strategy};
 use proptest::{prop_compose, prop_oneof};
-use zi_marktree::{Bias, Inserter, MarkBuilder, MarkTree, MarkTreeId};
+use zi_marktree::{Bias, Inserter, Invalidation, MarkBuilder, MarkTree, MarkTreeId, TaggedId};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Id(usize);
 
-impl From<Id> for u32 {
+impl From<Id> for u64 {
     #[inline]
-    fn from(id: Id) -> u32 {
-        debug_assert!(id.0 < u32::MAX as usize, "id.0 = {}", id.0);
-        id.0 as u32
+    fn from(id: Id) -> u64 {
+        id.0 as u64
     }
 }
 
-impl From<u32> for Id {
+impl From<u64> for Id {
     #[inline]
-    fn from(id: u32) -> Id {
+    fn from(id: u64) -> Id {
         Id(id as usize)
     }
 }
@@ -104,7 +103,25 @@ fn marktree_range_iter() {
 #[should_panic]
 fn marktree_id_too_large() {
     let mut tree = new(10);
-    tree.insert(0, Id(1 << 48));
+    tree.insert(0, Id(1 << 56));
+}
+
+#[test]
+fn marktree_tagged_ids() {
+    let mut tree = MarkTree::<TaggedId, 4>::new(10);
+    let (a, b) = (TaggedId::new(0, TaggedId::MAX_ID), TaggedId::new(u8::MAX, TaggedId::MAX_ID));
+    assert_eq!((b.tag(), b.id()), (u8::MAX, TaggedId::MAX_ID));
+    tree.insert(1, a).width(2);
+    tree.insert(2, b);
+    assert_iter_eq(tree.range(..), [(1..3, a), (2..2, b)]);
+    assert_eq!(tree.delete(a), Some(1..3));
+    assert_iter_eq(tree.range(..), [(2..2, b)]);
+}
+
+#[test]
+#[should_panic]
+fn marktree_tagged_id_too_large() {
+    TaggedId::new(0, 1 << 48);
 }
 
 #[test]
@@ -426,6 +443,283 @@ fn marktree_range_mark() {
     assert_iter_eq(tree.range(..), [(1..3, Id(1)), (2..5, Id(2))]);
 }
 
+#[test]
+fn marktree_payload() {
+    let mut tree = MarkTree::<Id, 4, &str>::new(10);
+    tree.insert(0, Id(0)).payload("a");
+    tree.insert(2, Id(1)).width(2).payload("b");
+    tree.insert(5, Id(2));
+    assert_iter_eq(
+        tree.range_with_payload(..),
+        [(0..0, Id(0), &"a"), (2..4, Id(1), &"b"), (5..5, Id(2), &"")],
+    );
+
+    tree.shift(0..1, 3);
+    assert_iter_eq(tree.range_with_payload(1..), [(4..6, Id(1), &"b"), (7..7, Id(2), &"")]);
+
+    // Reinserting an existing id replaces its payload.
+    tree.insert(0, Id(2)).payload("c");
+    assert_eq!(tree.payload(Id(2)), Some(&"c"));
+    *tree.payload_mut(Id(0)).unwrap() = "d";
+    assert_eq!(tree.remove(Id(0)), Some((0..0, "d")));
+    assert_eq!(tree.payload(Id(0)), None);
+    assert_eq!(tree.remove(Id(0)), None);
+
+    let tree = MarkTree::<Id, 4, &str>::build_with_payloads(
+        10,
+        [(Id(0), MarkBuilder::new(1).width(2), "x"), (Id(1), MarkBuilder::new(3), "y")],
+    );
+    assert_iter_eq(tree.range_with_payload(..), [(1..3, Id(0), &"x"), (3..3, Id(1), &"y")]);
+}
+
+#[test]
+fn marktree_invalidation() {
+    let mut tree = new(10);
+    tree.insert(2, Id(0)).width(2);
+    tree.insert(3, Id(1)).width(1).invalidation(Invalidation::Delete);
+    tree.insert(2, Id(2)).width(3).invalidation(Invalidation::PinLeft);
+    tree.insert(4, Id(3)).invalidation(Invalidation::PinRight);
+    // Only partially deleted.
+    tree.insert(1, Id(4)).width(2).invalidation(Invalidation::Delete);
+    // Empty items on the boundary of the deletion are not invalidated.
+    tree.insert(2, Id(5)).invalidation(Invalidation::Delete);
+    tree.insert(5, Id(6)).invalidation(Invalidation::Delete);
+
+    tree.shift(2..5, 1);
+    let mut invalidated = tree.invalidated().to_vec();
+    invalidated.sort();
+    // Collapsing items are not reported.
+    assert_eq!(invalidated, [Id(1), Id(2), Id(3)]);
+
+    assert!(tree.get(Id(0)).unwrap().is_empty());
+    assert_eq!(tree.get(Id(1)), None);
+    assert_eq!(tree.get(Id(2)), Some(2..2));
+    assert_eq!(tree.get(Id(3)), Some(3..3));
+    assert!(tree.get(Id(4)).is_some());
+    assert!(tree.get(Id(5)).is_some());
+    assert!(tree.get(Id(6)).is_some());
+
+    tree.shift(0..0, 1);
+    assert!(tree.invalidated().is_empty());
+
+    let mut tree = MarkTree::<Id, 4>::build(
+        10,
+        [(Id(0), MarkBuilder::new(1).width(2).invalidation(Invalidation::Delete))],
+    );
+    tree.shift(0..5, 0);
+    assert_eq!(tree.invalidated(), [Id(0)]);
+    assert_iter_eq(tree.range(..), []);
+}
+
+#[test]
+fn marktree_overlapping() {
+    let mut tree = new(100);
+    tree.insert(0, Id(0)).width(10);
+    tree.insert(6, Id(1));
+    tree.insert(5, Id(2)).width(20);
+    tree.insert(10, Id(3)).width(5);
+    tree.insert(12, Id(4));
+    (30..60).for_each(|i| drop(tree.insert(i, Id(i)).width(2)));
+
+    assert_iter_eq(tree.at(0), [(0..10, Id(0))]);
+    assert_iter_eq(tree.at(5), [(0..10, Id(0)), (5..25, Id(2))]);
+    assert_iter_eq(tree.at(6), [(0..10, Id(0)), (5..25, Id(2)), (6..6, Id(1))]);
+    // Ranges are exclusive.
+    assert_iter_eq(tree.at(10), [(5..25, Id(2)), (10..15, Id(3))]);
+    assert_iter_eq(tree.at(12), [(5..25, Id(2)), (10..15, Id(3)), (12..12, Id(4))]);
+    assert_iter_eq(tree.at(25), []);
+    assert_iter_eq(tree.at(45), [(44..46, Id(44)), (45..47, Id(45))]);
+
+    assert_iter_eq(
+        tree.overlapping(6..11),
+        [(0..10, Id(0)), (5..25, Id(2)), (6..6, Id(1)), (10..15, Id(3))],
+    );
+    assert_iter_eq(tree.overlapping(20..31), [(5..25, Id(2)), (30..32, Id(30))]);
+    assert_iter_eq(tree.overlapping(..), tree.range(..).collect::<Vec<_>>());
+
+    tree.delete(Id(2));
+    tree.shift(0..2, 0);
+    assert_iter_eq(tree.overlapping(4..9), [(0..8, Id(0)), (4..4, Id(1)), (8..13, Id(3))]);
+    assert_iter_eq(tree.overlapping(29..30), [(28..30, Id(30)), (29..31, Id(31))]);
+}
+
+#[test]
+fn marktree_range_rev() {
+    let mut tree = new(1000);
+    (0..100).for_each(|i| drop(tree.insert(i * 3, Id(i)).width(i % 3)));
+
+    let expected = tree.range(..).collect::<Vec<_>>();
+    assert_iter_eq(tree.range_rev(..), expected.iter().rev().cloned());
+    assert_iter_eq(tree.range_rev(30..60), (10..20).rev().map(|i| (i * 3..i * 3 + i % 3, Id(i))));
+    assert_iter_eq(tree.range_rev(..=4), [(3..4, Id(1)), (0..0, Id(0))]);
+    assert_eq!(tree.range_rev(..100).next(), Some((99..99, Id(33))));
+    assert_iter_eq(tree.range_rev(301..), []);
+}
+
+#[test]
+fn marktree_cursor() {
+    let mut tree = new(100);
+    tree.insert(0, Id(0));
+    tree.insert(10, Id(1)).width(5);
+    tree.insert(10, Id(2));
+    tree.insert(20, Id(3));
+
+    let all = tree.range(..).collect::<Vec<_>>();
+    let mut cursor = tree.cursor(11);
+    assert_eq!(cursor.prev(), Some(all[2].clone()));
+    assert_eq!(cursor.prev(), Some(all[1].clone()));
+    assert_eq!(cursor.next(), Some(all[1].clone()));
+    assert_eq!(cursor.next(), Some(all[2].clone()));
+    assert_eq!(cursor.next(), Some(all[3].clone()));
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.prev(), Some(all[3].clone()));
+
+    cursor.seek(10);
+    assert_eq!(cursor.prev(), Some(all[0].clone()));
+    assert_eq!(cursor.prev(), None);
+    assert_eq!(cursor.next(), Some(all[0].clone()));
+    assert_eq!(cursor.next(), Some(all[1].clone()));
+
+    // Walk across many leaves in both directions.
+    let mut tree = new(1000);
+    (0..300).for_each(|i| drop(tree.insert(i * 3 % 1000, Id(i)).width(i % 4)));
+    let all = tree.range(..).collect::<Vec<_>>();
+    let mut cursor = tree.cursor(0);
+    assert_iter_eq(iter::from_fn(|| cursor.next()), all.iter().cloned());
+    assert_iter_eq(iter::from_fn(|| cursor.prev()), all.iter().rev().cloned());
+
+    let mut cursor = tree.cursor(500);
+    let i = all.iter().position(|(range, _)| range.start >= 500).unwrap();
+    assert_iter_eq(iter::from_fn(|| cursor.next()), all[i..].iter().cloned());
+}
+
+#[test]
+fn marktree_shift_many() {
+    check_shift_many(
+        100,
+        [(0, 0), (5, 10), (10, 2), (20, 0), (30, 5)],
+        [(5, 0, 2), (8, 4, 0), (20, 1, 1)],
+    );
+    check_shift_many(100, [(10, 5)], [(0, 1, 0), (10, 5, 3), (50, 0, 10)]);
+    check_shift_many(100, [], [(0, 100, 5)]);
+    // Many edits within and across the leaves.
+    check_shift_many(
+        1000,
+        (0..200).map(|i| (i * 5, i % 7)),
+        (0..50).map(|i| (i * 20 + 3, i % 3, i % 4)),
+    );
+
+    let mut tree = new(20);
+    tree.insert(2, Id(0)).width(2).invalidation(Invalidation::Delete);
+    tree.insert(12, Id(1)).width(2).invalidation(Invalidation::PinRight);
+    tree.insert(15, Id(2));
+    tree.shift_many([(10..15, 1), (1..5, 0)]);
+    let mut invalidated = tree.invalidated().to_vec();
+    invalidated.sort();
+    assert_eq!(invalidated, [Id(0), Id(1)]);
+    assert_eq!(tree.len(), 12);
+    assert_iter_eq(tree.range(..), [(7..7, Id(1)), (7..7, Id(2))]);
+}
+
+#[test]
+fn marktree_snapshot() {
+    let mut tree = MarkTree::<Id, 4, &str>::new(1000);
+    (0..100).for_each(|i| drop(tree.insert(i * 5, Id(i)).width(2).payload("a")));
+
+    let snapshot = tree.snapshot();
+    assert!(snapshot.diff(&tree.snapshot()).is_empty());
+
+    tree.shift(400..402, 0);
+    tree.delete(Id(1));
+    tree.insert(1, Id(100));
+    assert_eq!(snapshot.get(Id(80)), Some(400..402));
+    assert_eq!(snapshot.len(), 1000);
+    assert_eq!(tree.len(), 998);
+
+    let mut diff = snapshot.diff(&tree.snapshot());
+    diff.sort_by_key(|(id, ..)| *id);
+    assert_eq!(diff[0], (Id(1), Some(5..7), None));
+    assert_eq!(diff[1], (Id(80), Some(400..402), Some(400..400)));
+    assert_eq!(diff[2], (Id(81), Some(405..407), Some(403..405)));
+    assert_eq!(diff.last(), Some(&(Id(100), None, Some(1..1))));
+    assert_eq!(diff.len(), 22);
+
+    tree.restore(&snapshot);
+    assert_eq!(tree.len(), 1000);
+    assert_eq!(tree.get(Id(1)), None);
+    assert_eq!(tree.get(Id(100)), None);
+    assert_eq!(tree.payload(Id(100)), None);
+    assert_iter_eq(
+        tree.range_with_payload(..),
+        (0..100).filter(|&i| i != 1).map(|i| (i * 5..i * 5 + 2, Id(i), &"a")),
+    );
+}
+
+#[test]
+fn marktree_encode() {
+    let mut tree = new(1000);
+    tree.insert(0, Id(0));
+    tree.insert(10, Id(1)).width(5).start_bias(Bias::Left);
+    tree.insert(20, Id(2)).start_bias(Bias::Left).invalidation(Invalidation::PinRight);
+    tree.insert(300, Id(1 << 20)).width(700).invalidation(Invalidation::Delete);
+
+    let bytes = tree.encode();
+    let mut decoded = MarkTree::<Id, 4>::decode(&bytes).unwrap();
+    assert_eq!(decoded.len(), 1000);
+    assert_iter_eq(decoded.range(..), tree.range(..).collect::<Vec<_>>());
+    assert_eq!(decoded.encode(), bytes);
+
+    // The biases and invalidation policies are preserved.
+    decoded.shift(10..10, 1);
+    decoded.shift(299..1001, 1);
+    assert_iter_eq(decoded.range(..), [(0..0, Id(0)), (10..16, Id(1)), (21..21, Id(2))]);
+
+    assert!(MarkTree::<Id, 4>::decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(MarkTree::<Id, 4>::decode(b"nope").is_err());
+    assert_eq!(MarkTree::<Id, 4>::decode(&new(5).encode()).unwrap().len(), 5);
+}
+
+#[test]
+fn marktree_encode_collapsed_ranges() {
+    let mut tree = new(100);
+    tree.insert(10, Id(0)).width(5);
+    tree.insert(10, Id(1)).width(5).start_bias(Bias::Left).end_bias(Bias::Right);
+    tree.insert(12, Id(2)).width(0).start_bias(Bias::Left);
+    tree.insert(20, Id(3)).width(5).end_bias(Bias::Left);
+    // Delete the text the ranges span so their start and end keys are at the same offset.
+    tree.shift(10..15, 0);
+    tree.shift(15..20, 0);
+
+    let bytes = tree.encode();
+    let decoded = MarkTree::<Id, 4>::decode(&bytes).unwrap();
+    assert_iter_eq(decoded.range(..), tree.range(..).collect::<Vec<_>>());
+    assert_eq!(decoded.encode(), bytes);
+}
+
+#[test]
+fn marktree_compact() {
+    let mut tree = new(1000);
+    (0..100).for_each(|i| drop(tree.insert(i * 10, Id(i)).width(i % 4)));
+    for i in 0..300 {
+        tree.shift(i..i + 1, 2);
+        tree.shift(i * 2..i * 2 + 2, 0);
+    }
+
+    let expected = tree.range(..).collect::<Vec<_>>();
+    let len = tree.len();
+    tree.compact();
+    assert_eq!(tree.len(), len);
+    assert_iter_eq(tree.range(..), expected.clone());
+    for (range, id) in expected {
+        assert_eq!(tree.get(id), Some(range));
+    }
+
+    let mut tree = new(10);
+    tree.shift(0..5, 1);
+    tree.compact();
+    assert_eq!(tree.len(), 6);
+}
+
 #[test]
 fn marktree_shift_range_mark() {
     #[track_caller]
@@ -489,6 +783,14 @@ proptest::proptest! {
         check_build(10000, at, widths);
     }
 
+    #[test]
+    fn marktree_prop_shift_many(
+        marks in vec((0..1000usize, 0..50usize), 0..200),
+        edits in vec((0..1000usize, 0..20usize, 0..20usize), 1..50),
+    ) {
+        check_shift_many(2000, marks, edits);
+    }
+
     #[test]
     fn marktree_prop_insert_delete(at in vec(arb_action(), 0..100)) {
         let n = 10000;
@@ -594,3 +896,42 @@ prop_compose! {
 fn arb_action() -> BoxedStrategy<Action> {
     prop_oneof![arb_action_insert(), (0..1000usize).prop_map(Id).prop_map(Action::Delete)].boxed()
 }
+
+fn check_shift_many(
+    n: usize,
+    marks: impl IntoIterator<Item = (usize, usize)>,
+    edits: impl IntoIterator<Item = (usize, usize, usize)>,
+) {
+    let mut edits = edits.into_iter().collect::<Vec<_>>();
+    edits.sort();
+    // Keep the edits disjoint.
+    let mut prev_end = None;
+    edits.retain(|&(start, len, _)| {
+        let disjoint = prev_end.is_none_or(|end| start > end);
+        if disjoint {
+            prev_end = Some(start + len);
+        }
+        disjoint
+    });
+    let edits =
+        edits.into_iter().map(|(start, len, by)| (start..start + len, by)).collect::<Vec<_>>();
+
+    let marks = marks
+        .into_iter()
+        .enumerate()
+        .map(|(i, (at, width))| (Id(i), MarkBuilder::new(at).width(width)))
+        .collect::<Vec<_>>();
+
+    let mut tree = MarkTree::<Id, 4>::build(n, marks.clone());
+    tree.shift_many(edits.clone());
+
+    let mut expected = MarkTree::<Id, 4>::build(n, marks.clone());
+    for (range, by) in edits.into_iter().rev() {
+        expected.shift(range, by);
+    }
+
+    assert_eq!(tree.len(), expected.len());
+    for (id, _) in marks {
+        assert_eq!(tree.get(id), expected.get(id), "{id:?}");
+    }
+}
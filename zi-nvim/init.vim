@@ -25,6 +25,20 @@ function! ClearUndoHistory()
     unlet old_undolevels
 endfunction
 
+" zi opens splits below and to the right
+set splitbelow splitright
+
+" Close all windows but one showing the scratch buffer and wipe all other buffers.
+function! ResetWindows(scratch)
+    silent! only!
+    execute 'buffer' a:scratch
+    for buf in nvim_list_bufs()
+        if buf != a:scratch
+            execute 'bwipeout!' buf
+        endif
+    endfor
+endfunction
+
 let mapleader = "\<Space>"
 
 nnoremap <silent> <leader>u <cmd>UndotreeToggle<CR> <bar> UndoTreeFocus<CR>
@@ -1,13 +1,15 @@
 //! Tests against a headless neovim instance
 
-use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use std::{fmt, mem};
 
-use anyhow::{Context, ensure};
+use anyhow::{Context, bail, ensure};
 use nvim_rs::error::LoopError;
 use tokio::process::{ChildStdin, Command};
-use zi::input::KeySequence;
+use zi::input::{KeyCode, KeyEvent, KeySequence};
 
 /// A fixture is a list of test cases. See [`TestCase`] for more information.
 pub struct Fixture {
@@ -15,7 +17,7 @@ pub struct Fixture {
     cases: Box<[TestCase]>,
 }
 
-/// A test case is a text buffer and a sequence of key inputs.
+/// A test case is a text buffer, or a set of files, and a sequence of key inputs.
 /// Text format:
 /// ```
 /// ==== any text here until new line
@@ -30,8 +32,22 @@ pub struct Fixture {
 ///
 /// ==== next test case
 /// ```
+///
+/// Instead of a single text buffer, a case can declare files with `++++ name [split|vsplit]`.
+/// See [`TestFile`] for how the files are opened.
+/// ```
+/// ==== test case with files
+/// ++++ a.txt
+/// text of a.txt
+/// ++++ b.txt vsplit
+/// text of b.txt
+/// ----
+/// <C-w>h:b b.txt<CR>
+/// ```
+#[derive(Debug, Clone)]
 pub struct TestCase {
     text: String,
+    files: Box<[TestFile]>,
     inputs: KeySequence,
 }
 
@@ -42,14 +58,94 @@ impl TestCase {
     ) -> Self {
         Self {
             text: text.into(),
+            files: Box::default(),
+            inputs: inputs.try_into().expect("could not convert into KeySequence"),
+        }
+    }
+
+    pub fn with_files(
+        files: impl IntoIterator<Item = TestFile>,
+        inputs: impl TryInto<KeySequence, Error: fmt::Debug>,
+    ) -> Self {
+        let files = files.into_iter().collect::<Box<[_]>>();
+        assert!(!files.is_empty(), "expected at least one file");
+        assert_eq!(
+            files[0].open,
+            OpenIn::Background,
+            "the first file is opened in the initial window"
+        );
+        Self {
+            text: String::new(),
+            files,
             inputs: inputs.try_into().expect("could not convert into KeySequence"),
         }
     }
 }
 
+impl fmt::Display for TestCase {
+    /// The test case in the fixture format without the `====` header.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.files[..] {
+            [] => writeln!(f, "{}", self.text)?,
+            files => {
+                for file in files {
+                    match file.open {
+                        OpenIn::Background => writeln!(f, "++++ {}", file.name)?,
+                        OpenIn::Split => writeln!(f, "++++ {} split", file.name)?,
+                        OpenIn::Vsplit => writeln!(f, "++++ {} vsplit", file.name)?,
+                    }
+                    writeln!(f, "{}", file.text)?;
+                }
+            }
+        }
+        writeln!(f, "----")?;
+        writeln!(f, "{}", self.inputs)
+    }
+}
+
+/// A file of a test case. The files are written to a temporary directory and opened from there.
+/// The first file is opened in the initial window, the rest are opened in the background unless
+/// they are opened in a split.
+#[derive(Debug, Clone)]
+pub struct TestFile {
+    name: String,
+    text: String,
+    open: OpenIn,
+}
+
+impl TestFile {
+    pub fn new(name: impl Into<String>, text: impl Into<String>, open: OpenIn) -> Self {
+        let name = name.into();
+        assert!(
+            !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '/'),
+            "invalid file name: `{name}`"
+        );
+        Self { name, text: text.into(), open }
+    }
+}
+
+/// Where a file is opened, the same as the corresponding neovim command.
+/// Splits are focused once opened and `splitbelow` and `splitright` are set to match zi.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OpenIn {
+    /// Load the file without showing it, `:badd`.
+    #[default]
+    Background,
+    /// A new window below the focused window, `:split`.
+    Split,
+    /// A new window to the right of the focused window, `:vsplit`.
+    Vsplit,
+}
+
 bitflags::bitflags! {
     #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct CompareFlags: u8 {
+        /// Compare the unnamed register and the named registers `a-z`.
+        const REGISTERS = 1 << 0;
+        /// Compare the pattern of the last search, the `/` register, outside of the command-line.
+        const SEARCH = 1 << 1;
+        /// Compare the anchor of the visual selection, the cursor is always compared.
+        const VISUAL = 1 << 2;
     }
 }
 
@@ -63,10 +159,41 @@ impl Fixture {
     }
 
     pub async fn nvim_vs_zi_with(self, nvim: &Nvim, flags: CompareFlags) -> zi::Result<()> {
-        let (mut editor, _tasks) = zi::Editor::new(zi::DummyBackend, self.size);
+        for case in &self.cases[..] {
+            // Each case gets its own editor so the windows and buffers of earlier cases are gone,
+            // neovim wipes them before each case.
+            let (mut editor, mut tasks) = zi::Editor::new(zi::DummyBackend, self.size);
+            nvim.run(&mut editor, &mut tasks, case, flags).await?;
+        }
+
+        Ok(())
+    }
 
+    /// Like [`Fixture::nvim_vs_zi_with`], but a failing case is minimized and the trace of the
+    /// minimized case is written to `trace_path` before returning the error.
+    /// Minimizing reruns the case many times, so property tests should only use this for the case
+    /// they shrink to.
+    pub async fn nvim_vs_zi_traced(
+        self,
+        nvim: &Nvim,
+        flags: CompareFlags,
+        trace_path: &Path,
+    ) -> zi::Result<()> {
         for case in &self.cases[..] {
-            nvim.run(&mut editor, case, flags).await?;
+            let (mut editor, mut tasks) = zi::Editor::new(zi::DummyBackend, self.size);
+            let Err(err) = nvim.run(&mut editor, &mut tasks, case, flags).await else { continue };
+
+            let minimized = nvim.minimize(self.size, case, flags).await;
+            let trace = nvim.trace(self.size, &minimized, flags).await?;
+            if let Some(dir) = trace_path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(trace_path, trace.to_string())?;
+            return Err(err.context(format!(
+                "minimized to `{}`, see the trace at {}",
+                minimized.inputs,
+                trace_path.display()
+            )));
         }
 
         Ok(())
@@ -86,8 +213,18 @@ impl Fixture {
         let mut cases = vec![];
 
         const TEST_CASE_HEADER: &str = "====";
+        const FILE_HEADER: &str = "++++";
         const SEP: &str = "----";
 
+        fn finish(mut text: String) -> String {
+            assert_eq!(
+                text.pop(),
+                Some('\n'),
+                "there should always be a newline after the text section (we don't want this to be part of the test though but remaining newlines are significant)"
+            );
+            text
+        }
+
         let file = std::fs::read_to_string(path)?;
         let mut sections = file.split(TEST_CASE_HEADER).peekable();
         if let Some(first) = sections.peek() {
@@ -97,22 +234,47 @@ impl Fixture {
 
         for section in sections {
             let mut text = String::new();
+            let mut files = vec![];
             let mut lines = section.split_inclusive('\n').filter(|line| !line.starts_with('#'));
             lines.next().expect("expected newline after ====");
 
             for line in lines.by_ref().take_while(|line| !line.starts_with(SEP)) {
-                text.push_str(line);
+                let Some(header) = line.strip_prefix(FILE_HEADER) else {
+                    text.push_str(line);
+                    continue;
+                };
+
+                match files.last_mut() {
+                    Some(TestFile { text: file_text, .. }) => {
+                        *file_text = finish(mem::take(&mut text))
+                    }
+                    None => assert!(text.is_empty(), "unexpected text before the first ++++"),
+                }
+
+                let mut words = header.split_whitespace();
+                let name = words.next().expect("expected file name after ++++");
+                let open = match words.next() {
+                    None => OpenIn::Background,
+                    Some("split") => OpenIn::Split,
+                    Some("vsplit") => OpenIn::Vsplit,
+                    Some(word) => {
+                        panic!("expected `split` or `vsplit` after file name, found `{word}`")
+                    }
+                };
+                files.push(TestFile::new(name, "", open));
             }
 
             let line = lines.next().expect("expected input key sequence line after ----");
             let inputs = KeySequence::from_str(line.trim()).expect("could not parse key sequence");
 
-            assert_eq!(
-                text.pop(),
-                Some('\n'),
-                "there should always be a newline after the text section (we don't want this to be part of the test though but remaining newlines are significant)"
-            );
-            cases.push(TestCase { text, inputs });
+            let text = finish(text);
+            cases.push(match files.last_mut() {
+                Some(file) => {
+                    file.text = text;
+                    TestCase::with_files(files, inputs)
+                }
+                None => TestCase::new(text, inputs),
+            });
 
             for line in lines {
                 assert!(
@@ -132,6 +294,8 @@ impl Fixture {
 
 pub struct Nvim {
     nvim: nvim_rs::Neovim<nvim_rs::compat::tokio::Compat<ChildStdin>>,
+    /// The number of the buffer the text of test cases without files is written to.
+    scratch: i64,
     #[allow(unused)]
     join_handle: tokio::task::JoinHandle<Result<(), Box<LoopError>>>,
     #[allow(unused)]
@@ -142,12 +306,96 @@ impl Nvim {
     pub async fn run(
         &self,
         editor: &mut zi::Editor,
+        tasks: &mut zi::Tasks,
+        case: &TestCase,
+        flags: CompareFlags,
+    ) -> zi::Result<()> {
+        self.run_traced(editor, tasks, case, flags, None).await
+    }
+
+    /// Shrink the key sequence of a failing test case by delta debugging: chunks of keys are
+    /// removed while the case still fails, halving the chunk size when none can be removed.
+    /// Each attempt runs in a new editor. The case is returned unchanged if it doesn't fail.
+    pub async fn minimize(&self, size: zi::Size, case: &TestCase, flags: CompareFlags) -> TestCase {
+        let fails = |keys: Vec<KeyEvent>| async move {
+            let case = TestCase { inputs: keys.into_iter().collect(), ..case.clone() };
+            let (mut editor, mut tasks) = zi::Editor::new(zi::DummyBackend, size);
+            self.run(&mut editor, &mut tasks, &case, flags).await.is_err()
+        };
+
+        let mut keys = case.inputs.clone().into_iter().collect::<Vec<_>>();
+        if !fails(keys.clone()).await {
+            return case.clone();
+        }
+
+        let mut chunks = 2;
+        while !keys.is_empty() {
+            let chunk_len = keys.len().div_ceil(chunks);
+            let mut removed = false;
+            for start in (0..keys.len()).step_by(chunk_len) {
+                let end = (start + chunk_len).min(keys.len());
+                let candidate = [&keys[..start], &keys[end..]].concat();
+                if fails(candidate.clone()).await {
+                    keys = candidate;
+                    chunks = chunks.saturating_sub(1).max(2);
+                    removed = true;
+                    break;
+                }
+            }
+
+            if !removed {
+                if chunk_len == 1 {
+                    break;
+                }
+                chunks = (chunks * 2).min(keys.len());
+            }
+        }
+
+        TestCase { inputs: keys.into_iter().collect(), ..case.clone() }
+    }
+
+    /// Run the test case in a new editor recording the state of zi and neovim after each key
+    /// until they diverge.
+    pub async fn trace(
+        &self,
+        size: zi::Size,
+        case: &TestCase,
+        flags: CompareFlags,
+    ) -> zi::Result<Trace> {
+        let (mut editor, mut tasks) = zi::Editor::new(zi::DummyBackend, size);
+        let mut steps = vec![];
+        // The error is recorded in the last step.
+        let _ = self.run_traced(&mut editor, &mut tasks, case, flags, Some(&mut steps)).await;
+        Ok(Trace { case: case.clone(), steps })
+    }
+
+    async fn run_traced(
+        &self,
+        editor: &mut zi::Editor,
+        tasks: &mut zi::Tasks,
         case: &TestCase,
         flags: CompareFlags,
+        trace: Option<&mut Vec<Step>>,
     ) -> zi::Result<()> {
+        self.nvim.call_function("ResetWindows", vec![self.scratch.into()]).await?;
+
+        let dir = match &case.files[..] {
+            [] => {
+                self.reset_text(editor, &case.text).await?;
+                None
+            }
+            files => Some(self.open_files(editor, tasks, files).await?),
+        };
+
+        let res = self.run_inputs(editor, tasks, case, dir.as_deref(), flags, trace).await;
+        if let Some(dir) = dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        res
+    }
+
+    async fn reset_text(&self, editor: &mut zi::Editor, initial: &str) -> zi::Result<()> {
         // Only remove the final newline. The rest of the newlines are significant.
-        let initial = &case.text;
-        let inputs = &case.inputs;
         let n = editor.buffer(zi::Active).text().len_bytes();
         editor.edit(zi::Active, &zi::Deltas::new([zi::Delta::new(0..n, initial)]))?;
         editor.set_cursor(zi::Active, (0, 0));
@@ -162,10 +410,84 @@ impl Nvim {
         self.nvim.call_function("ClearUndoHistory", vec![]).await?;
         self.nvim.get_current_win().await?.set_cursor((1, 0)).await?;
         self.nvim.input("<ESC><ESC>").await?;
+        Ok(())
+    }
+
+    /// Write the files to a new directory and open them in both editors, returning the directory.
+    async fn open_files(
+        &self,
+        editor: &mut zi::Editor,
+        tasks: &mut zi::Tasks,
+        files: &[TestFile],
+    ) -> zi::Result<PathBuf> {
+        editor.set_mode(zi::Mode::Normal);
+
+        static N: AtomicUsize = AtomicUsize::new(0);
+        let n = N.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("zi-nvim-{}-{n}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for file in files {
+            // Write the lines as neovim does so both editors see the same text.
+            std::fs::write(dir.join(&file.name), format!("{}\n", file.text))?;
+        }
+
+        for (i, file) in files.iter().enumerate() {
+            let cmd = match file.open {
+                _ if i == 0 => "edit",
+                OpenIn::Background => "badd",
+                OpenIn::Split => "split",
+                OpenIn::Vsplit => "vsplit",
+            };
+            self.nvim.command(&format!("{cmd} {}", dir.join(&file.name).display())).await?;
+        }
+        self.nvim.input("<ESC><ESC>").await?;
+
+        // Opening a file shows it in the active view, so open the background files first to leave
+        // the first file in the initial view.
+        let (background, shown): (Vec<_>, Vec<_>) = files
+            .iter()
+            .enumerate()
+            .partition(|&(i, file)| i > 0 && file.open == OpenIn::Background);
+        for (_, file) in background.into_iter().chain(shown) {
+            match file.open {
+                OpenIn::Background => {}
+                OpenIn::Split => {
+                    editor.split(zi::Active, zi::Direction::Down, zi::Constraint::Fill(1));
+                }
+                OpenIn::Vsplit => {
+                    editor.split(zi::Active, zi::Direction::Right, zi::Constraint::Fill(1));
+                }
+            }
+
+            let open = editor.open(dir.join(&file.name), zi::OpenFlags::empty())?;
+            let (buf, ()) = tokio::join!(open, editor.run_until_idle(tasks, IDLE));
+            buf?;
+        }
+
+        Ok(dir)
+    }
 
-        self.assert_eq(editor, flags)
+    async fn run_inputs(
+        &self,
+        editor: &mut zi::Editor,
+        tasks: &mut zi::Tasks,
+        case: &TestCase,
+        dir: Option<&Path>,
+        flags: CompareFlags,
+        mut trace: Option<&mut Vec<Step>>,
+    ) -> zi::Result<()> {
+        let res = self
+            .assert_eq(editor, dir, flags)
             .await
-            .context("did not reset state properly before test case")?;
+            .context("did not reset state properly before test case");
+        self.record(trace.as_deref_mut(), None, editor, &res).await?;
+        res?;
+
+        let inputs = &case.inputs;
+        let initial = match &case.files[..] {
+            [] => format!("text={:?}", case.text),
+            files => format!("files={files:?}"),
+        };
 
         for (i, key) in inputs.clone().into_iter().enumerate() {
             // https://github.com/neovim/neovim/issues/6159
@@ -173,28 +495,84 @@ impl Nvim {
             // Not sure if input is guaranteed to work though since I'm not sure what guarantees
             // nvim provides about when the input will be processed.
             self.nvim.input(&key.to_string()).await?;
+            let executes_command =
+                editor.mode() == zi::Mode::Command && key.code() == KeyCode::Enter;
             editor.handle_input(key.clone());
+            if executes_command {
+                // Commands run as tasks so they need to be driven to completion.
+                editor.run_until_idle(tasks, IDLE).await;
+            }
 
-            self.assert_eq(editor, flags).await.with_context(|| {
-                format!("index {i} in key sequence: `{inputs}`, key=`{key}` text={initial:?}")
-            })?;
+            let res = self.assert_eq(editor, dir, flags).await.with_context(|| {
+                format!("index {i} in key sequence: `{inputs}`, key=`{key}` {initial}")
+            });
+            self.record(trace.as_deref_mut(), Some(key), editor, &res).await?;
+            res?;
         }
 
         Ok(())
     }
 
-    // Compare the state of the editor with the state of the nvim instance
-    async fn assert_eq(&self, editor: &zi::Editor, flags: CompareFlags) -> zi::Result<()> {
-        let (mut vi_mode, mut blocking) = (None, None);
-        self.nvim.get_mode().await?.into_iter().for_each(|(key, value)| {
-            match key.as_str().unwrap() {
-                "mode" => vi_mode = Some(value.as_str().unwrap().to_owned()),
-                "blocking" => blocking = Some(value.as_bool().unwrap()),
-                _ => unreachable!(),
-            }
+    async fn record(
+        &self,
+        trace: Option<&mut Vec<Step>>,
+        key: Option<KeyEvent>,
+        editor: &zi::Editor,
+        res: &zi::Result<()>,
+    ) -> zi::Result<()> {
+        let Some(steps) = trace else { return Ok(()) };
+        steps.push(Step {
+            key,
+            zi: Snapshot::zi(editor),
+            vi: self.snapshot().await?,
+            error: res.as_ref().err().map(|err| format!("{err:#}")),
         });
+        Ok(())
+    }
+
+    /// The state of neovim, only the mode if neovim is blocked waiting for input.
+    async fn snapshot(&self) -> zi::Result<Snapshot> {
+        let (mode, blocking) = self.mode().await?;
+        if blocking {
+            return Ok(Snapshot { mode: format!("{mode} (blocking)"), cursor: None, text: None });
+        }
+
+        let (text, (line, col)) = tokio::try_join!(
+            async {
+                let buf = self.nvim.get_current_buf().await?;
+                Ok::<_, zi::Error>(buf.get_lines(0, -1, false).await?.join("\n"))
+            },
+            async { Ok(self.nvim.get_current_win().await?.get_cursor().await?) }
+        )?;
+        let cursor = zi::Point::new(line.saturating_sub(1) as usize, col as usize);
+        Ok(Snapshot { mode, cursor: Some(cursor), text: Some(text) })
+    }
+
+    /// The mode of neovim and whether it's blocked waiting for input.
+    async fn mode(&self) -> zi::Result<(String, bool)> {
+        let (mut mode, mut blocking) = (None, None);
+        for (key, value) in self.nvim.get_mode().await? {
+            match key.as_str() {
+                Some("mode") => mode = value.as_str().map(ToOwned::to_owned),
+                Some("blocking") => blocking = value.as_bool(),
+                _ => bail!("unexpected mode key: {key}"),
+            }
+        }
 
-        let (vi_mode, blocking) = (vi_mode.unwrap(), blocking.unwrap());
+        match (mode, blocking) {
+            (Some(mode), Some(blocking)) => Ok((mode, blocking)),
+            _ => bail!("incomplete mode"),
+        }
+    }
+
+    // Compare the state of the editor with the state of the nvim instance
+    async fn assert_eq(
+        &self,
+        editor: &zi::Editor,
+        dir: Option<&Path>,
+        flags: CompareFlags,
+    ) -> zi::Result<()> {
+        let (vi_mode, blocking) = self.mode().await?;
         if blocking {
             // nvim is waiting for input, can't do anything else until it's done.
             // It is important to check this upfront otherwise we will probably get stuck.
@@ -205,7 +583,7 @@ impl Nvim {
             return Ok(());
         }
 
-        let (vi_lines, (line, col)) = tokio::try_join!(
+        let (vi_lines, (line, col), vi_windows) = tokio::try_join!(
             async {
                 let buf = self.nvim.get_current_buf().await?;
                 let lines = buf.get_lines(0, -1, false).await?.join("\n");
@@ -216,7 +594,8 @@ impl Nvim {
                 let (line, col) = vi_win.get_cursor().await?;
                 let line = line.checked_sub(1).expect("1-indexed lines");
                 Ok((line, col))
-            }
+            },
+            self.windows(dir),
         )?;
 
         let zi_buf = editor.buffer(zi::Active);
@@ -230,14 +609,238 @@ impl Nvim {
             "v" => ensure!(editor.mode() == zi::Mode::Visual),
             "V" => ensure!(editor.mode() == zi::Mode::VisualLine),
             "\x16" => ensure!(editor.mode() == zi::Mode::VisualBlock),
-            _ => panic!("unknown mode: {vi_mode}"),
+            "c" => ensure!(editor.mode() == zi::Mode::Command),
+            // An error rather than a panic so failing cases can still be minimized.
+            _ => bail!("unknown mode: {vi_mode}"),
         };
 
         let vi_cursor = zi::Point::new(line as usize, col as usize);
         ensure_eq(vi_cursor, zi_cursor, vi_lines, zi_lines, flags)?;
 
+        let zi_windows = Windows::new(editor, dir);
+        ensure!(vi_windows == zi_windows, "vi: {vi_windows:?}\nzi: {zi_windows:?}");
+
+        if flags.contains(CompareFlags::REGISTERS) {
+            self.ensure_registers_eq(editor).await?;
+        }
+
+        // The `/` register is only set once the search is executed.
+        if flags.contains(CompareFlags::SEARCH) && vi_mode != "c" {
+            self.ensure_search_eq(editor).await?;
+        }
+
+        if flags.contains(CompareFlags::VISUAL) {
+            self.ensure_visual_eq(editor).await?;
+        }
+
         Ok(())
     }
+
+    async fn windows(&self, dir: Option<&Path>) -> zi::Result<Windows> {
+        // Fetch all the windows at once, they are in the same order as zi's views.
+        const EXPR: &str = "[map(nvim_tabpage_list_wins(0), \
+            {_, win -> [nvim_buf_get_name(winbufnr(win))] + nvim_win_get_cursor(win)}), \
+            index(nvim_tabpage_list_wins(0), win_getid())]";
+
+        let value = self.nvim.eval(EXPR).await?;
+        let (windows, focused) = match value.as_array().map(Vec::as_slice) {
+            Some([windows, focused]) => (
+                windows.as_array().context("expected a list of windows")?,
+                focused.as_u64().context("expected the index of the focused window")?,
+            ),
+            _ => bail!("unexpected windows value: {value}"),
+        };
+
+        let windows = windows
+            .iter()
+            .map(|window| match window.as_array().map(Vec::as_slice) {
+                Some([name, line, col]) => match (name.as_str(), line.as_u64(), col.as_u64()) {
+                    (Some(name), Some(line), Some(col)) => Ok(Window {
+                        file: file_name(Path::new(name), dir),
+                        cursor: zi::Point::new(line.saturating_sub(1) as usize, col as usize),
+                    }),
+                    _ => bail!("unexpected window value: {window}"),
+                },
+                _ => bail!("unexpected window value: {window}"),
+            })
+            .collect::<zi::Result<_>>()?;
+
+        Ok(Windows { windows, focused: focused as usize })
+    }
+
+    async fn ensure_registers_eq(&self, editor: &zi::Editor) -> zi::Result<()> {
+        const REGISTERS: &str = "\"abcdefghijklmnopqrstuvwxyz";
+
+        // Fetch all the registers at once, it's slow enough to make a request per register.
+        let expr = format!(
+            "map(split('{REGISTERS}', '\\zs'), {{_, name -> [getreg(name), getregtype(name)]}})"
+        );
+        let registers = self.nvim.eval(&expr).await?;
+        let registers = registers.as_array().context("expected a list of registers")?;
+
+        for (name, register) in REGISTERS.chars().zip(registers) {
+            let (content, kind) = match register.as_array().map(Vec::as_slice) {
+                Some([content, kind]) => (
+                    content.as_str().context("expected register content")?,
+                    kind.as_str().context("expected register type")?,
+                ),
+                _ => bail!("unexpected register value: {register}"),
+            };
+
+            // The type of an empty register is empty, otherwise blockwise types include the width.
+            let kind = match kind.chars().next() {
+                None => None,
+                Some('v') => Some(zi::RegisterKind::Charwise),
+                Some('V') => Some(zi::RegisterKind::Linewise),
+                Some('\x16') => Some(zi::RegisterKind::Blockwise),
+                Some(_) => bail!("unknown register type: {kind:?}"),
+            };
+
+            let vi_register = kind.map(|kind| (kind, content));
+            let zi_register = editor.register(name).map(|reg| (reg.kind, reg.content.as_str()));
+            ensure!(
+                vi_register == zi_register,
+                "register `{name}`\nvi: {vi_register:?}\nzi: {zi_register:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_search_eq(&self, editor: &zi::Editor) -> zi::Result<()> {
+        let pattern = self.nvim.call_function("getreg", vec!["/".into()]).await?;
+        let vi_pattern = pattern.as_str().context("expected a search pattern")?;
+        let zi_pattern = editor.search_pattern().unwrap_or_default();
+        ensure!(vi_pattern == zi_pattern, "search pattern\nvi: {vi_pattern:?}\nzi: {zi_pattern:?}");
+        Ok(())
+    }
+
+    async fn ensure_visual_eq(&self, editor: &zi::Editor) -> zi::Result<()> {
+        // The modes are already known to match, so neovim is in visual mode iff zi is.
+        let Some(zi_anchor) = editor.visual_anchor() else { return Ok(()) };
+
+        // `getpos('v')` is the end of the visual selection opposite the cursor as
+        // `[buf, lnum, col, off]`.
+        let pos = self.nvim.call_function("getpos", vec!["v".into()]).await?;
+        let vi_anchor = match pos.as_array().map(Vec::as_slice) {
+            Some([_, line, col, _]) => match (line.as_u64(), col.as_u64()) {
+                (Some(line), Some(col)) => {
+                    zi::Point::new(line.saturating_sub(1) as usize, col.saturating_sub(1) as usize)
+                }
+                _ => bail!("unexpected visual position: {pos}"),
+            },
+            _ => bail!("unexpected visual position: {pos}"),
+        };
+
+        ensure!(vi_anchor == zi_anchor, "visual anchor\nvi: {vi_anchor:?}\nzi: {zi_anchor:?}");
+        Ok(())
+    }
+}
+
+/// The states of zi and neovim after each key of a test case up to the first divergence.
+/// Displayed as the case in the fixture format followed by the steps, so that the case can be
+/// copied into a fixture as a regression test.
+pub struct Trace {
+    case: TestCase,
+    steps: Vec<Step>,
+}
+
+struct Step {
+    /// The key that was just input, `None` for the initial state.
+    key: Option<KeyEvent>,
+    zi: Snapshot,
+    vi: Snapshot,
+    error: Option<String>,
+}
+
+struct Snapshot {
+    mode: String,
+    cursor: Option<zi::Point>,
+    text: Option<String>,
+}
+
+impl Snapshot {
+    fn zi(editor: &zi::Editor) -> Self {
+        Self {
+            mode: format!("{:?}", editor.mode()),
+            cursor: Some(editor.cursor(zi::Active)),
+            text: Some(editor.buffer(zi::Active).text().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "==== regression")?;
+        writeln!(f, "{}", self.case)?;
+
+        for step in &self.steps {
+            match &step.key {
+                Some(key) => writeln!(f, "# `{key}`")?,
+                None => writeln!(f, "# initial")?,
+            }
+            writeln!(f, "#   zi: {}", step.zi)?;
+            writeln!(f, "#   vi: {}", step.vi)?;
+            if let Some(error) = &step.error {
+                for line in error.lines() {
+                    writeln!(f, "#   {line}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mode)?;
+        if let Some(cursor) = self.cursor {
+            write!(f, " {cursor:?}")?;
+        }
+        if let Some(text) = &self.text {
+            write!(f, " {text:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The windows in layout order, top to bottom and left to right.
+#[derive(Debug, PartialEq, Eq)]
+struct Windows {
+    windows: Vec<Window>,
+    focused: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Window {
+    /// The name of the test file shown in the window, if any.
+    file: Option<String>,
+    cursor: zi::Point,
+}
+
+impl Windows {
+    fn new(editor: &zi::Editor, dir: Option<&Path>) -> Self {
+        let active = editor.view(zi::Active).id();
+        let windows = editor
+            .views()
+            .map(|view| Window {
+                file: editor
+                    .buffer(view.buffer())
+                    .file_path()
+                    .and_then(|path| file_name(&path, dir)),
+                cursor: view.cursor(),
+            })
+            .collect();
+        let focused = editor.views().position(|view| view.id() == active).expect("active view");
+        Self { windows, focused }
+    }
+}
+
+/// The name of the test file at `path` if it's in `dir`.
+fn file_name(path: &Path, dir: Option<&Path>) -> Option<String> {
+    let name = path.strip_prefix(dir?).ok()?;
+    Some(name.to_string_lossy().into_owned())
 }
 
 fn ensure_eq(
@@ -263,6 +866,9 @@ fn ensure_lines_eq(vi_lines: &str, zi_lines: &str) -> Result<(), anyhow::Error>
 
 const DIR: &str = env!("CARGO_MANIFEST_DIR");
 
+/// How long the editor's tasks must be idle before a command or opening a file is finished.
+const IDLE: Duration = Duration::from_millis(50);
+
 impl Nvim {
     async fn spawn(width: u16, height: u16) -> zi::Result<Nvim> {
         let (nvim, join_handle, child) = nvim_rs::create::tokio::new_child_cmd(
@@ -280,11 +886,12 @@ impl Nvim {
 
         let buf = nvim.create_buf(false, true).await?;
         nvim.set_current_buf(&buf).await?;
+        let scratch = buf.get_number().await?;
         let win = nvim.get_current_win().await?;
         win.set_width(width as i64).await?;
         win.set_height(height as i64).await?;
 
-        Ok(Nvim { nvim, join_handle, child })
+        Ok(Nvim { nvim, scratch, join_handle, child })
     }
 }
 
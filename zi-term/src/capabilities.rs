@@ -0,0 +1,40 @@
+use zi::Capabilities;
+
+/// Detect the capabilities of the terminal from the environment.
+/// Most terminals can't be queried for these reliably, so this is heuristic based.
+pub fn detect(env: impl Fn(&str) -> Option<String>) -> Capabilities {
+    let term = env("TERM").unwrap_or_default();
+    let term_program = env("TERM_PROGRAM").unwrap_or_default();
+    let colorterm = env("COLORTERM").unwrap_or_default();
+
+    let kitty = term.contains("kitty") || env("KITTY_WINDOW_ID").is_some();
+    let wezterm = term_program == "WezTerm" || term.contains("wezterm");
+    let ghostty = term_program == "ghostty" || term.contains("ghostty");
+    let foot = term.starts_with("foot");
+    let alacritty = term.contains("alacritty");
+    let iterm = term_program == "iTerm.app";
+    let modern = kitty || wezterm || ghostty || foot || alacritty || iterm;
+    // Terminal multiplexers forward most sequences, but only if the outer terminal supports them.
+    let tmux = env("TMUX").is_some() || term.starts_with("tmux");
+
+    Capabilities {
+        truecolor: matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || modern
+            || term_program == "vscode",
+        // This one can actually be queried, see `probe`.
+        kitty_keyboard: false,
+        synchronized_output: modern || term_program == "vscode",
+        osc52: modern || tmux || term.starts_with("xterm"),
+        cursor_shape: !matches!(term.as_str(), "linux" | "dumb"),
+    }
+}
+
+/// Detect the capabilities of the current terminal, querying it where possible.
+/// This should be called after raw mode is enabled.
+pub fn probe() -> Capabilities {
+    let mut capabilities = detect(|var| std::env::var(var).ok());
+    capabilities.kitty_keyboard =
+        crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false);
+    capabilities
+}
@@ -1,16 +1,27 @@
+pub mod capabilities;
+pub mod messages;
+pub mod record;
+pub mod remote;
+
 use std::backtrace::Backtrace;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
 
 use crossterm::cursor::SetCursorStyle;
-use crossterm::event::DisableMouseCapture;
-use crossterm::terminal::EnterAlternateScreen;
-use crossterm::{cursor, execute, terminal};
-use futures_util::Stream;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::terminal::{BeginSynchronizedUpdate, EndSynchronizedUpdate, EnterAlternateScreen};
+use crossterm::{cursor, execute, queue, terminal};
+use futures_util::{Stream, StreamExt, stream};
 use mimalloc::MiMalloc;
 use tui::{Backend, Terminal};
-use zi::Editor;
 use zi::input::Event;
+use zi::{ClipboardSelection, Editor, TerminalRequest};
+
+use self::record::Recorder;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -18,16 +29,47 @@ static GLOBAL: MiMalloc = MiMalloc;
 pub struct App<B: Backend + io::Write> {
     term: Terminal<B>,
     panic_rx: Receiver<(String, Backtrace)>,
+    keyboard_enhancement: bool,
+    entered: bool,
+    recorder: Option<Recorder>,
 }
 
 impl<B: Backend + io::Write> App<B> {
     pub fn new(term: Terminal<B>, panic_rx: Receiver<(String, Backtrace)>) -> io::Result<Self> {
-        Ok(Self { term, panic_rx })
+        Ok(Self { term, panic_rx, keyboard_enhancement: false, entered: false, recorder: None })
+    }
+
+    /// Support recording with `:record`, the backend must write through [`Recorder::tee`].
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
     }
 
     pub fn enter(&mut self) -> io::Result<()> {
-        execute!(self.term.backend_mut(), EnterAlternateScreen, DisableMouseCapture)?;
+        execute!(
+            self.term.backend_mut(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        io::Write::write_all(self.term.backend_mut(), PUSH_TITLE.as_bytes())?;
         terminal::enable_raw_mode()?;
+        self.entered = true;
+        Ok(())
+    }
+
+    /// Enable the kitty keyboard protocol so ambiguous keys such as `<C-i>` and `<Tab>` can be
+    /// told apart, and the super and hyper modifiers and key releases are reported.
+    /// Only call this if the terminal supports it.
+    pub fn enable_keyboard_enhancement(&mut self) -> io::Result<()> {
+        execute!(
+            self.term.backend_mut(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+        self.keyboard_enhancement = true;
         Ok(())
     }
 
@@ -41,14 +83,11 @@ impl<B: Backend + io::Write> App<B> {
             .run(events, tasks, |editor| {
                 // Cursor styling isn't really exposed through the ratatui API, so we just hack it here.
                 // Looks much less janky if we set the cursor before rendering.
-                let style = match editor.mode() {
-                    zi::Mode::Normal | zi::Mode::Visual | zi::Mode::VisualLine | zi::Mode::VisualBlock => SetCursorStyle::SteadyBlock,
-                    zi::Mode::Insert | zi::Mode::Command => SetCursorStyle::SteadyBar,
-                    zi::Mode::OperatorPending(..) | zi::Mode::ReplacePending => {
-                        SetCursorStyle::SteadyUnderScore
-                    }
-                };
-                execute!(self.term.backend_mut(), cursor::Show, style)?;
+                if editor.capabilities().cursor_shape {
+                    execute!(self.term.backend_mut(), cursor::Show, cursor_style(editor))?;
+                } else {
+                    execute!(self.term.backend_mut(), cursor::Show)?;
+                }
 
                 self.render(editor)
             })
@@ -57,14 +96,172 @@ impl<B: Backend + io::Write> App<B> {
 
     #[tracing::instrument(skip_all, level = "debug")]
     fn render(&mut self, editor: &mut Editor) -> io::Result<()> {
+        let requests = editor.take_terminal_requests();
+        // Start recording before drawing so the recording starts with a complete frame.
+        for request in &requests {
+            if let TerminalRequest::Record(path) = request {
+                self.record(path.clone())?;
+            }
+        }
+
+        if let Some(recorder) = &self.recorder {
+            let size = self.term.size()?;
+            recorder.resize(size.width, size.height);
+        }
+
+        let sync = editor.capabilities().synchronized_output;
+        if sync {
+            queue!(self.term.backend_mut(), BeginSynchronizedUpdate)?;
+        }
+
         self.term.draw(|frame| editor.render(frame))?;
+
+        if sync {
+            execute!(self.term.backend_mut(), EndSynchronizedUpdate)?;
+        }
+
+        for request in requests {
+            match request {
+                TerminalRequest::SetClipboard(selection, text) => {
+                    let osc52 = osc52(selection, &text);
+                    io::Write::write_all(self.term.backend_mut(), osc52.as_bytes())?;
+                }
+                TerminalRequest::Record(_) => {}
+                TerminalRequest::SetTitle(title) => {
+                    io::Write::write_all(self.term.backend_mut(), osc0(&title).as_bytes())?;
+                }
+                TerminalRequest::SetCwd(path) => {
+                    io::Write::write_all(self.term.backend_mut(), osc7(&path).as_bytes())?;
+                }
+            }
+        }
+        io::Write::flush(self.term.backend_mut())?;
+
         Ok(())
     }
+
+    /// Start recording to `path`, or stop recording if `None`.
+    fn record(&mut self, path: Option<PathBuf>) -> io::Result<()> {
+        let Some(recorder) = &self.recorder else {
+            tracing::error!("recording is not supported");
+            return Ok(());
+        };
+
+        let Some(path) = path else {
+            recorder.stop();
+            return Ok(());
+        };
+
+        let size = self.term.size()?;
+        match recorder.start(&path, size.width, size.height) {
+            // Clear the screen so the next frame is drawn in full.
+            Ok(()) => self.term.clear(),
+            Err(err) => {
+                tracing::error!(?path, %err, "failed to start recording");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn cursor_style(editor: &Editor) -> SetCursorStyle {
+    let style = editor.cursor_style();
+    match (style.shape, style.blink) {
+        (tui::CursorShape::Block, false) => SetCursorStyle::SteadyBlock,
+        (tui::CursorShape::Block, true) => SetCursorStyle::BlinkingBlock,
+        (tui::CursorShape::Bar, false) => SetCursorStyle::SteadyBar,
+        (tui::CursorShape::Bar, true) => SetCursorStyle::BlinkingBar,
+        (tui::CursorShape::Underline, false) => SetCursorStyle::SteadyUnderScore,
+        (tui::CursorShape::Underline, true) => SetCursorStyle::BlinkingUnderScore,
+    }
+}
+
+/// The OSC 52 escape sequence that sets the terminal clipboard.
+fn osc52(selection: ClipboardSelection, text: &str) -> String {
+    let target = match selection {
+        ClipboardSelection::Clipboard => 'c',
+        ClipboardSelection::Primary => 'p',
+    };
+    format!("\x1b]52;{target};{}\x07", base64(text.as_bytes()))
+}
+
+/// Save the window title on the terminal's title stack so it can be restored with [`POP_TITLE`]
+/// once the editor exits, as the editor may set it with OSC 0.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// The OSC 0 escape sequence that sets the window title, without any control characters.
+fn osc0(title: &str) -> String {
+    let title = title.chars().filter(|c| !c.is_control()).collect::<String>();
+    format!("\x1b]0;{title}\x07")
+}
+
+/// The OSC 7 escape sequence that reports the working directory.
+fn osc7(path: &Path) -> String {
+    match zi::Url::from_file_path(path) {
+        Ok(url) => format!("\x1b]7;{url}\x07"),
+        Err(()) => {
+            tracing::error!(?path, "cannot report a relative working directory");
+            String::new()
+        }
+    }
+}
+
+/// Merge the key presses of text committed by an input method, which are read together, into text
+/// events so composed characters are typed as a whole, see [`zi::input::compose`].
+pub fn compose_input(
+    events: impl Stream<Item = io::Result<Event>>,
+) -> impl Stream<Item = io::Result<Event>> {
+    events.ready_chunks(64).flat_map(|chunk| {
+        let mut out = vec![];
+        let mut run = vec![];
+        for event in chunk {
+            match event {
+                Ok(event) => run.push(event),
+                Err(err) => {
+                    out.extend(zi::input::compose(run.drain(..)).into_iter().map(Ok));
+                    out.push(Err(err));
+                }
+            }
+        }
+        out.extend(zi::input::compose(run).into_iter().map(Ok));
+        stream::iter(out)
+    })
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
 }
 
 impl<W: Backend + io::Write> Drop for App<W> {
     fn drop(&mut self) {
-        _ = execute!(self.term.backend_mut(), crossterm::terminal::LeaveAlternateScreen);
+        if self.keyboard_enhancement {
+            _ = execute!(self.term.backend_mut(), PopKeyboardEnhancementFlags);
+        }
+        _ = execute!(
+            self.term.backend_mut(),
+            DisableBracketedPaste,
+            DisableMouseCapture,
+            crossterm::terminal::LeaveAlternateScreen
+        );
+        if self.entered {
+            _ = io::Write::write_all(self.term.backend_mut(), POP_TITLE.as_bytes());
+            _ = io::Write::flush(self.term.backend_mut());
+        }
         _ = terminal::disable_raw_mode();
 
         if let Ok((panic, backtrace)) = self.panic_rx.try_recv() {
@@ -7,10 +7,14 @@ use std::path::PathBuf;
 use clap::Parser;
 use crossterm::event::EventStream;
 use futures_util::StreamExt;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+use tracing_subscriber::{EnvFilter, Layer as _};
 use tui::Terminal;
 use tui::backend::CrosstermBackend;
 use zi::input::Event;
+use zi_term::messages::MessageLayer;
+use zi_term::record::{Recorder, Tee};
 
 #[derive(Parser)]
 struct Opts {
@@ -19,12 +23,33 @@ struct Opts {
     path: Option<PathBuf>,
     #[clap(long)]
     readonly: bool,
+    /// Record the session to an asciicast file.
+    #[clap(long)]
+    record: Option<PathBuf>,
+    /// Run the editor headless, serving remote terminals and clients over the unix socket.
+    #[clap(long, conflicts_with_all = ["remote", "record"])]
+    server: Option<PathBuf>,
+    /// Attach this terminal to the editor serving the unix socket, see `--server`.
+    #[clap(long, conflicts_with_all = ["path", "readonly", "record"])]
+    remote: Option<PathBuf>,
+    /// Write how long each phase of startup took to the file as startup goes.
+    #[clap(long, conflicts_with = "remote")]
+    startuptime: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let mut startup = zi::StartupProfile::new();
     let mut opts = Opts::parse();
 
+    if let Some(socket) = opts.remote {
+        return tokio::task::spawn_blocking(move || zi_term::remote::attach(&socket)).await?;
+    }
+
+    if let Some(path) = &opts.startuptime {
+        startup.write_to(path)?;
+    }
+
     const ZI_LOG: &str = "ZI_LOG";
 
     if std::env::var(ZI_LOG).is_ok() && opts.log.is_none() {
@@ -32,47 +57,81 @@ async fn main() -> anyhow::Result<()> {
         opts.log = Some(PathBuf::from("/tmp/zi.log"));
     }
 
-    if let Some(log) = opts.log {
-        let file = std::fs::OpenOptions::new().create(true).append(true).open(log)?;
-        tracing_subscriber::fmt()
-            .with_writer(file)
-            .with_ansi(false)
-            .with_env_filter(EnvFilter::from_env(ZI_LOG))
-            .init();
+    let log = match opts.log {
+        Some(log) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(log)?;
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(file)
+                .with_ansi(false)
+                .with_filter(EnvFilter::from_env(ZI_LOG));
+            Some(layer)
+        }
+        None => None,
+    };
+
+    // Warnings and errors are also recorded in the message log of the editor, see `:messages`.
+    let messages = MessageLayer::default();
+    tracing_subscriber::registry().with(log).with(messages.clone()).init();
+
+    let recorder = Recorder::default();
+    let term = match opts.server {
+        Some(_) => None,
+        None => Some(Terminal::new(CrosstermBackend::new(recorder.tee(io::stdout().lock())))?),
+    };
+    if let (Some(path), Some(term)) = (&opts.record, &term) {
+        let size = term.size()?;
+        recorder.start(path, size.width, size.height)?;
     }
-
-    let stdout = io::stdout().lock();
-    let term = Terminal::new(CrosstermBackend::new(stdout))?;
-    let (mut editor, tasks) = zi::Editor::new(zi_wasm::WasmBackend::default(), term.size()?);
+    // The server takes the size of the terminals that attach to it.
+    let size = match &term {
+        Some(term) => term.size()?,
+        None => tui::Rect::new(0, 0, 80, 24),
+    };
+    startup.record("setup terminal");
+    let (mut editor, tasks) = zi::Editor::new(zi_wasm::WasmBackend::default(), size);
+    messages.attach(editor.client());
+    editor.set_startup_profile(startup);
+    editor.record_startup("create editor");
 
     assert!(editor.register_plugin_manager(zi_wasm::PluginManager::default()).is_none());
 
+    let oldfiles_path = zi::dirs::data().join("oldfiles");
+    if let Err(err) = editor.load_oldfiles(&oldfiles_path) {
+        tracing::error!(%err, "failed to load oldfiles");
+    }
+
+    let folds_path = zi::dirs::data().join("folds");
+    if let Err(err) = editor.load_folds(&folds_path) {
+        tracing::error!(%err, "failed to load folds");
+    }
+
+    editor.settings().swap_dir.write(Some(zi::dirs::data().join("swap")));
+
+    let snippets_path = zi::dirs::config().join("snippets");
+    if let Err(err) = editor.load_snippets(&snippets_path) {
+        tracing::error!(%err, "failed to load snippets");
+    }
+    editor.record_startup("load state");
+
     let init_path = zi::dirs::config().join("init.zi");
     if init_path.exists() {
         for cmd in std::fs::read_to_string(init_path)?.parse::<zi::Commands>()? {
             editor.execute(cmd)?;
         }
     }
-
-    let (panic_tx, panic_rx) = std::sync::mpsc::sync_channel(1);
-    std::panic::update_hook(move |prev, info| {
-        let backtrace = Backtrace::capture();
-        let _ = panic_tx.send((info.to_string(), backtrace));
-        prev(info);
-    });
-
-    let mut app = zi_term::App::new(term, panic_rx)?;
-    app.enter()?;
-
-    let events = EventStream::new()
-        .filter_map(|ev| async { ev.map(|ev| Event::try_from(ev).ok()).transpose() });
+    editor.record_startup("load config");
 
     let client = editor.client();
     tokio::spawn(async move {
         if let Some(path) = opts.path {
             if path.exists() && path.is_dir() {
-                std::env::set_current_dir(&path)?;
-                client.with(|editor| editor.open_file_explorer(".")).await;
+                client
+                    .with(move |editor| {
+                        editor.change_dir(path)?;
+                        editor.open_file_explorer(".");
+                        Ok::<_, zi::Error>(())
+                    })
+                    .await?;
             } else {
                 let mut flags = zi::OpenFlags::SPAWN_LANGUAGE_SERVICES;
 
@@ -82,13 +141,51 @@ async fn main() -> anyhow::Result<()> {
 
                 client.with(move |editor| editor.open(path, flags)).await?.await?;
             }
+            client.with(|editor| editor.record_startup("open file")).await;
         }
         Ok::<_, zi::Error>(())
     });
 
     tokio::task::yield_now().await;
 
-    app.run(&mut editor, events, tasks).await?;
+    match (term, &opts.server) {
+        (Some(term), _) => run(term, recorder, &mut editor, tasks).await?,
+        (None, Some(socket)) => zi_term::remote::serve(&mut editor, tasks, socket).await?,
+        (None, None) => unreachable!("there is a terminal unless serving"),
+    }
+
+    editor.save_oldfiles(&oldfiles_path)?;
+    editor.save_folds(&folds_path)?;
+
+    Ok(())
+}
+
+async fn run(
+    term: Terminal<CrosstermBackend<Tee<io::StdoutLock<'static>>>>,
+    recorder: Recorder,
+    editor: &mut zi::Editor,
+    tasks: zi::Tasks,
+) -> anyhow::Result<()> {
+    let (panic_tx, panic_rx) = std::sync::mpsc::sync_channel(1);
+    std::panic::update_hook(move |prev, info| {
+        let backtrace = Backtrace::capture();
+        let _ = panic_tx.send((info.to_string(), backtrace));
+        prev(info);
+    });
+
+    let mut app = zi_term::App::new(term, panic_rx)?.with_recorder(recorder);
+    app.enter()?;
+    let capabilities = zi_term::capabilities::probe();
+    if capabilities.kitty_keyboard {
+        app.enable_keyboard_enhancement()?;
+    }
+    editor.set_capabilities(capabilities);
+
+    let events = zi_term::compose_input(
+        EventStream::new()
+            .filter_map(|ev| async { ev.map(|ev| Event::try_from(ev).ok()).transpose() }),
+    );
 
+    app.run(editor, events, tasks).await?;
     Ok(())
 }
@@ -0,0 +1,73 @@
+use std::fmt::{self, Write as _};
+use std::sync::{Arc, OnceLock};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use zi::MessageLevel;
+
+/// A tracing layer that records warnings and errors in the message log of the editor.
+/// Events before the editor is attached are dropped.
+#[derive(Clone, Default)]
+pub struct MessageLayer {
+    client: Arc<OnceLock<zi::Client>>,
+}
+
+impl MessageLayer {
+    pub fn attach(&self, client: zi::Client) {
+        let _ = self.client.set(client);
+    }
+}
+
+impl<S: Subscriber> Layer<S> for MessageLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = match *event.metadata().level() {
+            Level::ERROR => MessageLevel::Error,
+            Level::WARN => MessageLevel::Warning,
+            _ => return,
+        };
+
+        let Some(client) = self.client.get() else { return };
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let text = visitor.finish();
+        client.send(move |editor| {
+            editor.log(level, text);
+            Ok(())
+        });
+    }
+}
+
+/// Formats an event as its message followed by its other fields, e.g. `failed to save path=a.txt`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        match (self.message.is_empty(), self.fields.is_empty()) {
+            (_, true) => self.message,
+            (true, false) => self.fields,
+            (false, false) => format!("{} {}", self.message, self.fields),
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            let _ = write!(self.fields, "{}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"))
+    }
+}
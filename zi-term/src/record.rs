@@ -0,0 +1,97 @@
+//! Record the terminal to an asciicast file, see `:record` and `--record`.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use asciicast::{AsciicastWriter, EventKind, Header};
+
+/// A handle to the current recording, if any.
+/// Everything written to a writer wrapped with [`Recorder::tee`] is recorded, one output event
+/// per flush, so each rendered frame is a single event.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    recording: Arc<Mutex<Option<Recording>>>,
+}
+
+struct Recording {
+    writer: AsciicastWriter<BufWriter<File>>,
+    size: (u16, u16),
+}
+
+impl Recorder {
+    /// Start recording to `path`, replacing any current recording.
+    pub fn start(&self, path: impl AsRef<Path>, width: u16, height: u16) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = AsciicastWriter::new(file, &Header::new(width, height))?;
+        *self.lock() = Some(Recording { writer, size: (width, height) });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        *self.lock() = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Record a resize if the size differs from the last recorded size.
+    pub fn resize(&self, width: u16, height: u16) {
+        if self.lock().as_ref().is_some_and(|recording| recording.size != (width, height)) {
+            self.record(EventKind::Resize { width, height });
+        }
+    }
+
+    /// Wrap `w` so that everything written to it is recorded while recording.
+    pub fn tee<W: Write>(&self, w: W) -> Tee<W> {
+        Tee { inner: w, recorder: self.clone(), buf: vec![] }
+    }
+
+    /// Failing to record is not fatal, the recording is stopped instead.
+    fn record(&self, kind: EventKind) {
+        let mut recording = self.lock();
+        let Some(rec) = &mut *recording else { return };
+        if let EventKind::Resize { width, height } = kind {
+            rec.size = (width, height);
+        }
+
+        if let Err(err) = rec.writer.record(kind) {
+            tracing::error!(%err, "failed to record, stopping recording");
+            *recording = None;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Recording>> {
+        self.recording.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// See [`Recorder::tee`].
+pub struct Tee<W> {
+    inner: W,
+    recorder: Recorder,
+    /// The bytes written since the last flush.
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if self.recorder.is_recording() {
+            self.buf.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if !self.buf.is_empty() {
+            let output = String::from_utf8_lossy(&self.buf).into_owned();
+            self.buf.clear();
+            self.recorder.record(EventKind::Output(output));
+        }
+        Ok(())
+    }
+}
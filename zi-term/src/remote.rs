@@ -0,0 +1,646 @@
+//! Run the editor headless behind a unix socket, see `--server`, and attach terminals to it, see
+//! `--remote`.
+//!
+//! Peers speak msgpack-rpc. Requests are `[0, id, method, params]`, responses are
+//! `[1, id, error, result]`, and notifications are `[2, method, params]`.
+//!
+//! Requests to the server:
+//! - `attach(width, height)` starts sending `redraw(bytes)` notifications of the rendered frames
+//!   as terminal output. The editor takes the size of the most recently attached or resized
+//!   terminal. Each frame is rendered once and written to every attached terminal by a thread of
+//!   its own, so a slow terminal holds up neither the editor nor the other terminals.
+//! - `detach()` stops sending redraws.
+//! - `execute(command)`, `input(keys)`, `eval(expr)`, `text()`, and `mode()` call the editor.
+//!
+//! Notifications to the server are the input of an attached terminal: `key(key)`, `paste(text)`,
+//! and `resize(width, height)`.
+//! The server sends `quit()` to attached terminals once the editor quits.
+
+use std::io::{self, BufReader, BufWriter, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use rmpv::Value;
+use tokio::sync::mpsc;
+use tui::backend::{Backend, CrosstermBackend, WindowSize};
+use tui::{Cell, Rect, Terminal};
+use zi::input::{Event, KeyEvent};
+use zi::{Editor, TerminalRequest};
+
+/// A msgpack-rpc message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Request { id: u32, method: String, params: Vec<Value> },
+    Response { id: u32, error: Value, result: Value },
+    Notification { method: String, params: Vec<Value> },
+}
+
+impl Message {
+    /// Read the next message, `None` once the peer has closed the connection.
+    pub fn read(reader: &mut impl io::Read) -> io::Result<Option<Message>> {
+        let value = match rmpv::decode::read_value(reader) {
+            Ok(value) => value,
+            Err(rmpv::decode::Error::InvalidMarkerRead(err))
+                if err.kind() == io::ErrorKind::UnexpectedEof =>
+            {
+                return Ok(None);
+            }
+            Err(err) => return Err(io::Error::other(err)),
+        };
+        Message::try_from(value).map(Some)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        rmpv::encode::write_value(writer, &Value::from(self.clone())).map_err(io::Error::other)
+    }
+}
+
+impl TryFrom<Value> for Message {
+    type Error = io::Error;
+
+    fn try_from(value: Value) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid msgpack-rpc message");
+        let Value::Array(fields) = value else { return Err(invalid()) };
+        let id = |value: &Value| value.as_u64().and_then(|id| u32::try_from(id).ok());
+        let method = |value: Value| match value {
+            Value::String(s) => s.into_str(),
+            _ => None,
+        };
+        let params = |value: Value| match value {
+            Value::Array(params) => Some(params),
+            _ => None,
+        };
+
+        let mut fields = fields.into_iter();
+        let message = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(kind), Some(msgid), Some(m), Some(p)) if kind.as_u64() == Some(0) => {
+                Message::Request {
+                    id: id(&msgid).ok_or_else(invalid)?,
+                    method: method(m).ok_or_else(invalid)?,
+                    params: params(p).ok_or_else(invalid)?,
+                }
+            }
+            (Some(kind), Some(msgid), Some(error), Some(result)) if kind.as_u64() == Some(1) => {
+                Message::Response { id: id(&msgid).ok_or_else(invalid)?, error, result }
+            }
+            (Some(kind), Some(m), Some(p), None) if kind.as_u64() == Some(2) => {
+                Message::Notification {
+                    method: method(m).ok_or_else(invalid)?,
+                    params: params(p).ok_or_else(invalid)?,
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        match fields.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(message),
+        }
+    }
+}
+
+impl From<Message> for Value {
+    fn from(message: Message) -> Self {
+        Value::Array(match message {
+            Message::Request { id, method, params } => {
+                vec![0.into(), id.into(), method.into(), params.into()]
+            }
+            Message::Response { id, error, result } => vec![1.into(), id.into(), error, result],
+            Message::Notification { method, params } => {
+                vec![2.into(), method.into(), params.into()]
+            }
+        })
+    }
+}
+
+/// The sending half of a connection, shared by everything that sends to the peer.
+#[derive(Clone)]
+struct Peer {
+    stream: Arc<Mutex<UnixStream>>,
+}
+
+impl Peer {
+    fn new(stream: UnixStream) -> io::Result<Self> {
+        // Don't let a stuck peer block the editor forever.
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+        Ok(Self { stream: Arc::new(Mutex::new(stream)) })
+    }
+
+    fn send(&self, message: &Message) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap_or_else(|err| err.into_inner());
+        let mut writer = BufWriter::new(&mut *stream);
+        message.write(&mut writer)?;
+        writer.flush()
+    }
+
+    fn notify(&self, method: &str, params: Vec<Value>) -> io::Result<()> {
+        self.send(&Message::Notification { method: method.to_owned(), params })
+    }
+}
+
+/// Run the editor until it quits, serving the peers that connect to the socket at `path`.
+/// The socket is removed afterwards.
+pub async fn serve(editor: &mut Editor, tasks: zi::Tasks, path: &Path) -> io::Result<()> {
+    let listener = UnixListener::bind(path)?;
+    let _socket = RemoveOnDrop(path);
+
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let state = State {
+        attached: vec![],
+        size: None,
+        term: Terminal::new(RemoteBackend::new(editor.size()))?,
+    };
+    let server = Server {
+        client: editor.client(),
+        runtime: tokio::runtime::Handle::current(),
+        events: events_tx,
+        state: Arc::new(Mutex::new(state)),
+        next_id: Default::default(),
+    };
+
+    std::thread::spawn({
+        let server = server.clone();
+        move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let server = server.clone();
+                        std::thread::spawn(move || server.connection(stream));
+                    }
+                    Err(err) => tracing::error!(%err, "failed to accept connection"),
+                }
+            }
+        }
+    });
+
+    let events = futures_util::stream::poll_fn(move |cx| events_rx.poll_recv(cx));
+    editor.run(events, tasks, |editor| server.render(editor)).await?;
+
+    let attached = std::mem::take(&mut server.state().attached);
+    for attached in attached {
+        attached.quit();
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Server {
+    client: zi::Client,
+    runtime: tokio::runtime::Handle,
+    events: mpsc::UnboundedSender<io::Result<Event>>,
+    state: Arc<Mutex<State>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct State {
+    attached: Vec<Attached>,
+    /// The size of the most recently attached or resized terminal.
+    size: Option<zi::Size>,
+    /// The terminal the frames are rendered to, shared by the attached terminals as they are all
+    /// the size of the editor.
+    term: Terminal<RemoteBackend>,
+}
+
+/// The number of messages queued for an attached terminal before it is considered stuck.
+const QUEUE_LEN: usize = 32;
+
+/// A terminal attached to the server, it is sent the frames rendered at the size of the editor.
+struct Attached {
+    id: u64,
+    tx: SyncSender<Message>,
+    writer: JoinHandle<()>,
+}
+
+impl Server {
+    fn connection(self, stream: UnixStream) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Err(err) = self.serve_connection(id, stream) {
+            tracing::error!(%err, "remote connection failed");
+        }
+        self.detach(id);
+    }
+
+    fn serve_connection(&self, id: u64, stream: UnixStream) -> io::Result<()> {
+        let peer = Peer::new(stream.try_clone()?)?;
+        let mut reader = BufReader::new(stream);
+        while let Some(message) = Message::read(&mut reader)? {
+            match message {
+                Message::Request { id: msgid, method, params } => {
+                    let (server, peer) = (self.clone(), peer.clone());
+                    self.runtime.spawn(async move {
+                        let (error, result) = match server.request(id, &peer, &method, params).await
+                        {
+                            Ok(result) => (Value::Nil, result),
+                            Err(err) => (format!("{err:#}").into(), Value::Nil),
+                        };
+
+                        if let Err(err) = peer.send(&Message::Response { id: msgid, error, result })
+                        {
+                            tracing::error!(%err, "failed to respond to remote request");
+                        }
+                    });
+                }
+                Message::Notification { method, params } => {
+                    if let Err(err) = self.notification(&method, &params) {
+                        tracing::error!(%err, method, "invalid remote notification");
+                    }
+                }
+                Message::Response { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        id: u64,
+        peer: &Peer,
+        method: &str,
+        params: Vec<Value>,
+    ) -> zi::Result<Value> {
+        match (method, &params[..]) {
+            ("attach", [width, height]) => {
+                let size = as_size(width, height)?;
+                {
+                    let mut state = self.state();
+                    // The terminal may be showing anything, so the next frame is drawn in full.
+                    // The other terminals are sent it too as they all share the one terminal.
+                    state.term.clear()?;
+                    state.attached.push(Attached::new(id, peer.clone()));
+                }
+                self.resize(size);
+                // Round trip so the new terminal is rendered to after this request.
+                self.client.with(|_editor| ()).await;
+                Ok(Value::Nil)
+            }
+            ("detach", []) => {
+                self.detach(id);
+                Ok(Value::Nil)
+            }
+            ("execute", [cmd]) => {
+                let cmd = as_str(cmd)?.to_owned();
+                self.client.with(move |editor| editor.execute(cmd.as_str())).await?;
+                Ok(Value::Nil)
+            }
+            ("input", [keys]) => {
+                let keys = as_str(keys)?.to_owned();
+                self.client
+                    .with(move |editor| editor.input(keys.as_str()))
+                    .await
+                    .map_err(|_| anyhow::anyhow!("invalid key sequence"))?;
+                Ok(Value::Nil)
+            }
+            ("eval", [expr]) => {
+                let expr = as_str(expr)?.to_owned();
+                Ok(self.client.with(move |editor| editor.eval(&expr)).await?.into())
+            }
+            ("text", []) => {
+                Ok(self.client.with(|editor| editor.text(zi::Active).to_string()).await.into())
+            }
+            ("mode", []) => Ok(self.client.with(|editor| mode_name(editor.mode())).await.into()),
+            _ => anyhow::bail!("invalid request: `{method}` with {} params", params.len()),
+        }
+    }
+
+    fn notification(&self, method: &str, params: &[Value]) -> zi::Result<()> {
+        match (method, params) {
+            ("key", [key]) => {
+                let key = as_str(key)?
+                    .parse::<KeyEvent>()
+                    .map_err(|_| anyhow::anyhow!("invalid key: `{key}`"))?;
+                self.send(Event::Key(key));
+            }
+            ("paste", [text]) => self.send(Event::Paste(as_str(text)?.to_owned())),
+            ("resize", [width, height]) => self.resize(as_size(width, height)?),
+            _ => anyhow::bail!("invalid notification: `{method}` with {} params", params.len()),
+        }
+        Ok(())
+    }
+
+    fn render(&self, editor: &mut Editor) -> io::Result<()> {
+        let requests = editor.take_terminal_requests();
+        let mut state = self.state();
+        let Some(size) = state.size else { return Ok(()) };
+        if state.attached.is_empty() {
+            return Ok(());
+        }
+
+        let output = render(&mut state.term, editor, size, &requests)?;
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        let redraw = Message::Notification { method: "redraw".into(), params: vec![output.into()] };
+        state.attached.retain(|attached| match attached.tx.try_send(redraw.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tracing::info!("detaching remote terminal that is not keeping up");
+                false
+            }
+            // The writer has already logged why it stopped.
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+        Ok(())
+    }
+
+    fn resize(&self, size: zi::Size) {
+        self.state().size = Some(size);
+        self.send(Event::Resize(size.width, size.height));
+    }
+
+    fn detach(&self, id: u64) {
+        self.state().attached.retain(|attached| attached.id != id);
+    }
+
+    fn send(&self, event: Event) {
+        // The editor has quit if the receiver is gone.
+        let _ = self.events.send(Ok(event));
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Attached {
+    fn new(id: u64, peer: Peer) -> Self {
+        let (tx, rx) = std::sync::mpsc::sync_channel::<Message>(QUEUE_LEN);
+        let writer = std::thread::spawn(move || {
+            for message in rx {
+                if let Err(err) = peer.send(&message) {
+                    tracing::info!(%err, "detaching remote terminal");
+                    break;
+                }
+            }
+        });
+        Self { id, tx, writer }
+    }
+
+    /// Tell the terminal the editor has quit once it has been sent the queued frames.
+    fn quit(self) {
+        // The peer may well have gone already.
+        let _ = self.tx.send(Message::Notification { method: "quit".into(), params: vec![] });
+        drop(self.tx);
+        let _ = self.writer.join();
+    }
+}
+
+/// Render the editor to `term`, returning the terminal output to send to the attached terminals.
+fn render(
+    term: &mut Terminal<RemoteBackend>,
+    editor: &mut Editor,
+    size: zi::Size,
+    requests: &[TerminalRequest],
+) -> io::Result<Vec<u8>> {
+    term.backend_mut().size = size;
+    if editor.capabilities().cursor_shape {
+        queue!(term.backend_mut(), cursor::Show, crate::cursor_style(editor))?;
+    }
+
+    term.draw(|frame| editor.render(frame))?;
+
+    for request in requests {
+        match request {
+            TerminalRequest::SetClipboard(selection, text) => {
+                let osc52 = crate::osc52(*selection, text);
+                term.backend_mut().write_all(osc52.as_bytes())?;
+            }
+            TerminalRequest::Record(_) => {
+                tracing::error!("recording is not supported remotely")
+            }
+            TerminalRequest::SetTitle(title) => {
+                term.backend_mut().write_all(crate::osc0(title).as_bytes())?;
+            }
+            TerminalRequest::SetCwd(path) => {
+                term.backend_mut().write_all(crate::osc7(path).as_bytes())?;
+            }
+        }
+    }
+
+    Ok(term.backend_mut().take_output())
+}
+
+/// A backend that buffers the terminal output to send to an attached terminal.
+struct RemoteBackend {
+    inner: CrosstermBackend<Output>,
+    output: Output,
+    size: zi::Size,
+    cursor: (u16, u16),
+}
+
+impl RemoteBackend {
+    fn new(size: zi::Size) -> Self {
+        let output = Output::default();
+        Self { inner: CrosstermBackend::new(output.clone()), output, size, cursor: (0, 0) }
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut *self.output.0.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for RemoteBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(&mut self.inner)
+    }
+}
+
+/// Forwards to the crossterm backend, except for queries of the terminal which aren't possible.
+impl Backend for RemoteBackend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        self.inner.draw(content)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.inner.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.inner.show_cursor()
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        Ok(self.cursor)
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.cursor = (x, y);
+        self.inner.set_cursor(x, y)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.inner.clear()
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        Ok(Rect::new(0, 0, self.size.width, self.size.height))
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        let columns_rows = (self.size.width, self.size.height).into();
+        Ok(WindowSize { columns_rows, pixels: Default::default() })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.inner)
+    }
+}
+
+/// Attach this terminal to the server listening at `path` until the editor quits or the
+/// connection is closed.
+pub fn attach(path: &Path) -> anyhow::Result<()> {
+    let stream = UnixStream::connect(path)?;
+    let peer = Peer::new(stream.try_clone()?)?;
+
+    let _term = RawTerminal::enter()?;
+    let (width, height) = terminal::size()?;
+    peer.send(&Message::Request {
+        id: 0,
+        method: "attach".into(),
+        params: vec![width.into(), height.into()],
+    })?;
+
+    let done = Arc::new(AtomicBool::new(false));
+    let output = std::thread::spawn({
+        let done = done.clone();
+        move || {
+            let res = receive(stream);
+            done.store(true, Ordering::Release);
+            res
+        }
+    });
+
+    while !done.load(Ordering::Acquire) {
+        if !crossterm::event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+
+        let res = match Event::try_from(crossterm::event::read()?) {
+            Ok(Event::Key(key)) => peer.notify("key", vec![key.to_string().into()]),
+            Ok(Event::Paste(text)) => peer.notify("paste", vec![text.into()]),
+            Ok(Event::Resize(width, height)) => {
+                peer.notify("resize", vec![width.into(), height.into()])
+            }
+            _ => Ok(()),
+        };
+
+        // The server is gone, the output thread will notice too.
+        if res.is_err() {
+            break;
+        }
+    }
+
+    output.join().expect("output thread panicked")
+}
+
+/// Write the frames from the server to the terminal until told to quit.
+fn receive(stream: UnixStream) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut stdout = io::stdout().lock();
+    while let Some(message) = Message::read(&mut reader)? {
+        match message {
+            Message::Notification { method, params } => match (method.as_str(), &params[..]) {
+                ("redraw", [Value::Binary(output)]) => {
+                    stdout.write_all(output)?;
+                    stdout.flush()?;
+                }
+                ("quit", []) => break,
+                _ => tracing::warn!(%method, "unknown notification from server"),
+            },
+            Message::Response { error: Value::Nil, .. } => {}
+            Message::Response { error, .. } => anyhow::bail!("server error: {error}"),
+            Message::Request { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// Puts the terminal in raw mode on the alternate screen until dropped, restoring the title the
+/// editor may have set.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+        io::stdout().write_all(crate::PUSH_TITLE.as_bytes())?;
+        terminal::enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        _ = execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen);
+        _ = io::stdout().write_all(crate::POP_TITLE.as_bytes());
+        _ = io::stdout().flush();
+        _ = terminal::disable_raw_mode();
+    }
+}
+
+struct RemoveOnDrop<'a>(&'a Path);
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(self.0);
+    }
+}
+
+fn mode_name(mode: zi::Mode) -> &'static str {
+    match mode {
+        zi::Mode::Normal => "normal",
+        zi::Mode::Insert => "insert",
+        zi::Mode::Command => "command",
+        zi::Mode::Visual => "visual",
+        zi::Mode::VisualLine => "visual-line",
+        zi::Mode::VisualBlock => "visual-block",
+        zi::Mode::OperatorPending(..) => "operator-pending",
+        zi::Mode::ReplacePending => "replace-pending",
+    }
+}
+
+fn as_str(value: &Value) -> zi::Result<&str> {
+    value.as_str().ok_or_else(|| anyhow::anyhow!("expected a string, found `{value}`"))
+}
+
+fn as_size(width: &Value, height: &Value) -> zi::Result<zi::Size> {
+    let dimension = |value: &Value| {
+        value
+            .as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .ok_or_else(|| anyhow::anyhow!("expected a terminal dimension, found `{value}`"))
+    };
+
+    let (width, height) = (dimension(width)?, dimension(height)?);
+    // Leave room for the status line and command line.
+    anyhow::ensure!(height >= 3, "terminal is too small: {width}x{height}");
+    Ok(zi::Size::new(width, height))
+}
@@ -5,7 +5,7 @@ use std::path::{Path, PathBuf};
 
 use asciicast::Asciicast;
 use tui::Terminal;
-use tui::backend::CrosstermBackend;
+use tui::backend::{CrosstermBackend, TestBackend};
 use zi::OpenFlags;
 
 #[tokio::test]
@@ -37,6 +37,7 @@ async fn syntax_highlight() -> anyhow::Result<()> {
     snapshot_path("multiline highlight", "tests/zi-term/testdata/multiline-highlight.rs").await?;
     snapshot_path("multiline highlight 2", "tests/zi-term/testdata/multiline-highlight-2.rs")
         .await?;
+    snapshot_path("markdown injection", "tests/zi-term/testdata/injection.md").await?;
 
     Ok(())
 }
@@ -79,6 +80,124 @@ async fn scroll() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn syntax_folds_without_render() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("main.rs");
+    let text = "fn a() {\n}\n";
+    std::fs::write(&path, text)?;
+
+    let (mut editor, tasks) = zi::Editor::new(zi_wasm::WasmBackend::default(), (80, 24));
+    let client = editor.client();
+    tokio::spawn(async move {
+        editor.run(futures_util::stream::empty(), tasks, |_editor| Ok(())).await.unwrap()
+    });
+
+    // Nothing is rendered to parse the syntax in the background, so the folds must parse it.
+    let buf = client.with(move |editor| editor.open(path, OpenFlags::empty())).await?.await?;
+    let folds = client.with(move |editor| editor.update_folds(buf)).await;
+    assert_eq!(folds, [0..2]);
+
+    let folds = client
+        .with(move |editor| {
+            editor.edit(buf, &zi::Deltas::insert_at(text.len(), "fn b() {\n}\n")).unwrap();
+            editor.update_folds(buf)
+        })
+        .await;
+    assert_eq!(folds, [0..2, 2..4]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rainbow_delimiters() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("main.rs");
+    std::fs::write(&path, "fn f() { g((1)); }\n")?;
+
+    let (mut editor, tasks) = zi::Editor::new(zi_wasm::WasmBackend::default(), (80, 24));
+    let client = editor.client();
+    tokio::spawn(async move {
+        editor.run(futures_util::stream::empty(), tasks, |_editor| Ok(())).await.unwrap()
+    });
+
+    let buf = client.with(move |editor| editor.open(path, OpenFlags::empty())).await?.await?;
+    // The colors of the delimiters of the first line in order, i.e. `( ) { ( ( ) ) }`.
+    let delimiter_colors = |rainbow: bool| {
+        client.with(move |editor| {
+            let rust = zi::filetype!(rust);
+            if editor.rainbow_delimiters(rust) != rainbow {
+                editor.toggle_rainbow_delimiters(rust);
+            }
+            editor[buf].reparse();
+
+            let mut term = Terminal::new(TestBackend::new(80, 24)).unwrap();
+            term.draw(|f| editor.render(f)).unwrap();
+            let buffer = term.backend().buffer();
+            (0..buffer.area.width)
+                .map(|x| buffer.get(x, 0))
+                .filter(|cell| matches!(cell.symbol(), "(" | ")" | "{" | "}"))
+                .map(|cell| cell.fg)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let colors = delimiter_colors(true).await;
+    assert_eq!(colors.len(), 8);
+    // Each delimiter has the color of the one it is paired with.
+    assert_eq!(colors[0], colors[1]);
+    assert_eq!(colors[2], colors[7]);
+    assert_eq!(colors[3], colors[6]);
+    assert_eq!(colors[4], colors[5]);
+    // The nested parentheses are colored differently, and from the braces they are nested in.
+    assert_ne!(colors[3], colors[4]);
+    assert_ne!(colors[2], colors[3]);
+
+    // Without rainbow delimiters, parentheses are all highlighted alike.
+    let colors = delimiter_colors(false).await;
+    assert_eq!(colors[3], colors[4]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn syntax_indent() -> anyhow::Result<()> {
+    let (mut editor, tasks) = zi::Editor::new(zi_wasm::WasmBackend::default(), (80, 24));
+    let client = editor.client();
+    tokio::spawn(async move {
+        editor.run(futures_util::stream::empty(), tasks, |_editor| Ok(())).await.unwrap()
+    });
+
+    let dir = tempfile::tempdir()?;
+    for (name, text, keys, expected) in [
+        ("o.rs", "fn f() {\n    x;\n}\n", "jo", "fn f() {\n    x;\n    \n}\n"),
+        (
+            "cr.rs",
+            "fn f() {\n    g(1, 2);\n}\n",
+            "j$hhi<CR>",
+            "fn f() {\n    g(1, \n        2);\n}\n",
+        ),
+        (
+            "o.py",
+            "def f():\n    x = 1\n    y = 2\n",
+            "jo",
+            "def f():\n    x = 1\n    \n    y = 2\n",
+        ),
+        ("cr.py", "f(1, 2)\n", "$hi<CR>", "f(1, \n    2)\n"),
+    ] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, text)?;
+        client.with(move |editor| editor.open(path, OpenFlags::empty())).await?.await?;
+        let actual = client
+            .with(move |editor| {
+                editor.input(keys).unwrap();
+                editor.text(zi::Active).to_string()
+            })
+            .await;
+        assert_eq!(actual, expected, "{name}");
+        client.with(|editor| editor.input("<ESC>").unwrap()).await;
+    }
+    Ok(())
+}
+
 async fn snapshot_path(name: &'static str, path: impl AsRef<Path>) -> anyhow::Result<()> {
     let path = path.as_ref().to_path_buf();
     snapshot(name, |client| async move {
@@ -100,6 +219,13 @@ where
         editor.run(futures_util::stream::empty(), tasks, |_editor| Ok(())).await.unwrap()
     });
     f(client.clone()).await?;
+    // The syntax is otherwise parsed in the background and the first render has no highlights.
+    client
+        .with(|editor| {
+            let buf = editor.view(zi::Active).buffer();
+            editor[buf].reparse();
+        })
+        .await;
 
     let bytes = client
         .with(move |editor| {
@@ -134,9 +260,166 @@ where
     if std::env::var("UPDATE_EXPECT").is_ok() {
         cast.write_to(File::create(&path)?)?;
     } else {
+        // Compare what's on screen rather than the bytes so that the snapshots don't depend on
+        // the exact escape sequences used to draw it.
         let existing = Asciicast::read_from(BufReader::new(File::open(&path)?))?;
-        assert_eq!(existing, cast);
+        let (expected, actual) = (existing.screen(), cast.screen());
+        assert_eq!(expected, actual, "expected:\n{expected}\nactual:\n{actual}");
     }
 
     Ok(())
 }
+
+#[test]
+fn detect_capabilities() {
+    let detect = |vars: &'static [(&str, &str)]| {
+        zi_term::capabilities::detect(|var| {
+            vars.iter().find(|(k, _)| *k == var).map(|(_, v)| v.to_string())
+        })
+    };
+
+    let caps = detect(&[("TERM", "xterm-kitty"), ("COLORTERM", "truecolor")]);
+    assert!(caps.truecolor && caps.synchronized_output && caps.osc52 && caps.cursor_shape);
+
+    let caps = detect(&[("TERM", "linux")]);
+    assert!(!caps.truecolor && !caps.osc52 && !caps.cursor_shape);
+
+    let caps = detect(&[("TERM", "screen-256color"), ("TMUX", "/tmp/tmux-1000/default,1,0")]);
+    assert!(caps.osc52 && !caps.synchronized_output);
+}
+
+#[test]
+fn record() -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let recorder = zi_term::record::Recorder::default();
+    let mut out = recorder.tee(vec![]);
+    write!(out, "before")?;
+    out.flush()?;
+
+    let path = tempfile::NamedTempFile::new()?.into_temp_path();
+    recorder.start(&path, 80, 24)?;
+    write!(out, "a")?;
+    write!(out, "b")?;
+    out.flush()?;
+    recorder.resize(80, 24);
+    recorder.resize(100, 30);
+    write!(out, "c")?;
+    out.flush()?;
+    recorder.stop();
+    write!(out, "after")?;
+    out.flush()?;
+
+    let cast = Asciicast::read_from(BufReader::new(File::open(&path)?))?;
+    assert_eq!((cast.header().width, cast.header().height), (80, 24));
+    let events = cast.events().iter().map(|event| &event.kind).collect::<Vec<_>>();
+    assert_eq!(events, [
+        &asciicast::EventKind::Output("ab".into()),
+        &asciicast::EventKind::Resize { width: 100, height: 30 },
+        &asciicast::EventKind::Output("c".into()),
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn remote() -> anyhow::Result<()> {
+    use std::os::unix::net::UnixStream;
+
+    use rmpv::Value;
+    use zi_term::remote::Message;
+
+    /// A peer that attaches and calls the editor like a remote terminal would.
+    struct Peer {
+        stream: UnixStream,
+        reader: BufReader<UnixStream>,
+        next_id: u32,
+        redraws: usize,
+        quit: bool,
+    }
+
+    impl Peer {
+        fn request(&mut self, method: &str, params: Vec<Value>) -> anyhow::Result<Value> {
+            let id = self.next_id;
+            self.next_id += 1;
+            Message::Request { id, method: method.into(), params }.write(&mut self.stream)?;
+            loop {
+                match self.next()? {
+                    Message::Response { id: i, error: Value::Nil, result } if i == id => {
+                        return Ok(result);
+                    }
+                    Message::Response { error, .. } => anyhow::bail!("{error}"),
+                    _ => {}
+                }
+            }
+        }
+
+        fn notify(&mut self, method: &str, params: Vec<Value>) -> anyhow::Result<()> {
+            Ok(Message::Notification { method: method.into(), params }.write(&mut self.stream)?)
+        }
+
+        fn next(&mut self) -> anyhow::Result<Message> {
+            let message = Message::read(&mut self.reader)?.expect("server closed the connection");
+            if let Message::Notification { method, params } = &message {
+                match (method.as_str(), &params[..]) {
+                    ("redraw", [Value::Binary(output)]) => {
+                        assert!(!output.is_empty());
+                        self.redraws += 1;
+                    }
+                    ("quit", []) => self.quit = true,
+                    _ => panic!("unexpected notification: {message:?}"),
+                }
+            }
+            Ok(message)
+        }
+    }
+
+    let dir = tempfile::tempdir()?;
+    let socket = dir.path().join("zi.sock");
+    let (mut editor, tasks) = zi::Editor::new(zi::DummyBackend, zi::Size::new(80, 24));
+    let server = tokio::spawn({
+        let socket = socket.clone();
+        async move { zi_term::remote::serve(&mut editor, tasks, &socket).await }
+    });
+
+    let peer = tokio::task::spawn_blocking({
+        let socket = socket.clone();
+        move || -> anyhow::Result<()> {
+            let stream = loop {
+                match UnixStream::connect(&socket) {
+                    Ok(stream) => break stream,
+                    Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+                }
+            };
+
+            let reader = BufReader::new(stream.try_clone()?);
+            let mut peer = Peer { stream, reader, next_id: 0, redraws: 0, quit: false };
+
+            peer.request("input", vec!["ihello<Esc>".into()])?;
+            assert_eq!(peer.request("mode", vec![])?, Value::from("normal"));
+            assert_eq!(peer.request("eval", vec!["getline('.')".into()])?, Value::from("hello"));
+            assert!(peer.request("input", vec!["<NotAKey>".into()]).is_err());
+
+            peer.request("attach", vec![20.into(), 5.into()])?;
+            while peer.redraws == 0 {
+                peer.next()?;
+            }
+
+            // Input from the attached terminal is handled in order with requests.
+            peer.notify("key", vec!["A".into()])?;
+            peer.notify("paste", vec!["!".into()])?;
+            peer.notify("key", vec!["<Esc>".into()])?;
+            assert_eq!(peer.request("eval", vec!["getline('.')".into()])?, Value::from("hello!"));
+
+            peer.request("execute", vec!["qa!".into()])?;
+            while !peer.quit {
+                peer.next()?;
+            }
+            Ok(())
+        }
+    });
+
+    peer.await??;
+    server.await??;
+    assert!(!socket.exists());
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+
+use expect_test::Expect;
+use stdx::bomb::DropBomb;
+use tokio::sync::mpsc;
+use zi::input::{Event, KeySequence};
+
+/// A fake terminal that drives the editor through its event stream, as a frontend does.
+/// Time is paused once the driver is created so delays are simulated rather than slept, which
+/// makes timeouts (e.g. `timeoutlen`) and debounced features deterministic. Paused time only
+/// advances with [`Driver::wait`] or when the runtime has nothing else to do.
+/// This requires the current thread runtime, the default for `#[tokio::test]`.
+pub struct Driver {
+    size: zi::Size,
+    client: zi::Client,
+    events: mpsc::UnboundedSender<io::Result<Event>>,
+    frames: Vec<(String, String)>,
+    handle: Option<tokio::task::JoinHandle<()>>,
+    bomb: DropBomb,
+}
+
+impl Driver {
+    pub(crate) fn new(size: zi::Size, mut editor: zi::Editor, tasks: zi::Tasks) -> Self {
+        tokio::time::pause();
+
+        let (events, mut rx) = mpsc::unbounded_channel();
+        let client = editor.client();
+        let handle = tokio::spawn(async move {
+            let events = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+            editor.run(events, tasks, |_editor| Ok(())).await.unwrap()
+        });
+
+        Self {
+            size,
+            client,
+            events,
+            frames: vec![],
+            handle: Some(handle),
+            bomb: DropBomb::new("call `cleanup().await`"),
+        }
+    }
+
+    pub async fn with<R>(&self, f: impl FnOnce(&mut zi::Editor) -> R + Send + 'static) -> R
+    where
+        R: Send + 'static,
+    {
+        self.client.with(f).await
+    }
+
+    /// Type the keys, e.g. `ihello<Esc>`, and wait for the editor to handle them.
+    pub async fn input(&self, keys: &str) {
+        let keys = KeySequence::from_str(keys).expect("invalid key sequence");
+        for key in keys {
+            self.send(Event::Key(key));
+        }
+        self.settle().await;
+    }
+
+    /// Paste the text as the terminal does with bracketed paste.
+    pub async fn paste(&self, text: &str) {
+        self.send(Event::Paste(text.to_owned()));
+        self.settle().await;
+    }
+
+    /// Resize the terminal, later frames are rendered at the new size.
+    pub async fn resize(&mut self, width: u16, height: u16) {
+        self.size = zi::Size::new(width, height);
+        self.send(Event::Resize(width, height));
+        self.settle().await;
+    }
+
+    /// Advance the paused clock by `duration`, running the timers that are due.
+    pub async fn wait(&self, duration: Duration) {
+        tokio::time::advance(duration).await;
+        self.settle().await;
+    }
+
+    /// Render the current frame, see [`crate::TestContext::snapshot`] for the format.
+    pub async fn frame(&self) -> String {
+        let size = self.size;
+        self.client.with(move |editor| crate::render_frame(editor, size)).await
+    }
+
+    /// Render the current frame and keep it under `label` for [`Driver::assert_frames`].
+    pub async fn capture(&mut self, label: impl Into<String>) {
+        let frame = self.frame().await;
+        self.frames.push((label.into(), frame));
+    }
+
+    /// The captured frames in the order they were captured.
+    pub fn frames(&self) -> &[(String, String)] {
+        &self.frames
+    }
+
+    pub async fn snapshot(&self, expect: Expect) {
+        expect.assert_eq(&self.frame().await);
+    }
+
+    /// Assert on all the captured frames at once, each preceded by its label.
+    pub fn assert_frames(&self, expect: Expect) {
+        let frames = self
+            .frames
+            .iter()
+            .map(|(label, frame)| format!("-- {label} --\n{frame}"))
+            .collect::<String>();
+        expect.assert_eq(&frames);
+    }
+
+    pub async fn cleanup(mut self) {
+        self.bomb.defuse();
+        let handle = self.handle.take().unwrap();
+        handle.abort();
+
+        if let Err(err) = handle.await {
+            if err.is_panic() {
+                panic!("editor panicked: {err}");
+            }
+        }
+    }
+
+    fn send(&self, event: Event) {
+        self.events.send(Ok(event)).expect("editor stopped");
+    }
+
+    /// Wait for the editor to handle everything sent so far. Events and due timers are handled
+    /// before requests, so a round trip is enough.
+    async fn settle(&self) {
+        self.client.with(|_editor| ()).await
+    }
+}
@@ -1,5 +1,7 @@
 //! Testing utilities for zi
 
+mod driver;
+
 use std::future::IntoFuture;
 use std::io;
 use std::path::PathBuf;
@@ -12,6 +14,8 @@ use tui::backend::{Backend as _, TestBackend};
 use unicode_width::UnicodeWidthStr;
 use zi::Result;
 
+pub use self::driver::Driver;
+
 pub struct TestContext {
     size: zi::Size,
     client: zi::Client,
@@ -39,16 +43,8 @@ impl TestContext {
 
     pub async fn snapshot(&self, expect: Expect) {
         let size = self.size;
-        // Have to render twice to get updated output for certain events.
-        self.render().await;
-
-        self.client
-            .with(move |editor| {
-                let mut term = Terminal::new(TestBackend::new(size.width, size.height)).unwrap();
-                term.draw(|frame| editor.render(frame)).unwrap();
-                expect.assert_eq(&render(term.backend_mut()))
-            })
-            .await;
+        let frame = self.client.with(move |editor| render_frame(editor, size)).await;
+        expect.assert_eq(&frame)
     }
 
     pub async fn render(&self) {
@@ -136,6 +132,21 @@ impl TestContextBuilder {
         self.size = size.into();
         self
     }
+
+    /// Drive the editor with a fake terminal instead, see [`Driver`].
+    pub fn driver(self) -> Driver {
+        let (editor, tasks) = self.editor();
+        Driver::new(self.size, editor, tasks)
+    }
+
+    fn editor(&self) -> (zi::Editor, zi::Tasks) {
+        let (mut editor, tasks) = zi::Editor::new(zi::DummyBackend, self.size);
+        editor.set_mode(zi::Mode::Insert);
+        editor.edit(zi::Active, &zi::Deltas::insert_at(0, &self.scratch_content)).unwrap();
+        editor.set_cursor(zi::Active, self.scratch_content.len());
+        editor.set_mode(zi::Mode::Normal);
+        (editor, tasks)
+    }
 }
 
 impl IntoFuture for TestContextBuilder {
@@ -144,11 +155,7 @@ impl IntoFuture for TestContextBuilder {
 
     fn into_future(self) -> Self::IntoFuture {
         Box::pin(async move {
-            let (mut editor, tasks) = zi::Editor::new(zi::DummyBackend, self.size);
-            editor.set_mode(zi::Mode::Insert);
-            editor.edit(zi::Active, &zi::Deltas::insert_at(0, &self.scratch_content)).unwrap();
-            editor.set_cursor(zi::Active, self.scratch_content.len());
-            editor.set_mode(zi::Mode::Normal);
+            let (mut editor, tasks) = self.editor();
 
             let client = editor.client();
             let handle = tokio::spawn(async move {
@@ -165,6 +172,15 @@ impl IntoFuture for TestContextBuilder {
     }
 }
 
+fn render_frame(editor: &mut zi::Editor, size: zi::Size) -> String {
+    // Have to render twice to get updated output for certain events.
+    editor.render(&mut tui::TestFrame::new(size.width, size.height));
+
+    let mut term = Terminal::new(TestBackend::new(size.width, size.height)).unwrap();
+    term.draw(|frame| editor.render(frame)).unwrap();
+    render(term.backend_mut())
+}
+
 /// Copied from ratatui's `buffer_view`, but draws the cursor too.
 fn render(backend: &mut TestBackend) -> String {
     use std::fmt::Write as _;
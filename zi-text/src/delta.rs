@@ -129,6 +129,189 @@ impl<'a> Deltas<'a> {
     pub fn to_owned(&self) -> Deltas<'static> {
         Deltas::new(self.deltas.iter().map(|d| d.to_owned()))
     }
+
+    /// Compose two sets of deltas into one, where `b` is expressed in terms of the text after `a`
+    /// is applied. Applying the result is equivalent to applying `a` and then `b`.
+    pub fn compose(a: &Deltas<'_>, b: &Deltas<'_>) -> Deltas<'static> {
+        let (mut a, mut b) = (Ops::new(a), Ops::new(b));
+        let mut out = DeltasBuilder::default();
+        while !(a.is_done() && b.is_done()) {
+            match (a.peek(), b.peek()) {
+                (_, Op::Insert(text)) => {
+                    out.insert(text);
+                    b.take(text.len());
+                }
+                (Op::Delete(n), _) => {
+                    out.delete(n);
+                    a.take(n);
+                }
+                (x, y) => {
+                    let n = x.len().min(y.len());
+                    match (a.take(n), b.take(n)) {
+                        (Op::Retain(_), Op::Retain(_)) => out.retain(n),
+                        (Op::Retain(_), Op::Delete(_)) => out.delete(n),
+                        (Op::Insert(text), Op::Retain(_)) => out.insert(text),
+                        // Text inserted by `a` is deleted by `b`.
+                        (Op::Insert(_), Op::Delete(_)) => {}
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        }
+        out.finish()
+    }
+
+    /// Transform two sets of deltas that apply to the same text against each other.
+    /// Returns `(a', b')` where `a'` is `a` rebased over `b` and `b'` is `b` rebased over `a`, such
+    /// that applying `a` then `b'` results in the same text as applying `b` then `a'`.
+    /// Text inserted by both at the same position is ordered with `a`'s insertion first.
+    pub fn transform(a: &Deltas<'_>, b: &Deltas<'_>) -> (Deltas<'static>, Deltas<'static>) {
+        let (mut a, mut b) = (Ops::new(a), Ops::new(b));
+        let (mut a_out, mut b_out) = (DeltasBuilder::default(), DeltasBuilder::default());
+        while !(a.is_done() && b.is_done()) {
+            match (a.peek(), b.peek()) {
+                (Op::Insert(text), _) => {
+                    a_out.insert(text);
+                    b_out.retain(text.len());
+                    a.take(text.len());
+                }
+                (_, Op::Insert(text)) => {
+                    a_out.retain(text.len());
+                    b_out.insert(text);
+                    b.take(text.len());
+                }
+                (x, y) => {
+                    let n = x.len().min(y.len());
+                    match (a.take(n), b.take(n)) {
+                        (Op::Retain(_), Op::Retain(_)) => {
+                            a_out.retain(n);
+                            b_out.retain(n);
+                        }
+                        (Op::Delete(_), Op::Retain(_)) => a_out.delete(n),
+                        (Op::Retain(_), Op::Delete(_)) => b_out.delete(n),
+                        // Both delete the same text.
+                        (Op::Delete(_), Op::Delete(_)) => {}
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        }
+        (a_out.finish(), b_out.finish())
+    }
+}
+
+/// A set of deltas as a sequence of operations over the text from start to end.
+#[derive(Debug, Clone, Copy)]
+enum Op<'a> {
+    Retain(usize),
+    Delete(usize),
+    Insert(&'a str),
+}
+
+impl Op<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Op::Retain(n) | Op::Delete(n) => *n,
+            Op::Insert(text) => text.len(),
+        }
+    }
+}
+
+/// A cursor over the operations of a set of deltas.
+/// The rest of the text is retained once the operations are exhausted.
+struct Ops<'a> {
+    ops: std::vec::IntoIter<Op<'a>>,
+    head: Option<Op<'a>>,
+}
+
+impl<'a> Ops<'a> {
+    fn new(deltas: &'a Deltas<'_>) -> Self {
+        let mut ops = vec![];
+        let mut pos = 0;
+        for delta in deltas.deltas.iter() {
+            let range = delta.range();
+            if range.start > pos {
+                ops.push(Op::Retain(range.start - pos));
+            }
+
+            if !range.is_empty() {
+                ops.push(Op::Delete(range.len()));
+            }
+
+            if !delta.text().is_empty() {
+                ops.push(Op::Insert(delta.text()));
+            }
+            pos = range.end;
+        }
+
+        let mut ops = ops.into_iter();
+        Self { head: ops.next(), ops }
+    }
+
+    fn is_done(&self) -> bool {
+        self.head.is_none()
+    }
+
+    fn peek(&self) -> Op<'a> {
+        self.head.unwrap_or(Op::Retain(usize::MAX))
+    }
+
+    /// Consume `n` bytes of the current operation, `n` must be at most its length.
+    fn take(&mut self, n: usize) -> Op<'a> {
+        let Some(head) = self.head else { return Op::Retain(n) };
+        assert!(n <= head.len());
+        let (taken, rest) = match head {
+            Op::Retain(m) => (Op::Retain(n), Op::Retain(m - n)),
+            Op::Delete(m) => (Op::Delete(n), Op::Delete(m - n)),
+            Op::Insert(text) => {
+                let (taken, rest) = text.split_at(n);
+                (Op::Insert(taken), Op::Insert(rest))
+            }
+        };
+
+        self.head = if rest.len() == 0 { self.ops.next() } else { Some(rest) };
+        taken
+    }
+}
+
+/// Builds a set of deltas from a sequence of operations, merging adjacent deletions and
+/// insertions into a single delta.
+#[derive(Default)]
+struct DeltasBuilder {
+    deltas: Vec<Delta<'static>>,
+    pending: Option<(DeltaRange, String)>,
+    pos: usize,
+}
+
+impl DeltasBuilder {
+    fn retain(&mut self, n: usize) {
+        if n > 0 {
+            self.flush();
+            self.pos += n;
+        }
+    }
+
+    fn delete(&mut self, n: usize) {
+        let pos = self.pos;
+        self.pending.get_or_insert_with(|| (pos..pos, String::new())).0.end += n;
+        self.pos += n;
+    }
+
+    fn insert(&mut self, text: &str) {
+        let pos = self.pos;
+        self.pending.get_or_insert_with(|| (pos..pos, String::new())).1.push_str(text);
+    }
+
+    fn flush(&mut self) {
+        if let Some((range, text)) = self.pending.take() {
+            self.deltas.push(Delta::new(range, text));
+        }
+    }
+
+    fn finish(mut self) -> Deltas<'static> {
+        self.flush();
+        Deltas::new(self.deltas)
+    }
 }
 
 #[derive(Clone)]
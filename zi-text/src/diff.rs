@@ -0,0 +1,189 @@
+use std::iter;
+use std::ops::Range;
+
+use crate::{AnyText, Delta, Deltas};
+
+/// Changes larger than this many characters are replaced as a whole rather than refined to the
+/// differing characters, as the Myers algorithm is quadratic in the worst case.
+const MAX_REFINE_CHARS: usize = 1 << 12;
+
+/// A maximal run of lines that differ between two texts, see [`diff_lines`].
+/// One of the ranges is empty for pure insertions and deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineDiff {
+    /// The 0-indexed lines of the old text.
+    pub old: Range<usize>,
+    /// The 0-indexed lines of the new text.
+    pub new: Range<usize>,
+}
+
+/// Compute a minimal line-level diff from `old` to `new` with the Myers algorithm.
+/// Lines include their line ending, so a missing trailing newline is a difference.
+pub fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new = new.split_inclusive('\n').collect::<Vec<_>>();
+
+    // Common prefixes and suffixes are cheap to strip and usually make up most of the text.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut diffs = vec![];
+    let (mut i, mut j) = (0, 0);
+    // The end acts as a final match to flush the last difference.
+    for (x, y) in matches(a, b).into_iter().chain([(a.len(), b.len())]) {
+        if x > i || y > j {
+            diffs.push(LineDiff { old: prefix + i..prefix + x, new: prefix + j..prefix + y });
+        }
+        (i, j) = (x + 1, y + 1);
+    }
+
+    diffs
+}
+
+/// Compute the deltas that turn `old` into `new`.
+/// The differing lines are found with [`diff_lines`] and then refined to the differing characters,
+/// so text that is unchanged within a changed line (and anything tracking it) is left alone.
+pub fn diff(old: &dyn AnyText, new: &dyn AnyText) -> Deltas<'static> {
+    let (old, new) = (old.to_string(), new.to_string());
+    let line_starts = |s: &str| {
+        iter::once(0)
+            .chain(s.split_inclusive('\n').scan(0, |end, line| {
+                *end += line.len();
+                Some(*end)
+            }))
+            .collect::<Vec<_>>()
+    };
+    let (old_starts, new_starts) = (line_starts(&old), line_starts(&new));
+
+    let mut deltas = vec![];
+    for LineDiff { old: old_lines, new: new_lines } in diff_lines(&old, &new) {
+        let old_range = old_starts[old_lines.start]..old_starts[old_lines.end];
+        let new_range = new_starts[new_lines.start]..new_starts[new_lines.end];
+        refine(old_range.start, &old[old_range], &new[new_range], &mut deltas);
+    }
+
+    Deltas::new(deltas)
+}
+
+/// Push the deltas replacing the characters that differ between `old`, which starts at byte
+/// `offset`, and `new`.
+fn refine(offset: usize, old: &str, new: &str, deltas: &mut Vec<Delta<'static>>) {
+    let (a, b) = (old.chars().collect::<Vec<_>>(), new.chars().collect::<Vec<_>>());
+    let matches = if a.len() + b.len() > MAX_REFINE_CHARS { vec![] } else { matches(&a, &b) };
+
+    // Byte offsets of each character and the end of the strings.
+    let bytes = |s: &str| s.char_indices().map(|(i, _)| i).chain([s.len()]).collect::<Vec<_>>();
+    let (old_bytes, new_bytes) = (bytes(old), bytes(new));
+
+    let (mut i, mut j) = (0, 0);
+    for (x, y) in matches.into_iter().chain([(a.len(), b.len())]) {
+        if x > i || y > j {
+            let range = offset + old_bytes[i]..offset + old_bytes[x];
+            deltas.push(Delta::new(range, new[new_bytes[j]..new_bytes[y]].to_owned()));
+        }
+        (i, j) = (x + 1, y + 1);
+    }
+}
+
+/// The pairs of indices of matching elements of a shortest edit script from `a` to `b`, in order.
+/// This is the linear space variant of the Myers algorithm: rather than keeping the furthest reaching
+/// paths of every step to backtrack, the problem is split at the middle of an optimal path recursively.
+fn matches<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let max = (a.len() + b.len()).div_ceil(2);
+    // `v[k + max + 1]` is the furthest `x` reached on diagonal `k`, forwards and backwards respectively.
+    let mut v = (vec![0; 2 * max + 3], vec![0; 2 * max + 3]);
+    let mut matches = vec![];
+    find_matches(a, b, (0, 0), &mut v, &mut matches);
+    matches
+}
+
+/// Push the matches of `a` and `b`, which start at indices `i` and `j`, to `matches`.
+fn find_matches<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    (i, j): (usize, usize),
+    v: &mut (Vec<isize>, Vec<isize>),
+    matches: &mut Vec<(usize, usize)>,
+) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    matches.extend((0..prefix).map(|n| (i + n, j + n)));
+    let (a, b, i, j) = (&a[prefix..], &b[prefix..], i + prefix, j + prefix);
+
+    let suffix = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    // Without a common prefix or suffix at least two edits are needed, so both halves are smaller.
+    if !a.is_empty() && !b.is_empty() {
+        let (x, y) = middle(a, b, v);
+        find_matches(&a[..x], &b[..y], (i, j), v, matches);
+        find_matches(&a[x..], &b[y..], (i + x, j + y), v, matches);
+    }
+
+    matches.extend((0..suffix).map(|n| (i + a.len() + n, j + b.len() + n)));
+}
+
+/// Find a point on a shortest edit script from `a` to `b` about halfway along it, by searching
+/// forwards from the start and backwards from the end until the furthest reaching paths overlap.
+fn middle<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    (fwd, bwd): &mut (Vec<isize>, Vec<isize>),
+) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let idx = |k: isize| (k + max + 1) as usize;
+    fwd[idx(1)] = 0;
+    bwd[idx(1)] = 0;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && fwd[idx(k - 1)] < fwd[idx(k + 1)]) {
+                fwd[idx(k + 1)]
+            } else {
+                fwd[idx(k - 1)] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            fwd[idx(k)] = x;
+
+            // Diagonal `k` is diagonal `delta - k` when searching backwards.
+            if odd && (k - delta).abs() < d && x + bwd[idx(delta - k)] >= n {
+                return (x0 as usize, y0 as usize);
+            }
+        }
+
+        // Backwards, `x` and `y` count the elements from the ends.
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && bwd[idx(k - 1)] < bwd[idx(k + 1)]) {
+                bwd[idx(k + 1)]
+            } else {
+                bwd[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            bwd[idx(k)] = x;
+
+            if !odd && (k - delta).abs() <= d && x + fwd[idx(delta - k)] >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+
+    unreachable!("the searches must overlap by the time they have each done half the edits")
+}
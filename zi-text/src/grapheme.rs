@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{Text, TextSlice};
+
+/// The extended grapheme clusters of `s` along with their byte index.
+pub fn grapheme_indices(s: &str) -> impl DoubleEndedIterator<Item = (usize, &str)> {
+    s.grapheme_indices(true)
+}
+
+/// The number of cells the grapheme cluster occupies when displayed starting at the 0-indexed cell `col`.
+/// Tabs extend to the next multiple of `tab_width`, wide characters (e.g. CJK or emoji) occupy two cells.
+pub fn grapheme_width(grapheme: &str, col: usize, tab_width: u8) -> usize {
+    match grapheme {
+        "\t" => {
+            let tab_width = tab_width.max(1) as usize;
+            tab_width - col % tab_width
+        }
+        _ => grapheme.width(),
+    }
+}
+
+/// The number of cells `s` occupies when displayed starting at the 0-indexed cell `col`.
+pub fn display_width(s: &str, col: usize, tab_width: u8) -> usize {
+    grapheme_indices(s).fold(col, |col, (_, g)| col + grapheme_width(g, col, tab_width)) - col
+}
+
+/// An iterator over the segments of text a line at a time, see [`Text::graphemes`] and
+/// [`Text::words`]. Segments borrow from the line if possible.
+pub(crate) struct Segments<'a, I> {
+    lines: I,
+    /// The current line and the byte index it starts at.
+    line: Cow<'a, str>,
+    start: usize,
+    /// The byte index of the next segment within the line.
+    pos: usize,
+    next_segment: fn(&str) -> Option<&str>,
+}
+
+impl<'a, I> Segments<'a, I>
+where
+    I: Iterator<Item = (usize, Cow<'a, str>)>,
+{
+    pub(crate) fn graphemes(lines: I) -> Self {
+        Self::new(lines, |s| UnicodeSegmentation::graphemes(s, true).next())
+    }
+
+    /// The words are the segments between unicode word boundaries that contain an alphanumeric
+    /// character, punctuation and whitespace are skipped.
+    pub(crate) fn words(lines: I) -> impl Iterator<Item = (usize, Cow<'a, str>)> {
+        Self::new(lines, |s| s.split_word_bounds().next())
+            .filter(|(_, w)| w.chars().any(char::is_alphanumeric))
+    }
+
+    fn new(lines: I, next_segment: fn(&str) -> Option<&str>) -> Self {
+        Self { lines, line: Cow::Borrowed(""), start: 0, pos: 0, next_segment }
+    }
+}
+
+impl<'a, I> Iterator for Segments<'a, I>
+where
+    I: Iterator<Item = (usize, Cow<'a, str>)>,
+{
+    type Item = (usize, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(segment) = (self.next_segment)(&self.line[self.pos..]) {
+                let range = self.pos..self.pos + segment.len();
+                self.pos = range.end;
+                let segment = match self.line {
+                    Cow::Borrowed(line) => Cow::Borrowed(&line[range.clone()]),
+                    Cow::Owned(ref line) => Cow::Owned(line[range.clone()].to_owned()),
+                };
+                return Some((self.start + range.start, segment));
+            }
+
+            (self.start, self.line) = self.lines.next()?;
+            self.pos = 0;
+        }
+    }
+}
+
+/// The lines of `text` including their line endings and the byte index they start at.
+pub(crate) fn lines<'a, S: TextSlice<'a> + 'a>(
+    text: S,
+) -> impl Iterator<Item = (usize, Cow<'a, str>)> + 'a {
+    (0..text.len_lines()).filter_map(move |line| {
+        Some((text.try_line_to_byte(line)?, text.line_with_ending(line)?.to_cow()))
+    })
+}
+
+pub(crate) fn next_boundary<T: Text + ?Sized>(text: &T, byte_idx: usize) -> usize {
+    let (start, line) = line_at(text, byte_idx);
+    line.graphemes()
+        .map(|(i, g)| start + i + g.len())
+        .find(|&end| end > byte_idx)
+        .unwrap_or(byte_idx)
+}
+
+pub(crate) fn prev_boundary<T: Text + ?Sized>(text: &T, byte_idx: usize) -> usize {
+    let Some(prev) = byte_idx.checked_sub(1) else { return 0 };
+    // The cluster before the start of a line is the line ending of the line before it.
+    let (start, line) = line_at(text, prev);
+    line.graphemes()
+        .map(|(i, _)| start + i)
+        .take_while(|&i| i < byte_idx)
+        .last()
+        .unwrap_or(byte_idx)
+}
+
+/// The line containing `byte_idx` including its line ending and the byte index it starts at.
+/// Grapheme clusters never span lines (`\r\n` is a cluster of its own).
+fn line_at<T: Text + ?Sized>(text: &T, byte_idx: usize) -> (usize, T::Slice<'_>) {
+    let line = text.byte_to_line(byte_idx);
+    let start = text.line_to_byte(line);
+    let end = text.try_line_to_byte(line + 1).unwrap_or_else(|| text.len_bytes());
+    (start, text.byte_slice(start..end))
+}
@@ -0,0 +1,81 @@
+//! A polynomial hash of the contents of a text.
+//! Comparing hashes is constant time, but computing one is linear in the length of the text as
+//! the rope keeps no summaries of its chunks, so owners should only hash again when compared after
+//! an edit rather than on every edit.
+
+use std::ops::Range;
+
+use crate::{Text, TextSlice};
+
+/// The Mersenne prime `2^61 - 1`.
+const P: u64 = (1 << 61) - 1;
+const BASE: u64 = 0x1f3d_5b79_2c4a_6e81 % P;
+
+/// A hash of the contents of a text, `sum(byte[i] * BASE^i) mod P`.
+/// Equal texts have equal hashes. Different texts have equal hashes with negligible probability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash {
+    hash: u64,
+    len: usize,
+}
+
+impl ContentHash {
+    /// Hash the text, this is linear in the length of the text.
+    pub fn new<T: Text + ?Sized>(text: &T) -> Self {
+        Self { hash: hash_range(text, 0..text.len_bytes()), len: text.len_bytes() }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The contribution of the bytes in `range` to the hash of `text`.
+fn hash_range<T: Text + ?Sized>(text: &T, range: Range<usize>) -> u64 {
+    let mut offset = range.start;
+    let mut hash = 0;
+    for chunk in text.byte_slice(range).chunks() {
+        hash = add(hash, hash_str(chunk, offset));
+        offset += chunk.len();
+    }
+    hash
+}
+
+/// The contribution of `s` to the hash of a text if it starts at byte `offset`.
+fn hash_str(s: &str, offset: usize) -> u64 {
+    let mut power = pow(BASE, offset as u64);
+    let mut hash = 0;
+    for &byte in s.as_bytes() {
+        // Offset by one so that zero bytes contribute to the hash.
+        hash = add(hash, mul(byte as u64 + 1, power));
+        power = mul(power, BASE);
+    }
+    hash
+}
+
+fn add(a: u64, b: u64) -> u64 {
+    (a + b) % P
+}
+
+fn mul(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % P as u128) as u64
+}
+
+/// `base^exp mod P`.
+fn pow(mut base: u64, mut exp: u64) -> u64 {
+    let mut acc = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = mul(acc, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    acc
+}
@@ -3,9 +3,14 @@
 mod cow_str_impl;
 mod cursor;
 mod delta;
+mod diff;
 mod ext;
+mod grapheme;
+mod hash;
+mod line_ending;
 mod readonly;
 mod rope;
+pub mod search;
 mod str_impl;
 
 use std::any::Any;
@@ -20,7 +25,13 @@ use dyn_clone::DynClone;
 use zi_core::{Line, Point, PointOrByte, PointRange};
 
 pub use self::delta::{Delta, DeltaRange, Deltas};
+pub use self::diff::{LineDiff, diff, diff_lines};
 pub use self::ext::*;
+pub use self::grapheme::{display_width, grapheme_indices, grapheme_width};
+pub use self::hash::ContentHash;
+pub use self::line_ending::{
+    LineEnding, LineEndingNormalizer, LineEndingReader, ParseLineEndingError,
+};
 pub use self::readonly::ReadonlyText;
 
 /// Text that can be modified.
@@ -462,7 +473,7 @@ where
 
 /// Similar to [`Text`] except the returned lifetimes are tied to `'a` instead of `'self`.
 pub trait TextSlice<'a>: TextBase + Sized {
-    type Slice: TextSlice<'a>;
+    type Slice: TextSlice<'a> + 'a;
 
     /// There are some rust limitations/bugs when using `impl Trait` returns. We need to ensure the
     /// returned iterators are not tied to the lifetime of self. `impl Trait` currently seems to
@@ -503,6 +514,37 @@ pub trait TextSlice<'a>: TextBase + Sized {
         TextReader::new(self.chunks())
     }
 
+    /// Returns the line at the given index including the newline character(s).
+    #[inline]
+    fn line_with_ending(&self, line_idx: usize) -> Option<Self::Slice> {
+        let start = self.try_line_to_byte(line_idx)?;
+        let end = self.try_line_to_byte(line_idx + 1).unwrap_or_else(|| self.len_bytes());
+        Some(self.byte_slice(start..end))
+    }
+
+    /// Returns an iterator over the extended grapheme clusters of the slice and their byte index.
+    /// The slice is segmented a line at a time as clusters never span lines. Clusters are only
+    /// copied if their line is split across chunks.
+    fn graphemes(&self) -> impl Iterator<Item = (usize, Cow<'a, str>)> + 'a {
+        grapheme::Segments::graphemes(grapheme::lines(self.byte_slice(..)))
+    }
+
+    /// Returns an iterator over the words of the slice and their byte index, as defined by the
+    /// unicode word boundaries. Punctuation and whitespace are skipped.
+    fn words(&self) -> impl Iterator<Item = (usize, Cow<'a, str>)> + 'a {
+        grapheme::Segments::words(grapheme::lines(self.byte_slice(..)))
+    }
+
+    /// Returns the 0-indexed display column of the byte within its line.
+    /// Tabs extend to the next multiple of `tab_width`, wide characters occupy two cells.
+    #[inline]
+    fn byte_to_display_col(&self, byte_idx: usize, tab_width: u8) -> usize {
+        let start = self.line_to_byte(self.byte_to_line(byte_idx));
+        self.byte_slice(start..byte_idx)
+            .graphemes()
+            .fold(0, |col, (_, g)| col + grapheme_width(&g, col, tab_width))
+    }
+
     fn annotate<T: Copy>(
         &self,
         highlights: impl IntoIterator<Item = (PointRange, T)> + 'a,
@@ -515,7 +557,7 @@ pub trait TextSlice<'a>: TextBase + Sized {
 }
 
 pub trait Text: TextBase {
-    type Slice<'a>: TextSlice<'a>
+    type Slice<'a>: TextSlice<'a> + 'a
     where
         Self: 'a;
 
@@ -563,6 +605,48 @@ pub trait Text: TextBase {
         self.char_before_byte(self.point_to_byte(point))
     }
 
+    /// Returns the byte index of the end of the grapheme cluster starting at `byte_idx`,
+    /// or `byte_idx` if it is the end of the text.
+    #[inline]
+    fn next_grapheme_boundary(&self, byte_idx: usize) -> usize {
+        grapheme::next_boundary(self, byte_idx)
+    }
+
+    /// Returns the byte index of the start of the grapheme cluster ending at `byte_idx`,
+    /// or `0` if it is the start of the text.
+    #[inline]
+    fn prev_grapheme_boundary(&self, byte_idx: usize) -> usize {
+        grapheme::prev_boundary(self, byte_idx)
+    }
+
+    /// Returns the line at the given index including the newline character(s).
+    #[inline]
+    fn line_with_ending(&self, line_idx: usize) -> Option<Self::Slice<'_>> {
+        let start = self.try_line_to_byte(line_idx)?;
+        let end = self.try_line_to_byte(line_idx + 1).unwrap_or_else(|| self.len_bytes());
+        Some(self.byte_slice(start..end))
+    }
+
+    /// Returns an iterator over the extended grapheme clusters of the text and their byte index.
+    /// The text is segmented a line at a time as clusters never span lines. Clusters are only
+    /// copied if their line is split across chunks.
+    fn graphemes(&self) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+        grapheme::Segments::graphemes(grapheme::lines(self.byte_slice(..)))
+    }
+
+    /// Returns an iterator over the words of the text and their byte index, as defined by the
+    /// unicode word boundaries. Punctuation and whitespace are skipped.
+    fn words(&self) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+        grapheme::Segments::words(grapheme::lines(self.byte_slice(..)))
+    }
+
+    /// Returns the 0-indexed display column of the byte within its line.
+    /// Tabs extend to the next multiple of `tab_width`, wide characters occupy two cells.
+    #[inline]
+    fn byte_to_display_col(&self, byte_idx: usize, tab_width: u8) -> usize {
+        self.byte_slice(..).byte_to_display_col(byte_idx, tab_width)
+    }
+
     /// Returns the byte index of the first non-whitespace character on the line.
     #[inline]
     fn indent(&self) -> usize {
@@ -0,0 +1,208 @@
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::{fmt, mem};
+
+/// The line ending convention of a file.
+/// Text is always stored internally with `\n` line endings, this is only used to convert at the boundaries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The vim `fileformat` name of the line ending.
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
+            LineEnding::Cr => "mac",
+        }
+    }
+
+    /// Detect the line ending convention of `s` based on the first line ending found.
+    pub fn detect(s: &str) -> Option<Self> {
+        let mut normalizer = LineEndingNormalizer::default();
+        normalizer.normalize(s, |_| {});
+        normalizer.finish()
+    }
+
+    /// Wrap a reader of `\n` terminated text to produce text with this line ending.
+    pub fn reader<R: Read>(self, reader: R) -> LineEndingReader<R> {
+        LineEndingReader { inner: reader, line_ending: self, pending: &[] }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLineEndingError(String);
+
+impl fmt::Display for ParseLineEndingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown line ending: {} (expected `unix`, `dos`, or `mac`)", self.0)
+    }
+}
+
+impl std::error::Error for ParseLineEndingError {}
+
+impl FromStr for LineEnding {
+    type Err = ParseLineEndingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unix" | "lf" => Ok(LineEnding::Lf),
+            "dos" | "crlf" => Ok(LineEnding::CrLf),
+            "mac" | "cr" => Ok(LineEnding::Cr),
+            _ => Err(ParseLineEndingError(s.to_string())),
+        }
+    }
+}
+
+/// Converts streamed text with arbitrary line endings to `\n` line endings,
+/// detecting the convention used along the way.
+/// A `\r\n` split across chunks is handled correctly.
+#[derive(Debug, Default)]
+pub struct LineEndingNormalizer {
+    pending_cr: bool,
+    detected: Option<LineEnding>,
+}
+
+impl LineEndingNormalizer {
+    /// Normalize the next chunk of text, passing the normalized pieces to `f`.
+    pub fn normalize(&mut self, mut s: &str, mut f: impl FnMut(&str)) {
+        if mem::take(&mut self.pending_cr) {
+            match s.strip_prefix('\n') {
+                Some(rest) => {
+                    self.detected.get_or_insert(LineEnding::CrLf);
+                    s = rest;
+                }
+                None => {
+                    self.detected.get_or_insert(LineEnding::Cr);
+                }
+            }
+        }
+
+        if !s.contains('\r') {
+            if self.detected.is_none() && s.contains('\n') {
+                self.detected = Some(LineEnding::Lf);
+            }
+            f(s);
+            return;
+        }
+
+        while let Some(i) = s.find(['\r', '\n']) {
+            if s.as_bytes()[i] == b'\n' {
+                self.detected.get_or_insert(LineEnding::Lf);
+                f(&s[..=i]);
+                s = &s[i + 1..];
+                continue;
+            }
+
+            f(&s[..i]);
+            f("\n");
+            match s.as_bytes().get(i + 1) {
+                Some(b'\n') => {
+                    self.detected.get_or_insert(LineEnding::CrLf);
+                    s = &s[i + 2..];
+                }
+                Some(_) => {
+                    self.detected.get_or_insert(LineEnding::Cr);
+                    s = &s[i + 1..];
+                }
+                None => {
+                    // The `\n` of a `\r\n` may be in the next chunk.
+                    self.pending_cr = true;
+                    s = "";
+                }
+            }
+        }
+
+        f(s);
+    }
+
+    /// Signal the end of the input, returning the detected line ending (if any line ending was found).
+    pub fn finish(mut self) -> Option<LineEnding> {
+        if self.pending_cr {
+            self.detected.get_or_insert(LineEnding::Cr);
+        }
+        self.detected
+    }
+}
+
+/// A reader that converts the `\n` line endings of the inner reader to a given line ending.
+/// See [`LineEnding::reader`].
+pub struct LineEndingReader<R> {
+    inner: R,
+    line_ending: LineEnding,
+    /// Bytes of an expanded line ending that did not fit in the previous read.
+    pending: &'static [u8],
+}
+
+impl<R: Read> Read for LineEndingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if !self.pending.is_empty() {
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending = &self.pending[n..];
+            return Ok(n);
+        }
+
+        match self.line_ending {
+            LineEnding::Lf => self.inner.read(buf),
+            LineEnding::Cr => {
+                let n = self.inner.read(buf)?;
+                buf[..n].iter_mut().filter(|b| **b == b'\n').for_each(|b| *b = b'\r');
+                Ok(n)
+            }
+            LineEnding::CrLf => {
+                if buf.len() == 1 {
+                    let n = self.inner.read(buf)?;
+                    if n == 1 && buf[0] == b'\n' {
+                        buf[0] = b'\r';
+                        self.pending = b"\n";
+                    }
+                    return Ok(n);
+                }
+
+                // Only read into half the buffer so there is always room to expand every `\n`.
+                let half = buf.len() / 2;
+                let n = self.inner.read(&mut buf[..half])?;
+                let k = buf[..n].iter().filter(|&&b| b == b'\n').count();
+                // Expand in place from the back.
+                let mut j = n + k;
+                for i in (0..n).rev() {
+                    let b = buf[i];
+                    j -= 1;
+                    buf[j] = b;
+                    if b == b'\n' {
+                        j -= 1;
+                        buf[j] = b'\r';
+                    }
+                }
+                debug_assert_eq!(j, 0);
+                Ok(n + k)
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Regex search over any [`Text`].
+
+use std::ops::Range;
+
+pub use regex_cursor::engines::meta::Regex;
+use regex_cursor::{Cursor, Input};
+use stdx::sync::Cancel;
+
+use crate::{RopeCursor, Text, TextSlice};
+
+/// The number of lines a backward search scans at a time.
+pub const BACKWARD_CHUNK_LINES: usize = 256;
+
+/// A regex search that can be run over any text.
+/// All searches stop early once the search is cancelled.
+#[derive(Debug, Clone)]
+pub struct Search {
+    regex: Regex,
+    cancel: Cancel,
+}
+
+impl Search {
+    pub fn new(regex: Regex) -> Self {
+        Self { regex, cancel: Cancel::new() }
+    }
+
+    /// Stop searching once `cancel` is cancelled.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    #[inline]
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Returns an iterator over the non-overlapping matches that start at or after `byte_idx`
+    /// ordered by their start ascending.
+    pub fn forward<'a, T: Text + ?Sized>(
+        &'a self,
+        text: &'a T,
+        byte_idx: usize,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.find_iter(text, byte_idx..text.len_bytes())
+    }
+
+    /// Returns an iterator over the matches that start before `byte_idx` ordered by their start
+    /// descending. The text is scanned backwards [`BACKWARD_CHUNK_LINES`] lines at a time, so a
+    /// match can only extend that many lines past the chunk it starts in.
+    pub fn backward<'a, T: Text + ?Sized>(
+        &'a self,
+        text: &'a T,
+        byte_idx: usize,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        let chunks = 0..=text.byte_to_line(byte_idx) / BACKWARD_CHUNK_LINES;
+        chunks.rev().take_while(|_| !self.cancel.is_cancelled()).flat_map(move |chunk| {
+            let line = chunk * BACKWARD_CHUNK_LINES;
+            let start = text.line_to_byte(line);
+            let end = text
+                .try_line_to_byte(line + BACKWARD_CHUNK_LINES)
+                .map_or(byte_idx, |end| end.min(byte_idx));
+            let limit = text
+                .try_line_to_byte(line + 2 * BACKWARD_CHUNK_LINES)
+                .unwrap_or_else(|| text.len_bytes());
+            let mut matches = self
+                .find_iter(text, start..limit)
+                .take_while(|m| m.start < end)
+                .collect::<Vec<_>>();
+            matches.reverse();
+            matches
+        })
+    }
+
+    /// Returns the match that starts at `byte_idx` if there is one.
+    pub fn at<T: Text + ?Sized>(&self, text: &T, byte_idx: usize) -> Option<Range<usize>> {
+        // The leftmost match is the one starting at `byte_idx` if any match does.
+        self.forward(text, byte_idx).next().filter(|m| m.start == byte_idx)
+    }
+
+    /// Returns whether the regex matches anywhere in the slice.
+    pub fn is_match<'a>(&self, slice: impl TextSlice<'a>) -> bool {
+        self.regex.is_match(Input::new(self.cursor(slice))) && !self.cancel.is_cancelled()
+    }
+
+    fn find_iter<'a, T: Text + ?Sized>(
+        &'a self,
+        text: &'a T,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        let input = Input::new(self.cursor(text.byte_slice(..))).range(range);
+        // A cancelled search sees the end of the text early, so ignore anything it matched.
+        self.regex.find_iter(input).map(|m| m.range()).take_while(|_| !self.cancel.is_cancelled())
+    }
+
+    fn cursor<'a, S: TextSlice<'a>>(&self, slice: S) -> CancelCursor<RopeCursor<'a, S>> {
+        CancelCursor { cursor: RopeCursor::new(slice), cancel: self.cancel.clone() }
+    }
+}
+
+/// A cursor that ends early once cancelled.
+struct CancelCursor<C> {
+    cursor: C,
+    cancel: Cancel,
+}
+
+impl<C: Cursor> Cursor for CancelCursor<C> {
+    #[inline]
+    fn chunk(&self) -> &[u8] {
+        self.cursor.chunk()
+    }
+
+    #[inline]
+    fn utf8_aware(&self) -> bool {
+        self.cursor.utf8_aware()
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        !self.cancel.is_cancelled() && self.cursor.advance()
+    }
+
+    #[inline]
+    fn backtrack(&mut self) -> bool {
+        !self.cancel.is_cancelled() && self.cursor.backtrack()
+    }
+
+    #[inline]
+    fn total_bytes(&self) -> Option<usize> {
+        self.cursor.total_bytes()
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.cursor.offset()
+    }
+}
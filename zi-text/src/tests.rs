@@ -65,3 +65,145 @@ proptest! {
         assert_eq!(s, original, "applying the inverse delta should result in the original text");
     }
 }
+
+#[test]
+fn compose_deltas() {
+    #[track_caller]
+    fn t(text: &str, a: Deltas<'_>, b: Deltas<'_>, expected: Deltas<'_>) {
+        let composed = Deltas::compose(&a, &b);
+        assert_eq!(format!("{composed:?}"), format!("{expected:?}"));
+
+        let mut sequential = text.to_string();
+        a.apply(&mut sequential);
+        b.apply(&mut sequential);
+        let mut s = text.to_string();
+        composed.apply(&mut s);
+        assert_eq!(s, sequential);
+    }
+
+    t("abc", deltas![0..0 => "x"], deltas![4..4 => "y"], deltas![0..0 => "x", 3..3 => "y"]);
+    t("abc", deltas![1..2 => "xyz"], deltas![2..3 => ""], deltas![1..2 => "xz"]);
+    t("abc", deltas![1..1 => "x"], deltas![0..3 => "y"], deltas![0..2 => "y"]);
+    t("abcdef", deltas![1..2 => "", 4..5 => ""], deltas![1..3 => "x"], deltas![1..5 => "x"]);
+    t("abc", deltas![0..1 => "x"], Deltas::empty(), deltas![0..1 => "x"]);
+    t("abc", Deltas::empty(), deltas![2..3 => "x"], deltas![2..3 => "x"]);
+}
+
+#[test]
+fn transform_deltas() {
+    #[track_caller]
+    fn t(text: &str, a: Deltas<'_>, b: Deltas<'_>, expected: &str) {
+        let (a2, b2) = Deltas::transform(&a, &b);
+        let mut ab = text.to_string();
+        a.apply(&mut ab);
+        b2.apply(&mut ab);
+        let mut ba = text.to_string();
+        b.apply(&mut ba);
+        a2.apply(&mut ba);
+        assert_eq!(ab, expected);
+        assert_eq!(ba, expected);
+    }
+
+    t("abc", deltas![0..0 => "x"], deltas![3..3 => "y"], "xabcy");
+    t("abc", deltas![1..1 => "x"], deltas![1..1 => "y"], "axybc");
+    t("abc", deltas![0..2 => ""], deltas![1..3 => "y"], "y");
+    t("abcdef", deltas![1..5 => "x"], deltas![2..3 => "y"], "ayxf");
+    t("abc", deltas![0..3 => "x"], deltas![0..3 => "y"], "xy");
+}
+
+/// Build a set of non-overlapping deltas over a text of length `len` from arbitrary edits.
+fn deltas_in(len: usize, edits: Vec<(usize, usize, String)>) -> Deltas<'static> {
+    let mut edits = edits
+        .into_iter()
+        .map(|(start, n, text)| {
+            let start = start % (len + 1);
+            (start..start + n % (len - start + 1), text)
+        })
+        .collect::<Vec<_>>();
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut end = None;
+    Deltas::new(edits.into_iter().filter_map(|(range, text)| {
+        if end.is_some_and(|end| range.start <= end) {
+            return None;
+        }
+        end = Some(range.end);
+        Some(Delta::new(range, text))
+    }))
+}
+
+fn edits() -> impl Strategy<Value = Vec<(usize, usize, String)>> {
+    prop::collection::vec((any::<usize>(), 0..8usize, "[a-z]{0,4}"), 0..6)
+}
+
+proptest! {
+    #[test]
+    fn prop_compose_deltas(text in "[a-z]{0,16}", a in edits(), b in edits()) {
+        let a = deltas_in(text.len(), a);
+        let mut sequential = text.clone();
+        a.apply(&mut sequential);
+        let b = deltas_in(sequential.len(), b);
+        b.apply(&mut sequential);
+
+        let mut s = text.clone();
+        Deltas::compose(&a, &b).apply(&mut s);
+        assert_eq!(s, sequential);
+    }
+
+    #[test]
+    fn prop_transform_deltas(text in "[a-z]{0,16}", a in edits(), b in edits()) {
+        let a = deltas_in(text.len(), a);
+        let b = deltas_in(text.len(), b);
+        let (a2, b2) = Deltas::transform(&a, &b);
+
+        let mut ab = text.clone();
+        a.apply(&mut ab);
+        b2.apply(&mut ab);
+        let mut ba = text.clone();
+        b.apply(&mut ba);
+        a2.apply(&mut ba);
+        assert_eq!(ab, ba);
+    }
+}
+
+#[test]
+fn content_hash() {
+    assert_eq!(ContentHash::new("abc"), ContentHash::new(&Rope::from("abc")));
+    assert_eq!(ContentHash::new("abc").len(), 3);
+    assert_ne!(ContentHash::new("ab"), ContentHash::new("ba"));
+    assert_ne!(ContentHash::new(""), ContentHash::new("\0"));
+}
+
+proptest! {
+    #[test]
+    fn prop_content_hash(text in "[a-z\n]{0,4096}") {
+        // The rope is hashed chunk by chunk.
+        assert_eq!(ContentHash::new(&Rope::from(text.as_str())), ContentHash::new(text.as_str()));
+    }
+}
+
+#[test]
+fn line_endings() {
+    #[track_caller]
+    fn t(chunks: &[&str], expected: &str, line_ending: Option<LineEnding>) {
+        let mut normalizer = LineEndingNormalizer::default();
+        let mut s = String::new();
+        for chunk in chunks {
+            normalizer.normalize(chunk, |part| s.push_str(part));
+        }
+        assert_eq!(s, expected);
+        assert_eq!(normalizer.finish(), line_ending);
+
+        if let Some(line_ending) = line_ending {
+            let mut roundtrip = String::new();
+            line_ending.reader(expected.as_bytes()).read_to_string(&mut roundtrip).unwrap();
+            assert_eq!(roundtrip, chunks.concat());
+        }
+    }
+
+    t(&["abc"], "abc", None);
+    t(&["a\nb\n"], "a\nb\n", Some(LineEnding::Lf));
+    t(&["a\r\nb\r\n"], "a\nb\n", Some(LineEnding::CrLf));
+    t(&["a\r", "\nb\r", "\n"], "a\nb\n", Some(LineEnding::CrLf));
+    t(&["a\rb\r"], "a\nb\n", Some(LineEnding::Cr));
+}
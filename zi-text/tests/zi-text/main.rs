@@ -1,5 +1,6 @@
 use std::fmt;
 use std::io::Read;
+use std::ops::Range;
 
 use expect_test::{Expect, expect};
 use proptest::collection::vec;
@@ -58,6 +59,161 @@ fn char_at_byte() {
     assert_eq!("ab©d".char_at_byte(4), Some('d'));
 }
 
+#[test]
+fn grapheme_boundaries() {
+    // A combining accent, a wide character, an emoji ZWJ sequence, and a crlf line ending.
+    let s = "e\u{301}你👩\u{200d}💻\r\nx";
+    let boundaries = [0, 3, 6, 17, 19, 20];
+    for imp in impls(s) {
+        for w in boundaries.windows(2) {
+            assert_eq!(imp.next_grapheme_boundary(w[0]), w[1], "{s:?}: next from {}", w[0]);
+            assert_eq!(imp.prev_grapheme_boundary(w[1]), w[0], "{s:?}: prev from {}", w[1]);
+        }
+        assert_eq!(imp.next_grapheme_boundary(20), 20);
+        assert_eq!(imp.prev_grapheme_boundary(0), 0);
+    }
+}
+
+#[test]
+fn grapheme_display_width() {
+    assert_eq!(zi_text::display_width("e\u{301}", 0, 4), 1);
+    assert_eq!(zi_text::display_width("你好", 0, 4), 4);
+    assert_eq!(zi_text::display_width("a\tb", 0, 4), 5);
+    assert_eq!(zi_text::display_width("\t", 1, 4), 3);
+    assert_eq!(zi_text::display_width("\t", 0, 0), 1);
+}
+
+#[test]
+fn graphemes_and_words() {
+    let s = "e\u{301}你 it's\r\nfoo.bar\n";
+    for imp in impls(s) {
+        let graphemes = imp.graphemes().map(|(i, g)| (i, g.into_owned())).collect::<Vec<_>>();
+        assert_eq!(
+            graphemes,
+            [
+                (0, "e\u{301}"),
+                (3, "你"),
+                (6, " "),
+                (7, "i"),
+                (8, "t"),
+                (9, "'"),
+                (10, "s"),
+                (11, "\r\n"),
+                (13, "f"),
+                (14, "o"),
+                (15, "o"),
+                (16, "."),
+                (17, "b"),
+                (18, "a"),
+                (19, "r"),
+                (20, "\n"),
+            ]
+            .map(|(i, g)| (i, g.to_owned()))
+        );
+
+        let words = imp.words().map(|(i, w)| (i, w.into_owned())).collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            [(0, "e\u{301}"), (3, "你"), (7, "it's"), (13, "foo.bar")]
+                .map(|(i, w)| (i, w.to_owned()))
+        );
+
+        let line = imp.line(1).unwrap();
+        assert_eq!(
+            line.words().map(|(i, w)| (i, w.into_owned())).collect::<Vec<_>>(),
+            [(0, "foo.bar".to_owned())]
+        );
+    }
+}
+
+#[test]
+fn graphemes_across_chunks() {
+    // Long enough that the line is split across chunks of the rope.
+    let s = "e\u{301}".repeat(4096) + "\nab";
+    for imp in impls(&s) {
+        let mut graphemes = imp.graphemes();
+        for i in 0..4096 {
+            assert_eq!(
+                graphemes.next().map(|(i, g)| (i, g.into_owned())),
+                Some((i * 3, "e\u{301}".to_owned()))
+            );
+        }
+        assert_eq!(
+            graphemes.map(|(i, g)| (i, g.into_owned())).collect::<Vec<_>>(),
+            [(4096 * 3, "\n"), (4096 * 3 + 1, "a"), (4096 * 3 + 2, "b")]
+                .map(|(i, g)| (i, g.to_owned()))
+        );
+        assert_eq!(
+            imp.words().map(|(i, w)| (i, w.len())).collect::<Vec<_>>(),
+            [(0, 4096 * 3), (4096 * 3 + 1, 2)]
+        );
+    }
+}
+
+#[test]
+fn line_with_ending() {
+    for imp in impls("ab\r\ncd\nef") {
+        assert_eq!(imp.line_with_ending(0).unwrap().to_string(), "ab\r\n");
+        assert_eq!(imp.line_with_ending(1).unwrap().to_string(), "cd\n");
+        assert_eq!(imp.line_with_ending(2).unwrap().to_string(), "ef");
+        assert!(imp.line_with_ending(3).is_none());
+    }
+}
+
+#[test]
+fn byte_to_display_col() {
+    for imp in impls("a\tb\n你好x") {
+        assert_eq!(imp.byte_to_display_col(0, 4), 0);
+        assert_eq!(imp.byte_to_display_col(1, 4), 1);
+        assert_eq!(imp.byte_to_display_col(2, 4), 4);
+        assert_eq!(imp.byte_to_display_col(4, 4), 0);
+        assert_eq!(imp.byte_to_display_col(7, 4), 2);
+        assert_eq!(imp.byte_to_display_col(10, 4), 4);
+        assert_eq!(imp.byte_to_display_col(10, 4), imp.line(1).unwrap().byte_to_display_col(6, 4));
+    }
+}
+
+#[test]
+fn search() {
+    use zi_text::search::{Regex, Search};
+
+    let search = Search::new(Regex::new(r"\bab+").unwrap());
+    for imp in impls("ab abb\nxab abbb") {
+        let imp = &*imp;
+        assert_eq!(search.forward(imp, 0).collect::<Vec<_>>(), [0..2, 3..6, 11..15]);
+        assert_eq!(search.forward(imp, 1).collect::<Vec<_>>(), [3..6, 11..15]);
+        // The match at 8 is not at a word boundary even though the search starts there.
+        assert_eq!(search.forward(imp, 8).collect::<Vec<_>>(), [11..15]);
+        assert_eq!(search.backward(imp, 11).collect::<Vec<_>>(), [3..6, 0..2]);
+        assert_eq!(search.backward(imp, 12).collect::<Vec<_>>(), [11..15, 3..6, 0..2]);
+        assert_eq!(search.at(imp, 3), Some(3..6));
+        assert_eq!(search.at(imp, 4), None);
+        assert_eq!(search.at(imp, 8), None);
+        assert!(search.is_match(imp.line(1).unwrap()));
+        assert!(!Search::new(Regex::new("^ab$").unwrap()).is_match(imp.line(0).unwrap()));
+    }
+
+    // Backward searches scan the text a chunk of lines at a time.
+    let text = "ab\n".repeat(2 * zi_text::search::BACKWARD_CHUNK_LINES + 1);
+    for imp in impls(&text) {
+        let imp = &*imp;
+        let mut matches = search.forward(imp, 0).collect::<Vec<_>>();
+        assert_eq!(matches.len(), 2 * zi_text::search::BACKWARD_CHUNK_LINES + 1);
+        matches.reverse();
+        assert_eq!(search.backward(imp, imp.len_bytes()).collect::<Vec<_>>(), matches);
+    }
+
+    let cancel = stdx::sync::Cancel::new();
+    let search = Search::new(Regex::new("a").unwrap()).with_cancel(cancel.clone());
+    let text = "a".repeat(10);
+    let mut matches = search.forward(text.as_str(), 0);
+    assert_eq!(matches.next(), Some(0..1));
+    cancel.cancel();
+    assert_eq!(matches.next(), None);
+    assert_eq!(search.backward(text.as_str(), 10).next(), None);
+    assert!(!search.is_match(text.as_str()));
+}
+
 #[test]
 fn empty_text() {
     macro_rules! test {
@@ -358,3 +514,78 @@ const x: &str = r#"
         "##]],
     );
 }
+
+#[test]
+fn line_diff() {
+    #[track_caller]
+    fn check(old: &str, new: &str, expected: &[(Range<usize>, Range<usize>)]) {
+        let diffs = diff_lines(old, new);
+        let expected = expected
+            .iter()
+            .map(|(old, new)| LineDiff { old: old.clone(), new: new.clone() })
+            .collect::<Vec<_>>();
+        assert_eq!(diffs, expected, "{old:?} -> {new:?}");
+    }
+
+    check("", "", &[]);
+    check("a\n", "a\n", &[]);
+    check("", "a\n", &[(0..0, 0..1)]);
+    check("a\n", "", &[(0..1, 0..0)]);
+    check("a\nb\nc\n", "a\nc\n", &[(1..2, 1..1)]);
+    check("a\nc\n", "a\nb\nc\n", &[(1..1, 1..2)]);
+    check("a\nb\nc\n", "a\nx\nc\n", &[(1..2, 1..2)]);
+    check("a\nb", "a\nb\n", &[(1..2, 1..2)]);
+    check("a\nb\nc\nd\n", "b\nc\nd\ne\n", &[(0..1, 0..0), (4..4, 3..4)]);
+    check("a\nb\nc\na\nb\nb\na\n", "c\nb\na\nb\na\nc\n", &[
+        (0..2, 0..0),
+        (3..3, 1..2),
+        (5..6, 4..4),
+        (7..7, 5..6),
+    ]);
+}
+
+#[test]
+fn text_diff() {
+    #[track_caller]
+    fn check(old: &str, new: &str, expected: Deltas<'_>) {
+        let deltas = zi_text::diff(&old, &new);
+        assert_eq!(format!("{deltas:?}"), format!("{expected:?}"), "{old:?} -> {new:?}");
+    }
+
+    check("", "", Deltas::empty());
+    check("abc\n", "abc\n", Deltas::empty());
+    check("abc\n", "abd\n", deltas![2..3 => "d"]);
+    check("a\nb\n", "a\nx\nb\n", deltas![2..2 => "x\n"]);
+    check("a\nb\nc\n", "x\nb\ny\n", deltas![0..1 => "x", 4..5 => "y"]);
+    check("héllo wörld\n", "hello world\n", deltas![1..3 => "e", 8..10 => "o"]);
+    check("a\nb", "a\nb\n", deltas![3..3 => "\n"]);
+}
+
+proptest! {
+    #[test]
+    fn text_diff_applies(old in "[abc\n]{0,30}", new in "[abc\n]{0,30}") {
+        for mut imp in mut_impls(&old) {
+            imp.edit(&zi_text::diff(&old.as_str(), &new.as_str()));
+            assert_eq!(imp.to_string(), new);
+        }
+    }
+
+    #[test]
+    fn line_diff_applies(old in vec("[abc]\n", 0..20), new in vec("[abc]\n", 0..20)) {
+        let (old, new) = (old.concat(), new.concat());
+        let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+        let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+
+        // Replacing each differing range of the old lines with the new lines must produce the new text.
+        let mut patched = vec![];
+        let mut line = 0;
+        for diff in diff_lines(&old, &new) {
+            assert!(!diff.old.is_empty() || !diff.new.is_empty());
+            patched.extend_from_slice(&old_lines[line..diff.old.start]);
+            patched.extend_from_slice(&new_lines[diff.new.clone()]);
+            line = diff.old.end;
+        }
+        patched.extend_from_slice(&old_lines[line..]);
+        assert_eq!(patched.concat(), new);
+    }
+}
@@ -131,7 +131,7 @@ impl Motion for NextChar {
     fn motion(&self, text: &dyn AnyText, p: PointOrByte) -> PointOrByte {
         let byte = text.point_or_byte_to_byte(p);
         match text.char_at_byte(byte) {
-            Some(c) if c != '\n' => byte + c.len_utf8(),
+            Some(c) if !matches!(c, '\n' | '\r') => text.next_grapheme_boundary(byte),
             _ => byte,
         }
         .into()
@@ -148,7 +148,7 @@ impl Motion for PrevChar {
     fn motion(&self, text: &dyn AnyText, p: PointOrByte) -> PointOrByte {
         let byte = text.point_or_byte_to_byte(p);
         match text.char_before_byte(byte) {
-            Some(c) if c != '\n' => byte - c.len_utf8(),
+            Some(c) if c != '\n' => text.prev_grapheme_boundary(byte),
             _ => byte,
         }
         .into()
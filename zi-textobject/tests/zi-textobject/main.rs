@@ -170,6 +170,12 @@ fn motion_next_char() {
     check(&motion, "\n\n\n", 0, 0);
 
     check_range(&motion, "a", 0, Some(0..1));
+
+    // Grapheme clusters are moved over as a whole.
+    check(&motion, "e\u{301}x", 0, 3);
+    check(&motion, "你x", 0, 3);
+    check(&motion, "👩\u{200d}💻x", 0, 11);
+    check_range(&motion, "e\u{301}x", 0, Some(0..3));
 }
 
 #[test]
@@ -177,7 +183,10 @@ fn motion_prev_char() {
     let motion = PrevChar;
 
     check(&motion, "a", 1, 0);
-    check(&motion, "---------\u{a002d}-\u{fe2c}\0---\u{a05cc}\n", 25, 21)
+    check(&motion, "---------\u{a002d}-\u{fe2c}\0---\u{a05cc}\n", 25, 21);
+
+    check(&motion, "xe\u{301}", 4, 1);
+    check(&motion, "x👩\u{200d}💻", 12, 1);
 }
 
 #[test]
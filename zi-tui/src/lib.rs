@@ -1,20 +1,24 @@
 mod element;
 mod sequence;
+mod surface;
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::iter::Peekable;
 use std::marker::PhantomData;
 use std::str::FromStr;
 use std::{cmp, fmt};
 
+use unicode_width::UnicodeWidthChar;
+
 pub use ratatui::backend::Backend;
-pub use ratatui::buffer::Buffer;
+pub use ratatui::buffer::{Buffer, Cell};
 pub use ratatui::layout::{Constraint, Direction, Layout, Rect};
 pub use ratatui::style::{Color, Modifier, Style};
 pub use ratatui::text::{Line, Span, Text};
 pub use ratatui::widgets::{
-    Clear, List, ListDirection, ListItem, ListState, StatefulWidget, StatefulWidgetRef, Widget,
-    WidgetRef,
+    Block, Clear, List, ListDirection, ListItem, ListState, StatefulWidget, StatefulWidgetRef,
+    Widget, WidgetRef,
 };
 pub use ratatui::{Frame, Terminal, backend};
 
@@ -57,6 +61,10 @@ impl DynFrame for TestFrame {
 
 pub use self::element::Element;
 pub use self::sequence::ElementSeq;
+pub use self::surface::{
+    Attributes, Cursor, CursorShape, CursorStyle, FloatArea, RenderOp, Surface, SurfaceCell,
+    SurfaceColor, SurfaceFrame, SurfaceStyle,
+};
 
 pub fn vstack<I, S>(constraints: I, seq: S) -> impl Element
 where
@@ -125,6 +133,139 @@ impl fmt::Display for LineNumberStyle {
     }
 }
 
+/// The glyphs used to display whitespace in list mode, in the format `tab:>-,trail:-,nbsp:+`.
+/// Whitespace without a glyph is displayed as usual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListChars {
+    /// The first glyph is displayed at the start of the tab and the second fills the rest of it.
+    pub tab: Option<(char, char)>,
+    /// Displayed in place of spaces at the end of the line.
+    pub trail: Option<char>,
+    /// Displayed in place of non-breaking spaces.
+    pub nbsp: Option<char>,
+}
+
+impl Default for ListChars {
+    fn default() -> Self {
+        Self { tab: Some(('>', ' ')), trail: Some('-'), nbsp: Some('+') }
+    }
+}
+
+impl FromStr for ListChars {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list_chars = Self { tab: None, trail: None, nbsp: None };
+        for item in s.split(',').filter(|item| !item.is_empty()) {
+            let Some((key, value)) = item.split_once(':') else {
+                anyhow::bail!("invalid listchars item: {item} (expected `key:value`)")
+            };
+
+            let chars = value.chars().collect::<Vec<_>>();
+            match (key, &chars[..]) {
+                ("tab", &[a, b]) => list_chars.tab = Some((a, b)),
+                ("trail", &[c]) => list_chars.trail = Some(c),
+                ("nbsp", &[c]) => list_chars.nbsp = Some(c),
+                ("tab" | "trail" | "nbsp", _) => {
+                    anyhow::bail!("invalid listchars value for `{key}`: {value:?}")
+                }
+                _ => anyhow::bail!(
+                    "unknown listchars key: {key} (expected `nbsp`, `tab`, or `trail`)"
+                ),
+            }
+        }
+
+        Ok(list_chars)
+    }
+}
+
+impl fmt::Display for ListChars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = [
+            self.tab.map(|(a, b)| format!("tab:{a}{b}")),
+            self.trail.map(|c| format!("trail:{c}")),
+            self.nbsp.map(|c| format!("nbsp:{c}")),
+        ];
+        write!(f, "{}", items.into_iter().flatten().collect::<Vec<_>>().join(","))
+    }
+}
+
+/// The number of cells `c` occupies when displayed starting at the 0-indexed cell `col`.
+/// Tabs extend to the next multiple of `tab_width`.
+pub fn char_width(c: char, col: usize, tab_width: u8) -> usize {
+    match c {
+        '\t' => {
+            let tab_width = tab_width.max(1) as usize;
+            tab_width - col % tab_width
+        }
+        _ => c.width().unwrap_or(0),
+    }
+}
+
+/// Expand the tabs of the spans of a line to the next tab stop.
+/// In list mode, whitespace is replaced with the glyphs of `list_chars` patched with its style.
+fn expand_spans<'a>(
+    spans: Vec<Span<'a>>,
+    list_chars: Option<(ListChars, Style)>,
+    tab_width: u8,
+) -> Vec<Span<'a>> {
+    if list_chars.is_none() && spans.iter().all(|span| !span.content.contains('\t')) {
+        return spans;
+    }
+
+    // The char index after which all spaces are trailing.
+    let trail_start = spans
+        .iter()
+        .flat_map(|span| span.content.chars())
+        .enumerate()
+        .filter(|&(_, c)| c != ' ')
+        .last()
+        .map_or(0, |(i, _)| i + 1);
+
+    let mut out = Vec::with_capacity(spans.len());
+    let (mut idx, mut col) = (0, 0);
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let width = char_width(c, col, tab_width);
+            let glyph = match list_chars {
+                Some((ListChars { tab: Some((a, b)), .. }, _)) if c == '\t' => {
+                    Some(format!("{a}{}", b.to_string().repeat(width - 1)))
+                }
+                Some((ListChars { trail: Some(trail), .. }, _))
+                    if c == ' ' && idx >= trail_start =>
+                {
+                    Some(trail.to_string())
+                }
+                Some((ListChars { nbsp: Some(nbsp), .. }, _)) if c == '\u{a0}' => {
+                    Some(nbsp.to_string())
+                }
+                _ => None,
+            };
+            idx += 1;
+            col += width;
+
+            match (glyph, list_chars) {
+                (Some(glyph), Some((_, style))) => {
+                    if !text.is_empty() {
+                        out.push(Span::styled(std::mem::take(&mut text), span.style));
+                    }
+                    out.push(Span::styled(glyph, span.style.patch(style)));
+                }
+                // Tabs are not rendered by ratatui, so they are replaced with spaces.
+                // https://github.com/ratatui-org/ratatui/issues/876
+                _ if c == '\t' => text.push_str(&" ".repeat(width)),
+                _ => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            out.push(Span::styled(text, span.style));
+        }
+    }
+    out
+}
+
 pub struct Lines<'a, I: Iterator> {
     /// The 0-indexed line number to start with
     line_offset: usize,
@@ -132,6 +273,16 @@ pub struct Lines<'a, I: Iterator> {
     tab_width: u8,
     min_number_width: u8,
     cursor_line: usize,
+    /// Signs to display in the left padding column keyed by 0-indexed line number.
+    signs: HashMap<usize, (char, Style)>,
+    /// Text to display after the end of the line keyed by 0-indexed line number.
+    virtual_text: HashMap<usize, (String, Style)>,
+    /// The 0-indexed line displayed on each row, if the lines are not consecutive (e.g. due to folds).
+    row_lines: Vec<usize>,
+    /// The sorted rows that don't display a line.
+    filler_rows: Vec<usize>,
+    /// The glyphs and style to display whitespace with, if in list mode.
+    list_chars: Option<(ListChars, Style)>,
     chunks: Peekable<I>,
     _marker: PhantomData<&'a ()>,
 }
@@ -151,10 +302,55 @@ impl<I: Iterator> Lines<'_, I> {
             tab_width,
             min_number_width,
             cursor_line,
+            signs: Default::default(),
+            virtual_text: Default::default(),
+            row_lines: Default::default(),
+            filler_rows: Default::default(),
+            list_chars: None,
             chunks: chunks.peekable(),
             _marker: PhantomData,
         }
     }
+
+    /// Display signs in place of the left padding space, the first sign given for a line wins.
+    pub fn signs(mut self, signs: impl IntoIterator<Item = (usize, char, Style)>) -> Self {
+        for (line, sign, style) in signs {
+            self.signs.entry(line).or_insert((sign, style));
+        }
+        self
+    }
+
+    /// Display text after the end of lines.
+    pub fn virtual_text(mut self, text: impl IntoIterator<Item = (usize, String, Style)>) -> Self {
+        self.virtual_text.extend(text.into_iter().map(|(line, text, style)| (line, (text, style))));
+        self
+    }
+
+    /// Set the line displayed on each row, the chunks are still indexed by row.
+    pub fn row_lines(mut self, lines: Vec<usize>) -> Self {
+        self.row_lines = lines;
+        self
+    }
+
+    /// Set the sorted rows that don't display a line, they have no line number, sign or virtual text.
+    pub fn filler_rows(mut self, rows: Vec<usize>) -> Self {
+        self.filler_rows = rows;
+        self
+    }
+
+    /// Display whitespace with the given glyphs, patching the style of the text with `style`.
+    pub fn list_chars(mut self, list_chars: ListChars, style: Style) -> Self {
+        self.list_chars = Some((list_chars, style));
+        self
+    }
+
+    fn is_filler(&self, row: usize) -> bool {
+        self.filler_rows.binary_search(&row).is_ok()
+    }
+
+    fn line(&self, row: usize) -> usize {
+        self.row_lines.get(row).copied().unwrap_or(self.line_offset + row)
+    }
 }
 
 impl<'a, I> Lines<'a, I>
@@ -167,13 +363,6 @@ where
             1 + n.abs_diff(0).checked_ilog10().unwrap_or_default() as usize
         }
 
-        fn replace_tabs(tab_width: usize, span: &mut Span<'_>) {
-            if span.content.contains('\t') {
-                span.content =
-                    span.content.replace('\t', &format!("{:width$}", "", width = tab_width)).into();
-            }
-        }
-
         let mut lines = vec![];
         const SPACE: &str = " ";
         let mut number_width = match self.line_number_style {
@@ -192,7 +381,7 @@ where
             number_width = number_width.max(
                 1 + match self.line_number_style {
                     LineNumberStyle::None => 0,
-                    LineNumberStyle::Absolute => count_digits(self.line_offset + i + 1),
+                    LineNumberStyle::Absolute => count_digits(self.line(i) + 1),
                     LineNumberStyle::Relative => count_digits(self.cursor_line + 1),
                 },
             );
@@ -207,9 +396,15 @@ where
                 self.chunks.next();
             }
 
-            // Tabs are currently not rendered at all. We replace them with spaces for rendering purposes.
-            // https://github.com/ratatui-org/ratatui/issues/876
-            spans.iter_mut().for_each(|span| replace_tabs(self.tab_width as usize, span));
+            let text = spans.split_off(1);
+            spans.extend(expand_spans(text, self.list_chars, self.tab_width));
+
+            let virtual_text =
+                if self.is_filler(i) { None } else { self.virtual_text.remove(&self.line(i)) };
+            if let Some((text, style)) = virtual_text {
+                spans.push(Span::raw("    "));
+                spans.push(Span::styled(text, style));
+            }
 
             lines.push(Line::default().spans(spans));
         }
@@ -219,24 +414,35 @@ where
         for (i, line) in lines.iter_mut().enumerate() {
             // Set line number spans for each line.
             let style = Style::new().fg(Color::Rgb(0x58, 0x6e, 0x75));
+            if self.is_filler(i) {
+                line.spans[0] = Span::styled(" ".repeat(number_width + 1), style);
+                continue;
+            }
+
+            let line_idx = self.line(i);
             let line_number_span = match self.line_number_style {
                 LineNumberStyle::Relative => {
-                    let number = match self.cursor_line.cmp(&(i + self.line_offset)) {
-                        cmp::Ordering::Less => i + self.line_offset - self.cursor_line,
+                    let number = match self.cursor_line.cmp(&line_idx) {
+                        cmp::Ordering::Less => line_idx - self.cursor_line,
                         cmp::Ordering::Equal => self.cursor_line + 1,
-                        cmp::Ordering::Greater => self.cursor_line - i - self.line_offset,
+                        cmp::Ordering::Greater => self.cursor_line - line_idx,
                     };
 
                     Span::styled(format!(" {:width$} ", number, width = number_width - 1), style)
                 }
                 LineNumberStyle::Absolute => Span::styled(
-                    format!(" {:width$} ", self.line_offset + i + 1, width = number_width - 1),
+                    format!(" {:width$} ", line_idx + 1, width = number_width - 1),
                     style,
                 ),
                 LineNumberStyle::None => Span::styled("  ", style),
             };
 
             line.spans[0] = line_number_span;
+            if let Some(&(sign, style)) = self.signs.get(&line_idx) {
+                let number = &mut line.spans[0].content;
+                *number = number[SPACE.len()..].to_owned().into();
+                line.spans.insert(0, Span::styled(sign.to_string(), style));
+            }
         }
 
         lines.iter().enumerate().for_each(|(i, line)| {
@@ -0,0 +1,317 @@
+//! A description of a rendered frame that doesn't depend on ratatui or crossterm types, for
+//! frontends other than the terminal, e.g. a GUI or the web.
+//!
+//! Render to a [`SurfaceFrame`] and convert it to a [`Surface`] of cells. A frontend then either
+//! draws whole surfaces, or applies the [`RenderOp`]s from [`Surface::diff`] to update what it
+//! has displayed.
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::{Buffer, Color, DynFrame, Modifier, Rect};
+
+/// A grid of cells in row-major order along with the cursor and the floating windows drawn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Surface {
+    width: u16,
+    height: u16,
+    cells: Vec<SurfaceCell>,
+    cursor: Option<Cursor>,
+    floats: Vec<FloatArea>,
+}
+
+impl Surface {
+    /// A surface of blank cells.
+    pub fn new(width: u16, height: u16) -> Self {
+        let cells = vec![SurfaceCell::default(); width as usize * height as usize];
+        Self { width, height, cells, cursor: None, floats: vec![] }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    #[inline]
+    pub fn cells(&self) -> &[SurfaceCell] {
+        &self.cells
+    }
+
+    /// The cell at the 0-indexed column `x` and row `y`.
+    pub fn cell(&self, x: u16, y: u16) -> Option<&SurfaceCell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get(y as usize * self.width as usize + x as usize)
+    }
+
+    /// The cells of the 0-indexed row `y`.
+    pub fn row(&self, y: u16) -> &[SurfaceCell] {
+        let width = self.width as usize;
+        &self.cells[y as usize * width..(y as usize + 1) * width]
+    }
+
+    #[inline]
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.cursor
+    }
+
+    /// The floating windows, including their borders, bottom-most first.
+    #[inline]
+    pub fn floats(&self) -> &[FloatArea] {
+        &self.floats
+    }
+
+    pub fn with_cursor(mut self, cursor: Option<Cursor>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn with_floats(mut self, floats: impl IntoIterator<Item = FloatArea>) -> Self {
+        self.floats = floats.into_iter().collect();
+        self
+    }
+
+    /// The operations that update a display of `self` to display `next`.
+    /// Diff against [`Surface::default`] to draw `next` from scratch.
+    pub fn diff(&self, next: &Surface) -> Vec<RenderOp> {
+        let mut ops = vec![];
+        let resized = (self.width, self.height) != (next.width, next.height);
+        if resized {
+            ops.push(RenderOp::Resize { width: next.width, height: next.height });
+        }
+
+        for y in 0..next.height {
+            let row = next.row(y);
+            let prev = if resized { None } else { Some(self.row(y)) };
+            let mut x = 0;
+            while x < row.len() {
+                let changed = |x: usize| prev.is_none_or(|prev| prev[x] != row[x]);
+                if !changed(x) {
+                    x += 1;
+                    continue;
+                }
+
+                let start = x;
+                while x < row.len() && changed(x) {
+                    x += 1;
+                }
+                let cells = row[start..x].to_vec();
+                ops.push(RenderOp::Draw { x: start as u16, y, cells });
+            }
+        }
+
+        if resized || self.floats != next.floats {
+            ops.push(RenderOp::Floats(next.floats.clone()));
+        }
+
+        if resized || self.cursor != next.cursor {
+            ops.push(RenderOp::Cursor(next.cursor));
+        }
+
+        ops
+    }
+}
+
+/// A change to what a frontend displays, see [`Surface::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderOp {
+    /// The surface is resized, and all its cells are drawn after.
+    Resize { width: u16, height: u16 },
+    /// Draw consecutive cells of row `y` starting at column `x`.
+    Draw { x: u16, y: u16, cells: Vec<SurfaceCell> },
+    /// The floating windows drawn, bottom-most first. Their cells are drawn as usual, this only
+    /// allows a frontend to decorate them, e.g. with a shadow.
+    Floats(Vec<FloatArea>),
+    /// Show the cursor, or hide it if `None`.
+    Cursor(Option<Cursor>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceCell {
+    /// The grapheme displayed in the cell. Cells covered by a wide grapheme to their left are
+    /// empty.
+    pub symbol: String,
+    pub style: SurfaceStyle,
+}
+
+impl Default for SurfaceCell {
+    fn default() -> Self {
+        Self { symbol: String::from(" "), style: SurfaceStyle::default() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SurfaceStyle {
+    pub fg: SurfaceColor,
+    pub bg: SurfaceColor,
+    pub underline_color: SurfaceColor,
+    pub attributes: Attributes,
+}
+
+/// A color, the frontend decides what the default colors and the 256 indexed colors are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SurfaceColor {
+    #[default]
+    Default,
+    /// An ANSI color, the first 16 are the named colors.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl From<Color> for SurfaceColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Reset => Self::Default,
+            Color::Black => Self::Indexed(0),
+            Color::Red => Self::Indexed(1),
+            Color::Green => Self::Indexed(2),
+            Color::Yellow => Self::Indexed(3),
+            Color::Blue => Self::Indexed(4),
+            Color::Magenta => Self::Indexed(5),
+            Color::Cyan => Self::Indexed(6),
+            Color::Gray => Self::Indexed(7),
+            Color::DarkGray => Self::Indexed(8),
+            Color::LightRed => Self::Indexed(9),
+            Color::LightGreen => Self::Indexed(10),
+            Color::LightYellow => Self::Indexed(11),
+            Color::LightBlue => Self::Indexed(12),
+            Color::LightMagenta => Self::Indexed(13),
+            Color::LightCyan => Self::Indexed(14),
+            Color::White => Self::Indexed(15),
+            Color::Indexed(i) => Self::Indexed(i),
+            Color::Rgb(r, g, b) => Self::Rgb(r, g, b),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    pub struct Attributes: u16 {
+        const BOLD = 1 << 0;
+        const DIM = 1 << 1;
+        const ITALIC = 1 << 2;
+        const UNDERLINED = 1 << 3;
+        const SLOW_BLINK = 1 << 4;
+        const RAPID_BLINK = 1 << 5;
+        const REVERSED = 1 << 6;
+        const HIDDEN = 1 << 7;
+        const CROSSED_OUT = 1 << 8;
+    }
+}
+
+impl From<Modifier> for Attributes {
+    fn from(modifier: Modifier) -> Self {
+        [
+            (Modifier::BOLD, Attributes::BOLD),
+            (Modifier::DIM, Attributes::DIM),
+            (Modifier::ITALIC, Attributes::ITALIC),
+            (Modifier::UNDERLINED, Attributes::UNDERLINED),
+            (Modifier::SLOW_BLINK, Attributes::SLOW_BLINK),
+            (Modifier::RAPID_BLINK, Attributes::RAPID_BLINK),
+            (Modifier::REVERSED, Attributes::REVERSED),
+            (Modifier::HIDDEN, Attributes::HIDDEN),
+            (Modifier::CROSSED_OUT, Attributes::CROSSED_OUT),
+        ]
+        .into_iter()
+        .filter(|&(m, _)| modifier.contains(m))
+        .fold(Attributes::empty(), |acc, (_, attr)| acc | attr)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    pub x: u16,
+    pub y: u16,
+    pub shape: CursorShape,
+    pub blink: bool,
+}
+
+/// How the cursor is displayed, a steady block by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blink: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Bar,
+    Underline,
+}
+
+/// The area of a floating window including its border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FloatArea {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub z_index: u8,
+}
+
+impl FloatArea {
+    pub fn new(area: Rect, z_index: u8) -> Self {
+        Self { x: area.x, y: area.y, width: area.width, height: area.height, z_index }
+    }
+}
+
+/// A frame to render to for conversion to a [`Surface`].
+pub struct SurfaceFrame {
+    buffer: Buffer,
+    cursor: Option<(u16, u16)>,
+}
+
+impl SurfaceFrame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { buffer: Buffer::empty(Rect::new(0, 0, width, height)), cursor: None }
+    }
+
+    /// Convert the rendered frame, showing the cursor set while rendering with `style`.
+    pub fn into_surface(self, style: CursorStyle) -> Surface {
+        let Rect { width, height, .. } = self.buffer.area;
+        let mut surface = Surface::new(width, height);
+        // The number of cells still covered by the previous wide grapheme.
+        let mut covered = 0;
+        for (i, (cell, out)) in self.buffer.content().iter().zip(&mut surface.cells).enumerate() {
+            if i % width as usize == 0 {
+                covered = 0;
+            }
+
+            out.style = SurfaceStyle {
+                fg: cell.fg.into(),
+                bg: cell.bg.into(),
+                underline_color: cell.underline_color.into(),
+                attributes: cell.modifier.into(),
+            };
+
+            if covered > 0 {
+                covered -= 1;
+                out.symbol.clear();
+            } else {
+                out.symbol = cell.symbol().to_owned();
+                covered = cell.symbol().width().saturating_sub(1);
+            }
+        }
+
+        let CursorStyle { shape, blink } = style;
+        surface.cursor = self.cursor.map(|(x, y)| Cursor { x, y, shape, blink });
+        surface
+    }
+}
+
+impl DynFrame for SurfaceFrame {
+    fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffer
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) {
+        self.cursor = Some((x, y));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, OnceLock};
@@ -6,20 +6,25 @@ use std::sync::{Arc, OnceLock};
 use futures_util::future::BoxFuture;
 use futures_util::{FutureExt, Stream, StreamExt, TryStreamExt};
 use parking_lot::RwLock;
-use slotmap::SlotMap;
+use slotmap::{Key, KeyData, SlotMap};
 use smol_str::SmolStr;
 use tokio::select;
-use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::{self, UnboundedSender};
 use tokio::sync::oneshot;
 use tokio::task::JoinSet;
 use tokio_stream::wrappers::ReadDirStream;
 pub use wasmtime::Engine;
 use wasmtime::component::{Component, Linker, Resource, ResourceAny};
 use zi::command::{self, CommandRange, Handler, Word};
-use zi::{Active, BufferId, Client, LanguageConfig, Point, ViewId, dirs};
+use zi::event::{self, HandlerResult};
+use zi::{
+    Active, AnyText, BufferId, Client, Delta, Deltas, LanguageConfig, Mark, NamespaceId, Point,
+    Sign, Text, TextSlice, ViewGroupId, ViewId, VirtualText, dirs,
+};
 use zi_lsp::LanguageServerConfig;
 
 use crate::wit::Plugin;
+use crate::wit::exports::zi::api::events;
 use crate::wit::zi::api;
 
 pub fn engine() -> &'static Engine {
@@ -37,11 +42,89 @@ pub struct HostState {
     views: HashMap<u32, ViewId>,
     buffers: HashMap<u32, BufferId>,
     next_rep: AtomicU32,
+    subscriptions: Arc<RwLock<Subscriptions>>,
+    next_subscription: api::editor::Subscription,
+    // The mappings made by the plugin, which are removed when it is unloaded.
+    mappings: Vec<(zi::Mode, String)>,
+    sub_mode_mappings: Vec<(String, String)>,
+}
+
+/// The events a plugin is notified of, shared between its `HostState` and the `PluginManager`.
+#[derive(Default)]
+struct Subscriptions {
+    /// The buffers the plugin is notified of changes to.
+    changes: HashSet<BufferId>,
+    hooks: HashMap<api::editor::Subscription, Hook>,
+    /// The pickers opened by the plugin, which it is notified of the confirmed item of.
+    pickers: HashSet<ViewGroupId>,
+}
+
+/// A subscription to the events of a kind that match the filters that are set.
+struct Hook {
+    kind: api::editor::EventKind,
+    buf: Option<BufferId>,
+    file_type: Option<zi::FileType>,
+    mode: Option<zi::Mode>,
+}
+
+impl Hook {
+    /// Whether the hook matches the event, the filters that don't apply to the event are ignored.
+    fn matches(&self, event: &HookEvent, file_type: Option<zi::FileType>) -> bool {
+        self.kind == event.kind()
+            && self.buf.is_none_or(|buf| event.buf().is_none_or(|b| b == buf))
+            && self.file_type.is_none_or(|ft| file_type.is_none_or(|t| t == ft))
+            && self.mode.is_none_or(|mode| event.mode().is_none_or(|m| m == mode))
+    }
+}
+
+/// The events plugins can subscribe to with a [`Hook`].
+#[derive(Debug, Clone)]
+enum HookEvent {
+    BufferOpen { buf: BufferId },
+    BufferWrite { buf: BufferId },
+    CursorMove { buf: BufferId, cursor: Point },
+    ModeChange { from: zi::Mode, to: zi::Mode },
+}
+
+impl HookEvent {
+    fn kind(&self) -> api::editor::EventKind {
+        match self {
+            HookEvent::BufferOpen { .. } => api::editor::EventKind::BufferOpen,
+            HookEvent::BufferWrite { .. } => api::editor::EventKind::BufferWrite,
+            HookEvent::CursorMove { .. } => api::editor::EventKind::CursorMove,
+            HookEvent::ModeChange { .. } => api::editor::EventKind::ModeChange,
+        }
+    }
+
+    fn buf(&self) -> Option<BufferId> {
+        match *self {
+            HookEvent::BufferOpen { buf }
+            | HookEvent::BufferWrite { buf }
+            | HookEvent::CursorMove { buf, .. } => Some(buf),
+            HookEvent::ModeChange { .. } => None,
+        }
+    }
+
+    fn mode(&self) -> Option<zi::Mode> {
+        match *self {
+            HookEvent::ModeChange { to, .. } => Some(to),
+            _ => None,
+        }
+    }
 }
 
 impl HostState {
     fn new(client: Client) -> Self {
-        Self { client, views: HashMap::new(), buffers: HashMap::new(), next_rep: AtomicU32::new(1) }
+        Self {
+            client,
+            views: HashMap::new(),
+            buffers: HashMap::new(),
+            next_rep: AtomicU32::new(1),
+            subscriptions: Default::default(),
+            next_subscription: 1,
+            mappings: Vec::new(),
+            sub_mode_mappings: Vec::new(),
+        }
     }
 
     fn client(&self) -> &Client {
@@ -63,6 +146,72 @@ impl HostState {
         self.buffers.insert(rep, id);
         Resource::new_own(rep)
     }
+
+    fn buffer_id(&self, res: &Resource<api::editor::Buffer>) -> BufferId {
+        self.buffers[&res.rep()]
+    }
+}
+
+/// Convert the plugin's deltas to byte deltas, or `None` if any point is out of bounds or the
+/// deltas overlap (which `Deltas::new` would panic on).
+/// Adjacent deltas are merged in order, as `Deltas::new` rejects those too.
+fn to_deltas(text: &dyn AnyText, deltas: Vec<api::editor::Delta>) -> Option<Deltas<'static>> {
+    let mut deltas = deltas
+        .into_iter()
+        .map(|delta| to_byte_range(text, delta.start, delta.end).map(|range| (range, delta.text)))
+        .collect::<Option<Vec<_>>>()?;
+
+    // Insertions go before a replacement at the same point, and the sort is stable so they keep
+    // their order otherwise.
+    deltas.sort_by_key(|(range, _)| (range.start, range.end));
+    let mut merged = Vec::<(std::ops::Range<usize>, String)>::with_capacity(deltas.len());
+    for (range, text) in deltas {
+        match merged.last_mut() {
+            Some((prev, _)) if prev.end > range.start => return None,
+            Some((prev, prev_text)) if prev.end == range.start => {
+                prev.end = range.end;
+                prev_text.push_str(&text);
+            }
+            _ => merged.push((range, text)),
+        }
+    }
+
+    Some(Deltas::new(merged.into_iter().map(|(range, text)| Delta::new(range, text))))
+}
+
+fn to_byte_range(
+    text: &dyn AnyText,
+    start: api::editor::Point,
+    end: api::editor::Point,
+) -> Option<std::ops::Range<usize>> {
+    let range = to_byte(text, start)?..to_byte(text, end)?;
+    (range.start <= range.end).then_some(range)
+}
+
+/// The byte offset of `point`, if it is within the text and on a char boundary.
+fn to_byte(text: &dyn AnyText, point: api::editor::Point) -> Option<usize> {
+    let (line, col) = (point.line as usize, point.col as usize);
+    if line == text.len_lines() && col == 0 {
+        return Some(text.len_bytes());
+    }
+
+    let slice = text.line(line)?;
+    slice.to_cow().is_char_boundary(col).then(|| text.line_to_byte(line) + col)
+}
+
+/// Convert byte deltas to the plugin's deltas, `text` is the text the deltas apply to.
+fn from_deltas(text: &dyn AnyText, deltas: &Deltas<'_>) -> Vec<api::editor::Delta> {
+    deltas
+        .iter()
+        .map(|delta| {
+            let range = delta.range();
+            api::editor::Delta {
+                start: text.byte_to_point(range.start).into(),
+                end: text.byte_to_point(range.end).into(),
+                text: delta.text().to_owned(),
+            }
+        })
+        .collect()
 }
 
 pub type Store = wasmtime::Store<HostState>;
@@ -186,7 +335,7 @@ impl api::editor::HostBuffer for HostState {
         &mut self,
         buf: Resource<api::editor::Buffer>,
     ) -> Option<String> {
-        let buf_id = self.buffers[&buf.rep()];
+        let buf_id = self.buffer_id(&buf);
         self.client
             .with(move |editor| {
                 editor.buffer(buf_id).file_path().map(|p| p.to_string_lossy().into_owned())
@@ -198,19 +347,139 @@ impl api::editor::HostBuffer for HostState {
         &mut self,
         buf: Resource<api::editor::Buffer>,
     ) -> api::editor::BufferFlags {
-        let buf_id = self.buffers[&buf.rep()];
+        let buf_id = self.buffer_id(&buf);
         self.client
             .with(move |editor| editor.buffer(buf_id).flags().into())
             .await
     }
 
     async fn len_lines(&mut self, buf: Resource<api::editor::Buffer>) -> u32 {
-        let buf_id = self.buffers[&buf.rep()];
+        let buf_id = self.buffer_id(&buf);
         self.client
             .with(move |editor| editor.buffer(buf_id).text().len_lines() as u32)
             .await
     }
 
+    async fn get_id(&mut self, buf: Resource<api::editor::Buffer>) -> api::editor::BufferId {
+        self.buffer_id(&buf).data().as_ffi()
+    }
+
+    async fn get_text(&mut self, buf: Resource<api::editor::Buffer>) -> String {
+        let buf_id = self.buffer_id(&buf);
+        self.client.with(move |editor| editor.buffer(buf_id).text().to_string()).await
+    }
+
+    async fn get_slice(
+        &mut self,
+        buf: Resource<api::editor::Buffer>,
+        start: api::editor::Point,
+        end: api::editor::Point,
+    ) -> Result<String, api::editor::EditError> {
+        let buf_id = self.buffer_id(&buf);
+        self.client
+            .with(move |editor| {
+                let text = editor.buffer(buf_id).text();
+                let range =
+                    to_byte_range(text, start, end).ok_or(api::editor::EditError::InvalidRange)?;
+                Ok(text.byte_slice(range).to_string())
+            })
+            .await
+    }
+
+    async fn edit(
+        &mut self,
+        buf: Resource<api::editor::Buffer>,
+        deltas: Vec<api::editor::Delta>,
+    ) -> Result<(), api::editor::EditError> {
+        let buf_id = self.buffer_id(&buf);
+        self.client
+            .with(move |editor| {
+                let deltas = to_deltas(editor.buffer(buf_id).text(), deltas)
+                    .ok_or(api::editor::EditError::InvalidRange)?;
+                Ok(editor.edit(buf_id, &deltas)?)
+            })
+            .await
+    }
+
+    async fn subscribe_changes(&mut self, buf: Resource<api::editor::Buffer>) {
+        let buf_id = self.buffer_id(&buf);
+        self.subscriptions.write().changes.insert(buf_id);
+    }
+
+    async fn unsubscribe_changes(&mut self, buf: Resource<api::editor::Buffer>) {
+        let buf_id = self.buffer_id(&buf);
+        self.subscriptions.write().changes.remove(&buf_id);
+    }
+
+    async fn set_sign(
+        &mut self,
+        buf: Resource<api::editor::Buffer>,
+        ns: api::editor::NamespaceId,
+        line: api::editor::Line,
+        text: char,
+        hl: String,
+    ) -> Result<(), api::editor::EditError> {
+        let buf_id = self.buffer_id(&buf);
+        let ns = NamespaceId::from(KeyData::from_ffi(ns));
+        self.client
+            .with(move |editor| {
+                let byte = editor
+                    .buffer(buf_id)
+                    .text()
+                    .try_line_to_byte(line as usize)
+                    .ok_or(api::editor::EditError::InvalidRange)?;
+                let sign = Sign { text, hl: editor.highlight_id_by_name(hl) };
+                editor.create_mark(buf_id, ns, Mark::builder(byte).sign(sign));
+                Ok(())
+            })
+            .await
+    }
+
+    async fn set_virtual_text(
+        &mut self,
+        buf: Resource<api::editor::Buffer>,
+        ns: api::editor::NamespaceId,
+        line: api::editor::Line,
+        text: String,
+        hl: String,
+    ) -> Result<(), api::editor::EditError> {
+        let buf_id = self.buffer_id(&buf);
+        let ns = NamespaceId::from(KeyData::from_ffi(ns));
+        self.client
+            .with(move |editor| {
+                let byte = editor
+                    .buffer(buf_id)
+                    .text()
+                    .try_line_to_byte(line as usize)
+                    .ok_or(api::editor::EditError::InvalidRange)?;
+                let text = VirtualText { text: text.into(), hl: editor.highlight_id_by_name(hl) };
+                editor.create_mark(buf_id, ns, Mark::builder(byte).virtual_text(text));
+                Ok(())
+            })
+            .await
+    }
+
+    async fn clear_namespace(
+        &mut self,
+        buf: Resource<api::editor::Buffer>,
+        ns: api::editor::NamespaceId,
+        start: api::editor::Line,
+        end: api::editor::Line,
+    ) {
+        let buf_id = self.buffer_id(&buf);
+        let ns = NamespaceId::from(KeyData::from_ffi(ns));
+        self.client
+            .with(move |editor| {
+                let text = editor.buffer(buf_id).text();
+                let byte = |line: api::editor::Line| {
+                    text.try_line_to_byte(line as usize).unwrap_or(text.len_bytes())
+                };
+                let (start, end) = (byte(start), byte(end));
+                editor.clear_marks(buf_id, ns, start..end.max(start));
+            })
+            .await
+    }
+
     async fn drop(&mut self, buf: Resource<api::editor::Buffer>) -> wasmtime::Result<()> {
         self.buffers.remove(&buf.rep());
         Ok(())
@@ -235,6 +504,24 @@ impl api::editor::Host for HostState {
         self.push_view(view_id)
     }
 
+    async fn get_buffers(&mut self) -> Vec<Resource<api::editor::Buffer>> {
+        let buf_ids = self
+            .client
+            .with(|editor| editor.buffers().map(|buf| buf.id()).collect::<Vec<_>>())
+            .await;
+        buf_ids.into_iter().map(|id| self.push_buffer(id)).collect()
+    }
+
+    async fn get_buffer(
+        &mut self,
+        id: api::editor::BufferId,
+    ) -> Option<Resource<api::editor::Buffer>> {
+        let buf_id = BufferId::from(KeyData::from_ffi(id));
+        let exists =
+            self.client.with(move |editor| editor.buffers().any(|buf| buf.id() == buf_id)).await;
+        exists.then(|| self.push_buffer(buf_id))
+    }
+
     async fn save_all(&mut self, force: bool) -> Result<(), String> {
         command::save_all(&self.client, force).await.map_err(|e| e.to_string())
     }
@@ -262,6 +549,13 @@ impl api::editor::Host for HostState {
             .map_err(|e| e.to_string())
     }
 
+    async fn normal(&mut self, keys: String, remap: bool) -> Result<(), String> {
+        self.client
+            .with(move |editor| editor.normal(None, &keys, remap))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     async fn register_language(&mut self, filetype: String, language_services: Vec<String>) {
         self.client
             .with(move |editor| {
@@ -283,6 +577,142 @@ impl api::editor::Host for HostState {
             })
             .await
     }
+
+    async fn map(
+        &mut self,
+        modes: Vec<api::editor::Mode>,
+        lhs: String,
+        cmd: String,
+    ) -> Result<(), String> {
+        let modes = modes.into_iter().map(zi::Mode::from).collect::<Vec<_>>();
+        self.client
+            .with({
+                let (modes, lhs) = (modes.clone(), lhs.clone());
+                move |editor| editor.map_command(&modes, &lhs, &cmd)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        self.mappings.extend(modes.into_iter().map(|mode| (mode, lhs.clone())));
+        Ok(())
+    }
+
+    async fn unmap(&mut self, modes: Vec<api::editor::Mode>, lhs: String) -> Result<(), String> {
+        let modes = modes.into_iter().map(zi::Mode::from).collect::<Vec<_>>();
+        self.client
+            .with({
+                let (modes, lhs) = (modes.clone(), lhs.clone());
+                move |editor| editor.unmap(&modes, &lhs)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        self.mappings.retain(|(mode, mapped)| *mapped != lhs || !modes.contains(mode));
+        Ok(())
+    }
+
+    async fn map_sub_mode(&mut self, name: String, lhs: String, cmd: String) -> Result<(), String> {
+        self.client
+            .with({
+                let (name, lhs) = (name.clone(), lhs.clone());
+                move |editor| editor.map_sub_mode(&name, &lhs, &cmd)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        self.sub_mode_mappings.push((name, lhs));
+        Ok(())
+    }
+
+    async fn unmap_sub_mode(&mut self, name: String, lhs: String) -> Result<(), String> {
+        self.client
+            .with({
+                let (name, lhs) = (name.clone(), lhs.clone());
+                move |editor| editor.unmap_sub_mode(&name, &lhs)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        self.sub_mode_mappings.retain(|mapping| mapping.0 != name || mapping.1 != lhs);
+        Ok(())
+    }
+
+    async fn enter_sub_mode(&mut self, name: String) -> Result<(), String> {
+        self.client
+            .with(move |editor| editor.enter_sub_mode(&name))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn subscribe(
+        &mut self,
+        kind: api::editor::EventKind,
+        buf: Option<api::editor::BufferId>,
+        file_type: Option<String>,
+        mode: Option<api::editor::Mode>,
+    ) -> api::editor::Subscription {
+        let hook = Hook {
+            kind,
+            buf: buf.map(|id| BufferId::from(KeyData::from_ffi(id))),
+            file_type: file_type.map(|ft| zi::FileType::from_name(&ft)),
+            mode: mode.map(zi::Mode::from),
+        };
+
+        let id = self.next_subscription;
+        self.next_subscription += 1;
+        self.subscriptions.write().hooks.insert(id, hook);
+        id
+    }
+
+    async fn unsubscribe(&mut self, subscription: api::editor::Subscription) {
+        self.subscriptions.write().hooks.remove(&subscription);
+    }
+
+    async fn open_float(
+        &mut self,
+        text: String,
+        config: api::editor::FloatConfig,
+    ) -> Resource<api::editor::View> {
+        let config = zi::FloatConfig::from(config);
+        let view_id = self
+            .client
+            .with(move |editor| {
+                let buf = editor.create_readonly_buffer("float", text.into_bytes());
+                editor.open_float(buf, config)
+            })
+            .await;
+        self.push_view(view_id)
+    }
+
+    async fn open_picker(&mut self, title: String) -> Result<api::editor::PickerId, String> {
+        let picker = self
+            .client
+            .with(move |editor| editor.open_item_picker(&title))
+            .await
+            .map_err(|e| e.to_string())?;
+        self.subscriptions.write().pickers.insert(picker);
+        Ok(picker.data().as_ffi())
+    }
+
+    async fn push_picker_items(
+        &mut self,
+        picker: api::editor::PickerId,
+        items: Vec<api::editor::PickerItem>,
+    ) -> Result<(), String> {
+        let picker = ViewGroupId::from(KeyData::from_ffi(picker));
+        if !self.subscriptions.read().pickers.contains(&picker) {
+            return Err("picker not found".into());
+        }
+
+        let items = items
+            .into_iter()
+            .map(|item| zi::PickerItem { id: item.id, text: item.text })
+            .collect::<Vec<_>>();
+        self.client
+            .with(move |editor| editor.push_picker_items(picker, items))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn create_namespace(&mut self, name: String) -> api::editor::NamespaceId {
+        self.client.with(move |editor| editor.create_namespace(name.as_str())).await.data().as_ffi()
+    }
 }
 
 /// The plugin manager responsible for loading and running wasm plugins and keeping track of their state.
@@ -294,9 +724,50 @@ pub struct PluginManager {
 
 impl PluginManager {
     #[must_use]
-    fn add(&self, _name: impl Into<SmolStr>, tx: Sender<PluginRequest>) -> PluginId {
+    fn add(
+        &self,
+        _name: impl Into<SmolStr>,
+        tx: UnboundedSender<PluginRequest>,
+        subscriptions: Arc<RwLock<Subscriptions>>,
+    ) -> PluginId {
         // TODO name uniqueness check?
-        self.plugins.write().insert(PluginState { client: PluginClient(tx) })
+        self.plugins.write().insert(PluginState { client: PluginClient(tx), subscriptions })
+    }
+
+    /// Notify the plugins subscribed to the buffer of the change.
+    fn did_change_buffer(&self, event: &event::DidChangeBuffer) {
+        let mut deltas = None;
+        for state in self.plugins.read().values() {
+            if !state.subscriptions.read().changes.contains(&event.buf) {
+                continue;
+            }
+
+            let deltas =
+                deltas.get_or_insert_with(|| from_deltas(&*event.old_text, &event.deltas)).clone();
+            state.client.notify(PluginRequest::BufferChanged { buf: event.buf, deltas });
+        }
+    }
+
+    /// Notify the plugin that opened the picker of the confirmed item.
+    fn did_confirm_picker_item(&self, event: &event::DidConfirmPickerItem) {
+        for state in self.plugins.read().values() {
+            // The picker is closed once an item is confirmed.
+            if state.subscriptions.write().pickers.remove(&event.picker) {
+                let req = PluginRequest::PickerConfirmed { picker: event.picker, item: event.item };
+                state.client.notify(req);
+            }
+        }
+    }
+
+    /// Notify the plugins with a hook that matches the event.
+    fn dispatch_hook(&self, editor: &zi::Editor, event: HookEvent) {
+        let file_type = event.buf().map(|buf| editor.buffer(buf).file_type());
+        for state in self.plugins.read().values() {
+            let subscriptions = state.subscriptions.read();
+            if subscriptions.hooks.values().any(|hook| hook.matches(&event, file_type)) {
+                state.client.notify(PluginRequest::Hook(event.clone()));
+            }
+        }
     }
 
     fn with_plugin<F, T>(&self, id: PluginId, f: F) -> anyhow::Result<T>
@@ -333,7 +804,7 @@ slotmap::new_key_type! {
 }
 
 #[derive(Clone)]
-struct PluginClient(Sender<PluginRequest>);
+struct PluginClient(UnboundedSender<PluginRequest>);
 
 impl PluginClient {
     pub async fn execute(
@@ -344,13 +815,22 @@ impl PluginClient {
         force: bool,
     ) -> anyhow::Result<()> {
         let (tx, rx) = oneshot::channel();
-        self.0.send(PluginRequest::ExecuteCommand { name, range, args, force, tx }).await?;
+        self.0.send(PluginRequest::ExecuteCommand { name, range, args, force, tx })?;
         rx.await?
     }
+
+    /// Send the request without waiting for the plugin to catch up, for event handlers which are
+    /// synchronous. The queue is unbounded as a plugin that misses a change to a buffer would be
+    /// out of sync with it.
+    fn notify(&self, req: PluginRequest) {
+        // The plugin has stopped if the receiver is gone.
+        let _ = self.0.send(req);
+    }
 }
 
 struct PluginState {
     client: PluginClient,
+    subscriptions: Arc<RwLock<Subscriptions>>,
 }
 
 type Responder<T> = oneshot::Sender<anyhow::Result<T>>;
@@ -363,6 +843,15 @@ enum PluginRequest {
         force: bool,
         tx: Responder<()>,
     },
+    BufferChanged {
+        buf: BufferId,
+        deltas: Vec<api::editor::Delta>,
+    },
+    Hook(HookEvent),
+    PickerConfirmed {
+        picker: ViewGroupId,
+        item: u64,
+    },
 }
 
 #[async_trait::async_trait]
@@ -374,6 +863,43 @@ impl zi::plugin::PluginManager for PluginManager {
     async fn start(self: Arc<Self>, client: Client) -> anyhow::Result<()> {
         let engine = engine();
 
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidChangeBuffer>(move |_editor, event| {
+            plugins.did_change_buffer(event);
+            HandlerResult::Continue
+        });
+
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidOpenBuffer>(move |editor, event| {
+            plugins.dispatch_hook(editor, HookEvent::BufferOpen { buf: event.buf });
+            HandlerResult::Continue
+        });
+
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidSaveBuffer>(move |editor, event| {
+            plugins.dispatch_hook(editor, HookEvent::BufferWrite { buf: event.buf });
+            HandlerResult::Continue
+        });
+
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidMoveCursor>(move |editor, event| {
+            let event = HookEvent::CursorMove { buf: event.buf, cursor: event.cursor };
+            plugins.dispatch_hook(editor, event);
+            HandlerResult::Continue
+        });
+
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidChangeMode>(move |editor, event| {
+            plugins.dispatch_hook(editor, HookEvent::ModeChange { from: event.from, to: event.to });
+            HandlerResult::Continue
+        });
+
+        let plugins = self.clone();
+        event::subscribe_with::<event::DidConfirmPickerItem>(move |_editor, event| {
+            plugins.did_confirm_picker_item(event);
+            HandlerResult::Continue
+        });
+
         let components = self.load_plugin_components(engine).await?;
 
         let mut join_set = JoinSet::new();
@@ -409,6 +935,7 @@ impl zi::plugin::PluginManager for PluginManager {
             .into_boxed_slice();
 
         tracing::info!(n = plugin_hosts.len(), "instantiated plugins");
+        client.with(|editor| editor.record_startup("load plugins")).await;
 
         for host in plugin_hosts.into_vec() {
             join_set.spawn(host.start());
@@ -480,12 +1007,15 @@ impl PluginHost {
         let dep = self.plugin.zi_api_dependency();
         let name = dep.call_get_name(&mut self.store).await?;
 
+        // Add the plugin before initializing it so it is notified of changes to buffers it
+        // subscribes to during initialization.
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let subscriptions = self.store.data().subscriptions.clone();
+        let _id = self.manager.add(name, sender.clone(), subscriptions);
+
         let lifecycle = self.plugin.zi_api_lifecycle();
         let init = lifecycle.call_initialize(&mut self.store).await?;
 
-        let (sender, mut receiver) = mpsc::channel(16);
-        let _id = self.manager.add(name, sender.clone());
-
         let command = self.plugin.zi_api_command();
         self.handler = Some(command.handler().call_constructor(&mut self.store).await?);
 
@@ -499,7 +1029,7 @@ impl PluginHost {
 
             struct PluginExecutor {
                 name: Word,
-                sender: Sender<PluginRequest>,
+                sender: UnboundedSender<PluginRequest>,
             }
 
             impl zi::command::Executor for PluginExecutor {
@@ -514,15 +1044,13 @@ impl PluginHost {
                     let name = self.name.clone();
                     async move {
                         let (tx, rx) = oneshot::channel();
-                        sender
-                            .send(PluginRequest::ExecuteCommand {
-                                name: name.clone(),
-                                range,
-                                args,
-                                force,
-                                tx,
-                            })
-                            .await?;
+                        sender.send(PluginRequest::ExecuteCommand {
+                            name: name.clone(),
+                            range,
+                            args,
+                            force,
+                            tx,
+                        })?;
                         rx.await?
                     }
                     .boxed()
@@ -564,6 +1092,22 @@ impl PluginHost {
             handler.resource_drop_async(&mut self.store).await?;
         }
 
+        let state = self.store.data_mut();
+        let mappings = std::mem::take(&mut state.mappings);
+        let sub_mode_mappings = std::mem::take(&mut state.sub_mode_mappings);
+        state
+            .client()
+            .with(move |editor| {
+                // The mappings may have been removed by the user already.
+                for (mode, lhs) in mappings {
+                    let _ = editor.unmap(&[mode], &lhs);
+                }
+                for (name, lhs) in sub_mode_mappings {
+                    let _ = editor.unmap_sub_mode(&name, &lhs);
+                }
+            })
+            .await;
+
         Ok(())
     }
 
@@ -586,6 +1130,46 @@ impl PluginHost {
                     .await?;
                 let _ = tx.send(result.map_err(|e| anyhow::anyhow!("{e}")));
             }
+            PluginRequest::BufferChanged { buf, deltas } => {
+                let changes = deltas
+                    .iter()
+                    .map(|delta| events::Change {
+                        start: delta.start,
+                        end: delta.end,
+                        text: &delta.text,
+                    })
+                    .collect::<Box<_>>();
+                self.plugin
+                    .zi_api_events()
+                    .call_on_buffer_change(&mut self.store, buf.data().as_ffi(), &changes)
+                    .await?;
+            }
+            PluginRequest::Hook(event) => {
+                let events = self.plugin.zi_api_events();
+                let store = &mut self.store;
+                match event {
+                    HookEvent::BufferOpen { buf } => {
+                        events.call_on_buffer_open(store, buf.data().as_ffi()).await?
+                    }
+                    HookEvent::BufferWrite { buf } => {
+                        events.call_on_buffer_write(store, buf.data().as_ffi()).await?
+                    }
+                    HookEvent::CursorMove { buf, cursor } => {
+                        events
+                            .call_on_cursor_move(store, buf.data().as_ffi(), cursor.into())
+                            .await?
+                    }
+                    HookEvent::ModeChange { from, to } => {
+                        events.call_on_mode_change(store, from.into(), to.into()).await?
+                    }
+                }
+            }
+            PluginRequest::PickerConfirmed { picker, item } => {
+                self.plugin
+                    .zi_api_events()
+                    .call_on_picker_confirm(&mut self.store, picker.data().as_ffi(), item)
+                    .await?;
+            }
         }
 
         Ok(())
@@ -621,6 +1205,31 @@ mod test {
         Ok(plugins.into_boxed_slice())
     }
 
+    #[test]
+    fn adjacent_deltas() {
+        let point = |line, col| api::editor::Point { line, col };
+        let delta = |start, end, text: &str| api::editor::Delta { start, end, text: text.into() };
+        let text = "abcd\n";
+
+        let deltas = to_deltas(
+            &text,
+            vec![
+                delta(point(0, 2), point(0, 4), "y"),
+                delta(point(0, 0), point(0, 2), "x"),
+                delta(point(0, 2), point(0, 2), "-"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            deltas.iter().map(|d| (d.range(), d.text())).collect::<Vec<_>>(),
+            [(0..4, "x-y")]
+        );
+
+        let overlapping =
+            vec![delta(point(0, 0), point(0, 3), ""), delta(point(0, 2), point(0, 4), "")];
+        assert!(to_deltas(&text, overlapping).is_none());
+    }
+
     #[tokio::test]
     #[cfg_attr(test, mutants::skip)]
     async fn smoke() -> anyhow::Result<()> {
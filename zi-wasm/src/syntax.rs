@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ops::Bound;
+use std::ops::{Bound, Range};
 use std::sync::OnceLock;
 
 use parking_lot::RwLock;
@@ -9,8 +9,7 @@ use zi::tree_sitter::{
     self, InputEdit, Node, Parser, Query, QueryCapture, QueryCaptures, QueryCursor, Tree,
 };
 use zi::{
-    AnyText, AnyTextMut, AnyTextSlice, Delta, Deltas, FileType, Point, PointRange, Text, TextMut,
-    TextSlice, dirs,
+    AnyText, AnyTextSlice, Delta, Deltas, FileType, Point, PointRange, Text, TextSlice, dirs,
 };
 
 impl zi::Syntax for Syntax {
@@ -24,45 +23,32 @@ impl zi::Syntax for Syntax {
         self.highlights_query
     }
 
-    /// Set the text of the syntax tree.
-    /// Prefer using `edit` if you have a delta.
-    fn set(&mut self, text: &dyn AnyText) {
-        self.tree = PARSER.with(|parser| {
+    fn parse(&mut self, text: &dyn AnyText) -> Vec<Range<usize>> {
+        let tree = PARSER.with(|parser| {
             let mut parser = parser.borrow_mut();
             parser.set_language(&self.language).unwrap();
             parser.parse_with_options(
                 &mut |byte, _point| text.byte_slice(byte..).chunks().next().unwrap_or(""),
-                None,
+                self.tree.as_ref(),
                 None,
             )
         });
-    }
-
-    fn edit(
-        &mut self,
-        text: &mut dyn AnyTextMut,
-        deltas: &Deltas<'_>,
-    ) -> (Deltas<'static>, Option<Tree>) {
-        let deltas = match &mut self.tree {
-            Some(tree) => apply_deltas(text, deltas, tree),
-            _ => text.edit(deltas).to_owned(),
-        };
 
-        let prev_tree = PARSER.with(|parser| {
-            let mut parser = parser.borrow_mut();
-            parser.set_language(&self.language).unwrap();
-            if let Some(tree) = parser.parse_with_options(
-                &mut |byte, _point| text.byte_slice(byte..).chunks().next().unwrap_or(""),
-                self.tree.as_ref(),
-                None,
-            ) {
-                self.tree.replace(tree)
-            } else {
-                self.tree.clone()
-            }
-        });
+        let mut changed = vec![];
+        if let Some(tree) = tree {
+            changed = changed_lines(self.tree.as_ref(), &tree);
+            self.tree = Some(tree);
+        }
+        changed.extend(self.update_injections(text));
+        changed
+    }
 
-        (deltas, prev_tree)
+    fn edit(&mut self, text: &dyn AnyText, deltas: &Deltas<'_>) {
+        let trees = self
+            .tree
+            .iter_mut()
+            .chain(self.injections.iter_mut().filter_map(|layer| layer.tree.as_mut()));
+        edit_trees(text, deltas, trees);
     }
 
     fn highlights<'a, 'tree: 'a>(
@@ -85,13 +71,192 @@ impl zi::Syntax for Syntax {
             None => Box::new(Highlights::Empty),
         }
     }
+
+    fn injections(&self) -> Vec<&dyn zi::Syntax> {
+        self.injections.iter().map(|layer| layer as &dyn zi::Syntax).collect()
+    }
+
+    fn folds(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+    ) -> Option<Vec<Range<usize>>> {
+        let query = self.folds_query?;
+        let Some(tree) = &self.tree else { return Some(vec![]) };
+
+        let mut folds = vec![];
+        let mut captures = query_cursor.captures(
+            query,
+            tree.root_node(),
+            TextProvider(source.dyn_byte_slice((Bound::Unbounded, Bound::Unbounded))),
+        );
+        while let Some((m, idx)) = captures.next() {
+            let node = m.captures[*idx].node;
+            let (start, end) = (node.start_position(), node.end_position());
+            // A node ending at the start of a line does not include that line.
+            let end_row = if end.column == 0 { end.row.saturating_sub(1) } else { end.row };
+            if end_row > start.row {
+                folds.push(start.row..end_row + 1);
+            }
+        }
+
+        Some(folds)
+    }
+
+    fn indent(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        line: usize,
+    ) -> Option<usize> {
+        let query = self.indents_query?;
+        // Without a tree there's nothing to go on, so let the caller fall back to heuristics.
+        let tree = self.tree.as_ref()?;
+        let indent_idx = query.capture_index_for_name("indent");
+        let outdent_idx = query.capture_index_for_name("outdent");
+
+        // Tree-sitter columns are in bytes.
+        let first_col = source.line(line).map_or(0, |text| {
+            text.chars().take_while(|c| c.is_whitespace()).map(char::len_utf8).sum()
+        });
+
+        let (start, end) = (Point::new(0, 0), Point::new(line + 1, 0));
+        query_cursor.set_point_range(start.into()..end.into());
+        let mut captures = query_cursor.captures(
+            query,
+            tree.root_node(),
+            TextProvider(source.dyn_byte_slice((Bound::Unbounded, Bound::Unbounded))),
+        );
+
+        let mut rows = vec![];
+        let mut outdent = false;
+        while let Some((m, idx)) = captures.next() {
+            let capture = m.captures[*idx];
+            let (start, end) = (capture.node.start_position(), capture.node.end_position());
+            if Some(capture.index) == indent_idx
+                && start.row < line
+                && (end.row > line || (end.row == line && end.column > 0))
+            {
+                rows.push(start.row);
+            } else if Some(capture.index) == outdent_idx
+                && start.row == line
+                && start.column == first_col
+            {
+                outdent = true;
+            }
+        }
+
+        // Several nodes starting on the same line only indent once.
+        rows.sort_unstable();
+        rows.dedup();
+        Some(rows.len().saturating_sub(outdent as usize))
+    }
+
+    fn rainbows(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        range: PointRange,
+    ) -> Vec<(PointRange, usize)> {
+        let (Some(query), Some(tree)) = (self.rainbows_query, &self.tree) else { return vec![] };
+        let (Some(scope_idx), Some(bracket_idx)) = (
+            query.capture_index_for_name("rainbow.scope"),
+            query.capture_index_for_name("rainbow.bracket"),
+        ) else {
+            return vec![];
+        };
+
+        // The depth of a delimiter depends on the scopes before the range, so start from the top.
+        let start = tree_sitter::Point { row: 0, column: 0 };
+        query_cursor.set_point_range(start..range.end().into());
+        let mut captures = query_cursor.captures(
+            query,
+            tree.root_node(),
+            TextProvider(source.dyn_byte_slice((Bound::Unbounded, Bound::Unbounded))),
+        );
+
+        // The scopes enclosing the current capture, innermost last.
+        let mut scopes = Vec::<Node<'_>>::new();
+        let mut rainbows = vec![];
+        while let Some((m, idx)) = captures.next() {
+            let capture = m.captures[*idx];
+            let node = capture.node;
+            while scopes.last().is_some_and(|scope| {
+                node.start_byte() < scope.start_byte() || node.end_byte() > scope.end_byte()
+            }) {
+                scopes.pop();
+            }
+
+            if capture.index == scope_idx {
+                scopes.push(node);
+            } else if capture.index == bracket_idx {
+                let bracket = PointRange::new(node.start_position(), node.end_position());
+                if bracket.end() > range.start() {
+                    rainbows.push((bracket, scopes.len().saturating_sub(1)));
+                }
+            }
+        }
+
+        rainbows
+    }
+
+    fn conceals(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        range: PointRange,
+    ) -> Vec<(PointRange, zi::Conceal)> {
+        let Some(tree) = &self.tree else { return vec![] };
+        let query = self.highlights_query;
+
+        let mut conceals = vec![];
+        let mut matches = query_cursor.matches(
+            query,
+            smallest_node_that_covers_range(tree, range),
+            TextProvider(source.dyn_byte_slice((Bound::Unbounded, Bound::Unbounded))),
+        );
+        while let Some(m) = matches.next() {
+            let properties = query.property_settings(m.pattern_index);
+            let Some(property) = properties.iter().find(|property| &*property.key == "conceal")
+            else {
+                continue;
+            };
+
+            let substitute = property.value.as_deref().and_then(|value| value.chars().next());
+            // `(#set! @capture conceal ..)` only conceals that capture, otherwise all are concealed.
+            for capture in m
+                .captures
+                .iter()
+                .filter(|capture| property.capture_id.is_none_or(|id| id == capture.index as usize))
+            {
+                let node = capture.node;
+                conceals.push((
+                    PointRange::new(node.start_position(), node.end_position()),
+                    zi::Conceal { substitute },
+                ));
+            }
+        }
+
+        conceals
+    }
 }
 
+#[derive(Clone)]
 pub struct Syntax {
     file_type: FileType,
     language: tree_sitter::Language,
     highlights_query: &'static Query,
+    /// The optional `folds.scm` query, every capture is a foldable node.
+    folds_query: Option<&'static Query>,
+    /// The optional `injections.scm` query, see [`Syntax::update_injections`].
+    injections_query: Option<&'static Query>,
+    /// The optional `indents.scm` query of `@indent` and `@outdent` captures.
+    indents_query: Option<&'static Query>,
+    /// The optional `rainbows.scm` query of `@rainbow.scope` and `@rainbow.bracket` captures.
+    rainbows_query: Option<&'static Query>,
     tree: Option<Tree>,
+    /// The injected languages, each parsed over its injected ranges of the text.
+    injections: Vec<Syntax>,
 }
 
 /// The wasm engine to use for tree-sitter.
@@ -112,15 +277,25 @@ thread_local! {
 /// A cache of tree-sitter queries for each language.
 /// Creating a query and compiling a language is very expensive, so we cache them here forever.
 /// Not concerned about memory usage because these are not large, and there are not many languages.
-static QUERY_CACHE: OnceLock<RwLock<HashMap<FileType, (tree_sitter::Language, &'static Query)>>> =
-    OnceLock::new();
+static QUERY_CACHE: OnceLock<RwLock<HashMap<FileType, Queries>>> = OnceLock::new();
+
+/// The language with its highlights query and optional queries.
+#[derive(Clone)]
+struct Queries {
+    language: tree_sitter::Language,
+    highlights: &'static Query,
+    folds: Option<&'static Query>,
+    injections: Option<&'static Query>,
+    indents: Option<&'static Query>,
+    rainbows: Option<&'static Query>,
+}
 
 impl Syntax {
     #[tracing::instrument]
     pub fn for_file_type(file_type: FileType) -> anyhow::Result<Option<Self>> {
         let cache = QUERY_CACHE.get_or_init(Default::default);
         let read_guard = cache.read();
-        let (language, highlights_query) = match read_guard.get(&file_type) {
+        let queries = match read_guard.get(&file_type) {
             Some(cached) => cached.clone(),
             None => {
                 drop(read_guard);
@@ -150,13 +325,142 @@ impl Syntax {
                 let highlights_text = std::fs::read_to_string(highlights_path)?;
                 let highlights_query =
                     &*Box::leak(Box::new(Query::new(&language, &highlights_text)?));
-                cache.write().insert(file_type, (language.clone(), highlights_query));
-                (language, highlights_query)
+
+                let optional_query = |name: &str| -> anyhow::Result<Option<&'static Query>> {
+                    let path = grammar_dir.join(name);
+                    if !path.exists() {
+                        return Ok(None);
+                    }
+                    let text = std::fs::read_to_string(path)?;
+                    Ok(Some(&*Box::leak(Box::new(Query::new(&language, &text)?))))
+                };
+
+                let queries = Queries {
+                    folds: optional_query("folds.scm")?,
+                    injections: optional_query("injections.scm")?,
+                    indents: optional_query("indents.scm")?,
+                    rainbows: optional_query("rainbows.scm")?,
+                    highlights: highlights_query,
+                    language,
+                };
+                cache.write().insert(file_type, queries.clone());
+                queries
             }
         };
 
-        Ok(Some(Self { file_type, language, highlights_query, tree: None }))
+        Ok(Some(Self {
+            file_type,
+            language: queries.language,
+            highlights_query: queries.highlights,
+            folds_query: queries.folds,
+            injections_query: queries.injections,
+            indents_query: queries.indents,
+            rainbows_query: queries.rainbows,
+            tree: None,
+            injections: vec![],
+        }))
     }
+
+    /// Parse the languages injected by the injections query over their ranges of the text.
+    /// All injections of a language are combined into a single tree.
+    /// Only the host language is searched for injections, injected languages are not.
+    /// Returns the line ranges whose syntax changed in any injected language.
+    fn update_injections(&mut self, text: &dyn AnyText) -> Vec<Range<usize>> {
+        let (Some(query), Some(tree)) = (self.injections_query, &self.tree) else { return vec![] };
+        let Some(content_idx) = query.capture_index_for_name("injection.content") else {
+            return vec![];
+        };
+        let language_idx = query.capture_index_for_name("injection.language");
+
+        let mut ranges = HashMap::<FileType, Vec<tree_sitter::Range>>::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(
+            query,
+            tree.root_node(),
+            TextProvider(text.dyn_byte_slice((Bound::Unbounded, Bound::Unbounded))),
+        );
+        while let Some(m) = matches.next() {
+            // The language is either fixed with `(#set! injection.language "..")` or captured.
+            let properties = query.property_settings(m.pattern_index);
+            let language = match properties
+                .iter()
+                .find(|property| &*property.key == "injection.language")
+                .and_then(|property| property.value.as_deref())
+            {
+                Some(language) => language.to_owned(),
+                None => match language_idx.and_then(|idx| m.nodes_for_capture_index(idx).next()) {
+                    Some(node) => text.byte_slice(node.byte_range()).chunks().collect(),
+                    None => continue,
+                },
+            };
+
+            ranges
+                .entry(FileType::from_name(language.trim()))
+                .or_default()
+                .extend(m.nodes_for_capture_index(content_idx).map(|node| node.range()));
+        }
+
+        let mut changed = vec![];
+        let mut prev = std::mem::take(&mut self.injections);
+        for (file_type, mut ranges) in ranges {
+            ranges.sort_by_key(|range| range.start_byte);
+            ranges.dedup();
+
+            // Reuse the previous tree of the language so it is parsed incrementally.
+            let mut layer = match prev.iter().position(|layer| layer.file_type == file_type) {
+                Some(idx) => prev.swap_remove(idx),
+                None => match Self::for_file_type(file_type) {
+                    Ok(Some(layer)) => layer,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        tracing::error!(%file_type, %err, "failed to load injected language");
+                        continue;
+                    }
+                },
+            };
+
+            let tree = PARSER.with(|parser| {
+                let mut parser = parser.borrow_mut();
+                parser.set_language(&layer.language).unwrap();
+                if let Err(err) = parser.set_included_ranges(&ranges) {
+                    tracing::error!(%file_type, %err, "invalid injection ranges");
+                    return None;
+                }
+
+                let tree = parser.parse_with_options(
+                    &mut |byte, _point| text.byte_slice(byte..).chunks().next().unwrap_or(""),
+                    layer.tree.as_ref(),
+                    None,
+                );
+                parser.set_included_ranges(&[]).expect("passed invalid ranges");
+                tree
+            });
+            if let Some(tree) = &tree {
+                changed.extend(changed_lines(layer.tree.as_ref(), tree));
+            }
+            layer.tree = tree;
+            self.injections.push(layer);
+        }
+
+        // The languages no longer injected are no longer highlighted.
+        changed.extend(
+            prev.iter().filter_map(|layer| Some(lines(layer.tree.as_ref()?.root_node().range()))),
+        );
+        changed
+    }
+}
+
+/// The line ranges whose syntax differs between the trees, every line of `new` if there is no `old`.
+fn changed_lines(old: Option<&Tree>, new: &Tree) -> Vec<Range<usize>> {
+    match old {
+        Some(old) => old.changed_ranges(new).map(lines).collect(),
+        None => vec![lines(new.root_node().range())],
+    }
+}
+
+/// The 0-indexed lines the range spans.
+fn lines(range: tree_sitter::Range) -> Range<usize> {
+    range.start_point.row..range.end_point.row + 1
 }
 
 fn smallest_node_that_covers_range(tree: &Tree, range: PointRange) -> Node<'_> {
@@ -177,14 +481,16 @@ fn smallest_node_that_covers_range(tree: &Tree, range: PointRange) -> Node<'_> {
     cursor.node()
 }
 
-fn apply_deltas(
-    text: &mut dyn AnyTextMut,
+fn edit_trees<'a>(
+    text: &dyn AnyText,
     deltas: &Deltas<'_>,
-    tree: &mut Tree,
-) -> Deltas<'static> {
+    trees: impl IntoIterator<Item = &'a mut Tree>,
+) {
     // Since deltas are sorted in descending range and are disjoint, we can apply them in without interference.
-    deltas.iter().for_each(|delta| tree.edit(&delta_to_ts_edit(text.as_text(), delta)));
-    text.edit(deltas)
+    let edits = deltas.iter().map(|delta| delta_to_ts_edit(text, delta)).collect::<Vec<_>>();
+    for tree in trees {
+        edits.iter().for_each(|edit| tree.edit(edit));
+    }
 }
 
 // tree-sitter's `point.column` is byte-indexed, but very poorly documented
@@ -339,4 +645,72 @@ mod tests {
             assert_eq!(edit, input);
         }
     }
+
+    #[test]
+    fn parse_changed_lines() -> anyhow::Result<()> {
+        use zi::Syntax as _;
+
+        let mut text = String::from("fn a() {}\nfn b() {}\nfn c() {}\n");
+        let mut syntax =
+            Syntax::for_file_type(zi::filetype!(rust))?.expect("rust grammar should be installed");
+        // Everything changed without a previous tree.
+        let changed = syntax.parse(&text);
+        assert!((0..3).all(|line| changed.iter().any(|lines| lines.contains(&line))));
+
+        let range = text.find("{}\nfn c").unwrap()..text.find("\nfn c").unwrap();
+        let deltas = Deltas::new([Delta::new(range.clone(), "{ 1 }")]);
+        syntax.edit(&text, &deltas);
+        text.replace_range(range, "{ 1 }");
+        let changed = syntax.parse(&text);
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|lines| lines.start == 1 && lines.end == 2), "{changed:?}");
+
+        assert!(syntax.parse(&text).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_fence_injection() -> anyhow::Result<()> {
+        use zi::Syntax as _;
+
+        /// The captures of the injected rust highlights with the text they cover.
+        fn rust_captures(syntax: &Syntax, text: &str) -> Vec<(String, String)> {
+            let injections = syntax.injections();
+            let [rust] = &injections[..] else { panic!("expected only rust to be injected") };
+            assert_eq!(rust.file_type(), zi::filetype!(rust));
+
+            let (source, mut cursor) = (text.to_owned(), QueryCursor::new());
+            let range = PointRange::new((0usize, 0usize), (usize::MAX, usize::MAX));
+            rust.highlights(&mut cursor, &source, range)
+                .map(|capture| {
+                    let name = rust.capture_index_to_name(capture.index).to_owned();
+                    (name, text[capture.node.byte_range()].to_owned())
+                })
+                .collect()
+        }
+
+        let mut text = String::from("# Title\n\n```rust\nfn main() {}\n```\n");
+        let mut syntax = Syntax::for_file_type(zi::filetype!(markdown))?
+            .expect("markdown grammar should be installed");
+        syntax.parse(&text);
+
+        let captures = rust_captures(&syntax, &text);
+        assert!(captures.iter().any(|(name, text)| name.starts_with("keyword") && text == "fn"));
+        // Only the contents of the fence are parsed as rust.
+        assert!(captures.iter().all(|(_, text)| !text.contains('#') && !text.contains('`')));
+
+        // The injected tree is edited and reparsed along with the host tree.
+        let start = text.find("fn").unwrap();
+        let deltas = Deltas::new([Delta::new(start..start + "fn main() {}".len(), "struct S;")]);
+        syntax.edit(&text, &deltas);
+        text.replace_range(start..start + "fn main() {}".len(), "struct S;");
+        syntax.parse(&text);
+
+        let captures = rust_captures(&syntax, &text);
+        assert!(
+            captures.iter().any(|(name, text)| name.starts_with("keyword") && text == "struct")
+        );
+        assert!(captures.iter().all(|(_, text)| text != "fn"));
+        Ok(())
+    }
 }
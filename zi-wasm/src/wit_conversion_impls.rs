@@ -39,6 +39,14 @@ impl From<zi::Operator> for api::editor::Operator {
             zi::Operator::Change => api::editor::Operator::Change,
             zi::Operator::Delete => api::editor::Operator::Delete,
             zi::Operator::Yank => api::editor::Operator::Yank,
+            zi::Operator::Filter => api::editor::Operator::Filter,
+            zi::Operator::Fold => api::editor::Operator::Fold,
+            zi::Operator::Reindent => api::editor::Operator::Reindent,
+            zi::Operator::Surround => api::editor::Operator::Surround,
+            zi::Operator::Comment => api::editor::Operator::Comment,
+            zi::Operator::Lowercase => api::editor::Operator::Lowercase,
+            zi::Operator::Uppercase => api::editor::Operator::Uppercase,
+            zi::Operator::ToggleCase => api::editor::Operator::ToggleCase,
         }
     }
 }
@@ -49,6 +57,14 @@ impl From<api::editor::Operator> for zi::Operator {
             api::editor::Operator::Change => zi::Operator::Change,
             api::editor::Operator::Delete => zi::Operator::Delete,
             api::editor::Operator::Yank => zi::Operator::Yank,
+            api::editor::Operator::Filter => zi::Operator::Filter,
+            api::editor::Operator::Fold => zi::Operator::Fold,
+            api::editor::Operator::Reindent => zi::Operator::Reindent,
+            api::editor::Operator::Surround => zi::Operator::Surround,
+            api::editor::Operator::Comment => zi::Operator::Comment,
+            api::editor::Operator::Lowercase => zi::Operator::Lowercase,
+            api::editor::Operator::Uppercase => zi::Operator::Uppercase,
+            api::editor::Operator::ToggleCase => zi::Operator::ToggleCase,
         }
     }
 }
@@ -94,6 +110,26 @@ impl From<api::editor::VerticalAlignment> for zi::VerticalAlignment {
     }
 }
 
+impl From<api::editor::FloatAnchor> for zi::FloatAnchor {
+    fn from(anchor: api::editor::FloatAnchor) -> Self {
+        match anchor {
+            api::editor::FloatAnchor::Editor(pos) => zi::FloatAnchor::Editor { x: pos.x, y: pos.y },
+            api::editor::FloatAnchor::Cursor(offset) => {
+                zi::FloatAnchor::Cursor { x: offset.x, y: offset.y }
+            }
+            api::editor::FloatAnchor::Center => zi::FloatAnchor::Center,
+        }
+    }
+}
+
+impl From<api::editor::FloatConfig> for zi::FloatConfig {
+    fn from(config: api::editor::FloatConfig) -> Self {
+        zi::FloatConfig::new(config.anchor.into(), config.width, config.height)
+            .with_border(config.border)
+            .with_focusable(config.focusable)
+    }
+}
+
 impl From<zi::BufferFlags> for api::editor::BufferFlags {
     fn from(flags: zi::BufferFlags) -> Self {
         let mut out = api::editor::BufferFlags::empty();
@@ -1,31 +1,40 @@
 mod explorer;
 mod inspector;
 mod mark;
+mod output;
 pub mod picker;
+mod terminal;
 mod text;
 
 use std::any::Any;
 use std::fmt;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use encoding_rs::Encoding;
 use stdx::sync::Cancel;
 use tree_sitter::QueryCursor;
-use unicode_width::UnicodeWidthChar;
 use zi_core::BufferId;
-use zi_text::{AnyText, Delta, Deltas};
+use zi_input::KeyEvent;
+use zi_text::{AnyText, ContentHash, Delta, Deltas, LineEnding, Text, TextSlice};
 
 pub use self::explorer::ExplorerBuffer;
 pub use self::inspector::InspectorBuffer;
 use self::mark::Marks;
-pub use self::mark::{Mark, MarkBuilder, MarkId};
+pub use self::mark::{Conceal, Mark, MarkBuilder, MarkId, Sign, VirtualText};
+pub use self::output::OutputBuffer;
+pub(crate) use self::output::OutputSink;
 pub use self::picker::PickerBuffer;
+pub use self::terminal::TerminalBuffer;
 pub use self::text::TextBuffer;
 use crate::config::Setting;
 use crate::editor::{Resource, Selector};
+use crate::fuzzy;
 use crate::keymap::Keymap;
 use crate::private::Internal;
 use crate::syntax::{HighlightId, Syntax, Theme};
-use crate::{Client, Editor, FileType, Point, PointRange, Size, Url, View};
+use crate::{Client, Editor, FileType, Mode, Point, PointRange, Size, Url, View};
 
 impl Selector<Self> for BufferId {
     #[inline]
@@ -60,6 +69,32 @@ pub struct Settings {
     pub tab_width: Setting<u8>,
     pub indent: Setting<IndentSettings>,
     pub format_on_save: Setting<bool>,
+    /// The line ending convention of the file. The buffer text itself always uses `\n`,
+    /// this is applied when the buffer is written.
+    pub line_ending: Setting<LineEnding>,
+    /// The encoding of the file. The buffer text itself is always utf-8,
+    /// this is applied when the buffer is written.
+    pub encoding: Setting<&'static Encoding>,
+    /// Ensure the file ends with a newline when it is written.
+    pub fix_end_of_line: Setting<bool>,
+    /// Remove whitespace at the end of each line when the buffer is written, on by default as this
+    /// replaces trimming the line on leaving insert mode.
+    pub trim_trailing_whitespace: Setting<bool>,
+    /// Show the last commit to touch the cursor line at the end of it.
+    pub git_blame: Setting<bool>,
+    /// How fold ranges are computed.
+    pub fold_method: Setting<FoldMethod>,
+    /// The preferred maximum length of a line, a color column is shown just past it.
+    pub max_line_length: Setting<Option<u16>>,
+    /// Insert the closing bracket or quote when typing an opening one in insert mode.
+    pub autopairs: Setting<bool>,
+    /// Highlight misspelled words in comments and strings, or anywhere in plain text.
+    pub spell: Setting<bool>,
+    /// The comma-separated languages of the dictionaries to spell check with, e.g. `en_US,de_DE`.
+    pub spell_lang: Setting<String>,
+    /// The file words added with `zg` are appended to, `spell/{lang}.add` in the config directory
+    /// if unset.
+    pub spell_file: Setting<Option<PathBuf>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -68,12 +103,44 @@ pub enum IndentSettings {
     Tabs,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FoldMethod {
+    /// Fold by the `folds.scm` query of the language, falling back to indentation if there is none.
+    #[default]
+    Syntax,
+    /// Fold lines that are indented further than the line before them.
+    Indent,
+}
+
+impl FromStr for FoldMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "syntax" => Ok(Self::Syntax),
+            "indent" => Ok(Self::Indent),
+            _ => anyhow::bail!("unknown fold method: {s} (expected `indent` or `syntax`)"),
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             tab_width: Setting::new(4),
             indent: Setting::new(IndentSettings::Spaces(4)),
             format_on_save: Setting::new(true),
+            line_ending: Setting::new(LineEnding::Lf),
+            encoding: Setting::new(encoding_rs::UTF_8),
+            fix_end_of_line: Setting::new(true),
+            trim_trailing_whitespace: Setting::new(true),
+            git_blame: Setting::new(false),
+            fold_method: Setting::new(FoldMethod::default()),
+            max_line_length: Setting::new(None),
+            autopairs: Setting::new(false),
+            spell: Setting::new(false),
+            spell_lang: Setting::new("en_US".to_string()),
+            spell_file: Setting::new(None),
         }
     }
 }
@@ -85,10 +152,11 @@ pub struct Highlight {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct SyntaxHighlight {
+pub struct SyntaxHighlight<'a> {
     pub range: PointRange,
     pub id: HighlightId,
-    pub capture_idx: u32,
+    /// The name of the capture in the highlights query of the language the highlight is from.
+    pub capture_name: &'a str,
 }
 
 impl Resource for Buffer {
@@ -133,17 +201,25 @@ pub(crate) trait BufferHistory {
     fn snapshot(&mut self, flags: SnapshotFlags);
 
     fn snapshot_cursor(&mut self, cursor: Point);
+
+    /// Defer snapshots until the matching [`BufferHistory::end_undo_group`] so that all changes
+    /// in between are undone together. Groups may be nested.
+    fn start_undo_group(&mut self);
+
+    fn end_undo_group(&mut self);
 }
 
 // This wraps the trait to provide common functionality and to make it easier to control method privacy.
 pub struct Buffer {
     marks: Marks,
+    /// The lines whose syntax changed by [`Buffer::reparse`] since they were last rendered.
+    reparsed: Vec<Range<usize>>,
     inner: Box<dyn BufferInternal>,
 }
 
 impl Buffer {
     pub(crate) fn new(buffer: impl BufferInternal + 'static) -> Self {
-        Self { inner: buffer.boxed(), marks: Marks::default() }
+        Self { inner: buffer.boxed(), marks: Marks::default(), reparsed: vec![] }
     }
 
     pub fn id(&self) -> BufferId {
@@ -166,6 +242,13 @@ impl Buffer {
         self.inner.text()
     }
 
+    /// A hash of the contents of the buffer to compare it with other text.
+    /// Text buffers cache the hash until the next edit, so only the first call after an edit is
+    /// linear in the length of the text.
+    pub fn content_hash(&self) -> ContentHash {
+        self.inner.content_hash()
+    }
+
     pub fn file_url(&self) -> Option<&Url> {
         self.inner.file_url()
     }
@@ -213,10 +296,35 @@ impl Buffer {
         self.inner.on_leave(Internal(()));
     }
 
+    pub(crate) fn intercept_insert_key(&mut self, key: &KeyEvent) -> Option<Mode> {
+        self.inner.intercept_insert_key(Internal(()), key)
+    }
+
     pub(crate) fn pre_render(&mut self, client: &Client, view: &View, area: tui::Rect) {
         self.inner.pre_render(Internal(()), client, view, area);
     }
 
+    pub(crate) fn set_theme(&mut self, theme: &Theme) {
+        self.inner.set_theme(Internal(()), theme);
+    }
+
+    pub(crate) fn set_path(&mut self, path: &Path) {
+        self.inner.set_path(Internal(()), path);
+    }
+
+    /// Replace the readonly implementation of the buffer with a writable one.
+    /// Returns whether the buffer could be made writable, the marks are kept either way.
+    pub(crate) fn make_writable(&mut self) -> bool {
+        match self.inner.writable(Internal(())) {
+            Some(inner) => {
+                assert_eq!(inner.id(), self.id());
+                self.inner = inner;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub(crate) fn snapshot(&mut self, flags: SnapshotFlags) {
         self.inner.snapshot(flags);
     }
@@ -225,6 +333,14 @@ impl Buffer {
         self.inner.snapshot_cursor(cursor);
     }
 
+    pub(crate) fn start_undo_group(&mut self) {
+        self.inner.start_undo_group();
+    }
+
+    pub(crate) fn end_undo_group(&mut self) {
+        self.inner.end_undo_group();
+    }
+
     pub(crate) fn undo(&mut self) -> Option<UndoEntry> {
         self.inner.undo()
     }
@@ -242,7 +358,7 @@ impl Buffer {
         editor: &Editor,
         cursor: &'a mut QueryCursor,
         range: PointRange,
-    ) -> Box<dyn Iterator<Item = SyntaxHighlight> + 'a> {
+    ) -> Box<dyn Iterator<Item = SyntaxHighlight<'a>> + 'a> {
         self.inner.syntax_highlights(editor, cursor, range)
     }
 
@@ -259,11 +375,42 @@ impl Buffer {
         self.inner.syntax()
     }
 
-    pub(crate) fn char_width(&self, c: char) -> usize {
-        c.width().unwrap_or(match c {
-            '\t' => *self.settings().tab_width.read() as usize,
-            _ => 0,
-        })
+    /// Parse the syntax now if it is out of date rather than waiting for the background parse
+    /// started on render. Rendering can make do with the stale tree, but indentation and folds
+    /// computed from it would be wrong.
+    pub fn reparse(&mut self) {
+        let lines = self.inner.reparse(Internal(()));
+        self.reparsed.extend(lines);
+    }
+
+    /// Take the lines whose syntax changed by [`Buffer::reparse`] to render them again.
+    pub(crate) fn take_reparsed(&mut self) -> Vec<Range<usize>> {
+        std::mem::take(&mut self.reparsed)
+    }
+
+    /// The 0-indexed display column of `point`, the number of cells of its line before it.
+    /// Tabs extend to the next multiple of the tab width and wide characters occupy two cells.
+    pub fn display_col(&self, point: Point) -> usize {
+        let tab_width = *self.settings().tab_width.read();
+        let Some(line) = self.text().line(point.line()) else { return 0 };
+        line.byte_to_display_col(point.col().min(line.len_bytes()), tab_width)
+    }
+
+    /// The point of the grapheme cluster of `line` displayed at the 0-indexed display column `col`,
+    /// or of the last cluster of the line if `col` is past its end.
+    pub fn point_at_display_col(&self, line: usize, col: usize) -> Point {
+        let tab_width = *self.settings().tab_width.read();
+        let mut point = Point::new(line, 0);
+        let Some(text) = self.text().line(line) else { return point };
+        let mut end = 0;
+        for (byte, grapheme) in text.graphemes() {
+            point = Point::new(line, byte);
+            end += zi_text::grapheme_width(&grapheme, end, tab_width);
+            if end > col {
+                break;
+            }
+        }
+        point
     }
 }
 
@@ -286,6 +433,10 @@ pub(crate) trait BufferInternal: Send + Sync {
 
     fn as_any(&self) -> &dyn Any;
 
+    fn content_hash(&self) -> ContentHash {
+        ContentHash::new(self.text())
+    }
+
     fn path(&self) -> Option<PathBuf> {
         self.file_path()
     }
@@ -317,6 +468,12 @@ pub(crate) trait BufferInternal: Send + Sync {
         None
     }
 
+    /// Parse the syntax now if it is out of date, see [`Buffer::reparse`].
+    /// Returns the line ranges whose syntax changed.
+    fn reparse(&mut self, _: Internal) -> Vec<Range<usize>> {
+        vec![]
+    }
+
     /// Syntax highlights iterator.
     /// All ranges must be single-line ranges.
     /// The highlights must cover at least the given point range, it is valid to return
@@ -328,7 +485,7 @@ pub(crate) trait BufferInternal: Send + Sync {
         editor: &Editor,
         cursor: &'a mut QueryCursor,
         range: PointRange,
-    ) -> Box<dyn Iterator<Item = SyntaxHighlight> + 'a> {
+    ) -> Box<dyn Iterator<Item = SyntaxHighlight<'a>> + 'a> {
         let _ = (editor, cursor, range);
         Box::new(std::iter::empty())
     }
@@ -359,6 +516,12 @@ pub(crate) trait BufferInternal: Send + Sync {
         None
     }
 
+    /// Called for every key in insert mode before the keymap is consulted.
+    /// Returning `Some(mode)` consumes the key and switches to `mode`.
+    fn intercept_insert_key(&mut self, _: Internal, _key: &KeyEvent) -> Option<Mode> {
+        None
+    }
+
     /// Called just before rendering the buffer, returns whether the buffer needs to be re-rendered.
     fn pre_render(&mut self, _: Internal, _client: &Client, view: &View, _area: tui::Rect) {
         assert_eq!(self.id(), view.buffer());
@@ -366,6 +529,25 @@ pub(crate) trait BufferInternal: Send + Sync {
 
     /// Called when a view is closed that was displaying this buffer
     fn on_leave(&mut self, _: Internal) {}
+
+    /// Called when the theme changes, any highlight ids derived from the previous theme must be
+    /// recomputed.
+    #[doc(hidden)]
+    fn set_theme(&mut self, _: Internal, _theme: &Theme) {}
+
+    /// Point the buffer at the file at `path`, e.g. after the file has been renamed.
+    /// Buffers that are not backed by a file ignore this.
+    #[doc(hidden)]
+    fn set_path(&mut self, _: Internal, path: &Path) {
+        let _ = path;
+    }
+
+    /// A writable buffer with the same id and contents to replace this readonly buffer with.
+    /// The state of this buffer may be moved out as it is dropped afterwards.
+    #[doc(hidden)]
+    fn writable(&mut self, _: Internal) -> Option<Box<dyn BufferInternal>> {
+        None
+    }
 }
 
 impl dyn BufferInternal + '_ {
@@ -399,6 +581,20 @@ impl dyn BufferInternal + '_ {
             h.snapshot_cursor(cursor)
         }
     }
+
+    #[inline]
+    pub(crate) fn start_undo_group(&mut self) {
+        if let Some(h) = self.history_mut(Internal(())) {
+            h.start_undo_group()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn end_undo_group(&mut self) {
+        if let Some(h) = self.history_mut(Internal(())) {
+            h.end_undo_group()
+        }
+    }
 }
 
 // NOTE: remember to add all the methods to the Box<dyn Buffer> impl below, including default methods
@@ -458,11 +654,21 @@ impl BufferInternal for Box<dyn BufferInternal> {
         self.as_ref().version()
     }
 
+    #[inline]
+    fn content_hash(&self) -> ContentHash {
+        self.as_ref().content_hash()
+    }
+
     #[inline]
     fn syntax(&self) -> Option<&dyn Syntax> {
         self.as_ref().syntax()
     }
 
+    #[inline]
+    fn reparse(&mut self, internal: Internal) -> Vec<Range<usize>> {
+        self.as_mut().reparse(internal)
+    }
+
     #[inline]
     fn as_any(&self) -> &dyn Any {
         self.as_ref().as_any()
@@ -489,7 +695,7 @@ impl BufferInternal for Box<dyn BufferInternal> {
         editor: &Editor,
         cursor: &'a mut QueryCursor,
         range: PointRange,
-    ) -> Box<dyn Iterator<Item = SyntaxHighlight> + 'a> {
+    ) -> Box<dyn Iterator<Item = SyntaxHighlight<'a>> + 'a> {
         self.as_ref().syntax_highlights(editor, cursor, range)
     }
 
@@ -516,6 +722,11 @@ impl BufferInternal for Box<dyn BufferInternal> {
         self.as_mut().keymap(internal)
     }
 
+    #[inline]
+    fn intercept_insert_key(&mut self, internal: Internal, key: &KeyEvent) -> Option<Mode> {
+        self.as_mut().intercept_insert_key(internal, key)
+    }
+
     #[inline]
     fn pre_render(&mut self, internal: Internal, client: &Client, view: &View, area: tui::Rect) {
         self.as_mut().pre_render(internal, client, view, area)
@@ -525,28 +736,44 @@ impl BufferInternal for Box<dyn BufferInternal> {
     fn on_leave(&mut self, internal: Internal) {
         self.as_mut().on_leave(internal);
     }
+
+    #[inline]
+    fn set_theme(&mut self, internal: Internal, theme: &Theme) {
+        self.as_mut().set_theme(internal, theme);
+    }
+
+    #[inline]
+    fn set_path(&mut self, internal: Internal, path: &Path) {
+        self.as_mut().set_path(internal, path);
+    }
+
+    #[inline]
+    fn writable(&mut self, internal: Internal) -> Option<Box<dyn BufferInternal>> {
+        self.as_mut().writable(internal)
+    }
 }
 
 pub trait Entry: fmt::Display + fmt::Debug + Clone + Sync + Send + 'static {}
 
 impl<T> Entry for T where T: fmt::Display + fmt::Debug + Clone + Sync + Send + 'static {}
 
-/// Wrapper around a `nucleo::Injector` with cancellation support
+/// Wrapper around a `fuzzy::Injector` with cancellation support
 #[derive(Clone)]
 pub struct Injector<T> {
-    injector: nucleo::Injector<T>,
+    injector: fuzzy::Injector<T>,
     cancel: Cancel,
 }
 
 impl<T: Entry> Injector<T> {
-    pub fn new(injector: nucleo::Injector<T>, cancel: Cancel) -> Self {
+    pub(crate) fn new(injector: fuzzy::Injector<T>, cancel: Cancel) -> Self {
         Self { injector, cancel }
     }
 
     /// Push an item into the injector
     /// Returns `Err` if the injector has been cancelled
     pub(crate) fn push(&self, item: T) -> Result<(), ()> {
-        self.injector.push(item.clone(), |_, dst| dst[0] = format!("{item}").into());
+        let haystack = format!("{item}");
+        self.injector.push(item, haystack);
         if self.cancel.is_cancelled() { Err(()) } else { Ok(()) }
     }
 }
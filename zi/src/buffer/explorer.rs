@@ -1,10 +1,10 @@
 use std::path::MAIN_SEPARATOR;
 use std::sync::Arc;
-
-use nucleo::Nucleo;
+use std::time::Duration;
 
 use super::*;
 use crate::editor::{Action, get};
+use crate::fuzzy::Matcher;
 use crate::syntax::HighlightName;
 use crate::{Mode, filetype, hashmap, trie};
 
@@ -13,7 +13,7 @@ pub struct ExplorerBuffer<T: Entry, F: 'static> {
     path: PathBuf,
     url: Url,
     text: String,
-    nucleo: Nucleo<T>,
+    matcher: Matcher<T>,
     cancel: Cancel,
     keymap: Keymap,
     confirm: F,
@@ -28,33 +28,39 @@ where
     pub fn new(
         id: BufferId,
         path: PathBuf,
-        config: nucleo::Config,
         notify: impl Fn() + Send + Sync + 'static,
         confirm: F,
     ) -> (Self, Injector<T>) {
-        let nucleo = Nucleo::new(config, Arc::new(notify), None, 1);
+        let matcher = Matcher::new(Arc::new(notify));
         let cancel = Cancel::new();
-        let injector = Injector::new(nucleo.injector(), cancel.clone());
+        let injector = Injector::new(matcher.injector(), cancel.clone());
         let keymap = {
             let noop: Action = |_| {};
             let confirm: Action = |editor| {
                 let (view, buf) = get!(editor as Self);
                 let cursor = view.cursor();
-                if let Some(data) = buf
-                    .nucleo
-                    .snapshot()
-                    .get_matched_item(cursor.line() as u32)
-                    .map(|item| item.data.clone())
-                {
+                if let Some(data) = buf.matcher.get_matched_item(cursor.line()) {
                     let confirm = buf.confirm;
                     confirm(editor, data);
                 }
             };
+            let create: Action = |editor| editor.command_mode_with("create ");
+            let rename: Action = |editor| editor.prompt_explorer_rename();
+            let trash: Action = |editor| editor.command_mode_with("trash");
+            let refresh: Action = |editor| {
+                if let Err(err) = editor.refresh_explorer() {
+                    editor.set_error(err);
+                }
+            };
             Keymap::from(hashmap! {
                 Mode::Normal => trie! ({
                     // Prevent the user from using insert mode in this buffer
                     "i" => noop,
                     "<CR>" => confirm,
+                    "a" => create,
+                    "r" => rename,
+                    "d" => trash,
+                    "R" => refresh,
                 }),
             })
         };
@@ -62,7 +68,7 @@ where
             Self {
                 id,
                 path,
-                nucleo,
+                matcher,
                 cancel,
                 keymap,
                 confirm,
@@ -133,15 +139,13 @@ impl<T: Entry, F: Send + Sync> BufferInternal for ExplorerBuffer<T, F> {
     }
 
     fn pre_render(&mut self, _: Internal, _client: &Client, _view: &View, area: tui::Rect) {
-        if !self.nucleo.tick(10).changed {
+        if !self.matcher.tick(Duration::from_millis(10)) {
             return;
         }
 
-        let snapshot = self.nucleo.snapshot();
         self.text.clear();
-        let n = snapshot.matched_item_count().min(area.height as u32);
-        for item in snapshot.matched_items(..n) {
-            self.text.push_str(&format!("{}\n", item.data));
+        for (item, _) in self.matcher.matched_items(area.height as usize) {
+            self.text.push_str(&format!("{item}\n"));
         }
     }
 
@@ -87,16 +87,10 @@ impl BufferInternal for InspectorBuffer {
                     )
                     .find(|hl| hl.range.contains(&cursor))
                 {
-                    Some(hl) => {
-                        let capture_name = target_buffer
-                            .syntax()
-                            .expect("if buffer has syntax highlights it must have syntax")
-                            .capture_index_to_name(hl.capture_idx);
-                        match hl.id.style(&editor.theme().read()) {
-                            Some(style) => format!("{capture_name} -> {style}"),
-                            None => capture_name.to_string(),
-                        }
-                    }
+                    Some(hl) => match hl.id.style(&editor.theme().read()) {
+                        Some(style) => format!("{} -> {style}", hl.capture_name),
+                        None => hl.capture_name.to_string(),
+                    },
                     None => "no highlights".to_string(),
                 }
             };
@@ -1,18 +1,20 @@
 use std::collections::HashMap;
 use std::ops::{Range, RangeBounds};
+use std::sync::Arc;
 
 use itertools::Itertools;
-use slotmap::{Key, KeyData, SlotMap};
-use zi_marktree::{Bias, MarkTree, MarkTreeId};
+use slotmap::Key;
+use zi_marktree::{Bias, MarkTree, MarkTreeId, TaggedId};
 use zi_text::Deltas;
 
 use super::Buffer;
 use crate::NamespaceId;
 use crate::syntax::HighlightId;
 
-slotmap::new_key_type! {
-    pub struct MarkId;
-}
+/// The id of a mark, its namespace is encoded in the tag. Ids are allocated from a counter per
+/// namespace and never reused, so the id of a deleted (or replaced) mark never refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MarkId(TaggedId);
 
 impl Buffer {
     /// Create a new mark in the given namespace.
@@ -46,91 +48,109 @@ impl Buffer {
     ) -> impl Iterator<Item = (NamespaceId, Range<usize>, &Mark)> + '_ {
         self.marks.iter(range)
     }
+
+    /// The marks that intersect the range, including those that start before it.
+    pub(crate) fn overlapping_marks(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (NamespaceId, Range<usize>, &Mark)> + '_ {
+        self.marks.overlapping(range)
+    }
+
+    /// Changed by every change to the marks other than the edits of the text moving them.
+    pub(crate) fn marks_version(&self) -> u64 {
+        self.marks.version
+    }
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct Marks {
     namespaces: HashMap<NamespaceId, PerNs>,
+    version: u64,
 }
 
 #[derive(Debug)]
 struct PerNs {
-    marks: SlotMap<MarkId, Mark>,
-    tree: MarkTree<MarkId, 32>,
+    /// The tag of the ids of the marks in the namespace.
+    tag: u8,
+    /// The id of the next mark, the marks themselves are the payloads of the tree.
+    next_id: u64,
+    tree: MarkTree<MarkId, 32, Mark>,
 }
 
 impl PerNs {
-    fn new(text_len: usize) -> Self {
-        Self { marks: SlotMap::default(), tree: MarkTree::new(text_len + 1) }
+    fn new(text_len: usize, namespace: NamespaceId) -> Self {
+        // Only the index of the namespace is used as there's no room for the version of its key.
+        let tag = namespace.data().as_ffi() as u8;
+        Self { tag, next_id: 0, tree: MarkTree::new(text_len + 1) }
+    }
+
+    fn next_id(&mut self) -> MarkId {
+        let id = MarkId(TaggedId::new(self.tag, self.next_id));
+        self.next_id += 1;
+        id
     }
     fn iter(
         &self,
         range: impl RangeBounds<usize>,
     ) -> impl Iterator<Item = (Range<usize>, &Mark)> + '_ {
-        self.tree.range(range).map(move |(range, id)| (range, &self.marks[id]))
+        self.tree.range_with_payload(range).map(|(range, _, mark)| (range, mark))
+    }
+
+    fn overlapping(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, &Mark)> + '_ {
+        self.tree.overlapping(range).map(|(range, id)| {
+            (range, self.tree.payload(id).expect("every mark in the tree has a payload"))
+        })
     }
 
     fn edit(&mut self, deltas: &Deltas<'_>) {
-        deltas.iter().for_each(|delta| self.tree.shift(delta.range(), delta.text().len()));
+        self.tree.shift_many(deltas.iter().map(|delta| (delta.range(), delta.text().len())));
     }
 
     fn replace(&mut self, text_len: usize, builders: impl IntoIterator<Item = MarkBuilder>) {
-        self.marks.clear();
-        self.tree = MarkTree::build(
+        self.tree = MarkTree::build_with_payloads(
             text_len,
             builders.into_iter().map(|builder| {
-                let id = self.marks.insert_with_key(|id| builder.build(id));
-                (id, builder.builder)
+                let id = self.next_id();
+                (id, builder.builder, builder.build(id))
             }),
         )
     }
 
     fn create(&mut self, builder: MarkBuilder) -> MarkId {
-        let id = self.marks.insert_with_key(|id| builder.build(id));
-        builder.builder.insert(&mut self.tree, id);
+        let id = self.next_id();
+        builder.builder.insert_with_payload(&mut self.tree, id, builder.build(id));
         id
     }
 
     fn delete(&mut self, id: MarkId) -> Option<(Range<usize>, Mark)> {
-        let mark = self.marks.remove(id)?;
-        let range = self.tree.delete(id).expect("if map contains mark, tree should too");
-        Some((range, mark))
+        self.tree.remove(id)
     }
 
     fn drain(&mut self, range: impl RangeBounds<usize>) {
         let start_len = self.tree.len();
-        for (_range, id) in self.tree.drain(range) {
-            self.marks.remove(id).unwrap();
-        }
+        // Dropping the iterator removes the rest of the marks.
+        drop(self.tree.drain(range));
         assert_eq!(self.tree.len(), start_len);
     }
 }
 
-/// We're packing the 64bit `slotmap::KeyData` into 32 bits.
-/// Usually the upper 32-bits are for the version and the lower 32-bits are for the index.
-/// In practice, 12-bits should be enough for the version, and 20-bits for the index.
 impl MarkTreeId for MarkId {}
 
-impl From<u32> for MarkId {
+impl From<u64> for MarkId {
     #[inline]
-    fn from(raw: u32) -> MarkId {
-        let version = raw >> 20;
-        let index = raw << 12 >> 12;
-        MarkId::from(KeyData::from_ffi((version as u64) << 32 | index as u64))
+    fn from(raw: u64) -> MarkId {
+        MarkId(TaggedId::from(raw))
     }
 }
 
-impl From<MarkId> for u32 {
+impl From<MarkId> for u64 {
     #[inline]
-    fn from(id: MarkId) -> u32 {
-        let raw = id.data().as_ffi();
-        let version = raw >> 32;
-        let index = raw << 32 >> 32;
-
-        assert!(version < 1 << 12, "version is too large");
-        assert!(index < 1 << 20, "index is too large");
-
-        (version << 20 | index) as u32
+    fn from(id: MarkId) -> u64 {
+        id.0.into()
     }
 }
 
@@ -141,7 +161,11 @@ impl Marks {
         namespace: NamespaceId,
         builder: MarkBuilder,
     ) -> MarkId {
-        self.namespaces.entry(namespace).or_insert_with(|| PerNs::new(text_len)).create(builder)
+        self.version += 1;
+        self.namespaces
+            .entry(namespace)
+            .or_insert_with(|| PerNs::new(text_len, namespace))
+            .create(builder)
     }
 
     pub(crate) fn replace(
@@ -150,17 +174,20 @@ impl Marks {
         namespace: NamespaceId,
         builders: impl IntoIterator<Item = MarkBuilder>,
     ) {
+        self.version += 1;
         self.namespaces
             .entry(namespace)
-            .or_insert_with(|| PerNs::new(text_len))
+            .or_insert_with(|| PerNs::new(text_len, namespace))
             .replace(text_len, builders)
     }
 
     pub fn delete(&mut self, ns: NamespaceId, id: MarkId) -> Option<(Range<usize>, Mark)> {
+        self.version += 1;
         self.namespaces.get_mut(&ns).and_then(|ns| ns.delete(id))
     }
 
     pub fn drain(&mut self, ns: NamespaceId, range: impl RangeBounds<usize>) {
+        self.version += 1;
         if let Some(per_ns) = self.namespaces.get_mut(&ns) {
             per_ns.drain(range)
         }
@@ -185,11 +212,30 @@ impl Marks {
             .map(|(&ns, per_ns)| per_ns.iter(range).map(move |(range, mark)| (ns, range, mark)))
             .kmerge_by(|(m, a, _), (n, b, _)| m < n || (m == n && a.start < b.start))
     }
+
+    /// Returns an iterator over all marks that intersect the given range (all namespaces), unlike
+    /// `iter` this includes the marks that start before the range.
+    #[inline]
+    pub fn overlapping(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (NamespaceId, Range<usize>, &Mark)> + '_ {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.namespaces
+            .iter()
+            .map(|(&ns, per_ns)| {
+                per_ns.overlapping(range).map(move |(range, mark)| (ns, range, mark))
+            })
+            .kmerge_by(|(m, a, _), (n, b, _)| m < n || (m == n && a.start < b.start))
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct MarkBuilder {
     hl: HighlightId,
+    sign: Option<Sign>,
+    conceal: Option<Conceal>,
+    virtual_text: Option<VirtualText>,
     builder: zi_marktree::MarkBuilder,
 }
 
@@ -199,6 +245,24 @@ impl MarkBuilder {
         self
     }
 
+    /// Display `sign` in the gutter of the line the mark starts on.
+    pub fn sign(mut self, sign: Sign) -> Self {
+        self.sign = Some(sign);
+        self
+    }
+
+    /// Hide the text of the mark when rendered, see [`Conceal`].
+    pub fn conceal(mut self, conceal: Conceal) -> Self {
+        self.conceal = Some(conceal);
+        self
+    }
+
+    /// Display `text` after the end of the line the mark starts on.
+    pub fn virtual_text(mut self, text: VirtualText) -> Self {
+        self.virtual_text = Some(text);
+        self
+    }
+
     pub fn width(mut self, width: usize) -> Self {
         self.builder = self.builder.width(width);
         self
@@ -216,7 +280,13 @@ impl MarkBuilder {
 
     #[inline]
     fn build(self, id: MarkId) -> Mark {
-        Mark { id, hl: self.hl }
+        Mark {
+            id,
+            hl: self.hl,
+            sign: self.sign,
+            conceal: self.conceal,
+            virtual_text: self.virtual_text,
+        }
     }
 }
 
@@ -224,12 +294,44 @@ impl MarkBuilder {
 pub struct Mark {
     id: MarkId,
     hl: HighlightId,
+    sign: Option<Sign>,
+    conceal: Option<Conceal>,
+    virtual_text: Option<VirtualText>,
+}
+
+/// A single character displayed in the gutter, see [`MarkBuilder::sign`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Sign {
+    pub text: char,
+    pub hl: HighlightId,
+}
+
+/// Concealed text is hidden when rendered depending on the view's `conceallevel`.
+/// With level 1 it is replaced by `substitute` or a space, with level 2 by `substitute` if any,
+/// and with level 3 it is hidden entirely. The cursor line is never concealed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Conceal {
+    pub substitute: Option<char>,
+}
+
+/// Text displayed after the end of a line, see [`MarkBuilder::virtual_text`].
+/// Only one virtual text is displayed per line, later marks win.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VirtualText {
+    pub text: Arc<str>,
+    pub hl: HighlightId,
 }
 
 impl Mark {
     #[inline]
     pub fn builder(byte: usize) -> MarkBuilder {
-        MarkBuilder { builder: zi_marktree::MarkBuilder::new(byte), hl: Default::default() }
+        MarkBuilder {
+            builder: zi_marktree::MarkBuilder::new(byte),
+            hl: Default::default(),
+            sign: None,
+            conceal: None,
+            virtual_text: None,
+        }
     }
 
     #[inline]
@@ -241,4 +343,19 @@ impl Mark {
     pub fn highlight(&self) -> HighlightId {
         self.hl
     }
+
+    #[inline]
+    pub fn sign(&self) -> Option<Sign> {
+        self.sign
+    }
+
+    #[inline]
+    pub fn conceal(&self) -> Option<Conceal> {
+        self.conceal
+    }
+
+    #[inline]
+    pub fn virtual_text(&self) -> Option<&VirtualText> {
+        self.virtual_text.as_ref()
+    }
 }
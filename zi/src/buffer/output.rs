@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+use super::*;
+use crate::editor::{Action, get};
+use crate::{Mode, filetype, hashmap, trie};
+
+/// A readonly scratch buffer showing the output of a shell command as it is produced.
+/// `<C-c>` kills the command if it is still running.
+pub struct OutputBuffer {
+    id: BufferId,
+    url: Url,
+    text: String,
+    sink: OutputSink,
+    kill: Option<oneshot::Sender<()>>,
+    keymap: Keymap,
+    config: Settings,
+}
+
+/// The writing half of an [`OutputBuffer`].
+#[derive(Clone)]
+pub(crate) struct OutputSink {
+    output: Arc<Mutex<String>>,
+    /// Set when output has been pushed since the text was last synced.
+    dirty: Arc<AtomicBool>,
+    notify: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl OutputSink {
+    pub fn new(notify: impl Fn() + Send + Sync + 'static) -> Self {
+        Self { output: Default::default(), dirty: Default::default(), notify: Arc::new(notify) }
+    }
+
+    pub fn push(&self, s: &str) {
+        self.output.lock().push_str(s);
+        self.dirty.store(true, Ordering::Release);
+        (self.notify)();
+    }
+}
+
+impl OutputBuffer {
+    /// Create a buffer displaying everything pushed to `sink`, `<C-c>` fires `kill`.
+    pub(crate) fn new(id: BufferId, sink: OutputSink, kill: oneshot::Sender<()>) -> Self {
+        let keymap = {
+            let noop: Action = |_| {};
+            let interrupt: Action = |editor| {
+                let (_, buf) = get!(editor as Self);
+                if let Some(kill) = buf.kill.take() {
+                    let _ = kill.send(());
+                }
+            };
+            Keymap::from(hashmap! {
+                Mode::Normal => trie! ({
+                    // Prevent the user from using insert mode in this buffer
+                    "i" => noop,
+                    "<C-c>" => interrupt,
+                }),
+            })
+        };
+
+        Self {
+            id,
+            sink,
+            keymap,
+            kill: Some(kill),
+            url: Url::parse("buffer://output").unwrap(),
+            text: Default::default(),
+            config: Default::default(),
+        }
+    }
+}
+
+impl BufferInternal for OutputBuffer {
+    fn id(&self) -> BufferId {
+        self.id
+    }
+
+    fn flags(&self) -> BufferFlags {
+        BufferFlags::READONLY
+    }
+
+    fn flushed(&mut self, _: Internal) {
+        unreachable!("output buffer does not have a backing file")
+    }
+
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn file_url(&self) -> Option<&Url> {
+        None
+    }
+
+    fn file_type(&self) -> FileType {
+        filetype!(output)
+    }
+
+    fn settings(&self) -> &Settings {
+        &self.config
+    }
+
+    fn text(&self) -> &(dyn AnyText + 'static) {
+        &self.text
+    }
+
+    fn version(&self) -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self, _: Internal) -> &mut dyn Any {
+        self
+    }
+
+    fn edit_flags(&mut self, _: Internal, _deltas: &Deltas<'_>, _flags: EditFlags) {
+        unreachable!("output buffer is read-only")
+    }
+
+    fn keymap(&mut self, _: Internal) -> Option<&mut Keymap> {
+        Some(&mut self.keymap)
+    }
+
+    fn pre_render(&mut self, _: Internal, _client: &Client, _view: &View, _area: tui::Rect) {
+        if self.sink.dirty.swap(false, Ordering::AcqRel) {
+            self.text.clone_from(&self.sink.output.lock());
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 
-use nucleo::Nucleo;
-use nucleo::pattern::{CaseMatching, Normalization};
 use zi_text::TextMut;
 
 use super::*;
-use crate::editor::{Action, get};
+use crate::editor::{Action, QuickfixEntry, get};
+use crate::fuzzy::Matcher;
 use crate::lstypes::TextExt;
 use crate::{
-    Active, Direction, Mode, OpenFlags, VerticalAlignment, ViewId, filetype, hashmap, lstypes, trie,
+    Active, Direction, HighlightName, Mode, OpenFlags, VerticalAlignment, ViewId, filetype,
+    hashmap, lstypes, trie,
 };
 
 pub struct PickerBuffer<P: Picker> {
@@ -18,7 +20,7 @@ pub struct PickerBuffer<P: Picker> {
     /// The view that displays the results
     display_view: ViewId,
     text: String,
-    nucleo: Nucleo<P::Entry>,
+    matcher: Matcher<P::Entry>,
     cancel: Cancel,
     keymap: Keymap,
     picker: P,
@@ -38,8 +40,10 @@ pub trait Picker: Send + Sync + Copy + 'static {
 
     fn confirm(self, editor: &mut Editor, entry: Self::Entry);
 
-    fn config(self) -> nucleo::Config {
-        nucleo::Config::DEFAULT
+    /// Export all matched entries, usually to the quickfix list.
+    fn export(self, editor: &mut Editor, entries: Vec<Self::Entry>) {
+        let _ = entries;
+        editor.set_error("picker does not support exporting entries");
     }
 }
 
@@ -48,6 +52,11 @@ pub trait BufferPickerEntry: Entry {
     fn buffer_or_path(&self) -> Result<BufferId, &Path>;
 
     fn point(&self) -> Option<lstypes::EncodedPoint>;
+
+    /// The range to highlight in the preview
+    fn range(&self) -> Option<lstypes::EncodedRange> {
+        None
+    }
 }
 
 impl<P> BufferPickerEntry for P
@@ -89,18 +98,27 @@ where
         Self { preview, marker: PhantomData }
     }
 
-    fn config(self) -> nucleo::Config {
-        nucleo::Config::DEFAULT.match_paths()
-    }
-
     fn select(self, editor: &mut Editor, entry: Self::Entry) {
         let point = entry.point();
+        let range = entry.range();
 
         let preview = move |editor: &mut Editor, buf: BufferId| {
             editor.set_buffer(self.preview, buf);
             if let Some(point) = point.and_then(|p| editor.text(buf).decode_point(p)) {
                 editor.reveal(self.preview, point, VerticalAlignment::Center)
             }
+
+            let ns = editor.create_namespace(PREVIEW_NAMESPACE);
+            editor.clear_marks(buf, ns, ..);
+            if let Some(range) = range.and_then(|range| editor.text(buf).decode_range(&range)) {
+                let byte_range = editor.text(buf).point_range_to_byte_range(range);
+                let hl = editor.highlight_id_by_name(HighlightName::SEARCH);
+                editor.create_mark(
+                    buf,
+                    ns,
+                    Mark::builder(byte_range.start).width(byte_range.len()).hl(hl),
+                );
+            }
         };
 
         let path = match entry.buffer_or_path() {
@@ -155,8 +173,37 @@ where
             Ok(())
         })
     }
+
+    fn export(self, editor: &mut Editor, entries: Vec<Self::Entry>) {
+        let entries = entries
+            .into_iter()
+            .filter_map(|entry| {
+                let path = match entry.buffer_or_path() {
+                    Ok(buf) => editor[buf].file_path()?,
+                    Err(path) => path.to_path_buf(),
+                };
+
+                Some(QuickfixEntry {
+                    path,
+                    point: entry.point(),
+                    range: entry.range(),
+                    text: entry.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        editor.close_view(self.preview);
+        editor.set_quickfix(entries);
+        editor.open_quickfix();
+    }
 }
 
+/// The namespace of the marks used to highlight the selected entry in the preview.
+pub(crate) const PREVIEW_NAMESPACE: &str = "picker-preview";
+
+/// The namespace of the marks used to highlight the matched characters of the entries.
+const MATCH_NAMESPACE: &str = "picker-match";
+
 impl<P> PickerBuffer<P>
 where
     P: Picker,
@@ -167,13 +214,13 @@ where
         notify: impl Fn() + Send + Sync + 'static,
         picker: P,
     ) -> Self {
-        let nucleo = Nucleo::new(picker.config(), Arc::new(notify), None, 1);
+        let matcher = Matcher::new(Arc::new(notify));
         let cancel = Cancel::new();
         Self {
             id,
             display_view,
             cancel,
-            nucleo,
+            matcher,
             picker,
             dynamic_handler: None,
             url: Url::parse("buffer://picker").unwrap(),
@@ -183,6 +230,7 @@ where
                 let next: Action = |editor| Self::select(editor, Direction::Down);
                 let prev: Action = |editor| Self::select(editor, Direction::Up);
                 let confirm: Action = |editor| Self::confirm(editor);
+                let export: Action = |editor| Self::export(editor);
                 let close: Action = |editor| editor.close_view(Active);
 
                 Keymap::from(hashmap! {
@@ -191,12 +239,14 @@ where
                         "<Tab>" | "<C-j>" => next,
                         "<S-Tab>" | "<C-k>" => prev,
                         "<CR>" => confirm,
+                        "<C-q>" => export,
                     }),
                     Mode::Normal => trie!({
                         "<Esc>" | "<C-c>" | "q" => close,
                         "<Tab>" | "<C-j>" | "j" => next,
                         "<S-Tab>" | "<C-k>" | "k" => prev,
                         "<CR>" => confirm,
+                        "<C-q>" => export,
                     }),
                 })
             },
@@ -221,7 +271,7 @@ where
     }
 
     pub fn injector(&self) -> Injector<P::Entry> {
-        Injector::new(self.nucleo.injector(), self.cancel.clone())
+        Injector::new(self.matcher.injector(), self.cancel.clone())
     }
 
     #[must_use]
@@ -232,8 +282,8 @@ where
 }
 
 impl<P: Picker> PickerBuffer<P> {
-    fn item(&self, line: u32) -> Option<P::Entry> {
-        self.nucleo.snapshot().get_matched_item(line).map(|item| item.data.clone())
+    fn item(&self, line: usize) -> Option<P::Entry> {
+        self.matcher.get_matched_item(line)
     }
 
     fn confirm(editor: &mut Editor) {
@@ -243,18 +293,30 @@ impl<P: Picker> PickerBuffer<P> {
 
         let (_, picker_buf) = get!(editor as Self);
         let picker = picker_buf.picker;
-        if let Some(item) = picker_buf.item(cursor.line() as u32) {
+        if let Some(item) = picker_buf.item(cursor.line()) {
             picker.confirm(editor, item);
         }
     }
 
+    fn export(editor: &mut Editor) {
+        let (_, picker_buf) = get!(editor as Self);
+        let picker = picker_buf.picker;
+        let entries = picker_buf
+            .matcher
+            .matched_items(usize::MAX)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect::<Vec<_>>();
+        picker.export(editor, entries);
+    }
+
     fn select_current(buf_id: BufferId, editor: &mut Editor) {
         let picker_buf = editor[buf_id].as_any().downcast_ref::<Self>().unwrap();
         let display_view = picker_buf.display_view;
         let cursor = editor.cursor(display_view);
 
         let picker = picker_buf.picker;
-        if let Some(item) = picker_buf.item(cursor.line() as u32) {
+        if let Some(item) = picker_buf.item(cursor.line()) {
             picker.select(editor, item);
         }
     }
@@ -317,30 +379,20 @@ impl<P: Picker + Send + Sync> BufferInternal for PickerBuffer<P> {
             // swap the cancel token with a fresh one and cancel the previous one
             let cancel = mem::take(&mut self.cancel);
             cancel.cancel();
-            self.nucleo.restart(false);
+            self.matcher.restart();
             // if there is a dynamic handler, delegate the updated prompt to it
             handler(self.injector(), &self.text);
         } else {
-            self.nucleo.pattern.reparse(
-                0,
-                &self.text,
-                CaseMatching::Smart,
-                Normalization::Smart,
-                false,
-            );
+            self.matcher.reparse(&self.text);
         }
     }
 
     fn pre_render(&mut self, _: Internal, client: &Client, _view: &View, _area: tui::Rect) {
-        if !self.nucleo.tick(10).changed {
+        if !self.matcher.tick(Duration::from_millis(10)) {
             return;
         }
 
-        let snapshot = self.nucleo.snapshot();
-        let items = snapshot
-            .matched_items(..snapshot.matched_item_count().min(100))
-            .map(|item| item.data.clone())
-            .collect::<Vec<_>>();
+        let items = self.matcher.matched_items(100);
 
         let display_view = self.display_view;
         let buf_id = self.id;
@@ -354,11 +406,19 @@ impl<P: Picker + Send + Sync> BufferInternal for PickerBuffer<P> {
             let text = editor[buf].text();
 
             let mut s = String::new();
-            for item in items.iter() {
+            let mut marks = vec![];
+            let hl = editor.highlight_id_by_name(HighlightName::PICKER_MATCH);
+            for (item, indices) in items.iter() {
+                let start = s.len();
                 writeln!(s, "{item}")?;
+                marks.extend(match_ranges(&s[start..], indices).map(|range| {
+                    Mark::builder(start + range.start).width(range.end - range.start).hl(hl)
+                }));
             }
 
             editor.edit(display_view, &Deltas::new([Delta::new(0..text.len_bytes(), s)]))?;
+            let ns = editor.create_namespace(MATCH_NAMESPACE);
+            editor[buf].replace_marks(ns, marks);
             Ok(())
         });
     }
@@ -379,3 +439,26 @@ impl<P: Picker + Send + Sync> BufferInternal for PickerBuffer<P> {
         self
     }
 }
+
+/// Convert the sorted matched char indices of `line` into byte ranges, merging adjacent characters.
+fn match_ranges<'a>(line: &'a str, indices: &'a [u32]) -> impl Iterator<Item = Range<usize>> + 'a {
+    let mut indices = indices.iter().copied().peekable();
+    let mut ranges = line.char_indices().enumerate().filter_map(move |(i, (byte, c))| {
+        while indices.next_if(|&idx| (idx as usize) < i).is_some() {}
+        indices.next_if_eq(&(i as u32)).map(|_| byte..byte + c.len_utf8())
+    });
+
+    let mut current = ranges.next();
+    std::iter::from_fn(move || {
+        let mut range = current.take()?;
+        for next in ranges.by_ref() {
+            if next.start == range.end {
+                range.end = next.end;
+            } else {
+                current = Some(next);
+                break;
+            }
+        }
+        Some(range)
+    })
+}
@@ -0,0 +1,411 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::Mutex;
+use portable_pty::{Child, CommandBuilder, MasterPty, PtySize};
+use zi_input::{KeyCode, KeyModifiers};
+use zi_text::Rope;
+
+use super::*;
+use crate::{Active, filetype};
+
+const SCROLLBACK: usize = 5000;
+
+/// A buffer running a shell in a pseudo-terminal.
+/// The screen and scrollback of the terminal are rendered as the (readonly) text of the buffer.
+/// In insert mode keys are forwarded to the terminal, `<C-\><C-n>` returns to normal mode.
+pub struct TerminalBuffer {
+    id: BufferId,
+    url: Url,
+    /// The scrollback lines followed by the screen lines.
+    text: Rope,
+    /// The byte length of each scrollback line of the text (including the newline).
+    scrollback: VecDeque<usize>,
+    emulator: Arc<Mutex<Emulator>>,
+    pty: Mutex<Pty>,
+    /// Set when the terminal has produced output since the text was last rebuilt.
+    dirty: Arc<AtomicBool>,
+    exited: Arc<AtomicBool>,
+    size: PtySize,
+    /// Whether the previous key was `<C-\>`.
+    escape_pending: bool,
+    config: Settings,
+}
+
+/// The terminal emulator, along with the lines that scrolled off its screen and are yet to be
+/// appended to the text.
+///
+/// vt100 has no way to ask for the lines pushed into its scrollback since some point, it only
+/// exposes the scrollback by scrolling the screen back over it. However, while the screen is
+/// scrolled back by a non-zero offset, vt100 increments the offset for each line pushed into the
+/// scrollback so that the view stays on the same rows. So the parser is kept scrolled back by
+/// exactly one line (the anchor) between calls to [`Emulator::collect_scrolled`], and the offset
+/// minus one is then the number of new scrollback lines. The offset is capped at the length of
+/// the scrollback and only a screenful of it can be viewed at once, which is why output is fed
+/// to the parser a line at a time. The offset is reset to zero only to read the screen itself.
+struct Emulator {
+    parser: vt100::Parser,
+    scrolled: VecDeque<String>,
+    /// The length of the scrollback of the parser when the scrolled lines were last collected.
+    scrollback_len: usize,
+}
+
+impl Emulator {
+    fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            parser: vt100::Parser::new(rows, cols, SCROLLBACK),
+            scrolled: Default::default(),
+            scrollback_len: 0,
+        }
+    }
+
+    fn process(&mut self, bytes: &[u8]) {
+        // Feed the output a line at a time so lines are collected before more than a screenful scrolls off.
+        for line in bytes.split_inclusive(|&b| b == b'\n') {
+            self.parser.process(line);
+            self.collect_scrolled();
+        }
+    }
+
+    /// Collect the lines that scrolled off the screen since the last call.
+    fn collect_scrolled(&mut self) {
+        if self.parser.screen().alternate_screen() {
+            // The alternate screen has no scrollback.
+            return;
+        }
+
+        // The parser is left scrolled back by a line, and vt100 keeps the view in place as lines are
+        // pushed into the scrollback, so the offset counts the new lines (see `Emulator`).
+        let anchor = self.parser.screen().scrollback();
+        self.parser.set_scrollback(usize::MAX);
+        let len = self.parser.screen().scrollback();
+        let new = match anchor {
+            // Not anchored as the scrollback was empty (or the alternate screen was entered since).
+            0 => len.saturating_sub(self.scrollback_len),
+            _ => anchor - 1,
+        };
+
+        // vt100 can't show more than a screenful of scrollback at a time.
+        let (rows, cols) = self.parser.screen().size();
+        let new = new.min(rows as usize);
+        self.parser.set_scrollback(new);
+        self.scrolled.extend(self.parser.screen().rows(0, cols).take(new));
+        while self.scrolled.len() > SCROLLBACK {
+            self.scrolled.pop_front();
+        }
+
+        self.parser.set_scrollback(1);
+        self.scrollback_len = len;
+    }
+
+    /// The rows of the screen and the position of the cursor.
+    fn screen(&mut self) -> (Vec<String>, (u16, u16)) {
+        self.parser.set_scrollback(0);
+        let screen = self.parser.screen();
+        let rows = screen.rows(0, screen.size().1).collect();
+        let cursor = screen.cursor_position();
+        self.parser.set_scrollback(1);
+        (rows, cursor)
+    }
+}
+
+struct Pty {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+impl TerminalBuffer {
+    pub fn new(
+        id: BufferId,
+        cwd: &Path,
+        notify: impl Fn() + Send + Sync + 'static,
+    ) -> anyhow::Result<Self> {
+        let size = PtySize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 };
+        let pair = portable_pty::native_pty_system().openpty(size)?;
+
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let mut cmd = CommandBuilder::new(shell);
+        cmd.cwd(cwd);
+        cmd.env("TERM", "xterm-256color");
+        let child = pair.slave.spawn_command(cmd)?;
+        // Otherwise we never see EOF once the child exits.
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+
+        let emulator = Arc::new(Mutex::new(Emulator::new(size.rows, size.cols)));
+        let dirty = Arc::new(AtomicBool::new(true));
+        let exited = Arc::new(AtomicBool::new(false));
+
+        std::thread::Builder::new().name("terminal-reader".into()).spawn({
+            let emulator = Arc::clone(&emulator);
+            let dirty = Arc::clone(&dirty);
+            let exited = Arc::clone(&exited);
+            move || {
+                let mut buf = [0; 8192];
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => emulator.lock().process(&buf[..n]),
+                    }
+                    dirty.store(true, Ordering::Release);
+                    notify();
+                }
+
+                exited.store(true, Ordering::Release);
+                dirty.store(true, Ordering::Release);
+                notify();
+            }
+        })?;
+
+        Ok(Self {
+            id,
+            emulator,
+            dirty,
+            exited,
+            size,
+            url: Url::parse("buffer://terminal").unwrap(),
+            pty: Mutex::new(Pty { master: pair.master, writer, child }),
+            text: Default::default(),
+            scrollback: Default::default(),
+            escape_pending: false,
+            config: Default::default(),
+        })
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut pty = self.pty.lock();
+        if let Err(err) = pty.writer.write_all(bytes).and_then(|()| pty.writer.flush()) {
+            tracing::error!(?err, "failed to write to terminal");
+        }
+    }
+
+    fn resize(&mut self, area: tui::Rect) {
+        let size = PtySize { rows: area.height.max(1), cols: area.width.max(1), ..self.size };
+        if (size.rows, size.cols) == (self.size.rows, self.size.cols) {
+            return;
+        }
+
+        self.size = size;
+        self.emulator.lock().parser.set_size(size.rows, size.cols);
+        if let Err(err) = self.pty.lock().master.resize(size) {
+            tracing::error!(?err, "failed to resize terminal");
+        }
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Update the text with the new scrollback lines and the screen, returning the position of the terminal cursor.
+    /// Scrollback lines never change, so they are only appended and trimmed and only the screen is rewritten.
+    fn rebuild(&mut self) -> Point {
+        let mut emulator = self.emulator.lock();
+        let scrolled = std::mem::take(&mut emulator.scrolled);
+        let (mut lines, (row, col)) = emulator.screen();
+        drop(emulator);
+
+        let mut scrollback_bytes = self.scrollback.iter().sum::<usize>();
+        self.text.delete(scrollback_bytes..);
+
+        let mut appended = String::new();
+        for line in scrolled {
+            appended.push_str(&line);
+            appended.push('\n');
+            self.scrollback.push_back(line.len() + 1);
+        }
+        self.text.insert(scrollback_bytes, &appended);
+        scrollback_bytes += appended.len();
+
+        let mut evicted = 0;
+        while self.scrollback.len() > SCROLLBACK {
+            evicted += self.scrollback.pop_front().unwrap();
+        }
+        self.text.delete(..evicted);
+        scrollback_bytes -= evicted;
+
+        let cursor = Point::new(self.scrollback.len() + row as usize, col as usize);
+        // Pad the cursor line so the cursor can be placed after any trailing whitespace (e.g. of a prompt).
+        if let Some(line) = lines.get_mut(row as usize) {
+            let len = line.chars().count();
+            if len < cursor.col() {
+                line.extend(std::iter::repeat_n(' ', cursor.col() - len));
+            }
+        }
+
+        let end = lines.iter().rposition(|line| !line.trim_end().is_empty()).unwrap_or(0);
+        lines.truncate(end.max(row as usize) + 1);
+        if self.exited.load(Ordering::Acquire) {
+            lines.push("[Process exited]".to_string());
+        }
+
+        let mut screen = String::new();
+        for line in lines {
+            screen.push_str(&line);
+            screen.push('\n');
+        }
+        self.text.insert(scrollback_bytes, &screen);
+
+        cursor
+    }
+}
+
+impl Drop for TerminalBuffer {
+    fn drop(&mut self) {
+        let _ = self.pty.get_mut().child.kill();
+    }
+}
+
+impl BufferInternal for TerminalBuffer {
+    fn id(&self) -> BufferId {
+        self.id
+    }
+
+    fn flags(&self) -> BufferFlags {
+        BufferFlags::READONLY
+    }
+
+    fn flushed(&mut self, _: Internal) {
+        unreachable!("terminal buffer does not have a backing file")
+    }
+
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn file_url(&self) -> Option<&Url> {
+        None
+    }
+
+    fn file_type(&self) -> FileType {
+        filetype!(terminal)
+    }
+
+    fn settings(&self) -> &Settings {
+        &self.config
+    }
+
+    fn text(&self) -> &(dyn AnyText + 'static) {
+        &self.text
+    }
+
+    fn version(&self) -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self, _: Internal) -> &mut dyn Any {
+        self
+    }
+
+    fn edit_flags(&mut self, _: Internal, _deltas: &Deltas<'_>, _flags: EditFlags) {
+        unreachable!("terminal buffer is read-only")
+    }
+
+    fn intercept_insert_key(&mut self, _: Internal, key: &KeyEvent) -> Option<Mode> {
+        let ctrl = |c| key.code() == KeyCode::Char(c) && key.modifiers() == KeyModifiers::CONTROL;
+        if std::mem::take(&mut self.escape_pending) {
+            if ctrl('n') {
+                return Some(Mode::Normal);
+            }
+            self.write(&[0x1c]);
+        } else if ctrl('\\') {
+            self.escape_pending = true;
+            return Some(Mode::Insert);
+        }
+
+        if self.exited.load(Ordering::Acquire) {
+            // Nothing to send keys to, any key returns to normal mode.
+            return Some(Mode::Normal);
+        }
+
+        let application_cursor = self.emulator.lock().parser.screen().application_cursor();
+        if let Some(bytes) = encode_key(key, application_cursor) {
+            self.write(&bytes);
+        }
+        Some(Mode::Insert)
+    }
+
+    fn pre_render(&mut self, _: Internal, client: &Client, _view: &View, area: tui::Rect) {
+        self.resize(area);
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        let cursor = self.rebuild();
+        let buf = self.id;
+        client.send(move |editor| {
+            // Keep the cursor in sync with the terminal while the user is typing into it.
+            if editor.mode() == Mode::Insert && editor.view(Active).buffer() == buf {
+                editor.set_cursor(Active, cursor);
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Encode a key as the bytes a terminal would send for it.
+fn encode_key(key: &KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let mods = key.modifiers();
+    let mut bytes = vec![];
+    if mods.contains(KeyModifiers::ALT) {
+        bytes.push(0x1b);
+    }
+
+    let cursor = |c: u8| if application_cursor { [0x1b, b'O', c] } else { [0x1b, b'[', c] };
+
+    match key.code() {
+        KeyCode::Char(c) if mods.contains(KeyModifiers::CONTROL) => {
+            bytes.push(match c.to_ascii_lowercase() {
+                c @ 'a'..='z' => c as u8 - b'a' + 1,
+                '@' | ' ' => 0,
+                '[' => 0x1b,
+                '\\' => 0x1c,
+                ']' => 0x1d,
+                '^' => 0x1e,
+                '_' => 0x1f,
+                _ => return None,
+            })
+        }
+        KeyCode::Char(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        KeyCode::Enter => bytes.push(b'\r'),
+        KeyCode::Backspace => bytes.push(0x7f),
+        KeyCode::Tab if mods.contains(KeyModifiers::SHIFT) => bytes.extend_from_slice(b"\x1b[Z"),
+        KeyCode::Tab => bytes.push(b'\t'),
+        KeyCode::Esc => bytes.push(0x1b),
+        KeyCode::Up => bytes.extend_from_slice(&cursor(b'A')),
+        KeyCode::Down => bytes.extend_from_slice(&cursor(b'B')),
+        KeyCode::Right => bytes.extend_from_slice(&cursor(b'C')),
+        KeyCode::Left => bytes.extend_from_slice(&cursor(b'D')),
+        KeyCode::Home => bytes.extend_from_slice(&cursor(b'H')),
+        KeyCode::End => bytes.extend_from_slice(&cursor(b'F')),
+        KeyCode::Insert => bytes.extend_from_slice(b"\x1b[2~"),
+        KeyCode::Delete => bytes.extend_from_slice(b"\x1b[3~"),
+        KeyCode::PageUp => bytes.extend_from_slice(b"\x1b[5~"),
+        KeyCode::PageDown => bytes.extend_from_slice(b"\x1b[6~"),
+        KeyCode::F(n @ 1..=4) => bytes.extend_from_slice(&[0x1b, b'O', b'P' + n - 1]),
+        KeyCode::F(n) => {
+            let code = match n {
+                5 => 15,
+                6 => 17,
+                7 => 18,
+                8 => 19,
+                9 => 20,
+                10 => 21,
+                11 => 23,
+                12 => 24,
+                _ => return None,
+            };
+            bytes.extend_from_slice(format!("\x1b[{code}~").as_bytes())
+        }
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests;
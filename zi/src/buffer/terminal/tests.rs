@@ -0,0 +1,45 @@
+use zi_input::KeyEvent;
+
+use super::{Emulator, encode_key};
+
+#[track_caller]
+fn encode(key: &str, application_cursor: bool) -> Option<Vec<u8>> {
+    let key = key.parse::<KeyEvent>().unwrap();
+    encode_key(&key, application_cursor)
+}
+
+#[test]
+fn terminal_encode_key() {
+    assert_eq!(encode("a", false), Some(b"a".to_vec()));
+    assert_eq!(encode("A", false), Some(b"A".to_vec()));
+    assert_eq!(encode("<CR>", false), Some(b"\r".to_vec()));
+    assert_eq!(encode("<BS>", false), Some(vec![0x7f]));
+    assert_eq!(encode("<C-c>", false), Some(vec![0x03]));
+    assert_eq!(encode("<C-\\>", false), Some(vec![0x1c]));
+    assert_eq!(encode("<A-b>", false), Some(b"\x1bb".to_vec()));
+    assert_eq!(encode("<S-Tab>", false), Some(b"\x1b[Z".to_vec()));
+    assert_eq!(encode("<Up>", false), Some(b"\x1b[A".to_vec()));
+    assert_eq!(encode("<Up>", true), Some(b"\x1bOA".to_vec()));
+    assert_eq!(encode("<Del>", false), Some(b"\x1b[3~".to_vec()));
+}
+
+#[test]
+fn terminal_scrollback() {
+    let mut emulator = Emulator::new(4, 20);
+    let output = (0..100).map(|i| format!("{i}\r\n")).collect::<String>();
+    // Many more lines than the screen can hold scroll off in a single chunk.
+    emulator.process(output.as_bytes());
+    let scrolled = std::mem::take(&mut emulator.scrolled);
+    assert_eq!(scrolled, (0..97).map(|i| i.to_string()).collect::<Vec<_>>());
+    assert_eq!(emulator.screen(), (vec!["97".into(), "98".into(), "99".into(), "".into()], (3, 0)));
+
+    // Only the new lines are collected.
+    emulator.process(b"a\r\nb\r\n");
+    assert_eq!(emulator.scrolled, ["97", "98"]);
+
+    // The alternate screen does not add to the scrollback.
+    emulator.process(b"\x1b[?1049hvim\r\n\r\n\r\n\r\n\r\n\x1b[?1049l");
+    assert_eq!(emulator.scrolled, ["97", "98"]);
+    emulator.process(b"c\r\n");
+    assert_eq!(emulator.scrolled, ["97", "98", "99"]);
+}
@@ -1,9 +1,10 @@
 use std::mem;
+use std::sync::OnceLock;
 
-use zi_text::{AnyTextSlice, Text, TextMut, TextSlice};
+use zi_text::{AnyTextSlice, ContentHash, Rope, RopeBuilder, Text, TextMut, TextSlice};
 
 use super::*;
-use crate::syntax::{HighlightMap, HighlightName};
+use crate::syntax::HighlightMap;
 use crate::undo::UndoTree;
 
 pub struct TextBuffer<X> {
@@ -16,13 +17,24 @@ pub struct TextBuffer<X> {
     text: X,
     language_id: FileType,
     syntax: Option<Box<dyn Syntax>>,
+    /// Whether the syntax tree is out of date with the text, see [`TextBuffer::parse_syntax`].
+    needs_parse: bool,
+    /// The edits made since the syntax being parsed in the background was cloned, along with the
+    /// text before each edit. `None` if there is no parse in progress.
+    parsing: Option<Vec<(X, Deltas<'static>)>>,
     highlight_map: HighlightMap,
     version: u32,
+    /// The hash of the text, computed when first compared after an edit so edits stay cheap.
+    content_hash: OnceLock<ContentHash>,
     config: Settings,
     undo_tree: UndoTree<UndoEntry>,
     /// Changes to the buffer that have not been saved to the undo tree
     changes: Vec<Change>,
     saved_cursor: Option<Point>,
+    /// The number of open undo groups, snapshots are deferred until the outermost one ends.
+    undo_groups: usize,
+    /// Mappings specific to this buffer, e.g. `<CR>` in the command-line window.
+    keymap: Option<Keymap>,
 }
 
 impl<X: Text + Clone + 'static> BufferHistory for TextBuffer<X> {
@@ -50,6 +62,10 @@ impl<X: Text + Clone + 'static> BufferHistory for TextBuffer<X> {
 
     #[tracing::instrument(skip(self))]
     fn snapshot(&mut self, flags: SnapshotFlags) {
+        if self.undo_groups > 0 {
+            return;
+        }
+
         if !flags.contains(SnapshotFlags::ALLOW_EMPTY) && self.changes.is_empty() {
             return;
         }
@@ -64,6 +80,15 @@ impl<X: Text + Clone + 'static> BufferHistory for TextBuffer<X> {
     fn snapshot_cursor(&mut self, cursor: Point) {
         self.saved_cursor = Some(cursor);
     }
+
+    fn start_undo_group(&mut self) {
+        self.undo_groups += 1;
+    }
+
+    fn end_undo_group(&mut self) {
+        self.undo_groups = self.undo_groups.checked_sub(1).expect("no undo group to end");
+        self.snapshot(SnapshotFlags::empty());
+    }
 }
 
 impl<X: Text + Clone + Send + 'static> BufferInternal for TextBuffer<X> {
@@ -120,66 +145,94 @@ impl<X: Text + Clone + Send + 'static> BufferInternal for TextBuffer<X> {
         self.version
     }
 
+    fn content_hash(&self) -> ContentHash {
+        *self.content_hash.get_or_init(|| ContentHash::new(&self.text))
+    }
+
     fn history_mut(&mut self, _: Internal) -> Option<&mut dyn BufferHistory> {
         Some(self)
     }
 
+    fn keymap(&mut self, _: Internal) -> Option<&mut Keymap> {
+        self.keymap.as_mut()
+    }
+
     fn syntax_highlights<'a>(
         &'a self,
-        _editor: &Editor,
+        editor: &Editor,
         cursor: &'a mut QueryCursor,
         range: PointRange,
-    ) -> Box<dyn Iterator<Item = SyntaxHighlight> + 'a> {
+    ) -> Box<dyn Iterator<Item = SyntaxHighlight<'a>> + 'a> {
         let Some(syntax) = &self.syntax else {
             return Box::new(std::iter::empty());
         };
 
-        Box::new(syntax.highlights(cursor, &self.text, range).flat_map(move |capture| {
-            let range = capture.node.range();
+        let capture_names = syntax.capture_names();
+        let host = syntax.highlights(cursor, &self.text, range).flat_map(move |capture| {
             let id = self.highlight_map.get(capture.index);
-            // Split multi-line highlights into single-line highlights
-            (range.start_point.row..=range.end_point.row).map(move |idx| {
-                let start = if idx == range.start_point.row { range.start_point.column } else { 0 };
-                let end = if idx == range.end_point.row {
-                    range.end_point.column
-                } else {
-                    self.text.byte_slice(..).dyn_line(idx).unwrap().len_bytes()
-                };
-
-                SyntaxHighlight {
-                    range: PointRange::new(Point::new(idx, start), Point::new(idx, end)),
-                    capture_idx: capture.index,
-                    id,
-                }
+            self.split_capture(capture_names[capture.index as usize], id, capture.node.range())
+        });
+
+        let injections = syntax.injections();
+        if injections.is_empty() {
+            return Box::new(host);
+        }
+
+        // Injected languages only cover parts of the text, so their highlights are collected
+        // eagerly with a cursor each rather than holding onto many cursors.
+        let theme = editor.theme().read();
+        let layers = injections
+            .into_iter()
+            .map(|layer| {
+                let capture_names = layer.capture_names();
+                let map = HighlightMap::new(capture_names, &theme);
+                let mut cursor = QueryCursor::new();
+                let highlights = layer
+                    .highlights(&mut cursor, &self.text, range)
+                    .flat_map(|capture| {
+                        let id = map.get(capture.index);
+                        let name = capture_names[capture.index as usize];
+                        self.split_capture(name, id, capture.node.range())
+                    })
+                    .collect::<Vec<_>>();
+                Box::new(highlights.into_iter()) as Box<dyn Iterator<Item = _> + 'a>
             })
-        }))
+            .collect::<Vec<_>>();
+
+        // Keep the highlights ordered by start, the host language first on ties so the highlights
+        // of injected languages take precedence.
+        let layers = std::iter::once(Box::new(host) as Box<dyn Iterator<Item = _> + 'a>)
+            .chain(layers)
+            .enumerate()
+            .map(|(i, layer)| layer.map(move |hl| (i, hl)));
+        let key = |(i, hl): &(usize, SyntaxHighlight<'_>)| (hl.range.start(), *i);
+        Box::new(
+            itertools::kmerge_by(layers, move |a: &_, b: &_| key(a) < key(b)).map(|(_, hl)| hl),
+        )
     }
 
-    fn overlay_highlights(
-        &self,
-        editor: &Editor,
-        view: &View,
-        size: Size,
-    ) -> Box<dyn Iterator<Item = Highlight> + '_> {
-        assert_eq!(view.buffer(), self.id);
-        let cursor = view.cursor();
-        let text = editor.buffer(view).text();
-
-        // We have to be careful that we don't set the end column to the middle of a char
-        let end = match text.line(cursor.line()) {
-            Some(line) => {
-                let line_byte_width =
-                    line.chars().take(size.width as usize).map(|c| c.len_utf8()).sum::<usize>();
-                size.width.max(line_byte_width as u16)
-            }
-            _ => size.width,
-        } as usize;
+    fn pre_render(&mut self, _: Internal, client: &Client, _view: &View, _area: tui::Rect) {
+        self.parse_syntax(client);
+    }
 
-        // The current_line highlight
-        Box::new(std::iter::once(Highlight {
-            range: PointRange::new(cursor.with_col(0), cursor.with_col(end)),
-            id: editor.highlight_id_by_name(HighlightName::CURSORLINE),
-        }))
+    fn reparse(&mut self, _: Internal) -> Vec<Range<usize>> {
+        let Some(syntax) = &mut self.syntax else { return vec![] };
+        if !self.needs_parse && self.parsing.is_none() {
+            return vec![];
+        }
+
+        // The tree is up to date once this is done, so any background parse is of no use.
+        let lines = syntax.parse(&self.text);
+        self.needs_parse = false;
+        self.parsing = None;
+        lines
+    }
+
+    fn set_theme(&mut self, _: Internal, theme: &Theme) {
+        self.highlight_map = HighlightMap::new(
+            self.syntax.as_ref().map_or(&[][..], |syntax| syntax.capture_names()),
+            theme,
+        );
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -189,9 +242,85 @@ impl<X: Text + Clone + Send + 'static> BufferInternal for TextBuffer<X> {
     fn as_any_mut(&mut self, _: Internal) -> &mut dyn Any {
         self
     }
+
+    fn set_path(&mut self, _: Internal, path: &Path) {
+        (self.url, self.file_url) = urls(path);
+    }
+
+    fn writable(&mut self, _: Internal) -> Option<Box<dyn BufferInternal>> {
+        if self.text.as_text_mut().is_some() {
+            return None;
+        }
+
+        // Copy the text byte for byte so that all existing offsets remain valid.
+        let mut builder = RopeBuilder::new();
+        for chunk in self.text.byte_slice(..).chunks() {
+            builder.append(chunk);
+        }
+
+        // The syntax tree is still valid for the copy, but a parse in progress will not find
+        // this buffer when it's done so it must be parsed again.
+        let syntax = self.syntax.take();
+        Some(Box::new(TextBuffer::<Rope> {
+            id: self.id,
+            flags: self.flags.difference(BufferFlags::READONLY),
+            url: self.url.clone(),
+            file_url: self.file_url.clone(),
+            text: builder.build(),
+            language_id: self.language_id,
+            needs_parse: syntax.is_some() && (self.needs_parse || self.parsing.is_some()),
+            syntax,
+            parsing: None,
+            highlight_map: self.highlight_map.clone(),
+            version: self.version,
+            content_hash: mem::take(&mut self.content_hash),
+            config: mem::take(&mut self.config),
+            undo_tree: mem::take(&mut self.undo_tree),
+            changes: mem::take(&mut self.changes),
+            saved_cursor: self.saved_cursor.take(),
+            undo_groups: self.undo_groups,
+            keymap: self.keymap.take(),
+        }))
+    }
+}
+
+/// The resource url and file url of a buffer for the file at `path`.
+fn urls(path: &Path) -> (Url, Option<Url>) {
+    let path = std::fs::canonicalize(path).ok().unwrap_or_else(|| path.to_path_buf());
+    let file_url = Url::from_file_path(&path).ok();
+
+    let url = file_url.as_ref().map_or_else(
+        // maybe there's another reason a buffer wouldn't have a url?
+        || Url::parse("buffer://scratch").unwrap(),
+        |_url| Url::parse(&format!("buffer://{}", path.display())).unwrap(),
+    );
+    (url, file_url)
 }
 
 impl<X: Text + Clone> TextBuffer<X> {
+    /// Split a capture into single-line highlights.
+    fn split_capture<'a>(
+        &'a self,
+        capture_name: &'a str,
+        id: HighlightId,
+        range: tree_sitter::Range,
+    ) -> impl Iterator<Item = SyntaxHighlight<'a>> + 'a {
+        (range.start_point.row..=range.end_point.row).map(move |idx| {
+            let start = if idx == range.start_point.row { range.start_point.column } else { 0 };
+            let end = if idx == range.end_point.row {
+                range.end_point.column
+            } else {
+                self.text.byte_slice(..).dyn_line(idx).unwrap().len_bytes()
+            };
+
+            SyntaxHighlight {
+                range: PointRange::new(Point::new(idx, start), Point::new(idx, end)),
+                id,
+                capture_name,
+            }
+        })
+    }
+
     #[inline]
     pub fn new(
         id: BufferId,
@@ -203,15 +332,7 @@ impl<X: Text + Clone> TextBuffer<X> {
         mut syntax: Option<Box<dyn Syntax>>,
     ) -> Self {
         let flags = flags | BufferFlags::ENSURE_TRAILING_NEWLINE;
-        let path = path.as_ref();
-        let path = std::fs::canonicalize(path).ok().unwrap_or_else(|| path.to_path_buf());
-        let file_url = Url::from_file_path(&path).ok();
-
-        let url = file_url.as_ref().map_or_else(
-            // maybe there's another reason a buffer wouldn't have a url?
-            || Url::parse("buffer://scratch").unwrap(),
-            |_url| Url::parse(&format!("buffer://{}", path.display())).unwrap(),
-        );
+        let (url, file_url) = urls(path.as_ref());
 
         if text.as_text_mut().is_none() && !flags.contains(BufferFlags::READONLY) {
             panic!("must set readonly buffer flag for readonly text implementations")
@@ -226,9 +347,9 @@ impl<X: Text + Clone> TextBuffer<X> {
         //     }
         // };
 
-        if let Some(syntax) = &mut syntax {
+        // The syntax is parsed in the background before the first render.
+        if let Some(syntax) = &syntax {
             assert_eq!(syntax.file_type(), ft);
-            syntax.set(&text);
         }
 
         let highlight_map = HighlightMap::new(
@@ -242,22 +363,39 @@ impl<X: Text + Clone> TextBuffer<X> {
             url,
             file_url,
             text,
+            needs_parse: syntax.is_some(),
             syntax,
+            parsing: None,
             language_id: ft,
             highlight_map,
             config: Default::default(),
             changes: Default::default(),
             version: Default::default(),
+            content_hash: Default::default(),
             undo_tree: Default::default(),
             saved_cursor: Default::default(),
+            undo_groups: 0,
+            keymap: None,
         }
     }
 
+    /// Use `keymap` for this buffer, which takes precedence over the global keymap.
+    pub(crate) fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = Some(keymap);
+        self
+    }
+
     fn edit(&mut self, deltas: &Deltas<'_>, flags: EditFlags) {
         let deltas = deltas.to_owned();
 
         tracing::trace!(?flags, ?deltas, "edit buffer");
 
+        // The edit is replayed onto the syntax being parsed once it's done, which needs the text
+        // as it was before the edit.
+        if let Some(edits) = &mut self.parsing {
+            edits.push((self.text.clone(), deltas.clone()));
+        }
+
         match self.text.as_text_mut() {
             Some(text) => {
                 if !deltas.is_identity() {
@@ -265,10 +403,14 @@ impl<X: Text + Clone> TextBuffer<X> {
                     self.flags.insert(BufferFlags::DIRTY);
                 }
 
-                let (inversions, _prev_tree) = match self.syntax.as_mut() {
-                    Some(syntax) => syntax.edit(text, &deltas),
-                    None => (text.edit(&deltas), None),
-                };
+                if let Some(syntax) = &mut self.syntax {
+                    syntax.edit(text.as_text(), &deltas);
+                    self.needs_parse = true;
+                }
+                if !deltas.is_identity() {
+                    self.content_hash.take();
+                }
+                let inversions = text.edit(&deltas);
 
                 if !flags.contains(EditFlags::NO_RECORD) {
                     self.changes.push(Change { deltas, inversions });
@@ -281,3 +423,50 @@ impl<X: Text + Clone> TextBuffer<X> {
         }
     }
 }
+
+impl<X: Text + Clone + Send + 'static> TextBuffer<X> {
+    /// Reparse the syntax on the rayon pool if it is out of date and not already being parsed.
+    /// The highlights of the stale syntax tree are shown until the parse is done.
+    fn parse_syntax(&mut self, client: &Client) {
+        let Some(syntax) = &self.syntax else { return };
+        if !self.needs_parse || self.parsing.is_some() {
+            return;
+        }
+
+        self.needs_parse = false;
+        self.parsing = Some(vec![]);
+        let mut syntax = dyn_clone::clone_box(&**syntax);
+        let text = self.text.clone();
+        let (buf, client) = (self.id, client.clone());
+        crate::editor::pool().spawn(move || {
+            let changed = syntax.parse(&text);
+            client.send(move |editor| {
+                // The buffer may have been closed while it was being parsed.
+                if editor.buffers().all(|b| b.id() != buf) {
+                    return Ok(());
+                }
+
+                if let Some(this) =
+                    editor.buffer_mut(buf).as_any_mut(Internal(())).downcast_mut::<Self>()
+                {
+                    if this.finish_parse(syntax) {
+                        editor.damage_lines(buf, changed);
+                    }
+                }
+                Ok(())
+            });
+        });
+    }
+
+    /// Replace the syntax with the one parsed in the background, replaying the edits made to
+    /// the text in the meantime. The syntax is parsed again on the next render if there were any.
+    /// Returns `false` if the parse was superseded by [`BufferInternal::reparse`].
+    fn finish_parse(&mut self, mut syntax: Box<dyn Syntax>) -> bool {
+        let Some(edits) = self.parsing.take() else { return false };
+        for (text, deltas) in &edits {
+            syntax.edit(text, deltas);
+        }
+        self.syntax = Some(syntax);
+        true
+    }
+}
@@ -0,0 +1,183 @@
+//! Access to the system clipboard, through an external command (`wl-copy`, `xclip`, `pbcopy`,
+//! ...) or natively. When neither is available, the editor falls back to setting the clipboard
+//! via OSC 52 if the terminal supports it.
+
+use std::ffi::OsStr;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::{env, fmt};
+
+use anyhow::{Context, bail};
+
+/// A system clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClipboardSelection {
+    /// The clipboard, the `+` register.
+    Clipboard,
+    /// The primary selection, the `*` register. Platforms without one use the clipboard instead.
+    Primary,
+}
+
+impl ClipboardSelection {
+    /// The clipboard backing the register `name`, if any.
+    pub fn from_register(name: char) -> Option<Self> {
+        match name {
+            '+' => Some(ClipboardSelection::Clipboard),
+            '*' => Some(ClipboardSelection::Primary),
+            _ => None,
+        }
+    }
+
+    pub fn register(self) -> char {
+        match self {
+            ClipboardSelection::Clipboard => '+',
+            ClipboardSelection::Primary => '*',
+        }
+    }
+}
+
+impl FromStr for ClipboardSelection {
+    type Err = anyhow::Error;
+
+    /// Parse the names used by vim's `clipboard` option.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unnamedplus" => Ok(ClipboardSelection::Clipboard),
+            "unnamed" => Ok(ClipboardSelection::Primary),
+            _ => bail!("invalid clipboard: `{s}` (expected `unnamed` or `unnamedplus`)"),
+        }
+    }
+}
+
+/// The commands to copy to and paste from each clipboard, indexed by [`ClipboardSelection`].
+pub(crate) struct ClipboardCommands {
+    name: &'static str,
+    copy: [&'static [&'static str]; 2],
+    paste: [&'static [&'static str]; 2],
+}
+
+const WL_CLIPBOARD: ClipboardCommands = ClipboardCommands {
+    name: "wl-clipboard",
+    copy: [&["wl-copy", "--type", "text/plain"], &["wl-copy", "--primary", "--type", "text/plain"]],
+    paste: [&["wl-paste", "--no-newline"], &["wl-paste", "--no-newline", "--primary"]],
+};
+
+const XCLIP: ClipboardCommands = ClipboardCommands {
+    name: "xclip",
+    copy: [&["xclip", "-i", "-selection", "clipboard"], &["xclip", "-i", "-selection", "primary"]],
+    paste: [&["xclip", "-o", "-selection", "clipboard"], &["xclip", "-o", "-selection", "primary"]],
+};
+
+const XSEL: ClipboardCommands = ClipboardCommands {
+    name: "xsel",
+    copy: [&["xsel", "-i", "-b"], &["xsel", "-i", "-p"]],
+    paste: [&["xsel", "-o", "-b"], &["xsel", "-o", "-p"]],
+};
+
+const PBCOPY: ClipboardCommands = ClipboardCommands {
+    name: "pbcopy",
+    copy: [&["pbcopy"], &["pbcopy"]],
+    paste: [&["pbpaste"], &["pbpaste"]],
+};
+
+/// How the system clipboard is accessed, chosen once at startup by [`Provider::detect`].
+pub(crate) enum Provider {
+    Commands(&'static ClipboardCommands),
+    Native(arboard::Clipboard),
+    None,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Commands(commands) => write!(f, "{}", commands.name),
+            Provider::Native(_) => write!(f, "native"),
+            Provider::None => write!(f, "none"),
+        }
+    }
+}
+
+impl Provider {
+    /// Pick a provider for the current environment. Over ssh the local clipboard is not the
+    /// user's, so there is no provider and the clipboard is set via OSC 52 instead.
+    pub(crate) fn detect() -> Self {
+        if env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+            return Provider::None;
+        }
+
+        let candidates: &[(bool, &'static ClipboardCommands)] = &[
+            (cfg!(target_os = "macos"), &PBCOPY),
+            (env::var_os("WAYLAND_DISPLAY").is_some(), &WL_CLIPBOARD),
+            (env::var_os("DISPLAY").is_some(), &XCLIP),
+            (env::var_os("DISPLAY").is_some(), &XSEL),
+        ];
+
+        if let Some(&(_, commands)) = candidates
+            .iter()
+            .find(|(enabled, commands)| *enabled && has_executable(commands.copy[0][0]))
+        {
+            return Provider::Commands(commands);
+        }
+
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => Provider::Native(clipboard),
+            Err(err) => {
+                tracing::info!(%err, "no system clipboard");
+                Provider::None
+            }
+        }
+    }
+
+    pub(crate) fn set(&mut self, selection: ClipboardSelection, text: &str) -> crate::Result<()> {
+        match self {
+            Provider::Commands(commands) => {
+                let [program, args @ ..] = commands.copy[selection as usize] else {
+                    unreachable!()
+                };
+                let mut child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .with_context(|| format!("failed to run `{program}`"))?;
+                child.stdin.take().expect("stdin is piped").write_all(text.as_bytes())?;
+                let status = child.wait()?;
+                if !status.success() {
+                    bail!("`{program}` failed: {status}");
+                }
+            }
+            Provider::Native(clipboard) => clipboard.set_text(text)?,
+            Provider::None => bail!("no clipboard provider"),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&mut self, selection: ClipboardSelection) -> crate::Result<String> {
+        match self {
+            Provider::Commands(commands) => {
+                let [program, args @ ..] = commands.paste[selection as usize] else {
+                    unreachable!()
+                };
+                let output = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::null())
+                    .output()
+                    .with_context(|| format!("failed to run `{program}`"))?;
+                if !output.status.success() {
+                    bail!("`{program}` failed: {}", output.status);
+                }
+                Ok(String::from_utf8(output.stdout)?)
+            }
+            Provider::Native(clipboard) => Ok(clipboard.get_text()?),
+            Provider::None => bail!("no clipboard provider"),
+        }
+    }
+}
+
+fn has_executable(name: impl AsRef<OsStr>) -> bool {
+    let Some(path) = env::var_os("PATH") else { return false };
+    env::split_paths(&path).any(|dir| dir.join(name.as_ref()).is_file())
+}
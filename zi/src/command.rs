@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::future::Future;
-use std::ops::{Bound, Deref, RangeBounds, RangeInclusive};
+use std::ops::{Bound, Deref, Range, RangeBounds, RangeInclusive};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use chumsky::Parser;
 use chumsky::primitive::end;
@@ -10,10 +12,15 @@ use chumsky::text::{digits, ident, newline, whitespace};
 use futures_core::future::BoxFuture;
 use futures_util::{FutureExt, future};
 use smol_str::SmolStr;
+use zi_input::KeySequence;
 
+use crate::buffer::IndentSettings;
 use crate::editor::{SaveFlags, Selector};
-use crate::{Active, BufferFlags, Client, Editor, Error, OpenFlags, ViewId};
+use crate::{
+    Active, BufferFlags, Client, Dimension, Editor, Error, MessageLevel, Mode, OpenFlags, ViewId,
+};
 
+#[derive(Clone)]
 pub struct Commands(Box<[Command]>);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -26,6 +33,8 @@ bitflags::bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct CommandFlags: u8 {
         const RANGE = 0b0001;
+        /// The arguments are paths, so `%` expands to the path of the buffer.
+        const PATHS = 0b0010;
     }
 }
 
@@ -68,15 +77,118 @@ impl FromStr for Commands {
     }
 }
 
-#[derive(Clone)]
-pub enum CommandRange {}
+/// A range of lines a command applies to, e.g. `%`, `3,5` or `.,/end/-1`.
+#[derive(Clone, PartialEq, Eq)]
+pub enum CommandRange {
+    /// `%`, every line of the buffer.
+    Full,
+    /// `{start}` or `{start},{end}`, both inclusive.
+    Lines { start: Address, end: Address },
+}
+
+/// A line in a [`CommandRange`].
+#[derive(Clone, PartialEq, Eq)]
+pub enum Address {
+    /// `.`, the line of the cursor.
+    Current,
+    /// `$`, the last line.
+    Last,
+    /// A 1-indexed line number.
+    Line(usize),
+    /// `'{a-z}`, the line of a mark set with `:mark`.
+    Mark(char),
+    /// `/{pattern}/` or `?{pattern}?`, the next line below (or above) the cursor line matching the
+    /// pattern, wrapping around the end of the buffer.
+    Search { pattern: String, backward: bool },
+    /// `{address}+{n}` or `{address}-{n}`, `n` lines below or above the address.
+    /// A missing address is the cursor line and a missing `n` is 1, e.g. `+` is `.+1`.
+    Offset(Box<Address>, isize),
+}
+
+impl CommandRange {
+    /// Resolve the range to (0-indexed) line indices given the cursor line and the number of lines.
+    /// Marks and searches can't be resolved without the buffer, see [`CommandRange::resolve`].
+    pub fn lines(&self, cursor_line: usize, len_lines: usize) -> Result<Range<usize>, Error> {
+        self.resolve(cursor_line, len_lines, |addr| anyhow::bail!("cannot resolve `{addr:?}`"))
+    }
+
+    /// Resolve the range to (0-indexed) line indices, using `lookup` for the (0-indexed) line of
+    /// marks and searches.
+    pub fn resolve(
+        &self,
+        cursor_line: usize,
+        len_lines: usize,
+        mut lookup: impl FnMut(&Address) -> Result<usize, Error>,
+    ) -> Result<Range<usize>, Error> {
+        let last = len_lines.saturating_sub(1);
+        match self {
+            CommandRange::Full => Ok(0..len_lines.max(1)),
+            CommandRange::Lines { start, end } => {
+                let start = start.resolve(cursor_line, last, &mut lookup)?;
+                let end = end.resolve(cursor_line, last, &mut lookup)?;
+                if start > end {
+                    anyhow::bail!("backwards range")
+                }
+
+                if end > last {
+                    anyhow::bail!("invalid range")
+                }
+
+                Ok(start..end + 1)
+            }
+        }
+    }
+}
+
+impl Address {
+    fn resolve(
+        &self,
+        cursor_line: usize,
+        last: usize,
+        lookup: &mut impl FnMut(&Address) -> Result<usize, Error>,
+    ) -> Result<usize, Error> {
+        match self {
+            Address::Current => Ok(cursor_line),
+            Address::Last => Ok(last),
+            Address::Line(n) => Ok(n.saturating_sub(1)),
+            Address::Mark(_) | Address::Search { .. } => lookup(self),
+            Address::Offset(addr, n) => addr
+                .resolve(cursor_line, last, lookup)?
+                .checked_add_signed(*n)
+                .ok_or_else(|| anyhow::anyhow!("invalid range")),
+        }
+    }
+}
 
 impl fmt::Debug for CommandRange {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {}
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandRange::Full => write!(f, "%"),
+            CommandRange::Lines { start, end } if start == end => write!(f, "{start:?}"),
+            CommandRange::Lines { start, end } => write!(f, "{start:?},{end:?}"),
+        }
     }
 }
 
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Current => write!(f, "."),
+            Address::Last => write!(f, "$"),
+            Address::Line(n) => write!(f, "{n}"),
+            Address::Mark(c) => write!(f, "'{c}"),
+            Address::Search { pattern, backward: false } => {
+                write!(f, "/{}/", pattern.replace('/', "\\/"))
+            }
+            Address::Search { pattern, backward: true } => {
+                write!(f, "?{}?", pattern.replace('?', "\\?"))
+            }
+            Address::Offset(addr, n) => write!(f, "{addr:?}{n:+}"),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Command {
     range: Option<CommandRange>,
     kind: CommandKind,
@@ -136,25 +248,141 @@ fn commands() -> impl Parser<char, Commands, Error = chumsky::error::Simple<char
 }
 
 fn command() -> impl Parser<char, Command, Error = chumsky::error::Simple<char>> {
-    command_kind().map(|kind| Command { range: None, kind })
+    use chumsky::prelude::*;
+
+    filter(|&c: &char| c.is_whitespace() && c != '\n')
+        .repeated()
+        .ignore_then(range().or_not())
+        .then(command_kind())
+        .map(|(range, kind)| Command { range, kind })
+}
+
+// `filter` is used over `just` for the leading characters so they don't show up as expected in errors.
+fn range() -> impl Parser<char, CommandRange, Error = chumsky::error::Simple<char>> {
+    use chumsky::prelude::*;
+
+    let number = || {
+        digits(10).try_map(|n: String, span| {
+            n.parse::<usize>().map_err(|_| Simple::custom(span, "number too large"))
+        })
+    };
+
+    let delimited =
+        |delim: char| pattern(delim).delimited_by(filter(move |&c: &char| c == delim), just(delim));
+
+    let base = choice((
+        filter(|&c: &char| c == '.').to(Address::Current),
+        filter(|&c: &char| c == '$').to(Address::Last),
+        number().map(Address::Line),
+        filter(|&c: &char| c == '\'')
+            .ignore_then(filter(|c: &char| c.is_ascii_alphabetic()))
+            .map(Address::Mark),
+        delimited('/').map(|pattern| Address::Search { pattern, backward: false }),
+        delimited('?').map(|pattern| Address::Search { pattern, backward: true }),
+    ));
+
+    let offset = filter(|&c: &char| c == '+')
+        .to(1)
+        .or(filter(|&c: &char| c == '-').to(-1))
+        .then(number().or_not())
+        .try_map(|(sign, n), span| {
+            isize::try_from(n.unwrap_or(1))
+                .map(|n| sign * n)
+                .map_err(|_| Simple::custom(span, "number too large"))
+        });
+
+    let address = choice((
+        base.then(offset.clone().repeated()),
+        offset.repeated().at_least(1).map(|offsets| (Address::Current, offsets)),
+    ))
+    .map(|(addr, offsets)| {
+        if offsets.is_empty() {
+            addr
+        } else {
+            Address::Offset(Box::new(addr), offsets.into_iter().sum())
+        }
+    });
+
+    filter(|&c: &char| c == '%').to(CommandRange::Full).or(address
+        .clone()
+        .then(just(',').ignore_then(address).or_not())
+        .map(|(start, end)| CommandRange::Lines {
+            end: end.unwrap_or_else(|| start.clone()),
+            start,
+        }))
+}
+
+/// A pattern up to `delim`, which can be escaped with `\` within the pattern, other escapes are
+/// left as is.
+fn pattern(delim: char) -> impl Parser<char, String, Error = chumsky::error::Simple<char>> + Clone {
+    use chumsky::prelude::*;
+
+    just('\\')
+        .ignore_then(any())
+        .map(move |c| if c == delim { vec![c] } else { vec!['\\', c] })
+        .or(filter(move |&c: &char| c != delim && c != '\\' && c != '\n').map(|c| vec![c]))
+        .repeated()
+        .flatten()
+        .collect::<String>()
 }
 
 fn command_kind() -> impl Parser<char, CommandKind, Error = chumsky::error::Simple<char>> {
     use chumsky::prelude::*;
 
-    ident()
-        .or(digits(10))
-        .separated_by(filter(|&c: &char| c.is_whitespace() && c != '\n').ignored().repeated())
+    let space = || filter(|&c: &char| c.is_whitespace() && c != '\n');
+
+    // `!{cmd}` takes the rest of the line verbatim, including any `;`.
+    let shell = filter(|&c: &char| c == '!')
+        .ignore_then(filter(|&c: &char| c != '\n').repeated().collect::<String>())
+        .map(|cmd| CommandKind::Shell { cmd: cmd.trim().to_string() });
+    // `:g/{pattern}/{cmd}` also takes the rest of the line as the commands to run on each line.
+    // The delimiter can be any punctuation other than `\`, `"`, and `|`.
+    let delim = filter(|&c: &char| c.is_ascii_punctuation() && !matches!(c, '\\' | '"' | '|'));
+    let global = choice((just("global"), just("g"), just("vglobal"), just("v")))
+        .then(just('!').or_not())
+        .then(delim.then_with(|delim| pattern(delim).then_ignore(just(delim))))
+        .then(filter(|&c: &char| c != '\n').repeated().collect::<String>())
+        .try_map(|(((name, bang), pattern), cmds), span| {
+            let cmds = cmds.parse::<Commands>().map_err(|err| Simple::custom(span, err))?;
+            let invert = name.starts_with('v') || bang.is_some();
+            Ok(CommandKind::Global { pattern, invert, cmds })
+        });
+    // `:normal {keys}` takes the rest of the line as keys, spaces included.
+    let normal = choice((just("normal"), just("norm")))
+        .ignore_then(just('!').or_not())
+        .then_ignore(space().repeated().at_least(1))
+        .then(filter(|&c: &char| c != '\n').repeated().at_least(1).collect::<String>())
+        .map(|(bang, keys)| CommandKind::Normal { keys, remap: bang.is_none() });
+    // `:echo {expr}` takes the rest of the line as the expression, so whitespace in strings is kept.
+    let echo = just("echo")
+        .ignore_then(space().repeated().at_least(1))
+        .ignore_then(filter(|&c: &char| c != '\n').repeated().at_least(1).collect::<String>())
+        .map(|expr| CommandKind::Echo { expr });
+    // Arguments are anything without whitespace (e.g. paths), `;` separates commands.
+    // An expression argument, `` `=expr` ``, extends to the closing backtick, whitespace included.
+    let expr = just("`=")
+        .ignore_then(filter(|&c: &char| c != '`' && c != '\n').repeated().collect::<String>())
+        .then_ignore(just('`'))
+        .map(Arg::Expr);
+    let word = filter(|&c: &char| !c.is_whitespace() && c != ';')
+        .repeated()
         .at_least(1)
-        .allow_leading()
-        .allow_trailing()
+        .collect::<String>()
+        .map(|s| Arg::Word(Word::try_from(s).unwrap()));
+    let arg = expr.or(word);
+
+    let generic = space()
+        .repeated()
+        .ignore_then(ident().or(digits(10)))
         .then(just('!').or_not())
-        .map(|(words, bang)| {
-            let mut words = words.into_iter().map(|s| Word::try_from(s).unwrap());
-            let cmd = words.next().expect("expect at least 1 word");
-            let args = words.collect::<Box<_>>();
-            CommandKind::Generic { cmd, args, force: bang.is_some() }
-        })
+        .then(space().repeated().at_least(1).ignore_then(arg).repeated())
+        .then_ignore(space().repeated())
+        .map(|((cmd, bang), args): ((String, _), Vec<Arg>)| {
+            let cmd = Word::try_from(cmd).unwrap();
+            CommandKind::Generic { cmd, args: args.into(), force: bang.is_some() }
+        });
+
+    shell.or(global).or(normal).or(echo).or(generic)
 }
 
 /// A single word in a command, without whitespace.
@@ -215,8 +443,48 @@ impl TryFrom<&str> for Word {
     }
 }
 
+/// An argument of a command as written, see [`CommandKind::Generic`].
+#[derive(Clone, PartialEq, Eq)]
+pub enum Arg {
+    Word(Word),
+    /// `` `=expr` ``, replaced by the value of the expression before the command runs.
+    Expr(String),
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::Word(word) => write!(f, "{word}"),
+            Arg::Expr(expr) => write!(f, "`={expr}`"),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub enum CommandKind {
-    Generic { cmd: Word, args: Box<[Word]>, force: bool },
+    Generic {
+        cmd: Word,
+        args: Box<[Arg]>,
+        force: bool,
+    },
+    Shell {
+        cmd: String,
+    },
+    /// `:g/{pattern}/{cmd}`, or `:v/{pattern}/{cmd}` (`invert`) for the lines not matching.
+    Global {
+        pattern: String,
+        invert: bool,
+        cmds: Commands,
+    },
+    /// `:normal {keys}`, or `:normal! {keys}` (not `remap`) to ignore user mappings.
+    Normal {
+        keys: String,
+        remap: bool,
+    },
+    /// `:echo {expr}`
+    Echo {
+        expr: String,
+    },
 }
 
 impl fmt::Debug for CommandKind {
@@ -231,6 +499,21 @@ impl fmt::Debug for CommandKind {
                     write!(f, " {arg}")?;
                 }
             }
+            CommandKind::Shell { cmd } => write!(f, "!{cmd}")?,
+            CommandKind::Global { pattern, invert, cmds } => {
+                let name = if *invert { "v" } else { "g" };
+                write!(f, "{name}/{}/", pattern.replace('/', "\\/"))?;
+                for (i, cmd) in cmds.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, "{cmd:?}")?;
+                }
+            }
+            CommandKind::Normal { keys, remap } => {
+                write!(f, "normal{} {keys}", if *remap { "" } else { "!" })?
+            }
+            CommandKind::Echo { expr } => write!(f, "echo {expr}")?,
         }
         Ok(())
     }
@@ -315,12 +598,23 @@ impl Handler {
         args: Box<[Word]>,
         force: bool,
     ) -> Result<(), Error> {
-        self.check(range.as_ref(), &args)?;
-        let fut = self.executor.execute(editor.client(), range, args, force);
+        let fut = self.future(editor, range, args, force)?;
         editor.spawn("command handler", fut);
         Ok(())
     }
 
+    /// Returns a future that runs the command, see [`Handler::execute`] to run it in the background.
+    pub fn future(
+        &self,
+        editor: &Editor,
+        range: Option<CommandRange>,
+        args: Box<[Word]>,
+        force: bool,
+    ) -> Result<BoxFuture<'static, Result<(), Error>>, Error> {
+        self.check(range.as_ref(), &args)?;
+        Ok(self.executor.execute(editor.client(), range, args, force))
+    }
+
     fn check(&self, range: Option<&CommandRange>, args: &[Word]) -> Result<(), Error> {
         if !self.arity.contains(&(args.len() as u8)) {
             if self.arity.min == self.arity.max {
@@ -345,6 +639,16 @@ impl Handler {
     pub fn name(&self) -> Word {
         Word::clone(&self.name)
     }
+
+    #[inline]
+    pub fn arity(&self) -> &Arity {
+        &self.arity
+    }
+
+    #[inline]
+    pub fn flags(&self) -> CommandFlags {
+        self.opts
+    }
 }
 
 impl Arity {
@@ -361,13 +665,65 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
             Word::try_from("q").unwrap(),
             Arity::ZERO,
             CommandFlags::empty(),
+            executor_fn(|client, range, args, force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                quit(&client, Active, force).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("qa").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(move |editor| editor.quit_all(force)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("close").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
             executor_fn(|client, range, args, _force| async move {
                 assert!(range.is_none());
                 assert!(args.is_empty());
-                close_view(&client, Active).await;
+                client.with(|editor| editor.close_window(Active)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("only").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.view_only(editor.view(Active).id())).await;
                 Ok(())
             }),
         ),
+        Handler::new(
+            Word::try_from("resize").unwrap(),
+            Arity::from(0..=1),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                client.with(move |editor| resize(editor, Dimension::Height, args.first())).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("vertical").unwrap(),
+            Arity::from(1..=2),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                if &*args[0] != "resize" {
+                    anyhow::bail!("`vertical` is not supported for `{}`", args[0]);
+                }
+
+                client.with(move |editor| resize(editor, Dimension::Width, args.get(1))).await
+            }),
+        ),
         Handler::new(
             Word::try_from("w").unwrap(),
             Arity::ZERO,
@@ -396,8 +752,7 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
                 assert!(range.is_none());
                 assert!(args.is_empty());
                 save(&client, Active, force).await?;
-                close_view(&client, Active).await;
-                Ok(())
+                quit(&client, Active, force).await
             }),
         ),
         Handler::new(
@@ -408,8 +763,29 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
                 assert!(range.is_none());
                 assert!(args.is_empty());
                 save(&client, Active, force).await?;
-                close_view(&client, Active).await;
-                Ok(())
+                quit(&client, Active, force).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("wqa").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                save_all(&client, force).await?;
+                client.with(move |editor| editor.quit_all(force)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("xa").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                save_all(&client, force).await?;
+                client.with(move |editor| editor.quit_all(force)).await
             }),
         ),
         Handler::new(
@@ -445,6 +821,91 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
                 Ok(())
             }),
         ),
+        Handler::new(
+            Word::try_from("capabilities").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_capabilities()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("record").unwrap(),
+            Arity::from(0..=1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                let path = args.first().map(|path| PathBuf::from(path.as_str()));
+                client
+                    .with(move |editor| {
+                        let path = path.map(|path| editor.resolve_path(path));
+                        editor.record_terminal(path)
+                    })
+                    .await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("cd").unwrap(),
+            Arity::exact(1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.change_dir(args[0].as_str())).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("lcd").unwrap(),
+            Arity::exact(1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.change_local_dir(Active, args[0].as_str())).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("pwd").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client
+                    .with(|editor| {
+                        let cwd = editor.current_dir(Active).display().to_string();
+                        editor.notify(MessageLevel::Info, cwd)
+                    })
+                    .await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("help").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_help()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("checkhealth").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.checkhealth()).await;
+                Ok(())
+            }),
+        ),
         Handler::new(
             Word::try_from("explore").unwrap(),
             Arity::ZERO,
@@ -456,15 +917,321 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
                 Ok(())
             }),
         ),
+        Handler::new(
+            Word::try_from("create").unwrap(),
+            Arity::exact(1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.create_file(&args[0])).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("rename").unwrap(),
+            Arity::exact(1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.rename_active_file(&args[0])).await?.await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("trash").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.trash_file()).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("terminal").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_terminal()).await?;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("Gitstage").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.stage_hunk(Active)).await?.await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("Gitreset").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.reset_hunk(Active)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("Gitblame").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.toggle_git_blame(Active)).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("RainbowToggle").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client
+                    .with(|editor| {
+                        let ft = editor.buffer(Active).file_type();
+                        editor.toggle_rainbow_delimiters(ft)
+                    })
+                    .await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("diffsplit").unwrap(),
+            Arity::exact(1),
+            CommandFlags::PATHS,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.diff_split(Active, &*args[0])).await?.await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("diffget").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.diff_get(Active)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("diffput").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.diff_put(Active)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("diffoff").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.diff_off()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("recover").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.recover(Active)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("messages").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_messages()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("ls").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_buffer_list()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("b").unwrap(),
+            Arity::exact(1),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client
+                    .with(move |editor| {
+                        let buf = editor.find_buffer(&args[0])?;
+                        editor.set_buffer(Active, buf);
+                        Ok(())
+                    })
+                    .await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("bd").unwrap(),
+            Arity::from(0..=1),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, force| async move {
+                assert!(range.is_none());
+                client
+                    .with(move |editor| {
+                        let buf = match args.first() {
+                            Some(query) => editor.find_buffer(query)?,
+                            None => editor.buffer(Active).id(),
+                        };
+                        editor.delete_buffer(buf, force)
+                    })
+                    .await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("browse").unwrap(),
+            Arity::exact(1),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                if &*args[0] != "oldfiles" {
+                    anyhow::bail!("cannot browse `{}`", args[0]);
+                }
+
+                client.with(|editor| editor.open_oldfiles()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("copen").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.open_quickfix()).await;
+                Ok(())
+            }),
+        ),
+        Handler::new(
+            Word::try_from("cnext").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.quickfix_next()).await?.await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("cprev").unwrap(),
+            Arity::ZERO,
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.is_empty());
+                client.with(|editor| editor.quickfix_prev()).await?.await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("colorscheme").unwrap(),
+            Arity::exact(1),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() == 1);
+                client.with(move |editor| editor.colorscheme(&args[0])).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("highlight").unwrap(),
+            Arity::from(2..=u8::MAX),
+            CommandFlags::empty(),
+            executor_fn(|client, range, args, _force| async move {
+                assert!(range.is_none());
+                assert!(args.len() >= 2);
+                client.with(move |editor| highlight(editor, &args)).await
+            }),
+        ),
         Handler::new(
             Word::try_from("set").unwrap(),
-            Arity::exact(2),
+            Arity::from(2..=u8::MAX),
             CommandFlags::empty(),
             executor_fn(|client, range, args, _force| async move {
                 assert!(range.is_none());
-                assert!(args.len() == 2);
+                assert!(args.len() >= 2);
 
-                client.with(move |editor| set_option(editor, &args[0], &args[1])).await
+                // Values with spaces are split into multiple arguments, e.g. `:set statusline`.
+                let value = args[1..].iter().map(|arg| arg.as_str()).collect::<Vec<_>>().join(" ");
+                client.with(move |editor| set_option(editor, &args[0], &value)).await
+            }),
+        ),
+        map_handler(
+            "map",
+            &[Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock],
+            true,
+        ),
+        map_handler("nmap", &[Mode::Normal], true),
+        map_handler("vmap", &[Mode::Visual, Mode::VisualLine, Mode::VisualBlock], true),
+        map_handler("imap", &[Mode::Insert], true),
+        map_handler("cmap", &[Mode::Command], true),
+        map_handler(
+            "noremap",
+            &[Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock],
+            false,
+        ),
+        map_handler("nnoremap", &[Mode::Normal], false),
+        map_handler("vnoremap", &[Mode::Visual, Mode::VisualLine, Mode::VisualBlock], false),
+        map_handler("inoremap", &[Mode::Insert], false),
+        map_handler("cnoremap", &[Mode::Command], false),
+        Handler::new(
+            Word::try_from("d").unwrap(),
+            Arity::ZERO,
+            CommandFlags::RANGE,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(args.is_empty());
+                client.with(move |editor| delete(editor, range)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("retab").unwrap(),
+            Arity::ZERO,
+            CommandFlags::RANGE,
+            executor_fn(|client, range, args, _force| async move {
+                assert!(args.is_empty());
+                client.with(move |editor| retab(editor, range)).await
+            }),
+        ),
+        Handler::new(
+            Word::try_from("mark").unwrap(),
+            Arity::exact(1),
+            CommandFlags::RANGE,
+            executor_fn(|client, range, args, _force| async move {
+                client.with(move |editor| mark(editor, range, &args[0])).await
             }),
         ),
     ]
@@ -473,23 +1240,194 @@ pub(crate) fn builtin_handlers() -> HashMap<Word, Handler> {
     .collect()
 }
 
-pub fn set_option(editor: &Editor, key: &str, value: &str) -> crate::Result<()> {
+/// `:{mode}map {lhs} {rhs}` maps `lhs` to `rhs` in the modes of the command, recursively unless
+/// it's a `noremap` command. The arguments after `lhs` are joined by spaces, e.g.
+/// `:nmap <leader>n :set number true<CR>`.
+fn map_handler(name: &str, modes: &'static [Mode], recursive: bool) -> Handler {
+    Handler::new(
+        Word::try_from(name).unwrap(),
+        Arity::from(2..=u8::MAX),
+        CommandFlags::empty(),
+        executor_fn(move |client, range, args, _force| async move {
+            assert!(range.is_none());
+            assert!(args.len() >= 2);
+            let rhs = args[1..].iter().map(|arg| arg.as_str()).collect::<Vec<_>>().join("<space>");
+            client.with(move |editor| editor.map(modes, &args[0], &rhs, recursive)).await
+        }),
+    )
+}
+
+pub fn set_option(editor: &mut Editor, key: &str, value: &str) -> crate::Result<()> {
+    if let "modifiable" | "ma" = key {
+        return match value.parse()? {
+            true => editor.make_writable(Active),
+            false => anyhow::bail!("buffers cannot be made readonly"),
+        };
+    }
+
     let buf = editor.buffer(Active).settings();
     let view = editor.view(Active).settings();
 
     match key {
         "tabstop" | "ts" | "tabwidth" => buf.tab_width.write(value.parse()?),
+        "expandtab" | "et" => buf.indent.write(if value.parse()? {
+            IndentSettings::Spaces(*buf.tab_width.read())
+        } else {
+            IndentSettings::Tabs
+        }),
         "numberwidth" | "nuw" => view.line_number_width.write(value.parse()?),
         "numberstyle" | "nus" => view.line_number_style.write(value.parse()?),
+        "cursorline" | "cul" => view.cursor_line.write(value.parse()?),
+        "cursorcolumn" | "cuc" => view.cursor_column.write(value.parse()?),
+        "colorcolumn" | "cc" => view.color_columns.write(
+            value
+                .split(',')
+                .filter(|col| !col.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        ),
+        "list" => view.list.write(value.parse()?),
+        "listchars" | "lcs" => view.list_chars.write(value.parse()?),
+        "conceallevel" | "cole" => match value.parse()? {
+            level @ 0..=3 => view.conceal_level.write(level),
+            _ => anyhow::bail!("conceallevel must be between 0 and 3"),
+        },
+        "fileformat" | "ff" => buf.line_ending.write(value.parse()?),
+        "fixendofline" | "fixeol" => buf.fix_end_of_line.write(value.parse()?),
+        "trimtrailingwhitespace" | "ttw" => buf.trim_trailing_whitespace.write(value.parse()?),
+        "gitblame" => buf.git_blame.write(value.parse()?),
+        "autopairs" => buf.autopairs.write(value.parse()?),
+        "spell" => buf.spell.write(value.parse()?),
+        "spelllang" | "spl" => buf.spell_lang.write(value.to_string()),
+        "spellfile" | "spf" => {
+            buf.spell_file.write(Some(value).filter(|path| !path.is_empty()).map(PathBuf::from))
+        }
+        "foldmethod" | "fdm" => buf.fold_method.write(value.parse()?),
+        "maxlinelength" | "textwidth" | "tw" => {
+            buf.max_line_length.write(Some(value.parse()?).filter(|&len| len > 0))
+        }
+        "fileencoding" | "fenc" => match encoding_rs::Encoding::for_label(value.as_bytes()) {
+            Some(encoding) => buf.encoding.write(encoding),
+            None => anyhow::bail!("unknown encoding: `{value}`"),
+        },
+        "clipboard" | "cb" => editor.settings().clipboard.write(match value {
+            "" | "none" => None,
+            _ => Some(value.parse()?),
+        }),
+        "timeoutlen" | "tm" => {
+            editor.settings().timeout_len.write(Duration::from_millis(value.parse()?))
+        }
+        "keyhintsdelay" => {
+            editor.settings().key_hints_delay.write(Duration::from_millis(value.parse()?))
+        }
+        "mapleader" | "leader" => editor.settings().leader.write(
+            value.parse().map_err(|_| anyhow::anyhow!("invalid leader key: `{value}`"))?,
+        ),
+        "directory" | "dir" => editor
+            .settings()
+            .swap_dir
+            .write(Some(value).filter(|dir| !dir.is_empty()).map(PathBuf::from)),
+        "updatetime" | "ut" => {
+            editor.settings().update_time.write(Duration::from_millis(value.parse()?))
+        }
+        "renderstats" => editor.settings().render_stats.write(value.parse()?),
+        "redrawinterval" | "rdi" => {
+            editor.settings().redraw_interval.write(Duration::from_millis(value.parse()?))
+        }
+        "messagehistory" | "mhi" => editor.settings().message_history.write(value.parse()?),
+        "statusline" | "stl" => editor.settings().statusline.write(value.parse()?),
+        "guicursor" | "gcr" => editor.settings().guicursor.write(value.parse()?),
+        "title" => editor.settings().title.write(value.parse()?),
+        "reportcwd" => editor.settings().report_cwd.write(value.parse()?),
+        "completeconfirm" => editor.settings().completion_confirm_keys.write(
+            value
+                .parse::<KeySequence>()
+                .map_err(|_| anyhow::anyhow!("invalid keys: `{value}`"))?
+                .into_iter()
+                .collect(),
+        ),
         _ => anyhow::bail!("unknown parameter: `{key}`"),
     }
     Ok(())
 }
 
+/// `:highlight link {from} {to}` links highlights, otherwise `:highlight {name} {style..}` sets the
+/// style of a highlight, e.g. `:highlight keyword fg=#ff0000 bold`.
+fn highlight(editor: &mut Editor, args: &[Word]) -> crate::Result<()> {
+    match args {
+        [link, from, to] if &**link == "link" => editor.link_highlight(from, to),
+        [name, style @ ..] => {
+            let style = style.iter().map(|part| &**part).collect::<Vec<_>>().join(" ");
+            editor.set_highlight(name, style.parse()?)
+        }
+        [] => anyhow::bail!("expected a highlight name"),
+    }
+    Ok(())
+}
+
+/// `:[range]delete` deletes the lines in the range, or the cursor line.
+fn delete(editor: &mut Editor, range: Option<CommandRange>) -> crate::Result<()> {
+    let lines = match range {
+        Some(range) => editor.resolve_range(Active, &range)?,
+        None => {
+            let line = editor.cursor(Active).line();
+            line..line + 1
+        }
+    };
+    editor.delete_lines(Active, lines)?;
+    Ok(())
+}
+
+/// `:[range]retab` converts the indentation of the lines in the range, or the whole buffer, to
+/// match the buffer's indent setting.
+fn retab(editor: &mut Editor, range: Option<CommandRange>) -> crate::Result<()> {
+    let lines = match range {
+        Some(range) => editor.resolve_range(Active, &range)?,
+        None => 0..editor.buffer(Active).text().len_lines(),
+    };
+    editor.retab(Active, lines)?;
+    Ok(())
+}
+
+/// `:[range]mark {a-z}` sets the mark to the last line of the range, or the cursor line.
+fn mark(editor: &mut Editor, range: Option<CommandRange>, name: &str) -> crate::Result<()> {
+    let mut chars = name.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        anyhow::bail!("invalid mark name: `{name}`")
+    };
+
+    let line = match range {
+        Some(range) => editor.resolve_range(Active, &range)?.end - 1,
+        None => editor.cursor(Active).line(),
+    };
+    editor.set_named_mark(Active, c, line)
+}
+
+/// `:resize` sets the size of the active view, or grows or shrinks it given a `+` or `-` prefix.
+/// Without an argument the view is made as large as possible.
+fn resize(editor: &mut Editor, dimension: Dimension, arg: Option<&Word>) -> crate::Result<()> {
+    let Some(arg) = arg else {
+        editor.set_view_size(Active, dimension, u16::MAX);
+        return Ok(());
+    };
+
+    let parse = |n: &str| n.parse::<u16>().map_err(|_| anyhow::anyhow!("invalid size: `{arg}`"));
+    match (arg.strip_prefix('+'), arg.strip_prefix('-')) {
+        (Some(n), _) => editor.resize_view(Active, dimension, parse(n)? as i32),
+        (_, Some(n)) => editor.resize_view(Active, dimension, -(parse(n)? as i32)),
+        _ => editor.set_view_size(Active, dimension, parse(arg.as_str())?),
+    }
+    Ok(())
+}
+
 pub async fn close_view(client: &Client, selector: impl Selector<ViewId> + Send + 'static) -> () {
     client.with(move |editor| editor.close_view(selector)).await
 }
 
+pub async fn quit(client: &Client, selector: impl Selector<ViewId> + Send + 'static, force: bool) -> crate::Result<()> {
+    client.with(move |editor| editor.quit(selector, force)).await
+}
+
 pub async fn save(client: &Client, selector: impl Selector<ViewId> + Send + 'static, force: bool) -> crate::Result<()> {
     let save_flags = if force { SaveFlags::FORCE } else { SaveFlags::empty() };
     client.with(move |editor| {
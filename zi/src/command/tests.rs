@@ -43,6 +43,60 @@ fn parse_command() {
         ("set x y", expect![[r#"
                 set x y
             "#]]),
+        ("e src/main.rs", expect![[r#"
+                e src/main.rs
+            "#]]),
+        ("w! a.txt ", expect![[r#"
+                w! a.txt
+            "#]]),
+        ("%!sort -u", expect![[r#"
+                % !sort -u
+            "#]]),
+        ("3,$!tr a-z A-Z; echo", expect![[r#"
+                3,$ !tr a-z A-Z; echo
+            "#]]),
+        (".!date", expect![[r#"
+                . !date
+            "#]]),
+        ("'a,'b!sort", expect![[r#"
+                'a,'b !sort
+            "#]]),
+        (".+3,$-1!x", expect![[r#"
+                .+3,$-1 !x
+            "#]]),
+        ("+,++2!x", expect![[r#"
+                .+1,.+3 !x
+            "#]]),
+        (r"/fn/,/a\/b/-1!x", expect![[r#"
+                /fn/,/a\/b/-1 !x
+            "#]]),
+        ("?^}?+retab", expect![[r#"
+                ?^}?+1 retab
+            "#]]),
+        ("g/^a/d", expect![[r#"
+                g/^a/d
+            "#]]),
+        (r"%v/a\/b/retab; d", expect![[r#"
+                % v/a\/b/retab;d
+            "#]]),
+        ("1,3g!#x# .,+1d", expect![[r#"
+                1,3 v/x/.,.+1 d
+            "#]]),
+        ("global/x/!sort; d", expect![[r#"
+                g/x/!sort; d
+            "#]]),
+        ("%norm! A; x", expect![[r#"
+                % normal! A; x
+            "#]]),
+        ("normal <C-w>v", expect![[r#"
+                normal <C-w>v
+            "#]]),
+        ("echo 'a  b'; x", expect![[r#"
+                echo 'a  b'; x
+            "#]]),
+        ("set ts `=line('$') * 3` x", expect![[r#"
+                set ts `=line('$') * 3` x
+            "#]]),
         (":extra colon", expect![[r#"found ":""#]]),
         (" \n", expect![[r#"found "\n""#]]),
     ] {
@@ -52,3 +106,23 @@ fn parse_command() {
         };
     }
 }
+
+#[test]
+fn resolve_command_range() {
+    let lines = |src: &str, cursor_line, len_lines| {
+        let cmd = src.parse::<Command>().unwrap();
+        cmd.range().unwrap().lines(cursor_line, len_lines).map_err(|err| err.to_string())
+    };
+
+    assert_eq!(lines("%!x", 2, 5), Ok(0..5));
+    assert_eq!(lines("%!x", 0, 0), Ok(0..1));
+    assert_eq!(lines(".!x", 2, 5), Ok(2..3));
+    assert_eq!(lines("2,$!x", 0, 5), Ok(1..5));
+    assert_eq!(lines(".,4!x", 1, 5), Ok(1..4));
+    assert_eq!(lines("4,2!x", 0, 5), Err("backwards range".to_string()));
+    assert_eq!(lines("1,6!x", 0, 5), Err("invalid range".to_string()));
+    assert_eq!(lines(".+1,$-1!x", 1, 5), Ok(2..4));
+    assert_eq!(lines("-2,+!x", 2, 5), Ok(0..4));
+    assert_eq!(lines(".-3!x", 2, 5), Err("invalid range".to_string()));
+    assert_eq!(lines("'a!x", 0, 5), Err("cannot resolve `'a`".to_string()));
+}
@@ -1,5 +1,11 @@
+mod sources;
+
+use std::any::TypeId;
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::fmt;
 use std::ops::DerefMut;
+use std::sync::Arc;
 
 use futures_core::future::BoxFuture;
 use nucleo::Utf32Str;
@@ -7,15 +13,110 @@ use nucleo::pattern::{Atom, AtomKind, CaseMatching, Normalization};
 use zi_core::CompletionItem;
 use zi_text::{Delta, DeltaRange};
 
+pub use self::sources::{BufferWordProvider, PathProvider, SnippetProvider};
 use crate::{Editor, Result, lstypes};
 
+/// A source of completions. Items from all the providers of a buffer are merged, and items with the
+/// same label are only shown once.
 // FIXME this can maybe merge with `LanguageService` now
 pub trait CompletionProvider: Send + Sync + 'static {
+    /// Items of providers with a higher priority are ranked above those of lower priority providers
+    /// when they match equally well, and are the ones kept when labels are duplicated.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Characters that request completions from this provider when typed, e.g. `.` for members.
+    /// Only the providers with the typed trigger character are asked for completions.
+    fn trigger_characters(&self) -> &[char] {
+        &[]
+    }
+
     fn completions(
         &self,
         editor: &mut Editor,
         params: lstypes::CompletionParams,
     ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>>;
+
+    /// Fill in the missing fields of an item returned by this provider, such as its documentation.
+    /// Items are resolved lazily when they are selected.
+    fn resolve(
+        &self,
+        editor: &mut Editor,
+        item: CompletionItem,
+    ) -> BoxFuture<'static, Result<CompletionItem>> {
+        let _ = editor;
+        Box::pin(async move { Ok(item) })
+    }
+}
+
+/// The registered completion providers, the language servers of a buffer are providers too.
+pub(crate) struct CompletionProviders {
+    providers: Vec<(TypeId, Arc<dyn CompletionProvider>)>,
+}
+
+impl Default for CompletionProviders {
+    fn default() -> Self {
+        let mut providers = Self { providers: vec![] };
+        providers.register(BufferWordProvider);
+        providers.register(PathProvider);
+        providers.register(SnippetProvider);
+        providers
+    }
+}
+
+impl CompletionProviders {
+    /// Register the provider, replacing any provider of the same type.
+    pub(crate) fn register<P: CompletionProvider>(&mut self, provider: P) {
+        self.unregister::<P>();
+        self.providers.push((TypeId::of::<P>(), Arc::new(provider)));
+    }
+
+    pub(crate) fn unregister<P: CompletionProvider>(&mut self) {
+        self.providers.retain(|(id, _)| *id != TypeId::of::<P>());
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Arc<dyn CompletionProvider>> {
+        self.providers.iter().map(|(_, provider)| provider)
+    }
+}
+
+/// A completion item and the provider it came from, which is the one that can resolve it.
+#[derive(Clone)]
+pub(crate) struct Candidate {
+    item: CompletionItem,
+    provider: Arc<dyn CompletionProvider>,
+    priority: i32,
+    resolved: bool,
+}
+
+impl Candidate {
+    pub(crate) fn new(item: CompletionItem, provider: Arc<dyn CompletionProvider>) -> Self {
+        let priority = provider.priority();
+        Self { item, provider, priority, resolved: false }
+    }
+
+    pub(crate) fn item(&self) -> &CompletionItem {
+        &self.item
+    }
+
+    pub(crate) fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub(crate) fn into_item(self) -> CompletionItem {
+        self.item
+    }
+}
+
+impl fmt::Debug for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Candidate")
+            .field("item", &self.item)
+            .field("priority", &self.priority)
+            .field("resolved", &self.resolved)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -29,7 +130,7 @@ pub(crate) enum Completion {
 pub(crate) struct ActiveCompletionState {
     widget_state: RefCell<tui::ListState>,
     replacement_range: DeltaRange,
-    options: Vec<CompletionItem>,
+    options: Vec<Candidate>,
     matches: Vec<nucleo::Match>,
     matcher: nucleo::Matcher,
     query: String,
@@ -97,15 +198,56 @@ impl ActiveCompletionState {
         self.widget_state.borrow_mut()
     }
 
+    /// The index into the options of the selected item.
+    fn selected_idx(&self) -> Option<usize> {
+        let idx = self.widget_state.borrow().selected()?;
+        self.matches.get(idx).map(|m| m.idx as usize)
+    }
+
+    pub fn selected(&self) -> Option<&CompletionItem> {
+        self.selected_idx().map(|idx| &self.options[idx].item)
+    }
+
+    /// Accept the selected item, or the best match if nothing is selected.
+    /// Returns the delta to apply if the item is not already inserted.
+    pub fn confirm(&mut self) -> Option<Delta<'static>> {
+        if self.selected_idx().is_some() {
+            return None;
+        }
+
+        self.select_next()
+    }
+
+    /// The selected item and its provider if it hasn't been resolved yet.
+    /// The item is assumed to be resolved from here on to avoid resolving it more than once.
+    pub fn take_unresolved(
+        &mut self,
+    ) -> Option<(usize, CompletionItem, Arc<dyn CompletionProvider>)> {
+        let idx = self.selected_idx()?;
+        let candidate = &mut self.options[idx];
+        if candidate.resolved {
+            return None;
+        }
+
+        candidate.resolved = true;
+        Some((idx, candidate.item.clone(), Arc::clone(&candidate.provider)))
+    }
+
+    pub fn set_resolved(&mut self, idx: usize, item: CompletionItem) {
+        // The items may have been replaced in the meantime.
+        if let Some(candidate) = self.options.get_mut(idx).filter(|c| c.item.label == item.label) {
+            candidate.item = item;
+        }
+    }
+
     fn select(&mut self) -> Option<Delta<'static>> {
-        let item = self
-            .widget_state
-            .borrow()
-            .selected()
-            .and_then(|idx| self.matches.get(idx))
-            .and_then(|m| self.options.get(m.idx as usize))?;
-
-        let replacement_text = item.insert_text.as_deref().unwrap_or(&item.label);
+        let item = &self.options[self.selected_idx()?].item;
+
+        // Snippets are only expanded once the item is confirmed.
+        let replacement_text = match &item.insert_text {
+            Some(text) if !item.snippet => text,
+            _ => &item.label,
+        };
         Some(self.generate_delta(replacement_text.to_owned()))
     }
 
@@ -121,7 +263,7 @@ impl ActiveCompletionState {
         self.replacement_range.start
     }
 
-    pub fn set_items(&mut self, options: Vec<CompletionItem>) {
+    pub fn set_items(&mut self, options: Vec<Candidate>) {
         self.options = options;
         self.compute_matches();
     }
@@ -143,7 +285,7 @@ impl ActiveCompletionState {
     }
 
     pub fn matches(&self) -> impl ExactSizeIterator<Item = &CompletionItem> {
-        Box::new(self.matches.iter().map(|m| &self.options[m.idx as usize]))
+        Box::new(self.matches.iter().map(|m| &self.options[m.idx as usize].item))
     }
 
     fn compute_matches(&mut self) {
@@ -158,7 +300,8 @@ impl ActiveCompletionState {
 
         let mut buf = vec![];
         self.matches.clear();
-        self.matches.extend(self.options.iter().enumerate().filter_map(|(idx, item)| {
+        self.matches.extend(self.options.iter().enumerate().filter_map(|(idx, candidate)| {
+            let item = &candidate.item;
             pattern
                 .score(
                     Utf32Str::new(item.filter_text.as_ref().unwrap_or(&item.label), &mut buf),
@@ -166,7 +309,13 @@ impl ActiveCompletionState {
                 )
                 .map(|score| nucleo::Match { idx: idx as u32, score: score as u32 })
         }));
-        self.matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        // Equally good matches are ordered by the priority of their provider and then their sort
+        // text, with deprecated items last.
+        let options = &self.options;
+        self.matches.sort_by_key(|m| {
+            let Candidate { item, priority, .. } = &options[m.idx as usize];
+            (Reverse(m.score), Reverse(*priority), item.deprecated, item.sort_text.as_ref())
+        });
 
         self.widget_state.borrow_mut().select(None);
     }
@@ -0,0 +1,164 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use futures_core::future::BoxFuture;
+use zi_core::{CompletionItem, CompletionItemKind};
+use zi_text::{Text, TextSlice};
+
+use super::CompletionProvider;
+use crate::{Editor, Result, lstypes};
+
+/// Completes the words in the buffer, like vim's `<C-n>`.
+pub struct BufferWordProvider;
+
+impl BufferWordProvider {
+    /// Only the lines this far from the cursor are searched for words.
+    const MAX_LINE_DISTANCE: usize = 2500;
+
+    /// Shorter words aren't worth completing.
+    const MIN_WORD_LEN: usize = 3;
+}
+
+impl CompletionProvider for BufferWordProvider {
+    fn priority(&self) -> i32 {
+        -10
+    }
+
+    fn completions(
+        &self,
+        editor: &mut Editor,
+        params: lstypes::CompletionParams,
+    ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>> {
+        let lstypes::TextDocumentPointParams { url, point } = params.at;
+        let Some(buf) = editor.buffers().find(|buf| buf.file_url() == Some(&url)) else {
+            return Box::pin(async { Ok(Default::default()) });
+        };
+
+        let mut words = BTreeSet::new();
+        let start = point.line().saturating_sub(Self::MAX_LINE_DISTANCE);
+        let lines = buf.text().lines().enumerate().skip(start).take(2 * Self::MAX_LINE_DISTANCE);
+        for (i, line) in lines {
+            let line = line.to_cow();
+            let mut start = None;
+            for (j, c) in line.char_indices().chain([(line.len(), ' ')]) {
+                match (start, c.is_alphanumeric() || c == '_') {
+                    (None, true) => start = Some(j),
+                    (Some(s), false) => {
+                        // The word being typed is not a completion of itself.
+                        let at_cursor = i == point.line() && (s..=j).contains(&point.col());
+                        if !at_cursor && line[s..j].chars().count() >= Self::MIN_WORD_LEN {
+                            words.insert(line[s..j].to_string());
+                        }
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let items = words
+            .into_iter()
+            .map(|label| CompletionItem {
+                label,
+                kind: Some(CompletionItemKind::Text),
+                ..Default::default()
+            })
+            .collect();
+        Box::pin(async move { Ok(lstypes::CompletionResponse { items }) })
+    }
+}
+
+/// Completes the entries of the directory of the path before the cursor, e.g. `src/` or `~/`.
+/// Relative paths are relative to the directory of the buffer.
+pub struct PathProvider;
+
+impl CompletionProvider for PathProvider {
+    fn priority(&self) -> i32 {
+        5
+    }
+
+    fn trigger_characters(&self) -> &[char] {
+        &['/']
+    }
+
+    fn completions(
+        &self,
+        editor: &mut Editor,
+        params: lstypes::CompletionParams,
+    ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>> {
+        let lstypes::TextDocumentPointParams { url, point } = params.at;
+        let dir = editor.buffers().find(|buf| buf.file_url() == Some(&url)).and_then(|buf| {
+            let line = buf.text().line(point.line())?.to_cow();
+            let before = &line[..point.col().min(line.len())];
+            let path = before.rsplit(|c: char| c.is_whitespace() || "\"'`()<>=,".contains(c));
+            let path = path.next()?;
+            let dir = &path[..path.rfind('/')? + 1];
+            match dir.strip_prefix("~/") {
+                Some(rest) => Some(directories_next::BaseDirs::new()?.home_dir().join(rest)),
+                None if dir.starts_with('/') => Some(PathBuf::from(dir)),
+                None => Some(buf.file_path()?.parent()?.join(dir)),
+            }
+        });
+
+        Box::pin(async move {
+            let Some(dir) = dir else { return Ok(Default::default()) };
+            let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+                return Ok(Default::default());
+            };
+
+            let mut items = vec![];
+            while let Some(entry) = entries.next_entry().await? {
+                let is_dir = entry.file_type().await?.is_dir();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                items.push(CompletionItem {
+                    label: if is_dir { format!("{name}/") } else { name },
+                    kind: Some(if is_dir {
+                        CompletionItemKind::Folder
+                    } else {
+                        CompletionItemKind::File
+                    }),
+                    ..Default::default()
+                });
+            }
+
+            items.sort_by(|a, b| a.label.cmp(&b.label));
+            Ok(lstypes::CompletionResponse { items })
+        })
+    }
+}
+
+/// Completes the prefixes of the snippets of the filetype of the buffer and expands the snippet
+/// once confirmed, see [`Editor::load_snippets`].
+pub struct SnippetProvider;
+
+impl CompletionProvider for SnippetProvider {
+    fn completions(
+        &self,
+        editor: &mut Editor,
+        params: lstypes::CompletionParams,
+    ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>> {
+        let url = params.at.url;
+        let Some(buf) = editor.buffers().find(|buf| buf.file_url() == Some(&url)) else {
+            return Box::pin(async { Ok(Default::default()) });
+        };
+
+        let items = editor
+            .snippets()
+            .of(buf.file_type().as_str())
+            .flat_map(|snippet| {
+                snippet.prefixes.iter().map(move |prefix| CompletionItem {
+                    label: prefix.clone(),
+                    insert_text: Some(snippet.body.clone()),
+                    kind: Some(CompletionItemKind::Snippet),
+                    detail: Some(
+                        snippet.description.clone().unwrap_or_else(|| snippet.name.clone()),
+                    ),
+                    documentation: Some(snippet.body.clone()),
+                    snippet: true,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        Box::pin(async move { Ok(lstypes::CompletionResponse { items }) })
+    }
+}
@@ -4,6 +4,7 @@ use std::sync::OnceLock;
 static DIRS: OnceLock<Dirs> = OnceLock::new();
 
 struct Dirs {
+    data_dir: PathBuf,
     grammar_dir: PathBuf,
     plugin_dirs: &'static [PathBuf],
     config_dir: PathBuf,
@@ -33,10 +34,14 @@ fn dirs() -> &'static Dirs {
         let plugin_path = std::env::var("ZI_PLUGIN_PATH").ok().unwrap_or_default();
         let plugin_dirs = Box::leak(plugin_path.split(':').map(PathBuf::from).collect::<Box<_>>());
 
-        Dirs { grammar_dir, plugin_dirs, config_dir }
+        Dirs { data_dir: data, grammar_dir, plugin_dirs, config_dir }
     })
 }
 
+pub fn data() -> &'static Path {
+    &dirs().data_dir
+}
+
 pub fn grammar() -> &'static Path {
     &dirs().grammar_dir
 }
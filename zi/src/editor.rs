@@ -1,23 +1,63 @@
+mod autopairs;
+mod buffer_list;
+mod case;
+mod clipboard;
+mod command_window;
+mod comment;
 mod completion;
 
 mod config;
 pub(crate) mod cursor;
+mod cwd;
+mod damage;
 mod default_keymap;
 mod diagnostics;
+mod diff;
 mod dot;
 mod errors;
+mod eval;
 mod events;
+mod explorer;
+mod float;
+mod fold;
+mod git;
+mod global;
+mod guicursor;
+mod health;
+mod help;
+mod increment;
+mod indent;
+mod join;
+mod key_hints;
 mod lsp_requests;
+mod mapping;
 mod marks;
+mod messages;
+mod mouse;
+mod oldfiles;
+mod paste;
 mod pickers;
+mod quickfix;
+mod quit;
+mod range;
 mod register;
 mod render;
+mod report;
 mod search;
+mod shell;
+mod snippet;
+mod spell;
 mod state;
+mod statusline;
+mod sub_mode;
+mod surround;
+mod swap;
+mod terminal;
+mod theme;
 pub mod visual;
 
 use std::any::Any;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs::File;
 use std::future::Future;
 use std::ops::{self, Deref, Index, IndexMut};
@@ -29,9 +69,10 @@ use std::time::{Duration, Instant};
 use std::{cmp, fmt, io, mem};
 
 use anyhow::{anyhow, bail};
-use arboard::Clipboard;
+use encoding_rs::Encoding;
+use futures_core::future::BoxFuture;
 use futures_util::stream::FuturesUnordered;
-use futures_util::{Stream, StreamExt};
+use futures_util::{FutureExt, Stream, StreamExt};
 use ignore::WalkState;
 use slotmap::SlotMap;
 use stdx::path::{PathExt, Relative};
@@ -42,40 +83,65 @@ use tokio::sync::{Notify, oneshot};
 use ustr::Ustr;
 use zi_core::{PointOrByte, PointRange, Size};
 use zi_indent::Indent;
-use zi_input::{Event, KeyCode, KeyEvent, KeySequence};
+use zi_input::{Event, KeyCode, KeyEvent, KeyModifiers, KeySequence};
 use zi_text::{
-    AnyText, Delta, Deltas, ReadonlyText, Rope, RopeBuilder, RopeCursor, Text, TextSlice,
+    AnyText, ContentHash, Delta, Deltas, LineEnding, LineEndingNormalizer, ReadonlyText, Rope,
+    RopeBuilder, Text, TextSlice,
 };
 use zi_textobject::motion::{self, Motion, MotionFlags};
 use zi_textobject::{TextObject, TextObjectFlags, TextObjectKind};
 
+use self::case::{Case, case_deltas};
+use self::command_window::CommandHistory;
 use self::config::Settings;
+use self::damage::Damage;
+pub use self::damage::RenderStats;
 use self::diagnostics::BufferDiagnostics;
+use self::diff::Diff;
 use self::dot::Dot;
 pub use self::errors::EditError;
+use self::fold::SavedFolds;
+use self::git::{GitBlame, GitDiff};
+pub use self::git::{Hunk, HunkKind};
+pub use self::guicursor::GuiCursor;
+pub(crate) use self::mapping::Mapping;
+pub use self::messages::{Message, MessageLevel};
+use self::oldfiles::OldFiles;
+pub use self::paste::PasteFlags;
+pub use self::pickers::PickerItem;
+use self::quickfix::Quickfix;
+pub use self::quickfix::QuickfixEntry;
 use self::register::Registers;
 pub use self::register::{Register, RegisterKind};
+use self::report::Reported;
 pub use self::search::Match;
 use self::search::SearchState;
+use self::snippet::SnippetSession;
+use self::spell::SpellState;
 use self::state::{OperatorPendingState, State};
+use self::swap::SwapState;
+pub use self::statusline::{Segment, StatusLine};
 use crate::buffer::picker::{BufferPicker, BufferPickerEntry, DynamicHandler, Picker};
 use crate::buffer::{
     Buffer, BufferFlags, EditFlags, ExplorerBuffer, IndentSettings, Injector, InspectorBuffer,
-    PickerBuffer, SnapshotFlags, TextBuffer,
+    OutputBuffer, PickerBuffer, SnapshotFlags, TerminalBuffer, TextBuffer,
 };
-use crate::command::{self, Command, CommandKind, Handler, Word};
-use crate::completion::Completion;
+use crate::command::{self, Command, CommandFlags, CommandKind, Handler, Word};
+use crate::completion::{Completion, CompletionProviders};
+use crate::editorconfig::EditorConfig;
 use crate::event::EventHandler;
 use crate::keymap::{DynKeymap, Keymap, TrieResult};
 use crate::language_service::LanguageServiceInstance;
-use crate::layout::Layer;
+use crate::layout::{BufferKind, Dimension, FloatAnchor, FloatConfig, Layer, Placement};
 use crate::plugin::PluginManager;
+use crate::snippet::Snippets;
 use crate::syntax::{HighlightId, Syntax, Theme};
 use crate::view::{SetCursorFlags, ViewGroup};
 use crate::{
-    BufferId, Direction, Error, FileType, LanguageService, LanguageServiceId, Location, Mode,
-    Namespace, NamespaceId, Operator, Point, Result, Setting, Url, VerticalAlignment, View,
-    ViewGroupId, ViewId, event, filetype, language, layout,
+    BufferId, Capabilities, Direction, Error, FileType, LanguageService, LanguageServiceId,
+    Location, Mode, Namespace, NamespaceId, Operator, Point, Result, Setting, StartupProfile,
+    TerminalRequest, Url, VerticalAlignment, View, ViewGroupId, ViewId, event, filetype, language,
+    layout,
 };
 
 bitflags::bitflags! {
@@ -97,24 +163,7 @@ bitflags::bitflags! {
     }
 }
 
-// Ergonomic closure like clipboard access macro.
-// This exists for two reasons:
-// - We don't use a function so we can do partial borrows of `self`
-// - We want to hide the `Arc` error handling boilerplate. The arc is necessary as the error is not clone.
-macro_rules! with_clipboard {
-    ($self:expr, |$cb:ident| $body:expr) => {
-        match &mut $self.clipboard {
-            Ok(cb) => {
-                let $cb = cb;
-                let res = $body;
-                res.map_err(Arc::new)
-            }
-            Err(err) => Err(Arc::clone(err)),
-        }
-    };
-}
-
-fn pool() -> &'static rayon::ThreadPool {
+pub(crate) fn pool() -> &'static rayon::ThreadPool {
     static POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
     POOL.get_or_init(|| rayon::ThreadPoolBuilder::new().build().unwrap())
 }
@@ -145,9 +194,28 @@ pub struct Editor {
     // We key diagnostics by `path` instead of `BufferId` as it is valid to send diagnostics for an unloaded buffer.
     // The per-buffer diagnostics are sorted by range.
     diagnostics: HashMap<PathBuf, BufferDiagnostics>,
+    git: HashMap<BufferId, GitDiff>,
+    git_blame: HashMap<BufferId, GitBlame>,
+    diff: Option<Diff>,
     empty_buffer: BufferId,
+    /// Buffers opened by the user, see [`Editor::listed_buffers`].
+    listed_buffers: Vec<BufferId>,
+    /// The numbers of the listed buffers, see [`Editor::buffer_number`].
+    buffer_numbers: HashMap<BufferId, u32>,
+    /// The number given to the next listed buffer, numbers are never reused.
+    next_buffer_number: u32,
+    oldfiles: OldFiles,
+    command_history: CommandHistory,
+    completion_providers: CompletionProviders,
+    snippets: Snippets,
+    snippet_session: Option<SnippetSession>,
+    spell: SpellState,
+    swap: SwapState,
+    /// Folds of files that have not been opened yet, see [`Editor::load_folds`].
+    saved_folds: HashMap<PathBuf, SavedFolds>,
     settings: Settings,
     search_state: SearchState,
+    quickfix: Quickfix,
     state: State,
     keymap: Keymap,
     active_language_services_by_ft: HashMap<FileType, Vec<LanguageServiceId>>,
@@ -156,14 +224,50 @@ pub struct Editor {
     tree: layout::ViewTree,
     /// error to be displayed in the status line
     status_error: Option<String>,
+    /// The log of errors and other messages shown by `:messages`.
+    messages: VecDeque<Message>,
     command_handlers: HashMap<Word, Handler>,
     // plugins: Plugins,
     notify_quit: Notify,
     backend: Box<dyn Backend>,
     plugin_managers: BTreeMap<&'static str, Arc<dyn PluginManager + Send + Sync>>,
-    clipboard: Result<Clipboard, Arc<arboard::Error>>,
+    /// Errors from plugin managers, kept for `:checkhealth`.
+    plugin_errors: Vec<String>,
+    clipboard: crate::clipboard::Provider,
+    capabilities: Capabilities,
+    terminal_requests: Vec<TerminalRequest>,
+    reported: Reported,
+    startup: StartupProfile,
+    /// The working directory set with `:cd`, see [`Editor::current_dir`].
+    cwd: PathBuf,
+    /// The active view and its cursor when [`event::DidMoveCursor`] was last dispatched.
+    last_cursor: Option<(ViewId, Point)>,
     dot: Dot,
     count: Option<usize>,
+    /// The register selected with `"` for the next command.
+    selected_register: Option<char>,
+    /// The action of an ambiguous key sequence and the keys pressed after it. The action is run if
+    /// no mapping extends the sequence before `keys_deadline`.
+    pending_action: Option<(Mapping, Vec<KeyEvent>)>,
+    /// Uses tokio's clock, as does `key_hints_deadline`, so that paused time in tests applies.
+    keys_deadline: Option<tokio::time::Instant>,
+    /// The continuations of the current key sequence, shown once `key_hints_deadline` passes.
+    key_hints: Vec<(KeyEvent, String)>,
+    key_hints_deadline: Option<tokio::time::Instant>,
+    /// The keymap without the mappings defined by the user, for non-recursive mappings.
+    builtin_keymap: Keymap,
+    /// The number of user mappings being expanded.
+    mapping_depth: usize,
+    /// Whether the expansion of the user mappings was aborted as they recursed too deeply, the
+    /// remaining keys of the expansion are discarded.
+    mapping_aborted: bool,
+    /// Whether the keys being handled are the expansion of a non-recursive mapping.
+    noremap: bool,
+    /// The mappings of the sub-modes defined by plugins, by name.
+    sub_mode_keymap: Keymap<String, KeyEvent, Mapping>,
+    /// The active sub-mode, see [`Editor::map_sub_mode`].
+    sub_mode: Option<String>,
+    damage: Damage,
 }
 
 macro_rules! mode {
@@ -243,9 +347,10 @@ fn request_redraw() {
 }
 
 macro_rules! set_error {
-    ($editor:ident, $error:expr) => {
-        $editor.status_error = Some($error.to_string())
-    };
+    ($editor:ident, $error:expr) => {{
+        let error = $error.to_string();
+        $editor.notify($crate::editor::MessageLevel::Error, error)
+    }};
 }
 
 use set_error;
@@ -467,14 +572,34 @@ impl Editor {
             requests_tx,
             // plugins,
             empty_buffer,
+            listed_buffers: Default::default(),
+            buffer_numbers: Default::default(),
+            next_buffer_number: 1,
+            oldfiles: Default::default(),
+            command_history: Default::default(),
+            completion_providers: Default::default(),
+            snippets: Default::default(),
+            snippet_session: None,
+            spell: Default::default(),
+            swap: Default::default(),
+            saved_folds: Default::default(),
             settings,
-            clipboard: Clipboard::new().map_err(Arc::new),
+            clipboard: crate::clipboard::Provider::detect(),
+            capabilities: Default::default(),
+            terminal_requests: Default::default(),
+            reported: Default::default(),
+            startup: Default::default(),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            last_cursor: None,
             backend: Box::new(backend),
             keymap: default_keymap::new(),
             tree: layout::ViewTree::new(size, active_view),
             command_handlers: command::builtin_handlers(),
             registers: Default::default(),
             diagnostics: Default::default(),
+            git: Default::default(),
+            git_blame: Default::default(),
+            diff: Default::default(),
             notify_quit: Default::default(),
             view_groups: Default::default(),
             language_config: Default::default(),
@@ -482,10 +607,25 @@ impl Editor {
             active_language_services_by_ft: Default::default(),
             state: Default::default(),
             search_state: Default::default(),
+            quickfix: Default::default(),
             status_error: Default::default(),
+            messages: Default::default(),
             plugin_managers: Default::default(),
+            plugin_errors: Default::default(),
             dot: Default::default(),
             count: None,
+            selected_register: None,
+            pending_action: None,
+            keys_deadline: None,
+            key_hints: Default::default(),
+            key_hints_deadline: None,
+            builtin_keymap: default_keymap::new(),
+            mapping_depth: 0,
+            mapping_aborted: false,
+            noremap: false,
+            sub_mode_keymap: Default::default(),
+            sub_mode: None,
+            damage: Default::default(),
         };
 
         let notify_redraw = NOTIFY_REDRAW.get_or_init(Default::default);
@@ -541,8 +681,12 @@ impl Editor {
             match content_inspector::inspect(&buf[..n]) {
                 content_inspector::ContentType::UTF_8
                 | content_inspector::ContentType::UTF_8_BOM => {}
+                // Other encodings are transcoded on open, but the readonly implementation requires utf-8.
+                content_inspector::ContentType::UTF_16LE
+                | content_inspector::ContentType::UTF_16BE
+                    if !open_flags.contains(OpenFlags::READONLY) => {}
                 _ => {
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, "non-utf8 data"));
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported encoding"));
                 }
             }
 
@@ -562,7 +706,7 @@ impl Editor {
         open_flags: OpenFlags,
     ) -> io::Result<impl Future<Output = Result<BufferId>> + 'static> {
         let theme = self.theme().clone();
-        let mut path = path.as_ref().to_path_buf();
+        let mut path = self.resolve_path(path);
         self.check_open(&mut path, open_flags)?;
 
         let ft = FileType::detect(&path);
@@ -610,6 +754,19 @@ impl Editor {
                 let path = path.to_path_buf();
                 client
                     .with(move |editor| match plan {
+                        Plan::Replace(id)
+                            if !flags.contains(BufferFlags::READONLY)
+                                && !editor[id].flags().contains(BufferFlags::READONLY) =>
+                        {
+                            // Reload the text in place to keep the marks, cursors, and history.
+                            let deltas = zi_text::diff(editor[id].text(), &text);
+                            editor
+                                .edit_flags(id, &deltas, EditFlags::NO_ENSURE_TRAILING_NEWLINE)
+                                .expect("buffer is writable");
+                            editor[id].snapshot(SnapshotFlags::empty());
+                            editor[id].flushed();
+                            id
+                        }
                         Plan::Replace(id) => {
                             let buf = Buffer::new(TextBuffer::new(
                                 id,
@@ -640,6 +797,8 @@ impl Editor {
             }
 
             let start = Instant::now();
+            let loaded = !matches!(plan, Plan::Existing(_));
+            let mut format = None;
             let buf = if let Plan::Existing(id) = plan {
                 id
             } else if open_flags.contains(OpenFlags::READONLY) {
@@ -649,16 +808,55 @@ impl Editor {
                 execute(&client, plan, ft, &path, text, theme, BufferFlags::READONLY, syntax).await
             } else {
                 let rope = if path.exists() {
-                    rope_from_reader(tokio::fs::File::open(&path).await?).await?
+                    let reader = tokio::fs::File::open(&path).await?;
+                    let (rope, detected) = match rope_from_reader(reader, None).await {
+                        Ok(res) => res,
+                        // Not valid utf-8, fallback to a single byte encoding which can decode anything.
+                        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                            tracing::info!(
+                                ?path,
+                                "file is not valid utf-8, falling back to windows-1252"
+                            );
+                            let reader = tokio::fs::File::open(&path).await?;
+                            rope_from_reader(reader, Some(encoding_rs::WINDOWS_1252)).await?
+                        }
+                        Err(err) => return Err(err.into()),
+                    };
+                    format = Some(detected);
+                    rope
                 } else {
                     Rope::new()
                 };
                 execute(&client, plan, ft, &path, rope, theme, BufferFlags::empty(), syntax).await
             };
 
+            // Only newly loaded buffers are configured so settings changed since are kept.
+            let editorconfig = match std::path::absolute(&path) {
+                Ok(path) if loaded => EditorConfig::for_path(&path)
+                    .inspect_err(|err| tracing::warn!(?path, %err, "failed to read editorconfig"))
+                    .ok(),
+                _ => None,
+            };
+
             client
                 .with(move |editor| {
+                    let settings = editor[buf].settings();
+                    if let Some(format) = format {
+                        settings.encoding.write(format.encoding);
+                        if let Some(line_ending) = format.line_ending {
+                            settings.line_ending.write(line_ending);
+                        }
+                    }
+
+                    if let Some(editorconfig) = editorconfig {
+                        editorconfig.apply(settings);
+                    }
+
                     if !open_flags.contains(OpenFlags::BACKGROUND) {
+                        editor.list_buffer(buf);
+                        if let Some(path) = editor[buf].file_path() {
+                            editor.record_oldfile(path);
+                        }
                         editor.set_buffer(Active, buf);
                     }
 
@@ -677,6 +875,42 @@ impl Editor {
         })
     }
 
+    /// Make a readonly buffer editable in place, keeping its id, marks, cursors, and syntax.
+    /// This is a no-op if the buffer is already writable.
+    pub fn make_writable(&mut self, selector: impl Selector<BufferId>) -> Result<()> {
+        let buf = selector.select(self);
+        if !self[buf].flags().contains(BufferFlags::READONLY) {
+            return Ok(());
+        }
+
+        if !self[buf].make_writable() {
+            bail!("buffer `{}` cannot be made writable", self[buf].url());
+        }
+
+        // Writable text has its line endings normalized to `\n` as if it were opened normally.
+        let mut normalizer = LineEndingNormalizer::default();
+        let mut builder = RopeBuilder::new();
+        for chunk in self[buf].text().byte_slice(..).chunks() {
+            normalizer.normalize(chunk, |s| {
+                builder.append(s);
+            });
+        }
+
+        if let Some(line_ending) = normalizer.finish() {
+            self[buf].settings().line_ending.write(line_ending);
+        }
+
+        let normalized = builder.build();
+        if ContentHash::new(&normalized) != self[buf].content_hash() {
+            let deltas = zi_text::diff(self[buf].text(), &normalized);
+            let flags = EditFlags::NO_RECORD | EditFlags::NO_ENSURE_TRAILING_NEWLINE;
+            self.edit_flags(buf, &deltas, flags).expect("buffer is writable");
+            self[buf].flushed();
+        }
+
+        Ok(())
+    }
+
     pub fn register_command(&mut self, handler: Handler) -> Option<Handler> {
         self.command_handlers.insert(handler.name(), handler)
     }
@@ -700,6 +934,8 @@ impl Editor {
     }
 
     async fn shutdown(&mut self) {
+        self.delete_swaps();
+
         for mut server in mem::take(&mut self.active_language_services).into_values() {
             // TODO shutdown concurrently
             if let Err(err) = server.shutdown().await {
@@ -725,8 +961,8 @@ impl Editor {
         self.status_error.as_deref()
     }
 
+    /// Show an error in the status line, it is also recorded in the message log.
     pub fn set_error(&mut self, error: impl fmt::Display) {
-        // TODO push all the corresponding tracing error in here
         set_error!(self, error);
     }
 
@@ -736,12 +972,49 @@ impl Editor {
             return (1, self.tree.area().height + 1);
         }
 
-        let (view, buf) = get_ref!(self);
-        let area = self.tree.view_area(view.id());
-        let (x, y) = view.cursor_viewport_coords(buf);
+        let view = self.view(Active).id();
+        let area = self.tree.view_area(view);
+        let (x, y) = self.view_cursor_coords(view);
         (x + area.x, y + area.y)
     }
 
+    /// The 0-indexed display column of the cursor of the view, where tabs extend to the next tab stop.
+    pub fn virtual_col(&self, selector: impl Selector<ViewId>) -> usize {
+        let view = &self[selector.select(self)];
+        self.buffer(view.buffer()).display_col(view.cursor())
+    }
+
+    /// The point displayed at the coordinates relative to the start of the text of the view,
+    /// e.g. to find the text under the mouse. `None` if there is no line at the row.
+    pub fn view_point_at(
+        &self,
+        selector: impl Selector<ViewId>,
+        (x, y): (u16, u16),
+    ) -> Option<Point> {
+        let view = &self[selector.select(self)];
+        let buf = self.buffer(view.buffer());
+        let (rows, fillers) = self.view_rows(view.id());
+        if fillers.contains(&(y as usize)) {
+            return None;
+        }
+        let line = *rows.get(y as usize)?;
+        Some(buf.point_at_display_col(line, view.offset().col + x as usize))
+    }
+
+    /// The position of the cursor of the view relative to the start of its text.
+    pub(crate) fn view_cursor_coords(&self, view: ViewId) -> (u16, u16) {
+        let view = &self[view];
+        let buf = self.buffer(view.buffer());
+        let (x, y) = view.cursor_viewport_coords(buf);
+        if self.closed_folds(buf.id()).is_empty() && !self.is_diffing(view.id()) {
+            return (x, y);
+        }
+
+        let (rows, _) = self.view_rows(view.id());
+        let row = rows.partition_point(|&line| line <= view.cursor().line()).saturating_sub(1);
+        (x, row as u16)
+    }
+
     pub fn input<S>(&mut self, seq: S) -> Result<(), S::Error>
     where
         S: TryInto<KeySequence>,
@@ -756,7 +1029,46 @@ impl Editor {
     pub fn handle_input(&mut self, event: impl Into<Event>) {
         match event.into() {
             Event::Key(key) => self.handle_key_event(key),
+            // Mappings are triggered on press, releases are ignored.
+            Event::KeyRelease(_) => (),
             Event::Resize(width, height) => self.resize(Size::new(width, height)),
+            Event::Mouse(event) => self.handle_mouse_event(event),
+            Event::Paste(text) => {
+                self.status_error = None;
+                if let Err(err) = self.paste_text(Active, &text) {
+                    set_error!(self, err);
+                }
+            }
+            Event::Text(text) => self.handle_text(&text),
+        }
+    }
+
+    /// Type text as a whole, e.g. composed by an input method, so its grapheme clusters are not
+    /// split up by the keys handled in between. Unless in insert or command mode without a
+    /// pending key sequence or sub-mode, or in a terminal, each character is handled as a key
+    /// instead.
+    fn handle_text(&mut self, text: &str) {
+        let mode = mode!(self);
+        let (_, buf) = get!(self);
+        let terminal = buf.as_any().is::<TerminalBuffer>();
+        if !matches!(mode, Mode::Insert | Mode::Command)
+            || terminal
+            || self.sub_mode.is_some()
+            || self.keys_pending()
+        {
+            for c in text.chars() {
+                self.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            }
+            return;
+        }
+
+        self.status_error = None;
+        for c in text.chars() {
+            self.dot.maybe_record(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+
+        if let Err(err) = self.paste_text(Active, text) {
+            set_error!(self, err);
         }
     }
 
@@ -772,6 +1084,9 @@ impl Editor {
         request_redraw();
     }
 
+    /// The most events handled after another before rendering.
+    const MAX_COALESCED_EVENTS: usize = 256;
+
     #[doc(hidden)]
     pub async fn test_run(mut self, tasks: Tasks) -> io::Result<()> {
         self.run(futures_util::stream::empty(), tasks, |_| Ok(())).await
@@ -786,6 +1101,7 @@ impl Editor {
         Self::subscribe_async_hooks().await;
 
         render(self)?;
+        self.record_startup("first render");
 
         let mut plugin_manager_handles = FuturesUnordered::from_iter(
             self.plugin_managers.values().cloned().map(|m| tokio::spawn(m.start(self.client()))),
@@ -796,33 +1112,40 @@ impl Editor {
         let mut callbacks = pin!(callbacks.buffer_unordered(128).peekable());
 
         let mut events = pin!(events);
+        let mut last_render = tokio::time::Instant::now();
+        // Whether a render was skipped as it was too soon after the last one.
+        let mut render_pending = false;
         loop {
+            let keys_deadline = self.keys_deadline.into_iter().chain(self.key_hints_deadline).min();
+            let render_deadline =
+                render_pending.then(|| last_render + *self.settings.redraw_interval.read());
+            // Anything may change other than with key events, see `Damage`.
+            let mut damage_all = true;
             select! {
                 biased;
-                Some(event) = events.next() => self.handle_input(event?),
+                Some(event) = events.next() => {
+                    let event = event?;
+                    damage_all = !matches!(event, Event::Key(_));
+                    self.handle_input(event)
+                }
+                () = tokio::time::sleep_until(keys_deadline.unwrap_or_else(tokio::time::Instant::now)), if keys_deadline.is_some() => self.on_keys_deadline(),
+                () = tokio::time::sleep_until(render_deadline.unwrap_or_else(tokio::time::Instant::now)), if render_deadline.is_some() => damage_all = false,
                 () = notify_redraw.notified() => tracing::debug!("redrawing due to request"),
-                f = callbacks.select_next_some() => match f {
-                    Ok(f) => if let Err(err) = f(self) {
-                        tracing::error!(error = &*err, "task callback failed");
-                        self.set_error(err);
-                    }
-                    Err(err) => {
-                        tracing::error!(error = &*err, "task failed");
-                        self.set_error(err);
-                    }
-                },
-                req = requests.select_next_some() => {
-                    // If the receiver dropped then we just ignore the request.
-                    let _ = req.tx.send((req.f)(self));
-                },
+                f = callbacks.select_next_some() => {
+                    self.handle_callback(f);
+                    damage_all = !self.only_lines_damaged();
+                }
+                req = requests.select_next_some() => self.handle_request(req),
                 Some(res) = plugin_manager_handles.next() => match res {
                         Ok(Ok(())) => (),
                         Ok(Err(err)) => {
                             tracing::error!(error = &*err, "plugin manager failed");
+                            self.plugin_errors.push(format!("{err:#}"));
                             self.set_error(err);
                         }
                         Err(err) => {
                             tracing::error!(error = &err as &dyn std::error::Error, "plugin manager died");
+                            self.plugin_errors.push(err.to_string());
                             self.set_error(err);
                         }
                     },
@@ -830,13 +1153,42 @@ impl Editor {
                 () = self.notify_quit.notified() => break,
             }
 
+            // Handle the input that is already available before rendering, e.g. key repeat.
+            for _ in 0..Self::MAX_COALESCED_EVENTS {
+                if self.should_quit() {
+                    break;
+                }
+
+                let Some(Some(event)) = events.next().now_or_never() else { break };
+                let event = event?;
+                damage_all |= !matches!(event, Event::Key(_));
+                self.handle_input(event);
+            }
+
+            self.check_cursor_moved();
+            self.sync_diff_views();
+            self.schedule_spell_marks();
+            if damage_all {
+                self.damage_all();
+            }
+
             // Don't immediately break here as we want to finish handling any events first
             if self.should_quit() {
                 // TODO if we don't break here some assertions fail
                 break;
             }
 
+            // Render at most once per `redraw_interval`, the skipped render happens once it has
+            // passed unless something else triggers a render first.
+            let now = tokio::time::Instant::now();
+            if now < last_render + *self.settings.redraw_interval.read() {
+                render_pending = true;
+                continue;
+            }
+
             render(self)?;
+            last_render = now;
+            render_pending = false;
         }
 
         self.shutdown().await;
@@ -844,6 +1196,42 @@ impl Editor {
         Ok(())
     }
 
+    /// Run the pending tasks until none have made progress for `idle`, to drive the editor
+    /// without [`Editor::run`], e.g. to wait for a command to finish in tests.
+    #[doc(hidden)]
+    pub async fn run_until_idle(&mut self, tasks: &mut Tasks, idle: Duration) {
+        let mut callbacks = pin!((&mut tasks.callbacks).buffer_unordered(128));
+        loop {
+            select! {
+                biased;
+                Some(f) = callbacks.next() => self.handle_callback(f),
+                Some(req) = tasks.requests.next() => self.handle_request(req),
+                () = tokio::time::sleep(idle) => break,
+            }
+        }
+    }
+
+    /// Run the callback of a finished task, the task and callback errors are reported.
+    fn handle_callback(&mut self, f: Result<EditorCallback, Error>) {
+        match f {
+            Ok(f) => {
+                if let Err(err) = f(self) {
+                    tracing::error!(error = &*err, "task callback failed");
+                    self.set_error(err);
+                }
+            }
+            Err(err) => {
+                tracing::error!(error = &*err, "task failed");
+                self.set_error(err);
+            }
+        }
+    }
+
+    fn handle_request(&mut self, req: Request) {
+        // If the receiver dropped then we just ignore the request.
+        let _ = req.tx.send((req.f)(self));
+    }
+
     fn update_search(&mut self) {
         let view = self.tree().active();
         let active_buffer = self[view].buffer();
@@ -854,8 +1242,8 @@ impl Editor {
         match k {
             ":" => {}
             "/" => {
-                use regex_cursor::Input;
-                use regex_cursor::engines::meta::Regex;
+                use stdx::sync::Cancel;
+                use zi_text::search::{Regex, Search};
 
                 if query.is_empty() {
                     return;
@@ -865,27 +1253,24 @@ impl Editor {
                     return;
                 }
 
-                let regex = match Regex::new(query) {
-                    Ok(regex) => regex,
+                // This is run synchronously, so the search is cancelled after a strict limit to
+                // prevent noticable latency. However, this may mean not all matches are found which
+                // needs a solution.
+                let cancel = Cancel::after(Duration::from_millis(20));
+                let search = match Regex::new(query) {
+                    Ok(regex) => Search::new(regex).with_cancel(cancel),
                     Err(err) => return set_error!(self, err),
                 };
 
                 let (view, buf) = get!(self);
 
                 let text = buf.text();
-                let input = Input::new(RopeCursor::new(text.byte_slice(..)));
-
-                let start_time = Instant::now();
                 self.search_state.set_matches(
                     text.point_to_byte(view.cursor()),
-                    regex
-                        .find_iter(input)
-                        // This is run synchronously, so we add a strict limit to prevent noticable latency.
-                        // However, this may mean not all matches are found which needs a solution.
+                    search
+                        .forward(text, 0)
                         .take(1000)
-                        .take_while(|_| start_time.elapsed() < Duration::from_millis(20))
-                        .map(|m| {
-                            let byte_range = m.range().clone();
+                        .map(|byte_range| {
                             #[cfg(debug_assertions)]
                             text.byte_slice(byte_range.clone());
                             Match { byte_range }
@@ -901,7 +1286,7 @@ impl Editor {
 
     fn handle_insert(&mut self, c: char) -> Result<(), EditError> {
         match &mut self.state {
-            State::Insert(..) => self.insert_char(Active, c),
+            State::Insert(..) => self.insert_char_autopair(Active, c),
             State::Command(state) => {
                 state.buffer.push(c);
                 self.update_search();
@@ -914,8 +1299,23 @@ impl Editor {
     #[inline]
     fn handle_key_event(&mut self, key: KeyEvent) {
         self.status_error = None;
+        if let Some(name) = self.sub_mode.clone() {
+            self.handle_sub_mode_key(name, key);
+            return;
+        }
+
         let mode = mode!(self);
 
+        if mode == Mode::Insert {
+            let (_, buf) = get!(self);
+            if let Some(to) = buf.intercept_insert_key(&key) {
+                if to != mode {
+                    self.set_mode(to);
+                }
+                return;
+            }
+        }
+
         // Save the key if we're in Normal mode (it might be the start of a change)
         if mode == Mode::Normal
             && !self.dot.is_replaying()
@@ -926,25 +1326,82 @@ impl Editor {
         }
 
         self.dot.maybe_record(&key);
+        self.dispatch_key(key);
+    }
 
-        let mut empty = Keymap::default();
-        let (_, buf) = get!(self);
-        let mut keymap = self.keymap.pair(buf.keymap().unwrap_or(&mut empty));
-
+    fn dispatch_key(&mut self, key: KeyEvent) {
+        let mode = mode!(self);
+        self.key_hints.clear();
+        self.key_hints_deadline = None;
         tracing::trace!(%key, "handling key");
-        match key.code() {
-            KeyCode::Char(c) if matches!(mode, Mode::ReplacePending) => {
+        if mode == Mode::Insert
+            && self.pending_action.is_none()
+            && self.settings.completion_confirm_keys.read().contains(&key)
+            && self.confirm_completion()
+        {
+            return;
+        }
+
+        if let KeyCode::Char(c) = key.code() {
+            if mode == Mode::ReplacePending {
+                if let State::ReplacePending(Some(surround)) = &self.state {
+                    if let Err(err) = self.surround_char(surround.clone(), c) {
+                        set_error!(self, err);
+                    }
+                    return;
+                }
+
                 self.set_mode(Mode::Insert);
                 self.move_cursor(Active, Direction::Right, 1);
                 let _ = self.delete_char(Active);
                 let _ = self.insert_char(Active, c);
                 self.set_mode(Mode::Normal);
+                return;
             }
+        }
+
+        let mut empty = Keymap::default();
+        let (_, buf) = get!(self);
+        let keymap = if self.noremap { &mut self.builtin_keymap } else { &mut self.keymap };
+        let mut keymap = keymap.pair(buf.keymap().unwrap_or(&mut empty));
+        let (res, buffered) = keymap.on_key(mode, key.clone());
+
+        if let Some((mapping, mut keys)) = self.pending_action.take() {
+            match res {
+                TrieResult::Partial if buffered.is_empty() => {
+                    keys.push(key.clone());
+                    self.pending_action = Some((mapping, keys));
+                }
+                // A longer mapping was completed, or is itself ambiguous.
+                TrieResult::Found(_) | TrieResult::Ambiguous(_) if buffered.is_empty() => (),
+                _ => {
+                    // No mapping extends the ambiguous sequence so its action is used after all
+                    // and the keys that followed it are handled again.
+                    self.reset_keymap();
+                    self.run_mapping(mapping);
+                    keys.push(key);
+                    keys.into_iter().for_each(|key| self.dispatch_key(key));
+                    return;
+                }
+            }
+        }
+
+        if let TrieResult::Ambiguous(mapping) = &res {
+            self.pending_action = Some((mapping.clone(), vec![]));
+        }
+
+        self.keys_deadline = self
+            .pending_action
+            .is_some()
+            .then(|| tokio::time::Instant::now() + *self.settings.timeout_len.read());
+        self.key_hints_deadline = matches!(res, TrieResult::Partial | TrieResult::Ambiguous(_))
+            .then(|| tokio::time::Instant::now() + *self.settings.key_hints_delay.read());
+
+        match key.code() {
             KeyCode::Char(_c) if matches!(mode, Mode::Insert | Mode::Command) => {
-                let (res, buffered) = keymap.on_key(mode, key);
                 match res {
-                    TrieResult::Found(f) => f(self),
-                    TrieResult::Partial | TrieResult::Nothing => (),
+                    TrieResult::Found(mapping) => self.run_mapping(mapping),
+                    TrieResult::Partial | TrieResult::Ambiguous(_) | TrieResult::Nothing => (),
                 }
 
                 for event in buffered {
@@ -958,16 +1415,21 @@ impl Editor {
                     }
                 }
             }
-            _ => match keymap.on_key(mode, key).0 {
-                TrieResult::Found(f) => {
-                    f(self);
-                    if mode == Mode::Normal && mode!(self) == Mode::Normal && self.count.is_none() {
+            _ => match res {
+                TrieResult::Found(mapping) => {
+                    self.run_mapping(mapping);
+                    if mode == Mode::Normal
+                        && mode!(self) == Mode::Normal
+                        && self.count.is_none()
+                        && self.selected_register.is_none()
+                    {
                         self.dot.clear_normal_keys();
                     }
                 }
-                TrieResult::Partial => (),
+                TrieResult::Partial | TrieResult::Ambiguous(_) => (),
                 TrieResult::Nothing => {
                     self.count = None;
+                    self.selected_register = None;
                     if matches!(mode, Mode::OperatorPending(_) | Mode::ReplacePending) {
                         self.set_mode(Mode::Normal)
                     }
@@ -976,6 +1438,47 @@ impl Editor {
         }
     }
 
+    fn on_keys_deadline(&mut self) {
+        let now = tokio::time::Instant::now();
+        if self.key_hints_deadline.is_some_and(|deadline| deadline <= now) {
+            self.key_hints_deadline = None;
+            self.update_key_hints();
+        }
+
+        if self.keys_deadline.is_some_and(|deadline| deadline <= now) {
+            self.timeout_keys();
+        }
+    }
+
+    /// No key extended the ambiguous key sequence within `timeoutlen`, so run its action.
+    fn timeout_keys(&mut self) {
+        self.keys_deadline = None;
+        self.key_hints.clear();
+        let Some((mapping, keys)) = self.pending_action.take() else { return };
+        self.reset_keymap();
+        self.run_mapping(mapping);
+        keys.into_iter().for_each(|key| self.dispatch_key(key));
+    }
+
+    /// Whether a key sequence is pending in the global keymap or the keymap of the active buffer.
+    fn keys_pending(&mut self) -> bool {
+        let mode = mode!(self);
+        let (_, buf) = get!(self);
+        self.pending_action.is_some()
+            || !self.keymap.continuations(&mode).is_empty()
+            || buf.keymap().is_some_and(|keymap| !keymap.continuations(&mode).is_empty())
+    }
+
+    /// Discard the keys of the current key sequence.
+    fn reset_keymap(&mut self) {
+        let (_, buf) = get!(self);
+        if let Some(keymap) = buf.keymap() {
+            keymap.reset();
+        }
+        self.keymap.reset();
+        self.builtin_keymap.reset();
+    }
+
     #[inline]
     pub fn mode(&self) -> Mode {
         mode!(self)
@@ -1041,18 +1544,78 @@ impl Editor {
         Error: From<<C as TryInto<Command>>::Error>,
     {
         let cmd: Command = cmd.try_into()?;
+        let desc = match cmd.kind() {
+            CommandKind::Generic { .. } => Some("command handler"),
+            CommandKind::Shell { .. } if cmd.range().is_some() => Some("filter"),
+            CommandKind::Normal { .. } => Some("normal"),
+            CommandKind::Echo { .. } => Some("echo"),
+            CommandKind::Shell { .. } | CommandKind::Global { .. } => None,
+        };
+
+        let fut = self.command_future(cmd)?;
+        match desc {
+            Some(desc) => self.spawn(desc, fut),
+            None => {
+                // Not `self.spawn` as the command may well outlive the callback timeout.
+                let client = self.client();
+                tokio::spawn(async move {
+                    if let Err(err) = fut.await {
+                        client.send(move |_| Err(err));
+                    }
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a future that runs the command to completion, see [`Editor::execute`] to run it
+    /// in the background.
+    pub fn command_future<C>(
+        &mut self,
+        cmd: C,
+    ) -> crate::Result<BoxFuture<'static, crate::Result<()>>>
+    where
+        C: TryInto<Command>,
+        Error: From<<C as TryInto<Command>>::Error>,
+    {
+        let cmd: Command = cmd.try_into()?;
+        // Commands may change anything, e.g. settings or the theme, see `Damage`.
+        self.damage_all();
         let range = cmd.range();
         match cmd.kind() {
-            CommandKind::Generic { cmd, args, force } => {
-                if let Some(handler) = self.command_handlers.get(cmd) {
-                    handler.execute(self, range.cloned(), args.clone(), *force)?;
-                } else {
-                    anyhow::bail!("unknown command: {cmd}")
+            CommandKind::Generic { cmd, args, force } => match self.command_handlers.get(cmd) {
+                Some(handler) => {
+                    let paths = handler.flags().contains(CommandFlags::PATHS);
+                    let args = self.expand_args(args, paths)?;
+                    handler.future(self, range.cloned(), args, *force)
+                }
+                None => anyhow::bail!("unknown command: {cmd}"),
+            },
+            CommandKind::Shell { cmd } => match range {
+                Some(range) => {
+                    let lines = self.resolve_range(Active, range)?;
+                    Ok(self.filter(Active, lines, cmd)?.boxed())
                 }
+                None => Ok(self.shell(cmd)?.boxed()),
+            },
+            CommandKind::Global { pattern, invert, cmds } => {
+                Ok(self.global(range, pattern, *invert, cmds.clone())?.boxed())
+            }
+            CommandKind::Normal { keys, remap } => {
+                let lines = range.map(|range| self.resolve_range(Active, range)).transpose()?;
+                self.normal(lines, keys, *remap)?;
+                Ok(std::future::ready(Ok(())).boxed())
+            }
+            CommandKind::Echo { expr } => {
+                if range.is_some() {
+                    anyhow::bail!("echo does not take a range");
+                }
+                let value = self.eval(expr)?;
+                self.notify(MessageLevel::Info, value);
+                Ok(std::future::ready(Ok(())).boxed())
             }
         }
-
-        Ok(())
     }
 
     fn execute_buffered_command(&mut self) -> Result<()> {
@@ -1063,23 +1626,39 @@ impl Editor {
             return Ok(());
         }
 
+        if let Some(expr) = state.buffer.strip_prefix('=') {
+            let expr = expr.to_string();
+            self.set_mode(Mode::Insert);
+            return self.insert_expression(&expr);
+        }
+
         let Some(cmd) = state.buffer.strip_prefix(':') else {
             bail!("command must start with `:`")
         };
 
-        let cmd = cmd.parse::<Command>();
+        let cmd = cmd.to_string();
         state.buffer.clear();
         self.set_mode(Mode::Normal);
-        self.execute(cmd?)?;
+        self.record_command(&cmd);
+        self.execute(cmd.parse::<Command>()?)?;
 
         Ok(())
     }
 
+    /// Leave command mode, returning to insert mode if prompting for an expression to insert.
+    pub(crate) fn exit_command_mode(&mut self) {
+        match &self.state {
+            State::Command(state) if state.buffer.starts_with('=') => self.set_mode(Mode::Insert),
+            _ => self.set_mode(Mode::Normal),
+        }
+    }
+
     #[inline]
     pub fn set_mode(&mut self, to: Mode) {
         let from = mode!(self);
 
         self.dispatch(event::WillChangeMode { from, to });
+        self.leave_sub_mode();
         self.state = State::new(self, to);
 
         self.dispatch(event::DidChangeMode { from, to });
@@ -1087,28 +1666,7 @@ impl Editor {
 
     fn insert_to_normal(&mut self) {
         assert_eq!(self.mode(), Mode::Insert);
-        let (view, buf) = self.get(Active);
-
-        {
-            // Clear any whitespace at the end of the cursor line when exiting insert mode
-            let cursor = self[view].cursor();
-            if let Some(range) = self[buf].text().line(cursor.line()).and_then(|line| {
-                let end = Point::new(cursor.line(), line.len_bytes());
-                let mut start = end;
-                for c in line.chars().rev() {
-                    if !c.is_whitespace() {
-                        break;
-                    }
-
-                    start = start.left(c.len_utf8());
-                }
-
-                (start != end).then(|| PointRange::new(start, end))
-            }) {
-                let byte_range = self[buf].text().point_range_to_byte_range(range);
-                let _ = self.edit(Active, &Deltas::delete(byte_range));
-            }
-        }
+        let (_, buf) = self.get(Active);
 
         self[buf].snapshot(SnapshotFlags::empty());
         // Move cursor left when exiting insert mode
@@ -1180,6 +1738,72 @@ impl Editor {
         split_view
     }
 
+    /// The area of the screen the view is displayed in.
+    pub fn view_area(&self, selector: impl Selector<ViewId>) -> tui::Rect {
+        self.tree.view_area(selector.select(self))
+    }
+
+    /// Grow (or shrink if negative) the view along the dimension by `delta` cells,
+    /// `<C-w>+`, `<C-w>-`, `<C-w>>`, and `<C-w><`.
+    pub fn resize_view(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        dimension: Dimension,
+        delta: i32,
+    ) {
+        let view = selector.select(self);
+        self.tree.resize_view(view, dimension, |size| {
+            (size as i32).saturating_add(delta).clamp(0, u16::MAX as i32) as u16
+        });
+    }
+
+    /// Set the size of the view along the dimension, clamped so that its neighbours keep at least
+    /// one cell, `:resize N` and `:vertical resize N`.
+    pub fn set_view_size(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        dimension: Dimension,
+        size: u16,
+    ) {
+        let view = selector.select(self);
+        self.tree.resize_view(view, dimension, |_| size);
+    }
+
+    /// Rotate the views in the split containing the view forwards (or backwards),
+    /// `<C-w>r` and `<C-w>R`.
+    pub fn rotate_views(&mut self, selector: impl Selector<ViewId>, forward: bool) -> Result<()> {
+        let view = selector.select(self);
+        self.tree.rotate(view, forward).map_err(|()| anyhow!("cannot rotate a split view"))
+    }
+
+    /// Exchange the view with the next one in its split, or the `n`th (zero-indexed) one if
+    /// given, `<C-w>x`. The other view is focused so the focus stays in the same place.
+    pub fn exchange_view(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        n: Option<usize>,
+    ) -> Result<()> {
+        let view = selector.select(self);
+        match self.tree.exchange(view, n) {
+            Ok(Some(other)) => self.focus(other),
+            Ok(None) => {}
+            Err(()) => bail!("cannot exchange with a split view"),
+        }
+        Ok(())
+    }
+
+    /// Move the view to the far edge of the layout in the direction, spanning its full width or
+    /// height, `<C-w>H`, `<C-w>J`, `<C-w>K`, and `<C-w>L`.
+    pub fn move_view_to_edge(&mut self, selector: impl Selector<ViewId>, direction: Direction) {
+        let view = selector.select(self);
+        self.tree.move_to_edge(view, direction);
+    }
+
+    /// Make all views in each split equally sized, `<C-w>=`.
+    pub fn equalize_views(&mut self) {
+        self.tree.equalize();
+    }
+
     pub fn focus(&mut self, selector: impl Selector<ViewId>) {
         let id = selector.select(self);
         self.tree.focus(id);
@@ -1224,8 +1848,21 @@ impl Editor {
                 };
                 let start_byte_idx =
                     byte_idx.checked_sub(c.len_utf8()).expect("just checked there's a char here");
+                // Delete an empty pair inserted by autopairs as a whole.
+                let end_byte_idx = match autopairs::closing(c) {
+                    Some(close)
+                        if mode!(self) == Mode::Insert
+                            && *buf.settings().autopairs.read()
+                            && text.byte_slice(byte_idx..).chars().next() == Some(close) =>
+                    {
+                        byte_idx + close.len_utf8()
+                    }
+                    _ => byte_idx,
+                };
 
-                buf.edit(&Deltas::delete(start_byte_idx..byte_idx));
+                let deltas = Deltas::delete(start_byte_idx..end_byte_idx);
+                let (old_text, version) = (dyn_clone::clone_box(buf.text()), buf.version());
+                buf.edit(&deltas);
 
                 view.set_cursor_bytewise(
                     mode!(self),
@@ -1235,7 +1872,8 @@ impl Editor {
                     SetCursorFlags::empty(),
                 );
 
-                let view = view.id();
+                let (view, buf) = (view.id(), buf.id());
+                self.damage_edit(buf, &*old_text, &deltas, version);
                 self.dispatch(event::DidDeleteChar { view });
 
                 Ok(())
@@ -1254,6 +1892,10 @@ impl Editor {
 
         let cursor_byte = self[view.buffer()].text().point_to_byte(cursor);
         let view_id = view.id();
+        // Typing over a selected snippet placeholder replaces it.
+        if let Some(range) = self.take_snippet_placeholder(view_id, cursor_byte) {
+            self.edit(view_id, &Deltas::delete(range))?;
+        }
         self.edit(view_id, &Deltas::insert_at(cursor_byte, &*c.encode_utf8(&mut cbuf)))?;
 
         let (view, buf) = get!(self);
@@ -1279,11 +1921,10 @@ impl Editor {
                 Ok(())
             }
             State::Insert(state) => {
-                if let Completion::Active(state) = &mut state.completion {
-                    if let Some(delta) = state.select_next() {
-                        self.apply_completion_delta(delta);
-                    }
-                } else {
+                // Tab moves through the completion menu, unless there is nothing to complete.
+                if state.completion.matches().len() > 0 {
+                    self.complete_next();
+                } else if !self.next_tabstop() {
                     let (view, buf) = self.get(Active);
                     let indent = *self[buf].settings().indent.read();
                     match indent {
@@ -1300,7 +1941,7 @@ impl Editor {
             | State::VisualBlock(..)
             | State::Command(..)
             | State::OperatorPending(_)
-            | State::ReplacePending => Ok(()),
+            | State::ReplacePending(_) => Ok(()),
         }
     }
 
@@ -1311,12 +1952,10 @@ impl Editor {
                 Ok(())
             }
             State::Insert(state) => {
-                if let Completion::Active(state) = &mut state.completion {
-                    if let Some(delta) = state.select_prev() {
-                        self.apply_completion_delta(delta);
-                    }
+                if state.completion.matches().len() > 0 {
+                    self.complete_prev();
                 } else {
-                    // TODO
+                    self.prev_tabstop();
                 }
                 Ok(())
             }
@@ -1326,29 +1965,38 @@ impl Editor {
             | State::VisualBlock(..)
             | State::Command(..)
             | State::OperatorPending(_)
-            | State::ReplacePending => Ok(()),
+            | State::ReplacePending(_) => Ok(()),
         }
     }
 
     fn indent_newline(&mut self, selector: impl Selector<ViewId>) -> Result<(), EditError> {
         let (view, buf) = self.get(selector);
+        self[buf].reparse();
         let text = self[buf].text();
         let cursor = self[view].cursor();
-        if cursor.col() > 0 || !text.line(cursor.line()).unwrap().is_empty() {
-            // Simple heuristics to determine if we should indent the new line
+        if cursor.col() > 0 {
             return Ok(());
         }
 
         let tab_width = *self[buf].settings().tab_width.read();
-        match zi_indent::indent(zi_indent::Config { tab_width }, text, cursor.line()) {
-            Indent::Bytes(bytes) => {
-                let start_byte = text.line_to_byte(cursor.line());
-                let deltas = Deltas::insert_at(start_byte, " ".repeat(bytes));
-                self.edit(buf, &deltas)?;
-                self.set_cursor(view, cursor.right(bytes));
-                Ok(())
-            }
-        }
+        // Prefer the language's indents query, falling back to heuristics.
+        let level = self[buf].syntax().and_then(|syntax| {
+            syntax.indent(&mut tree_sitter::QueryCursor::new(), text, cursor.line())
+        });
+        let bytes = match level {
+            Some(level) => level * tab_width as usize,
+            // Simple heuristics to determine if we should indent the new line
+            None if !text.line(cursor.line()).unwrap().is_empty() => return Ok(()),
+            None => match zi_indent::indent(zi_indent::Config { tab_width }, text, cursor.line()) {
+                Indent::Bytes(bytes) => bytes,
+            },
+        };
+
+        let start_byte = text.line_to_byte(cursor.line());
+        let deltas = Deltas::insert_at(start_byte, " ".repeat(bytes));
+        self.edit(buf, &deltas)?;
+        self.set_cursor(view, cursor.right(bytes));
+        Ok(())
     }
 
     fn get(&self, selector: impl Selector<ViewId>) -> (ViewId, BufferId) {
@@ -1435,13 +2083,15 @@ impl Editor {
             // This edit won't affect the primary delta so we don't need to adjust it.
             let len = self[buf].text().len_bytes();
             let newline_deltas = Deltas::insert_at(len, "\n");
-            let old_text = dyn_clone::clone_box(self[buf].text());
+            let (old_text, version) = (dyn_clone::clone_box(self[buf].text()), self[buf].version());
             self[buf].edit(&newline_deltas);
+            self.damage_edit(buf, &*old_text, &newline_deltas, version);
             self.dispatch(event::DidChangeBuffer { buf, old_text, deltas: newline_deltas });
         }
 
-        let old_text = dyn_clone::clone_box(self[buf].text());
+        let (old_text, version) = (dyn_clone::clone_box(self[buf].text()), self[buf].version());
         self[buf].edit_flags(deltas, flags);
+        self.damage_edit(buf, &*old_text, deltas, version);
 
         // set the cursor again in relevant views as it may be out of bounds after the edit
         for view in self.views_into_buf(buf) {
@@ -1499,15 +2149,69 @@ impl Editor {
 
     pub fn visual_op(&mut self, operator: Operator, selector: impl Selector<ViewId> + Copy) {
         let Some(sel) = self.visual_selection(selector) else { return };
+        if operator == Operator::Surround {
+            let buf = self[selector.select(self)].buffer();
+            let text = self[buf].text();
+            let range = match sel {
+                visual::Selection::Line { start_line, end_line } => {
+                    surround::surround_lines(text, start_line..end_line + 1)
+                }
+                visual::Selection::Charwise { .. } => sel.byte_ranges(text).remove(0),
+                visual::Selection::Block { .. } => return self.set_mode(Mode::Normal),
+            };
+            self.surround_pending(range);
+            return;
+        }
+
+        if matches!(
+            operator,
+            Operator::Filter | Operator::Fold | Operator::Reindent | Operator::Comment
+        ) {
+            let lines = sel.lines();
+            let lines = *lines.start()..*lines.end() + 1;
+            match operator {
+                Operator::Filter => self.prompt_filter(lines),
+                Operator::Fold => {
+                    self.create_fold(selector, lines);
+                    self.set_mode(Mode::Normal);
+                }
+                Operator::Comment => {
+                    self.set_mode(Mode::Normal);
+                    set_error_if!(self: self.toggle_comment(selector, lines));
+                }
+                _ => {
+                    self.set_mode(Mode::Normal);
+                    if let Err(err) = self.reindent(selector, lines) {
+                        set_error!(self, err);
+                    }
+                }
+            }
+            return;
+        }
+
+        if let Some(case) = Case::of(operator) {
+            let view = selector.select(self);
+            let buf = self[view].buffer();
+            let text = self[buf].text();
+            let deltas = Deltas::new(
+                sel.byte_ranges(text).into_iter().flat_map(|range| case_deltas(text, range, case)),
+            );
+            self.set_mode(Mode::Normal);
+            if let Err(err) = self.edit(buf, &deltas) {
+                set_error!(self, err);
+                return;
+            }
+            self[buf].snapshot(SnapshotFlags::empty());
+            self.set_cursor(view, sel.start_point());
+            return;
+        }
+
         let view = selector.select(self);
         let buf = self[view].buffer();
         let content = sel.content(self[buf].text());
         let kind = sel.register_kind();
 
-        if let Err(err) = with_clipboard!(self, |cb| cb.set_text(content.clone())) {
-            set_error!(self, err);
-        }
-        self.registers.get_or_insert(Registers::UNNAMED).set(kind, content);
+        self.set_register(kind, content);
 
         if matches!(operator, Operator::Delete | Operator::Change) {
             let byte_ranges = sel.byte_ranges(self[buf].text());
@@ -1575,30 +2279,40 @@ impl Editor {
         self.visual_op(Operator::Change, selector);
     }
 
-    pub fn register(&self, name: char) -> Option<&register::Register> {
-        self.registers.get(name)
+    pub fn visual_filter(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Filter, selector);
     }
 
-    pub fn paste_after(&mut self, selector: impl Selector<ViewId>) -> Result<(), EditError> {
-        // FIXME very naive implementation.
-        let Some(reg) = self.registers.get(Registers::UNNAMED).cloned() else {
-            return Ok(());
-        };
+    pub fn visual_fold(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Fold, selector);
+    }
 
-        match reg.kind {
-            register::RegisterKind::Charwise => self.insert(selector, &reg.content),
-            register::RegisterKind::Linewise => {
-                let (view, buf) = self.get(selector);
-                let cursor = self[view].cursor();
-                let text = self[buf].text();
-                let line_start_byte = text.line_to_byte(cursor.line() + 1).min(text.len_bytes());
-                let deltas =
-                    Deltas::new([Delta::insert_at(line_start_byte, format!("{}\n", &reg.content))]);
-                self.edit(buf, &deltas)?;
-                self.move_cursor(view, Direction::Down, 1);
-                Ok(())
-            }
-        }
+    pub fn visual_reindent(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Reindent, selector);
+    }
+
+    pub fn visual_surround(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Surround, selector);
+    }
+
+    pub fn visual_comment(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Comment, selector);
+    }
+
+    pub fn visual_lowercase(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Lowercase, selector);
+    }
+
+    pub fn visual_uppercase(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::Uppercase, selector);
+    }
+
+    pub fn visual_toggle_case(&mut self, selector: impl Selector<ViewId> + Copy) {
+        self.visual_op(Operator::ToggleCase, selector);
+    }
+
+    pub fn register(&self, name: char) -> Option<&register::Register> {
+        self.registers.get(name)
     }
 
     /// Applies the text object to the pending operator if there is one.
@@ -1692,8 +2406,61 @@ impl Editor {
             range = start_byte..end_byte;
         }
 
+        if operator == Operator::Surround {
+            let range = if obj_kind.is_linewise() {
+                let end_line = if end_point.col() == 0 && end_point.line() > start_point.line() {
+                    end_point.line()
+                } else {
+                    end_point.line() + 1
+                };
+                surround::surround_lines(text, start_point.line()..end_line)
+            } else {
+                range
+            };
+            self.surround_pending(range);
+            return Ok(());
+        }
+
+        if matches!(
+            operator,
+            Operator::Filter | Operator::Fold | Operator::Reindent | Operator::Comment
+        ) {
+            // Filtering, folding, reindenting and commenting are always linewise, an end at the
+            // start of a line is exclusive.
+            let end_line = if end_point.col() == 0 && end_point.line() > start_point.line() {
+                end_point.line()
+            } else {
+                end_point.line() + 1
+            };
+
+            let lines = start_point.line()..end_line;
+            match operator {
+                Operator::Filter => self.prompt_filter(lines),
+                Operator::Fold => {
+                    self.create_fold(view, lines);
+                    self.set_mode(Mode::Normal);
+                }
+                Operator::Comment => {
+                    self.set_mode(Mode::Normal);
+                    set_error_if!(self: self.toggle_comment(view, lines));
+                }
+                _ => {
+                    self.set_mode(Mode::Normal);
+                    self.reindent(view, lines)?;
+                }
+            }
+            return Ok(());
+        }
+
         let (deltas, new_cursor) = match operator {
+            Operator::Filter
+            | Operator::Fold
+            | Operator::Reindent
+            | Operator::Surround
+            | Operator::Comment => unreachable!("handled above"),
             Operator::Delete | Operator::Change => {
+                let deleted = text.byte_slice(range.clone()).to_cow();
+                self.set_register(obj_kind, deleted);
                 let deltas = Deltas::delete(range.clone());
                 let cursor = match obj_kind {
                     // linewise deletions move the line but maintain the column
@@ -1707,12 +2474,20 @@ impl Editor {
             }
             Operator::Yank => {
                 let text = text.byte_slice(range.clone()).to_cow();
-                if let Err(err) = with_clipboard!(self, |cb| cb.set_text(text.clone())) {
-                    set_error!(self, err);
-                }
-                self.registers.get_or_insert(Registers::UNNAMED).set(obj_kind, text);
+                self.set_register(obj_kind, text);
                 (Deltas::empty(), None)
             }
+            Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase => {
+                let case = Case::of(operator).expect("is a case operator");
+                let deltas = Deltas::new(case_deltas(text, range.clone(), case));
+                let cursor = match obj_kind {
+                    TextObjectKind::Linewise => {
+                        PointOrByte::Point(start_point.with_col(cursor.col()))
+                    }
+                    TextObjectKind::Charwise => PointOrByte::Byte(range.start),
+                };
+                (deltas, Some(cursor))
+            }
         };
 
         match operator {
@@ -1725,7 +2500,16 @@ impl Editor {
                 self.set_mode(Mode::Normal);
                 return Ok(());
             }
-            Operator::Yank | Operator::Delete => {}
+            Operator::Yank
+            | Operator::Delete
+            | Operator::Filter
+            | Operator::Fold
+            | Operator::Reindent
+            | Operator::Surround
+            | Operator::Comment
+            | Operator::Lowercase
+            | Operator::Uppercase
+            | Operator::ToggleCase => {}
         }
 
         self.edit(view, &deltas)?;
@@ -1748,7 +2532,16 @@ impl Editor {
                 }
                 self.set_mode(Mode::Normal)
             }
-            Operator::Yank => self.set_mode(Mode::Normal),
+            Operator::Yank
+            | Operator::Filter
+            | Operator::Fold
+            | Operator::Reindent
+            | Operator::Surround
+            | Operator::Comment => self.set_mode(Mode::Normal),
+            Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase => {
+                self[buf].snapshot(SnapshotFlags::empty());
+                self.set_mode(Mode::Normal)
+            }
         }
 
         if let Some(new_cursor) = new_cursor {
@@ -1781,7 +2574,16 @@ impl Editor {
         }
 
         match operator {
-            Operator::Delete | Operator::Change => {}
+            Operator::Delete
+            | Operator::Change
+            | Operator::Filter
+            | Operator::Fold
+            | Operator::Reindent
+            | Operator::Surround
+            | Operator::Comment
+            | Operator::Lowercase
+            | Operator::Uppercase
+            | Operator::ToggleCase => {}
             Operator::Yank => self.dispatch(event::DidYankText { buf, range }),
         }
 
@@ -1938,25 +2740,48 @@ impl Editor {
             event::dispatch_async(&client, event::WillSaveBuffer { buf }).await?;
 
             // Need to refetch flags as the hooks may have updated them
-            let (flags, text) = client
+            let (flags, text, line_ending, encoding) = client
                 .with(move |editor| {
+                    if editor[buf].flags().contains(BufferFlags::DIRTY)
+                        || save_flags.contains(SaveFlags::FORCE)
+                    {
+                        editor.apply_save_policies(buf)?;
+                    }
+
                     let buf = &editor[buf];
-                    (buf.flags(), dyn_clone::clone_box(buf.text()))
+                    let settings = buf.settings();
+                    let line_ending = *settings.line_ending.read();
+                    let encoding = *settings.encoding.read();
+                    Ok::<_, Error>((
+                        buf.flags(),
+                        dyn_clone::clone_box(buf.text()),
+                        line_ending,
+                        encoding,
+                    ))
                 })
-                .await;
+                .await?;
 
             if !flags.contains(BufferFlags::DIRTY) && !save_flags.contains(SaveFlags::FORCE) {
                 tracing::info!("buffer is not dirty, skipping write");
                 return Ok(());
             }
 
-            use tokio_util::compat::FuturesAsyncReadCompatExt;
-            let mut file = tokio::fs::File::create(path).await?;
-            let mut reader = futures_util::io::AllowStdIo::new(text.reader()).compat();
-            let mut writer = tokio::io::BufWriter::new(&mut file);
-            tokio::io::copy(&mut reader, &mut writer).await?;
-            writer.flush().await?;
-            file.flush().await?;
+            let mut reader = line_ending.reader(text.reader());
+            if encoding == encoding_rs::UTF_8 {
+                use tokio_util::compat::FuturesAsyncReadCompatExt;
+                let mut file = tokio::fs::File::create(path).await?;
+                let mut reader = futures_util::io::AllowStdIo::new(reader).compat();
+                let mut writer = tokio::io::BufWriter::new(&mut file);
+                tokio::io::copy(&mut reader, &mut writer).await?;
+                writer.flush().await?;
+                file.flush().await?;
+            } else {
+                use std::io::Read;
+                // Encode before creating the file so it's not truncated if the text can't be encoded.
+                let mut s = String::new();
+                reader.read_to_string(&mut s)?;
+                tokio::fs::write(path, encode(&s, encoding)?).await?;
+            }
 
             tracing::info!("buffer written to disk");
 
@@ -1970,6 +2795,53 @@ impl Editor {
         }
     }
 
+    /// Apply the buffer's `trim_trailing_whitespace` and `fix_end_of_line` settings.
+    /// All the changes are applied as a single undo step.
+    fn apply_save_policies(&mut self, buf: BufferId) -> Result<(), EditError> {
+        let settings = self[buf].settings();
+        let trim = *settings.trim_trailing_whitespace.read();
+        let fix_eol = *settings.fix_end_of_line.read();
+
+        let text = self[buf].text();
+        let len = text.len_bytes();
+        let mut missing_eol = fix_eol && !text.is_empty() && text.chars().next_back() != Some('\n');
+        let mut deltas = vec![];
+        if trim {
+            for (idx, line) in text.lines().enumerate() {
+                let trailing = line
+                    .chars()
+                    .rev()
+                    .take_while(|c| c.is_whitespace())
+                    .map(char::len_utf8)
+                    .sum::<usize>();
+                if trailing == 0 {
+                    continue;
+                }
+
+                let end = text.line_to_byte(idx) + line.len_bytes();
+                if end == len && missing_eol {
+                    // Deltas can't overlap, so replace the whitespace with the newline instead.
+                    deltas.push(Delta::new(end - trailing..end, "\n"));
+                    missing_eol = false;
+                } else {
+                    deltas.push(Delta::delete(end - trailing..end));
+                }
+            }
+        }
+
+        if missing_eol {
+            deltas.push(Delta::insert_at(len, "\n"));
+        }
+
+        if deltas.is_empty() {
+            return Ok(());
+        }
+
+        self.edit_flags(buf, &Deltas::new(deltas), EditFlags::NO_ENSURE_TRAILING_NEWLINE)?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        Ok(())
+    }
+
     pub fn close_view(&mut self, selector: impl Selector<ViewId>) {
         let view = selector.select(self);
         if self.tree.close_view(view).is_err() {
@@ -2023,6 +2895,34 @@ impl Editor {
         self.set_buffer(inspector_view, buf);
     }
 
+    /// Display the special buffer `buf` according to the placement rule configured for `kind`,
+    /// falling back to `default` if there is no such rule.
+    /// Returns the view the buffer is displayed in.
+    pub(crate) fn open_placed(
+        &mut self,
+        kind: BufferKind,
+        default: Placement,
+        buf: BufferId,
+    ) -> ViewId {
+        let placement = self.settings().placement_rules.read().get(kind).unwrap_or(default);
+        match placement {
+            Placement::Replace => {
+                self.set_buffer(Active, buf);
+                self.view(Active).id()
+            }
+            Placement::Dock { edge, size } => {
+                let view = self.split(Active, edge, size);
+                self.set_buffer(view, buf);
+                view
+            }
+            Placement::Float { .. } => {
+                let view = self.create_view(buf);
+                self.tree.push(Layer::new_with_area(view, move |area| placement.area(area)));
+                view
+            }
+        }
+    }
+
     /// Create a new view group with the given url.
     /// If a view group with the same url already exists, returns `Err(id)`
     pub fn create_view_group(&mut self, url: Url) -> Result<ViewGroupId, ViewGroupId> {
@@ -2070,6 +2970,11 @@ impl Editor {
         self.namespaces.insert_with_key(|id| Namespace::new(id, name))
     }
 
+    pub(crate) fn namespace_by_name(&self, name: impl Into<Ustr>) -> Option<NamespaceId> {
+        let name = name.into();
+        self.namespaces.values().find(|ns| ns.name() == name).map(|ns| ns.id())
+    }
+
     pub fn align_view(&mut self, selector: impl Selector<ViewId>, alignment: VerticalAlignment) {
         let view = selector.select(self);
         let (view, buf) = get!(self: view);
@@ -2157,6 +3062,12 @@ impl Editor {
         self.search_state.matches().iter()
     }
 
+    /// The pattern of the last search, the `/` register.
+    pub fn search_pattern(&self) -> Option<&str> {
+        let (_, query) = &self.search_state.last_update;
+        (!query.is_empty()).then_some(query.as_str())
+    }
+
     pub fn current_location(&self) -> Location {
         let (view, buf) = get_ref!(self);
         Location { buf: buf.id(), point: view.cursor() }
@@ -2192,6 +3103,43 @@ impl Editor {
         &self.settings
     }
 
+    #[inline]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        tracing::info!(?capabilities, "set terminal capabilities");
+        self.capabilities = capabilities;
+    }
+
+    #[inline]
+    pub fn startup_profile(&self) -> &StartupProfile {
+        &self.startup
+    }
+
+    /// Replace the startup profile, e.g. with one started before the editor was created.
+    pub fn set_startup_profile(&mut self, profile: StartupProfile) {
+        self.startup = profile;
+    }
+
+    /// Record that the phase `name` of startup has ended, see [`StartupProfile::record`].
+    pub fn record_startup(&mut self, name: impl Into<String>) {
+        self.startup.record(name);
+    }
+
+    /// Take the pending requests to the terminal, the frontend should call this after each render.
+    pub fn take_terminal_requests(&mut self) -> Vec<TerminalRequest> {
+        self.report_changes();
+        mem::take(&mut self.terminal_requests)
+    }
+
+    /// Ask the frontend to record every frame to an asciicast file at `path`, or to stop the
+    /// current recording if `None`.
+    pub fn record_terminal(&mut self, path: Option<PathBuf>) {
+        self.terminal_requests.push(TerminalRequest::Record(path));
+    }
+
     #[inline]
     pub fn language_config(&self) -> &language::Config {
         &self.language_config
@@ -2201,44 +3149,114 @@ impl Editor {
     pub fn language_config_mut(&mut self) -> &mut language::Config {
         &mut self.language_config
     }
+
+    /// Whether nested delimiters are colored by depth for the file type.
+    pub fn rainbow_delimiters(&self, ft: FileType) -> bool {
+        self.language_config.languages.get(&ft).is_some_and(|config| config.rainbow_delimiters)
+    }
+
+    /// Toggle coloring nested delimiters by depth for the file type.
+    pub fn toggle_rainbow_delimiters(&mut self, ft: FileType) {
+        let config = self.language_config.languages.entry(ft).or_default();
+        config.rainbow_delimiters = !config.rainbow_delimiters;
+        request_redraw();
+    }
+}
+
+/// The on-disk format of a text file.
+#[derive(Debug, Clone, Copy)]
+struct TextFormat {
+    encoding: &'static Encoding,
+    /// The detected line ending of the text (if it contains any line endings).
+    line_ending: Option<LineEnding>,
 }
 
-async fn rope_from_reader(reader: impl tokio::io::AsyncRead + Unpin) -> io::Result<Rope> {
+/// Read the text into a rope, decoding it to utf-8 and normalizing line endings to `\n`.
+/// If `encoding` is `None`, the encoding is detected from the byte order mark (defaulting to utf-8)
+/// and an `InvalidData` error is returned if the text is malformed.
+/// Otherwise, the text is decoded with the given encoding with malformed sequences replaced.
+async fn rope_from_reader(
+    reader: impl tokio::io::AsyncRead + Unpin,
+    encoding: Option<&'static Encoding>,
+) -> io::Result<(Rope, TextFormat)> {
     let mut reader = tokio::io::BufReader::new(reader);
     let mut builder = RopeBuilder::new();
+    let mut normalizer = LineEndingNormalizer::default();
 
-    // Handle utf-8 byte order mark.
-    // Not supporting other encodings for now.
     let buf = reader.fill_buf().await?;
-    if let [0xEF, 0xBB, 0xBF, ..] = buf {
-        // Skip the BOM before decoding. This means we won't preserve it on save but we don't care.
-        reader.consume(3)
+    // The BOM is skipped by the decoder. This means we won't preserve it on save for utf-8 but we don't care.
+    let (encoding, strict) = match encoding {
+        Some(encoding) => (encoding, false),
+        None => (Encoding::for_bom(buf).map_or(encoding_rs::UTF_8, |(encoding, _)| encoding), true),
     };
+    let mut decoder = encoding.new_decoder_with_bom_removal();
 
+    let mut s = String::new();
     loop {
         let buf = reader.fill_buf().await?;
-        if buf.is_empty() {
-            break;
-        }
-
-        let s = match std::str::from_utf8(buf) {
-            Ok(s) => s,
-            Err(err) => {
-                let n = err.valid_up_to();
-                if n == 0 {
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        let last = buf.is_empty();
+
+        s.clear();
+        let read = if strict {
+            let len = decoder.max_utf8_buffer_length_without_replacement(buf.len());
+            s.reserve(len.expect("buffer length overflow"));
+            match decoder.decode_to_string_without_replacement(buf, &mut s, last) {
+                (encoding_rs::DecoderResult::Malformed(..), _) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed {} data", encoding.name()),
+                    ));
+                }
+                (result, read) => {
+                    debug_assert_eq!(result, encoding_rs::DecoderResult::InputEmpty);
+                    read
                 }
-                unsafe { std::str::from_utf8_unchecked(&buf[..n]) }
             }
+        } else {
+            let len = decoder.max_utf8_buffer_length(buf.len());
+            s.reserve(len.expect("buffer length overflow"));
+            let (result, read, _) = decoder.decode_to_string(buf, &mut s, last);
+            debug_assert_eq!(result, encoding_rs::CoderResult::InputEmpty);
+            read
         };
 
-        builder.append(s);
+        normalizer.normalize(&s, |s| {
+            builder.append(s);
+        });
+
+        reader.consume(read);
+
+        if last {
+            break;
+        }
+    }
+
+    Ok((builder.build(), TextFormat { encoding, line_ending: normalizer.finish() }))
+}
+
+/// Encode the utf-8 `text` with the given encoding.
+fn encode(text: &str, encoding: &'static Encoding) -> io::Result<Vec<u8>> {
+    // encoding_rs does not support encoding to utf-16 as it is not required by the web.
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        let le = encoding == encoding_rs::UTF_16LE;
+        // Utf-16 files are only detected via their BOM, so write it back.
+        let mut bytes = Vec::with_capacity(2 + text.len() * 2);
+        bytes.extend_from_slice(if le { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&if le { unit.to_le_bytes() } else { unit.to_be_bytes() });
+        }
+        return Ok(bytes);
+    }
 
-        let n = s.len();
-        reader.consume(n);
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("text contains characters that cannot be encoded as {}", encoding.name()),
+        ));
     }
 
-    Ok(builder.build())
+    Ok(bytes.into_owned())
 }
 
 pub trait Selector<T> {
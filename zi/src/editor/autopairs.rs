@@ -0,0 +1,54 @@
+use super::*;
+
+/// The pairs of characters completed by autopairs, see [`Settings::autopairs`](crate::buffer::Settings::autopairs).
+const PAIRS: [(char, char); 6] =
+    [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\''), ('`', '`')];
+
+/// The character that closes the pair opened by `c`.
+pub(super) fn closing(c: char) -> Option<char> {
+    PAIRS.iter().find(|&&(open, _)| open == c).map(|&(_, close)| close)
+}
+
+fn is_closing(c: char) -> bool {
+    PAIRS.iter().any(|&(_, close)| close == c)
+}
+
+impl Editor {
+    /// Insert a typed character, if the buffer has autopairs enabled an opening character also
+    /// inserts its closing character and a closing character types over an existing one.
+    pub(super) fn insert_char_autopair(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        c: char,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        if !*self[buf].settings().autopairs.read() {
+            return self.insert_char(view, c);
+        }
+
+        let text = self[buf].text();
+        let byte = text.point_to_byte(self[view].cursor());
+        let next = text.byte_slice(byte..).chars().next();
+        let prev = text.byte_slice(..byte).chars().next_back();
+
+        if next == Some(c) && is_closing(c) {
+            self.motion(view, motion::NextChar)?;
+            return Ok(());
+        }
+
+        let Some(close) = closing(c) else { return self.insert_char(view, c) };
+        // Only complete the pair before whitespace or a closing character, and don't pair quotes
+        // after a word as they are more likely to be apostrophes.
+        let pair = next.is_none_or(|next| next.is_whitespace() || is_closing(next))
+            && (close != c || !prev.is_some_and(|prev| prev.is_alphanumeric() || prev == c));
+
+        self.insert_char(view, c)?;
+        if pair {
+            let cursor = self[view].cursor();
+            let byte = self[buf].text().point_to_byte(cursor);
+            self.edit(buf, &Deltas::insert_at(byte, close.to_string()))?;
+            self.set_cursor(view, cursor);
+        }
+        Ok(())
+    }
+}
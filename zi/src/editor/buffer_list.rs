@@ -0,0 +1,148 @@
+use super::*;
+use crate::lstypes;
+
+impl Editor {
+    /// The buffers opened by the user in the order they were opened.
+    /// These are the buffers shown by `:ls` and the buffer picker.
+    pub fn listed_buffers(&self) -> impl ExactSizeIterator<Item = BufferId> + '_ {
+        self.listed_buffers.iter().copied()
+    }
+
+    pub(super) fn list_buffer(&mut self, buf: BufferId) {
+        if !self.listed_buffers.contains(&buf) {
+            self.listed_buffers.push(buf);
+            self.buffer_numbers.insert(buf, self.next_buffer_number);
+            self.next_buffer_number += 1;
+        }
+    }
+
+    /// The number used to refer to a listed buffer in `:ls` and `:b`.
+    /// Numbers are assigned in the order buffers are listed and are never reused,
+    /// so a buffer keeps its number until it is deleted.
+    pub fn buffer_number(&self, selector: impl Selector<BufferId>) -> Option<u32> {
+        self.buffer_numbers.get(&selector.select(self)).copied()
+    }
+
+    /// The `:ls` listing of the listed buffers.
+    /// `%` marks the active buffer and `+` marks buffers with unsaved changes.
+    pub fn buffer_list(&self) -> String {
+        use std::fmt::Write;
+
+        let active = self.buffer(Active).id();
+        let mut s = String::new();
+        for buf in self.listed_buffers() {
+            let buffer = &self[buf];
+            let _ = writeln!(
+                s,
+                "{:>3} {}{} {}",
+                self.buffer_numbers[&buf],
+                if buf == active { '%' } else { ' ' },
+                if buffer.flags().contains(BufferFlags::DIRTY) { '+' } else { ' ' },
+                buffer.file_path().unwrap_or_default().display(),
+            );
+        }
+        s
+    }
+
+    pub fn open_buffer_list(&mut self) {
+        let list = self.buffer_list();
+        let buf = self.create_readonly_buffer("buffers", list.into_bytes());
+        self.set_buffer(Active, buf);
+    }
+
+    pub fn open_buffer_picker(&mut self) -> ViewGroupId {
+        #[derive(Clone, Debug)]
+        struct BufferEntry {
+            buf: BufferId,
+            number: u32,
+            path: PathBuf,
+            dirty: bool,
+        }
+
+        impl fmt::Display for BufferEntry {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let dirty = if self.dirty { '+' } else { ' ' };
+                write!(f, "{} {dirty} {}", self.number, self.path.display())
+            }
+        }
+
+        impl BufferPickerEntry for BufferEntry {
+            #[inline]
+            fn buffer_or_path(&self) -> Result<BufferId, &Path> {
+                Ok(self.buf)
+            }
+
+            #[inline]
+            fn point(&self) -> Option<lstypes::EncodedPoint> {
+                None
+            }
+        }
+
+        let split_ratio = *self.settings().generic_picker_split_ratio.read();
+        self.open_static_picker::<BufferPicker<BufferEntry>>(
+            Url::parse("view-group://buffers").unwrap(),
+            "buffers",
+            split_ratio,
+            |editor, injector| {
+                // Most recently opened first
+                for buf in editor.listed_buffers().rev() {
+                    let buffer = &editor[buf];
+                    if let Err(()) = injector.push(BufferEntry {
+                        buf,
+                        number: editor.buffer_numbers[&buf],
+                        path: buffer.file_path().unwrap_or_default(),
+                        dirty: buffer.flags().contains(BufferFlags::DIRTY),
+                    }) {
+                        break;
+                    }
+                }
+            },
+        )
+    }
+
+    /// Find a listed buffer by its number or by a unique substring of its path.
+    pub fn find_buffer(&self, query: &str) -> Result<BufferId> {
+        if let Ok(n) = query.parse::<u32>() {
+            return self
+                .listed_buffers()
+                .find(|&buf| self.buffer_numbers[&buf] == n)
+                .ok_or_else(|| anyhow!("buffer {n} does not exist"));
+        }
+
+        let mut matches = self.listed_buffers().filter(|&buf| {
+            self[buf].file_path().is_some_and(|path| path.to_string_lossy().contains(query))
+        });
+
+        match (matches.next(), matches.next()) {
+            (Some(buf), None) => Ok(buf),
+            (None, _) => bail!("no matching buffer for `{query}`"),
+            (Some(_), Some(_)) => bail!("more than one match for `{query}`"),
+        }
+    }
+
+    /// Delete the buffer from the buffer list, replacing it in any views that are showing it.
+    /// Refuses to delete a buffer with unsaved changes unless `force` is set,
+    /// and refuses to delete the last listed buffer if it is visible.
+    pub fn delete_buffer(&mut self, selector: impl Selector<BufferId>, force: bool) -> Result<()> {
+        let buf = selector.select(self);
+        if self[buf].flags().contains(BufferFlags::DIRTY) && !force {
+            bail!("buffer has unsaved changes (add ! to override)");
+        }
+
+        let views = self.views_into_buf(buf).collect::<Vec<_>>();
+        if !views.is_empty() {
+            let Some(&replacement) = self.listed_buffers.iter().rev().find(|&&b| b != buf) else {
+                bail!("cannot delete the last buffer");
+            };
+
+            for view in views {
+                self.set_buffer(view, replacement);
+            }
+        }
+
+        self.listed_buffers.retain(|&b| b != buf);
+        self.buffer_numbers.remove(&buf);
+        self.close_buffer(buf);
+        Ok(())
+    }
+}
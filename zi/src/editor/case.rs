@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+use super::*;
+
+/// How the case operators change the case of characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Case {
+    Lower,
+    Upper,
+    Toggle,
+}
+
+impl Case {
+    pub(super) fn of(operator: Operator) -> Option<Self> {
+        match operator {
+            Operator::Lowercase => Some(Case::Lower),
+            Operator::Uppercase => Some(Case::Upper),
+            Operator::ToggleCase => Some(Case::Toggle),
+            _ => None,
+        }
+    }
+
+    /// The character in this case, this may be more than one character (e.g. `ß` is `SS`).
+    fn convert(self, c: char) -> String {
+        match self {
+            Case::Lower => c.to_lowercase().collect(),
+            Case::Upper => c.to_uppercase().collect(),
+            Case::Toggle if c.is_lowercase() => c.to_uppercase().collect(),
+            Case::Toggle if c.is_uppercase() => c.to_lowercase().collect(),
+            Case::Toggle => c.to_string(),
+        }
+    }
+}
+
+/// The deltas to change the case of the text in `range`, one per character that changes.
+pub(super) fn case_deltas(
+    text: &dyn AnyText,
+    range: Range<usize>,
+    case: Case,
+) -> Vec<Delta<'static>> {
+    let mut byte = range.start;
+    let mut deltas = vec![];
+    for c in text.byte_slice(range).chars() {
+        let converted = case.convert(c);
+        if converted.chars().ne([c]) {
+            deltas.push(Delta::new(byte..byte + c.len_utf8(), converted));
+        }
+        byte += c.len_utf8();
+    }
+    deltas
+}
+
+impl Editor {
+    /// Toggle the case of `n` characters from the cursor, up to the end of the line, and move the
+    /// cursor past them, `~`.
+    pub fn toggle_case(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        n: usize,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let text = self[buf].text();
+        let cursor = self[view].cursor();
+        let start = text.point_to_byte(cursor);
+        let line_end = text.line_to_byte(cursor.line())
+            + text.line(cursor.line()).map_or(0, |line| line.len_bytes());
+        let chars = text.byte_slice(start..line_end).chars().take(n).collect::<Vec<_>>();
+        if chars.is_empty() {
+            return Ok(());
+        }
+
+        let end = start + chars.iter().map(|c| c.len_utf8()).sum::<usize>();
+        // The converted characters may have a different length.
+        let new_end = start + chars.iter().map(|&c| Case::Toggle.convert(c).len()).sum::<usize>();
+        let deltas = Deltas::new(case_deltas(text, start..end, Case::Toggle));
+        self.edit(buf, &deltas)?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, new_end);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert() {
+        let convert = |s: &str, case: Case| s.chars().map(|c| case.convert(c)).collect::<String>();
+        assert_eq!(convert("Hello, World", Case::Lower), "hello, world");
+        assert_eq!(convert("Hello, World", Case::Upper), "HELLO, WORLD");
+        assert_eq!(convert("Hello, World", Case::Toggle), "hELLO, wORLD");
+        assert_eq!(convert("straße", Case::Upper), "STRASSE");
+        assert_eq!(convert("ΣΑΣ", Case::Toggle), "σασ");
+    }
+}
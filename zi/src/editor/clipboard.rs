@@ -0,0 +1,97 @@
+use super::*;
+use crate::ClipboardSelection;
+
+impl Editor {
+    /// Select the register used by the next yank, delete or put, `"`. Only the unnamed register
+    /// and the clipboard registers (`+` and `*`) are supported.
+    pub fn select_register(&mut self, name: char) -> Result<()> {
+        if name != Registers::UNNAMED && ClipboardSelection::from_register(name).is_none() {
+            bail!("invalid register: `{name}`");
+        }
+        self.selected_register = Some(name);
+        Ok(())
+    }
+
+    pub(crate) fn take_register(&mut self) -> Option<char> {
+        self.selected_register.take()
+    }
+
+    /// Store yanked or deleted text in the unnamed register and the selected register, if any.
+    /// The clipboard registers also set the system clipboard, as does the unnamed register if it
+    /// is synced with a clipboard by the `clipboard` setting.
+    pub(crate) fn set_register(
+        &mut self,
+        kind: impl Into<RegisterKind>,
+        content: impl Into<String>,
+    ) {
+        let (kind, content) = (kind.into(), content.into());
+        let name = self.take_register().unwrap_or(Registers::UNNAMED);
+        let synced = *self.settings.clipboard.read();
+        if let Some(selection) = ClipboardSelection::from_register(name) {
+            if let Err(err) = self.set_clipboard(selection, &content) {
+                set_error!(self, err);
+            }
+            self.registers.get_or_insert(name).set(kind, content.clone());
+        } else if let Some(selection) = synced {
+            // Don't complain about a missing clipboard on every yank if it was only implied.
+            if let Err(err) = self.set_clipboard(selection, &content) {
+                tracing::debug!(%err, "failed to sync the unnamed register with the clipboard");
+            }
+        }
+
+        self.registers.get_or_insert(Registers::UNNAMED).set(kind, content);
+    }
+
+    /// The contents of the register `name`. The clipboard registers, and the unnamed register if
+    /// it is synced with a clipboard, are read from the system clipboard when possible. Text that
+    /// was yanked in the editor keeps its kind, other text is linewise if it ends with a newline.
+    pub(crate) fn read_register(&mut self, name: char) -> Option<Register> {
+        let stored = self.registers.get(name).cloned();
+        let selection = match ClipboardSelection::from_register(name) {
+            Some(selection) => Some(selection),
+            None if name == Registers::UNNAMED => *self.settings.clipboard.read(),
+            None => None,
+        };
+        let Some(selection) = selection else { return stored };
+
+        let content = match self.clipboard.get(selection) {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::debug!(%err, ?selection, "failed to read the clipboard");
+                return stored;
+            }
+        };
+
+        match stored {
+            Some(reg) if reg.content == content => Some(reg),
+            _ => {
+                let kind = if content.ends_with('\n') {
+                    RegisterKind::Linewise
+                } else {
+                    RegisterKind::Charwise
+                };
+                Some(Register { kind, content })
+            }
+        }
+    }
+
+    /// Set the system clipboard, falling back to the terminal's clipboard if there is no provider.
+    fn set_clipboard(&mut self, selection: ClipboardSelection, text: &str) -> Result<()> {
+        match self.clipboard.set(selection, text) {
+            Ok(()) => Ok(()),
+            Err(_) if self.capabilities.osc52 => {
+                self.terminal_requests.push(TerminalRequest::SetClipboard(selection, text.into()));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The name of the clipboard provider, for `:checkhealth`.
+    pub(crate) fn clipboard_provider(&self) -> String {
+        match &self.clipboard {
+            crate::clipboard::Provider::None if self.capabilities.osc52 => "osc 52".into(),
+            provider => provider.to_string(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use super::*;
+use crate::{hashmap, trie};
+
+/// The maximum number of commands to remember.
+const MAX_COMMAND_HISTORY: usize = 100;
+
+/// The height of the command-line window.
+const COMMAND_WINDOW_HEIGHT: u16 = 7;
+
+/// Commands entered in command mode, oldest first.
+#[derive(Debug, Default)]
+pub(super) struct CommandHistory {
+    commands: VecDeque<String>,
+}
+
+impl CommandHistory {
+    fn record(&mut self, cmd: &str) {
+        self.commands.retain(|c| c != cmd);
+        self.commands.push_back(cmd.to_string());
+        if self.commands.len() > MAX_COMMAND_HISTORY {
+            self.commands.pop_front();
+        }
+    }
+}
+
+impl Editor {
+    /// Commands entered in command mode, oldest first. Repeated commands are only remembered once.
+    pub fn command_history(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.command_history.commands.iter().map(|cmd| cmd.as_str())
+    }
+
+    pub(super) fn record_command(&mut self, cmd: &str) {
+        let cmd = cmd.trim();
+        if !cmd.is_empty() {
+            self.command_history.record(cmd);
+        }
+    }
+
+    /// Open the command history in a split below the active view, `q:`.
+    /// The history can be edited like any other text and `<CR>` executes the command on the cursor
+    /// line after closing the window.
+    pub fn open_command_window(&mut self) -> ViewId {
+        let keymap = {
+            let execute: Action = |editor| {
+                set_error_if!(editor: editor.execute_command_window_line());
+            };
+            Keymap::from(hashmap! {
+                Mode::Normal => trie!({ "<CR>" => execute }),
+                Mode::Insert => trie!({ "<CR>" => execute }),
+            })
+        };
+
+        let text = self.command_history().map(|cmd| format!("{cmd}\n")).collect::<String>();
+        let buf = {
+            let theme = self.theme();
+            let theme = theme.read();
+            self.buffers.insert_with_key(|id| {
+                Buffer::new(
+                    TextBuffer::new(
+                        id,
+                        BufferFlags::empty(),
+                        filetype!(text),
+                        "command-line",
+                        Rope::from(text.as_str()),
+                        &theme,
+                        None,
+                    )
+                    .with_keymap(keymap),
+                )
+            })
+        };
+
+        let view = self.split(Active, Direction::Down, tui::Constraint::Max(COMMAND_WINDOW_HEIGHT));
+        self.set_buffer(view, buf);
+        // The most recent command.
+        let line = self.command_history.commands.len().saturating_sub(1);
+        self.set_cursor(view, Point::new(line, 0));
+        view
+    }
+
+    fn execute_command_window_line(&mut self) -> Result<()> {
+        let (view, buf) = get_ref!(self);
+        let line = buf.text().line(view.cursor().line()).map(|line| line.to_cow().into_owned());
+        let view = view.id();
+
+        self.set_mode(Mode::Normal);
+        self.close_view(view);
+
+        let Some(cmd) = line.filter(|cmd| !cmd.trim().is_empty()) else { return Ok(()) };
+        self.record_command(&cmd);
+        self.execute(cmd.as_str())
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Range;
+
+use super::*;
+use crate::CommentTokens;
+use crate::view::SetCursorFlags;
+
+impl Editor {
+    /// The comment strings of the file type, either configured in its
+    /// [`LanguageConfig`](crate::LanguageConfig) or builtin.
+    pub fn comment_tokens(&self, ft: FileType) -> Option<CommentTokens> {
+        self.language_config
+            .languages
+            .get(&ft)
+            .and_then(|config| config.comment_tokens.clone())
+            .or_else(|| CommentTokens::for_file_type(ft))
+    }
+
+    /// Toggle the comments of `lines`, `gc`. If every non-blank line is commented the comments are
+    /// removed, otherwise all the lines are commented at the smallest indentation among them.
+    /// Languages without line comments wrap the lines in a block comment instead.
+    pub fn toggle_comment(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        lines: Range<usize>,
+    ) -> Result<()> {
+        let (view, buf) = self.get(selector);
+        let ft = self[buf].file_type();
+        let Some(tokens) = self.comment_tokens(ft) else {
+            bail!("no comment string for file type `{ft}`")
+        };
+
+        let text = self[buf].text();
+        let lines = lines
+            .filter_map(|line| {
+                let content = text.line(line)?.to_cow();
+                let content = content.trim_end_matches(['\n', '\r']);
+                Some((text.line_to_byte(line), content.to_string()))
+            })
+            // Blank lines are neither commented nor uncommented.
+            .filter(|(_, content)| !content.trim().is_empty())
+            .collect::<Vec<_>>();
+        let Some(first) = lines.first().map(|(start, _)| *start) else { return Ok(()) };
+
+        let deltas = match (&tokens.line, &tokens.block) {
+            (Some(token), _) => line_comment(&lines, token),
+            (None, Some((open, close))) => block_comment(&lines, open, close),
+            (None, None) => bail!("no comment string for file type `{ft}`"),
+        };
+
+        self.edit(buf, &deltas)?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        let line = self[buf].text().byte_to_line(first);
+        self.set_cursor_flags(view, Point::new(line, 0), SetCursorFlags::START_OF_LINE);
+        Ok(())
+    }
+}
+
+/// The deltas to toggle the line comments of `lines`, the start byte and content of each
+/// non-blank line.
+fn line_comment<'a>(lines: &[(usize, String)], token: &str) -> Deltas<'a> {
+    let token = token.trim_end();
+    if lines.iter().all(|(_, content)| content.trim_start().starts_with(token)) {
+        return Deltas::new(lines.iter().map(|(start, content)| {
+            let indent = content.len() - content.trim_start().len();
+            let rest = &content[indent + token.len()..];
+            let len = token.len() + usize::from(rest.starts_with(' '));
+            Delta::delete(start + indent..start + indent + len)
+        }));
+    }
+
+    let indent = lines
+        .iter()
+        .map(|(_, content)| content.len() - content.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    Deltas::new(
+        lines.iter().map(|(start, _)| Delta::insert_at(start + indent, format!("{token} "))),
+    )
+}
+
+/// The deltas to toggle a block comment around `lines`.
+fn block_comment<'a>(lines: &[(usize, String)], open: &str, close: &str) -> Deltas<'a> {
+    let ((first_start, first), (last_start, last)) = match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Deltas::empty(),
+    };
+    let indent = first.len() - first.trim_start().len();
+    let end = last.trim_end().len();
+
+    let commented = first.trim_start().starts_with(open)
+        && last.trim_end().ends_with(close)
+        && (lines.len() > 1 || first.trim().len() >= open.len() + close.len());
+    if commented {
+        let open_end =
+            indent + open.len() + usize::from(first[indent + open.len()..].starts_with(' '));
+        let mut close_start = end - close.len();
+        if last[..close_start].ends_with(' ') {
+            close_start -= 1;
+        }
+        if lines.len() == 1 {
+            // Don't let the deletions overlap in an empty comment.
+            close_start = close_start.max(open_end);
+        }
+        return Deltas::new([
+            Delta::delete(first_start + indent..first_start + open_end),
+            Delta::delete(last_start + close_start..last_start + end),
+        ]);
+    }
+
+    Deltas::new([
+        Delta::insert_at(first_start + indent, format!("{open} ")),
+        Delta::insert_at(last_start + end, format!(" {close}")),
+    ])
+}
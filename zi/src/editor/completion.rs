@@ -1,31 +1,48 @@
-use std::any::TypeId;
+use std::cmp::Reverse;
 use std::future::Future;
-use std::sync::{Arc, OnceLock};
+use std::sync::Arc;
 
 use futures_core::future::BoxFuture;
 use futures_util::{StreamExt, TryFutureExt, TryStreamExt, stream};
-use parking_lot::RwLock;
-use rustc_hash::FxHashMap;
-use zi_core::CompletionItem;
+use rustc_hash::FxHashSet;
+use zi_core::{BufferId, CompletionItem};
 use zi_text::{Delta, Deltas};
 
-use super::{Selector, State, active_servers_of};
-use crate::completion::{Completion, CompletionProvider};
+use super::{Selector, State, active_servers_of, set_error_if};
+use crate::completion::{Candidate, Completion, CompletionProvider};
 use crate::{Active, Editor, LanguageServiceId, Result, ViewId, lstypes};
 
-static COMPLETION_PROVIDERS: OnceLock<RwLock<FxHashMap<TypeId, Arc<dyn CompletionProvider>>>> =
-    OnceLock::new();
-
 impl Editor {
+    /// Add a source of completions, replacing the provider of the same type if there is one.
+    /// The words of the buffer and file paths are completed by default, see
+    /// [`BufferWordProvider`](crate::BufferWordProvider) and [`PathProvider`](crate::PathProvider).
     pub fn register_completion_provider<P: CompletionProvider + 'static>(&mut self, provider: P) {
-        COMPLETION_PROVIDERS
-            .get_or_init(Default::default)
-            .write()
-            .insert(TypeId::of::<P>(), Arc::new(provider));
+        self.completion_providers.register(provider);
     }
 
     pub fn unregister_completion_provider<P: CompletionProvider + 'static>(&mut self) {
-        COMPLETION_PROVIDERS.get_or_init(Default::default).write().remove(&TypeId::of::<P>());
+        self.completion_providers.unregister::<P>();
+    }
+
+    /// The completion providers of the buffer, its language servers and the registered providers.
+    fn completion_providers_of(&self, buf: BufferId) -> Vec<Arc<dyn CompletionProvider>> {
+        active_servers_of!(self, buf)
+            .filter_map(|&server| {
+                let service = &self.active_language_services[&server];
+                service.completion_capabilities()?;
+                let trigger_characters = service.completion_trigger_characters();
+                let provider = LspCompletionProvider { server, trigger_characters };
+                Some(Arc::new(provider) as Arc<dyn CompletionProvider>)
+            })
+            .chain(self.completion_providers.iter().cloned())
+            .collect()
+    }
+
+    /// Whether typing `c` requests completions from a provider of the active buffer.
+    pub(super) fn is_completion_trigger_character(&self, c: char) -> bool {
+        self.completion_providers_of(self.buffer(Active).id())
+            .iter()
+            .any(|provider| provider.trigger_characters().contains(&c))
     }
 
     pub fn completions(&self) -> Option<impl ExactSizeIterator<Item = &CompletionItem>> {
@@ -36,16 +53,99 @@ impl Editor {
     }
 
     pub fn trigger_completion(&mut self, trigger: Option<char>) {
-        let fut = self.request_completions(Active);
+        // Characters that are part of words trigger completion from every provider.
+        let trigger_character = trigger.filter(|&c| !c.is_ascii_alphanumeric() && c != '_');
+        let fut = self.request_completion_candidates(Active, trigger_character);
         let at = self.cursor_byte(Active);
 
         let State::Insert(state) = &mut self.state else { return };
         state.completion.activate(at, trigger);
 
-        self.callback("completions", fut.map_err(Into::into), move |editor, items| {
+        self.callback("completions", fut, move |editor, candidates| {
             let State::Insert(state) = &mut editor.state else { return Ok(()) };
             if let Completion::Active(state) = &mut state.completion {
-                state.set_items(items);
+                state.set_items(candidates);
+            }
+
+            Ok(())
+        });
+    }
+
+    /// The selected completion item, if any.
+    pub fn selected_completion(&self) -> Option<&CompletionItem> {
+        match &self.state {
+            State::Insert(state) => match &state.completion {
+                Completion::Active(state) => state.selected(),
+                Completion::Inactive => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Select the next completion item, or trigger completion if it's not active, `<C-n>`.
+    pub fn complete_next(&mut self) {
+        let State::Insert(state) = &mut self.state else { return };
+        match &mut state.completion {
+            Completion::Active(state) => {
+                if let Some(delta) = state.select_next() {
+                    self.apply_completion_delta(delta);
+                }
+                self.resolve_selected_completion();
+            }
+            Completion::Inactive => self.trigger_completion(None),
+        }
+    }
+
+    /// Select the previous completion item, or trigger completion if it's not active, `<C-p>`.
+    pub fn complete_prev(&mut self) {
+        let State::Insert(state) = &mut self.state else { return };
+        match &mut state.completion {
+            Completion::Active(state) => {
+                if let Some(delta) = state.select_prev() {
+                    self.apply_completion_delta(delta);
+                }
+                self.resolve_selected_completion();
+            }
+            Completion::Inactive => self.trigger_completion(None),
+        }
+    }
+
+    /// Accept the selected completion item, or the best match if none is selected, and close the
+    /// completion menu. Returns whether there was an item to accept.
+    pub fn confirm_completion(&mut self) -> bool {
+        let State::Insert(state) = &mut self.state else { return false };
+        let Completion::Active(completion) = &mut state.completion else { return false };
+        if completion.matches().len() == 0 {
+            return false;
+        }
+
+        let delta = completion.confirm();
+        let start = completion.start_byte();
+        let item = completion.selected().cloned();
+        state.completion.deactivate();
+        if let Some(delta) = delta {
+            self.apply_completion_delta(delta);
+        }
+
+        if let Some(item) = item.filter(|item| item.snippet) {
+            let snippet = item.insert_text.as_deref().unwrap_or(&item.label);
+            let view = self.tree().active();
+            set_error_if!(self: self.expand_snippet(view, start..start + item.label.len(), snippet));
+        }
+        true
+    }
+
+    /// Request the missing details of the selected completion item, such as its documentation.
+    fn resolve_selected_completion(&mut self) {
+        let State::Insert(state) = &mut self.state else { return };
+        let Completion::Active(state) = &mut state.completion else { return };
+        let Some((idx, item, provider)) = state.take_unresolved() else { return };
+
+        let fut = provider.resolve(self, item);
+        self.callback("resolve completion", fut, move |editor, item| {
+            let State::Insert(state) = &mut editor.state else { return Ok(()) };
+            if let Completion::Active(state) = &mut state.completion {
+                state.set_resolved(idx, item);
             }
 
             Ok(())
@@ -64,39 +164,25 @@ impl Editor {
         &mut self,
         view: impl Selector<ViewId>,
     ) -> impl Future<Output = Result<Vec<CompletionItem>>> {
-        enum Provider {
-            Lsp(LspCompletionProvider),
-            Provider(Arc<dyn CompletionProvider>),
-        }
-
-        impl CompletionProvider for Provider {
-            fn completions(
-                &self,
-                editor: &mut Editor,
-                params: lstypes::CompletionParams,
-            ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>> {
-                match self {
-                    Provider::Lsp(provider) => provider.completions(editor, params),
-                    Provider::Provider(provider) => provider.completions(editor, params),
-                }
-            }
-        }
+        self.request_completion_candidates(view, None)
+            .map_ok(|candidates| candidates.into_iter().map(Candidate::into_item).collect())
+    }
 
+    /// Request completions from the providers of the buffer of the view, or only from those with
+    /// the trigger character if there is one. Items with the same label are deduplicated.
+    fn request_completion_candidates(
+        &mut self,
+        view: impl Selector<ViewId>,
+        trigger_character: Option<char>,
+    ) -> impl Future<Output = Result<Vec<Candidate>>> {
         let view = view.select(self);
         let buf = self[view].buffer();
-        let providers = active_servers_of!(self, buf)
-            .filter_map(|&server| {
-                self.active_language_services[&server]
-                    .completion_capabilities()
-                    .map(|_| Provider::Lsp(LspCompletionProvider { server }))
+        let providers = self
+            .completion_providers_of(buf)
+            .into_iter()
+            .filter(|provider| {
+                trigger_character.is_none_or(|c| provider.trigger_characters().contains(&c))
             })
-            .chain(
-                COMPLETION_PROVIDERS
-                    .get_or_init(Default::default)
-                    .read()
-                    .values()
-                    .map(|provider| Provider::Provider(Arc::clone(provider))),
-            )
             .collect::<Vec<_>>();
 
         let point = self[view].cursor();
@@ -107,27 +193,47 @@ impl Editor {
                 let params = lstypes::CompletionParams {
                     at: lstypes::TextDocumentPointParams { url, point },
                 };
-                Some(provider.completions(self, params))
+                let fut = provider.completions(self, params);
+                Some(fut.map_ok(move |res| (provider, res)))
             })
             .collect::<Vec<_>>();
 
         async move {
-            stream::iter(futs)
+            let mut candidates = stream::iter(futs)
                 .buffered(16)
-                .try_fold(vec![], |mut acc, res| async move {
-                    acc.extend(res.items);
+                .try_fold(vec![], |mut acc, (provider, res)| async move {
+                    acc.extend(
+                        res.items
+                            .into_iter()
+                            .map(|item| Candidate::new(item, Arc::clone(&provider))),
+                    );
                     Ok(acc)
                 })
-                .await
+                .await?;
+
+            // Keep the item of the provider with the highest priority if a label is duplicated.
+            candidates.sort_by_key(|candidate| Reverse(candidate.priority()));
+            let mut seen = FxHashSet::default();
+            candidates.retain(|candidate| seen.insert(candidate.item().label.clone()));
+            Ok(candidates)
         }
     }
 }
 
 struct LspCompletionProvider {
     server: LanguageServiceId,
+    trigger_characters: Vec<char>,
 }
 
 impl CompletionProvider for LspCompletionProvider {
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    fn trigger_characters(&self) -> &[char] {
+        &self.trigger_characters
+    }
+
     fn completions(
         &self,
         editor: &mut Editor,
@@ -135,4 +241,16 @@ impl CompletionProvider for LspCompletionProvider {
     ) -> BoxFuture<'static, Result<lstypes::CompletionResponse>> {
         editor.active_language_services.get_mut(&self.server).unwrap().completion(params)
     }
+
+    fn resolve(
+        &self,
+        editor: &mut Editor,
+        item: CompletionItem,
+    ) -> BoxFuture<'static, Result<CompletionItem>> {
+        let service = editor.active_language_services.get_mut(&self.server).unwrap();
+        match service.completion_resolve_capabilities() {
+            Some(()) => service.resolve_completion_item(item),
+            None => Box::pin(async move { Ok(item) }),
+        }
+    }
 }
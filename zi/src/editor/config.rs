@@ -1,4 +1,12 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use zi_input::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{GuiCursor, StatusLine};
+use crate::ClipboardSelection;
 use crate::config::Setting;
+use crate::layout::PlacementRules;
 use crate::syntax::Theme;
 
 /// Global editor configuration shared between all views/buffers
@@ -8,6 +16,36 @@ pub struct Settings {
     pub diagnostics_picker_split_ratio: Setting<(u16, u16)>,
     pub global_search_split_ratio: Setting<(u16, u16)>,
     pub theme: Setting<Theme>,
+    /// Where special buffers (pickers, the explorer, etc.) are displayed when opened.
+    pub placement_rules: Setting<PlacementRules>,
+    /// The system clipboard the unnamed register is synced with, if any.
+    pub clipboard: Setting<Option<ClipboardSelection>>,
+    /// How long to wait for the next key of a mapping that is also the prefix of a longer one.
+    pub timeout_len: Setting<Duration>,
+    /// How long to wait before showing the continuations of a partially entered key sequence.
+    pub key_hints_delay: Setting<Duration>,
+    /// The key `<leader>` is replaced by in user mappings.
+    pub leader: Setting<KeyEvent>,
+    /// The layout of the status line.
+    pub statusline: Setting<StatusLine>,
+    /// The keys that accept the selected completion item in insert mode.
+    pub completion_confirm_keys: Setting<Vec<KeyEvent>>,
+    /// The most messages kept in the message log, older messages are dropped.
+    pub message_history: Setting<usize>,
+    /// The directory unsaved changes are written to for crash recovery, disabled if unset.
+    pub swap_dir: Setting<Option<PathBuf>>,
+    /// How long a buffer must stop changing for before its swap file is written.
+    pub update_time: Setting<Duration>,
+    /// Show how much rendering work is done in the top right corner, see `Editor::render_stats`.
+    pub render_stats: Setting<bool>,
+    /// The least time between renders, renders are skipped until it has passed.
+    pub redraw_interval: Setting<Duration>,
+    /// The shape of the cursor and whether it blinks in each mode.
+    pub guicursor: Setting<GuiCursor>,
+    /// Set the title of the terminal window to the current file and mode.
+    pub title: Setting<bool>,
+    /// Report the working directory to the terminal as it changes, e.g. with `:cd`.
+    pub report_cwd: Setting<bool>,
 }
 
 impl Default for Settings {
@@ -18,6 +56,24 @@ impl Default for Settings {
             diagnostics_picker_split_ratio: Setting::new((2, 1)),
             global_search_split_ratio: Setting::new((1, 2)),
             theme: Setting::new(Theme::default()),
+            placement_rules: Default::default(),
+            clipboard: Setting::new(None),
+            timeout_len: Setting::new(Duration::from_millis(1000)),
+            key_hints_delay: Setting::new(Duration::from_millis(500)),
+            leader: Setting::new(KeyEvent::from(KeyCode::Char('\\'))),
+            statusline: Default::default(),
+            completion_confirm_keys: Setting::new(vec![KeyEvent::new(
+                KeyCode::Char('y'),
+                KeyModifiers::CONTROL,
+            )]),
+            message_history: Setting::new(500),
+            swap_dir: Setting::new(None),
+            update_time: Setting::new(Duration::from_millis(4000)),
+            render_stats: Setting::new(false),
+            redraw_interval: Setting::new(Duration::from_secs(1) / 60),
+            guicursor: Default::default(),
+            title: Setting::new(false),
+            report_cwd: Setting::new(false),
         }
     }
 }
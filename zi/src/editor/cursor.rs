@@ -1,7 +1,8 @@
 use zi_core::PointOrByte;
 use zi_textobject::{TextObject, motion};
 
-use super::{Selector, get, get_ref, mode};
+use super::{Active, Selector, get, get_ref, mode};
+use crate::event;
 use crate::view::SetCursorFlags;
 use crate::{Direction, Editor, Mode, Point, ViewId};
 
@@ -67,6 +68,17 @@ impl Editor {
         }
     }
 
+    /// Dispatch [`event::DidMoveCursor`] if the cursor of the active view has moved since this was
+    /// last called, or another view became active.
+    pub(super) fn check_cursor_moved(&mut self) {
+        let view = self.view(Active);
+        let (view, buf, cursor) = (view.id(), view.buffer(), view.cursor());
+        if self.last_cursor != Some((view, cursor)) {
+            self.last_cursor = Some((view, cursor));
+            self.dispatch(event::DidMoveCursor { view, buf, cursor });
+        }
+    }
+
     #[inline]
     pub fn set_cursor_bytewise(&mut self, selector: impl Selector<ViewId>, byte: usize) {
         let view_id = selector.select(self);
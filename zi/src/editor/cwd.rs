@@ -0,0 +1,47 @@
+use anyhow::Context as _;
+
+use super::*;
+
+impl Editor {
+    /// The working directory of the view, set with `:lcd`, or the editor's set with `:cd`.
+    pub fn current_dir(&self, selector: impl Selector<ViewId>) -> &Path {
+        self.view(selector).local_dir().unwrap_or(&self.cwd)
+    }
+
+    /// Resolve `path` relative to the working directory of the active view.
+    pub fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.current_dir(Active).join(path)
+    }
+
+    /// Change the working directory of the editor, `:cd`. This also clears the local working
+    /// directory of the active view.
+    pub fn change_dir(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.cwd = self.resolve_dir(path.as_ref())?;
+        let view = Active.select(self);
+        self[view].set_local_dir(None);
+        Ok(())
+    }
+
+    /// Change the working directory of the view only, `:lcd`. Views split from it inherit it.
+    pub fn change_local_dir(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let view = selector.select(self);
+        let dir = self.resolve_dir(path.as_ref())?;
+        self[view].set_local_dir(Some(dir));
+        Ok(())
+    }
+
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        let dir = self
+            .resolve_path(path)
+            .canonicalize()
+            .with_context(|| format!("failed to change directory to `{}`", path.display()))?;
+        if !dir.is_dir() {
+            bail!("not a directory: `{}`", path.display());
+        }
+        Ok(dir)
+    }
+}
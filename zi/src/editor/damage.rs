@@ -0,0 +1,398 @@
+//! Damage tracking so that rendering a frame only renders the rows of views that may have changed.
+//!
+//! Edits remember the lines they changed, so the views showing them only render the rows of those
+//! lines, and of the lines the cursor moved between, and copy the rest from the previous frame.
+//! Anything that moves lines to other rows, e.g. scrolling or folding, renders the whole view.
+//! Commands, whether typed or mapped to keys, and changes to settings or the theme may change
+//! anything, as may any other event, e.g. a request or a task callback, so everything is rendered
+//! after them. The exception is a background parse finishing, which only renders the rows of the
+//! lines whose syntax changed, see [`Editor::damage_lines`]. The status line is only rendered when
+//! what it displays of the active view may have changed.
+
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+
+use tui::Rect;
+use zi_core::Offset;
+
+use super::*;
+
+/// Counters of the rendering work done, see `:set renderstats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// The number of frames rendered.
+    pub frames: u64,
+    /// The number of views with any rows rendered rather than copied from the previous frame.
+    pub views_rendered: u64,
+    /// The number of views copied from the previous frame entirely.
+    pub views_reused: u64,
+    /// The number of rows of views rendered, the other rows are copied from the previous frame.
+    pub rows_rendered: u64,
+}
+
+#[derive(Default)]
+pub(super) struct Damage {
+    /// Whether anything may have changed since the last frame, rather than only what is tracked.
+    all: bool,
+    /// The mode and search pattern of the last frame, which affect what other views display too.
+    state: Option<(Mode, Option<String>)>,
+    views: RefCell<HashMap<ViewId, RenderedView>>,
+    /// The lines of buffers that changed without an edit since the last frame.
+    lines: HashMap<BufferId, Vec<Range<usize>>>,
+    /// The lines of buffers edited since the last frame.
+    edits: HashMap<BufferId, EditDamage>,
+    /// Whether the last callback only changed `lines`, rather than possibly anything.
+    only_lines: bool,
+    status_line: Option<(StatusLineKey, Vec<tui::Cell>)>,
+    stats: Cell<RenderStats>,
+}
+
+/// The lines changed by the edits from one version of a buffer to another.
+struct EditDamage {
+    from: u32,
+    to: u32,
+    /// The changed lines, open-ended from the first edit that changed the number of lines.
+    lines: Vec<Range<usize>>,
+}
+
+/// What a view is rendered from, besides what can only be changed by events that damage all.
+#[derive(Debug, PartialEq, Eq)]
+struct ViewKey {
+    area: Rect,
+    buf: BufferId,
+    version: u32,
+    marks: u64,
+    offset: Offset,
+    cursor: Point,
+    active: bool,
+}
+
+struct RenderedView {
+    key: ViewKey,
+    /// The frame the view was last rendered or copied in.
+    frame: u64,
+    /// Whether each row displays the line after the one above it, so no rows are folds or fillers.
+    consecutive: bool,
+    cells: Vec<tui::Cell>,
+    number_width: u16,
+}
+
+/// What the status line is rendered from, besides what can only be changed by events that damage
+/// all. The mode is part of the state that damages all when it changes.
+#[derive(PartialEq, Eq)]
+struct StatusLineKey {
+    area: Rect,
+    buf: BufferId,
+    version: u32,
+    dirty: bool,
+    cursor: Point,
+    sub_mode: Option<String>,
+    error: Option<String>,
+}
+
+impl Editor {
+    /// The rendering work done so far.
+    pub fn render_stats(&self) -> RenderStats {
+        self.damage.stats.get()
+    }
+
+    /// Render everything in the next frame.
+    pub(super) fn damage_all(&mut self) {
+        self.damage.all = true;
+    }
+
+    pub(super) fn begin_frame(&mut self) {
+        let state = Some((self.mode(), self.search_pattern().map(str::to_owned)));
+        // Views in diff mode are aligned with each other, so editing one changes the other.
+        if state != self.damage.state || self.diff.is_some() {
+            self.damage.state = state;
+            self.damage.all = true;
+        }
+
+        for buf in self.buffers.values_mut() {
+            let lines = buf.take_reparsed();
+            if !lines.is_empty() {
+                self.damage.lines.entry(buf.id()).or_default().extend(lines);
+            }
+        }
+
+        let views = &self.views;
+        self.damage.views.get_mut().retain(|&view, _| views.contains_key(view));
+        self.update_render_stats(|stats| stats.frames += 1);
+    }
+
+    pub(super) fn end_frame(&mut self) {
+        self.damage.all = false;
+        self.damage.lines.clear();
+        self.damage.edits.clear();
+    }
+
+    /// Render the views showing any of `lines` of the buffer in the next frame, for changes to how
+    /// the lines are displayed that are not edits. The callback calling this damages nothing else.
+    pub(crate) fn damage_lines(
+        &mut self,
+        buf: BufferId,
+        lines: impl IntoIterator<Item = Range<usize>>,
+    ) {
+        self.damage.lines.entry(buf).or_default().extend(lines);
+        self.damage.only_lines = true;
+    }
+
+    /// Whether the callback just handled only damaged lines with [`Editor::damage_lines`].
+    pub(super) fn only_lines_damaged(&mut self) -> bool {
+        std::mem::take(&mut self.damage.only_lines)
+    }
+
+    /// Remember the lines changed by the deltas just applied to `old_text`, which was version
+    /// `from` of the buffer.
+    pub(super) fn damage_edit(
+        &mut self,
+        buf: BufferId,
+        old_text: &dyn AnyText,
+        deltas: &Deltas<'_>,
+        from: u32,
+    ) {
+        let to = self[buf].version();
+        let edit = self.damage.edits.entry(buf).or_insert_with(|| EditDamage {
+            from,
+            to: from,
+            lines: vec![],
+        });
+        if edit.to != from {
+            // Something other than an edit changed the buffer in between, so anything may change.
+            edit.lines = vec![0..usize::MAX];
+        }
+        edit.to = to;
+
+        for delta in deltas.iter() {
+            let range = delta.range();
+            let (start, end) =
+                (old_text.byte_to_line(range.start), old_text.byte_to_line(range.end));
+            // The lines below move to other rows if the number of lines changed.
+            if delta.text().matches('\n').count() != end - start {
+                edit.lines.push(start..usize::MAX);
+                break;
+            }
+            edit.lines.push(start..end + 1);
+        }
+    }
+
+    /// The rows of the view that may have changed since the previous frame, to render them and
+    /// copy the rest, or `None` if the whole view must be rendered.
+    pub(super) fn damaged_rows(&self, area: Rect, view: ViewId) -> Option<Vec<Range<u16>>> {
+        let views = self.damage.views.borrow();
+        let rendered = views.get(&view)?;
+        // A view that was not displayed in the previous frame missed the damage since.
+        if self.damage.all || rendered.frame + 1 != self.render_stats().frames {
+            return None;
+        }
+
+        let (key, prev) = (self.view_key(area, view), &rendered.key);
+        if key == *prev && !self.lines_damaged(view) {
+            return Some(vec![]);
+        }
+
+        // Only rows that still display the same lines can be copied.
+        if !rendered.consecutive
+            || !self.consecutive_rows(view)
+            || (key.area, key.buf, key.marks, key.offset, key.active)
+                != (prev.area, prev.buf, prev.marks, prev.offset, prev.active)
+        {
+            return None;
+        }
+
+        // The visual selection and search matches may move with the cursor and with edits.
+        if key != *prev
+            && (self.visual_selection(view).is_some()
+                || self.search_state.hlsearch && !self.search_state.matches().is_empty())
+        {
+            return None;
+        }
+
+        // The cursor column and relative line numbers move with the cursor.
+        let settings = self[view].settings();
+        let cursor_moved = key.cursor != prev.cursor;
+        let relative = *settings.line_number_style.read() == tui::LineNumberStyle::Relative;
+        if cursor_moved
+            && (*settings.cursor_column.read()
+                || relative && key.cursor.line() != prev.cursor.line())
+        {
+            return None;
+        }
+
+        let mut lines = self.damage.lines.get(&key.buf).cloned().unwrap_or_default();
+        if key.version != prev.version {
+            let edit = self.damage.edits.get(&key.buf)?;
+            if (edit.from, edit.to) != (prev.version, key.version) {
+                return None;
+            }
+            lines.extend(edit.lines.iter().cloned());
+        }
+
+        // The cursor line is highlighted, revealed, and may have its blame displayed after it.
+        if cursor_moved {
+            lines.extend([prev.cursor.line(), key.cursor.line()].map(|line| line..line + 1));
+        }
+
+        // The line numbers may get narrower if lines were deleted from the end of the view.
+        let (offset, height) = (key.offset.line, area.height as usize);
+        if lines.iter().any(|lines| lines.end == usize::MAX)
+            && offset + height >= self[key.buf].text().len_lines()
+        {
+            return None;
+        }
+
+        let row = |line: usize| (line.clamp(offset, offset + height) - offset) as u16;
+        let mut rows = lines
+            .into_iter()
+            .map(|lines| row(lines.start)..row(lines.end))
+            .filter(|rows| !rows.is_empty())
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|rows| rows.start);
+        // Overlapping or adjacent rows are merged into the first of them.
+        rows.dedup_by(|rows, prev| {
+            let merge = rows.start <= prev.end;
+            if merge {
+                prev.end = prev.end.max(rows.end);
+            }
+            merge
+        });
+        Some(rows)
+    }
+
+    /// Copy the view from the previous frame, see [`Editor::damaged_rows`].
+    pub(super) fn copy_view(&self, area: Rect, surface: &mut tui::Buffer, view: ViewId) {
+        let views = self.damage.views.borrow();
+        let rendered = &views[&view];
+        for (i, cell) in rendered.cells.iter().enumerate() {
+            let (x, y) = (i % area.width as usize, i / area.width as usize);
+            *surface.get_mut(area.x + x as u16, area.y + y as u16) = cell.clone();
+        }
+        self[view].number_width.set(rendered.number_width);
+    }
+
+    /// Remember the view with the `rows` just rendered to copy it into later frames.
+    pub(super) fn rendered_view(
+        &self,
+        area: Rect,
+        surface: &tui::Buffer,
+        view: ViewId,
+        rows: &[Range<u16>],
+    ) {
+        let frame = self.render_stats().frames;
+        let mut views = self.damage.views.borrow_mut();
+        if rows.is_empty() {
+            views.get_mut(&view).expect("only rendered views are copied").frame = frame;
+            self.update_render_stats(|stats| stats.views_reused += 1);
+            return;
+        }
+
+        let key = self.view_key(area, view);
+        let mut cells = match views.remove(&view) {
+            Some(rendered) if rendered.key.area == area => rendered.cells,
+            _ => vec![tui::Cell::default(); area.area() as usize],
+        };
+        for y in rows.iter().flat_map(|rows| rows.clone()) {
+            let start = y as usize * area.width as usize;
+            for x in 0..area.width {
+                cells[start + x as usize] = surface.get(area.x + x, area.y + y).clone();
+            }
+        }
+
+        let consecutive = self.consecutive_rows(view);
+        let number_width = self[view].number_width.get();
+        views.insert(view, RenderedView { key, frame, consecutive, cells, number_width });
+        let n = rows.iter().map(|rows| rows.len()).sum::<usize>();
+        self.update_render_stats(|stats| {
+            stats.views_rendered += 1;
+            stats.rows_rendered += n as u64;
+        });
+    }
+
+    /// Copy the status line from the previous frame if it can't have changed, returning whether
+    /// it did.
+    pub(super) fn reuse_status_line(&self, area: Rect, surface: &mut tui::Buffer) -> bool {
+        let Some((key, cells)) = &self.damage.status_line else { return false };
+        if self.damage.all || *key != self.status_line_key(area) {
+            return false;
+        }
+
+        for (x, cell) in (area.left()..area.right()).zip(cells) {
+            *surface.get_mut(x, area.y) = cell.clone();
+        }
+        true
+    }
+
+    /// Remember the rendered status line to copy it into later frames.
+    pub(super) fn rendered_status_line(&mut self, area: Rect, surface: &tui::Buffer) {
+        let cells = (area.left()..area.right()).map(|x| surface.get(x, area.y).clone()).collect();
+        self.damage.status_line = Some((self.status_line_key(area), cells));
+    }
+
+    /// Show the render stats in the top right corner of the views.
+    pub(super) fn render_stats_overlay(&self, area: Rect, surface: &mut tui::Buffer) {
+        if !*self.settings.render_stats.read() || area.height == 0 {
+            return;
+        }
+
+        let RenderStats { frames, views_rendered, views_reused, rows_rendered } =
+            self.render_stats();
+        let text = format!(
+            " frames {frames} views {views_rendered}/{} rows {rows_rendered} ",
+            views_rendered + views_reused
+        );
+        let width = (text.chars().count() as u16).min(area.width);
+        let style = tui::Style::default()
+            .bg(tui::Color::Rgb(0x07, 0x36, 0x42))
+            .fg(tui::Color::Rgb(0x88, 0x88, 0x88));
+        surface.set_stringn(area.right() - width, area.y, &text, width as usize, style);
+    }
+
+    /// Whether each row of the view displays the line after the one above it, so copied rows
+    /// display the same lines as long as the view is not scrolled.
+    fn consecutive_rows(&self, view: ViewId) -> bool {
+        let (rows, fillers) = self.view_rows(view);
+        let offset = self[view].offset().line;
+        fillers.is_empty() && rows.iter().enumerate().all(|(i, &line)| line == offset + i)
+    }
+
+    /// Whether any of the lines displayed by the view are damaged.
+    fn lines_damaged(&self, view: ViewId) -> bool {
+        let Some(lines) = self.damage.lines.get(&self[view].buffer()) else { return false };
+        let rows = self.view_rows(view).0;
+        let (Some(&first), Some(&last)) = (rows.first(), rows.last()) else { return false };
+        lines.iter().any(|lines| lines.start <= last && first < lines.end)
+    }
+
+    fn view_key(&self, area: Rect, view: ViewId) -> ViewKey {
+        let view = &self[view];
+        ViewKey {
+            area,
+            buf: view.buffer(),
+            version: self[view.buffer()].version(),
+            marks: self[view.buffer()].marks_version(),
+            offset: view.offset(),
+            cursor: view.cursor(),
+            active: view.id() == self.view(Active).id(),
+        }
+    }
+
+    fn status_line_key(&self, area: Rect) -> StatusLineKey {
+        let (view, buf) = get_ref!(self);
+        StatusLineKey {
+            area,
+            buf: buf.id(),
+            version: buf.version(),
+            dirty: buf.flags().contains(BufferFlags::DIRTY),
+            cursor: view.cursor(),
+            sub_mode: self.sub_mode.clone(),
+            error: self.status_error.clone(),
+        }
+    }
+
+    fn update_render_stats(&self, f: impl FnOnce(&mut RenderStats)) {
+        let mut stats = self.damage.stats.get();
+        f(&mut stats);
+        self.damage.stats.set(stats);
+    }
+}
@@ -4,14 +4,15 @@ use stdx::merge::Merge;
 use zi_input::KeyEvent;
 use zi_textobject::{Around, Within, delimiter};
 
-use crate::editor::{Action, SaveFlags, set_error_if};
+use crate::editor::{Mapping, PasteFlags, SaveFlags, set_error_if};
 use crate::keymap::Keymap;
 use crate::{
-    Active, Direction, Editor, Mark, Mode, Operator, VerticalAlignment, hashmap, motion, trie,
+    Active, Dimension, Direction, Editor, Mark, Mode, Operator, VerticalAlignment, hashmap, motion,
+    trie,
 };
 
 pub(super) fn new() -> Keymap {
-    static KEYMAP: OnceLock<Keymap<Mode, KeyEvent, Action>> = OnceLock::new();
+    static KEYMAP: OnceLock<Keymap<Mode, KeyEvent, Mapping>> = OnceLock::new();
 
     macro_rules! count_fn {
         ($name:ident, $digit:expr) => {
@@ -44,6 +45,51 @@ pub(super) fn new() -> Keymap {
         editor.set_mode(Mode::OperatorPending(Operator::Yank));
     }
 
+    fn filter_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Filter));
+    }
+
+    fn fold_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Fold));
+    }
+
+    fn reindent_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Reindent));
+    }
+
+    fn comment_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Comment));
+    }
+
+    fn lowercase_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Lowercase));
+    }
+
+    fn uppercase_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Uppercase));
+    }
+
+    fn toggle_case_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::ToggleCase));
+    }
+
+    fn toggle_case(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1);
+        set_error_if!(editor: editor.toggle_case(Active, n))
+    }
+
+    fn surround_operator_pending(editor: &mut Editor) {
+        editor.set_mode(Mode::OperatorPending(Operator::Surround));
+    }
+
+    fn change_surround_pending(editor: &mut Editor) {
+        editor.change_surround_pending();
+    }
+
+    fn delete_surround_pending(editor: &mut Editor) {
+        editor.delete_surround_pending();
+    }
+
     fn delete_till_end_of_line(editor: &mut Editor) {
         delete_operator_pending(editor);
         set_error_if!(editor: editor.text_object(Active, zi_textobject::Until('\n')));
@@ -58,6 +104,46 @@ pub(super) fn new() -> Keymap {
         set_error_if!(editor: editor.paste_after(Active));
     }
 
+    fn paste_before(editor: &mut Editor) {
+        set_error_if!(editor: editor.paste_before(Active));
+    }
+
+    fn paste_indented(editor: &mut Editor) {
+        set_error_if!(editor: editor.paste(Active, PasteFlags::INDENT));
+    }
+
+    fn paste_before_indented(editor: &mut Editor) {
+        set_error_if!(editor: editor.paste(Active, PasteFlags::BEFORE | PasteFlags::INDENT));
+    }
+
+    fn select_unnamed_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.select_register('"'));
+    }
+
+    fn select_clipboard_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.select_register('+'));
+    }
+
+    fn select_primary_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.select_register('*'));
+    }
+
+    fn insert_unnamed_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.insert_register('"'));
+    }
+
+    fn insert_clipboard_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.insert_register('+'));
+    }
+
+    fn insert_primary_register(editor: &mut Editor) {
+        set_error_if!(editor: editor.insert_register('*'));
+    }
+
+    fn insert_expression_register(editor: &mut Editor) {
+        editor.prompt_expression();
+    }
+
     fn insert_mode(editor: &mut Editor) {
         editor.set_mode(Mode::Insert);
     }
@@ -83,6 +169,10 @@ pub(super) fn new() -> Keymap {
         editor.set_mode(Mode::Normal);
     }
 
+    fn exit_command_mode(editor: &mut Editor) {
+        editor.exit_command_mode();
+    }
+
     fn visual_mode(editor: &mut Editor) {
         editor.set_mode(Mode::Visual);
     }
@@ -107,12 +197,103 @@ pub(super) fn new() -> Keymap {
         editor.visual_change(Active);
     }
 
+    fn visual_filter(editor: &mut Editor) {
+        editor.visual_filter(Active);
+    }
+
+    fn visual_fold(editor: &mut Editor) {
+        editor.visual_fold(Active);
+    }
+
+    fn visual_reindent(editor: &mut Editor) {
+        editor.visual_reindent(Active);
+    }
+
+    fn visual_surround(editor: &mut Editor) {
+        editor.visual_surround(Active);
+    }
+
+    fn visual_comment(editor: &mut Editor) {
+        editor.visual_comment(Active);
+    }
+
+    fn visual_lowercase(editor: &mut Editor) {
+        editor.visual_lowercase(Active);
+    }
+
+    fn visual_uppercase(editor: &mut Editor) {
+        editor.visual_uppercase(Active);
+    }
+
+    fn visual_toggle_case(editor: &mut Editor) {
+        editor.visual_toggle_case(Active);
+    }
+
+    fn increment(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.increment(Active, n))
+    }
+
+    fn decrement(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.increment(Active, -n))
+    }
+
+    fn visual_increment(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.visual_increment(Active, n, false))
+    }
+
+    fn visual_decrement(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.visual_increment(Active, -n, false))
+    }
+
+    fn visual_increment_progressive(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.visual_increment(Active, n, true))
+    }
+
+    fn visual_decrement_progressive(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i64;
+        set_error_if!(editor: editor.visual_increment(Active, -n, true))
+    }
+
+    fn join_lines(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(2).max(2);
+        let line = editor.cursor(Active).line();
+        set_error_if!(editor: editor.join_lines(Active, line..line + n, false))
+    }
+
+    fn join_lines_raw(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(2).max(2);
+        let line = editor.cursor(Active).line();
+        set_error_if!(editor: editor.join_lines(Active, line..line + n, true))
+    }
+
+    fn visual_join_lines(editor: &mut Editor) {
+        set_error_if!(editor: editor.visual_join_lines(Active, false))
+    }
+
+    fn visual_join_lines_raw(editor: &mut Editor) {
+        set_error_if!(editor: editor.visual_join_lines(Active, true))
+    }
+
     fn prev_line(editor: &mut Editor) {
-        set_error_if!(editor: editor.motion(Active, motion::PrevLine))
+        // Operators act on the lines within closed folds as usual.
+        let skip_folds = !matches!(editor.mode(), Mode::OperatorPending(_));
+        set_error_if!(editor: editor.motion(Active, motion::PrevLine));
+        if skip_folds {
+            editor.skip_closed_fold(Active, Direction::Up);
+        }
     }
 
     fn next_line(editor: &mut Editor) {
-        set_error_if!(editor: editor.motion(Active, motion::NextLine))
+        let skip_folds = !matches!(editor.mode(), Mode::OperatorPending(_));
+        set_error_if!(editor: editor.motion(Active, motion::NextLine));
+        if skip_folds {
+            editor.skip_closed_fold(Active, Direction::Down);
+        }
     }
 
     fn prev_char(editor: &mut Editor) {
@@ -151,6 +332,11 @@ pub(super) fn new() -> Keymap {
         set_error_if!(editor: editor.text_object(Active, Within(delimiter::AngleBracket)));
     }
 
+    fn inside_hunk(editor: &mut Editor) {
+        let hunk = editor.hunk_text_object(Active);
+        set_error_if!(editor: editor.text_object(Active, hunk));
+    }
+
     fn around_paren(editor: &mut Editor) {
         set_error_if!(editor: editor.text_object(Active, Around(delimiter::Paren)));
     }
@@ -204,6 +390,23 @@ pub(super) fn new() -> Keymap {
         editor.spawn("find references", fut);
     }
 
+    fn goto_next_hunk(editor: &mut Editor) {
+        // In diff mode `]c` and `[c` move between differences rather than git hunks, as in vim.
+        if editor.is_diffing(Active) {
+            editor.goto_next_diff(Active);
+        } else {
+            editor.goto_next_hunk(Active);
+        }
+    }
+
+    fn goto_prev_hunk(editor: &mut Editor) {
+        if editor.is_diffing(Active) {
+            editor.goto_prev_diff(Active);
+        } else {
+            editor.goto_prev_hunk(Active);
+        }
+    }
+
     fn goto_start(editor: &mut Editor) {
         editor.scroll(Active, Direction::Up, usize::MAX);
     }
@@ -212,6 +415,42 @@ pub(super) fn new() -> Keymap {
         editor.scroll(Active, Direction::Down, usize::MAX);
     }
 
+    fn toggle_fold(editor: &mut Editor) {
+        set_error_if!(editor: editor.toggle_fold(Active))
+    }
+
+    fn close_fold(editor: &mut Editor) {
+        set_error_if!(editor: editor.close_fold(Active))
+    }
+
+    fn goto_next_misspelling(editor: &mut Editor) {
+        editor.goto_next_misspelling(Active);
+    }
+
+    fn goto_prev_misspelling(editor: &mut Editor) {
+        editor.goto_prev_misspelling(Active);
+    }
+
+    fn open_spell_suggestions(editor: &mut Editor) {
+        editor.open_spell_suggestions(Active);
+    }
+
+    fn add_good_word(editor: &mut Editor) {
+        set_error_if!(editor: editor.add_good_word(Active))
+    }
+
+    fn open_fold(editor: &mut Editor) {
+        set_error_if!(editor: editor.open_fold(Active))
+    }
+
+    fn open_all_folds(editor: &mut Editor) {
+        editor.open_all_folds(Active);
+    }
+
+    fn close_all_folds(editor: &mut Editor) {
+        editor.close_all_folds(Active);
+    }
+
     fn align_view_top(editor: &mut Editor) {
         let view = editor.view(Active).id();
         editor.align_view(view, VerticalAlignment::Top);
@@ -238,6 +477,7 @@ pub(super) fn new() -> Keymap {
         editor.set_cursor(Active, editor.cursor(Active).with_col(0));
         set_error_if!(editor: editor.insert_char(Active, '\n'));
         set_error_if!(editor: editor.motion(Active, motion::PrevLine));
+        set_error_if!(editor: editor.indent_newline(Active));
     }
 
     fn next_token(editor: &mut Editor) {
@@ -313,6 +553,10 @@ pub(super) fn new() -> Keymap {
         editor.open_global_search(".");
     }
 
+    fn open_buffer_picker(editor: &mut Editor) {
+        editor.open_buffer_picker();
+    }
+
     fn open_file_explorer(editor: &mut Editor) {
         if let Some(path) = editor.buffer(Active).path().as_ref().and_then(|p| p.parent()) {
             editor.open_file_explorer(path);
@@ -345,10 +589,81 @@ pub(super) fn new() -> Keymap {
         editor.focus_direction(Direction::Down);
     }
 
+    fn increase_height(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i32;
+        editor.resize_view(Active, Dimension::Height, n);
+    }
+
+    fn decrease_height(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i32;
+        editor.resize_view(Active, Dimension::Height, -n);
+    }
+
+    fn increase_width(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i32;
+        editor.resize_view(Active, Dimension::Width, n);
+    }
+
+    fn decrease_width(editor: &mut Editor) {
+        let n = editor.take_count().unwrap_or(1) as i32;
+        editor.resize_view(Active, Dimension::Width, -n);
+    }
+
+    fn maximize_height(editor: &mut Editor) {
+        let n = editor.take_count().map_or(u16::MAX, |n| n as u16);
+        editor.set_view_size(Active, Dimension::Height, n);
+    }
+
+    fn maximize_width(editor: &mut Editor) {
+        let n = editor.take_count().map_or(u16::MAX, |n| n as u16);
+        editor.set_view_size(Active, Dimension::Width, n);
+    }
+
+    fn rotate_views(editor: &mut Editor) {
+        set_error_if!(editor: editor.rotate_views(Active, true))
+    }
+
+    fn rotate_views_backwards(editor: &mut Editor) {
+        set_error_if!(editor: editor.rotate_views(Active, false))
+    }
+
+    fn exchange_view(editor: &mut Editor) {
+        let n = editor.take_count().map(|n| n.saturating_sub(1));
+        set_error_if!(editor: editor.exchange_view(Active, n))
+    }
+
+    fn move_view_far_left(editor: &mut Editor) {
+        editor.move_view_to_edge(Active, Direction::Left);
+    }
+
+    fn move_view_far_down(editor: &mut Editor) {
+        editor.move_view_to_edge(Active, Direction::Down);
+    }
+
+    fn move_view_far_up(editor: &mut Editor) {
+        editor.move_view_to_edge(Active, Direction::Up);
+    }
+
+    fn move_view_far_right(editor: &mut Editor) {
+        editor.move_view_to_edge(Active, Direction::Right);
+    }
+
+    fn equalize_views(editor: &mut Editor) {
+        editor.equalize_views();
+    }
+
     fn view_only(editor: &mut Editor) {
         editor.view_only(editor.view(Active).id())
     }
 
+    fn close_window(editor: &mut Editor) {
+        set_error_if!(editor: editor.close_window(Active))
+    }
+
+    fn quit(editor: &mut Editor) {
+        set_error_if!(editor: editor.quit(Active, false))
+    }
+
     fn undo(editor: &mut Editor) {
         set_error_if!(editor: editor.undo(Active))
     }
@@ -398,6 +713,10 @@ pub(super) fn new() -> Keymap {
         editor.open_marks(Active);
     }
 
+    fn open_command_window(editor: &mut Editor) {
+        editor.open_command_window();
+    }
+
     fn tab(editor: &mut Editor) {
         set_error_if!(editor: editor.tab())
     }
@@ -410,6 +729,14 @@ pub(super) fn new() -> Keymap {
         editor.trigger_completion(None)
     }
 
+    fn complete_next(editor: &mut Editor) {
+        editor.complete_next()
+    }
+
+    fn complete_prev(editor: &mut Editor) {
+        editor.complete_prev()
+    }
+
     fn execute_buffered_command(editor: &mut Editor) {
         set_error_if!(editor: editor.execute_buffered_command());
     }
@@ -477,6 +804,8 @@ pub(super) fn new() -> Keymap {
                     "'" => inside_apostrophe,
                     "\"" => inside_quote,
                     "`" => inside_backtick,
+
+                    "h" => inside_hunk,
                 },
                 "a" => {
                     "b" => around_paren,
@@ -499,30 +828,63 @@ pub(super) fn new() -> Keymap {
 
             Keymap::from(hashmap! {
                 Mode::Command => trie!({
-                    "<ESC>" | "<C-c>" => normal_mode,
+                    "<ESC>" | "<C-c>" => exit_command_mode,
                     "<BS>" => backspace,
                     "<CR>" => execute_buffered_command,
                 }),
                 Mode::Insert => trie!({
                     "<ESC>" | "<C-c>" => normal_mode,
                     "<C-Space>" => trigger_completion,
+                    "<C-n>" => complete_next,
+                    "<C-p>" => complete_prev,
                     "<CR>" => insert_newline,
                     "<BS>" => backspace,
                     "<Tab>" => tab,
                     "<S-Tab>" => backtab,
+                    "<C-r>" => {
+                        "\"" => insert_unnamed_register,
+                        "+" => insert_clipboard_register,
+                        "*" => insert_primary_register,
+                        "=" => insert_expression_register,
+                    },
                     "f" => {
                         "d" => normal_mode,
                     },
                 }),
                 Mode::OperatorPending(Operator::Delete) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
                     "d" => text_object_current_line_inclusive,
+                    "s" => delete_surround_pending,
                 })),
                 Mode::OperatorPending(Operator::Change) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
                     "c" => text_object_current_line_exclusive,
+                    "s" => change_surround_pending,
                 })),
-                Mode::OperatorPending(Operator::Yank) => count_trie.clone().merge(operator_pending_trie).merge(trie!({
+                Mode::OperatorPending(Operator::Yank) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
                     "y" => text_object_current_line_exclusive,
+                    "s" => surround_operator_pending,
+                })),
+                Mode::OperatorPending(Operator::Surround) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "s" => text_object_current_line_exclusive,
+                })),
+                Mode::OperatorPending(Operator::Filter) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "!" => text_object_current_line_inclusive,
+                })),
+                Mode::OperatorPending(Operator::Reindent) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "=" => text_object_current_line_inclusive,
+                })),
+                Mode::OperatorPending(Operator::Comment) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "c" => text_object_current_line_inclusive,
+                })),
+                Mode::OperatorPending(Operator::Lowercase) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "u" => text_object_current_line_inclusive,
                 })),
+                Mode::OperatorPending(Operator::Uppercase) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "U" => text_object_current_line_inclusive,
+                })),
+                Mode::OperatorPending(Operator::ToggleCase) => count_trie.clone().merge(operator_pending_trie.clone()).merge(trie!({
+                    "~" => text_object_current_line_inclusive,
+                })),
+                Mode::OperatorPending(Operator::Fold) => count_trie.clone().merge(operator_pending_trie),
                 Mode::ReplacePending => trie!({
                     "<ESC>" | "<C-c>" => normal_mode,
                 }),
@@ -538,28 +900,70 @@ pub(super) fn new() -> Keymap {
                     "B" => prev_token,
                     "%" => matchit,
                     "G" => goto_end,
+                    "\"" => {
+                        "\"" => select_unnamed_register,
+                        "+" => select_clipboard_register,
+                        "*" => select_primary_register,
+                    },
                     "y" => visual_yank,
                     "d" | "x" => visual_delete,
                     "c" => visual_change,
+                    "!" => visual_filter,
+                    "=" => visual_reindent,
+                    "J" => visual_join_lines,
+                    "u" => visual_lowercase,
+                    "U" => visual_uppercase,
+                    "~" => visual_toggle_case,
+                    "S" => visual_surround,
+                    "z" => {
+                        "f" => visual_fold,
+                    },
                     "V" => visual_line_mode,
                     "<C-v>" => visual_block_mode,
                     "g" => {
                         "g" => goto_start,
+                        "c" => visual_comment,
+                        "J" => visual_join_lines_raw,
+                        "<C-a>" => visual_increment_progressive,
+                        "<C-x>" => visual_decrement_progressive,
                     },
+                    "<C-a>" => visual_increment,
+                    "<C-x>" => visual_decrement,
                 })),
                 Mode::VisualLine => count_trie.clone().merge(trie!({
                     "<ESC>" | "<C-c>" => normal_mode,
                     "j" => next_line,
                     "k" => prev_line,
                     "G" => goto_end,
+                    "\"" => {
+                        "\"" => select_unnamed_register,
+                        "+" => select_clipboard_register,
+                        "*" => select_primary_register,
+                    },
                     "y" => visual_yank,
                     "d" | "x" => visual_delete,
                     "c" => visual_change,
+                    "!" => visual_filter,
+                    "=" => visual_reindent,
+                    "J" => visual_join_lines,
+                    "u" => visual_lowercase,
+                    "U" => visual_uppercase,
+                    "~" => visual_toggle_case,
+                    "S" => visual_surround,
+                    "z" => {
+                        "f" => visual_fold,
+                    },
                     "v" => visual_mode,
                     "<C-v>" => visual_block_mode,
                     "g" => {
                         "g" => goto_start,
+                        "c" => visual_comment,
+                        "J" => visual_join_lines_raw,
+                        "<C-a>" => visual_increment_progressive,
+                        "<C-x>" => visual_decrement_progressive,
                     },
+                    "<C-a>" => visual_increment,
+                    "<C-x>" => visual_decrement,
                 })),
                 Mode::VisualBlock => count_trie.clone().merge(trie!({
                     "<ESC>" | "<C-c>" => normal_mode,
@@ -572,14 +976,34 @@ pub(super) fn new() -> Keymap {
                     "W" => next_token,
                     "B" => prev_token,
                     "G" => goto_end,
+                    "\"" => {
+                        "\"" => select_unnamed_register,
+                        "+" => select_clipboard_register,
+                        "*" => select_primary_register,
+                    },
                     "y" => visual_yank,
                     "d" | "x" => visual_delete,
                     "c" => visual_change,
+                    "!" => visual_filter,
+                    "=" => visual_reindent,
+                    "J" => visual_join_lines,
+                    "u" => visual_lowercase,
+                    "U" => visual_uppercase,
+                    "~" => visual_toggle_case,
+                    "z" => {
+                        "f" => visual_fold,
+                    },
                     "v" => visual_mode,
                     "V" => visual_line_mode,
                     "g" => {
                         "g" => goto_start,
+                        "c" => visual_comment,
+                        "J" => visual_join_lines_raw,
+                        "<C-a>" => visual_increment_progressive,
+                        "<C-x>" => visual_decrement_progressive,
                     },
+                    "<C-a>" => visual_increment,
+                    "<C-x>" => visual_decrement,
                 })),
                 Mode::Normal => count_trie.merge(trie!({
                     "<C-s>" => save,
@@ -591,10 +1015,22 @@ pub(super) fn new() -> Keymap {
                     "<C-y>" => scroll_line_up,
                     "<Tab>" => tab,
                     "r" => replace_pending,
+                    "q" => {
+                        ":" => open_command_window,
+                    },
                     "m" => tmp_create_mark_test,
                     "d" => delete_operator_pending,
                     "c" => change_operator_pending,
+                    "\"" => {
+                        "\"" => select_unnamed_register,
+                        "+" => select_clipboard_register,
+                        "*" => select_primary_register,
+                    },
                     "y" => yank_operator_pending,
+                    "!" => filter_operator_pending,
+                    "=" => reindent_operator_pending,
+                    "~" => toggle_case,
+                    "J" => join_lines,
                     "C" => change_till_end_of_line,
                     "D" => delete_till_end_of_line,
                     "%" => matchit,
@@ -612,6 +1048,7 @@ pub(super) fn new() -> Keymap {
                     "o" => open_newline,
                     "O" => open_newline_above,
                     "p" => paste,
+                    "P" => paste_before,
                     "w" => next_word,
                     "b" => prev_word,
                     "W" => next_token,
@@ -620,6 +1057,8 @@ pub(super) fn new() -> Keymap {
                     "A" => append_eol,
                     "u" => undo,
                     "<C-r>" => redo,
+                    "<C-a>" => increment,
+                    "<C-x>" => decrement,
                     "." => dot_repeat,
                     "<C-h>" => focus_left,
                     "<C-j>" => focus_down,
@@ -629,7 +1068,18 @@ pub(super) fn new() -> Keymap {
                     "n" => goto_next_match,
                     "N" => goto_prev_match,
                     "G" => goto_end,
+                    "]" => {
+                        "c" => goto_next_hunk,
+                        "p" => paste_indented,
+                        "s" => goto_next_misspelling,
+                    },
+                    "[" => {
+                        "c" => goto_prev_hunk,
+                        "p" => paste_before_indented,
+                        "s" => goto_prev_misspelling,
+                    },
                     "<space>" => {
+                        "b" => open_buffer_picker,
                         "e" => open_file_explorer,
                         "o" => open_file_picker,
                         "f" => open_file_picker_here,
@@ -645,6 +1095,11 @@ pub(super) fn new() -> Keymap {
                         "t" => goto_type_definition,
                         "r" => find_references,
                         "g" => goto_start,
+                        "c" => comment_operator_pending,
+                        "J" => join_lines_raw,
+                        "u" => lowercase_operator_pending,
+                        "U" => uppercase_operator_pending,
+                        "~" => toggle_case_operator_pending,
                     },
                     "t" => {
                         "s" => inspect,
@@ -653,15 +1108,39 @@ pub(super) fn new() -> Keymap {
                         "t" => align_view_top,
                         "z" => align_view_center,
                         "b" => align_view_bottom,
+                        "a" => toggle_fold,
+                        "c" => close_fold,
+                        "o" => open_fold,
+                        "R" => open_all_folds,
+                        "M" => close_all_folds,
+                        "f" => fold_operator_pending,
+                        "=" => open_spell_suggestions,
+                        "g" => add_good_word,
                     },
                     "<C-w>" => {
-                        "o" => view_only,
+                        "o" | "<C-o>" => view_only,
+                        "c" => close_window,
+                        "q" | "<C-q>" => quit,
                         "v" | "<C-v>" => split_vertical,
                         "s" | "<C-s>" => split_horizontal,
                         "h" | "<C-h>" => focus_left,
                         "k" | "<C-k>" => focus_up,
                         "j" | "<C-j>" => focus_down,
                         "l" | "<C-l>" => focus_right,
+                        "+" => increase_height,
+                        "-" => decrease_height,
+                        ">" => increase_width,
+                        "<" => decrease_width,
+                        "_" => maximize_height,
+                        "|" => maximize_width,
+                        "=" => equalize_views,
+                        "r" | "<C-r>" => rotate_views,
+                        "R" => rotate_views_backwards,
+                        "x" | "<C-x>" => exchange_view,
+                        "H" => move_view_far_left,
+                        "J" => move_view_far_down,
+                        "K" => move_view_far_up,
+                        "L" => move_view_far_right,
                     },
                 })),
             })
@@ -0,0 +1,373 @@
+use std::ops::Range;
+
+use zi_text::LineDiff;
+
+use super::git::byte_range;
+use super::*;
+use crate::buffer::{MarkBuilder, Sign};
+use crate::syntax::HighlightName;
+
+const NAMESPACE: &str = "diff";
+
+/// How long to wait after an edit to either buffer before diffing them again.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A pair of views whose buffers are compared line by line, see [`Editor::diff_views`].
+#[derive(Debug)]
+pub(super) struct Diff {
+    views: [ViewId; 2],
+    /// The buffers of the views when the diff was started.
+    buffers: [BufferId; 2],
+    /// The differences from the buffer of the first view to the buffer of the second.
+    hunks: Vec<LineDiff>,
+    /// Incremented whenever the diff is scheduled or recomputed, so stale results are dropped.
+    generation: u64,
+}
+
+impl Diff {
+    fn other(&self, view: ViewId) -> Option<ViewId> {
+        match self.views {
+            [a, b] if a == view => Some(b),
+            [a, b] if b == view => Some(a),
+            _ => None,
+        }
+    }
+}
+
+impl Editor {
+    /// Open `path` in a vertical split and diff it against the buffer in the view.
+    pub fn diff_split(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        path: impl AsRef<Path>,
+    ) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let view = selector.select(self);
+        let open = self.open(path, OpenFlags::SPAWN_LANGUAGE_SERVICES)?;
+        let split = self.split(view, Direction::Right, tui::Constraint::Fill(1));
+        let client = self.client();
+        Ok(async move {
+            let buf = open.await?;
+            client
+                .with(move |editor| {
+                    editor.set_buffer(split, buf);
+                    editor.diff_views(view, split);
+                })
+                .await;
+            Ok(())
+        })
+    }
+
+    /// Compare the buffers of the two views, replacing any existing diff.
+    /// The diff is kept up to date with edits to either buffer until [`Editor::diff_off`].
+    pub fn diff_views(&mut self, a: impl Selector<ViewId>, b: impl Selector<ViewId>) {
+        let views = [a.select(self), b.select(self)];
+        self.diff_off();
+        let buffers = views.map(|view| self[view].buffer());
+        self.diff = Some(Diff { views, buffers, hunks: vec![], generation: 0 });
+        self.update_diff();
+    }
+
+    /// Stop comparing buffers.
+    pub fn diff_off(&mut self) {
+        let Some(diff) = self.diff.take() else { return };
+        let ns = self.create_namespace(NAMESPACE);
+        for buf in diff.buffers {
+            self[buf].clear_marks(ns, ..);
+        }
+        request_redraw();
+    }
+
+    pub fn is_diffing(&self, selector: impl Selector<ViewId>) -> bool {
+        let view = selector.select(self);
+        self.diff.as_ref().is_some_and(|diff| diff.views.contains(&view))
+    }
+
+    /// The differences from the buffer of the other view in the diff to the buffer of this view,
+    /// i.e. `old` is lines of the other buffer and `new` is lines of this one.
+    /// Returns `None` if the view is not part of a diff.
+    pub fn diff_hunks(&self, selector: impl Selector<ViewId>) -> Option<Vec<LineDiff>> {
+        let view = selector.select(self);
+        let diff = self.diff.as_ref()?;
+        if diff.views[0] == view {
+            let hunks = diff.hunks.iter();
+            Some(
+                hunks
+                    .map(|hunk| LineDiff { old: hunk.new.clone(), new: hunk.old.clone() })
+                    .collect(),
+            )
+        } else if diff.views[1] == view {
+            Some(diff.hunks.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Move the cursor to the start of the next difference after the cursor line.
+    pub fn goto_next_diff(&mut self, selector: impl Selector<ViewId>) -> Option<LineDiff> {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        let hunk = self.diff_hunks(view)?.into_iter().find(|hunk| start_line(hunk) > line)?;
+        self.set_cursor(view, Point::new(start_line(&hunk), 0));
+        Some(hunk)
+    }
+
+    /// Move the cursor to the start of the previous difference before the cursor line.
+    pub fn goto_prev_diff(&mut self, selector: impl Selector<ViewId>) -> Option<LineDiff> {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        let hunk = self.diff_hunks(view)?.into_iter().rfind(|hunk| start_line(hunk) < line)?;
+        self.set_cursor(view, Point::new(start_line(&hunk), 0));
+        Some(hunk)
+    }
+
+    /// Replace the difference under the cursor with the lines of the other buffer.
+    pub fn diff_get(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let (other, hunk) = self.diff_at_cursor(view)?;
+        self.copy_lines((other, hunk.old), (view, hunk.new.clone()))?;
+        self.set_cursor(view, Point::new(hunk.new.start, 0));
+        Ok(())
+    }
+
+    /// Replace the corresponding lines of the other buffer with the difference under the cursor.
+    pub fn diff_put(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let (other, hunk) = self.diff_at_cursor(view)?;
+        self.copy_lines((view, hunk.new), (other, hunk.old))
+    }
+
+    fn diff_at_cursor(&self, view: ViewId) -> Result<(ViewId, LineDiff)> {
+        let (Some(other), Some(hunks)) =
+            (self.diff.as_ref().and_then(|diff| diff.other(view)), self.diff_hunks(view))
+        else {
+            bail!("not in diff mode")
+        };
+
+        let line = self[view].cursor().line();
+        match hunks.into_iter().find(|hunk| hunk.new.contains(&line) || start_line(hunk) == line) {
+            Some(hunk) => Ok((other, hunk)),
+            None => bail!("no difference at cursor"),
+        }
+    }
+
+    fn copy_lines(
+        &mut self,
+        (from, from_lines): (ViewId, Range<usize>),
+        (to, to_lines): (ViewId, Range<usize>),
+    ) -> Result<()> {
+        let (from, to) = (self[from].buffer(), self[to].buffer());
+        if self[to].flags().contains(BufferFlags::READONLY) {
+            bail!(EditError::Readonly);
+        }
+
+        let text = self[from].text();
+        let lines = text.byte_slice(byte_range(text, &from_lines)).to_cow().into_owned();
+        let range = byte_range(self[to].text(), &to_lines);
+        self.edit(to, &Deltas::new([Delta::new(range, lines)]))?;
+        self[to].snapshot(SnapshotFlags::empty());
+        self.update_diff();
+        Ok(())
+    }
+
+    /// Recompute the diff on the rayon pool once `buf` stops changing, if it is part of the diff.
+    pub(super) fn schedule_diff(&mut self, buf: BufferId) {
+        let Some(diff) = self.diff.as_mut().filter(|diff| diff.buffers.contains(&buf)) else {
+            return;
+        };
+        diff.generation += 1;
+        let generation = diff.generation;
+
+        let client = self.client();
+        self.spawn("diff", async move {
+            tokio::time::sleep(DEBOUNCE).await;
+            client
+                .with(move |editor| {
+                    if editor.diff.as_ref().is_none_or(|diff| diff.generation != generation) {
+                        return;
+                    }
+
+                    let Some((old, new)) = editor.diff_texts() else { return };
+                    let client = editor.client();
+                    pool().spawn(move || {
+                        let hunks = zi_text::diff_lines(&old, &new);
+                        client.send(move |editor| {
+                            // Either buffer may have changed while it was being diffed.
+                            if editor
+                                .diff
+                                .as_ref()
+                                .is_some_and(|diff| diff.generation == generation)
+                            {
+                                editor.set_diff_hunks(hunks);
+                            }
+                            Ok(())
+                        });
+                    });
+                })
+                .await;
+            Ok(())
+        });
+    }
+
+    /// Recompute the diff immediately, dropping any scheduled update.
+    fn update_diff(&mut self) {
+        let Some((old, new)) = self.diff_texts() else { return };
+        if let Some(diff) = &mut self.diff {
+            diff.generation += 1;
+        }
+        self.set_diff_hunks(zi_text::diff_lines(&old, &new));
+    }
+
+    /// The texts of the buffers of the diff.
+    /// Returns `None` and ends the diff if either view is closed or shows another buffer.
+    fn diff_texts(&mut self) -> Option<(String, String)> {
+        let diff = self.diff.as_ref()?;
+        let valid = diff.views.iter().zip(&diff.buffers).all(|(&view, &buf)| {
+            self.tree.views().any(|v| v == view) && self[view].buffer() == buf
+        });
+        if !valid {
+            self.diff_off();
+            return None;
+        }
+
+        let [old, new] = diff.buffers;
+        Some((self[old].text().to_string(), self[new].text().to_string()))
+    }
+
+    fn set_diff_hunks(&mut self, hunks: Vec<LineDiff>) {
+        let Some(diff) = &self.diff else { return };
+        let [old, new] = diff.buffers;
+        let ns = self.create_namespace(NAMESPACE);
+        let marks = self.diff_marks(old, hunks.iter().map(|hunk| (&hunk.old, &hunk.new)));
+        self[old].replace_marks(ns, marks);
+        let marks = self.diff_marks(new, hunks.iter().map(|hunk| (&hunk.new, &hunk.old)));
+        self[new].replace_marks(ns, marks);
+
+        if let Some(diff) = &mut self.diff {
+            diff.hunks = hunks;
+        }
+        self.sync_diff_views();
+        request_redraw();
+    }
+
+    /// Highlight the lines of `buf` that differ, given pairs of the lines of `buf` and the
+    /// corresponding lines of the other buffer.
+    fn diff_marks<'a>(
+        &self,
+        buf: BufferId,
+        hunks: impl Iterator<Item = (&'a Range<usize>, &'a Range<usize>)>,
+    ) -> Vec<MarkBuilder> {
+        let hl = |name| self.highlight_id_by_name(name);
+        let (added, changed) = (hl(HighlightName::DIFF_ADDED), hl(HighlightName::DIFF_CHANGED));
+        let deleted = hl(HighlightName::DIFF_DELETED);
+
+        let text = self[buf].text();
+        hunks
+            .flat_map(|(lines, other)| {
+                if lines.is_empty() {
+                    // Lines only the other buffer has, mark where they would be.
+                    let (line, sign) = match lines.start.checked_sub(1) {
+                        Some(line) => (line, Sign { text: '_', hl: deleted }),
+                        None => (0, Sign { text: '‾', hl: deleted }),
+                    };
+                    let byte = byte_range(text, &(line..line)).start;
+                    return vec![Mark::builder(byte).sign(sign)];
+                }
+
+                let hl = if other.is_empty() { added } else { changed };
+                lines
+                    .clone()
+                    .map(|line| {
+                        let range = byte_range(text, &(line..line + 1));
+                        Mark::builder(range.start).width(range.len()).hl(hl)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Scroll and move the cursor of the other view of the diff to match the active view.
+    pub(super) fn sync_diff_views(&mut self) {
+        let view = self.tree.active();
+        let (Some(other), Some(hunks)) =
+            (self.diff.as_ref().and_then(|diff| diff.other(view)), self.diff_hunks(view))
+        else {
+            return;
+        };
+
+        let cursor = self[view].cursor();
+        let offset = self[view].offset().line;
+        let point = Point::new(map_line(&hunks, cursor.line()), cursor.col());
+        if self[other].cursor() != point {
+            self.set_cursor(other, point);
+        }
+        let area = self.tree.view_area(other);
+        self.views[other].set_offset_line(area, map_line(&hunks, offset));
+    }
+
+    /// The line displayed on each row of the view, and the rows that display no line.
+    /// A closed fold takes up a single row, and filler rows stand in for the lines only the other
+    /// buffer of a diff has so the lines of both views stay aligned.
+    pub(super) fn view_rows(&self, view: ViewId) -> (Vec<usize>, Vec<usize>) {
+        let view = &self[view];
+        let buf = view.buffer();
+        let len_lines = self[buf].text().len_lines();
+        let offset = view.offset().line;
+        let height = self.tree.view_area(view.id()).height as usize;
+        let lines = fold::visible_lines(&self.closed_folds(buf), offset, len_lines, height);
+
+        let fillers = self
+            .diff_hunks(view.id())
+            .into_iter()
+            .flatten()
+            .filter(|hunk| hunk.old.len() > hunk.new.len())
+            .map(|hunk| (hunk.new.end, hunk.old.len() - hunk.new.len()))
+            .collect::<BTreeMap<_, _>>();
+        if fillers.is_empty() {
+            return (lines, vec![]);
+        }
+
+        // The filler rows above a line have the same line as it, so searching for the last row of
+        // a line finds the row it is displayed on. Lines missing from the end go below the last line.
+        let end = (lines.len() < height).then_some(len_lines);
+        let (mut rows, mut filler_rows) = (Vec::with_capacity(height), vec![]);
+        for (i, line) in lines.into_iter().chain(end).enumerate() {
+            // Filler rows above the first row are scrolled out of view unless at the top.
+            if i > 0 || offset == 0 {
+                let n = fillers.get(&line).copied().unwrap_or_default();
+                filler_rows.extend(rows.len()..rows.len() + n);
+                rows.extend(std::iter::repeat_n(line, n));
+            }
+
+            if line < len_lines {
+                rows.push(line);
+            }
+        }
+
+        rows.truncate(height);
+        filler_rows.retain(|&row| row < rows.len());
+        (rows, filler_rows)
+    }
+}
+
+/// The line a difference starts on in the new text, lines missing from it belong to the line above.
+fn start_line(hunk: &LineDiff) -> usize {
+    if hunk.new.is_empty() { hunk.new.start.saturating_sub(1) } else { hunk.new.start }
+}
+
+/// Map a line of the new text to the corresponding line of the old text.
+fn map_line(hunks: &[LineDiff], line: usize) -> usize {
+    let (mut old_end, mut new_end) = (0, 0);
+    for hunk in hunks {
+        if line < hunk.new.start {
+            break;
+        }
+
+        if line < hunk.new.end {
+            return hunk.old.start + (line - hunk.new.start).min(hunk.old.len().saturating_sub(1));
+        }
+
+        (old_end, new_end) = (hunk.old.end, hunk.new.end);
+    }
+
+    old_end + (line - new_end)
+}
@@ -0,0 +1,149 @@
+use super::*;
+use crate::command::{Arg, Word};
+use crate::expr::{self, Value};
+
+impl Editor {
+    /// Evaluate an expression in the context of the active view, e.g. `line('.') * 2` or
+    /// `expand('%:t') . '.bak'`. Besides the string functions of the expression language,
+    /// `line`, `col`, `getline`, and `expand` query the editor.
+    pub fn eval(&self, src: &str) -> Result<String> {
+        expr::eval(src, |name, args| self.eval_function(name, &args)).map(|value| value.to_string())
+    }
+
+    /// Replace command arguments of the form `` `=expr` `` with the value of the expression.
+    /// If the arguments are `paths`, a leading `%` with any modifiers is replaced as by `expand`,
+    /// e.g. `%:h/mod.rs`, and a leading `\%` is a literal `%`.
+    pub(crate) fn expand_args(&self, args: &[Arg], paths: bool) -> Result<Box<[Word]>> {
+        args.iter()
+            .map(|arg| match arg {
+                Arg::Expr(expr) => {
+                    let value = self.eval(expr)?;
+                    Word::try_from(value.as_str())
+                        .map_err(|_| anyhow!("expression value contains whitespace: `{value}`"))
+                }
+                Arg::Word(arg) if paths && arg.starts_with('%') => {
+                    // The modifiers are a `:` followed by a single character each.
+                    let mut end = 1;
+                    let mut chars = arg[1..].chars();
+                    while let (Some(':'), Some(c)) = (chars.next(), chars.next()) {
+                        end += 1 + c.len_utf8();
+                    }
+                    let path = self.expand(Active.select(self), &arg[..end])? + &arg[end..];
+                    Word::try_from(path.as_str())
+                        .map_err(|_| anyhow!("expanded path contains whitespace: `{path}`"))
+                }
+                Arg::Word(arg) if paths && arg.starts_with("\\%") => {
+                    Ok(Word::try_from(&arg[1..]).unwrap())
+                }
+                Arg::Word(arg) => Ok(arg.clone()),
+            })
+            .collect()
+    }
+
+    /// Prompt for an expression in the command line to insert its value, `<C-r>=` in insert mode.
+    pub(crate) fn prompt_expression(&mut self) {
+        self.set_mode(Mode::Command);
+        let State::Command(state) = &mut self.state else { unreachable!("set command mode") };
+        state.buffer = String::from("=");
+    }
+
+    /// Evaluate the expression and insert its value at the cursor. An empty expression reuses the
+    /// previous one, which is kept in the `=` register.
+    pub(crate) fn insert_expression(&mut self, expr: &str) -> Result<()> {
+        let expr = match expr.trim() {
+            "" => match self.registers.get(Registers::EXPRESSION) {
+                Some(reg) => reg.content.clone(),
+                None => bail!("no previous expression"),
+            },
+            expr => expr.to_string(),
+        };
+
+        self.registers.get_or_insert(Registers::EXPRESSION).set(RegisterKind::Charwise, &*expr);
+        let value = self.eval(&expr)?;
+        Ok(self.paste_text(Active, &value)?)
+    }
+
+    fn eval_function(&self, name: &str, args: &[Value]) -> Result<Value> {
+        let arg = match (name, args) {
+            (_, [arg]) => arg,
+            ("line" | "col" | "getline" | "expand", _) => {
+                bail!("`{name}` expects 1 argument, got {}", args.len())
+            }
+            _ => bail!("unknown function: `{name}`"),
+        };
+
+        let (view, buf) = self.get(Active);
+        let text = self[buf].text();
+        let cursor = self[view].cursor();
+        let line_text = |line: usize| {
+            text.line(line).map_or(String::new(), |line| {
+                line.to_cow().trim_end_matches(['\n', '\r']).to_string()
+            })
+        };
+
+        match name {
+            "line" => Ok(self.eval_line(buf, cursor, arg)?.map_or(0, |line| line + 1).into()),
+            "col" => match arg.to_string().as_str() {
+                "." => Ok((cursor.col() + 1).into()),
+                "$" => Ok((line_text(cursor.line()).len() + 1).into()),
+                arg => bail!("invalid argument to `col`: `{arg}`"),
+            },
+            "getline" => match self.eval_line(buf, cursor, arg)? {
+                Some(line) => Ok(line_text(line).into()),
+                None => Ok(Value::from("")),
+            },
+            "expand" => Ok(self.expand(buf, &arg.to_string())?.into()),
+            _ => bail!("unknown function: `{name}`"),
+        }
+    }
+
+    /// The 0-indexed line of a `line` argument: a line number, `.` for the cursor, `$` for the
+    /// last line, or `'x` for a mark. `None` if there is no such line.
+    fn eval_line(&self, buf: BufferId, cursor: Point, arg: &Value) -> Result<Option<usize>> {
+        let text = self[buf].text();
+        let len_lines = text.len_lines();
+        let line = match arg {
+            Value::Number(n) => (*n > 0).then(|| *n as usize - 1),
+            Value::String(s) => match s.as_str() {
+                "." => Some(cursor.line()),
+                "$" => len_lines.checked_sub(1),
+                s => match s.strip_prefix('\'').and_then(|s| s.chars().next()) {
+                    Some(c) => self.named_mark(buf, c).map(|byte| text.byte_to_line(byte)),
+                    None => Some(arg.as_number()?.saturating_sub(1) as usize),
+                },
+            },
+        };
+        Ok(line.filter(|&line| line < len_lines))
+    }
+
+    /// Expand `%` to the path of the buffer, optionally followed by modifiers: `:p` for the
+    /// absolute path, `:h` for the parent, `:t` for the file name, `:r` to remove the extension,
+    /// and `:e` for the extension. The buffer url is used if it has no file.
+    fn expand(&self, buf: BufferId, arg: &str) -> Result<String> {
+        let Some(modifiers) = arg.strip_prefix('%').filter(|s| s.is_empty() || s.starts_with(':'))
+        else {
+            bail!("invalid argument to `expand`: `{arg}`");
+        };
+
+        let mut path = match self[buf].file_path() {
+            Some(path) => path,
+            None => return Ok(self[buf].url().to_string()),
+        };
+
+        for modifier in modifiers.split(':').skip(1) {
+            path = match modifier {
+                "p" => self.resolve_path(&path),
+                "h" => match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                },
+                "t" => path.file_name().map_or_else(Default::default, |name| name.into()),
+                "r" => path.with_extension(""),
+                "e" => path.extension().map_or_else(Default::default, |ext| ext.into()),
+                _ => bail!("invalid modifier in `expand`: `:{modifier}`"),
+            };
+        }
+
+        Ok(path.display().to_string())
+    }
+}
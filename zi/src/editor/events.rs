@@ -10,6 +10,37 @@ impl Editor {
 
         event::subscribe_with::<event::DidSaveBuffer>(|editor, event| {
             editor.refresh_semantic_tokens(event.buf);
+            editor.delete_swap(event.buf);
+            HandlerResult::Continue
+        });
+
+        event::subscribe_with::<event::DidOpenBuffer>(|editor, event| {
+            editor.restore_folds(event.buf);
+            editor.check_swap(event.buf);
+            let fut = editor.refresh_git_diff(event.buf);
+            editor.spawn("git diff", fut);
+            HandlerResult::Continue
+        });
+
+        event::subscribe_with::<event::DidChangeBuffer>(|editor, event| {
+            editor.schedule_git_diff(event.buf);
+            if editor.view(Active).buffer() == event.buf {
+                editor.schedule_git_blame(editor.view(Active).id());
+            }
+            editor.schedule_swap(event.buf);
+            editor.schedule_diff(event.buf);
+            HandlerResult::Continue
+        });
+
+        event::subscribe_with::<event::DidCloseView>(|editor, event| {
+            if editor.is_diffing(event.view) {
+                editor.diff_off();
+            }
+            HandlerResult::Continue
+        });
+
+        event::subscribe_with::<event::DidMoveCursor>(|editor, event| {
+            editor.schedule_git_blame(event.view);
             HandlerResult::Continue
         });
 
@@ -26,6 +57,10 @@ impl Editor {
                 editor.refresh_semantic_tokens(Active.select(editor))
             }
 
+            if event.from == Mode::Insert && event.to != Mode::Insert {
+                editor.end_snippet_session();
+            }
+
             // Handle dot repeat recording based on mode transitions
             if !editor.dot.is_replaying() {
                 if Dot::should_start_recording(event.from, event.to) {
@@ -43,6 +78,7 @@ impl Editor {
                 return HandlerResult::Continue;
             }
 
+            let is_trigger = editor.is_completion_trigger_character(event.char);
             let State::Insert(state) = &mut editor.state else { return HandlerResult::Continue };
 
             match event.char {
@@ -50,9 +86,9 @@ impl Editor {
                     Completion::Active(state) => state.update_query(Some(event.char)),
                     Completion::Inactive => editor.trigger_completion(Some(event.char)),
                 },
-                '.' | ':' => {
+                c if is_trigger => {
                     state.completion.deactivate();
-                    editor.trigger_completion(Some(event.char));
+                    editor.trigger_completion(Some(c));
                 }
                 _ => state.completion.deactivate(),
             }
@@ -0,0 +1,245 @@
+use std::path::MAIN_SEPARATOR;
+
+use super::*;
+use crate::lstypes;
+
+impl Editor {
+    /// The directory of the active file explorer and the path of the entry under the cursor (if any).
+    pub(crate) fn explorer_entry(&self) -> Result<(PathBuf, Option<PathBuf>)> {
+        let (view, buf) = get_ref!(self);
+        if buf.file_type() != filetype!(explorer) {
+            bail!("not in a file explorer");
+        }
+
+        let dir = buf.path().expect("explorer has a path");
+        let entry = buf
+            .text()
+            .line(view.cursor().line())
+            .map(|line| line.to_string())
+            .filter(|line| {
+                let name = line.trim_end().trim_end_matches(MAIN_SEPARATOR);
+                !matches!(name, "" | "." | "..")
+            })
+            .map(|line| dir.join(line.trim_end()));
+        Ok((dir, entry))
+    }
+
+    /// The directory file operations are relative to and the path they apply to (if any).
+    /// In a file explorer, these are its directory and the entry under the cursor. Otherwise, the
+    /// directory of the active buffer's file and the file itself, or the working directory if the
+    /// buffer has no file.
+    fn file_operation_target(&self) -> (PathBuf, Option<PathBuf>) {
+        if let Ok(target) = self.explorer_entry() {
+            return target;
+        }
+
+        match self.buffer(Active).file_path() {
+            Some(path) => match path.parent() {
+                Some(dir) => (dir.to_path_buf(), Some(path)),
+                None => (self.current_dir(Active).to_path_buf(), Some(path)),
+            },
+            None => (self.current_dir(Active).to_path_buf(), None),
+        }
+    }
+
+    /// Reload the entries of the active file explorer.
+    pub fn refresh_explorer(&mut self) -> Result<()> {
+        let (dir, _) = self.explorer_entry()?;
+        self.open_file_explorer(dir);
+        Ok(())
+    }
+
+    /// Reload the active file explorer after a file operation, does nothing outside of one.
+    fn refresh_explorer_if_active(&mut self) -> Result<()> {
+        if self.buffer(Active).file_type() == filetype!(explorer) {
+            self.refresh_explorer()?;
+        }
+        Ok(())
+    }
+
+    /// Create a file relative to the directory of the active file explorer (or buffer, see
+    /// [`Editor::rename_active_file`]), or a directory if `name` ends with a separator.
+    pub fn create_file(&mut self, name: &str) -> Result<()> {
+        let (dir, _) = self.file_operation_target();
+        let path = dir.join(name);
+        let res = if name.ends_with(['/', MAIN_SEPARATOR]) {
+            std::fs::create_dir_all(&path)
+        } else {
+            path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| {
+                File::create_new(&path)?;
+                Ok(())
+            })
+        };
+
+        res.map_err(|err| anyhow!("failed to create `{}`: {err}", path.display()))?;
+        self.refresh_explorer_if_active()
+    }
+
+    /// Move the entry under the cursor in the active file explorer (or the active buffer's file)
+    /// to the trash.
+    pub fn trash_file(&mut self) -> Result<()> {
+        let (_, Some(path)) = self.file_operation_target() else { bail!("no file to delete") };
+        stdx::fs::trash(&path)
+            .map_err(|err| anyhow!("failed to delete `{}`: {err}", path.display()))?;
+        self.refresh_explorer_if_active()
+    }
+
+    /// Rename the entry under the cursor in the active file explorer, or the file of the active
+    /// buffer elsewhere. `to` is relative to the directory of the explorer or file.
+    pub fn rename_active_file(
+        &mut self,
+        to: &str,
+    ) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let (dir, Some(from)) = self.file_operation_target() else { bail!("no file to rename") };
+        let fut = self.rename_file(from, dir.join(to))?;
+        let client = self.client();
+        Ok(async move {
+            fut.await?;
+            // The user may have navigated away in the meantime
+            client.with(|editor| editor.refresh_explorer_if_active()).await
+        })
+    }
+
+    /// Rename a file or directory on disk.
+    /// Language services that are interested are sent `workspace/willRenameFiles` beforehand and
+    /// the edits they respond with are applied. Open buffers of the renamed files are retargeted.
+    pub fn rename_file(
+        &mut self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+    ) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let from = std::path::absolute(from)?;
+        let to = std::path::absolute(to)?;
+        if to.symlink_metadata().is_ok() {
+            bail!("`{}` already exists", to.display());
+        }
+
+        let files = match (Url::from_file_path(&from), Url::from_file_path(&to)) {
+            (Ok(old_url), Ok(new_url)) => vec![lstypes::FileRename { old_url, new_url }],
+            _ => vec![],
+        };
+
+        let requests = self
+            .active_language_services
+            .values_mut()
+            .filter(|service| service.will_rename_files_capabilities().is_some())
+            .filter(|_| !files.is_empty())
+            .map(|service| {
+                service.will_rename_files(lstypes::RenameFilesParams { files: files.clone() })
+            })
+            .collect::<Vec<_>>();
+
+        let client = self.client();
+        Ok(async move {
+            for res in futures_util::future::join_all(requests).await {
+                match res {
+                    Ok(Some(edit)) => {
+                        let fut =
+                            client.with(move |editor| editor.apply_workspace_edit(edit)).await;
+                        if let Err(err) = fut.await {
+                            tracing::error!(?err, "failed to apply willRenameFiles edits");
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => tracing::error!(?err, "willRenameFiles request failed"),
+                }
+            }
+
+            tokio::fs::rename(&from, &to).await.map_err(|err| {
+                anyhow!("failed to rename `{}` to `{}`: {err}", from.display(), to.display())
+            })?;
+
+            client.with(move |editor| editor.retarget_buffers(&from, &to)).await;
+            Ok(())
+        })
+    }
+
+    /// Point the buffers of files at or under `from` to the corresponding path under `to`.
+    fn retarget_buffers(&mut self, from: &Path, to: &Path) {
+        // Buffer paths are canonical, so `from` must be too but it no longer exists.
+        let to = to.canonicalize().unwrap_or_else(|_| to.to_path_buf());
+        let from = match (from.parent().map(Path::canonicalize), from.file_name()) {
+            (Some(Ok(parent)), Some(name)) => parent.join(name),
+            _ => from.to_path_buf(),
+        };
+
+        let retargeted = self
+            .buffers
+            .values_mut()
+            .filter_map(|buf| {
+                let path = buf.file_path()?;
+                let rest = path.strip_prefix(&from).ok()?;
+                buf.set_path(&to.join(rest));
+                Some(buf.id())
+            })
+            .collect::<Vec<_>>();
+
+        // Language services see the buffers as newly opened documents at their new location.
+        for buf in retargeted {
+            tracing::debug!(?buf, ?from, ?to, "retargeted buffer");
+            self.dispatch(event::DidOpenBuffer { buf });
+        }
+    }
+
+    /// Apply the edits of `edit` to their buffers, opening the files that aren't open in the
+    /// background. The edits are not saved.
+    pub fn apply_workspace_edit(
+        &mut self,
+        edit: lstypes::WorkspaceEdit,
+    ) -> impl Future<Output = Result<()>> + 'static {
+        let opens = edit
+            .changes
+            .into_iter()
+            .map(|(url, edits)| {
+                let open = match url.to_file_path() {
+                    Ok(path) => self.open(path, OpenFlags::BACKGROUND).map_err(Into::into),
+                    Err(()) => Err(anyhow!("cannot edit non-file url `{url}`")),
+                };
+                (open, edits)
+            })
+            .collect::<Vec<_>>();
+
+        let client = self.client();
+        async move {
+            for (open, edits) in opens {
+                let buf = open?.await?;
+                client
+                    .with(move |editor| {
+                        let text = editor[buf].text();
+                        let deltas = edits
+                            .into_iter()
+                            .map(|edit| {
+                                let range = edit.range.decode(text).ok_or_else(|| {
+                                    anyhow!("invalid range in workspace edit: {}", edit.range)
+                                })?;
+                                let range = text.point_range_to_byte_range(range);
+                                Ok(Delta::new(range, edit.new_text))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        editor.edit(buf, &Deltas::new(deltas))?;
+                        Ok::<_, Error>(())
+                    })
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Enter command mode with `cmd` already typed.
+    pub(crate) fn command_mode_with(&mut self, cmd: &str) {
+        self.set_mode(Mode::Command);
+        let State::Command(state) = &mut self.state else { unreachable!("set command mode") };
+        state.buffer.push_str(cmd);
+    }
+
+    pub(crate) fn prompt_explorer_rename(&mut self) {
+        match self.explorer_entry() {
+            Ok((dir, Some(path))) => {
+                let name = path.strip_prefix(&dir).unwrap_or(&path).display().to_string();
+                self.command_mode_with(&format!("rename {name}"))
+            }
+            Ok((_, None)) => self.set_error("no entry to rename"),
+            Err(err) => self.set_error(err),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use super::*;
+
+impl Editor {
+    /// Display `buf` in a new floating window above the split tree, returns the view of the float.
+    /// A cursor anchor is relative to where the cursor is now, the float doesn't follow it.
+    /// Close the float with [`Editor::close_view`].
+    pub fn open_float(&mut self, buf: BufferId, config: FloatConfig) -> ViewId {
+        let config = match config.anchor() {
+            FloatAnchor::Cursor { x, y } => {
+                let (cursor_x, cursor_y) = self.cursor_viewport_coords();
+                let cursor_x = cursor_x + self.view(Active).number_width.get();
+                let (x, y) = (cursor_x.saturating_add_signed(x), cursor_y.saturating_add_signed(y));
+                config.with_anchor(FloatAnchor::Editor { x, y })
+            }
+            _ => config,
+        };
+
+        let view = self.create_view(buf);
+        self[view].settings().line_number_style.write(tui::LineNumberStyle::None);
+        self.tree.push_float(view, config);
+        view
+    }
+}
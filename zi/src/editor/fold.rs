@@ -0,0 +1,348 @@
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use super::*;
+use crate::buffer::{FoldMethod, MarkBuilder, MarkId};
+
+/// The marks of every fold of a buffer as of the last time they were computed.
+const NAMESPACE: &str = "folds";
+/// The marks of the folds created with `zf`, these are folds in addition to the computed ones.
+const MANUAL_NAMESPACE: &str = "folds.manual";
+/// The marks of the closed folds of a buffer. These are separate from the computed folds
+/// so that recomputing the folds does not open them.
+const CLOSED_NAMESPACE: &str = "folds.closed";
+
+/// The manual and closed folds of files that are not open, see [`Editor::save_folds`].
+#[derive(Debug, Default, Clone)]
+pub(super) struct SavedFolds {
+    manual: Vec<Range<usize>>,
+    closed: Vec<Range<usize>>,
+}
+
+impl Editor {
+    /// Compute the folds of the buffer according to its fold method.
+    /// Returns the line ranges sorted by start line with enclosing folds first.
+    pub fn update_folds(&mut self, selector: impl Selector<BufferId>) -> Vec<Range<usize>> {
+        let buf = selector.select(self);
+        self[buf].reparse();
+        let buffer = &self[buf];
+        let text = buffer.text();
+        let syntax_folds = match *buffer.settings().fold_method.read() {
+            FoldMethod::Syntax => buffer
+                .syntax()
+                .and_then(|syntax| syntax.folds(&mut tree_sitter::QueryCursor::new(), text)),
+            FoldMethod::Indent => None,
+        };
+
+        let mut folds =
+            syntax_folds.unwrap_or_else(|| indent_folds(text, *buffer.settings().tab_width.read()));
+        folds.extend(self.fold_marks(buf, MANUAL_NAMESPACE).into_iter().map(|(_, lines)| lines));
+        folds.sort_by_key(|lines| (lines.start, Reverse(lines.end)));
+        folds.dedup();
+
+        let marks = folds.iter().map(|lines| fold_mark(text, lines)).collect::<Vec<_>>();
+        let ns = self.create_namespace(NAMESPACE);
+        self[buf].replace_marks(ns, marks);
+        folds
+    }
+
+    /// The closed folds of the buffer, nested closed folds are merged into the outermost one.
+    pub fn closed_folds(&self, selector: impl Selector<BufferId>) -> Vec<Range<usize>> {
+        let mut folds = self.closed_fold_marks(selector.select(self));
+        folds.sort_by_key(|(_, lines)| (lines.start, Reverse(lines.end)));
+
+        let mut merged = Vec::<Range<usize>>::with_capacity(folds.len());
+        for (_, lines) in folds {
+            match merged.last_mut() {
+                Some(last) if lines.start < last.end => last.end = last.end.max(lines.end),
+                _ => merged.push(lines),
+            }
+        }
+        merged
+    }
+
+    /// Create a closed fold of the lines, `zf`.
+    pub fn create_fold(&mut self, selector: impl Selector<ViewId>, lines: Range<usize>) {
+        // A single line can't be folded.
+        if lines.len() < 2 {
+            return;
+        }
+
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        for name in [MANUAL_NAMESPACE, CLOSED_NAMESPACE] {
+            let ns = self.create_namespace(name);
+            let mark = fold_mark(self[buf].text(), &lines);
+            self[buf].create_mark(ns, mark);
+        }
+        self.set_cursor(view, Point::new(lines.start, 0));
+    }
+
+    /// Close the innermost open fold under the cursor, `zc`.
+    pub fn close_fold(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let line = self[view].cursor().line();
+        let closed = self.closed_fold_marks(buf);
+        let Some(fold) = self
+            .update_folds(buf)
+            .into_iter()
+            .rev()
+            .filter(|fold| fold.contains(&line))
+            .find(|fold| closed.iter().all(|(_, lines)| lines != fold))
+        else {
+            bail!("no fold found");
+        };
+
+        let ns = self.create_namespace(CLOSED_NAMESPACE);
+        let mark = fold_mark(self[buf].text(), &fold);
+        self[buf].create_mark(ns, mark);
+        self.set_cursor(view, Point::new(fold.start, 0));
+        Ok(())
+    }
+
+    /// Open the outermost closed fold under the cursor, `zo`.
+    pub fn open_fold(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let line = self[view].cursor().line();
+        let Some((id, _)) = self
+            .closed_fold_marks(buf)
+            .into_iter()
+            .filter(|(_, lines)| lines.contains(&line))
+            .min_by_key(|(_, lines)| (lines.start, Reverse(lines.end)))
+        else {
+            bail!("no fold found");
+        };
+
+        let ns = self.create_namespace(CLOSED_NAMESPACE);
+        self[buf].delete_mark(ns, id);
+        request_redraw();
+        Ok(())
+    }
+
+    /// Open the fold under the cursor if it is closed, otherwise close it, `za`.
+    pub fn toggle_fold(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        if self.closed_folds(view).iter().any(|fold| fold.contains(&line)) {
+            self.open_fold(view)
+        } else {
+            self.close_fold(view)
+        }
+    }
+
+    /// Open every fold of the buffer, `zR`.
+    pub fn open_all_folds(&mut self, selector: impl Selector<BufferId>) {
+        let buf = selector.select(self);
+        let ns = self.create_namespace(CLOSED_NAMESPACE);
+        self[buf].clear_marks(ns, ..);
+        request_redraw();
+    }
+
+    /// Close every fold of the buffer, `zM`.
+    pub fn close_all_folds(&mut self, selector: impl Selector<ViewId>) {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let folds = self.update_folds(buf);
+        let text = self[buf].text();
+        let marks = folds.iter().map(|lines| fold_mark(text, lines)).collect::<Vec<_>>();
+        let ns = self.create_namespace(CLOSED_NAMESPACE);
+        self[buf].replace_marks(ns, marks);
+
+        // Keep the cursor on a visible line.
+        let line = self[view].cursor().line();
+        if let Some(fold) = self.closed_folds(buf).into_iter().find(|fold| fold.contains(&line)) {
+            self.set_cursor(view, Point::new(fold.start, 0));
+        }
+    }
+
+    /// Move the cursor out of the closed fold it was moved into, in the direction it was moving.
+    /// Moving down skips past the fold, moving up moves to the first line of the fold.
+    pub(crate) fn skip_closed_fold(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        direction: Direction,
+    ) {
+        let view = selector.select(self);
+        let cursor = self[view].cursor();
+        let Some(fold) = self
+            .closed_folds(view)
+            .into_iter()
+            .find(|fold| fold.start < cursor.line() && fold.contains(&cursor.line()))
+        else {
+            return;
+        };
+
+        let line = match direction {
+            Direction::Down if fold.end < self.text(view).len_lines() => fold.end,
+            _ => fold.start,
+        };
+        self.set_cursor(view, Point::new(line, cursor.col()));
+    }
+
+    /// Restore the folds saved for the file of the buffer, if any.
+    pub(super) fn restore_folds(&mut self, buf: BufferId) {
+        let Some(saved) = self[buf].file_path().and_then(|path| self.saved_folds.remove(&path))
+        else {
+            return;
+        };
+
+        // The file may have changed since the folds were saved.
+        let len_lines = self[buf].text().len_lines();
+        for (name, folds) in [(MANUAL_NAMESPACE, saved.manual), (CLOSED_NAMESPACE, saved.closed)] {
+            let text = self[buf].text();
+            let marks = folds
+                .iter()
+                .filter(|lines| lines.len() > 1 && lines.end <= len_lines)
+                .map(|lines| fold_mark(text, lines))
+                .collect::<Vec<_>>();
+            let ns = self.create_namespace(name);
+            self[buf].replace_marks(ns, marks);
+        }
+    }
+
+    /// Load folds persisted by [`Editor::save_folds`], they are restored when the file is opened.
+    pub fn load_folds(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        for line in content.lines() {
+            // Ignore malformed lines rather than failing to load the rest.
+            let Some((kind, lines, path)) = parse_saved_fold(line) else { continue };
+            let saved = self.saved_folds.entry(PathBuf::from(path)).or_default();
+            match kind {
+                "manual" => saved.manual.push(lines),
+                "closed" => saved.closed.push(lines),
+                _ => continue,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Persist the manual and closed folds of every file to `path`, one fold per line.
+    pub fn save_folds(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut files = self.saved_folds.clone().into_iter().collect::<BTreeMap<_, _>>();
+        for buf in self.buffers.keys() {
+            let Some(file) = self[buf].file_path() else { continue };
+            let folds = |name| self.fold_marks(buf, name).into_iter().map(|(_, lines)| lines);
+            let saved = SavedFolds {
+                manual: folds(MANUAL_NAMESPACE).collect(),
+                closed: folds(CLOSED_NAMESPACE).collect(),
+            };
+            files.insert(file, saved);
+        }
+
+        let mut content = String::new();
+        for (file, saved) in files {
+            // Can't represent these with the line based format
+            let Some(file) = file.to_str().filter(|s| !s.contains('\n')) else { continue };
+            let folds = saved.manual.iter().map(|lines| ("manual", lines));
+            for (kind, lines) in folds.chain(saved.closed.iter().map(|lines| ("closed", lines))) {
+                content.push_str(&format!("{kind}\t{},{}\t{file}\n", lines.start, lines.end));
+            }
+        }
+
+        std::fs::write(path, content)
+    }
+
+    fn closed_fold_marks(&self, buf: BufferId) -> Vec<(MarkId, Range<usize>)> {
+        self.fold_marks(buf, CLOSED_NAMESPACE)
+    }
+
+    fn fold_marks(&self, buf: BufferId, namespace: &str) -> Vec<(MarkId, Range<usize>)> {
+        let Some(ns) = self.namespace_by_name(namespace) else { return vec![] };
+        let text = self[buf].text();
+        self[buf]
+            .marks(..)
+            .filter(|(mark_ns, ..)| *mark_ns == ns)
+            .map(|(_, range, mark)| (mark.id(), fold_lines(text, range)))
+            .filter(|(_, lines)| lines.len() > 1)
+            .collect()
+    }
+}
+
+/// The lines displayed on each row of a view starting at `offset`, a closed fold takes up a single row.
+/// `folds` must be sorted and disjoint.
+pub(crate) fn visible_lines(
+    folds: &[Range<usize>],
+    offset: usize,
+    len_lines: usize,
+    height: usize,
+) -> Vec<usize> {
+    let mut lines = Vec::with_capacity(height);
+    let mut line = offset;
+    while lines.len() < height && line < len_lines {
+        lines.push(line);
+        line = match folds.iter().find(|fold| fold.contains(&line)) {
+            Some(fold) => fold.end,
+            None => line + 1,
+        };
+    }
+    lines
+}
+
+/// A fold mark spans from the start of its first line to the start of its last line,
+/// so edits within the last line do not change which lines it covers.
+fn fold_mark(text: &dyn AnyText, lines: &Range<usize>) -> MarkBuilder {
+    let byte = |line| text.try_line_to_byte(line).unwrap_or_else(|| text.len_bytes());
+    let start = byte(lines.start);
+    Mark::builder(start).width(byte(lines.end - 1) - start)
+}
+
+/// Parse a line of the format written by [`Editor::save_folds`], `<kind>\t<start>,<end>\t<path>`.
+fn parse_saved_fold(line: &str) -> Option<(&str, Range<usize>, &str)> {
+    let mut parts = line.splitn(3, '\t');
+    let (kind, lines, path) = (parts.next()?, parts.next()?, parts.next()?);
+    let (start, end) = lines.split_once(',')?;
+    Some((kind, start.parse().ok()?..end.parse().ok()?, path))
+}
+
+fn fold_lines(text: &dyn AnyText, range: Range<usize>) -> Range<usize> {
+    text.byte_to_line(range.start)..text.byte_to_line(range.end) + 1
+}
+
+/// Fold each line with the lines after it that are indented further, blank lines in between are included.
+fn indent_folds(text: &dyn AnyText, tab_width: u8) -> Vec<Range<usize>> {
+    let mut folds = vec![];
+    // The lines that may start a fold along with their indentation, innermost last.
+    let mut stack = Vec::<(usize, usize)>::new();
+    let mut last_nonblank = 0;
+    for (line, content) in text.lines().enumerate() {
+        let Some(indent) = indent_width(content.chars(), tab_width) else { continue };
+        while let Some(&(start, _)) = stack.last().filter(|&&(_, level)| indent <= level) {
+            stack.pop();
+            if last_nonblank > start {
+                folds.push(start..last_nonblank + 1);
+            }
+        }
+
+        stack.push((line, indent));
+        last_nonblank = line;
+    }
+
+    for (start, _) in stack {
+        if last_nonblank > start {
+            folds.push(start..last_nonblank + 1);
+        }
+    }
+
+    folds
+}
+
+/// The width of the leading whitespace of the line, `None` if the line is blank.
+fn indent_width(chars: impl Iterator<Item = char>, tab_width: u8) -> Option<usize> {
+    let mut width = 0;
+    for c in chars {
+        match c {
+            ' ' | '\t' => width += tui::char_width(c, width, tab_width),
+            '\n' | '\r' => return None,
+            _ => return Some(width),
+        }
+    }
+    None
+}
@@ -0,0 +1,624 @@
+use std::ops::Range;
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio_util::sync::CancellationToken;
+use zi_text::LineDiff;
+
+use super::*;
+use crate::buffer::Sign;
+use crate::syntax::HighlightName;
+
+const NAMESPACE: &str = "git-signs";
+
+/// How long to wait after an edit (or cursor movement for blame) before running git again.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A contiguous range of lines in a buffer that differs from the git index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub kind: HunkKind,
+    /// The 0-indexed lines of the file in the index.
+    pub base: Range<usize>,
+    /// The 0-indexed lines of the buffer, empty for deletions.
+    pub lines: Range<usize>,
+}
+
+impl Hunk {
+    /// The line the hunk starts on in the buffer, deletions belong to the line above them.
+    pub fn start_line(&self) -> usize {
+        match self.kind {
+            HunkKind::Added | HunkKind::Changed => self.lines.start,
+            HunkKind::Deleted => self.lines.start.saturating_sub(1),
+        }
+    }
+
+    fn contains_line(&self, line: usize) -> bool {
+        self.lines.contains(&line) || self.start_line() == line
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Added,
+    Changed,
+    Deleted,
+}
+
+#[derive(Debug)]
+pub(super) struct GitDiff {
+    /// The contents of the file in the index, with line endings normalized to `\n` like the buffer text.
+    base: Arc<str>,
+    /// The line ending of the file in the index.
+    line_ending: LineEnding,
+    hunks: Vec<Hunk>,
+    /// Bumped on every edit so only the last of a burst of edits results in a diff.
+    generation: u64,
+}
+
+/// Blame annotations of the lines of a buffer, see [`Settings::git_blame`](crate::buffer::Settings).
+#[derive(Debug, Default)]
+pub(super) struct GitBlame {
+    /// The version of the buffer the annotations are for.
+    version: u32,
+    /// The line ranges that have been blamed or are being blamed.
+    requested: Vec<Range<usize>>,
+    lines: HashMap<usize, String>,
+    /// Cancels the requests in flight once the buffer has changed.
+    cancel: CancellationToken,
+    /// Bumped on every edit or cursor movement so only the last of a burst results in a request.
+    generation: u64,
+}
+
+impl Editor {
+    /// The hunks of the buffer that differ from the git index, sorted by line.
+    /// This is empty if the file of the buffer is not tracked by git.
+    pub fn git_hunks(&self, selector: impl Selector<BufferId>) -> &[Hunk] {
+        let buf = selector.select(self);
+        self.git.get(&buf).map_or(&[], |diff| &diff.hunks)
+    }
+
+    /// Move the cursor to the start of the next hunk after the cursor line.
+    pub fn goto_next_hunk(&mut self, selector: impl Selector<ViewId>) -> Option<Hunk> {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        let hunk = self.git_hunks(view).iter().find(|hunk| hunk.start_line() > line)?.clone();
+        self.set_cursor(view, Point::new(hunk.start_line(), 0));
+        Some(hunk)
+    }
+
+    /// Move the cursor to the start of the previous hunk before the cursor line.
+    pub fn goto_prev_hunk(&mut self, selector: impl Selector<ViewId>) -> Option<Hunk> {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        let hunk = self.git_hunks(view).iter().rfind(|hunk| hunk.start_line() < line)?.clone();
+        self.set_cursor(view, Point::new(hunk.start_line(), 0));
+        Some(hunk)
+    }
+
+    fn hunk_at_cursor(&self, view: ViewId) -> Result<(BufferId, Hunk)> {
+        let buf = self[view].buffer();
+        let Some(diff) = self.git.get(&buf) else { bail!("buffer is not tracked by git") };
+        let line = self[view].cursor().line();
+        match diff.hunks.iter().find(|hunk| hunk.contains_line(line)) {
+            Some(hunk) => Ok((buf, hunk.clone())),
+            None => bail!("no hunk at cursor"),
+        }
+    }
+
+    /// The hunk under the cursor as a linewise text object, e.g. `dih`.
+    pub(crate) fn hunk_text_object(&self, selector: impl Selector<ViewId>) -> HunkTextObject {
+        let view = selector.select(self);
+        let line = self[view].cursor().line();
+        let hunk = self.git_hunks(view).iter().find(|hunk| hunk.lines.contains(&line));
+        HunkTextObject { lines: hunk.map(|hunk| hunk.lines.clone()) }
+    }
+
+    /// Write the hunk under the cursor to the git index.
+    pub fn stage_hunk(
+        &mut self,
+        selector: impl Selector<ViewId>,
+    ) -> Result<impl Future<Output = Result<()>> + Send + 'static> {
+        let view = selector.select(self);
+        let (buf, hunk) = self.hunk_at_cursor(view)?;
+        let path = self[buf].file_path().expect("git tracked buffers have a path");
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            bail!("invalid path `{}`", path.display())
+        };
+
+        let GitDiff { base, line_ending, .. } = &self.git[&buf];
+        let text = self[buf].text();
+        let name = name.to_string_lossy();
+        let mut patch = format!("--- a/{name}\n+++ b/{name}\n");
+        // With zero lines of context an empty range refers to the line before it.
+        // The hunk is applied alone so it starts at the same line in the new index contents.
+        let old = hunk.base.start + !hunk.base.is_empty() as usize;
+        let new = hunk.base.start + !hunk.lines.is_empty() as usize;
+        patch.push_str(&format!("@@ -{old},{} +{new},{} @@\n", hunk.base.len(), hunk.lines.len()));
+
+        // The patch is applied to the index as is, so it must use the line endings of the index.
+        let mut push_line = |prefix: char, line: &str| {
+            patch.push(prefix);
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    patch.push_str(line);
+                    patch.push_str(line_ending.as_str());
+                }
+                None => {
+                    patch.push_str(line);
+                    patch.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        };
+        base.split_inclusive('\n')
+            .skip(hunk.base.start)
+            .take(hunk.base.len())
+            .for_each(|line| push_line('-', line));
+        text.byte_slice(byte_range(text, &hunk.lines))
+            .to_cow()
+            .split_inclusive('\n')
+            .for_each(|line| push_line('+', line));
+
+        let dir = dir.to_path_buf();
+        let refresh = self.refresh_git_diff(buf);
+        Ok(async move {
+            let mut child = tokio::process::Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["apply", "--cached", "--unidiff-zero", "-"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            stdin.write_all(patch.as_bytes()).await?;
+            drop(stdin);
+
+            let output = child.wait_with_output().await?;
+            if !output.status.success() {
+                bail!("failed to stage hunk: {}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+
+            refresh.await
+        })
+    }
+
+    /// Replace the hunk under the cursor with the contents of the git index.
+    pub fn reset_hunk(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let (buf, hunk) = self.hunk_at_cursor(view)?;
+        let base = self.git[&buf]
+            .base
+            .split_inclusive('\n')
+            .skip(hunk.base.start)
+            .take(hunk.base.len())
+            .collect::<String>();
+
+        let range = byte_range(self[buf].text(), &hunk.lines);
+        self.edit(buf, &Deltas::new([Delta::new(range, base)]))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, Point::new(hunk.lines.start, 0));
+        Ok(())
+    }
+
+    /// Toggle showing the blame of the cursor line for the buffer.
+    pub fn toggle_git_blame(&mut self, selector: impl Selector<BufferId>) {
+        let buf = selector.select(self);
+        let setting = &self[buf].settings().git_blame;
+        let enabled = *setting.read();
+        setting.write(!enabled);
+        self.damage_all();
+        if self.view(Active).buffer() == buf {
+            self.schedule_git_blame(self.view(Active).id());
+        }
+        request_redraw();
+    }
+
+    /// The blame annotation of `line` of the buffer, if it has been computed.
+    pub fn git_blame(&self, selector: impl Selector<BufferId>, line: usize) -> Option<&str> {
+        let buf = selector.select(self);
+        let blame =
+            self.git_blame.get(&buf).filter(|blame| blame.version == self[buf].version())?;
+        blame.lines.get(&line).map(String::as_str)
+    }
+
+    /// Blame the lines visible in the view if blame is enabled for its buffer and the cursor line
+    /// has not been blamed yet. The buffer contents are blamed, so uncommitted changes are accounted for.
+    pub fn request_git_blame(
+        &mut self,
+        selector: impl Selector<ViewId>,
+    ) -> Option<impl Future<Output = Result<()>> + Send + 'static> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        if !*self[buf].settings().git_blame.read() {
+            return None;
+        }
+
+        let path = self[buf].file_path()?;
+        let version = self[buf].version();
+        let cursor_line = self[view].cursor().line();
+        let blame = self.git_blame.entry(buf).or_default();
+        if blame.version != version {
+            blame.cancel.cancel();
+            *blame = GitBlame { version, generation: blame.generation, ..Default::default() };
+        }
+
+        if blame.requested.iter().any(|lines| lines.contains(&cursor_line)) {
+            return None;
+        }
+
+        let text = self.buffers[buf].text();
+        let start = self.views[view].offset().line.min(cursor_line);
+        let height = self.tree.view_area(view).height as usize;
+        let lines = start..(start + height).max(cursor_line + 1).min(text.len_lines());
+        if lines.is_empty() {
+            return None;
+        }
+
+        blame.requested.push(lines.clone());
+        let cancel = blame.cancel.clone();
+        let contents = text.to_string();
+        let client = self.client();
+        Some(async move {
+            // Dropping the blame kills git.
+            let blame = tokio::select! {
+                () = cancel.cancelled() => return Ok(()),
+                blame = git_blame(&path, contents, lines) => blame,
+            };
+
+            // Not being tracked by git is not worth reporting.
+            let annotations = match blame {
+                Ok(annotations) => annotations,
+                Err(err) => {
+                    tracing::debug!(?err, "git blame failed");
+                    return Ok(());
+                }
+            };
+            client
+                .with(move |editor| {
+                    if let Some(blame) =
+                        editor.git_blame.get_mut(&buf).filter(|blame| blame.version == version)
+                    {
+                        blame.lines.extend(annotations);
+                        request_redraw();
+                    }
+                })
+                .await;
+            Ok(())
+        })
+    }
+
+    /// Schedule a blame of the lines around the cursor of the view once the cursor stops moving
+    /// and the buffer stops changing. A request in flight for an older version of the buffer is cancelled.
+    pub(super) fn schedule_git_blame(&mut self, view: ViewId) {
+        let buf = self[view].buffer();
+        if !*self[buf].settings().git_blame.read() {
+            return;
+        }
+
+        let version = self[buf].version();
+        let blame = self.git_blame.entry(buf).or_default();
+        if blame.version != version {
+            blame.cancel.cancel();
+        }
+        blame.generation += 1;
+        let generation = blame.generation;
+
+        let client = self.client();
+        self.spawn("git blame", async move {
+            tokio::time::sleep(DEBOUNCE).await;
+            let request = client
+                .with(move |editor| {
+                    editor.git_blame.get(&buf).filter(|blame| blame.generation == generation)?;
+                    if !editor.views.contains_key(view) {
+                        return None;
+                    }
+                    editor.request_git_blame(view)
+                })
+                .await;
+
+            match request {
+                Some(request) => request.await,
+                None => Ok(()),
+            }
+        });
+    }
+
+    /// Reload the contents of the buffer's file from the git index and diff the buffer against it.
+    pub fn refresh_git_diff(
+        &mut self,
+        selector: impl Selector<BufferId>,
+    ) -> impl Future<Output = Result<()>> + Send + 'static {
+        let buf = selector.select(self);
+        let path = self[buf].file_path();
+        let client = self.client();
+        async move {
+            let Some(path) = path else { return Ok(()) };
+            let base = git_index_contents(&path).await?.map(|base| normalize_line_endings(&base));
+            let diff = client
+                .with(move |editor| {
+                    let Some((base, line_ending)) = base else {
+                        if editor.git.remove(&buf).is_some() {
+                            let ns = editor.create_namespace(NAMESPACE);
+                            editor[buf].clear_marks(ns, ..);
+                        }
+                        return None;
+                    };
+
+                    editor
+                        .git
+                        .entry(buf)
+                        .and_modify(|diff| {
+                            diff.base = base.as_str().into();
+                            diff.line_ending = line_ending;
+                        })
+                        .or_insert_with(|| GitDiff {
+                            base: base.as_str().into(),
+                            line_ending,
+                            hunks: vec![],
+                            generation: 0,
+                        });
+                    editor.diff_git(buf, Duration::ZERO)
+                })
+                .await;
+
+            if let Some(diff) = diff {
+                diff.await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Schedule a diff of the buffer against its git base once it stops changing.
+    pub(super) fn schedule_git_diff(&mut self, buf: BufferId) {
+        if let Some(fut) = self.diff_git(buf, DEBOUNCE) {
+            self.spawn("git diff", fut);
+        }
+    }
+
+    /// Returns a future that diffs the buffer against its git base after `delay`,
+    /// unless the buffer is changed again in the meantime.
+    fn diff_git(
+        &mut self,
+        buf: BufferId,
+        delay: Duration,
+    ) -> Option<impl Future<Output = Result<()>> + Send + 'static> {
+        let diff = self.git.get_mut(&buf)?;
+        diff.generation += 1;
+        let generation = diff.generation;
+        let client = self.client();
+        Some(async move {
+            tokio::time::sleep(delay).await;
+
+            let snapshot = client
+                .with(move |editor| {
+                    let diff = editor.git.get(&buf).filter(|diff| diff.generation == generation)?;
+                    let buffer = &editor[buf];
+                    Some((Arc::clone(&diff.base), buffer.text().to_string(), buffer.version()))
+                })
+                .await;
+            let Some((base, text, version)) = snapshot else { return Ok(()) };
+
+            let hunks = diff_lines(&base, &text);
+            client
+                .with(move |editor| {
+                    if editor[buf].version() == version {
+                        editor.set_git_hunks(buf, hunks);
+                    }
+                })
+                .await;
+            Ok(())
+        })
+    }
+
+    fn set_git_hunks(&mut self, buf: BufferId, hunks: Vec<Hunk>) {
+        let ns = self.create_namespace(NAMESPACE);
+        let sign = |name: HighlightName, text| Sign { text, hl: self.highlight_id_by_name(name) };
+        let added = sign(HighlightName::GIT_ADDED, '┃');
+        let changed = sign(HighlightName::GIT_CHANGED, '┃');
+        let deleted = sign(HighlightName::GIT_DELETED, '_');
+        let top_deleted = sign(HighlightName::GIT_DELETED, '‾');
+
+        let text = self[buf].text();
+        let marks = hunks
+            .iter()
+            .flat_map(|hunk| {
+                let (lines, sign) = match hunk.kind {
+                    HunkKind::Added => (hunk.lines.clone(), added),
+                    HunkKind::Changed => (hunk.lines.clone(), changed),
+                    // Deletions have no lines of their own, mark the line above (or the first line).
+                    HunkKind::Deleted => match hunk.lines.start.checked_sub(1) {
+                        Some(line) => (line..line + 1, deleted),
+                        None => (0..1, top_deleted),
+                    },
+                };
+                lines.map(move |line| (line, sign))
+            })
+            .map(|(line, sign)| {
+                let byte = text.try_line_to_byte(line).unwrap_or_else(|| text.len_bytes());
+                Mark::builder(byte).sign(sign)
+            })
+            .collect::<Vec<_>>();
+
+        self[buf].replace_marks(ns, marks);
+        if let Some(diff) = self.git.get_mut(&buf) {
+            diff.hunks = hunks;
+        }
+        request_redraw();
+    }
+}
+
+pub(crate) struct HunkTextObject {
+    lines: Option<Range<usize>>,
+}
+
+impl TextObject for HunkTextObject {
+    fn byte_range(&self, text: &dyn AnyText, _byte: usize) -> Option<Range<usize>> {
+        Some(byte_range(text, self.lines.as_ref()?))
+    }
+
+    fn default_kind(&self) -> TextObjectKind {
+        TextObjectKind::Linewise
+    }
+}
+
+pub(super) fn byte_range(text: &dyn AnyText, lines: &Range<usize>) -> Range<usize> {
+    let byte = |line| text.try_line_to_byte(line).unwrap_or_else(|| text.len_bytes());
+    byte(lines.start)..byte(lines.end)
+}
+
+/// Compute the hunks of `text` that differ from `base` line by line.
+fn diff_lines(base: &str, text: &str) -> Vec<Hunk> {
+    zi_text::diff_lines(base, text)
+        .into_iter()
+        .map(|LineDiff { old: base, new: lines }| {
+            let kind = match (base.is_empty(), lines.is_empty()) {
+                (true, _) => HunkKind::Added,
+                (_, true) => HunkKind::Deleted,
+                _ => HunkKind::Changed,
+            };
+            Hunk { kind, base, lines }
+        })
+        .collect()
+}
+
+/// Normalize the line endings of the index contents to `\n`, returning the detected line ending.
+/// Otherwise a file checked out with different line endings (e.g. with `core.autocrlf`) differs on every line.
+fn normalize_line_endings(contents: &str) -> (String, LineEnding) {
+    let mut normalizer = LineEndingNormalizer::default();
+    let mut normalized = String::with_capacity(contents.len());
+    normalizer.normalize(contents, |s| normalized.push_str(s));
+    (normalized, normalizer.finish().unwrap_or_default())
+}
+
+/// Blame `lines` of `contents` as the contents of the file at `path`.
+async fn git_blame(
+    path: &Path,
+    contents: String,
+    lines: Range<usize>,
+) -> Result<HashMap<usize, String>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        bail!("invalid path `{}`", path.display())
+    };
+
+    let mut child = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["blame", "--porcelain", "--contents", "-"])
+        .arg(format!("-L{},{}", lines.start + 1, lines.end))
+        .arg("--")
+        .arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let write = async move {
+        let _ = stdin.write_all(contents.as_bytes()).await;
+    };
+    let ((), output) = tokio::join!(write, child.wait_with_output());
+    let output = output?;
+    if !output.status.success() {
+        bail!("git blame failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+    Ok(parse_blame(&String::from_utf8_lossy(&output.stdout), now))
+}
+
+/// Parse the output of `git blame --porcelain` into annotations for each (0-indexed) line.
+fn parse_blame(porcelain: &str, now: u64) -> HashMap<usize, String> {
+    #[derive(Default)]
+    struct Commit<'a> {
+        author: &'a str,
+        time: u64,
+        summary: &'a str,
+    }
+
+    let mut commits = HashMap::<&str, Commit<'_>>::default();
+    let mut annotations = HashMap::default();
+    // The commit and line of the entry being parsed.
+    let mut entry = None;
+    for line in porcelain.lines() {
+        let Some((sha, line)) = entry else {
+            // Each entry starts with `<sha> <original line> <final line> [<lines in group>]`.
+            let mut parts = line.split(' ');
+            let (Some(sha), Some(line)) = (parts.next(), parts.nth(1)) else { continue };
+            let Ok(line) = line.parse::<usize>() else { continue };
+            commits.entry(sha).or_default();
+            entry = Some((sha, line));
+            continue;
+        };
+
+        // The contents of the line ends the entry.
+        if line.starts_with('\t') {
+            let commit = &commits[sha];
+            let annotation = if sha.bytes().all(|b| b == b'0') {
+                "Not committed yet".to_string()
+            } else {
+                let age = relative_time(now.saturating_sub(commit.time));
+                format!("{}, {age} • {}", commit.author, commit.summary)
+            };
+            annotations.insert(line - 1, annotation);
+            entry = None;
+            continue;
+        }
+
+        let commit = commits.get_mut(sha).expect("inserted with the header");
+        match line.split_once(' ') {
+            Some(("author", author)) => commit.author = author,
+            Some(("author-time", time)) => commit.time = time.parse().unwrap_or_default(),
+            Some(("summary", summary)) => commit.summary = summary,
+            _ => {}
+        }
+    }
+
+    annotations
+}
+
+fn relative_time(secs: u64) -> String {
+    const UNITS: [(u64, &str); 6] = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    for (size, unit) in UNITS {
+        let n = secs / size;
+        if n > 0 {
+            return format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" });
+        }
+    }
+
+    "just now".to_string()
+}
+
+/// Read the contents of `path` in the git index, returns `None` if the file is not tracked.
+async fn git_index_contents(path: &Path) -> Result<Option<String>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else { return Ok(None) };
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!(":./{}", name.to_string_lossy()))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => Ok(String::from_utf8(output.stdout).ok()),
+        Ok(_) => Ok(None),
+        // Not having git installed is not an error.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+use super::*;
+use crate::Mark;
+use crate::command::{CommandRange, Commands};
+
+const NAMESPACE: &str = "global";
+
+impl Editor {
+    /// Returns a future that runs the commands with the cursor on each line in the range that
+    /// matches the pattern (or doesn't if `invert`), `:g/pattern/cmd` and `:v/pattern/cmd`.
+    /// The lines are tracked as the commands edit the buffer, lines deleted by an earlier command
+    /// are skipped. All the changes are undone together.
+    pub(crate) fn global(
+        &mut self,
+        range: Option<&CommandRange>,
+        pattern: &str,
+        invert: bool,
+        cmds: Commands,
+    ) -> Result<impl Future<Output = Result<()>> + Send + 'static> {
+        use zi_text::search::{Regex, Search};
+
+        if cmds.iter().next().is_none() {
+            bail!("missing command");
+        }
+
+        if cmds.iter().any(|cmd| matches!(cmd.kind(), CommandKind::Global { .. })) {
+            bail!("cannot nest `:global`");
+        }
+
+        if pattern.is_empty() {
+            bail!("empty pattern");
+        }
+
+        let search = Search::new(Regex::new(pattern)?);
+        let (view, buf) = self.get(Active);
+        let lines = match range {
+            Some(range) => self.resolve_range(view, range)?,
+            None => 0..self[buf].text().len_lines(),
+        };
+
+        let text = self[buf].text();
+        let len_bytes = text.len_bytes();
+        let byte = |line| text.try_line_to_byte(line).unwrap_or(len_bytes);
+        // Mark each line including its newline, so a mark is empty once its line is deleted.
+        let marks = lines
+            .filter(|&line| text.line(line).is_some_and(|slice| search.is_match(slice)) != invert)
+            .map(|line| Mark::builder(byte(line)).width(byte(line + 1) - byte(line)))
+            .collect::<Vec<_>>();
+
+        if marks.is_empty() {
+            bail!("pattern not found: `{pattern}`");
+        }
+
+        let ns = self.create_namespace(NAMESPACE);
+        self[buf].replace_marks(ns, marks);
+        self[buf].start_undo_group();
+
+        let client = self.client();
+        Ok(async move {
+            let res = async {
+                while let Some(line) =
+                    client.with(move |editor| editor.next_global_line(buf)).await?
+                {
+                    client.with(move |editor| editor.set_cursor(Active, Point::new(line, 0))).await;
+                    for cmd in cmds.iter().cloned() {
+                        client.with(move |editor| editor.command_future(cmd)).await?.await?;
+                    }
+                }
+                Ok(())
+            }
+            .await;
+
+            client
+                .with(move |editor| {
+                    if editor.buffers.contains_key(buf) {
+                        editor[buf].clear_marks(ns, ..);
+                        editor[buf].end_undo_group();
+                    }
+                })
+                .await;
+            res
+        })
+    }
+
+    /// Remove the first remaining line marked by `:global` and return it, skipping deleted lines.
+    fn next_global_line(&mut self, buf: BufferId) -> Result<Option<usize>> {
+        if self[Active].buffer() != buf {
+            bail!("`:global` cannot switch buffers");
+        }
+
+        let ns = self.create_namespace(NAMESPACE);
+        loop {
+            let Some((id, range)) = self[buf]
+                .marks(..)
+                .find(|(mark_ns, ..)| *mark_ns == ns)
+                .map(|(_, range, mark)| (mark.id(), range))
+            else {
+                return Ok(None);
+            };
+
+            self[buf].delete_mark(ns, id);
+            let text = self[buf].text();
+            if !is_deleted(&range, text.len_bytes()) {
+                return Ok(Some(text.byte_to_line(range.start)));
+            }
+        }
+    }
+}
+
+/// Whether the line of a `:global` mark has been deleted.
+/// An empty last line has an empty mark to begin with, so it is never considered deleted.
+fn is_deleted(range: &Range<usize>, len_bytes: usize) -> bool {
+    range.is_empty() && range.start < len_bytes
+}
@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use super::*;
+
+/// The shape of the cursor and whether it blinks in each mode, `:set guicursor`.
+///
+/// A comma separated list of `{modes}:{args}` where `{modes}` is a dash separated list of `n`
+/// (normal), `v` (visual), `i` (insert), `c` (command), `o` (operator pending), `r` (replace
+/// pending) or `a` (all modes), and `{args}` is a dash separated list of `block`, `ver{n}` (a bar),
+/// `hor{n}` (an underline) and `blinkon{n}` which blinks the cursor unless `n` is 0. The size `n`
+/// of bars and underlines is accepted for compatibility but it is up to the frontend. Later parts
+/// override earlier ones, e.g. `a:block-blinkon0,i-c:ver25-blinkon500`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuiCursor {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Part {
+    modes: Vec<char>,
+    shape: Option<tui::CursorShape>,
+    blink: Option<bool>,
+}
+
+impl GuiCursor {
+    /// The style of the cursor in `mode`, a steady block if no part applies to it.
+    pub fn style(&self, mode: Mode) -> tui::CursorStyle {
+        let name = match mode {
+            Mode::Normal => 'n',
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => 'v',
+            Mode::Insert => 'i',
+            Mode::Command => 'c',
+            Mode::OperatorPending(..) => 'o',
+            Mode::ReplacePending => 'r',
+        };
+
+        let mut style = tui::CursorStyle::default();
+        for part in &self.parts {
+            if part.modes.iter().any(|&m| m == name || m == 'a') {
+                style.shape = part.shape.unwrap_or(style.shape);
+                style.blink = part.blink.unwrap_or(style.blink);
+            }
+        }
+        style
+    }
+}
+
+impl Default for GuiCursor {
+    fn default() -> Self {
+        "n-v:block,i-c:ver25,o-r:hor20".parse().unwrap()
+    }
+}
+
+impl FromStr for GuiCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(',').filter(|part| !part.is_empty()).map(parse_part);
+        Ok(Self { parts: parts.collect::<Result<_>>()? })
+    }
+}
+
+fn parse_part(s: &str) -> Result<Part> {
+    let Some((modes, args)) = s.split_once(':') else {
+        bail!("expected `{{modes}}:{{args}}` in guicursor: `{s}`")
+    };
+
+    let modes = modes
+        .split('-')
+        .map(|mode| match mode {
+            "n" | "v" | "i" | "c" | "o" | "r" | "a" => Ok(mode.chars().next().unwrap()),
+            _ => bail!("unknown guicursor mode: `{mode}`"),
+        })
+        .collect::<Result<_>>()?;
+
+    let mut part = Part { modes, shape: None, blink: None };
+    for arg in args.split('-') {
+        if arg == "block" {
+            part.shape = Some(tui::CursorShape::Block);
+        } else if let Some(n) = arg.strip_prefix("ver") {
+            parse_size(n)?;
+            part.shape = Some(tui::CursorShape::Bar);
+        } else if let Some(n) = arg.strip_prefix("hor") {
+            parse_size(n)?;
+            part.shape = Some(tui::CursorShape::Underline);
+        } else if let Some(n) = arg.strip_prefix("blinkon") {
+            let n = n.parse::<u32>().map_err(|_| anyhow!("invalid blinkon: `{arg}`"))?;
+            part.blink = Some(n > 0);
+        } else {
+            bail!("unknown guicursor argument: `{arg}`");
+        }
+    }
+
+    Ok(part)
+}
+
+/// The percentage of the cell a bar or underline covers, which may be omitted.
+fn parse_size(n: &str) -> Result<()> {
+    match n {
+        "" => Ok(()),
+        _ => match n.parse::<u8>() {
+            Ok(1..=100) => Ok(()),
+            _ => bail!("cursor size must be between 1 and 100: `{n}`"),
+        },
+    }
+}
@@ -0,0 +1,122 @@
+use super::*;
+use crate::{Commands, dirs};
+
+impl Editor {
+    /// Open a readonly buffer reporting the health of the editor's environment.
+    pub fn checkhealth(&mut self) {
+        let report = self.health_report();
+        let buf = self.create_readonly_buffer("health", report.into_bytes());
+        self.set_buffer(Active, buf);
+    }
+
+    /// Open a readonly buffer describing the detected capabilities of the terminal.
+    /// This is the terminal section of the health report on its own.
+    pub fn open_capabilities(&mut self) {
+        let mut report = String::new();
+        self.write_terminal_health(&mut report).expect("writing to a string is infallible");
+        let buf = self.create_readonly_buffer("capabilities", report.into_bytes());
+        self.set_buffer(Active, buf);
+    }
+
+    pub fn health_report(&self) -> String {
+        let mut report = String::new();
+        self.write_health_report(&mut report).expect("writing to a string is infallible");
+        report
+    }
+
+    fn write_health_report(&self, r: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(r, "language services")?;
+        if self.language_config.language_services.is_empty() {
+            writeln!(r, "  none configured")?;
+        }
+        for (id, config) in &self.language_config.language_services {
+            let Some(command) = config.command() else {
+                writeln!(r, "  {id}: ok")?;
+                continue;
+            };
+
+            match which(command.as_ref()) {
+                Some(path) => writeln!(r, "  {id}: ok ({})", path.display())?,
+                None => writeln!(r, "  {id}: error: `{}` not found", command.to_string_lossy())?,
+            }
+        }
+
+        writeln!(r, "\ngrammars")?;
+        for ft in FileType::known().iter() {
+            let dir = dirs::grammar().join(ft);
+            let missing = ["language.wasm", "highlights.scm"]
+                .into_iter()
+                .filter(|file| !dir.join(file).exists())
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                writeln!(r, "  {ft}: ok")?;
+            } else {
+                writeln!(r, "  {ft}: missing {} in {}", missing.join(", "), dir.display())?;
+            }
+        }
+
+        writeln!(r, "\nterminal")?;
+        self.write_terminal_health(r)?;
+
+        writeln!(r, "\nclipboard")?;
+        writeln!(r, "  provider: {}", self.clipboard_provider())?;
+        match *self.settings.clipboard.read() {
+            Some(selection) => {
+                writeln!(r, "  unnamed register: synced with `{}`", selection.register())?
+            }
+            None => writeln!(r, "  unnamed register: not synced")?,
+        }
+
+        writeln!(r, "\nconfig")?;
+        let init_path = dirs::config().join("init.zi");
+        match std::fs::read_to_string(&init_path) {
+            Ok(config) => match config.parse::<Commands>() {
+                Ok(_) => writeln!(r, "  {}: ok", init_path.display())?,
+                Err(err) => writeln!(r, "  {}: error: {err}", init_path.display())?,
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                writeln!(r, "  {}: not found", init_path.display())?
+            }
+            Err(err) => writeln!(r, "  {}: error: {err}", init_path.display())?,
+        }
+
+        writeln!(r, "\nplugins")?;
+        for name in self.plugin_managers.keys() {
+            writeln!(r, "  {name}: registered")?;
+        }
+        if self.plugin_errors.is_empty() {
+            writeln!(r, "  no errors")?;
+        }
+        for err in &self.plugin_errors {
+            writeln!(r, "  error: {err}")?;
+        }
+
+        writeln!(r, "\nstartup")?;
+        if self.startup.phases().is_empty() {
+            writeln!(r, "  not recorded")?;
+        }
+        for phase in self.startup.phases() {
+            let ms = phase.duration.as_secs_f64() * 1000.0;
+            writeln!(r, "  {}: {ms:.3}ms", phase.name)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_terminal_health(&self, r: &mut impl fmt::Write) -> fmt::Result {
+        for (name, supported) in self.capabilities.iter() {
+            writeln!(r, "  {name}: {}", if supported { "yes" } else { "no" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the executable `command` in `$PATH` (or directly if it is a path).
+fn which(command: &Path) -> Option<PathBuf> {
+    if command.components().count() > 1 {
+        return command.is_file().then(|| command.to_path_buf());
+    }
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).map(|dir| dir.join(command)).find(|path| path.is_file())
+}
@@ -0,0 +1,36 @@
+use super::*;
+
+impl Editor {
+    /// Open a readonly buffer listing the available commands.
+    /// It is placed according to the rule for [`BufferKind::Help`], by default in a split above.
+    pub fn open_help(&mut self) -> ViewId {
+        let mut help = String::new();
+        self.write_help(&mut help).expect("writing to a string is infallible");
+        let buf = self.create_readonly_buffer("help", help.into_bytes());
+        self.open_placed(
+            BufferKind::Help,
+            Placement::Dock { edge: Direction::Up, size: tui::Constraint::Percentage(50) },
+            buf,
+        )
+    }
+
+    fn write_help(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        let mut handlers = self.command_handlers.values().collect::<Vec<_>>();
+        handlers.sort_by_cached_key(|handler| handler.name().to_string());
+
+        writeln!(w, "commands")?;
+        for handler in handlers {
+            let arity = handler.arity();
+            write!(w, "  :{}", handler.name())?;
+            if handler.flags().contains(CommandFlags::RANGE) {
+                write!(w, " [range]")?;
+            }
+            match (arity.min, arity.max) {
+                (0, 0) => writeln!(w)?,
+                (min, max) if min == max => writeln!(w, " ({min} args)")?,
+                (min, max) => writeln!(w, " ({min} to {max} args)")?,
+            }
+        }
+        Ok(())
+    }
+}
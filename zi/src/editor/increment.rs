@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+use super::*;
+
+impl Editor {
+    /// Add `n` to the first number at or after the cursor on its line, `<C-a>` and `<C-x>`.
+    /// Decimal numbers may be negative, hexadecimal (`0x`) and binary (`0b`) numbers are unsigned
+    /// and wrap around. The cursor is moved to the last character of the number.
+    pub fn increment(&mut self, selector: impl Selector<ViewId>, n: i64) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let cursor = self[view].cursor();
+        let Some(range) = self.increment_line(buf, cursor.line(), cursor.col(), n)? else {
+            return Ok(());
+        };
+
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, range.end - 1);
+        Ok(())
+    }
+
+    /// Add `n` to the first number of each line of the visual selection, `<C-a>` and `<C-x>` in
+    /// visual mode. If `progressive`, each number is changed by `n` more than the previous one,
+    /// `g<C-a>` and `g<C-x>`.
+    pub fn visual_increment(
+        &mut self,
+        selector: impl Selector<ViewId> + Copy,
+        n: i64,
+        progressive: bool,
+    ) -> Result<(), EditError> {
+        let Some(sel) = self.visual_selection(selector) else { return Ok(()) };
+        let (view, buf) = self.get(selector);
+        self.set_mode(Mode::Normal);
+
+        let mut step = n;
+        for line in sel.lines() {
+            let col = match sel {
+                visual::Selection::Charwise { start, .. } if line == start.line() => start.col(),
+                visual::Selection::Block { start_col, .. } => start_col,
+                _ => 0,
+            };
+            if self.increment_line(buf, line, col, step)?.is_some() && progressive {
+                step = step.saturating_add(n);
+            }
+        }
+
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, sel.start_point());
+        Ok(())
+    }
+
+    /// Add `n` to the first number of `line` that ends after byte `col`.
+    /// Returns the byte range of the new number if there was one.
+    fn increment_line(
+        &mut self,
+        buf: BufferId,
+        line: usize,
+        col: usize,
+        n: i64,
+    ) -> Result<Option<Range<usize>>, EditError> {
+        let text = self[buf].text();
+        let Some(content) = text.line(line).map(|line| line.to_cow()) else { return Ok(None) };
+        let Some(number) = find_number(&content, col) else { return Ok(None) };
+        let Some(new) = number.add(&content[number.range.clone()], n) else { return Ok(None) };
+
+        let start = text.line_to_byte(line) + number.range.start;
+        let range = start..start + number.range.len();
+        let end = start + new.len();
+        self.edit(buf, &Deltas::new([Delta::new(range, new)]))?;
+        Ok(Some(start..end))
+    }
+}
+
+/// A number within a line.
+#[derive(Debug, PartialEq, Eq)]
+struct Number {
+    /// The byte range of the number including its sign or radix prefix.
+    range: Range<usize>,
+    radix: u32,
+}
+
+impl Number {
+    /// The text of the number `text` plus `n`. Leading zeros and the case of hexadecimal digits
+    /// are preserved. Returns `None` if the number is too large.
+    fn add(&self, text: &str, n: i64) -> Option<String> {
+        if self.radix == 10 {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => (-1, digits),
+                None => (1, text),
+            };
+            let value = sign * digits.parse::<i128>().ok()?;
+            let value = value.checked_add(n.into())?;
+            let width = if digits.len() > 1 && digits.starts_with('0') { digits.len() } else { 0 };
+            let sign = if value < 0 { "-" } else { "" };
+            return Some(format!("{sign}{:0width$}", value.unsigned_abs()));
+        }
+
+        let (prefix, digits) = text.split_at(2);
+        let value = u64::from_str_radix(digits, self.radix).ok()?.wrapping_add_signed(n);
+        let width = digits.len();
+        let digits = match self.radix {
+            2 => format!("{value:0width$b}"),
+            _ if digits.chars().any(|c| c.is_ascii_uppercase()) => format!("{value:0width$X}"),
+            _ => format!("{value:0width$x}"),
+        };
+        Some(format!("{prefix}{digits}"))
+    }
+}
+
+/// The first number in `line` that ends after byte `col`.
+fn find_number(line: &str, col: usize) -> Option<Number> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let (radix, digits) = match bytes[i..] {
+            [b'0', b'x' | b'X', c, ..] if c.is_ascii_hexdigit() => (16, i + 2),
+            [b'0', b'b' | b'B', b'0' | b'1', ..] => (2, i + 2),
+            _ => (10, i),
+        };
+        let end =
+            digits + bytes[digits..].iter().take_while(|&&b| char::from(b).is_digit(radix)).count();
+        let start = if radix == 10 && i > 0 && bytes[i - 1] == b'-' { i - 1 } else { i };
+        if end > col {
+            return Some(Number { range: start..end, radix });
+        }
+        i = end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_and_add() {
+        let add = |line: &str, col: usize, n: i64| {
+            let number = find_number(line, col)?;
+            let new = number.add(&line[number.range.clone()], n)?;
+            Some(format!("{}{new}{}", &line[..number.range.start], &line[number.range.end..]))
+        };
+
+        assert_eq!(add("x = 9;", 0, 1).as_deref(), Some("x = 10;"));
+        assert_eq!(add("x = 9;", 5, 1), None);
+        assert_eq!(add("a1 b2", 2, 3).as_deref(), Some("a1 b5"));
+        assert_eq!(add("-1", 1, 3).as_deref(), Some("2"));
+        assert_eq!(add("1", 0, -3).as_deref(), Some("-2"));
+        assert_eq!(add("007", 0, 1).as_deref(), Some("008"));
+        assert_eq!(add("0xff", 1, 1).as_deref(), Some("0x100"));
+        assert_eq!(add("0x0F", 0, 1).as_deref(), Some("0x10"));
+        assert_eq!(add("0x00", 0, -1).as_deref(), Some("0xffffffffffffffff"));
+        assert_eq!(add("0b0111", 0, 1).as_deref(), Some("0b1000"));
+        assert_eq!(add("0b2", 0, 1).as_deref(), Some("1b2"));
+    }
+}
@@ -0,0 +1,94 @@
+use std::ops::Range;
+
+use super::*;
+use crate::view::SetCursorFlags;
+
+impl Editor {
+    /// Recompute the indentation of `lines` with the language's indents query, falling back to
+    /// heuristics if there is none, `=`. Lines containing only whitespace are cleared.
+    pub fn reindent(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        lines: Range<usize>,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        // Reindenting only changes whitespace, so the tree need not be parsed again for each line.
+        self[buf].reparse();
+        // Each line is indented relative to the already reindented lines above it.
+        for line in lines.clone() {
+            let text = self[buf].text();
+            let Some(content) = text.line(line) else { break };
+            let indent = if content.chars().all(char::is_whitespace) {
+                String::new()
+            } else {
+                self.indentation(buf, self.indent_width(buf, line))
+            };
+            let start = text.line_to_byte(line);
+            let range = start..start + content.indent();
+            if text.byte_slice(range.clone()).to_cow() != indent {
+                self.edit(buf, &Deltas::new([Delta::new(range, indent)]))?;
+            }
+        }
+
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor_flags(view, Point::new(lines.start, 0), SetCursorFlags::START_OF_LINE);
+        Ok(())
+    }
+
+    /// Convert the indentation of `lines` to tabs or spaces according to the buffer's indent
+    /// setting, `:retab`. A tab is `tab_width` columns wide.
+    pub fn retab(
+        &mut self,
+        selector: impl Selector<BufferId>,
+        lines: Range<usize>,
+    ) -> Result<(), EditError> {
+        let buf = selector.select(self);
+        let tab_width = *self[buf].settings().tab_width.read();
+        for line in lines {
+            let text = self[buf].text();
+            let Some(content) = text.line(line) else { break };
+            let indent = self.indentation(buf, columns(&content.to_cow(), tab_width));
+            let start = text.line_to_byte(line);
+            let range = start..start + content.indent();
+            if text.byte_slice(range.clone()).to_cow() != indent {
+                self.edit(buf, &Deltas::new([Delta::new(range, indent)]))?;
+            }
+        }
+
+        self[buf].snapshot(SnapshotFlags::empty());
+        Ok(())
+    }
+
+    /// The width in columns `line` should be indented by.
+    fn indent_width(&self, buf: BufferId, line: usize) -> usize {
+        let text = self[buf].text();
+        let tab_width = *self[buf].settings().tab_width.read();
+        let level = self[buf]
+            .syntax()
+            .and_then(|syntax| syntax.indent(&mut tree_sitter::QueryCursor::new(), text, line));
+        match level {
+            Some(level) => level * tab_width as usize,
+            None => match zi_indent::reindent(zi_indent::Config { tab_width }, text, line) {
+                Indent::Bytes(bytes) => bytes,
+            },
+        }
+    }
+
+    /// The whitespace for an indent of `width` columns, using tabs as much as possible if the
+    /// buffer is indented with tabs.
+    pub(super) fn indentation(&self, buf: BufferId, width: usize) -> String {
+        match *self[buf].settings().indent.read() {
+            IndentSettings::Spaces(_) => " ".repeat(width),
+            IndentSettings::Tabs => {
+                let tab_width = (*self[buf].settings().tab_width.read()).max(1) as usize;
+                format!("{}{}", "\t".repeat(width / tab_width), " ".repeat(width % tab_width))
+            }
+        }
+    }
+}
+
+/// The width in columns of the indentation of `line`, a tab is `tab_width` columns wide.
+pub(super) fn columns(line: &str, tab_width: u8) -> usize {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    line.byte_to_display_col(indent, tab_width)
+}
@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use super::*;
+
+impl Editor {
+    /// Join `lines` into a single line, `J`. The indentation of the joined lines is removed and
+    /// they're separated by a space, unless the line already ends with whitespace or the joined
+    /// text is empty or starts with `)`. If the lines are comments, the comment leaders of the
+    /// joined lines are removed too. If `raw`, the lines are joined as is, `gJ`.
+    /// The cursor is moved to where the last line was joined.
+    pub fn join_lines(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        lines: Range<usize>,
+        raw: bool,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let leader = if raw {
+            None
+        } else {
+            self.comment_tokens(self[buf].file_type()).and_then(|tokens| tokens.line)
+        };
+
+        let text = self[buf].text();
+        let Some(first) = text.line(lines.start).map(|line| line.to_cow()) else { return Ok(()) };
+        let leader = leader.filter(|leader| first.trim_start().starts_with(leader.trim_end()));
+        let mut tail = first.chars().next_back();
+
+        let mut deltas = vec![];
+        // The difference in length between the new text and the old text of the deltas so far.
+        let mut shift = 0isize;
+        let mut join_point = None;
+        for line in lines.start + 1..lines.end {
+            let Some(next) = text.line(line).map(|line| line.to_cow()) else { break };
+            let end = text.line_to_byte(line - 1)
+                + text.line(line - 1).map_or(0, |line| line.len_bytes());
+            let start = text.line_to_byte(line);
+
+            let (strip, separator) = if raw {
+                (0, "")
+            } else {
+                let mut rest = next.trim_start();
+                if let Some(leader) = &leader {
+                    if let Some(uncommented) = rest.strip_prefix(leader.trim_end()) {
+                        rest = uncommented.trim_start();
+                    }
+                }
+
+                let separator = match tail {
+                    Some(c) if !c.is_whitespace() && !rest.is_empty() && !rest.starts_with(')') => {
+                        " "
+                    }
+                    _ => "",
+                };
+                (next.len() - rest.len(), separator)
+            };
+
+            let rest = &next[strip..];
+            tail = rest.chars().next_back().or(tail);
+            join_point = Some(end.saturating_add_signed(shift));
+            shift += separator.len() as isize - (start + strip - end) as isize;
+            deltas.push(Delta::new(end..start + strip, separator));
+        }
+
+        let Some(join_point) = join_point else { return Ok(()) };
+        self.edit(buf, &Deltas::new(deltas))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, join_point);
+        Ok(())
+    }
+
+    /// Delete `lines`, `:d`. The cursor is moved to the line after them.
+    pub fn delete_lines(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        lines: Range<usize>,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let text = self[buf].text();
+        let len_bytes = text.len_bytes();
+        let byte = |line| text.try_line_to_byte(line).unwrap_or(len_bytes);
+        let (mut start, end) = (byte(lines.start), byte(lines.end));
+        if start == end {
+            return Ok(());
+        }
+
+        // Deleting the last line without a trailing newline also deletes the newline before it.
+        if end == len_bytes && text.get_char(len_bytes - 1) != Some('\n') {
+            start = start.saturating_sub(1);
+        }
+
+        self.edit(buf, &Deltas::delete(start..end))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        let line = lines.start.min(self[buf].text().len_lines().saturating_sub(1));
+        self.set_cursor(view, Point::new(line, 0));
+        Ok(())
+    }
+
+    /// Join the lines of the visual selection, at least two, `J` and `gJ` in visual mode.
+    pub fn visual_join_lines(
+        &mut self,
+        selector: impl Selector<ViewId> + Copy,
+        raw: bool,
+    ) -> Result<(), EditError> {
+        let Some(sel) = self.visual_selection(selector) else { return Ok(()) };
+        let lines = sel.lines();
+        self.set_mode(Mode::Normal);
+        let start = *lines.start();
+        self.join_lines(selector, start..(*lines.end() + 1).max(start + 2), raw)
+    }
+}
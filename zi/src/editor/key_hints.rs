@@ -0,0 +1,26 @@
+use super::*;
+
+impl Editor {
+    /// The keys that continue the partially entered key sequence and what they do. These are only
+    /// available once the sequence has been pending for `keyhintsdelay`.
+    pub fn key_hints(&self) -> &[(KeyEvent, String)] {
+        &self.key_hints
+    }
+
+    /// Collect the continuations of the current key sequence from the global keymap and the
+    /// keymap of the active buffer, which takes precedence.
+    pub(super) fn update_key_hints(&mut self) {
+        let mode = mode!(self);
+        let (_, buf) = get!(self);
+        let describe = |(key, mapping): (&KeyEvent, Option<&Mapping>)| {
+            (key.clone(), mapping.map_or_else(|| "+prefix".to_string(), Mapping::description))
+        };
+
+        let mut hints =
+            self.keymap.continuations(&mode).into_iter().map(describe).collect::<BTreeMap<_, _>>();
+        if let Some(keymap) = buf.keymap() {
+            hints.extend(keymap.continuations(&mode).into_iter().map(describe));
+        }
+        self.key_hints = hints.into_iter().collect();
+    }
+}
@@ -177,7 +177,7 @@ impl Editor {
 
     fn lsp_root_path(&self, _server: LanguageServiceId) -> PathBuf {
         // TODO this should be configurable per language server
-        std::env::current_dir().unwrap()
+        self.cwd.clone()
     }
 
     fn lsp_workspace_root(&self, server: LanguageServiceId) -> WorkspaceFolder {
@@ -0,0 +1,188 @@
+use std::ops::Range;
+
+use super::*;
+
+/// What a key sequence is mapped to.
+#[derive(Debug, Clone)]
+pub(crate) enum Mapping {
+    /// A builtin action, described by the name of the function for the key hints.
+    Action { action: Action, desc: &'static str },
+    /// Keys handled as if they were typed, defined by `:map` and friends. The keys are only
+    /// remapped by other user mappings if the mapping is `recursive`.
+    Keys { keys: KeySequence, recursive: bool },
+    /// A command run as if by `:{cmd}`, defined by plugins.
+    Command { cmd: String },
+}
+
+impl Mapping {
+    /// A short description of the mapping, shown in the key hints.
+    pub(crate) fn description(&self) -> String {
+        match self {
+            Mapping::Action { desc, .. } => desc.replace('_', " "),
+            Mapping::Keys { keys, .. } => keys.to_string(),
+            Mapping::Command { cmd } => format!(":{cmd}"),
+        }
+    }
+}
+
+impl Editor {
+    /// The maximum number of nested user mappings, mappings deeper than this are assumed to
+    /// recurse forever.
+    const MAX_MAPPING_DEPTH: usize = 100;
+
+    /// Map the keys `lhs` to `rhs` in each of `modes`, `:map` and friends. `<leader>` in either is
+    /// replaced by the leader key. The keys of `rhs` are remapped by user mappings if `recursive`.
+    pub fn map(&mut self, modes: &[Mode], lhs: &str, rhs: &str, recursive: bool) -> Result<()> {
+        let lhs = self.parse_mapping_keys(lhs)?;
+        let keys = self.parse_mapping_keys(rhs)?;
+        for &mode in modes {
+            let mapping = Mapping::Keys { keys: keys.clone(), recursive };
+            self.keymap.insert(mode, lhs.clone(), mapping);
+        }
+        Ok(())
+    }
+
+    /// Map the keys `lhs` to the command `cmd` in each of `modes`, for plugins. Unlike
+    /// [`Editor::map`], it is an error for `lhs` to conflict with a builtin mapping, i.e. for
+    /// either to be a prefix of the other.
+    pub fn map_command(&mut self, modes: &[Mode], lhs: &str, cmd: &str) -> Result<()> {
+        let keys = self.parse_lhs(lhs)?;
+        cmd.parse::<Command>()?;
+        for mode in modes {
+            if !matches!(self.builtin_keymap.get(mode, keys.clone()), TrieResult::Nothing) {
+                bail!("`{lhs}` conflicts with a builtin mapping in {mode:?} mode");
+            }
+        }
+
+        for &mode in modes {
+            self.keymap.insert(mode, keys.clone(), Mapping::Command { cmd: cmd.to_owned() });
+        }
+        Ok(())
+    }
+
+    /// Remove the mappings of the keys `lhs` in each of `modes`.
+    pub fn unmap(&mut self, modes: &[Mode], lhs: &str) -> Result<()> {
+        let keys = self.parse_lhs(lhs)?;
+        for mode in modes {
+            self.keymap.remove(mode, keys.clone());
+        }
+        Ok(())
+    }
+
+    pub(super) fn run_mapping(&mut self, mapping: Mapping) {
+        match mapping {
+            Mapping::Action { action, .. } => action(self),
+            Mapping::Keys { keys, recursive } => {
+                if self.mapping_aborted {
+                    return;
+                }
+
+                if self.mapping_depth >= Self::MAX_MAPPING_DEPTH {
+                    // Unwind the whole expansion, otherwise a mapping that expands to itself more
+                    // than once would still be expanded exponentially many times.
+                    self.mapping_aborted = true;
+                    self.set_error("recursive mapping");
+                    return;
+                }
+
+                self.mapping_depth += 1;
+                let noremap = std::mem::replace(&mut self.noremap, !recursive);
+                for key in keys {
+                    if self.mapping_aborted {
+                        break;
+                    }
+                    self.dispatch_key(key);
+                }
+                self.noremap = noremap;
+                self.mapping_depth -= 1;
+
+                if self.mapping_depth == 0 && self.mapping_aborted {
+                    self.mapping_aborted = false;
+                    self.reset_keymap();
+                }
+            }
+            Mapping::Command { cmd } => {
+                if let Err(err) = self.execute(cmd.as_str()) {
+                    set_error!(self, err);
+                }
+            }
+        }
+    }
+
+    /// Handle `keys` as if they were typed in normal mode, `:normal`. Given `lines`, the keys are
+    /// handled once for each line with the cursor at its start. The keys are remapped by user
+    /// mappings if `remap`. An incomplete command is aborted and insert mode is left afterwards as
+    /// if by `<Esc>`. The changes are undone together.
+    pub fn normal(&mut self, lines: Option<Range<usize>>, keys: &str, remap: bool) -> Result<()> {
+        // Spaces are typed as is rather than separating keys.
+        let keys = self.parse_mapping_keys(&keys.replace(' ', "<space>"))?;
+        let buf = self[Active].buffer();
+        let lines = match lines {
+            Some(lines) => lines.map(Some).collect::<Vec<_>>(),
+            None => vec![None],
+        };
+
+        self[buf].start_undo_group();
+        for line in lines {
+            if mode!(self) != Mode::Normal {
+                self.set_mode(Mode::Normal);
+            }
+
+            if let Some(line) = line {
+                // Earlier lines may have been deleted.
+                if line >= self[buf].text().len_lines() {
+                    break;
+                }
+                self.set_cursor(Active, Point::new(line, 0));
+            }
+
+            let noremap = std::mem::replace(&mut self.noremap, !remap);
+            keys.clone().into_iter().for_each(|key| self.handle_key_event(key));
+            self.noremap = noremap;
+
+            self.pending_action = None;
+            self.count = None;
+            self.reset_keymap();
+        }
+
+        if mode!(self) != Mode::Normal {
+            self.set_mode(Mode::Normal);
+        }
+        // The keys may have deleted the buffer.
+        if let Some(buffer) = self.buffers.get_mut(buf) {
+            buffer.end_undo_group();
+        }
+        Ok(())
+    }
+
+    /// Parse the keys of the left hand side of a mapping, which can't be empty.
+    pub(super) fn parse_lhs(&self, lhs: &str) -> Result<KeySequence> {
+        if lhs.is_empty() {
+            bail!("the keys of a mapping cannot be empty");
+        }
+        self.parse_mapping_keys(lhs)
+    }
+
+    fn parse_mapping_keys(&self, keys: &str) -> Result<KeySequence> {
+        const LEADER: &str = "<leader>";
+
+        let leader = self.settings.leader.read().clone();
+        let mut parsed = vec![];
+        let mut offset = 0;
+        for (i, part) in keys.to_ascii_lowercase().split(LEADER).enumerate() {
+            if i > 0 {
+                parsed.push(leader.clone());
+            }
+
+            let part = &keys[offset..offset + part.len()];
+            offset += part.len() + LEADER.len();
+            if !part.is_empty() {
+                let part =
+                    part.parse::<KeySequence>().map_err(|_| anyhow!("invalid keys: `{keys}`"))?;
+                parsed.extend(part);
+            }
+        }
+
+        Ok(parsed.into_iter().collect())
+    }
+}
@@ -1,7 +1,9 @@
 use std::ops::{Range, RangeBounds};
 
+use anyhow::{anyhow, bail};
+
 use super::{Editor, Selector};
-use crate::{BufferId, Mark, MarkBuilder, MarkId, NamespaceId};
+use crate::{BufferId, Mark, MarkBuilder, MarkId, NamespaceId, Result};
 
 impl Editor {
     #[inline]
@@ -13,6 +15,16 @@ impl Editor {
         self.buffer(selector).marks(byte_range)
     }
 
+    /// The marks that intersect the byte range, including those that start before it.
+    #[inline]
+    pub fn overlapping_marks(
+        &self,
+        selector: impl Selector<BufferId>,
+        byte_range: impl RangeBounds<usize>,
+    ) -> impl Iterator<Item = (NamespaceId, Range<usize>, &Mark)> {
+        self.buffer(selector).overlapping_marks(byte_range)
+    }
+
     pub fn clear_marks(
         &mut self,
         selector: impl Selector<BufferId>,
@@ -44,4 +56,34 @@ impl Editor {
         let namespace = namespace.select(self);
         self.buffer_mut(selector).delete_mark(namespace, mark);
     }
+
+    /// Set the mark `name` to the start of `line`, `:mark`. The mark is referred to as `'{name}`
+    /// in command ranges and moves with edits to the buffer.
+    pub fn set_named_mark(
+        &mut self,
+        selector: impl Selector<BufferId>,
+        name: char,
+        line: usize,
+    ) -> Result<()> {
+        if !name.is_ascii_alphabetic() {
+            bail!("invalid mark name: `{name}`");
+        }
+
+        let buf = selector.select(self);
+        let byte =
+            self[buf].text().try_line_to_byte(line).ok_or_else(|| anyhow!("invalid line"))?;
+        let ns = self.create_namespace(named_mark_namespace(name).as_str());
+        self.buffer_mut(buf).replace_marks(ns, [Mark::builder(byte)]);
+        Ok(())
+    }
+
+    /// The byte offset of the mark `name` in the buffer, see [`Editor::set_named_mark`].
+    pub fn named_mark(&self, selector: impl Selector<BufferId>, name: char) -> Option<usize> {
+        let ns = self.namespace_by_name(named_mark_namespace(name).as_str())?;
+        self.marks(selector, ..).find(|&(id, ..)| id == ns).map(|(_, range, _)| range.start)
+    }
+}
+
+fn named_mark_namespace(name: char) -> String {
+    format!("mark:{name}")
 }
@@ -0,0 +1,81 @@
+use super::*;
+use crate::Mark;
+use crate::syntax::HighlightName;
+
+const NAMESPACE: &str = "messages";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageLevel {
+    Error,
+    Warning,
+    Info,
+}
+
+impl MessageLevel {
+    fn highlight(self) -> HighlightName {
+        match self {
+            MessageLevel::Error => HighlightName::MESSAGE_ERROR,
+            MessageLevel::Warning => HighlightName::MESSAGE_WARNING,
+            MessageLevel::Info => HighlightName::MESSAGE_INFO,
+        }
+    }
+}
+
+/// An entry of the message log shown by `:messages`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub level: MessageLevel,
+    pub text: String,
+}
+
+impl Editor {
+    /// Record a message in the message log without showing it in the status line.
+    /// The oldest messages are dropped once there are more than the `messagehistory` setting.
+    pub fn log(&mut self, level: MessageLevel, text: impl fmt::Display) {
+        let text = text.to_string();
+        tracing::debug!(?level, %text, "log message");
+        self.messages.push_back(Message { level, text });
+
+        let max = *self.settings.message_history.read();
+        while self.messages.len() > max {
+            self.messages.pop_front();
+        }
+    }
+
+    /// Record a message in the message log and show it in the status line.
+    pub fn notify(&mut self, level: MessageLevel, text: impl fmt::Display) {
+        let text = text.to_string();
+        self.status_error = Some(text.clone());
+        self.log(level, text);
+    }
+
+    /// The messages of the log, the oldest first.
+    pub fn messages(&self) -> impl ExactSizeIterator<Item = &Message> + '_ {
+        self.messages.iter()
+    }
+
+    pub fn clear_messages(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Open a readonly buffer of the message log, `:messages`.
+    pub fn open_messages(&mut self) -> BufferId {
+        let mut text = String::new();
+        let mut marks = Vec::with_capacity(self.messages.len());
+        for message in &self.messages {
+            let hl = self.highlight_id_by_name(message.level.highlight());
+            marks.push(Mark::builder(text.len()).width(message.text.len()).hl(hl));
+            text.push_str(&message.text);
+            text.push('\n');
+        }
+
+        // Show the most recent messages.
+        let last = text.lines().count().saturating_sub(1);
+        let buf = self.create_readonly_buffer("messages", text.into_bytes());
+        let ns = self.create_namespace(NAMESPACE);
+        self[buf].replace_marks(ns, marks);
+        self.set_buffer(Active, buf);
+        self.set_cursor(Active, (last, 0));
+        buf
+    }
+}
@@ -0,0 +1,58 @@
+use zi_input::{MouseButton, MouseEvent, MouseEventKind};
+
+use super::*;
+
+impl Editor {
+    /// Lines scrolled per wheel event.
+    const MOUSE_SCROLL_LINES: usize = 3;
+
+    /// Clicking focuses the view under the pointer and moves the cursor there, dragging extends a
+    /// visual selection from where the button was pressed and the wheel scrolls the view under
+    /// the pointer. Views are drawn without separators between them, so there is nothing to drag
+    /// to resize a split, use `<C-w>+` and friends or `:resize` instead.
+    pub(super) fn handle_mouse_event(&mut self, event: MouseEvent) {
+        let mode = mode!(self);
+        if mode == Mode::Command {
+            return;
+        }
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(view) = self.tree.view_at(event.column, event.row) else { return };
+                if !matches!(mode, Mode::Normal | Mode::Insert) {
+                    self.set_mode(Mode::Normal);
+                }
+                self.focus(view);
+                let point = self.mouse_point(view, event.column, event.row);
+                self.set_cursor(view, point);
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                // Keep selecting in the view where the drag started even if the pointer leaves it.
+                let view = self.view(Active).id();
+                if mode == Mode::Normal {
+                    self.set_mode(Mode::Visual);
+                }
+                let point = self.mouse_point(view, event.column, event.row);
+                self.set_cursor(view, point);
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let Some(view) = self.tree.view_at(event.column, event.row) else { return };
+                let direction = match event.kind {
+                    MouseEventKind::ScrollDown => Direction::Down,
+                    _ => Direction::Up,
+                };
+                self.scroll(view, direction, Self::MOUSE_SCROLL_LINES);
+            }
+            _ => (),
+        }
+    }
+
+    /// The point of the text of the view displayed at the screen position, clamped to the view.
+    fn mouse_point(&self, view: ViewId, column: u16, row: u16) -> Point {
+        let area = self.tree.view_area(view);
+        let x = column.saturating_sub(area.x + self[view].number_width.get());
+        let y = row.saturating_sub(area.y).min(area.height.saturating_sub(1));
+        // Below the end of the buffer is the last line.
+        (0..=y).rev().find_map(|y| self.view_point_at(view, (x, y))).unwrap_or_default()
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// The maximum number of old files to remember.
+const MAX_OLDFILES: usize = 100;
+
+/// Recently opened files, most recent first.
+#[derive(Debug, Default)]
+pub(super) struct OldFiles {
+    files: VecDeque<PathBuf>,
+}
+
+impl OldFiles {
+    fn record(&mut self, path: PathBuf) {
+        self.files.retain(|p| *p != path);
+        self.files.push_front(path);
+        self.files.truncate(MAX_OLDFILES);
+    }
+}
+
+impl Editor {
+    /// Recently opened files (including those from previous sessions if loaded), most recent first.
+    pub fn oldfiles(&self) -> impl ExactSizeIterator<Item = &Path> {
+        self.oldfiles.files.iter().map(|path| path.as_path())
+    }
+
+    pub(super) fn record_oldfile(&mut self, path: PathBuf) {
+        self.oldfiles.record(path)
+    }
+
+    /// Load old files persisted by [`Editor::save_oldfiles`].
+    /// Files opened in this session remain more recent than the loaded ones.
+    pub fn load_oldfiles(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            let path = PathBuf::from(line);
+            if !self.oldfiles.files.contains(&path) && self.oldfiles.files.len() < MAX_OLDFILES {
+                self.oldfiles.files.push_back(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Persist the old files to `path`, one per line.
+    pub fn save_oldfiles(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut content = String::new();
+        for file in self.oldfiles() {
+            // Can't represent these with the line based format
+            let Some(file) = file.to_str().filter(|s| !s.contains('\n')) else { continue };
+            content.push_str(file);
+            content.push('\n');
+        }
+
+        std::fs::write(path, content)
+    }
+
+    pub fn open_oldfiles(&mut self) -> ViewGroupId {
+        let split_ratio = *self.settings().file_picker_split_ratio.read();
+        self.open_static_picker::<BufferPicker<stdx::path::Display>>(
+            Url::parse("view-group://oldfiles").unwrap(),
+            "oldfiles",
+            split_ratio,
+            |editor, injector| {
+                for path in editor.oldfiles().filter(|path| path.is_file()) {
+                    if let Err(()) = injector.push(path.to_path_buf().display_owned()) {
+                        break;
+                    }
+                }
+            },
+        )
+    }
+}
@@ -0,0 +1,239 @@
+use super::indent::columns;
+use super::*;
+
+bitflags::bitflags! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct PasteFlags: u8 {
+        /// Put the text before the cursor (or above the line), `P`.
+        const BEFORE = 1 << 0;
+        /// Adjust the indentation of linewise text to the cursor line, `]p` and `[p`.
+        const INDENT = 1 << 1;
+    }
+}
+
+impl Editor {
+    /// Put the selected register after the cursor, `p`.
+    pub fn paste_after(&mut self, selector: impl Selector<ViewId>) -> Result<(), EditError> {
+        self.paste(selector, PasteFlags::empty())
+    }
+
+    /// Put the selected register before the cursor, `P`.
+    pub fn paste_before(&mut self, selector: impl Selector<ViewId>) -> Result<(), EditError> {
+        self.paste(selector, PasteFlags::BEFORE)
+    }
+
+    /// Put the selected register, or the unnamed register, count times according to its kind.
+    /// Charwise text is put after the character under the cursor, linewise text on new lines
+    /// below the cursor line and blockwise text as a block from the column after the cursor.
+    pub fn paste(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        flags: PasteFlags,
+    ) -> Result<(), EditError> {
+        let n = self.take_count().unwrap_or(1);
+        let (view, buf) = self.get(selector);
+        let name = self.take_register().unwrap_or(Registers::UNNAMED);
+        let Some(reg) = self.read_register(name) else { return Ok(()) };
+        if reg.content.is_empty() {
+            return Ok(());
+        }
+
+        let before = flags.contains(PasteFlags::BEFORE);
+        match reg.kind {
+            RegisterKind::Charwise => {
+                self.paste_charwise(view, buf, &reg.content.repeat(n), before)
+            }
+            RegisterKind::Linewise => {
+                let mut content = reg.content;
+                if !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                if flags.contains(PasteFlags::INDENT) {
+                    content = self.indent_like_cursor_line(view, buf, &content);
+                }
+                self.paste_linewise(view, buf, &content.repeat(n), before)
+            }
+            RegisterKind::Blockwise => self.paste_blockwise(view, buf, &reg.content, n, before),
+        }
+    }
+
+    /// Handle text pasted into the terminal. In insert mode the text is inserted literally at the
+    /// cursor, without mappings or indentation, and in normal mode it is put after the cursor.
+    pub fn paste_text(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        text: &str,
+    ) -> Result<(), EditError> {
+        // Terminals send line breaks as carriage returns.
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let (view, buf) = self.get(selector);
+        match &mut self.state {
+            State::Insert(..) => {
+                let byte = self[buf].text().point_to_byte(self[view].cursor());
+                self.edit(buf, &Deltas::insert_at(byte, &text))?;
+                self.set_cursor(view, byte + text.len());
+                Ok(())
+            }
+            State::Command(state) => {
+                state.buffer.push_str(&text.replace('\n', " "));
+                self.update_search();
+                Ok(())
+            }
+            State::Normal(..) => self.paste_charwise(view, buf, &text, false),
+            _ => Ok(()),
+        }
+    }
+
+    /// Insert the contents of the register at the cursor in insert mode, `<C-r>{register}`.
+    pub fn insert_register(&mut self, name: char) -> Result<()> {
+        match self.read_register(name) {
+            Some(reg) => Ok(self.paste_text(Active, &reg.content)?),
+            None => bail!("register is empty: `{name}`"),
+        }
+    }
+
+    fn paste_charwise(
+        &mut self,
+        view: ViewId,
+        buf: BufferId,
+        content: &str,
+        before: bool,
+    ) -> Result<(), EditError> {
+        let text = self[buf].text();
+        let mut byte = text.point_to_byte(self[view].cursor());
+        if !before {
+            // After the character under the cursor, unless the line is empty.
+            if let Some(c) = text.char_at_byte(byte).filter(|c| !matches!(c, '\n' | '\r')) {
+                byte += c.len_utf8();
+            }
+        }
+
+        self.edit(buf, &Deltas::insert_at(byte, content))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        // The cursor ends on the last character of the text unless it spans multiple lines.
+        let cursor = match content.chars().next_back() {
+            Some(c) if !content.contains('\n') => byte + content.len() - c.len_utf8(),
+            _ => byte,
+        };
+        self.set_cursor(view, cursor);
+        Ok(())
+    }
+
+    fn paste_linewise(
+        &mut self,
+        view: ViewId,
+        buf: BufferId,
+        content: &str,
+        before: bool,
+    ) -> Result<(), EditError> {
+        let text = self[buf].text();
+        let cursor = self[view].cursor();
+        let line = if before { cursor.line() } else { cursor.line() + 1 };
+        let byte = text.try_line_to_byte(line).unwrap_or_else(|| text.len_bytes());
+        let missing_eol =
+            byte == text.len_bytes() && !text.is_empty() && text.chars().next_back() != Some('\n');
+        let content = if missing_eol {
+            format!("\n{}", content.strip_suffix('\n').unwrap_or(content))
+        } else {
+            content.to_string()
+        };
+
+        self.edit(buf, &Deltas::insert_at(byte, content))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor_flags(view, Point::new(line, 0), SetCursorFlags::START_OF_LINE);
+        Ok(())
+    }
+
+    /// Put each line of the block `n` times at the same column of consecutive lines from the
+    /// cursor, padding short lines with spaces and adding lines past the end of the buffer.
+    fn paste_blockwise(
+        &mut self,
+        view: ViewId,
+        buf: BufferId,
+        content: &str,
+        n: usize,
+        before: bool,
+    ) -> Result<(), EditError> {
+        let text = self[buf].text();
+        let cursor = self[view].cursor();
+        let cursor_line = text.line(cursor.line()).map(|line| line.to_cow()).unwrap_or_default();
+        let mut col = cursor_line[..cursor.col().min(cursor_line.len())].chars().count();
+        if !before && !cursor_line.is_empty() {
+            col += 1;
+        }
+
+        let pieces = content.split('\n').collect::<Vec<_>>();
+        let width = pieces.iter().map(|piece| piece.chars().count()).max().unwrap_or_default();
+        let mut deltas = vec![];
+        let mut appended = String::new();
+        for (i, piece) in pieces.iter().enumerate() {
+            let padded = format!("{piece:width$}").repeat(n);
+            match text.line(cursor.line() + i).map(|line| line.to_cow()) {
+                Some(line) => {
+                    let start = text.line_to_byte(cursor.line() + i);
+                    let len = line.chars().count();
+                    let delta = if len > col {
+                        // Keep the text after the block aligned.
+                        let offset = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+                        Delta::insert_at(start + offset, padded)
+                    } else {
+                        let block = format!("{}{}", " ".repeat(col - len), padded);
+                        Delta::insert_at(start + line.len(), block.trim_end().to_string())
+                    };
+                    deltas.push(delta);
+                }
+                None => {
+                    appended.push('\n');
+                    appended.push_str(&" ".repeat(col));
+                    appended.push_str(padded.trim_end());
+                }
+            }
+        }
+
+        let cursor_byte = text.line_to_byte(cursor.line())
+            + cursor_line.char_indices().nth(col).map_or(cursor_line.len(), |(i, _)| i);
+        if !appended.is_empty() {
+            let len = text.len_bytes();
+            let appended = match text.chars().next_back() {
+                // Add the lines before the final newline.
+                Some('\n') => format!("{}\n", &appended[1..]),
+                _ => appended,
+            };
+            deltas.push(Delta::insert_at(len, appended));
+        }
+
+        self.edit(buf, &Deltas::new(deltas))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, cursor_byte);
+        Ok(())
+    }
+
+    /// Reindent the lines of `content` so the first is indented like the cursor line while
+    /// keeping the indentation of the others relative to it.
+    fn indent_like_cursor_line(&self, view: ViewId, buf: BufferId, content: &str) -> String {
+        let tab_width = *self[buf].settings().tab_width.read();
+        let text = self[buf].text();
+        let cursor_line = text.line(self[view].cursor().line()).map(|line| line.to_cow());
+        let target = columns(&cursor_line.unwrap_or_default(), tab_width);
+        let Some(first) = content.lines().find(|line| !line.trim().is_empty()) else {
+            return content.to_string();
+        };
+        let first = columns(first, tab_width);
+
+        content
+            .split_inclusive('\n')
+            .map(|line| {
+                let trimmed = line.trim_start_matches([' ', '\t']);
+                if trimmed.trim().is_empty() {
+                    return line.to_string();
+                }
+                let width = (columns(line, tab_width) + target).saturating_sub(first);
+                format!("{}{trimmed}", self.indentation(buf, width))
+            })
+            .collect()
+    }
+}
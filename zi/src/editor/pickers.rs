@@ -1,9 +1,11 @@
 use super::*;
+use crate::buffer::picker::PREVIEW_NAMESPACE;
 use crate::{Mark, lstypes};
 
 impl Editor {
     pub fn open_file_explorer(&mut self, path: impl AsRef<Path>) {
-        inner(self, path.as_ref());
+        let path = self.resolve_path(path);
+        inner(self, &path);
 
         fn inner(editor: &mut Editor, path: &Path) {
             let mut injector = None;
@@ -11,7 +13,6 @@ impl Editor {
                 let (explorer, inj) = ExplorerBuffer::new(
                     id,
                     path.to_path_buf(),
-                    nucleo::Config::DEFAULT.match_paths(),
                     request_redraw,
                     |editor, path: Relative| {
                         let path = path.into_inner();
@@ -33,7 +34,12 @@ impl Editor {
             });
 
             let injector = injector.unwrap();
-            editor.set_buffer(Active, buf);
+            // Navigating within an existing explorer replaces it rather than opening another.
+            if editor.buffer(Active).file_type() == filetype!(explorer) {
+                editor.set_buffer(Active, buf);
+            } else {
+                editor.open_placed(BufferKind::Explorer, Placement::Replace, buf);
+            }
             editor.set_mode(Mode::Normal);
 
             // Cannot use parallel iterator as it doesn't sort.
@@ -75,7 +81,7 @@ impl Editor {
     where
         P: Picker,
     {
-        self.open_picker::<P>(view_group_url, path, split_ratio, None, f)
+        self.open_picker::<P>(view_group_url, path, split_ratio, BufferKind::Picker, None, f)
     }
 
     fn open_dynamic_picker<P>(
@@ -92,16 +98,20 @@ impl Editor {
             view_group_url,
             path,
             split_ratio,
+            BufferKind::Picker,
             Some(Arc::new(dynamic_source)),
             |_, _| {},
         )
     }
 
-    fn open_picker<P>(
+    /// Open a picker placed according to the rule for `kind`, by default the preview takes up
+    /// the bottom of the editor as given by `split_ratio`.
+    pub(super) fn open_picker<P>(
         &mut self,
         view_group_url: Url,
         path: impl AsRef<Path>,
         split_ratio: (u16, u16),
+        kind: BufferKind,
         dynamic_source: Option<DynamicHandler<P::Entry>>,
         f: impl FnOnce(&mut Self, Injector<P::Entry>),
     ) -> ViewGroupId
@@ -123,9 +133,13 @@ impl Editor {
             view
         });
 
-        self.tree.push(Layer::new_with_area(preview, move |area| {
-            tui::Layout::vertical(tui::Constraint::from_fills([split_ratio.0, split_ratio.1]))
-                .areas::<2>(area)[1]
+        let placement = self.settings().placement_rules.read().get(kind);
+        self.tree.push(Layer::new_with_area(preview, move |area| match placement {
+            Some(placement) => placement.area(area),
+            None => {
+                tui::Layout::vertical(tui::Constraint::from_fills([split_ratio.0, split_ratio.1]))
+                    .areas::<2>(area)[1]
+            }
         }));
 
         let display_view = self.split(Active, Direction::Left, tui::Constraint::Fill(1));
@@ -155,6 +169,9 @@ impl Editor {
                 // restore the mode if the picker view group is closed
                 if editor.views[event.view].group() == Some(view_group) {
                     editor.set_mode(prev_mode);
+                    // remove any highlights left behind by previewing entries
+                    let ns = editor.create_namespace(PREVIEW_NAMESPACE);
+                    editor.buffers.values_mut().for_each(|buf| buf.clear_marks(ns, ..));
                     event::HandlerResult::Unsubscribe
                 } else {
                     event::HandlerResult::Continue
@@ -222,16 +239,20 @@ impl Editor {
         )
     }
 
+    /// Open a picker of the files in the directory `path`, which are shown relative to the
+    /// working directory.
     pub fn open_file_picker(&mut self, path: impl AsRef<Path>) -> ViewGroupId {
         let path = path.as_ref();
+        let root = self.resolve_path(path);
+        let cwd = self.current_dir(Active).to_path_buf();
         let split_ratio = *self.settings().file_picker_split_ratio.read();
-        self.open_static_picker::<BufferPicker<stdx::path::Display>>(
+        self.open_static_picker::<BufferPicker<stdx::path::Relative>>(
             Url::parse("view-group://files").unwrap(),
             path,
             split_ratio,
-            |_editor, injector| {
+            move |_editor, injector| {
                 let mut entries =
-                    ignore::WalkBuilder::new(path).build().filter_map(|entry| match entry {
+                    ignore::WalkBuilder::new(root).build().filter_map(|entry| match entry {
                         Ok(entry) => match entry.file_type() {
                             Some(ft) if ft.is_file() => Some(entry),
                             _ => None,
@@ -244,14 +265,15 @@ impl Editor {
 
                 let deadline = std::time::Instant::now() + std::time::Duration::from_millis(50);
                 for entry in entries.by_ref() {
-                    if let Err(()) = injector.push(entry.into_path().display_owned()) {
+                    if let Err(()) = injector.push(entry.into_path().display_relative_to(&cwd)) {
                         break;
                     }
 
                     if std::time::Instant::now() > deadline {
                         pool().spawn(move || {
                             for entry in entries {
-                                if let Err(()) = injector.push(entry.into_path().display_owned()) {
+                                let entry = entry.into_path().display_relative_to(&cwd);
+                                if let Err(()) = injector.push(entry) {
                                     break;
                                 }
                             }
@@ -341,6 +363,11 @@ impl Editor {
             fn point(&self) -> Option<lstypes::EncodedPoint> {
                 Some(self.range.start())
             }
+
+            #[inline]
+            fn range(&self) -> Option<lstypes::EncodedRange> {
+                Some(self.range.clone())
+            }
         }
 
         let split_ratio = *self.settings().diagnostics_picker_split_ratio.read();
@@ -367,11 +394,12 @@ impl Editor {
     pub fn open_global_search(&mut self, path: impl AsRef<Path>) -> ViewGroupId {
         #[derive(Clone, Debug)]
         struct Entry {
-            #[allow(unused)]
-            // TODO can be used to highlight the matching portion of the line
-            byte_range: ops::Range<usize>,
             path: PathBuf,
+            /// The working directory the path is shown relative to.
+            cwd: Arc<Path>,
             line: usize,
+            /// The range of the match within the line
+            range: PointRange,
             content: String,
         }
 
@@ -383,17 +411,24 @@ impl Editor {
 
             #[inline]
             fn point(&self) -> Option<lstypes::EncodedPoint> {
-                Some(Point::new(self.line, 0).into())
+                Some(self.range.start().into())
+            }
+
+            #[inline]
+            fn range(&self) -> Option<lstypes::EncodedRange> {
+                Some(self.range.into())
             }
         }
 
         impl fmt::Display for Entry {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(f, "{}:{} {}", self.path.display(), self.line, self.content)
+                let path = self.path.strip_prefix(&self.cwd).unwrap_or(&self.path);
+                write!(f, "{}:{} {}", path.display(), self.line, self.content)
             }
         }
 
-        let path = path.as_ref().to_path_buf();
+        let path = self.resolve_path(path);
+        let cwd = Arc::<Path>::from(self.current_dir(Active));
         let split_ratio = *self.settings().global_search_split_ratio.read();
         self.open_dynamic_picker::<BufferPicker<Entry>>(
             Url::parse("view-group://search").unwrap(),
@@ -406,10 +441,12 @@ impl Editor {
                 let searcher = search::searcher();
 
                 let walk = ignore::WalkBuilder::new(&path).build_parallel();
+                let cwd = cwd.clone();
 
                 pool().spawn(move || {
                     walk.run(|| {
                         let injector = injector.clone();
+                        let cwd = cwd.clone();
                         let mut searcher = searcher.clone();
                         let matcher = matcher.clone();
 
@@ -423,12 +460,17 @@ impl Editor {
                             };
 
                             let mut quit = false;
-                            let sink = search::Sink(|line, content, byte_range| {
+                            let sink = search::Sink(&matcher, |line, content, columns| {
+                                let line = line.checked_sub(1).expect("1-indexed") as usize;
                                 quit = injector
                                     .push(Entry {
-                                        byte_range,
-                                        line: line.checked_sub(1).expect("1-indexed") as usize,
+                                        line,
+                                        range: PointRange::new(
+                                            (line, columns.start),
+                                            (line, columns.end),
+                                        ),
                                         path: entry.path().to_path_buf(),
+                                        cwd: cwd.clone(),
                                         content: content.trim_end().to_string(),
                                     })
                                     .is_err();
@@ -453,4 +495,78 @@ impl Editor {
             },
         )
     }
+
+    /// Open a picker titled `title` of the items pushed with [`Editor::push_picker_items`], for
+    /// pickers whose items come from elsewhere (e.g. plugins). Confirming an item closes the
+    /// picker and dispatches [`event::DidConfirmPickerItem`]. Errors if the picker is already open.
+    pub fn open_item_picker(&mut self, title: &str) -> Result<ViewGroupId> {
+        let mut url = Url::parse("view-group://picker").unwrap();
+        url.set_path(title);
+        let mut opened = false;
+        let split_ratio = *self.settings().generic_picker_split_ratio.read();
+        let view_group =
+            self.open_static_picker::<ItemPicker>(url, title, split_ratio, |_, _| opened = true);
+        if !opened {
+            bail!("picker is already open: `{title}`");
+        }
+        Ok(view_group)
+    }
+
+    /// Push items to a picker opened with [`Editor::open_item_picker`].
+    pub fn push_picker_items(
+        &mut self,
+        picker: ViewGroupId,
+        items: impl IntoIterator<Item = PickerItem>,
+    ) -> Result<()> {
+        let injector = self
+            .views()
+            .filter(|view| view.group() == Some(picker))
+            .find_map(|view| {
+                self[view.buffer()].as_any().downcast_ref::<PickerBuffer<ItemPicker>>()
+            })
+            .map(|buf| buf.injector())
+            .ok_or_else(|| anyhow!("picker is not open"))?;
+
+        for item in items {
+            if injector.push(item).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An item of a picker opened with [`Editor::open_item_picker`], displayed as `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerItem {
+    /// Identifies the item in [`event::DidConfirmPickerItem`].
+    pub id: u64,
+    pub text: String,
+}
+
+impl fmt::Display for PickerItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ItemPicker {
+    preview: ViewId,
+}
+
+impl Picker for ItemPicker {
+    type Entry = PickerItem;
+
+    fn new(preview: ViewId) -> Self {
+        Self { preview }
+    }
+
+    fn select(self, _editor: &mut Editor, _item: Self::Entry) {}
+
+    fn confirm(self, editor: &mut Editor, item: Self::Entry) {
+        let picker = editor.view(self.preview).group().expect("picker views are in a group");
+        editor.close_view(self.preview);
+        editor.dispatch(event::DidConfirmPickerItem { picker, item: item.id });
+    }
 }
@@ -0,0 +1,114 @@
+use super::*;
+use crate::lstypes::{self, TextExt};
+
+/// An entry in the quickfix list, usually exported from a picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickfixEntry {
+    pub path: PathBuf,
+    pub point: Option<lstypes::EncodedPoint>,
+    /// The range to highlight when previewing the entry.
+    pub range: Option<lstypes::EncodedRange>,
+    /// The text to display for the entry.
+    pub text: String,
+}
+
+impl fmt::Display for QuickfixEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl BufferPickerEntry for QuickfixEntry {
+    #[inline]
+    fn buffer_or_path(&self) -> Result<BufferId, &Path> {
+        Err(&self.path)
+    }
+
+    #[inline]
+    fn point(&self) -> Option<lstypes::EncodedPoint> {
+        self.point.clone()
+    }
+
+    #[inline]
+    fn range(&self) -> Option<lstypes::EncodedRange> {
+        self.range.clone()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Quickfix {
+    entries: Vec<QuickfixEntry>,
+    idx: usize,
+}
+
+impl Editor {
+    pub fn quickfix(&self) -> &[QuickfixEntry] {
+        &self.quickfix.entries
+    }
+
+    pub fn set_quickfix(&mut self, entries: impl Into<Vec<QuickfixEntry>>) {
+        self.quickfix = Quickfix { entries: entries.into(), idx: 0 };
+    }
+
+    pub fn open_quickfix(&mut self) -> ViewGroupId {
+        let split_ratio = *self.settings().generic_picker_split_ratio.read();
+        self.open_picker::<BufferPicker<QuickfixEntry>>(
+            Url::parse("view-group://quickfix").unwrap(),
+            "quickfix",
+            split_ratio,
+            BufferKind::Quickfix,
+            None,
+            |editor, injector| {
+                for entry in &editor.quickfix.entries {
+                    if let Err(()) = injector.push(entry.clone()) {
+                        break;
+                    }
+                }
+            },
+        )
+    }
+
+    /// Jump to the next entry in the quickfix list (wrapping around).
+    /// The returned future resolves once the cursor is on the entry.
+    pub fn quickfix_next(&mut self) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let n = self.quickfix.entries.len();
+        if n == 0 {
+            bail!("quickfix list is empty");
+        }
+
+        self.quickfix.idx = (self.quickfix.idx + 1) % n;
+        self.goto_quickfix()
+    }
+
+    /// Jump to the previous entry in the quickfix list (wrapping around).
+    /// The returned future resolves once the cursor is on the entry.
+    pub fn quickfix_prev(&mut self) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let n = self.quickfix.entries.len();
+        if n == 0 {
+            bail!("quickfix list is empty");
+        }
+
+        self.quickfix.idx = self.quickfix.idx.checked_sub(1).unwrap_or(n - 1);
+        self.goto_quickfix()
+    }
+
+    fn goto_quickfix(&mut self) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let entry = self.quickfix.entries[self.quickfix.idx].clone();
+        let fut = self.open(&entry.path, OpenFlags::SPAWN_LANGUAGE_SERVICES)?;
+        let client = self.client();
+        Ok(async move {
+            let buf = fut.await?;
+            client
+                .with(move |editor| {
+                    if let Some(point) =
+                        entry.point().and_then(|p| editor.text(buf).decode_point(p))
+                    {
+                        editor.jump_to(Location::new(buf, point));
+                        editor.reveal(Active, point, VerticalAlignment::Center);
+                    }
+                })
+                .await;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+impl Editor {
+    /// Close the view, `:q`. Closing the last view quits the editor which is refused if any buffer
+    /// has unsaved changes, unless `force` is set.
+    pub fn quit(&mut self, selector: impl Selector<ViewId>, force: bool) -> Result<()> {
+        let view = selector.select(self);
+        if !force && self.tree.is_last_view(view) {
+            self.ensure_saved()?;
+        }
+
+        self.close_view(view);
+        Ok(())
+    }
+
+    /// Close every view, quitting the editor, `:qa`.
+    /// Refused if any buffer has unsaved changes, unless `force` is set.
+    pub fn quit_all(&mut self, force: bool) -> Result<()> {
+        if !force {
+            self.ensure_saved()?;
+        }
+
+        for view in self.tree.views().collect::<Vec<_>>() {
+            self.close_view(view);
+        }
+        Ok(())
+    }
+
+    /// Close the view unless it is the last one, `<C-w>c`.
+    pub fn close_window(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        if self.tree.is_last_view(view) {
+            bail!("cannot close the last window");
+        }
+
+        self.close_view(view);
+        Ok(())
+    }
+
+    /// The file buffers with unsaved changes.
+    pub fn dirty_buffers(&self) -> impl Iterator<Item = BufferId> + '_ {
+        self.buffers
+            .values()
+            .filter(|buf| buf.file_path().is_some() && buf.flags().contains(BufferFlags::DIRTY))
+            .map(|buf| buf.id())
+    }
+
+    fn ensure_saved(&self) -> Result<()> {
+        let mut dirty = self.dirty_buffers().filter_map(|buf| self[buf].file_path());
+        match (dirty.next(), dirty.count()) {
+            (None, _) => Ok(()),
+            (Some(path), 0) => {
+                bail!("`{}` has unsaved changes (add ! to override)", path.display())
+            }
+            (Some(path), n) => bail!(
+                "`{}` and {n} more buffer(s) have unsaved changes (add ! to override)",
+                path.display()
+            ),
+        }
+    }
+}
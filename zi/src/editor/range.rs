@@ -0,0 +1,57 @@
+use std::ops::Range;
+
+use super::*;
+use crate::command::{Address, CommandRange};
+
+impl Editor {
+    /// Resolve a command range to (0-indexed) lines of the buffer of the view, relative to its
+    /// cursor.
+    pub fn resolve_range(
+        &self,
+        selector: impl Selector<ViewId>,
+        range: &CommandRange,
+    ) -> Result<Range<usize>> {
+        let (view, buf) = self.get(selector);
+        let cursor_line = self[view].cursor().line();
+        let text = self[buf].text();
+        range.resolve(cursor_line, text.len_lines(), |addr| match addr {
+            Address::Mark(c) => self
+                .named_mark(buf, *c)
+                .map(|byte| text.byte_to_line(byte))
+                .ok_or_else(|| anyhow!("mark not set: `{c}`")),
+            Address::Search { pattern, backward } => {
+                self.search_line(buf, pattern, cursor_line, *backward)
+            }
+            _ => unreachable!("only marks and searches are looked up"),
+        })
+    }
+
+    /// The first line after (or before) `line` matching `pattern`, wrapping around the end of the
+    /// buffer.
+    fn search_line(
+        &self,
+        buf: BufferId,
+        pattern: &str,
+        line: usize,
+        backward: bool,
+    ) -> Result<usize> {
+        use zi_text::search::{Regex, Search};
+
+        if pattern.is_empty() {
+            bail!("empty pattern");
+        }
+
+        let search = Search::new(Regex::new(pattern)?);
+        let text = self[buf].text();
+        let len_lines = text.len_lines();
+        let mut lines: Box<dyn Iterator<Item = usize>> = if backward {
+            Box::new((0..line).rev().chain((line..len_lines).rev()))
+        } else {
+            Box::new((line + 1..len_lines).chain(0..=line))
+        };
+
+        lines
+            .find(|&i| text.line(i).is_some_and(|slice| search.is_match(slice)))
+            .ok_or_else(|| anyhow!("pattern not found: `{pattern}`"))
+    }
+}
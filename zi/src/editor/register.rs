@@ -9,6 +9,8 @@ pub struct Registers {
 
 impl Registers {
     pub const UNNAMED: char = '"';
+    /// The last expression evaluated with `<C-r>=` in insert mode.
+    pub const EXPRESSION: char = '=';
 
     pub fn get(&self, name: char) -> Option<&Register> {
         self.registers.get(&name)
@@ -37,6 +39,8 @@ pub enum RegisterKind {
     #[default]
     Charwise,
     Linewise,
+    /// A rectangular block, the lines of the block are separated by newlines.
+    Blockwise,
 }
 
 impl From<TextObjectKind> for RegisterKind {
@@ -1,17 +1,24 @@
 use std::borrow::Cow;
-use std::fmt;
+use std::ops::Range;
 
+use itertools::{Either, Itertools};
 use stdx::iter::IteratorExt;
 use stdx::merge::Merge;
 use tui::{Rect, StatefulWidget, Widget as _};
-use zi_core::{IteratorRangeExt, Offset, PointRange};
+use zi_core::{CompletionItem, IteratorRangeExt, Offset, PointRange, Size};
 use zi_text::{AnyTextSlice, PointRangeExt, Text, TextSlice};
 
 use super::{Editor, State, get_ref};
 use crate::completion::Completion;
 use crate::editor::Resource;
 use crate::syntax::HighlightName;
-use crate::{Active, ViewId};
+use crate::{Active, Conceal, FloatAnchor, FloatConfig, Mode, Theme, View, ViewId};
+
+/// The width of the completion menu, including the kind icons.
+const COMPLETION_MENU_WIDTH: u16 = 50;
+
+/// The maximum width of the documentation of the selected completion item.
+const COMPLETION_DOCUMENTATION_MAX_WIDTH: u16 = 60;
 
 impl Editor {
     pub fn render(&mut self, frame: &mut impl tui::DynFrame) {
@@ -21,6 +28,7 @@ impl Editor {
         let client = self.client();
 
         tracing::debug!(%tree_area, %buffer_area, "render editor");
+        self.begin_frame();
 
         // Only iterate over the views that are in the view tree, as otherwise they are definitely
         // not visible and we don't need to render them.
@@ -32,42 +40,17 @@ impl Editor {
         });
 
         self.tree.render(self, frame.buffer_mut());
+        self.render_completion(tree_area, frame.buffer_mut());
+        self.render_key_hints(tree_area, frame.buffer_mut());
+        self.render_stats_overlay(tree_area, frame.buffer_mut());
 
         // HACK probably there is a nicer way to not special case the cmd and statusline
-        let (view, buf) = get_ref!(self);
-        let path = buf.file_path();
-        let path = path.as_ref().map(|p| p.display());
-        let display = path
-            .as_ref()
-            .map_or_else(|| buf.url() as &dyn fmt::Display, |p| p as &dyn fmt::Display);
-
-        let mut status_spans = vec![tui::Span::styled(
-            format!("{}:{}:{} ", display, view.cursor().line() + 1_usize, view.cursor().col()),
-            tui::Style::new()
-                .fg(tui::Color::Rgb(0x88, 0x88, 0x88))
-                .bg(tui::Color::Rgb(0x07, 0x36, 0x42)),
-        )];
-
-        // The error should probably go in the cmd line not the status line.
-        if let Some(error) = &self.status_error {
-            status_spans.push(tui::Span::styled(
-                error,
-                tui::Style::new()
-                    .fg(tui::Color::Rgb(0xff, 0x00, 0x00))
-                    .bg(tui::Color::Rgb(0x07, 0x36, 0x42)),
-            ));
+        let area = Rect { x: 0, y: tree_area.height, width: tree_area.width, height: 1 };
+        if !self.reuse_status_line(area, frame.buffer_mut()) {
+            self.render_status_line(area, frame.buffer_mut());
+            self.rendered_status_line(area, frame.buffer_mut());
         }
 
-        // FIXME probably a better way than manually padding the right
-        status_spans.push(tui::Span::styled(
-            " ".repeat(tree_area.width as usize),
-            tui::Style::new()
-                .fg(tui::Color::Rgb(0x88, 0x88, 0x88))
-                .bg(tui::Color::Rgb(0x07, 0x36, 0x42)),
-        ));
-
-        let status = tui::Line::default().spans(status_spans);
-
         let cmd = tui::Text::styled(
             match &self.state {
                 State::Command(state) => Cow::Borrowed(state.buffer.as_str()),
@@ -79,51 +62,168 @@ impl Editor {
                 .bg(tui::Color::Rgb(0x00, 0x2b, 0x36)),
         );
 
-        let widget = tui::vstack([tui::Constraint::Max(1), tui::Constraint::Max(1)], (status, cmd));
-
-        widget.render(
-            tui::Rect {
-                x: 0,
-                y: tree_area.height,
-                width: tree_area.width,
-                height: Self::BOTTOM_BAR_HEIGHT,
-            },
+        cmd.render(
+            Rect { x: 0, y: tree_area.height + 1, width: tree_area.width, height: 1 },
             frame.buffer_mut(),
         );
 
+        if !self.capabilities.truecolor {
+            approximate_colors(frame.buffer_mut());
+        }
+
+        let (view, _) = get_ref!(self);
         let (x, y) = self.cursor_viewport_coords();
         let offset = match &self.state {
+            // The display width rather than the length so the cursor, and with it the candidate
+            // window of an input method, is at the end of wide or multibyte text.
             State::Command(state) => {
-                state.buffer.len().checked_sub(1).expect("should have a preceding `/` or `:`")
-                    as u16
+                let width = zi_text::display_width(&state.buffer, 0, 1);
+                width.checked_sub(1).expect("should have a preceding `/` or `:`") as u16
             }
             _ => view.number_width.get(),
         };
 
         frame.set_cursor(x + offset, y);
+        self.end_frame();
+    }
+
+    /// Render to a surface for frontends that don't use ratatui, see [`tui::Surface`].
+    /// The editor is resized to `width` by `height` first if it is not that size already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `height` leaves no room for the status line and command line.
+    pub fn render_surface(&mut self, width: u16, height: u16) -> tui::Surface {
+        // The size of the editor excludes the status line and command line.
+        if self.size() != Size::new(width, height.saturating_sub(Self::BOTTOM_BAR_HEIGHT)) {
+            self.resize(Size::new(width, height));
+        }
+
+        let mut frame = tui::SurfaceFrame::new(width, height);
+        self.render(&mut frame);
+        frame.into_surface(self.cursor_style()).with_floats(self.tree.float_areas())
+    }
+
+    /// The style of the cursor for the current mode, see [`GuiCursor`].
+    pub fn cursor_style(&self) -> tui::CursorStyle {
+        self.settings.guicursor.read().style(self.mode())
     }
 
     #[tracing::instrument(skip_all)]
     pub(crate) fn render_view(&self, area: Rect, surface: &mut tui::Buffer, view: ViewId) {
         assert_eq!(surface.area.intersection(area), area);
-
         let theme = self.theme();
         let theme = theme.read();
-        let background = self
-            .highlight_id_by_name(HighlightName::BACKGROUND)
-            .style(&theme)
-            .unwrap_or_else(|| theme.default_style());
+        let background = tui::Style::from(
+            self.highlight_id_by_name(HighlightName::BACKGROUND)
+                .style(&theme)
+                .unwrap_or_else(|| theme.default_style()),
+        );
+
+        let min_number_width = *self[view].settings().line_number_width.read();
+        let mut rows = self.damaged_rows(area, view);
+        if let Some(damaged) = &rows {
+            self.copy_view(area, surface, view);
+            // The damaged rows can't widen the line numbers of the copied rows.
+            let number_width = self[view].number_width.get();
+            let min_number_width =
+                min_number_width.max(number_width.saturating_sub(1).try_into().unwrap_or(u8::MAX));
+            let width =
+                self.render_view_rows(area, surface, view, damaged, background, min_number_width);
+            if width.is_some_and(|width| width != number_width as usize) {
+                rows = None;
+            }
+        }
+
+        let rows = rows.unwrap_or_else(|| {
+            let rows = vec![0..area.height];
+            let width =
+                self.render_view_rows(area, surface, view, &rows, background, min_number_width);
+            self[view].number_width.set(width.expect("there is a range of rows") as u16);
+            rows
+        });
+
+        if !rows.is_empty() {
+            let background = background.bg.unwrap_or(tui::Color::Reset);
+            self.render_view_guides(area, surface, view, &theme, background);
+        }
+        self.rendered_view(area, surface, view, &rows);
+    }
+
+    /// Render the rows of the view, returning the width of the line numbers they need if any.
+    fn render_view_rows(
+        &self,
+        area: Rect,
+        surface: &mut tui::Buffer,
+        view: ViewId,
+        rows: &[Range<u16>],
+        background: tui::Style,
+        min_number_width: u8,
+    ) -> Option<usize> {
+        rows.iter()
+            .map(|rows| {
+                let area = Rect { y: area.y + rows.start, height: rows.len() as u16, ..area };
+                surface.set_style(area, background);
+                self.render_view_content(area, surface, view, rows.start, min_number_width)
+            })
+            .max()
+    }
 
-        surface.set_style(area, background);
-        let number_width = self.render_view_content(area, surface, view);
-        self[view].number_width.set(number_width as u16);
+    /// Highlight the cursor line, cursor column, and color columns of the view.
+    /// Only cells with the `background` color are highlighted so other highlights take precedence.
+    fn render_view_guides(
+        &self,
+        area: Rect,
+        surface: &mut tui::Buffer,
+        view: ViewId,
+        theme: &Theme,
+        background: tui::Color,
+    ) {
+        let view = &self[view];
+        let settings = view.settings();
+        let number_width = view.number_width.get().min(area.width);
+        let area = Rect { x: area.x + number_width, width: area.width - number_width, ..area };
+        let (cursor_x, cursor_y) = self.view_cursor_coords(view.id());
+
+        let mut paint = |rect: Rect, name| {
+            let style = self.highlight_id_by_name(name).style(theme);
+            let Some(bg) = style.and_then(|style| tui::Style::from(style).bg) else { return };
+
+            let rect = rect.intersection(area);
+            for y in rect.top()..rect.bottom() {
+                for x in rect.left()..rect.right() {
+                    let cell = surface.get_mut(x, y);
+                    if cell.bg == background {
+                        cell.set_bg(bg);
+                    }
+                }
+            }
+        };
+
+        let offset_col = view.offset().col;
+        let max_line_length = *self[view.buffer()].settings().max_line_length.read();
+        let color_columns = settings.color_columns.read();
+        for col in color_columns.iter().copied().chain(max_line_length.map(|len| len + 1)) {
+            // Columns are 1-indexed.
+            let Some(x) = (col as usize).checked_sub(offset_col + 1) else { continue };
+            if x < area.width as usize {
+                let rect = Rect { x: area.x + x as u16, width: 1, ..area };
+                paint(rect, HighlightName::COLORCOLUMN);
+            }
+        }
+
+        if *settings.cursor_column.read() {
+            paint(Rect { x: area.x + cursor_x, width: 1, ..area }, HighlightName::CURSORCOLUMN);
+        }
 
-        if view == self.view(Active).id() {
-            self.render_completion(area, surface, view);
+        if *settings.cursor_line.read() {
+            paint(Rect { y: area.y + cursor_y, height: 1, ..area }, HighlightName::CURSORLINE);
         }
     }
 
-    fn render_completion(&self, view_area: Rect, surface: &mut tui::Buffer, view: ViewId) {
+    /// Render the completion menu of the active view in a float below the start of the completion,
+    /// above all other floats.
+    fn render_completion(&self, tree_area: Rect, surface: &mut tui::Buffer) {
         let State::Insert(state) = &self.state else { return };
         let Completion::Active(state) = &state.completion else { return };
 
@@ -131,25 +231,36 @@ impl Editor {
             return;
         }
 
+        let view = self.view(Active).id();
+        let view_area = self.tree.view_area(view);
         let height = state.matches().take(20).len() as u16;
         let start_point = self.text(view).byte_to_point(state.start_byte());
         let offset = self[view].offset();
-        let area = Rect {
+        let anchor = FloatAnchor::Editor {
             x: view_area.x + self[view].number_width.get() + start_point.col() as u16
                 - offset.col as u16,
             y: view_area.y + start_point.line() as u16 - offset.line as u16 + 1,
-            height,
-            width: 50,
-        }
-        .intersection(view_area);
+        };
+        let area = FloatConfig::new(anchor, COMPLETION_MENU_WIDTH, height).area(tree_area);
 
+        let style = tui::Style::default()
+            .bg(tui::Color::Rgb(0x07, 0x36, 0x42))
+            .fg(tui::Color::Rgb(0x88, 0x88, 0x88));
+        // Leave space for the kind icons at the end of each item.
+        let label_width = area.width.saturating_sub(5) as usize;
         tui::Clear.render(area, surface);
         let list = tui::List::new(state.matches().map(|item| {
-            tui::ListItem::new(tui::Text::from(&*item.label).left_aligned()).style(
-                tui::Style::default()
-                    .bg(tui::Color::Rgb(0x07, 0x36, 0x42))
-                    .fg(tui::Color::Rgb(0x88, 0x88, 0x88)),
-            )
+            let label_style = if item.deprecated {
+                style.add_modifier(tui::Modifier::CROSSED_OUT)
+            } else {
+                style
+            };
+            let icon = item.kind.map_or("", |kind| kind.icon());
+            let line = tui::Line::from(vec![
+                tui::Span::styled(format!(" {:<label_width$}", item.label), label_style),
+                tui::Span::styled(format!("{icon:>3} "), style.add_modifier(tui::Modifier::DIM)),
+            ]);
+            tui::ListItem::new(line).style(style)
         }))
         .scroll_padding(3)
         .highlight_style(
@@ -159,9 +270,90 @@ impl Editor {
         );
 
         StatefulWidget::render(list, area, surface, &mut state.widget_state());
+
+        if let Some(item) = state.selected() {
+            self.render_completion_documentation(item, area, tree_area, surface, style);
+        }
     }
 
-    fn render_view_content(&self, area: Rect, surface: &mut tui::Buffer, view: ViewId) -> usize {
+    /// Render the details and documentation of the selected completion item next to the menu.
+    fn render_completion_documentation(
+        &self,
+        item: &CompletionItem,
+        menu_area: Rect,
+        tree_area: Rect,
+        surface: &mut tui::Buffer,
+        style: tui::Style,
+    ) {
+        let doc = [item.detail.as_deref(), item.documentation.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|doc| !doc.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if doc.is_empty() {
+            return;
+        }
+
+        let text = tui::Text::styled(doc, style);
+        let width = (text.width() as u16).min(COMPLETION_DOCUMENTATION_MAX_WIDTH);
+        let height = (text.height() as u16).min(menu_area.height.max(10));
+        let config = |x| FloatConfig::new(FloatAnchor::Editor { x, y: menu_area.y }, width, height);
+        // Prefer the right side of the menu, but use the left if it would overlap.
+        let mut area = config(menu_area.right()).with_border(true).area(tree_area);
+        if area.intersects(menu_area) {
+            area = config(menu_area.x.saturating_sub(width + 2)).with_border(true).area(tree_area);
+        }
+
+        tui::Clear.render(area, surface);
+        let block = tui::Block::bordered().style(style);
+        let inner = block.inner(area);
+        block.render(area, surface);
+        text.render(inner, surface);
+    }
+    /// Render the key hints in columns at the bottom of the views.
+    fn render_key_hints(&self, area: Rect, surface: &mut tui::Buffer) {
+        if self.key_hints.is_empty() || area.height == 0 {
+            return;
+        }
+
+        let key_width = self.key_hints.iter().map(|(key, _)| key.to_string().len()).max();
+        let key_width = key_width.unwrap_or_default();
+        let entries = self
+            .key_hints
+            .iter()
+            .map(|(key, desc)| format!("{:>key_width$} → {desc}", key.to_string()))
+            .collect::<Vec<_>>();
+
+        let width = entries.iter().map(|entry| entry.chars().count()).max().unwrap_or_default();
+        let width = width as u16 + 2;
+        let columns = (area.width / width).max(1);
+        let rows = (entries.len() as u16).div_ceil(columns).min(area.height / 2).max(1);
+        let area = Rect { y: area.bottom() - rows, height: rows, ..area };
+
+        let style = tui::Style::default()
+            .bg(tui::Color::Rgb(0x07, 0x36, 0x42))
+            .fg(tui::Color::Rgb(0x88, 0x88, 0x88));
+        tui::Clear.render(area, surface);
+        surface.set_style(area, style);
+        for (i, entry) in entries.iter().take((rows * columns) as usize).enumerate() {
+            let (column, row) = (i as u16 / rows, i as u16 % rows);
+            let x = area.x + 1 + column * width;
+            let max_width = area.right().saturating_sub(x) as usize;
+            surface.set_stringn(x, area.y + row, entry, max_width, style);
+        }
+    }
+
+    /// Render the lines of the view on the rows of `area`, the rows of the view from `skip` on.
+    /// Returns the width of the line numbers, at least `min_number_width` and the separator.
+    fn render_view_content(
+        &self,
+        area: Rect,
+        surface: &mut tui::Buffer,
+        view: ViewId,
+        skip: u16,
+        min_number_width: u8,
+    ) -> usize {
         let theme = self.theme();
         let theme = theme.read();
         let mut query_cursor = tree_sitter::QueryCursor::new();
@@ -170,12 +362,21 @@ impl Editor {
         let buf = self.buffer(view.buffer());
         let text = buf.text();
 
-        let line_offset = view.offset().line;
-        let relevant_point_range =
-            PointRange::new((line_offset, 0usize), (line_offset + area.height as usize, 0usize));
+        let line_offset = view.offset().line + skip as usize;
+        let folds = self.closed_folds(view.buffer());
+        let (mut rows, fillers) = self.view_rows(view.id());
+        // Only views whose rows display consecutive lines are rendered from a row other than the
+        // first, see `Editor::damaged_rows`.
+        assert!(skip == 0 || fillers.is_empty());
+        rows.drain(..rows.len().min(skip as usize));
+        rows.truncate(area.height as usize);
+        // Closed folds pull lines from below the view into it.
+        let end_line =
+            rows.last().map_or(0, |&line| line + 1).max(line_offset + area.height as usize);
+        let relevant_point_range = PointRange::new((line_offset, 0usize), (end_line, 0usize));
         let relevant_byte_range = {
             let start_byte = text.line_to_byte(line_offset);
-            let end_byte = match text.try_line_to_byte(line_offset + area.height as usize) {
+            let end_byte = match text.try_line_to_byte(end_line) {
                 Some(end) => end + text.line(line_offset).unwrap().len_bytes(),
                 None => text.len_bytes(),
             };
@@ -187,6 +388,43 @@ impl Editor {
             .skip_while(|hl| hl.range.end().line() < line_offset)
             .filter_map(|hl| Some((hl.range, hl.id.style(&theme)?)));
 
+        let rainbow_highlights = buf
+            .syntax()
+            .filter(|_| self.rainbow_delimiters(buf.file_type()))
+            .map_or(vec![], |syntax| {
+                syntax.rainbows(&mut tree_sitter::QueryCursor::new(), text, relevant_point_range)
+            })
+            .into_iter()
+            .filter_map(|(range, depth)| {
+                let name = &HighlightName::RAINBOW[depth % HighlightName::RAINBOW.len()];
+                Some((range, theme.highlight_id_by_name(name).style(&theme)?))
+            });
+
+        let signs = buf
+            .marks(relevant_byte_range.clone())
+            .filter_map(|(_, range, mark)| {
+                let sign = mark.sign()?;
+                let style = theme.default_style().merge(sign.hl.style(&theme)?);
+                Some((text.byte_to_line(range.start), sign.text, style.into()))
+            })
+            .collect::<Vec<_>>();
+
+        let mark_virtual_text = buf
+            .marks(relevant_byte_range.clone())
+            .filter_map(|(_, range, mark)| {
+                let virtual_text = mark.virtual_text()?;
+                let default_style = theme.default_style();
+                let style = virtual_text
+                    .hl
+                    .style(&theme)
+                    .map_or(default_style, |style| default_style.merge(style));
+                let line = text.byte_to_line(range.start);
+                Some((line, virtual_text.text.to_string(), style.into()))
+            })
+            .collect::<Vec<_>>();
+
+        let conceals = self.conceals(view, relevant_point_range, relevant_byte_range.clone());
+
         let mark_highlights = buf
             .marks(relevant_byte_range)
             .filter(|(_, range, _)| !range.is_empty())
@@ -206,7 +444,9 @@ impl Editor {
             .skip_while(|hl| hl.range.end().line() < line_offset)
             .filter_map(|hl| Some((hl.range, hl.id.style(&theme)?)));
 
+        // Rainbow delimiters take precedence over syntax highlights, but not over semantic ones.
         let view_highlights = syntax_highlights
+            .range_merge(rainbow_highlights)
             .range_merge(overlay_highlights)
             .range_merge(mark_highlights)
             .inspect(|(range, style)| {
@@ -256,27 +496,251 @@ impl Editor {
         let lines = text
             .line_slice(line_offset..)
             .lines()
+            .take(end_line - line_offset)
             // We always want to render a line even if the buffer is empty.
             .default_if_empty(|| Box::new("") as Box<dyn AnyTextSlice<'_>>);
 
         let chunks = zi_text::annotate(lines, highlights);
+        let chunks = if conceals.is_empty() {
+            Either::Left(chunks)
+        } else {
+            let conceals = conceals
+                .into_iter()
+                .map(|(range, conceal)| (range - Offset::new(line_offset, 0), conceal))
+                .collect();
+            let style = self.highlight_id_by_name(HighlightName::CONCEAL).style(&theme);
+            let level = *view.settings().conceal_level.read();
+            Either::Right(conceal(chunks, conceals, level, style))
+        };
 
-        let lines = tui::Lines::new(
+        let chunks = chunks.filter_map(|(line, text, style)| {
+            // Map each line to the row it is displayed on, skipping the lines hidden by folds.
+            let row = if folds.is_empty() && fillers.is_empty() {
+                line
+            } else {
+                let line = line_offset + line;
+                let row = rows.partition_point(|&l| l <= line).checked_sub(1)?;
+                (rows[row] == line && fillers.binary_search(&row).is_err()).then_some(row)?
+            };
+            Some((row, text, style))
+        });
+
+        // Filler rows are filled with `-` as in vim's diff mode.
+        let filler_style = tui::Style::from({
+            let default_style = theme.default_style();
+            self.highlight_id_by_name(HighlightName::DIFF_DELETED)
+                .style(&theme)
+                .map_or(default_style, |style| default_style.merge(style))
+        });
+        let filler_chunks = fillers
+            .iter()
+            .map(|&row| (row, Cow::Owned("-".repeat(area.width as usize)), filler_style));
+
+        let fold_summaries = folds.iter().filter(|fold| rows.contains(&fold.start)).map(|fold| {
+            let default_style = theme.default_style();
+            let style = self
+                .highlight_id_by_name(HighlightName::FOLDED)
+                .style(&theme)
+                .map_or(default_style, |style| default_style.merge(style));
+            (fold.start, format!("⋯ {} lines", fold.len()), style.into())
+        });
+
+        let mut lines = tui::Lines::new(
             line_offset,
             view.cursor().line(),
             *view.settings().line_number_style.read(),
             *buf.settings().tab_width.read(),
-            *view.settings().line_number_width.read(),
-            chunks.inspect(|(_, text, _)| tracing::trace!(?text, "render chunk")).map(
-                |(line, text, style)| {
+            min_number_width,
+            chunks
+                .inspect(|(_, text, _)| tracing::trace!(?text, "render chunk"))
+                .map(|(line, text, style)| {
                     let default_style = theme.default_style();
                     // The merge is still necessary to fill in the missing fields in the style.
                     let style = default_style.merge(style.unwrap_or(default_style));
                     (line, text, style.into())
-                },
-            ),
+                })
+                .merge_by(filler_chunks, |a, b| a.0 <= b.0),
+        )
+        .signs(signs)
+        .row_lines(rows.clone())
+        .filler_rows(fillers)
+        .virtual_text(
+            mark_virtual_text
+                .into_iter()
+                .chain(fold_summaries)
+                .chain(self.cursor_line_blame(view, &theme)),
         );
 
+        if *view.settings().list.read() {
+            let style = self.highlight_id_by_name(HighlightName::WHITESPACE).style(&theme);
+            let style = style.map_or_else(tui::Style::default, Into::into);
+            lines = lines.list_chars(*view.settings().list_chars.read(), style);
+        }
+
         lines.render_(area, surface)
     }
+
+    /// The single-line ranges within `range` concealed by the syntax or marks of the view's buffer,
+    /// sorted and disjoint. Empty if the view does not conceal text.
+    fn conceals(
+        &self,
+        view: &View,
+        range: PointRange,
+        byte_range: Range<usize>,
+    ) -> Vec<(PointRange, Conceal)> {
+        if *view.settings().conceal_level.read() == 0 {
+            return vec![];
+        }
+
+        let buf = self.buffer(view.buffer());
+        let text = buf.text();
+        let syntax_conceals = buf.syntax().map_or(vec![], |syntax| {
+            syntax.conceals(&mut tree_sitter::QueryCursor::new(), text, range)
+        });
+        let mark_conceals = buf.marks(byte_range).filter_map(|(_, range, mark)| {
+            Some((text.byte_range_to_point_range(&range), mark.conceal()?))
+        });
+
+        let cursor_line = view.cursor().line();
+        let mut conceals = syntax_conceals
+            .into_iter()
+            .chain(mark_conceals)
+            .flat_map(|(range, conceal)| range.explode(text).map(move |range| (range, conceal)))
+            // The cursor line is always revealed so the cursor is over the text it is on.
+            .filter(|(range, _)| !range.is_empty() && range.start().line() != cursor_line)
+            .collect::<Vec<_>>();
+
+        conceals.sort_by_key(|(range, _)| range.start());
+        // Overlapping ranges are merged into the first of them.
+        conceals.dedup_by(|(range, _), (prev, _)| {
+            let overlaps = range.start() < prev.end();
+            if overlaps {
+                *prev = PointRange::new(prev.start(), prev.end().max(range.end()));
+            }
+            overlaps
+        });
+        conceals
+    }
+
+    /// The blame of the cursor line of the active view to display after it, if enabled.
+    fn cursor_line_blame(&self, view: &View, theme: &Theme) -> Option<(usize, String, tui::Style)> {
+        if view.id() != self.tree.active() || !*self[view.buffer()].settings().git_blame.read() {
+            return None;
+        }
+
+        let line = view.cursor().line();
+        let blame = self.git_blame(view.buffer(), line)?;
+        let default_style = theme.default_style();
+        let style = self
+            .highlight_id_by_name(HighlightName::GIT_BLAME)
+            .style(theme)
+            .map_or(default_style, |style| default_style.merge(style));
+        Some((line, blame.to_string(), style.into()))
+    }
+}
+
+/// Replace the concealed text of the chunks depending on the conceal `level`, see [`Conceal`].
+/// `conceals` must be single-line ranges that are sorted and disjoint.
+fn conceal<'a, S: Copy + 'a>(
+    chunks: impl Iterator<Item = (usize, Cow<'a, str>, Option<S>)> + 'a,
+    conceals: Vec<(PointRange, Conceal)>,
+    level: u8,
+    style: Option<S>,
+) -> impl Iterator<Item = (usize, Cow<'a, str>, Option<S>)> + 'a {
+    fn slice<'a>(text: &Cow<'a, str>, range: Range<usize>) -> Cow<'a, str> {
+        match text {
+            Cow::Borrowed(text) => Cow::Borrowed(&text[range]),
+            Cow::Owned(text) => Cow::Owned(text[range].to_owned()),
+        }
+    }
+
+    let mut conceals = conceals.into_iter().peekable();
+    // The line and column of the start of the next chunk.
+    let (mut line, mut col) = (0, 0);
+    chunks.flat_map(move |(chunk_line, text, chunk_style)| {
+        if chunk_line != line {
+            (line, col) = (chunk_line, 0);
+        }
+        let (start, end) = (col, col + text.len());
+        col = end;
+
+        let mut pieces = vec![];
+        // The column up to which the chunk has been emitted.
+        let mut i = start;
+        while let Some(&(range, conceal)) = conceals.peek() {
+            let (conceal_start, conceal_end) = (range.start().col(), range.end().col());
+            if range.start().line() > line || (range.start().line() == line && conceal_start >= end)
+            {
+                break;
+            }
+
+            if range.start().line() < line || conceal_end <= i {
+                conceals.next();
+                continue;
+            }
+
+            if conceal_start > i {
+                pieces.push((line, slice(&text, i - start..conceal_start - start), chunk_style));
+            }
+
+            // A range spanning multiple chunks is substituted once, in the chunk it starts in.
+            if conceal_start >= start {
+                let substitute = match level {
+                    1 => Some(conceal.substitute.unwrap_or(' ')),
+                    2 => conceal.substitute,
+                    _ => None,
+                };
+                pieces.extend(substitute.map(|c| (line, Cow::Owned(c.to_string()), style)));
+            }
+
+            i = conceal_end.min(end);
+            if conceal_end > end {
+                break;
+            }
+            conceals.next();
+        }
+
+        // Every line needs a chunk to be rendered, even if it's entirely concealed.
+        if i < end || pieces.is_empty() {
+            pieces.push((line, slice(&text, i - start..end - start), chunk_style));
+        }
+        pieces
+    })
+}
+
+/// Approximate 24-bit colors with the closest color in the xterm 256 color palette.
+fn approximate_colors(surface: &mut tui::Buffer) {
+    for cell in &mut surface.content {
+        cell.fg = approximate_color(cell.fg);
+        cell.bg = approximate_color(cell.bg);
+    }
+}
+
+fn approximate_color(color: tui::Color) -> tui::Color {
+    const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+    let tui::Color::Rgb(r, g, b) = color else { return color };
+
+    let nearest_level =
+        |c: u8| (0..CUBE_LEVELS.len()).min_by_key(|&i| CUBE_LEVELS[i].abs_diff(c)).unwrap() as u8;
+    let distance = |(x, y, z): (u8, u8, u8)| {
+        [(x, r), (y, g), (z, b)].iter().map(|&(a, b)| (a.abs_diff(b) as u32).pow(2)).sum::<u32>()
+    };
+
+    // The 6x6x6 color cube occupies indices 16..232.
+    let (lr, lg, lb) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (CUBE_LEVELS[lr as usize], CUBE_LEVELS[lg as usize], CUBE_LEVELS[lb as usize]);
+    let cube_idx = 16 + 36 * lr + 6 * lg + lb;
+
+    // The grayscale ramp occupies indices 232..256 with values 8, 18, .., 238.
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let gray_step = (avg.saturating_sub(3) / 10).min(23);
+    let gray = 8 + 10 * gray_step;
+    let gray_idx = 232 + gray_step;
+
+    if distance((gray, gray, gray)) < distance(cube) {
+        tui::Color::Indexed(gray_idx)
+    } else {
+        tui::Color::Indexed(cube_idx)
+    }
 }
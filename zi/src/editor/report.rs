@@ -0,0 +1,41 @@
+use super::*;
+
+/// What was last reported to the frontend via [`TerminalRequest`]s, to only report changes.
+#[derive(Debug, Default)]
+pub(super) struct Reported {
+    title: Option<String>,
+    cwd: Option<PathBuf>,
+}
+
+impl Editor {
+    /// The title of the terminal window, the current file and mode.
+    pub fn terminal_title(&self) -> String {
+        let file = self.status_segment(Segment::File);
+        let dirty = self.status_segment(Segment::Dirty);
+        let mode = self.status_segment(Segment::Mode);
+        match dirty.as_str() {
+            "" => format!("{file} ({mode}) - zi"),
+            _ => format!("{file} {dirty} ({mode}) - zi"),
+        }
+    }
+
+    /// Request the terminal title and working directory be set if they changed since they were
+    /// last reported, see [`Settings::title`] and [`Settings::report_cwd`].
+    pub(super) fn report_changes(&mut self) {
+        let title = self.settings.title.read().then(|| self.terminal_title());
+        if title != self.reported.title {
+            if let Some(title) = &title {
+                self.terminal_requests.push(TerminalRequest::SetTitle(title.clone()));
+            }
+            self.reported.title = title;
+        }
+
+        let cwd = self.settings.report_cwd.read().then(|| self.current_dir(Active).to_path_buf());
+        if cwd != self.reported.cwd {
+            if let Some(cwd) = &cwd {
+                self.terminal_requests.push(TerminalRequest::SetCwd(cwd.clone()));
+            }
+            self.reported.cwd = cwd;
+        }
+    }
+}
@@ -100,13 +100,17 @@ pub(super) fn searcher() -> Searcher {
         .build()
 }
 
+/// A sink calling `F` with the line number, the line, and the byte range of the first match of
+/// `M` within the line for each matching line.
 #[derive(Clone, Debug)]
-pub(crate) struct Sink<F>(pub F)
+pub(crate) struct Sink<M, F>(pub M, pub F)
 where
+    M: Matcher,
     F: FnMut(u64, &str, ops::Range<usize>) -> io::Result<bool>;
 
-impl<F> grep::searcher::Sink for Sink<F>
+impl<M, F> grep::searcher::Sink for Sink<M, F>
 where
+    M: Matcher,
     F: FnMut(u64, &str, ops::Range<usize>) -> Result<bool, io::Error>,
 {
     type Error = io::Error;
@@ -125,12 +129,13 @@ where
             }
         };
 
-        let start_byte = mat.absolute_byte_offset() as usize;
-        let mut byte_range = start_byte..start_byte + matched.len();
-        if matched.ends_with('\n') {
-            byte_range.end -= 1;
-        }
+        // The searcher only reports the matching line, so the match within it is found here.
+        let byte_range = match self.0.find(mat.bytes()) {
+            Ok(Some(m)) => m.start()..m.end(),
+            Ok(None) => 0..0,
+            Err(err) => return Err(io::Error::error_message(err)),
+        };
 
-        (self.0)(line_number, matched, byte_range)
+        (self.1)(line_number, matched, byte_range)
     }
 }
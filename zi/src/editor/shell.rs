@@ -0,0 +1,164 @@
+use std::ops::Range;
+use std::process::Stdio;
+
+use super::*;
+use crate::buffer::OutputSink;
+
+impl Editor {
+    /// Pipe `lines` of the buffer in the view through the shell command `cmd` and replace them with its output.
+    /// The lines are only replaced if the command succeeds and the buffer has not changed in the meantime,
+    /// anything written to stderr is shown as an error.
+    pub fn filter(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        lines: Range<usize>,
+        cmd: &str,
+    ) -> Result<impl Future<Output = Result<()>> + 'static> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        if self[buf].flags().contains(BufferFlags::READONLY) {
+            bail!(EditError::Readonly);
+        }
+
+        let text = self[buf].text();
+        let start = text.try_line_to_byte(lines.start).unwrap_or_else(|| text.len_bytes());
+        let end = text.try_line_to_byte(lines.end).unwrap_or_else(|| text.len_bytes());
+        let input = text.byte_slice(start..end).to_cow().into_owned();
+        let trailing_newline = input.ends_with('\n');
+        let version = self[buf].version();
+
+        let mut child = shell_command(cmd)
+            .current_dir(self.current_dir(view))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| anyhow!("failed to run `{cmd}`: {err}"))?;
+
+        let cmd = cmd.to_string();
+        let client = self.client();
+        Ok(async move {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            // Write the input concurrently with reading the output, otherwise a command that
+            // produces output before consuming all its input can deadlock on a full pipe.
+            let write = async move {
+                // The command is free to not read its input.
+                let _ = stdin.write_all(input.as_bytes()).await;
+            };
+            let ((), output) = tokio::join!(write, child.wait_with_output());
+            let output = output?;
+
+            let stderr = String::from_utf8_lossy(&output.stderr).trim_end().to_string();
+            if !output.status.success() {
+                if stderr.is_empty() {
+                    bail!("`{cmd}` failed: {}", output.status);
+                }
+                bail!(stderr);
+            }
+
+            let mut stdout = String::from_utf8(output.stdout)
+                .map_err(|_| anyhow!("output of `{cmd}` is not valid UTF-8"))?;
+            // Keep the lines after the range on their own line.
+            if trailing_newline && !stdout.is_empty() && !stdout.ends_with('\n') {
+                stdout.push('\n');
+            }
+
+            client
+                .with(move |editor| {
+                    if editor[buf].version() != version {
+                        bail!("buffer changed while running `{cmd}`, not replacing");
+                    }
+
+                    editor.edit(buf, &Deltas::new([Delta::new(start..end, stdout)]))?;
+                    editor[buf].snapshot(SnapshotFlags::empty());
+                    if editor.views.get(view).is_some_and(|view| view.buffer() == buf) {
+                        editor.set_cursor(view, Point::new(lines.start, 0));
+                    }
+
+                    if !stderr.is_empty() {
+                        editor.set_error(stderr);
+                    }
+                    Ok(())
+                })
+                .await
+        })
+    }
+
+    /// Run the shell command `cmd` in the working directory and show its output in a split below.
+    /// The returned future resolves once the command exits or is killed with `<C-c>` in the output buffer.
+    pub fn shell(
+        &mut self,
+        cmd: &str,
+    ) -> Result<impl Future<Output = Result<()>> + Send + 'static> {
+        let mut child = shell_command(cmd)
+            .current_dir(self.current_dir(Active))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| anyhow!("failed to run `{cmd}`: {err}"))?;
+
+        let sink = OutputSink::new(request_redraw);
+        let (kill, mut killed) = oneshot::channel();
+        let buf = self
+            .buffers
+            .insert_with_key(|id| Buffer::new(OutputBuffer::new(id, sink.clone(), kill)));
+        self.open_placed(
+            BufferKind::Output,
+            Placement::Dock { edge: Direction::Down, size: tui::Constraint::Percentage(30) },
+            buf,
+        );
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        Ok(async move {
+            let output = async { tokio::try_join!(pump(stdout, &sink), pump(stderr, &sink)) };
+            tokio::select! {
+                res = output => { res?; }
+                // The sender being dropped means the output buffer is gone, kill the command too.
+                _ = &mut killed => child.start_kill()?,
+            }
+
+            let status = child.wait().await?;
+            sink.push(&format!("\n[{status}]\n"));
+            Ok(())
+        })
+    }
+
+    /// Enter command mode with a filter over `lines` prefilled, as done by the `!` operator.
+    pub(crate) fn prompt_filter(&mut self, lines: Range<usize>) {
+        let range = if lines.len() <= 1 {
+            format!("{}", lines.start + 1)
+        } else {
+            format!("{},{}", lines.start + 1, lines.end)
+        };
+        self.command_mode_with(&format!("{range}!"));
+    }
+}
+
+/// Push the output of `reader` to `sink` a line at a time.
+async fn pump(reader: impl tokio::io::AsyncRead + Unpin, sink: &OutputSink) -> io::Result<()> {
+    let mut reader = tokio::io::BufReader::new(reader);
+    let mut line = vec![];
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        sink.push(&String::from_utf8_lossy(&line));
+        line.clear();
+    }
+    Ok(())
+}
+
+fn shell_command(cmd: &str) -> tokio::process::Command {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(cmd);
+    command
+}
@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use zi_marktree::Bias;
+
+use super::*;
+use crate::snippet::{Snippet, Snippets};
+use crate::{Mark, MarkId};
+
+const NAMESPACE: &str = "snippet";
+
+/// An expanded snippet, its tabstops are visited with `<Tab>` and `<S-Tab>` in insert mode.
+#[derive(Debug)]
+pub(super) struct SnippetSession {
+    buf: BufferId,
+    /// The marks of the tabstops in the order they are visited.
+    tabstops: Vec<MarkId>,
+    current: usize,
+    /// Whether the placeholder of the current tabstop is replaced by the next typed character.
+    placeholder_selected: bool,
+}
+
+impl Editor {
+    /// Load the snippets in `dir`. Snippets are defined per filetype in `{filetype}.json` files in
+    /// the format of VSCode or `{filetype}.toml` files with the same structure, and are completed
+    /// by their prefix. The snippets in `all.json` or `all.toml` apply to every filetype.
+    pub fn load_snippets(&mut self, dir: impl AsRef<Path>) -> Result<()> {
+        self.snippets.load(dir.as_ref())
+    }
+
+    pub(crate) fn snippets(&self) -> &Snippets {
+        &self.snippets
+    }
+
+    /// Insert the snippet at the cursor and move to its first tabstop. The snippet is in the syntax
+    /// of the language server protocol, e.g. `fn ${1:name}($2) {\n\t$0\n}`.
+    pub fn insert_snippet(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        snippet: &str,
+    ) -> Result<(), EditError> {
+        let view = selector.select(self);
+        let at = self.cursor_byte(view);
+        self.expand_snippet(view, at..at, snippet)
+    }
+
+    /// Replace `range` of the buffer of the view with the snippet and move to its first tabstop.
+    pub(super) fn expand_snippet(
+        &mut self,
+        view: ViewId,
+        range: Range<usize>,
+        snippet: &str,
+    ) -> Result<(), EditError> {
+        let buf = self[view].buffer();
+        let path = self[buf].file_path();
+        let snippet = Snippet::parse(snippet, |name| snippet_variable(path.as_deref(), name));
+
+        // Continue the indentation of the line on the following lines of the snippet.
+        let text = self[buf].text();
+        let line = text.line(text.byte_to_line(range.start)).map(|line| line.to_cow().into_owned());
+        let indent = line
+            .as_deref()
+            .map_or("", |line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]);
+        let tab = match *self[buf].settings().indent.read() {
+            IndentSettings::Spaces(n) => " ".repeat(n as usize),
+            IndentSettings::Tabs => "\t".to_string(),
+        };
+        let snippet = snippet.indent(indent, &tab);
+
+        self.end_snippet_session();
+        self.edit(buf, &Deltas::single(range.clone(), snippet.text().to_string()))?;
+
+        let ns = self.create_namespace(NAMESPACE);
+        let tabstops = snippet
+            .tabstops()
+            .iter()
+            .map(|tabstop| {
+                let mark = Mark::builder(range.start + tabstop.start).width(tabstop.len());
+                // Text typed at the start of a placeholder belongs to it.
+                let mark = if tabstop.is_empty() { mark } else { mark.start_bias(Bias::Left) };
+                self.create_mark(buf, ns, mark)
+            })
+            .collect();
+
+        self.snippet_session =
+            Some(SnippetSession { buf, tabstops, current: 0, placeholder_selected: false });
+        self.jump_to_tabstop(view, 0);
+        Ok(())
+    }
+
+    /// Move to the next tabstop of the expanded snippet, returns whether there was one.
+    pub fn next_tabstop(&mut self) -> bool {
+        let Some(session) = &self.snippet_session else { return false };
+        let idx = session.current + 1;
+        self.jump_to_tabstop(self.tree.active(), idx)
+    }
+
+    /// Move to the previous tabstop of the expanded snippet, returns whether there was one.
+    pub fn prev_tabstop(&mut self) -> bool {
+        let Some(session) = &self.snippet_session else { return false };
+        let Some(idx) = session.current.checked_sub(1) else { return false };
+        self.jump_to_tabstop(self.tree.active(), idx)
+    }
+
+    /// Move the cursor to the start of the tabstop and select its placeholder. The snippet is done
+    /// once the last tabstop is reached.
+    fn jump_to_tabstop(&mut self, view: ViewId, idx: usize) -> bool {
+        let Some(session) = &self.snippet_session else { return false };
+        let buf = session.buf;
+        let Some(range) = session
+            .tabstops
+            .get(idx)
+            .filter(|_| self[view].buffer() == buf)
+            .and_then(|&mark| self.tabstop_range(buf, mark))
+        else {
+            self.end_snippet_session();
+            return false;
+        };
+
+        self.set_cursor_bytewise(view, range.start);
+        let Some(session) = &mut self.snippet_session else { unreachable!() };
+        session.current = idx;
+        session.placeholder_selected = !range.is_empty();
+        if idx + 1 == session.tabstops.len() {
+            self.end_snippet_session();
+        }
+        true
+    }
+
+    fn tabstop_range(&self, buf: BufferId, mark: MarkId) -> Option<Range<usize>> {
+        let ns = self.namespace_by_name(NAMESPACE)?;
+        self.marks(buf, ..)
+            .find(|(id, _, m)| *id == ns && m.id() == mark)
+            .map(|(_, range, _)| range)
+    }
+
+    pub(super) fn end_snippet_session(&mut self) {
+        let Some(session) = self.snippet_session.take() else { return };
+        if let Some(ns) = self.namespace_by_name(NAMESPACE) {
+            if self.buffers.contains_key(session.buf) {
+                self.clear_marks(session.buf, ns, ..);
+            }
+        }
+    }
+
+    /// The range of the selected placeholder if it starts at `byte`, the placeholder is replaced by
+    /// the character typed there.
+    pub(super) fn take_snippet_placeholder(
+        &mut self,
+        view: ViewId,
+        byte: usize,
+    ) -> Option<Range<usize>> {
+        let buf = self[view].buffer();
+        let session = self.snippet_session.as_mut()?;
+        if !mem::take(&mut session.placeholder_selected) || session.buf != buf {
+            return None;
+        }
+
+        let mark = session.tabstops[session.current];
+        self.tabstop_range(buf, mark).filter(|range| range.start == byte && !range.is_empty())
+    }
+}
+
+/// The value of the snippet variable, e.g. `$TM_FILENAME`.
+fn snippet_variable(path: Option<&Path>, name: &str) -> Option<String> {
+    let path = path?;
+    let s = match name {
+        "TM_FILEPATH" => path.as_os_str(),
+        "TM_FILENAME" => path.file_name()?,
+        "TM_FILENAME_BASE" => path.file_stem()?,
+        "TM_DIRECTORY" => path.parent()?.as_os_str(),
+        _ => return None,
+    };
+    Some(s.to_string_lossy().into_owned())
+}
@@ -0,0 +1,510 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write as _;
+use std::ops::{Bound, Range, RangeBounds};
+
+use super::*;
+use crate::spell::{self, Dictionary};
+use crate::syntax::HighlightName;
+use crate::{Mark, dirs, lstypes};
+
+const NAMESPACE: &str = "spell";
+
+/// How long the visible lines must stay unchanged before their misspelled words are marked.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The number of lines checked at a time when looking for the next or previous misspelled word.
+const SEARCH_LINES: usize = 256;
+
+/// The directories searched for the dictionary of a language after `spell` in the config directory.
+const SYSTEM_DICTIONARY_DIRS: &[&str] =
+    &["/usr/share/hunspell", "/usr/share/myspell", "/usr/share/myspell/dicts"];
+
+#[derive(Debug, Default)]
+pub(super) struct SpellState {
+    /// The dictionary of each language, `None` if it couldn't be loaded.
+    dictionaries: HashMap<String, Option<Arc<Dictionary>>>,
+    /// The words of each spell file, these are added with `zg`.
+    good_words: HashMap<PathBuf, Arc<HashSet<String>>>,
+    /// What the spell marks of each visible buffer are, or are about to be, computed for.
+    marked: HashMap<BufferId, SpellCheck>,
+    generation: u64,
+}
+
+/// The state of a buffer its spell marks depend on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SpellCheck {
+    version: u32,
+    /// The visible lines of the buffer.
+    lines: Vec<Range<usize>>,
+    /// The languages to check the lines in, `None` if spell checking is disabled.
+    langs: Option<Vec<String>>,
+}
+
+impl Editor {
+    /// Add the dictionary of a language from the contents of its `.dic` and `.aff` files, replacing
+    /// the dictionary that would otherwise be loaded for it.
+    pub fn add_spell_dictionary(&mut self, lang: &str, dic: &str, aff: &str) -> Result<()> {
+        let dictionary = Dictionary::parse(dic, aff)?;
+        self.spell.dictionaries.insert(lang.to_string(), Some(Arc::new(dictionary)));
+        self.spell.marked.clear();
+        Ok(())
+    }
+
+    /// Move the cursor to the start of the next misspelled word after it.
+    pub fn goto_next_misspelling(
+        &mut self,
+        selector: impl Selector<ViewId>,
+    ) -> Option<Range<usize>> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let cursor = self.cursor_byte(view);
+        // Check a few lines at a time to stop at the first misspelled word.
+        let range = (self[view].cursor().line()..self[buf].text().len_lines())
+            .step_by(SEARCH_LINES)
+            .find_map(|start| {
+                let misspellings = self.misspellings(buf, start..start + SEARCH_LINES);
+                misspellings.into_iter().find(|range| range.start > cursor)
+            })?;
+        self.set_cursor_bytewise(view, range.start);
+        Some(range)
+    }
+
+    /// Move the cursor to the start of the previous misspelled word before it.
+    pub fn goto_prev_misspelling(
+        &mut self,
+        selector: impl Selector<ViewId>,
+    ) -> Option<Range<usize>> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let cursor = self.cursor_byte(view);
+        let lines = 0..self[view].cursor().line() + 1;
+        let range = lines.rev().step_by(SEARCH_LINES).find_map(|end| {
+            let start = end.saturating_sub(SEARCH_LINES - 1);
+            self.misspellings(buf, start..=end).into_iter().rfind(|range| range.start < cursor)
+        })?;
+        self.set_cursor_bytewise(view, range.start);
+        Some(range)
+    }
+
+    /// The corrections of the word under the cursor, the closest first.
+    pub fn spell_suggestions(&mut self, selector: impl Selector<ViewId>) -> Vec<String> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let Some((_, word)) = self.word_at_cursor(view) else { return vec![] };
+        self.load_dictionaries(buf);
+
+        let mut seen = HashSet::new();
+        self.spell_langs(buf)
+            .iter()
+            .filter_map(|lang| self.spell.dictionaries.get(lang)?.as_ref())
+            .flat_map(|dictionary| dictionary.suggest(&word))
+            .filter(|suggestion| seen.insert(suggestion.clone()))
+            .collect()
+    }
+
+    /// Open a picker of the corrections of the word under the cursor, `z=`.
+    pub fn open_spell_suggestions(
+        &mut self,
+        selector: impl Selector<ViewId>,
+    ) -> Option<ViewGroupId> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let Some((range, _)) = self.word_at_cursor(view) else {
+            self.set_error("no word under cursor");
+            return None;
+        };
+
+        let suggestions = self.spell_suggestions(view);
+        if suggestions.is_empty() {
+            self.set_error("no spelling suggestions");
+            return None;
+        }
+
+        let point_range = self[buf].text().byte_range_to_point_range(&range);
+        let ratio = *self.settings().generic_picker_split_ratio.read();
+        let vg_id = self.open_static_picker::<SpellPicker>(
+            Url::parse("view-group://spell-suggestions").unwrap(),
+            "spell suggestions",
+            ratio,
+            move |_editor, injector| {
+                for word in suggestions {
+                    let entry = SpellSuggestion { buf, range: range.clone(), point_range, word };
+                    if let Err(()) = injector.push(entry) {
+                        break;
+                    }
+                }
+            },
+        );
+
+        Some(vg_id)
+    }
+
+    /// Add the word under the cursor to the spell file so it is no longer misspelled, `zg`.
+    pub fn add_good_word(&mut self, selector: impl Selector<ViewId>) -> Result<()> {
+        let view = selector.select(self);
+        let buf = self[view].buffer();
+        let Some((_, word)) = self.word_at_cursor(view) else { bail!("no word under cursor") };
+
+        let path = self.spell_file(buf);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{word}")?;
+
+        self.load_good_words(&path);
+        Arc::make_mut(self.spell.good_words.entry(path).or_default()).insert(word);
+        self.spell.marked.clear();
+        request_redraw();
+        Ok(())
+    }
+
+    /// Mark the misspelled words of the visible lines of buffers with spell checking enabled once
+    /// they stop changing.
+    pub(super) fn schedule_spell_marks(&mut self) {
+        let mut checks = HashMap::<BufferId, SpellCheck>::default();
+        for view in self.tree.views() {
+            let view = &self.views[view];
+            let buf = view.buffer();
+            let start = view.offset().line;
+            let height = self.tree.view_area(view.id()).height as usize;
+            let check = checks.entry(buf).or_insert_with(|| SpellCheck {
+                version: self[buf].version(),
+                lines: vec![],
+                langs: self[buf].settings().spell.read().then(|| self.spell_langs(buf)),
+            });
+            check.lines.push(start..start + height);
+        }
+
+        if checks == self.spell.marked {
+            return;
+        }
+
+        self.spell.marked = checks;
+        self.spell.generation += 1;
+        let generation = self.spell.generation;
+        let client = self.client();
+        self.spawn("spell", async move {
+            tokio::time::sleep(DEBOUNCE).await;
+            client
+                .with(move |editor| {
+                    if editor.spell.generation == generation {
+                        editor.update_spell_marks(generation);
+                    }
+                })
+                .await;
+            Ok(())
+        });
+    }
+
+    /// Replace the spell marks of the visible buffers with their misspelled words. The words are
+    /// checked, and any dictionaries that haven't been loaded yet are loaded, on the rayon pool.
+    fn update_spell_marks(&mut self, generation: u64) {
+        let ns = self.create_namespace(NAMESPACE);
+        let mut jobs = vec![];
+        for (buf, check) in self.spell.marked.clone() {
+            let Some(langs) = check.langs else {
+                self.clear_marks(buf, ns, ..);
+                continue;
+            };
+
+            // Which words are in comments or strings depends on the syntax tree.
+            self[buf].reparse();
+            let path = self.spell_file(buf);
+            self.load_good_words(&path);
+            let good_words = Arc::clone(&self.spell.good_words[&path]);
+            let text = self[buf].text();
+            let regions = check
+                .lines
+                .into_iter()
+                .flat_map(|lines| self.spell_regions(buf, lines))
+                .map(|region| (region.start, text.byte_slice(region).to_cow().into_owned()))
+                .collect::<Vec<_>>();
+            jobs.push((buf, langs, good_words, regions));
+        }
+
+        let cached = self.spell.dictionaries.clone();
+        let client = self.client();
+        pool().spawn(move || {
+            let mut loaded = HashMap::<String, Result<Arc<Dictionary>>>::default();
+            let marked = jobs
+                .into_iter()
+                .map(|(buf, langs, good_words, regions)| {
+                    let dictionaries = langs
+                        .iter()
+                        .filter_map(|lang| match cached.get(lang) {
+                            Some(dictionary) => dictionary.clone(),
+                            None => loaded
+                                .entry(lang.clone())
+                                .or_insert_with(|| load_dictionary(lang).map(Arc::new))
+                                .as_ref()
+                                .ok()
+                                .cloned(),
+                        })
+                        .collect::<Vec<_>>();
+                    // Without a dictionary every word would be misspelled.
+                    if dictionaries.is_empty() {
+                        return (buf, vec![]);
+                    }
+
+                    let mut misspellings = regions
+                        .iter()
+                        .flat_map(|(start, s)| misspelled(&dictionaries, &good_words, *start, s))
+                        .collect::<Vec<_>>();
+                    // Views of the same buffer may show the same lines.
+                    misspellings.sort_by_key(|range| range.start);
+                    misspellings.dedup();
+                    (buf, misspellings)
+                })
+                .collect::<Vec<_>>();
+
+            client.send(move |editor| {
+                for (lang, dictionary) in loaded {
+                    let dictionary = dictionary.map_err(|err| editor.set_error(err)).ok();
+                    editor.spell.dictionaries.entry(lang).or_insert(dictionary);
+                }
+
+                // The buffers may have changed or scrolled while they were being checked.
+                if editor.spell.generation != generation {
+                    return Ok(());
+                }
+
+                let hl = editor.highlight_id_by_name(HighlightName::SPELL_BAD);
+                for (buf, misspellings) in marked {
+                    let marks = misspellings
+                        .into_iter()
+                        .map(|range| Mark::builder(range.start).width(range.len()).hl(hl))
+                        .collect::<Vec<_>>();
+                    editor[buf].replace_marks(ns, marks);
+                }
+                request_redraw();
+                Ok(())
+            });
+        });
+    }
+
+    /// The byte ranges of the misspelled words of `lines` of the buffer, none if spell checking is
+    /// disabled. Only comments and strings are checked unless the buffer is plain text.
+    fn misspellings(&mut self, buf: BufferId, lines: impl RangeBounds<usize>) -> Vec<Range<usize>> {
+        if !*self[buf].settings().spell.read() {
+            return vec![];
+        }
+
+        // Which words are in comments or strings depends on the syntax tree.
+        self[buf].reparse();
+        self.load_dictionaries(buf);
+        let path = self.spell_file(buf);
+        self.load_good_words(&path);
+
+        let langs = self.spell_langs(buf);
+        let dictionaries = langs
+            .iter()
+            .filter_map(|lang| self.spell.dictionaries.get(lang)?.clone())
+            .collect::<Vec<_>>();
+        // Without a dictionary every word would be misspelled.
+        if dictionaries.is_empty() {
+            return vec![];
+        }
+
+        let good_words = &self.spell.good_words[&path];
+        let text = self[buf].text();
+        self.spell_regions(buf, lines)
+            .into_iter()
+            .flat_map(|region| {
+                let s = text.byte_slice(region.clone()).to_cow();
+                misspelled(&dictionaries, good_words, region.start, &s).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The byte ranges of `lines` of the buffer to spell check, ordered and disjoint.
+    fn spell_regions(&self, buf: BufferId, lines: impl RangeBounds<usize>) -> Vec<Range<usize>> {
+        let buffer = &self[buf];
+        let text = buffer.text();
+        let start = match lines.start_bound() {
+            Bound::Included(&line) => line,
+            Bound::Excluded(&line) => line + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match lines.end_bound() {
+            Bound::Included(&line) => line + 1,
+            Bound::Excluded(&line) => line,
+            Bound::Unbounded => usize::MAX,
+        };
+        let line_byte = |line| text.try_line_to_byte(line).unwrap_or_else(|| text.len_bytes());
+
+        if buffer.file_type() == filetype!(text) {
+            return vec![line_byte(start)..line_byte(end)];
+        }
+
+        let mut query_cursor = tree_sitter::QueryCursor::new();
+        let mut regions = buffer
+            .syntax_highlights(self, &mut query_cursor, PointRange::new((start, 0), (end, 0)))
+            .filter(|hl| {
+                hl.capture_name.starts_with("comment") || hl.capture_name.starts_with("string")
+            })
+            .map(|hl| text.point_range_to_byte_range(hl.range))
+            .collect::<Vec<_>>();
+        regions.sort_by_key(|range| range.start);
+
+        // Captures may be nested, e.g. an escape sequence within a string.
+        let mut merged = Vec::<Range<usize>>::with_capacity(regions.len());
+        for range in regions {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// The byte range of the word under the cursor and the word.
+    fn word_at_cursor(&self, view: ViewId) -> Option<(Range<usize>, String)> {
+        let text = self[self[view].buffer()].text();
+        let cursor = self[view].cursor();
+        let line = text.line(cursor.line())?.to_cow();
+        let line_start = text.line_to_byte(cursor.line());
+        let (i, word) =
+            spell::words(&line).find(|&(i, word)| (i..i + word.len()).contains(&cursor.col()))?;
+        Some((line_start + i..line_start + i + word.len(), word.to_string()))
+    }
+
+    /// The languages of the buffer, e.g. `en_US,de_DE`.
+    fn spell_langs(&self, buf: BufferId) -> Vec<String> {
+        let langs = self[buf].settings().spell_lang.read();
+        langs.split(',').map(str::trim).filter(|lang| !lang.is_empty()).map(String::from).collect()
+    }
+
+    /// Load the dictionaries of the languages of the buffer that haven't been loaded yet.
+    fn load_dictionaries(&mut self, buf: BufferId) {
+        for lang in self.spell_langs(buf) {
+            if self.spell.dictionaries.contains_key(&lang) {
+                continue;
+            }
+
+            let dictionary = match load_dictionary(&lang) {
+                Ok(dictionary) => Some(Arc::new(dictionary)),
+                Err(err) => {
+                    self.set_error(err);
+                    None
+                }
+            };
+            self.spell.dictionaries.insert(lang, dictionary);
+        }
+    }
+
+    /// The spell file of the buffer, by default `spell/{lang}.add` in the config directory.
+    fn spell_file(&self, buf: BufferId) -> PathBuf {
+        if let Some(path) = &*self[buf].settings().spell_file.read() {
+            return path.clone();
+        }
+
+        let lang = self.spell_langs(buf).into_iter().next().unwrap_or_default();
+        dirs::config().join("spell").join(format!("{lang}.add"))
+    }
+
+    fn load_good_words(&mut self, path: &Path) {
+        if self.spell.good_words.contains_key(path) {
+            return;
+        }
+
+        let words = match std::fs::read_to_string(path) {
+            Ok(words) => words.lines().map(str::trim).map(String::from).collect(),
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    self.set_error(err);
+                }
+                HashSet::new()
+            }
+        };
+        self.spell.good_words.insert(path.to_path_buf(), Arc::new(words));
+    }
+}
+
+/// Load the dictionary of the language from the first directory that has one.
+fn load_dictionary(lang: &str) -> Result<Dictionary> {
+    let search_dirs = std::iter::once(dirs::config().join("spell"))
+        .chain(SYSTEM_DICTIONARY_DIRS.iter().map(PathBuf::from));
+    search_dirs
+        .map(|dir| Dictionary::load(&dir, lang))
+        .find_map(|dictionary| dictionary.transpose())
+        .transpose()?
+        .ok_or_else(|| anyhow!("no dictionary found for `{lang}`"))
+}
+
+/// The byte ranges of the words of `text` that are in none of the dictionaries or the good words,
+/// offset by `start`.
+fn misspelled<'a>(
+    dictionaries: &'a [Arc<Dictionary>],
+    good_words: &'a HashSet<String>,
+    start: usize,
+    text: &'a str,
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    spell::words(text)
+        .filter(|(_, word)| {
+            !good_words.contains(*word)
+                && !dictionaries.iter().any(|dictionary| dictionary.check(word))
+        })
+        .map(move |(i, word)| start + i..start + i + word.len())
+}
+
+#[derive(Debug, Clone)]
+struct SpellSuggestion {
+    buf: BufferId,
+    /// The byte range of the misspelled word.
+    range: Range<usize>,
+    point_range: PointRange,
+    word: String,
+}
+
+impl fmt::Display for SpellSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.word)
+    }
+}
+
+impl BufferPickerEntry for SpellSuggestion {
+    #[inline]
+    fn buffer_or_path(&self) -> Result<BufferId, &Path> {
+        Ok(self.buf)
+    }
+
+    #[inline]
+    fn point(&self) -> Option<lstypes::EncodedPoint> {
+        Some(self.point_range.start().into())
+    }
+
+    #[inline]
+    fn range(&self) -> Option<lstypes::EncodedRange> {
+        Some(self.point_range.into())
+    }
+}
+
+/// Previews the misspelled word and replaces it with the confirmed suggestion.
+#[derive(Clone, Copy)]
+struct SpellPicker {
+    preview: ViewId,
+}
+
+impl Picker for SpellPicker {
+    type Entry = SpellSuggestion;
+
+    fn new(preview: ViewId) -> Self {
+        Self { preview }
+    }
+
+    fn select(self, editor: &mut Editor, entry: Self::Entry) {
+        BufferPicker::<SpellSuggestion>::new(self.preview).select(editor, entry)
+    }
+
+    fn confirm(self, editor: &mut Editor, entry: Self::Entry) {
+        editor.close_view(self.preview);
+        let SpellSuggestion { buf, range, word, .. } = entry;
+        set_error_if!(editor: editor.edit(buf, &Deltas::single(range.clone(), word)));
+        editor[buf].snapshot(SnapshotFlags::empty());
+        if editor[Active].buffer() == buf {
+            editor.set_cursor_bytewise(Active, range.start);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::surround::PendingSurround;
 use super::{Active, Editor};
 use crate::completion::Completion;
 use crate::{Mode, Operator, Point};
@@ -12,7 +13,9 @@ pub(super) enum State {
     VisualLine(VisualState),
     VisualBlock(VisualState),
     OperatorPending(OperatorPendingState),
-    ReplacePending,
+    /// Waiting for a character, to replace the character under the cursor unless it is for a
+    /// pending surround operation.
+    ReplacePending(Option<PendingSurround>),
 }
 
 impl Default for State {
@@ -31,7 +34,7 @@ impl State {
             Mode::VisualLine => State::VisualLine(VisualState { anchor: editor.cursor(Active) }),
             Mode::VisualBlock => State::VisualBlock(VisualState { anchor: editor.cursor(Active) }),
             Mode::OperatorPending(op) => State::OperatorPending(OperatorPendingState::new(op)),
-            Mode::ReplacePending => State::ReplacePending,
+            Mode::ReplacePending => State::ReplacePending(None),
         }
     }
 
@@ -44,7 +47,7 @@ impl State {
             State::VisualLine(..) => Mode::VisualLine,
             State::VisualBlock(..) => Mode::VisualBlock,
             State::OperatorPending(state) => Mode::OperatorPending(state.operator),
-            State::ReplacePending => Mode::ReplacePending,
+            State::ReplacePending(_) => Mode::ReplacePending,
         }
     }
 
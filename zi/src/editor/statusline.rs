@@ -0,0 +1,228 @@
+use std::mem;
+use std::str::FromStr;
+
+use tui::Widget as _;
+
+use super::*;
+use crate::lstypes::Severity;
+use crate::syntax::HighlightName;
+
+/// The layout of the status line, `:set statusline`.
+///
+/// The layout is text with `{segment}` placeholders, where a segment can be given a highlight
+/// group with `{segment:group}`. `%=` separates the left and right aligned sections, or the left,
+/// center and right aligned sections if there are two of them. Literal braces are written `{{`
+/// and `}}`, e.g. `{mode:keyword} {file}{dirty} %= {diagnostics:error} {lsp} {line}:{col}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine {
+    sections: Vec<Vec<Item>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Text(String),
+    Segment { segment: Segment, highlight: Option<String> },
+}
+
+/// A piece of information shown in the status line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// `mode`, the current mode, e.g. `NORMAL`.
+    Mode,
+    /// `file`, the path of the buffer or its url if it has no file.
+    File,
+    /// `dirty`, `[+]` if the buffer has unsaved changes.
+    Dirty,
+    /// `diagnostics`, the number of diagnostics of each severity in the buffer, e.g. `E2 W1`.
+    Diagnostics,
+    /// `lsp`, the language services of the buffer.
+    Lsp,
+    /// `line`, the 1-indexed line of the cursor.
+    Line,
+    /// `col`, the column of the cursor.
+    Column,
+    /// `percent`, how far through the buffer the cursor line is.
+    Percent,
+    /// `format`, the encoding and line ending of the buffer if they're not the default.
+    Format,
+    /// `error`, the last error.
+    Error,
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        "{file}:{line}:{col} {format}{error:error}".parse().unwrap()
+    }
+}
+
+impl FromStr for StatusLine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sections = s.split("%=").map(parse_section).collect::<Result<Vec<_>>>()?;
+        if sections.len() > 3 {
+            bail!("expected at most 3 sections separated by `%=`");
+        }
+        Ok(Self { sections })
+    }
+}
+
+fn parse_section(mut s: &str) -> Result<Vec<Item>> {
+    let mut items = vec![];
+    let mut text = String::new();
+    while let Some(i) = s.find(['{', '}']) {
+        text.push_str(&s[..i]);
+        let brace = &s[i..i + 1];
+        s = &s[i + 1..];
+        if let Some(rest) = s.strip_prefix(brace) {
+            text.push_str(brace);
+            s = rest;
+            continue;
+        }
+
+        if brace == "}" {
+            bail!("unmatched `}}` in status line");
+        }
+
+        let Some(end) = s.find('}') else { bail!("unclosed `{{` in status line") };
+        let (segment, highlight) = match s[..end].split_once(':') {
+            Some((segment, highlight)) => (segment, Some(highlight.to_string())),
+            None => (&s[..end], None),
+        };
+        if !text.is_empty() {
+            items.push(Item::Text(mem::take(&mut text)));
+        }
+        items.push(Item::Segment { segment: segment.parse()?, highlight });
+        s = &s[end + 1..];
+    }
+
+    text.push_str(s);
+    if !text.is_empty() {
+        items.push(Item::Text(text));
+    }
+    Ok(items)
+}
+
+impl FromStr for Segment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "mode" => Segment::Mode,
+            "file" => Segment::File,
+            "dirty" => Segment::Dirty,
+            "diagnostics" => Segment::Diagnostics,
+            "lsp" => Segment::Lsp,
+            "line" => Segment::Line,
+            "col" | "column" => Segment::Column,
+            "percent" => Segment::Percent,
+            "format" => Segment::Format,
+            "error" => Segment::Error,
+            _ => bail!("unknown status line segment: `{s}`"),
+        })
+    }
+}
+
+impl Editor {
+    pub(super) fn render_status_line(&self, area: tui::Rect, surface: &mut tui::Buffer) {
+        let theme = self.theme();
+        let theme = theme.read();
+        let style =
+            |name: &str| theme.highlight_id_by_name(name).style(&theme).map(tui::Style::from);
+        let base = style(HighlightName::STATUSLINE.as_ref()).unwrap_or_else(|| {
+            tui::Style::new()
+                .fg(tui::Color::Rgb(0x88, 0x88, 0x88))
+                .bg(tui::Color::Rgb(0x07, 0x36, 0x42))
+        });
+        surface.set_style(area, base);
+
+        let statusline = self.settings.statusline.read();
+        let n = statusline.sections.len();
+        for (i, section) in statusline.sections.iter().enumerate() {
+            let spans = section
+                .iter()
+                .map(|item| match item {
+                    Item::Text(text) => tui::Span::styled(text.as_str(), base),
+                    Item::Segment { segment, highlight } => {
+                        let style = highlight.as_deref().and_then(style);
+                        let style = style.map_or(base, |style| base.patch(style));
+                        tui::Span::styled(self.status_segment(*segment), style)
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let line = tui::Line::default().spans(spans);
+            let line = match (i, n) {
+                (0, _) => line.left_aligned(),
+                (1, 3) => line.centered(),
+                _ => line.right_aligned(),
+            };
+            line.render(area, surface);
+        }
+    }
+
+    pub(super) fn status_segment(&self, segment: Segment) -> String {
+        let (view, buf) = get_ref!(self);
+        let cursor = view.cursor();
+        match segment {
+            Segment::Mode => match (self.sub_mode(), self.mode()) {
+                (Some(sub_mode), _) => sub_mode.to_uppercase(),
+                (_, Mode::Normal | Mode::OperatorPending(_) | Mode::ReplacePending) => {
+                    "NORMAL".into()
+                }
+                (_, mode) => mode.to_string(),
+            },
+            Segment::File => buf
+                .file_path()
+                .map_or_else(|| buf.url().to_string(), |path| path.display().to_string()),
+            Segment::Dirty if buf.flags().contains(BufferFlags::DIRTY) => "[+]".into(),
+            Segment::Dirty => String::new(),
+            Segment::Diagnostics => {
+                let Some(diagnostics) =
+                    buf.file_path().and_then(|path| self.diagnostics.get(&path))
+                else {
+                    return String::new();
+                };
+                let diagnostics = diagnostics.read();
+                [
+                    (Severity::Error, 'E'),
+                    (Severity::Warning, 'W'),
+                    (Severity::Info, 'I'),
+                    (Severity::Hint, 'H'),
+                ]
+                .into_iter()
+                .filter_map(|(severity, c)| {
+                    let n = diagnostics.1.iter().filter(|diag| diag.severity == severity).count();
+                    (n > 0).then(|| format!("{c}{n}"))
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+            }
+            Segment::Lsp => active_servers_of!(self, buf.id())
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Segment::Line => (cursor.line() + 1).to_string(),
+            Segment::Column => cursor.col().to_string(),
+            Segment::Percent => {
+                let len_lines = buf.text().len_lines().max(1);
+                format!("{}%", (cursor.line() + 1).min(len_lines) * 100 / len_lines)
+            }
+            Segment::Format => {
+                // Only show the encoding and line ending if they're not the default.
+                let settings = buf.settings();
+                let encoding = *settings.encoding.read();
+                let line_ending = *settings.line_ending.read();
+                [
+                    (encoding != encoding_rs::UTF_8).then(|| encoding.name().to_lowercase()),
+                    (line_ending != LineEnding::Lf).then(|| line_ending.to_string()),
+                ]
+                .into_iter()
+                .flatten()
+                .map(|indicator| format!("[{indicator}] "))
+                .collect()
+            }
+            Segment::Error => self.status_error.clone().unwrap_or_default(),
+        }
+    }
+}
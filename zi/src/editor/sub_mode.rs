@@ -0,0 +1,70 @@
+use super::*;
+
+impl Editor {
+    /// Map the keys `lhs` to the command `cmd` in the sub-mode `name`, defining it if needed.
+    ///
+    /// A sub-mode is layered on top of the current mode with its own mappings, e.g. a window mode
+    /// where `h`, `j`, `k` and `l` move between windows until `<Esc>`. While it's active, keys are
+    /// handled by its mappings and the first key that isn't mapped leaves it and is handled as
+    /// usual. As there is no timeout, a sequence that is the prefix of another runs immediately.
+    pub fn map_sub_mode(&mut self, name: &str, lhs: &str, cmd: &str) -> Result<()> {
+        let keys = self.parse_lhs(lhs)?;
+        cmd.parse::<Command>()?;
+        let mapping = Mapping::Command { cmd: cmd.to_owned() };
+        self.sub_mode_keymap.insert(name.to_owned(), keys, mapping);
+        Ok(())
+    }
+
+    /// Remove the mapping of the keys `lhs` in the sub-mode `name`. The sub-mode is left if it
+    /// has no mappings left.
+    pub fn unmap_sub_mode(&mut self, name: &str, lhs: &str) -> Result<()> {
+        let keys = self.parse_lhs(lhs)?;
+        let name = name.to_owned();
+        self.sub_mode_keymap.remove(&name, keys);
+        if self.sub_mode.as_ref() == Some(&name) && !self.sub_mode_keymap.has_mappings(&name) {
+            self.leave_sub_mode();
+        }
+        Ok(())
+    }
+
+    /// Enter the sub-mode `name`, see [`Editor::map_sub_mode`].
+    pub fn enter_sub_mode(&mut self, name: &str) -> Result<()> {
+        if !self.sub_mode_keymap.has_mappings(&name.to_owned()) {
+            bail!("unknown sub-mode: `{name}`");
+        }
+
+        self.reset_keymap();
+        self.sub_mode_keymap.reset();
+        self.sub_mode = Some(name.to_owned());
+        Ok(())
+    }
+
+    /// Leave the active sub-mode, if any.
+    pub fn leave_sub_mode(&mut self) {
+        self.sub_mode = None;
+    }
+
+    /// The active sub-mode, if any.
+    pub fn sub_mode(&self) -> Option<&str> {
+        self.sub_mode.as_deref()
+    }
+
+    pub(super) fn handle_sub_mode_key(&mut self, name: String, key: KeyEvent) {
+        let (res, buffered) = self.sub_mode_keymap.on_key(name, key);
+        match res {
+            TrieResult::Found(mapping) | TrieResult::Ambiguous(mapping) => {
+                self.sub_mode_keymap.reset();
+                self.run_mapping(mapping);
+            }
+            TrieResult::Partial => (),
+            TrieResult::Nothing => {
+                self.leave_sub_mode();
+                // `<Esc>` only leaves the sub-mode.
+                buffered
+                    .into_iter()
+                    .filter(|key| key.code() != KeyCode::Esc)
+                    .for_each(|key| self.handle_key_event(key));
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::ops::Range;
+
+use zi_textobject::Around;
+use zi_textobject::delimiter::{AngleBracket, Apostrophe, Backtick, Brace, Bracket, Paren, Quote};
+
+use super::*;
+
+/// A surround operation waiting for a character to be typed.
+#[derive(Debug, Clone)]
+pub(super) enum PendingSurround {
+    /// `ys{motion}`, surround the byte range with the delimiters of the character.
+    Add(Range<usize>),
+    /// `cs`, waiting for the delimiter to change.
+    ChangeFrom,
+    /// `cs{old}`, waiting for the delimiter to change `old` to.
+    ChangeTo(char),
+    /// `ds`, waiting for the delimiter to delete.
+    Delete,
+}
+
+impl Editor {
+    /// Surround `range` of the buffer in the view with the delimiters of `c`, `ys`.
+    /// Opening brackets pad the text with a space, closing brackets don't, `b`, `B`, `r` and `a`
+    /// are aliases for `)`, `}`, `]` and `>` respectively. Any other character is used as is.
+    pub fn surround(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        range: Range<usize>,
+        c: char,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let (open, close) = delimiters(c);
+        let deltas = if range.is_empty() {
+            Deltas::insert_at(range.start, format!("{open}{close}"))
+        } else {
+            Deltas::new([Delta::insert_at(range.start, open), Delta::insert_at(range.end, close)])
+        };
+        self.edit(buf, &deltas)?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, range.start);
+        Ok(())
+    }
+
+    /// Replace the delimiters of `old` around the cursor with those of `new`, `cs`.
+    /// Does nothing if the cursor is not surrounded by `old`.
+    pub fn change_surround(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        old: char,
+        new: char,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let Some((open, close)) = self.surrounding(view, old) else { return Ok(()) };
+        let (new_open, new_close) = delimiters(new);
+        let start = open.start;
+        self.edit(buf, &Deltas::new([Delta::new(open, new_open), Delta::new(close, new_close)]))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, start);
+        Ok(())
+    }
+
+    /// Delete the delimiters of `c` around the cursor, `ds`.
+    /// Does nothing if the cursor is not surrounded by `c`.
+    pub fn delete_surround(
+        &mut self,
+        selector: impl Selector<ViewId>,
+        c: char,
+    ) -> Result<(), EditError> {
+        let (view, buf) = self.get(selector);
+        let Some((open, close)) = self.surrounding(view, c) else { return Ok(()) };
+        let start = open.start;
+        self.edit(buf, &Deltas::new([Delta::delete(open), Delta::delete(close)]))?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        self.set_cursor(view, start);
+        Ok(())
+    }
+
+    /// Wait for the character of `ys{motion}` to surround `range` with.
+    pub(crate) fn surround_pending(&mut self, range: Range<usize>) {
+        self.await_surround(PendingSurround::Add(range));
+    }
+
+    /// Wait for the delimiters of `cs`.
+    pub(crate) fn change_surround_pending(&mut self) {
+        self.await_surround(PendingSurround::ChangeFrom);
+    }
+
+    /// Wait for the delimiter of `ds`.
+    pub(crate) fn delete_surround_pending(&mut self) {
+        self.await_surround(PendingSurround::Delete);
+    }
+
+    fn await_surround(&mut self, surround: PendingSurround) {
+        self.set_mode(Mode::ReplacePending);
+        if let State::ReplacePending(pending) = &mut self.state {
+            *pending = Some(surround);
+        }
+    }
+
+    /// Handle a character typed for a pending surround operation.
+    pub(super) fn surround_char(
+        &mut self,
+        surround: PendingSurround,
+        c: char,
+    ) -> Result<(), EditError> {
+        if let PendingSurround::ChangeFrom = surround {
+            self.await_surround(PendingSurround::ChangeTo(c));
+            return Ok(());
+        }
+
+        self.set_mode(Mode::Normal);
+        match surround {
+            PendingSurround::Add(range) => self.surround(Active, range, c),
+            PendingSurround::ChangeFrom => unreachable!("handled above"),
+            PendingSurround::ChangeTo(old) => self.change_surround(Active, old, c),
+            PendingSurround::Delete => self.delete_surround(Active, c),
+        }
+    }
+
+    /// The byte ranges of the opening and closing delimiters of `c` around the cursor.
+    fn surrounding(&self, view: ViewId, c: char) -> Option<(Range<usize>, Range<usize>)> {
+        let text = self[self[view].buffer()].text();
+        let byte = text.point_to_byte(self[view].cursor());
+        let obj = around(c)?;
+        // The cursor may be on the opening delimiter, which isn't found searching backwards.
+        let range = obj.byte_range(text, byte).or_else(|| {
+            let next = byte + text.char_at_byte(byte)?.len_utf8();
+            obj.byte_range(text, next)
+        })?;
+        let open_len = text.char_at_byte(range.start)?.len_utf8();
+        let close_len = text.byte_slice(..range.end).chars().next_back()?.len_utf8();
+        Some((range.start..range.start + open_len, range.end - close_len..range.end))
+    }
+}
+
+/// The byte range of the text of `lines` without the indentation and final line ending, this is
+/// what a linewise surround applies to.
+pub(super) fn surround_lines(text: &dyn AnyText, lines: Range<usize>) -> Range<usize> {
+    let first = text.line(lines.start).map_or(0, |line| line.indent());
+    let last = lines.end.saturating_sub(1).max(lines.start);
+    let end = text.line(last).map_or(0, |line| line.len_bytes());
+    text.line_to_byte(lines.start) + first..text.line_to_byte(last) + end
+}
+
+/// The opening and closing delimiters to surround text with for `c`.
+fn delimiters(c: char) -> (String, String) {
+    let (open, close) = match c {
+        '(' => ("( ", " )"),
+        ')' | 'b' => ("(", ")"),
+        '{' => ("{ ", " }"),
+        '}' | 'B' => ("{", "}"),
+        '[' => ("[ ", " ]"),
+        ']' | 'r' => ("[", "]"),
+        '<' | '>' | 'a' => ("<", ">"),
+        _ => return (c.to_string(), c.to_string()),
+    };
+    (open.to_string(), close.to_string())
+}
+
+/// The text object of the delimiters of `c` including the delimiters themselves.
+fn around(c: char) -> Option<Box<dyn TextObject>> {
+    let obj: Box<dyn TextObject> = match c {
+        '(' | ')' | 'b' => Box::new(Around(Paren)),
+        '{' | '}' | 'B' => Box::new(Around(Brace)),
+        '[' | ']' | 'r' => Box::new(Around(Bracket)),
+        '<' | '>' | 'a' => Box::new(Around(AngleBracket)),
+        '"' => Box::new(Around(Quote)),
+        '\'' => Box::new(Around(Apostrophe)),
+        '`' => Box::new(Around(Backtick)),
+        _ => return None,
+    };
+    Some(obj)
+}
@@ -0,0 +1,275 @@
+use std::time::UNIX_EPOCH;
+
+use super::*;
+
+/// The first line of a swap file.
+const MAGIC: &str = "zi swap 1";
+
+#[derive(Debug, Default)]
+pub(super) struct SwapState {
+    /// The buffers with a write of their swap file scheduled, with the generation of the latest
+    /// scheduled write. Only the latest write goes ahead.
+    pending: HashMap<BufferId, u64>,
+    generation: u64,
+    /// The swap files written by this process, these are deleted on exit.
+    written: HashMap<BufferId, PathBuf>,
+}
+
+/// The unsaved contents of a buffer, written to the swap directory so they can be recovered after
+/// a crash.
+#[derive(Debug, PartialEq, Eq)]
+struct Swap {
+    /// The process that wrote the swap file.
+    pid: u32,
+    /// The modification time of the file when the swap file was written, `None` if it didn't
+    /// exist. The swap file is stale if the file was modified since.
+    mtime: Option<Duration>,
+    cursor: Point,
+    text: String,
+}
+
+impl Swap {
+    fn serialize(&self) -> String {
+        let mtime = match self.mtime {
+            Some(mtime) => format!("{}.{:09}", mtime.as_secs(), mtime.subsec_nanos()),
+            None => "none".to_string(),
+        };
+        format!("{MAGIC}\npid {}\nmtime {mtime}\ncursor {}\n\n{}", self.pid, self.cursor, self.text)
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let (header, text) = s.split_once("\n\n").ok_or_else(|| anyhow!("missing swap header"))?;
+        let mut lines = header.lines();
+        if lines.next() != Some(MAGIC) {
+            bail!("not a swap file");
+        }
+
+        let (mut pid, mut mtime, mut cursor) = (None, None, Point::default());
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("pid", value)) => pid = Some(value.parse()?),
+                Some(("mtime", "none")) => mtime = None,
+                Some(("mtime", value)) => {
+                    let (secs, nanos) = value.split_once('.').unwrap_or((value, "0"));
+                    mtime = Some(Duration::new(secs.parse()?, nanos.parse()?));
+                }
+                Some(("cursor", value)) => cursor = value.parse()?,
+                // Ignore unknown fields for forwards compatibility.
+                _ => {}
+            }
+        }
+
+        let pid = pid.ok_or_else(|| anyhow!("missing swap pid"))?;
+        Ok(Self { pid, mtime, cursor, text: text.to_string() })
+    }
+}
+
+impl Editor {
+    /// Write the swap file of the buffer once it has stopped changing for `updatetime`.
+    pub(super) fn schedule_swap(&mut self, buf: BufferId) {
+        if self.swap_path(buf).is_none() {
+            return;
+        }
+
+        self.swap.generation += 1;
+        let generation = self.swap.generation;
+        self.swap.pending.insert(buf, generation);
+
+        let delay = *self.settings.update_time.read();
+        let client = self.client();
+        // Not `self.spawn` as the delay may well outlive the callback timeout.
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let fut = client
+                .with(move |editor| {
+                    // The buffer changed again since, so the write is rescheduled.
+                    if editor.swap.pending.get(&buf) != Some(&generation) {
+                        return None;
+                    }
+                    editor.write_swap(buf)
+                })
+                .await;
+            if let Some(fut) = fut {
+                if let Err(err) = fut.await {
+                    client.send(move |_| Err(err));
+                }
+            }
+        });
+    }
+
+    /// Returns a future that writes the unsaved contents of the buffer to its swap file,
+    /// `None` if the buffer has no swap file.
+    pub fn write_swap(
+        &mut self,
+        selector: impl Selector<BufferId>,
+    ) -> Option<impl Future<Output = Result<()>> + Send + 'static> {
+        let buf = selector.select(self);
+        self.swap.pending.remove(&buf);
+        let swap_path = self.swap_path(buf)?;
+        let buffer = self.buffers.get(buf)?;
+        // The buffer may have been saved in the meantime.
+        if !buffer.flags().contains(BufferFlags::DIRTY) {
+            return None;
+        }
+
+        let path = buffer.file_path()?;
+        let text = buffer.text().to_string();
+        let cursor =
+            self.views.values().find(|view| view.buffer() == buf).map(|view| view.cursor());
+        self.swap.written.insert(buf, swap_path.clone());
+
+        Some(async move {
+            let mtime = match tokio::fs::metadata(&path).await {
+                Ok(metadata) => Some(mtime(&metadata)?),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err.into()),
+            };
+
+            let pid = std::process::id();
+            let swap = Swap { pid, mtime, cursor: cursor.unwrap_or_default(), text };
+            if let Some(dir) = swap_path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+
+            // Write to a temporary file first so a crash mid-write doesn't leave a truncated swap.
+            let tmp = swap_path.with_extension("swp.tmp");
+            tokio::fs::write(&tmp, swap.serialize()).await?;
+            tokio::fs::rename(&tmp, &swap_path).await?;
+            Ok(())
+        })
+    }
+
+    /// Replace the contents of the buffer with the unsaved changes of its swap file.
+    /// This is a single edit of the differences so it can be undone to get back to the file.
+    pub fn recover(&mut self, selector: impl Selector<BufferId>) -> Result<()> {
+        let buf = selector.select(self);
+        let Some(swap_path) = self.swap_path(buf) else { bail!("buffer has no swap file") };
+        let swap = match std::fs::read_to_string(&swap_path) {
+            Ok(swap) => Swap::parse(&swap)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => bail!("no swap file found"),
+            Err(err) => return Err(err.into()),
+        };
+
+        let deltas = zi_text::diff(self[buf].text(), &swap.text.as_str());
+        self.edit(buf, &deltas)?;
+        self[buf].snapshot(SnapshotFlags::empty());
+        if self[Active].buffer() == buf {
+            self.set_cursor(Active, swap.cursor);
+        }
+        Ok(())
+    }
+
+    /// Offer to recover the unsaved changes in the swap file of a newly opened buffer.
+    /// Stale swap files, i.e. those of files that have been modified since, are deleted.
+    pub(super) fn check_swap(&mut self, buf: BufferId) {
+        let Some(swap_path) = self.swap_path(buf) else { return };
+        let Some(path) = self[buf].file_path() else { return };
+        let swap = match std::fs::read_to_string(&swap_path) {
+            Ok(swap) => Swap::parse(&swap),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(err) => Err(err.into()),
+        };
+
+        let swap = match swap {
+            Ok(swap) => swap,
+            Err(err) => return self.set_error(format!("`{}`: {err}", swap_path.display())),
+        };
+
+        if swap.pid != std::process::id() && is_running(swap.pid) {
+            return self.notify(
+                MessageLevel::Warning,
+                format!("`{}` is being edited by another process ({})", path.display(), swap.pid),
+            );
+        }
+
+        let mtime = std::fs::metadata(&path).ok().map(|metadata| mtime(&metadata)).transpose();
+        let stale = match mtime {
+            Ok(mtime) => {
+                mtime != swap.mtime
+                    || zi_text::ContentHash::new(swap.text.as_str()) == self[buf].content_hash()
+            }
+            Err(_) => true,
+        };
+
+        if stale {
+            tracing::info!(path = %swap_path.display(), "deleting stale swap file");
+            if let Err(err) = std::fs::remove_file(&swap_path) {
+                self.set_error(err);
+            }
+            return;
+        }
+
+        self.notify(
+            MessageLevel::Warning,
+            format!(
+                "found unsaved changes to `{}` from a previous session, use :recover to restore them",
+                path.display()
+            ),
+        );
+    }
+
+    /// Delete the swap file of the buffer if it was written by this process.
+    pub(super) fn delete_swap(&mut self, buf: BufferId) {
+        self.swap.pending.remove(&buf);
+        let Some(swap_path) = self.swap.written.remove(&buf) else { return };
+        match std::fs::remove_file(&swap_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => self.set_error(err),
+        }
+    }
+
+    /// Delete all swap files written by this process, done on a clean exit.
+    pub(super) fn delete_swaps(&mut self) {
+        let bufs = self.swap.written.keys().copied().collect::<Vec<_>>();
+        for buf in bufs {
+            self.delete_swap(buf);
+        }
+    }
+
+    /// The swap file of the buffer in the swap directory, `None` if swap files are disabled or the
+    /// buffer isn't backed by a writable file.
+    fn swap_path(&self, buf: BufferId) -> Option<PathBuf> {
+        let dir = self.settings.swap_dir.read().clone()?;
+        let buffer = self.buffers.get(buf)?;
+        if buffer.flags().contains(BufferFlags::READONLY) {
+            return None;
+        }
+
+        let path = std::path::absolute(buffer.file_path()?).ok()?;
+        // Flatten the path into a file name, e.g. `/home/a.txt` is `%home%a.txt.swp`.
+        let name = path.to_string_lossy().replace(['/', '\\'], "%");
+        Some(dir.join(format!("{name}.swp")))
+    }
+}
+
+fn mtime(metadata: &std::fs::Metadata) -> io::Result<Duration> {
+    let mtime = metadata.modified()?;
+    Ok(mtime.duration_since(UNIX_EPOCH).unwrap_or_default())
+}
+
+/// Whether the process is still running, assumed not if it can't be determined.
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    proc.join("self").exists() && proc.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_roundtrip() {
+        let swap = Swap {
+            pid: 42,
+            mtime: Some(Duration::new(1700000000, 5)),
+            cursor: Point::new(1, 2),
+            text: "a\n\nb\n".to_string(),
+        };
+        assert_eq!(Swap::parse(&swap.serialize()).unwrap(), swap);
+
+        let swap = Swap { mtime: None, ..swap };
+        assert_eq!(Swap::parse(&swap.serialize()).unwrap(), swap);
+        assert!(Swap::parse("a\n\nb").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use super::*;
+
+impl Editor {
+    /// Open a terminal running the user's shell in the current directory and start typing into it.
+    pub fn open_terminal(&mut self) -> Result<BufferId> {
+        let cwd = self.current_dir(Active).to_path_buf();
+        let buf = self.buffers.try_insert_with_key(|id| {
+            TerminalBuffer::new(id, &cwd, request_redraw).map(Buffer::new)
+        })?;
+        self.open_placed(BufferKind::Terminal, Placement::Replace, buf);
+        self.set_mode(Mode::Insert);
+        Ok(buf)
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Context as _;
+use zi_core::style::Style;
+
+use super::*;
+
+/// Themes bundled with the editor, in addition to the `default` theme.
+/// Themes in `dirs::config()/themes` take precedence.
+const BUNDLED_THEMES: &[(&str, &str)] = &[
+    ("gruvbox-dark", include_str!("../../themes/gruvbox-dark.toml")),
+    ("solarized-light", include_str!("../../themes/solarized-light.toml")),
+];
+
+impl Editor {
+    /// Switch to the theme with the given name, see [`Editor::colorschemes`].
+    pub fn colorscheme(&mut self, name: &str) -> Result<()> {
+        let theme = load_theme(name)?;
+        self.set_theme(theme);
+        Ok(())
+    }
+
+    /// The names of the available themes, sorted.
+    pub fn colorschemes(&self) -> Vec<String> {
+        let mut names = ["default"]
+            .into_iter()
+            .chain(BUNDLED_THEMES.iter().map(|&(name, _)| name))
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if let Ok(entries) = std::fs::read_dir(themes_dir()) {
+            names.extend(entries.filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_owned())
+            }));
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Set the style of the highlight `name` of the current theme, see [`Theme::set_highlight`].
+    pub fn set_highlight(&mut self, name: &str, style: Style) {
+        self.update_theme(|theme| theme.set_highlight(name, style));
+    }
+
+    /// Link the highlight `from` to `to` in the current theme, see [`Theme::link_highlight`].
+    pub fn link_highlight(&mut self, from: &str, to: &str) {
+        self.update_theme(|theme| theme.link_highlight(from, to));
+    }
+
+    fn update_theme(&mut self, f: impl FnOnce(&mut Theme)) {
+        let mut theme = self.theme().read().clone();
+        f(&mut theme);
+        self.set_theme(theme);
+    }
+
+    /// Replace the theme, updating the highlights of all buffers.
+    pub fn set_theme(&mut self, theme: Theme) {
+        // Keep the ids of the current theme valid as they are stored in marks, etc.
+        let theme = theme.with_ids_of(&self.theme().read());
+        for buf in self.buffers.values_mut() {
+            buf.set_theme(&theme);
+        }
+        self.settings().theme.write(theme);
+        self.damage_all();
+        request_redraw();
+    }
+}
+
+fn themes_dir() -> PathBuf {
+    crate::dirs::config().join("themes")
+}
+
+fn load_theme(name: &str) -> Result<Theme> {
+    let path = themes_dir().join(format!("{name}.toml"));
+    match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            return content
+                .parse()
+                .with_context(|| format!("failed to load theme `{}`", path.display()));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    match BUNDLED_THEMES.iter().find(|&&(theme, _)| theme == name) {
+        Some((_, content)) => content.parse(),
+        None if name == "default" => Ok(Theme::default()),
+        None => bail!("unknown colorscheme: {name}"),
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 
 use zi_core::{Point, PointRange};
 use zi_text::{PointRangeExt, Text, TextBase, TextSlice};
@@ -50,8 +50,9 @@ impl Selection {
 
     pub fn register_kind(&self) -> RegisterKind {
         match self {
-            Self::Charwise { .. } | Self::Block { .. } => RegisterKind::Charwise,
+            Self::Charwise { .. } => RegisterKind::Charwise,
             Self::Line { .. } => RegisterKind::Linewise,
+            Self::Block { .. } => RegisterKind::Blockwise,
         }
     }
 
@@ -87,6 +88,16 @@ impl Selection {
         }
     }
 
+    /// The lines the selection spans.
+    pub fn lines(&self) -> RangeInclusive<usize> {
+        match *self {
+            Self::Charwise { start, end } => start.line()..=end.line(),
+            Self::Line { start_line, end_line } | Self::Block { start_line, end_line, .. } => {
+                start_line..=end_line
+            }
+        }
+    }
+
     pub fn start_point(&self) -> Point {
         match self {
             Self::Charwise { start, .. } => *start,
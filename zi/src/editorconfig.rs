@@ -0,0 +1,261 @@
+//! Support for [EditorConfig](https://editorconfig.org) files.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use globset::GlobBuilder;
+use zi_text::LineEnding;
+
+use crate::buffer::{IndentSettings, Settings};
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// The EditorConfig properties that apply to a file, unset properties are left as is.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<u8>,
+    pub tab_width: Option<u8>,
+    pub end_of_line: Option<LineEnding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub max_line_length: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+impl EditorConfig {
+    /// The properties that apply to `path` from the `.editorconfig` files of its ancestors, up to
+    /// and including the first with `root = true`. Closer files take precedence.
+    pub fn for_path(path: &Path) -> io::Result<Self> {
+        let mut files = vec![];
+        for dir in path.ancestors().skip(1) {
+            let content = match std::fs::read_to_string(dir.join(FILE_NAME)) {
+                Ok(content) => content,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let file = File::parse(&content);
+            let root = file.root;
+            files.push((dir, file));
+            if root {
+                break;
+            }
+        }
+
+        let mut properties = HashMap::new();
+        for (dir, file) in files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else { continue };
+            for section in &file.sections {
+                if section.matches(relative) {
+                    properties.extend(section.properties.iter().cloned());
+                }
+            }
+        }
+
+        Ok(Self::from_properties(&properties))
+    }
+
+    fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let get = |key: &str| properties.get(key).map(String::as_str).filter(|&v| v != "unset");
+        let indent_style = get("indent_style").and_then(|style| match style {
+            "space" => Some(IndentStyle::Space),
+            "tab" => Some(IndentStyle::Tab),
+            _ => None,
+        });
+        let tab_width = get("tab_width").and_then(|width| width.parse().ok());
+        // `indent_size = tab` means the indent is a tab of `tab_width`.
+        let indent_size = match get("indent_size") {
+            Some("tab") => tab_width,
+            size => size.and_then(|size| size.parse().ok()),
+        };
+
+        Self {
+            indent_style,
+            indent_size,
+            // The tab width defaults to the indent size.
+            tab_width: tab_width.or(indent_size),
+            end_of_line: get("end_of_line").and_then(|eol| eol.parse().ok()),
+            trim_trailing_whitespace: get("trim_trailing_whitespace")
+                .and_then(|trim| trim.parse().ok()),
+            insert_final_newline: get("insert_final_newline")
+                .and_then(|insert| insert.parse().ok()),
+            max_line_length: get("max_line_length").and_then(|len| len.parse().ok()),
+        }
+    }
+
+    /// Apply the properties that are set to the buffer settings.
+    pub fn apply(&self, settings: &Settings) {
+        if let Some(tab_width) = self.tab_width {
+            settings.tab_width.write(tab_width);
+        }
+
+        let indent = *settings.indent.read();
+        let size = self.indent_size.unwrap_or(match indent {
+            IndentSettings::Spaces(n) => n,
+            IndentSettings::Tabs => *settings.tab_width.read(),
+        });
+        let tabs = match self.indent_style {
+            Some(style) => style == IndentStyle::Tab,
+            None => indent == IndentSettings::Tabs,
+        };
+        settings.indent.write(if tabs {
+            IndentSettings::Tabs
+        } else {
+            IndentSettings::Spaces(size)
+        });
+
+        if let Some(line_ending) = self.end_of_line {
+            settings.line_ending.write(line_ending);
+        }
+        if let Some(trim) = self.trim_trailing_whitespace {
+            settings.trim_trailing_whitespace.write(trim);
+        }
+        if let Some(insert) = self.insert_final_newline {
+            settings.fix_end_of_line.write(insert);
+        }
+        if let Some(len) = self.max_line_length {
+            settings.max_line_length.write(Some(len));
+        }
+    }
+}
+
+/// A parsed `.editorconfig` file.
+#[derive(Debug, Default)]
+struct File {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug)]
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+impl File {
+    /// Parse the ini-like format, invalid lines are ignored.
+    fn parse(content: &str) -> Self {
+        let mut file = File::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                file.sections.push(Section { glob: glob.to_string(), properties: vec![] });
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match file.sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                // Properties before the first section are the preamble.
+                None if key == "root" => file.root = value == "true",
+                None => {}
+            }
+        }
+        file
+    }
+}
+
+impl Section {
+    /// Whether the section applies to `path`, relative to the directory of the file.
+    fn matches(&self, path: &Path) -> bool {
+        // A glob without a `/` matches the file name in any directory.
+        let glob = match self.glob.strip_prefix('/') {
+            Some(glob) => glob.to_string(),
+            None if self.glob.contains('/') => self.glob.clone(),
+            None => format!("**/{}", self.glob),
+        };
+
+        match GlobBuilder::new(&glob).literal_separator(true).build() {
+            Ok(glob) => glob.compile_matcher().is_match(path),
+            Err(err) => {
+                tracing::warn!(glob = %self.glob, %err, "invalid editorconfig glob");
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let file = File::parse(
+            r#"
+# comment
+root = true
+
+[*]
+indent_style = space
+indent_size = 4
+
+[*.{js,ts}]
+indent_size = 2
+
+[Makefile]
+indent_style = tab
+
+[lib/**/*.rs]
+max_line_length = 100
+"#,
+        );
+        assert!(file.root);
+        assert_eq!(file.sections.len(), 4);
+
+        let matching = |path: &str| {
+            file.sections
+                .iter()
+                .filter(|section| section.matches(Path::new(path)))
+                .map(|section| section.glob.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching("a.rs"), ["*"]);
+        assert_eq!(matching("src/a.ts"), ["*", "*.{js,ts}"]);
+        assert_eq!(matching("sub/Makefile"), ["*", "Makefile"]);
+        assert_eq!(matching("lib/a/b.rs"), ["*", "lib/**/*.rs"]);
+        assert_eq!(matching("src/lib/b.rs"), ["*"]);
+    }
+
+    #[test]
+    fn properties() {
+        let properties = |props: &[(&str, &str)]| {
+            EditorConfig::from_properties(
+                &props.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+            )
+        };
+
+        assert_eq!(
+            properties(&[("indent_style", "tab"), ("indent_size", "tab"), ("tab_width", "8")]),
+            EditorConfig {
+                indent_style: Some(IndentStyle::Tab),
+                indent_size: Some(8),
+                tab_width: Some(8),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            properties(&[("indent_size", "2"), ("end_of_line", "crlf"), ("max_line_length", "80")]),
+            EditorConfig {
+                indent_size: Some(2),
+                tab_width: Some(2),
+                end_of_line: Some(LineEnding::CrLf),
+                max_line_length: Some(80),
+                ..Default::default()
+            }
+        );
+        assert_eq!(properties(&[("indent_size", "unset")]), EditorConfig::default());
+    }
+}
@@ -1,4 +1,4 @@
-use zi_core::{BufferId, Mode, ViewId};
+use zi_core::{BufferId, Mode, Point, ViewGroupId, ViewId};
 use zi_text::{AnyText, Deltas};
 
 use super::{AsyncEvent, Event};
@@ -43,6 +43,17 @@ pub struct DidChangeMode {
 
 impl Event for DidChangeMode {}
 
+/// The cursor of the active view moved, or another view became active. This is dispatched once
+/// the input that is available has been handled rather than for every movement.
+#[derive(Debug, Clone)]
+pub struct DidMoveCursor {
+    pub view: ViewId,
+    pub buf: BufferId,
+    pub cursor: Point,
+}
+
+impl Event for DidMoveCursor {}
+
 #[derive(Debug, Clone)]
 pub struct DidInsertChar {
     pub view: ViewId,
@@ -80,6 +91,15 @@ pub struct DidYankText {
 
 impl Event for DidYankText {}
 
+/// An item of a picker opened with `Editor::open_item_picker` was confirmed.
+#[derive(Debug, Clone)]
+pub struct DidConfirmPickerItem {
+    pub picker: ViewGroupId,
+    pub item: u64,
+}
+
+impl Event for DidConfirmPickerItem {}
+
 #[derive(Debug, Clone)]
 pub struct WillSaveBuffer {
     pub buf: BufferId,
@@ -0,0 +1,318 @@
+//! A small expression language for computing values inline, e.g. with the `=` register or in
+//! command arguments.
+//!
+//! Values are integers or strings. Numbers support `+ - * / %`, strings are concatenated with
+//! `.` (or `..`). Strings are single quoted literals (`''` is a quote) or double quoted with
+//! backslash escapes. Functions are called as `name(arg, ...)`.
+
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+
+use crate::Result;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    Number(i64),
+    String(String),
+}
+
+impl Value {
+    pub fn as_number(&self) -> Result<i64> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            Value::String(s) => {
+                s.trim().parse().map_err(|_| anyhow!("expected a number, found `'{s}'`"))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(n as i64)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+/// Evaluate the expression. Functions other than the builtin string functions are looked up with
+/// `call`, which is expected to fail for unknown functions.
+pub(crate) fn eval(src: &str, call: impl FnMut(&str, Vec<Value>) -> Result<Value>) -> Result<Value> {
+    let mut parser = Parser { src, pos: 0, call };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        bail!("unexpected `{c}` in expression: `{src}`");
+    }
+    Ok(value)
+}
+
+/// Evaluates while parsing, there is no control flow so every subexpression is evaluated anyway.
+struct Parser<'a, F> {
+    src: &'a str,
+    pos: usize,
+    call: F,
+}
+
+impl<'a, F> Parser<'a, F>
+where
+    F: FnMut(&str, Vec<Value>) -> Result<Value>,
+{
+    fn expr(&mut self) -> Result<Value> {
+        let mut lhs = self.term()?;
+        loop {
+            self.skip_whitespace();
+            lhs = match self.peek() {
+                Some(op @ ('+' | '-')) => {
+                    self.bump();
+                    let (a, b) = (lhs.as_number()?, self.term()?.as_number()?);
+                    let n = if op == '+' { a.checked_add(b) } else { a.checked_sub(b) };
+                    Value::Number(checked(n)?)
+                }
+                Some('.') => {
+                    self.bump();
+                    self.eat('.');
+                    Value::String(format!("{lhs}{}", self.term()?))
+                }
+                _ => return Ok(lhs),
+            };
+        }
+    }
+
+    fn term(&mut self) -> Result<Value> {
+        let mut lhs = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.peek() {
+                Some(c @ ('*' | '/' | '%')) => c,
+                _ => return Ok(lhs),
+            };
+            self.bump();
+            let (a, b) = (lhs.as_number()?, self.unary()?.as_number()?);
+            if b == 0 && op != '*' {
+                bail!("division by zero");
+            }
+
+            lhs = Value::Number(checked(match op {
+                '*' => a.checked_mul(b),
+                '/' => a.checked_div(b),
+                _ => a.checked_rem(b),
+            })?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('-') => {
+                self.bump();
+                Ok(Value::Number(checked(self.unary()?.as_number()?.checked_neg())?))
+            }
+            Some('+') => {
+                self.bump();
+                Ok(Value::Number(self.unary()?.as_number()?))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.bump();
+                let value = self.expr()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some('\'') => self.single_quoted(),
+            Some('"') => self.double_quoted(),
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                Ok(Value::Number(
+                    digits.parse().map_err(|_| anyhow!("number too large: {digits}"))?,
+                ))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                self.expect('(')?;
+                let mut args = vec![];
+                self.skip_whitespace();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expr()?);
+                        self.skip_whitespace();
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                self.apply(name, args)
+            }
+            Some(c) => bail!("unexpected `{c}` in expression: `{}`", self.src),
+            None => bail!("unexpected end of expression: `{}`", self.src),
+        }
+    }
+
+    fn apply(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let arg = |args: &[Value]| -> Result<String> {
+            match args {
+                [arg] => Ok(arg.to_string()),
+                _ => bail!("`{name}` expects 1 argument, got {}", args.len()),
+            }
+        };
+
+        match name {
+            "len" => Ok(arg(&args)?.chars().count().into()),
+            "toupper" => Ok(arg(&args)?.to_uppercase().into()),
+            "tolower" => Ok(arg(&args)?.to_lowercase().into()),
+            "trim" => Ok(arg(&args)?.trim().into()),
+            _ => (self.call)(name, args),
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<Value> {
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') if self.eat('\'') => s.push('\''),
+                Some('\'') => return Ok(Value::String(s)),
+                Some(c) => s.push(c),
+                None => bail!("unterminated string: `{}`", self.src),
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<Value> {
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Value::String(s)),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some(c) => s.push(c),
+                    None => bail!("unterminated string: `{}`", self.src),
+                },
+                Some(c) => s.push(c),
+                None => bail!("unterminated string: `{}`", self.src),
+            }
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if !self.eat(c) {
+            match self.peek() {
+                Some(found) => {
+                    bail!("expected `{c}`, found `{found}` in expression: `{}`", self.src)
+                }
+                None => bail!("expected `{c}` at end of expression: `{}`", self.src),
+            }
+        }
+        Ok(())
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.bump();
+        }
+        eaten
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+}
+
+fn checked(n: Option<i64>) -> Result<i64> {
+    n.ok_or_else(|| anyhow!("integer overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_expr() {
+        let check = |src: &str, expected: &str| {
+            let value = eval(src, |name, args| match name {
+                "add" => Ok((args[0].as_number()? + args[1].as_number()?).into()),
+                _ => bail!("unknown function: `{name}`"),
+            });
+            let actual = match value {
+                Ok(value) => value.to_string(),
+                Err(err) => format!("error: {err}"),
+            };
+            assert_eq!(actual, expected, "{src}");
+        };
+
+        check("1 + 2 * 3", "7");
+        check("(1 + 2) * 3", "9");
+        check("7 / 2 - 7 % 2", "2");
+        check("-3 - -4", "1");
+        check("1 - 2 - 3", "-4");
+        check("'a' . 1 . 2", "a12");
+        check("1 + 2 . 'a' . (1 + 2)", "3a3");
+        check("\"x\\ty\" .. 'it''s'", "x\tyit's");
+        check("' 12 ' * 2", "24");
+        check("len('héllo') + add(1, 2)", "8");
+        check("toupper('ab') . tolower('CD')", "ABcd");
+        check("1 / 0", "error: division by zero");
+        check("'a' + 1", "error: expected a number, found `'a'`");
+        check("nope()", "error: unknown function: `nope`");
+        check("len(1, 2)", "error: `len` expects 1 argument, got 2");
+        check("1 +", "error: unexpected end of expression: `1 +`");
+        check("(1", "error: expected `)` at end of expression: `(1`");
+        check("1 2", "error: unexpected `2` in expression: `1 2`");
+        check("'abc", "error: unterminated string: `'abc`");
+    }
+}
@@ -0,0 +1,536 @@
+//! An fzf style fuzzy matcher used by the pickers.
+//!
+//! Scoring is a dynamic programming search for the best alignment of the pattern in the haystack,
+//! rewarding matches at word boundaries and consecutive runs and penalizing gaps.
+
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use parking_lot::{Condvar, Mutex};
+use rayon::prelude::*;
+
+use crate::editor::pool;
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+
+/// Bonus for matching the first character of a word (e.g. after `_` or `-`).
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+/// Bonus for matching the first character of a word following whitespace.
+const BONUS_BOUNDARY_WHITE: i32 = BONUS_BOUNDARY + 2;
+/// Bonus for matching the first character of a word following a delimiter such as `/`.
+const BONUS_BOUNDARY_DELIMITER: i32 = BONUS_BOUNDARY + 1;
+/// Bonus for a camelCase transition or the start of a number.
+const BONUS_CAMEL123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+/// Minimum bonus for each character of a consecutive run, enough to make a run beat a gap.
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+/// The bonus of the first pattern character counts more as it anchors the whole match.
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Number,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_lowercase() {
+            CharClass::Lower
+        } else if c.is_uppercase() {
+            CharClass::Upper
+        } else if c.is_numeric() {
+            CharClass::Number
+        } else if c.is_whitespace() {
+            CharClass::White
+        } else if matches!(c, '/' | '\\' | ',' | ':' | ';' | '|') {
+            CharClass::Delimiter
+        } else if c.is_alphanumeric() {
+            // Letters without case
+            CharClass::Lower
+        } else {
+            CharClass::NonWord
+        }
+    }
+
+    fn is_word(self) -> bool {
+        matches!(self, CharClass::Lower | CharClass::Upper | CharClass::Number)
+    }
+
+    /// The bonus for matching a character of class `self` that follows a character of class `prev`.
+    fn bonus(self, prev: CharClass) -> i32 {
+        if self.is_word() {
+            match prev {
+                CharClass::White => return BONUS_BOUNDARY_WHITE,
+                CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+                CharClass::NonWord => return BONUS_BOUNDARY,
+                _ => {}
+            }
+        }
+
+        match (prev, self) {
+            (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL123,
+            (prev, CharClass::Number) if prev != CharClass::Number => BONUS_CAMEL123,
+            (_, CharClass::NonWord | CharClass::Delimiter) => BONUS_BOUNDARY,
+            (_, CharClass::White) => BONUS_BOUNDARY_WHITE,
+            _ => 0,
+        }
+    }
+}
+
+fn fold(c: char) -> char {
+    if c.is_uppercase() { c.to_lowercase().next().unwrap_or(c) } else { c }
+}
+
+/// A successful match of a [`Pattern`] against a haystack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: u32,
+    /// The sorted char indices of the matched characters in the haystack.
+    pub indices: Vec<u32>,
+}
+
+/// A parsed fuzzy pattern.
+/// Whitespace separated terms must all match (in any order).
+#[derive(Debug, Clone, Default)]
+pub struct Pattern {
+    atoms: Vec<Atom>,
+}
+
+#[derive(Debug, Clone)]
+struct Atom {
+    chars: Vec<char>,
+    /// Smart case: the atom is only case sensitive if it contains an uppercase character.
+    case_sensitive: bool,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Self {
+        let atoms = pattern
+            .split_whitespace()
+            .map(|word| Atom {
+                chars: word.chars().collect(),
+                case_sensitive: word.chars().any(char::is_uppercase),
+            })
+            .collect();
+        Self { atoms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    /// Score the haystack against the pattern, returning `None` if it does not match.
+    pub fn score(&self, haystack: &str) -> Option<FuzzyMatch> {
+        let chars = haystack.chars().collect::<Vec<_>>();
+        let mut score = 0;
+        let mut indices = vec![];
+        for atom in &self.atoms {
+            let (s, idxs) = atom.score(&chars)?;
+            score += s;
+            indices.extend(idxs);
+        }
+
+        indices.sort_unstable();
+        indices.dedup();
+        Some(FuzzyMatch { score: score.max(0) as u32, indices })
+    }
+}
+
+impl Atom {
+    fn eq(&self, p: char, h: char) -> bool {
+        if self.case_sensitive { p == h } else { fold(p) == fold(h) }
+    }
+
+    fn score(&self, haystack: &[char]) -> Option<(i32, Vec<u32>)> {
+        let m = self.chars.len();
+
+        // Cheap subsequence check which also narrows down the window to search.
+        // The match can't start before the first occurrence of the first character,
+        // or end after the last occurrence of the last character.
+        let mut start = None;
+        let mut i = 0;
+        for (j, &c) in haystack.iter().enumerate() {
+            if self.eq(self.chars[i], c) {
+                start.get_or_insert(j);
+                i += 1;
+                if i == m {
+                    break;
+                }
+            }
+        }
+
+        if i < m {
+            return None;
+        }
+
+        let start = start?;
+        let end = 1 + haystack.iter().rposition(|&c| self.eq(self.chars[m - 1], c))?;
+        let window = &haystack[start..end];
+        let n = window.len();
+
+        let mut prev =
+            if start == 0 { CharClass::White } else { CharClass::of(haystack[start - 1]) };
+        let bonuses = window
+            .iter()
+            .map(|&c| {
+                let class = CharClass::of(c);
+                let bonus = class.bonus(prev);
+                prev = class;
+                bonus
+            })
+            .collect::<Vec<_>>();
+
+        // `scores[i * n + j]` is the best score of matching `chars[..=i]` with `chars[i]` at `window[j]`.
+        // `from` records the position of `chars[i - 1]` in that alignment to recover the indices.
+        let mut scores = vec![None::<i32>; m * n];
+        let mut consecutive = vec![0usize; m * n];
+        let mut from = vec![0usize; m * n];
+
+        for i in 0..m {
+            // The best score of a previous match followed by a gap ending just before `j`.
+            let mut gap = None::<(i32, usize)>;
+            for j in 0..n {
+                if i > 0 && j >= 2 {
+                    let extended = gap.map(|(score, k)| (score + SCORE_GAP_EXTENSION, k));
+                    let opened =
+                        scores[(i - 1) * n + j - 2].map(|score| (score + SCORE_GAP_START, j - 2));
+                    gap = match (extended, opened) {
+                        (Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+                        (a, b) => a.or(b),
+                    };
+                }
+
+                if !self.eq(self.chars[i], window[j]) {
+                    continue;
+                }
+
+                let idx = i * n + j;
+                let bonus = bonuses[j];
+                if i == 0 {
+                    scores[idx] = Some(SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER);
+                    consecutive[idx] = 1;
+                    continue;
+                }
+
+                let mut best = None::<(i32, usize, usize)>;
+                if let Some(prev) = j.checked_sub(1).and_then(|k| scores[(i - 1) * n + k]) {
+                    let mut run = consecutive[(i - 1) * n + j - 1] + 1;
+                    let mut bonus = bonus;
+                    if run > 1 {
+                        // The whole run shares the bonus of its first character,
+                        // unless this character starts a better boundary in which case a new run begins.
+                        let first = bonuses[j + 1 - run];
+                        if bonus >= BONUS_BOUNDARY && bonus > first {
+                            run = 1;
+                        } else {
+                            bonus = bonus.max(BONUS_CONSECUTIVE).max(first);
+                        }
+                    }
+                    best = Some((prev + SCORE_MATCH + bonus, j - 1, run));
+                }
+
+                if let Some((score, k)) = gap {
+                    let score = score + SCORE_MATCH + bonus;
+                    if best.is_none_or(|(best, ..)| score > best) {
+                        best = Some((score, k, 1));
+                    }
+                }
+
+                if let Some((score, k, run)) = best {
+                    scores[idx] = Some(score);
+                    from[idx] = k;
+                    consecutive[idx] = run;
+                }
+            }
+        }
+
+        let (mut j, score) = (0..n).filter_map(|j| Some((j, scores[(m - 1) * n + j]?))).fold(
+            None,
+            |acc: Option<(usize, i32)>, (j, score)| match acc {
+                Some((_, best)) if best >= score => acc,
+                _ => Some((j, score)),
+            },
+        )?;
+
+        let mut indices = vec![0; m];
+        for i in (0..m).rev() {
+            indices[i] = (start + j) as u32;
+            j = from[i * n + j];
+        }
+
+        Some((score, indices))
+    }
+}
+
+/// A matched item, indexing into the items of a [`Matcher`].
+#[derive(Debug, Clone)]
+struct Matched {
+    idx: u32,
+    score: u32,
+    /// The length of the haystack, shorter haystacks are preferred among equal scores.
+    len: u32,
+    indices: Vec<u32>,
+}
+
+/// Identifies the inputs that produced a set of results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Key {
+    epoch: u64,
+    pattern_version: u64,
+    len: usize,
+}
+
+#[derive(Debug, Default)]
+struct Results {
+    key: Key,
+    matches: Vec<Matched>,
+}
+
+/// A batch of items in insertion order. Batches are immutable once taken by the [`Matcher`],
+/// so match jobs can work on a snapshot of them without any locking.
+type Chunk<T> = Arc<[(T, String)]>;
+
+/// The items pushed by injectors that the matcher has not taken yet.
+struct Pending<T> {
+    items: Mutex<Vec<(T, String)>>,
+    /// Whether the matcher has been notified since it last took the pending items,
+    /// so a burst of pushes only notifies once.
+    notified: AtomicBool,
+    /// Set when the matcher is restarted so that the injectors of the previous items stop.
+    closed: AtomicBool,
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self { items: Default::default(), notified: Default::default(), closed: Default::default() }
+    }
+}
+
+#[derive(Default)]
+struct Done {
+    results: Mutex<Option<Results>>,
+    condvar: Condvar,
+}
+
+/// Matches a growing set of items against a [`Pattern`] in the background on the rayon pool.
+/// Results are sorted by descending score, an empty pattern matches everything in insertion order.
+pub(crate) struct Matcher<T> {
+    pending: Arc<Pending<T>>,
+    chunks: Vec<Chunk<T>>,
+    /// The index of the first item of each chunk.
+    offsets: Vec<usize>,
+    len: usize,
+    /// Bumped on restart to discard the results of jobs started before it.
+    epoch: u64,
+    done: Arc<Done>,
+    pattern: Arc<Pattern>,
+    pattern_version: u64,
+    notify: Arc<dyn Fn() + Send + Sync>,
+    /// The key of the most recently started job.
+    scheduled: Option<Key>,
+    running: bool,
+    results: Results,
+}
+
+impl<T: Clone + Send + Sync + 'static> Matcher<T> {
+    pub fn new(notify: Arc<dyn Fn() + Send + Sync>) -> Self {
+        Self {
+            pending: Default::default(),
+            chunks: vec![],
+            offsets: vec![],
+            len: 0,
+            epoch: 0,
+            done: Default::default(),
+            pattern: Default::default(),
+            pattern_version: 0,
+            notify,
+            scheduled: None,
+            running: false,
+            results: Default::default(),
+        }
+    }
+
+    pub fn injector(&self) -> Injector<T> {
+        Injector { pending: Arc::clone(&self.pending), notify: Arc::clone(&self.notify) }
+    }
+
+    /// Remove all items, existing injectors are invalidated.
+    /// This doesn't wait for a running job, its results are discarded when it finishes.
+    pub fn restart(&mut self) {
+        self.pending.closed.store(true, Ordering::Release);
+        self.pending = Default::default();
+        self.chunks.clear();
+        self.offsets.clear();
+        self.len = 0;
+        self.epoch += 1;
+        self.results = Default::default();
+    }
+
+    pub fn reparse(&mut self, pattern: &str) {
+        self.pattern = Arc::new(Pattern::parse(pattern));
+        self.pattern_version += 1;
+    }
+
+    /// Start matching if anything has changed and wait up to `timeout` for it to finish.
+    /// Returns whether the results have changed.
+    pub fn tick(&mut self, timeout: Duration) -> bool {
+        let mut changed = self.collect();
+        self.take_pending();
+        self.schedule();
+        if self.running {
+            let mut results = self.done.results.lock();
+            self.done.condvar.wait_while_for(&mut results, |results| results.is_none(), timeout);
+            drop(results);
+            changed |= self.collect();
+        }
+        changed
+    }
+
+    pub fn matched_item_count(&self) -> usize {
+        self.results.matches.len()
+    }
+
+    pub fn get_matched_item(&self, n: usize) -> Option<T> {
+        let matched = self.results.matches.get(n)?;
+        Some(self.item(matched.idx).0.clone())
+    }
+
+    /// The first `limit` matched items along with the char indices of the matched characters.
+    pub fn matched_items(&self, limit: usize) -> Vec<(T, Vec<u32>)> {
+        self.results
+            .matches
+            .iter()
+            .take(limit)
+            .map(|matched| (self.item(matched.idx).0.clone(), matched.indices.clone()))
+            .collect()
+    }
+
+    fn item(&self, idx: u32) -> &(T, String) {
+        let idx = idx as usize;
+        let chunk = self.offsets.partition_point(|&offset| offset <= idx) - 1;
+        &self.chunks[chunk][idx - self.offsets[chunk]]
+    }
+
+    /// Move the items pushed since the last call into a new chunk.
+    fn take_pending(&mut self) {
+        // Reset the flag before taking the items so a concurrent push notifies again.
+        self.pending.notified.store(false, Ordering::Release);
+        let items = mem::take(&mut *self.pending.items.lock());
+        if items.is_empty() {
+            return;
+        }
+
+        self.offsets.push(self.len);
+        self.len += items.len();
+        self.chunks.push(items.into());
+    }
+
+    fn collect(&mut self) -> bool {
+        let Some(results) = self.done.results.lock().take() else { return false };
+        self.running = false;
+
+        if results.key.epoch != self.epoch || results.key.pattern_version != self.pattern_version {
+            // Stale, `schedule` will start another job
+            return false;
+        }
+
+        self.results = results;
+        true
+    }
+
+    fn schedule(&mut self) {
+        if self.running {
+            return;
+        }
+
+        let key = Key { epoch: self.epoch, pattern_version: self.pattern_version, len: self.len };
+        if self.scheduled == Some(key) {
+            return;
+        }
+
+        self.scheduled = Some(key);
+        self.running = true;
+
+        let chunks = self.chunks.clone();
+        let offsets = self.offsets.clone();
+        let done = Arc::clone(&self.done);
+        let pattern = Arc::clone(&self.pattern);
+        let notify = Arc::clone(&self.notify);
+        pool().spawn(move || {
+            let matches = match_items(&pattern, &chunks, &offsets, key.len);
+            *done.results.lock() = Some(Results { key, matches });
+            done.condvar.notify_all();
+            notify();
+        });
+    }
+}
+
+fn match_items<T: Send + Sync>(
+    pattern: &Pattern,
+    chunks: &[Chunk<T>],
+    offsets: &[usize],
+    len: usize,
+) -> Vec<Matched> {
+    if pattern.is_empty() {
+        return (0..len)
+            .map(|idx| Matched { idx: idx as u32, score: 0, len: 0, indices: vec![] })
+            .collect();
+    }
+
+    let mut matches = chunks
+        .par_iter()
+        .zip(offsets)
+        .flat_map(|(chunk, &offset)| {
+            chunk.par_iter().enumerate().filter_map(move |(i, (_, haystack))| {
+                let FuzzyMatch { score, indices } = pattern.score(haystack)?;
+                let (idx, len) = ((offset + i) as u32, haystack.len() as u32);
+                Some(Matched { idx, score, len, indices })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Prefer higher scores, then shorter haystacks, then earlier items.
+    matches.par_sort_unstable_by(|a, b| {
+        b.score.cmp(&a.score).then_with(|| a.len.cmp(&b.len)).then_with(|| a.idx.cmp(&b.idx))
+    });
+    matches
+}
+
+/// Pushes items into a [`Matcher`].
+pub(crate) struct Injector<T> {
+    pending: Arc<Pending<T>>,
+    notify: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl<T> Clone for Injector<T> {
+    fn clone(&self) -> Self {
+        Self { pending: Arc::clone(&self.pending), notify: Arc::clone(&self.notify) }
+    }
+}
+
+impl<T> Injector<T> {
+    /// Push an item with the text to match against.
+    /// The item is dropped if the matcher has been restarted since the injector was created.
+    /// The matcher takes the pushed items in batches, it is only notified once per batch.
+    pub fn push(&self, item: T, haystack: String) {
+        if self.pending.closed.load(Ordering::Acquire) {
+            return;
+        }
+
+        self.pending.items.lock().push((item, haystack));
+        if !self.pending.notified.swap(true, Ordering::AcqRel) {
+            (self.notify)();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
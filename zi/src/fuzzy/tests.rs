@@ -0,0 +1,74 @@
+use super::Pattern;
+
+#[track_caller]
+fn indices(pattern: &str, haystack: &str) -> Option<Vec<u32>> {
+    Pattern::parse(pattern).score(haystack).map(|m| m.indices)
+}
+
+#[track_caller]
+fn score(pattern: &str, haystack: &str) -> u32 {
+    Pattern::parse(pattern).score(haystack).expect("should match").score
+}
+
+#[test]
+fn fuzzy_match_indices() {
+    assert_eq!(indices("abc", "abc"), Some(vec![0, 1, 2]));
+    assert_eq!(indices("abc", "axbxc"), Some(vec![0, 2, 4]));
+    assert_eq!(indices("abc", "acb"), None);
+    assert_eq!(indices("", "abc"), Some(vec![]));
+
+    // Prefers the word boundary and the consecutive run over the earlier scattered match.
+    assert_eq!(indices("bar", "xbxaxr/bar"), Some(vec![7, 8, 9]));
+    assert_eq!(indices("fb", "foo_bar"), Some(vec![0, 4]));
+    assert_eq!(indices("fb", "fooBar"), Some(vec![0, 3]));
+
+    // Terms are matched independently.
+    assert_eq!(indices("bar foo", "foo/bar"), Some(vec![0, 1, 2, 4, 5, 6]));
+    assert_eq!(indices("bar baz", "foo/bar"), None);
+}
+
+#[test]
+fn fuzzy_match_smart_case() {
+    assert!(indices("abc", "ABC").is_some());
+    assert!(indices("Abc", "abc").is_none());
+    assert!(indices("Abc", "Abc").is_some());
+}
+
+#[test]
+fn fuzzy_match_ranking() {
+    // Boundaries beat the middle of words
+    assert!(score("bar", "foo/bar") > score("bar", "foobar"));
+    // Consecutive beats scattered
+    assert!(score("bar", "xbar") > score("bar", "xbxaxr"));
+    // Short gaps beat long gaps
+    assert!(score("ab", "axb") > score("ab", "axxxxb"));
+    // Whitespace boundaries beat other boundaries
+    assert!(score("b", "a b") > score("b", "a_b"));
+}
+
+#[test]
+fn matcher_restart() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::Matcher;
+
+    let mut matcher = Matcher::new(Arc::new(|| {}));
+    let injector = matcher.injector();
+    for item in ["foo", "bar", "baz"] {
+        injector.push(item, item.to_string());
+    }
+
+    matcher.reparse("ba");
+    assert!(matcher.tick(Duration::from_secs(5)));
+    assert_eq!(matcher.matched_items(10), [("bar", vec![0, 1]), ("baz", vec![0, 1])]);
+
+    // Restarting invalidates the old injector without waiting for anything.
+    matcher.restart();
+    assert_eq!(matcher.matched_item_count(), 0);
+    injector.push("bax", "bax".to_string());
+    matcher.injector().push("bay", "bay".to_string());
+    assert!(matcher.tick(Duration::from_secs(5)));
+    assert_eq!(matcher.get_matched_item(0), Some("bay"));
+    assert_eq!(matcher.matched_item_count(), 1);
+}
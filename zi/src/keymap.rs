@@ -6,16 +6,19 @@ use stdx::merge::Merge;
 use zi_input::KeyEvent;
 
 use crate::Mode;
-use crate::editor::Action;
+use crate::editor::Mapping;
 
 mod macros;
 
-pub trait DynKeymap<M = Mode, K = KeyEvent, V = Action> {
+pub trait DynKeymap<M = Mode, K = KeyEvent, V = Mapping> {
     fn on_key(&mut self, mode: M, key: K) -> (TrieResult<V>, Vec<K>);
+
+    /// Discard the keys of the current sequence and return them.
+    fn reset(&mut self) -> Vec<K>;
 }
 
 #[derive(Debug, Clone)]
-pub struct Keymap<M = Mode, K = KeyEvent, V = Action> {
+pub struct Keymap<M = Mode, K = KeyEvent, V = Mapping> {
     maps: HashMap<M, Trie<K, V>>,
     /// The keys that have been pressed so far
     buffer: Vec<K>,
@@ -37,6 +40,10 @@ where
     fn on_key(&mut self, mode: M, key: K) -> (TrieResult<V>, Vec<K>) {
         self.on_key(mode, key)
     }
+
+    fn reset(&mut self) -> Vec<K> {
+        std::mem::take(&mut self.buffer)
+    }
 }
 
 impl<M, K, V> Keymap<M, K, V>
@@ -45,12 +52,29 @@ where
     K: Eq + Hash + Clone,
     V: Clone,
 {
-    // This method should be useful eventually, just cfg it to hide warnings
-    #[cfg(test)]
+    /// Map the key sequence in `mode` to `value`, returning the previous value of the sequence.
     pub fn insert(&mut self, mode: M, keys: impl IntoIterator<Item = K>, value: V) -> Option<V> {
         self.maps.entry(mode).or_default().insert(keys.into_iter().peekable(), value)
     }
 
+    /// Remove the mapping of the key sequence in `mode`, returning its value.
+    pub fn remove(&mut self, mode: &M, keys: impl IntoIterator<Item = K>) -> Option<V> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        self.maps.get_mut(mode)?.remove(keys.iter())
+    }
+
+    /// The result of the key sequence in `mode` as if it were typed from the start, without
+    /// affecting the current sequence.
+    pub fn get(&self, mode: &M, keys: impl IntoIterator<Item = K>) -> TrieResult<&V> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        self.maps.get(mode).map_or(TrieResult::Nothing, |trie| trie.get(keys.iter()))
+    }
+
+    /// Whether there are any mappings in `mode`.
+    pub fn has_mappings(&self, mode: &M) -> bool {
+        self.maps.get(mode).is_some_and(|trie| !trie.children.is_empty())
+    }
+
     /// Returns the result of the key sequence and the keys that were discarded
     pub fn on_key(&mut self, mode: M, key: K) -> (TrieResult<V>, Vec<K>) {
         if let Some(last_mode) = &self.last_mode {
@@ -76,6 +100,7 @@ where
                 (TrieResult::Found(v.clone()), vec![])
             }
             TrieResult::Partial => (TrieResult::Partial, vec![]),
+            TrieResult::Ambiguous(v) => (TrieResult::Ambiguous(v.clone()), vec![]),
             TrieResult::Nothing => {
                 let mut cancelled = std::mem::take(&mut self.buffer);
                 let key = cancelled.last().expect("buffer can't be empty");
@@ -91,14 +116,37 @@ where
                 // We now pop off the key from the discard as it's being used
                 let key = cancelled.pop().expect("we just checked `last` exists");
                 match self.on_key(mode, key).0 {
-                    TrieResult::Found(v) => (TrieResult::Found(v), cancelled),
-                    TrieResult::Partial => (TrieResult::Partial, cancelled),
                     TrieResult::Nothing => unreachable!("just checked it above"),
+                    res => (res, cancelled),
                 }
             }
         }
     }
 
+    /// The keys that continue the current key sequence in `mode` and their values, `None` if the
+    /// key starts a longer sequence.
+    pub fn continuations(&self, mode: &M) -> Vec<(&K, Option<&V>)> {
+        if self.buffer.is_empty() || self.last_mode.as_ref() != Some(mode) {
+            return vec![];
+        }
+
+        let Some(mut trie) = self.maps.get(mode) else { return vec![] };
+        for key in &self.buffer {
+            match trie.children.get(key) {
+                Some(TrieNode::Trie(next)) => trie = next,
+                _ => return vec![],
+            }
+        }
+
+        trie.children
+            .iter()
+            .map(|(key, node)| match node {
+                TrieNode::Trie(_) => (key, None),
+                TrieNode::Value(v) => (key, Some(v)),
+            })
+            .collect()
+    }
+
     /// Operate two keymaps simultaneously with right-bias
     pub fn pair<'a>(
         &'a mut self,
@@ -116,19 +164,21 @@ impl<M, K, V> Default for Keymap<M, K, V> {
 
 #[derive(Debug, Clone)]
 pub struct Trie<K, V> {
+    /// The value of the keys leading to this trie if they are also a complete sequence.
+    value: Option<V>,
     children: HashMap<K, TrieNode<K, V>>,
 }
 
 impl<K: Eq + Hash, V> Merge for Trie<K, V> {
     fn merge(self, other: Self) -> Self {
         let children = self.children.merge(other.children);
-        Self { children }
+        Self { value: other.value.or(self.value), children }
     }
 }
 
 impl<K, V> Trie<K, V> {
     pub(crate) fn new(children: HashMap<K, TrieNode<K, V>>) -> Self {
-        Self { children }
+        Self { value: None, children }
     }
 }
 
@@ -136,6 +186,9 @@ impl<K, V> Trie<K, V> {
 pub enum TrieResult<V> {
     Found(V),
     Partial,
+    /// The keys are a complete sequence but also the prefix of a longer one.
+    /// The value should be used if no other key arrives within the timeout.
+    Ambiguous(V),
     Nothing,
 }
 
@@ -149,7 +202,7 @@ where
     {
         let k = match keys.next() {
             Some(k) => k,
-            None => return TrieResult::Partial,
+            None => return self.value.as_ref().map_or(TrieResult::Partial, TrieResult::Ambiguous),
         };
 
         match self.children.get(k) {
@@ -159,7 +212,6 @@ where
         }
     }
 
-    #[cfg(test)]
     fn insert<I: Iterator<Item = K>>(
         &mut self,
         mut keys: std::iter::Peekable<I>,
@@ -179,24 +231,64 @@ where
         };
 
         match self.children.entry(k) {
-            Entry::Occupied(mut entry) => match entry.get_mut() {
-                TrieNode::Trie(trie) => trie.insert(keys, value),
-                TrieNode::Value(_) => match entry.insert(mk_new_node(keys, value)) {
-                    TrieNode::Value(v) => Some(v),
-                    _ => unreachable!("we know it's a value"),
-                },
-            },
+            Entry::Occupied(mut entry) => {
+                let node = entry.get_mut();
+                if matches!(node, TrieNode::Value(_)) && keys.peek().is_some() {
+                    // The shorter sequence becomes ambiguous with the longer one.
+                    let prev = std::mem::replace(node, TrieNode::Trie(Trie::default()));
+                    let (TrieNode::Value(prev), TrieNode::Trie(trie)) = (prev, &mut *node) else {
+                        unreachable!("we know it's a value")
+                    };
+                    trie.value = Some(prev);
+                }
+
+                match node {
+                    TrieNode::Trie(trie) if keys.peek().is_none() => trie.value.replace(value),
+                    TrieNode::Trie(trie) => trie.insert(keys, value),
+                    TrieNode::Value(v) => Some(std::mem::replace(v, value)),
+                }
+            }
             Entry::Vacant(entry) => {
                 entry.insert(mk_new_node(keys, value));
                 None
             }
         }
     }
+
+    /// Remove the value of the key sequence, removing any tries left empty.
+    fn remove<'a>(&mut self, mut keys: impl Iterator<Item = &'a K>) -> Option<V>
+    where
+        K: 'a,
+    {
+        let Some(k) = keys.next() else { return self.value.take() };
+
+        let node = self.children.get_mut(k)?;
+        match node {
+            TrieNode::Trie(trie) => {
+                let value = trie.remove(keys)?;
+                if trie.children.is_empty() {
+                    // Only the value of the prefix is left, if any.
+                    match trie.value.take() {
+                        Some(prefix) => *node = TrieNode::Value(prefix),
+                        None => {
+                            self.children.remove(k);
+                        }
+                    }
+                }
+                Some(value)
+            }
+            TrieNode::Value(_) if keys.next().is_none() => match self.children.remove(k) {
+                Some(TrieNode::Value(value)) => Some(value),
+                _ => unreachable!("we know it's a value"),
+            },
+            TrieNode::Value(_) => None,
+        }
+    }
 }
 
 impl<K, V> Default for Trie<K, V> {
     fn default() -> Self {
-        Self { children: Default::default() }
+        Self { value: None, children: Default::default() }
     }
 }
 
@@ -210,6 +302,11 @@ impl<K: Eq + Hash, V> Merge for TrieNode<K, V> {
     fn merge(self, other: Self) -> Self {
         match (self, other) {
             (Self::Trie(a), Self::Trie(b)) => Self::Trie(a.merge(b)),
+            // The value is kept as the value of the prefix, unless the trie already has one.
+            (Self::Value(a), Self::Trie(mut b)) => {
+                b.value = b.value.or(Some(a));
+                Self::Trie(b)
+            }
             (_, b) => b,
         }
     }
@@ -273,10 +370,14 @@ where
 
         let (v, buf, state) = match (lhs, rhs) {
             (_, Found(v)) => (Found(v), rbuf, Both),
-            // This case means the right side has a binding that "shadows" the same binding on the left side.
-            // We basically just pretend the left side doesn't exist.
-            (Found(_), Partial) => (Partial, rbuf, Right),
+            (Partial | Ambiguous(_), Ambiguous(v)) => (Ambiguous(v), rbuf, Both),
+            (_, Ambiguous(v)) => (Ambiguous(v), rbuf, Right),
+            // This case means the right side has a longer binding that extends the binding on the left side.
+            // The left binding is only used if the right binding isn't completed.
+            (Found(v), Partial) => (Ambiguous(v), rbuf, Right),
             (Found(v), Nothing) => (Found(v), lbuf, Both),
+            (Ambiguous(v), Partial) => (Ambiguous(v), rbuf, Both),
+            (Ambiguous(v), Nothing) => (Ambiguous(v), lbuf, Left),
             (Partial, Partial) => {
                 debug_assert_eq!(lbuf, rbuf, "buffers should be the same to reach this state");
                 (Partial, rbuf, Both)
@@ -289,6 +390,12 @@ where
         self.state = state;
         (v, buf)
     }
+
+    fn reset(&mut self) -> Vec<K> {
+        self.state = State::default();
+        let (lbuf, rbuf) = (self.a.reset(), self.b.reset());
+        if lbuf.len() > rbuf.len() { lbuf } else { rbuf }
+    }
 }
 
 #[cfg(test)]
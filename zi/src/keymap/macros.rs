@@ -19,7 +19,10 @@ macro_rules! hashmap {
 #[macro_export]
 macro_rules! trie {
     (@trie $value:path) => {
-        $crate::keymap::TrieNode::Value($value as _)
+        $crate::keymap::TrieNode::Value($crate::editor::Mapping::Action {
+            action: $value as _,
+            desc: stringify!($value),
+        })
     };
 
     (
@@ -41,10 +41,27 @@ fn keymap() {
     assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
     assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Found(3), vec![]));
 
-    // Overwrite with longer key sequence
-    assert_eq!(keymap.insert(Mode::Normal, ['f', 'd', 'd'], 5), Some(3));
+    // Extend with a longer key sequence, the shorter one is ambiguous
+    assert!(keymap.insert(Mode::Normal, ['f', 'd', 'd'], 5).is_none());
     assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
-    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Partial, vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Ambiguous(3), vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Found(5), vec![]));
+
+    // The ambiguous sequence is abandoned by a key that doesn't extend it
+    assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Ambiguous(3), vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'i'), (Found(2), vec!['f', 'd']));
+
+    // Reset returns the buffered keys, as happens when the timeout elapses
+    assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Ambiguous(3), vec![]));
+    assert_eq!(keymap.reset(), vec!['f', 'd']);
+    assert_eq!(keymap.on_key(Mode::Normal, 'i'), (Found(2), vec![]));
+
+    // Overwrite the shorter sequence of an ambiguous one
+    assert_eq!(keymap.insert(Mode::Normal, ['f', 'd'], 7), Some(3));
+    assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Ambiguous(7), vec![]));
     assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Found(5), vec![]));
 
     // Different mode
@@ -110,8 +127,59 @@ fn keymap_pair() {
     assert_eq!(c.on_key(Mode::Normal, 'h'), (Partial, vec![]));
     assert_eq!(c.on_key(Mode::Normal, 'a'), (Found(13), vec![]));
 
-    // abc on the right should shadow ab on the left.
+    // abc on the right makes ab on the left ambiguous.
     assert_eq!(c.on_key(Mode::Normal, 'a'), (Partial, vec![]));
-    assert_eq!(c.on_key(Mode::Normal, 'b'), (Partial, vec![]));
+    assert_eq!(c.on_key(Mode::Normal, 'b'), (Ambiguous(4), vec![]));
     assert_eq!(c.on_key(Mode::Normal, 'c'), (Found(14), vec![]));
+
+    assert_eq!(c.on_key(Mode::Normal, 'a'), (Partial, vec![]));
+    assert_eq!(c.on_key(Mode::Normal, 'b'), (Ambiguous(4), vec![]));
+    assert_eq!(c.reset(), vec!['a', 'b']);
+}
+
+#[test]
+fn keymap_continuations() {
+    let mut keymap = Keymap::<Mode, char, u32>::default();
+    assert!(keymap.insert(Mode::Normal, ['g', 'g'], 1).is_none());
+    assert!(keymap.insert(Mode::Normal, ['g', 'a', 'b'], 2).is_none());
+    assert!(keymap.continuations(&Mode::Normal).is_empty());
+
+    assert_eq!(keymap.on_key(Mode::Normal, 'g'), (Partial, vec![]));
+    let mut continuations = keymap.continuations(&Mode::Normal);
+    continuations.sort();
+    assert_eq!(continuations, [(&'a', None), (&'g', Some(&1))]);
+    assert!(keymap.continuations(&Mode::Insert).is_empty());
+
+    assert_eq!(keymap.on_key(Mode::Normal, 'g'), (Found(1), vec![]));
+    assert!(keymap.continuations(&Mode::Normal).is_empty());
+}
+
+#[test]
+fn keymap_remove() {
+    let mut keymap = Keymap::<Mode, char, u32>::default();
+    assert!(keymap.insert(Mode::Normal, ['f', 'd'], 1).is_none());
+    assert!(keymap.insert(Mode::Normal, ['f', 'd', 'd'], 2).is_none());
+    assert!(keymap.insert(Mode::Normal, ['g'], 3).is_none());
+
+    assert_eq!(keymap.get(&Mode::Normal, ['f']), Partial);
+    assert_eq!(keymap.get(&Mode::Normal, ['f', 'd']), Ambiguous(&1));
+    assert_eq!(keymap.get(&Mode::Normal, ['g', 'x']), Found(&3));
+    assert_eq!(keymap.get(&Mode::Normal, ['x']), Nothing);
+    assert_eq!(keymap.get(&Mode::Insert, ['g']), Nothing);
+
+    assert_eq!(keymap.remove(&Mode::Normal, ['f']), None);
+    assert_eq!(keymap.remove(&Mode::Normal, ['g', 'x']), None);
+    assert_eq!(keymap.remove(&Mode::Insert, ['g']), None);
+
+    // Removing the longer sequence leaves the shorter one unambiguous.
+    assert_eq!(keymap.remove(&Mode::Normal, ['f', 'd', 'd']), Some(2));
+    assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Partial, vec![]));
+    assert_eq!(keymap.on_key(Mode::Normal, 'd'), (Found(1), vec![]));
+
+    assert_eq!(keymap.remove(&Mode::Normal, ['f', 'd']), Some(1));
+    assert_eq!(keymap.on_key(Mode::Normal, 'f'), (Nothing, vec!['f']));
+    assert!(keymap.has_mappings(&Mode::Normal));
+
+    assert_eq!(keymap.remove(&Mode::Normal, ['g']), Some(3));
+    assert!(!keymap.has_mappings(&Mode::Normal));
 }
@@ -38,10 +38,37 @@ pub struct KnownFileTypes {
     pub json: FileType,
     pub picker: FileType,
     pub explorer: FileType,
+    pub terminal: FileType,
+    pub output: FileType,
     pub zig: FileType,
     pub python: FileType,
     pub yaml: FileType,
     pub nix: FileType,
+    pub markdown: FileType,
+}
+
+impl KnownFileTypes {
+    /// Iterate over the known file types of actual languages (excluding special buffer types).
+    pub fn iter(&self) -> impl Iterator<Item = FileType> {
+        [
+            self.gqlt,
+            self.javascript,
+            self.typescript,
+            self.c,
+            self.rust,
+            self.fsharp,
+            self.go,
+            self.haskell,
+            self.toml,
+            self.json,
+            self.zig,
+            self.python,
+            self.yaml,
+            self.nix,
+            self.markdown,
+        ]
+        .into_iter()
+    }
 }
 
 fn ft(ft: &str) -> FileType {
@@ -74,9 +101,12 @@ impl FileType {
             zig: ft("zig"),
             picker: ft("picker"),
             explorer: ft("explorer"),
+            terminal: ft("terminal"),
+            output: ft("output"),
             python: ft("python"),
             yaml: ft("yaml"),
             nix: ft("nix"),
+            markdown: ft("markdown"),
         })
     }
 
@@ -101,6 +131,7 @@ impl FileType {
                 Some("py") => filetype!(python),
                 Some("yaml") | Some("yml") => filetype!(yaml),
                 Some("nix") => filetype!(nix),
+                Some("md") => filetype!(markdown),
                 _ => filetype!(text),
             },
             None => filetype!(text),
@@ -189,10 +220,72 @@ impl Config {
 #[derive(Debug, Default)]
 pub struct LanguageConfig {
     pub language_services: Box<[LanguageServiceId]>,
+    /// Whether to color nested delimiters by depth, see [`Syntax::rainbows`](crate::Syntax::rainbows).
+    pub rainbow_delimiters: bool,
+    /// The comment strings used by the comment operator, defaults to [`CommentTokens::for_file_type`].
+    pub comment_tokens: Option<CommentTokens>,
 }
 
 impl LanguageConfig {
     pub fn new(language_servers: impl IntoIterator<Item = LanguageServiceId>) -> Self {
-        Self { language_services: language_servers.into_iter().collect() }
+        Self {
+            language_services: language_servers.into_iter().collect(),
+            rainbow_delimiters: false,
+            comment_tokens: None,
+        }
+    }
+
+    pub fn with_rainbow_delimiters(mut self, enabled: bool) -> Self {
+        self.rainbow_delimiters = enabled;
+        self
+    }
+
+    pub fn with_comment_tokens(mut self, tokens: CommentTokens) -> Self {
+        self.comment_tokens = Some(tokens);
+        self
+    }
+}
+
+/// The strings that start a line comment and delimit a block comment in a language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentTokens {
+    pub line: Option<String>,
+    pub block: Option<(String, String)>,
+}
+
+impl CommentTokens {
+    pub fn line(line: impl Into<String>) -> Self {
+        Self { line: Some(line.into()), block: None }
+    }
+
+    pub fn block(open: impl Into<String>, close: impl Into<String>) -> Self {
+        Self { line: None, block: Some((open.into(), close.into())) }
+    }
+
+    pub fn with_block(mut self, open: impl Into<String>, close: impl Into<String>) -> Self {
+        self.block = Some((open.into(), close.into()));
+        self
+    }
+
+    /// The builtin comment strings of the known file types.
+    pub fn for_file_type(ft: FileType) -> Option<Self> {
+        let known = FileType::known();
+        let tokens =
+            if [known.c, known.rust, known.go, known.javascript, known.typescript].contains(&ft) {
+                Self::line("//").with_block("/*", "*/")
+            } else if [known.zig, known.gqlt].contains(&ft) {
+                Self::line("//")
+            } else if ft == known.fsharp {
+                Self::line("//").with_block("(*", "*)")
+            } else if ft == known.haskell {
+                Self::line("--").with_block("{-", "-}")
+            } else if ft == known.nix {
+                Self::line("#").with_block("/*", "*/")
+            } else if [known.toml, known.python, known.yaml].contains(&ft) {
+                Self::line("#")
+            } else {
+                return None;
+            };
+        Some(tokens)
     }
 }
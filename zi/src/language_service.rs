@@ -1,6 +1,7 @@
 pub mod lstypes;
 
 use std::any::Any;
+use std::ffi::OsStr;
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
@@ -109,6 +110,15 @@ pub trait LanguageService {
         None
     }
 
+    fn completion_resolve_capabilities(&self) -> Option<()> {
+        None
+    }
+
+    /// The characters that request completions from the service when typed.
+    fn completion_trigger_characters(&self) -> Vec<char> {
+        vec![]
+    }
+
     fn reference_capabilities(&self) -> Option<()> {
         None
     }
@@ -125,6 +135,10 @@ pub trait LanguageService {
         None
     }
 
+    fn will_rename_files_capabilities(&self) -> Option<()> {
+        None
+    }
+
     /// Initialize the language service.
     /// This must be called before any other method and should only be called exactly once.
     fn initialize(&mut self, params: lstypes::InitializeParams) -> ResponseFuture<()> {
@@ -184,6 +198,15 @@ pub trait LanguageService {
         unimplemented!()
     }
 
+    /// Fill in the missing fields of a completion item, such as its documentation.
+    fn resolve_completion_item(
+        &mut self,
+        item: lstypes::CompletionItem,
+    ) -> ResponseFuture<lstypes::CompletionItem> {
+        let _ = item;
+        unimplemented!()
+    }
+
     fn semantic_tokens_full(
         &mut self,
         // Bit of a hack parameter, find another cleaner way
@@ -208,6 +231,16 @@ pub trait LanguageService {
         unimplemented!()
     }
 
+    /// Notify the service that files are about to be renamed.
+    /// The service may return edits to apply before the rename, e.g. to update imports.
+    fn will_rename_files(
+        &mut self,
+        params: lstypes::RenameFilesParams,
+    ) -> ResponseFuture<Option<lstypes::WorkspaceEdit>> {
+        let _ = params;
+        unimplemented!()
+    }
+
     fn shutdown(&mut self) -> ResponseFuture<()> {
         async { Ok(()) }.boxed()
     }
@@ -226,4 +259,9 @@ pub trait LanguageServiceConfig {
         cwd: &Path,
         client: LanguageClient,
     ) -> Result<(Box<dyn LanguageService + Send>, BoxFuture<'static, Result<()>>)>;
+
+    /// The command used to start the service (if any), used to check that it is installed.
+    fn command(&self) -> Option<&OsStr> {
+        None
+    }
 }
@@ -5,8 +5,8 @@ use std::collections::HashMap;
 use std::fmt;
 
 use url::Url;
-pub use zi_core::{CompletionItem, Point, PointRange};
-use zi_text::Text;
+pub use zi_core::{CompletionItem, CompletionItemKind, Point, PointRange};
+use zi_text::{Text, TextSlice};
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct InitializeParams {
@@ -41,6 +41,29 @@ pub struct ReferenceParams {
     pub at: TextDocumentPointParams,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RenameFilesParams {
+    pub files: Vec<FileRename>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FileRename {
+    pub old_url: Url,
+    pub new_url: Url,
+}
+
+/// Edits to multiple documents, which may not be open.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct WorkspaceEdit {
+    pub changes: Vec<(Url, Vec<TextEdit>)>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TextEdit {
+    pub range: EncodedRange,
+    pub new_text: String,
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum GotoDefinitionResponse {
     Array(Vec<Location>),
@@ -84,6 +107,13 @@ impl fmt::Display for EncodedRange {
     }
 }
 
+impl From<PointRange> for EncodedRange {
+    #[inline]
+    fn from(range: PointRange) -> Self {
+        Self { range, encoding: PositionEncoding::Utf8 }
+    }
+}
+
 impl EncodedRange {
     pub fn new(encoding: PositionEncoding, range: PointRange) -> Self {
         Self { encoding, range }
@@ -126,6 +156,60 @@ pub enum PositionEncoding {
     /// UTF-16 code units
     #[default]
     Utf16,
+    /// UTF-32 code units (codepoints)
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The number of code units of `c` in this encoding.
+    #[inline]
+    pub fn len_char(self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+
+    /// Convert the byte to a point with the column in code units of this encoding.
+    pub fn encode<T: Text + ?Sized>(self, text: &T, byte: usize) -> Point {
+        let line = text.byte_to_line(byte);
+        let line_start = text.line_to_byte(line);
+        let col = match self {
+            PositionEncoding::Utf8 => byte - line_start,
+            PositionEncoding::Utf16 => {
+                text.byte_to_utf16_cu(byte) - text.byte_to_utf16_cu(line_start)
+            }
+            PositionEncoding::Utf32 => text.byte_slice(line_start..byte).chars().count(),
+        };
+        Point::new(line, col)
+    }
+
+    /// Convert a point with the column in code units of this encoding to a byte point.
+    /// Returns `None` if the line is out of bounds. As per the LSP spec, a column past the end of
+    /// the line means the end of the line. A column within a character means its start.
+    pub fn decode<T: Text + ?Sized>(self, text: &T, point: Point) -> Option<Point> {
+        if point.line() > text.len_lines() {
+            return None;
+        }
+
+        // The line after the last one is only valid as the end of the text.
+        let Some(line) = text.line(point.line()) else {
+            return Some(Point::new(point.line(), 0));
+        };
+
+        let mut units = 0;
+        let mut col = 0;
+        for c in line.chars() {
+            units += self.len_char(c);
+            if units > point.col() {
+                break;
+            }
+            col += c.len_utf8();
+        }
+
+        Some(Point::new(point.line(), col))
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -189,23 +273,8 @@ pub(crate) trait TextExt {
 }
 
 impl<T: Text> TextExt for T {
+    #[inline]
     fn decode_point(&self, EncodedPoint { point, encoding }: EncodedPoint) -> Option<Point> {
-        if point.line() > self.len_lines() {
-            return None;
-        }
-
-        match encoding {
-            PositionEncoding::Utf8 => Some(Point::new(point.line(), point.col())),
-            PositionEncoding::Utf16 => {
-                let line_start_byte = self.line_to_byte(point.line());
-                let line_start_cu = self.byte_to_utf16_cu(line_start_byte);
-                if line_start_cu + point.col() > self.len_utf16_cu() {
-                    return None;
-                }
-
-                let byte = self.utf16_cu_to_byte(line_start_cu + point.col());
-                Some(self.byte_to_point(byte))
-            }
-        }
+        encoding.decode(self, point)
     }
 }
@@ -1,19 +1,217 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use tui::{Constraint, Layout, Rect, Widget as _};
 
 use crate::{Direction, Editor, Size, ViewId};
 
+/// Kinds of special buffers whose placement is configurable via [`PlacementRules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BufferKind {
+    Picker,
+    Explorer,
+    Quickfix,
+    Terminal,
+    Output,
+    Help,
+}
+
+/// Where a special buffer is displayed when it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Display the buffer in the active view.
+    Replace,
+    /// Dock against an edge of the editor taking up `size` of the space perpendicular to it.
+    /// e.g. `Dock { edge: Down, size: Percentage(30) }` is the bottom 30% of the editor.
+    Dock { edge: Direction, size: Constraint },
+    /// Float in the center of the editor above all other views.
+    Float { width: Constraint, height: Constraint },
+}
+
+impl Placement {
+    /// Compute the area the placement occupies within `area`.
+    pub fn area(self, area: Rect) -> Rect {
+        match self {
+            Placement::Replace => area,
+            Placement::Dock { edge, size } => match edge {
+                Direction::Up => Layout::vertical([size, Constraint::Fill(1)]).areas::<2>(area)[0],
+                Direction::Down => {
+                    Layout::vertical([Constraint::Fill(1), size]).areas::<2>(area)[1]
+                }
+                Direction::Left => {
+                    Layout::horizontal([size, Constraint::Fill(1)]).areas::<2>(area)[0]
+                }
+                Direction::Right => {
+                    Layout::horizontal([Constraint::Fill(1), size]).areas::<2>(area)[1]
+                }
+            },
+            Placement::Float { width, height } => {
+                let [_, area, _] =
+                    Layout::vertical([Constraint::Fill(1), height, Constraint::Fill(1)])
+                        .areas(area);
+                let [_, area, _] =
+                    Layout::horizontal([Constraint::Fill(1), width, Constraint::Fill(1)])
+                        .areas(area);
+                area
+            }
+        }
+    }
+}
+
+/// Where a floating window is positioned, see [`FloatConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatAnchor {
+    /// The top-left corner of the float is at `(x, y)` in the editor.
+    Editor { x: u16, y: u16 },
+    /// The top-left corner of the float is offset from the cursor of the active view,
+    /// e.g. `Cursor { x: 0, y: 1 }` is the cell below the cursor.
+    Cursor { x: i16, y: i16 },
+    /// Center the float in the editor.
+    Center,
+}
+
+/// The configuration of a floating window opened with [`Editor::open_float`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatConfig {
+    anchor: FloatAnchor,
+    width: u16,
+    height: u16,
+    border: bool,
+    z_index: u8,
+    focusable: bool,
+}
+
+impl FloatConfig {
+    /// A float with content of size `width` by `height` at `anchor`.
+    /// By default it has no border, a z-index of 0, and is focusable.
+    pub fn new(anchor: FloatAnchor, width: u16, height: u16) -> Self {
+        Self { anchor, width, height, border: false, z_index: 0, focusable: true }
+    }
+
+    pub fn anchor(&self) -> FloatAnchor {
+        self.anchor
+    }
+
+    /// Draw a border around the float. The border is outside of the content size.
+    #[must_use]
+    pub fn with_border(mut self, border: bool) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Floats with a higher z-index are drawn above those with a lower one, floats with the same
+    /// z-index are drawn in the order they were opened.
+    #[must_use]
+    pub fn with_z_index(mut self, z_index: u8) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
+    /// A focusable float becomes the active view when it is opened and can be clicked on.
+    /// Otherwise, it is only displayed and the focus stays where it was.
+    #[must_use]
+    pub fn with_focusable(mut self, focusable: bool) -> Self {
+        self.focusable = focusable;
+        self
+    }
+
+    #[must_use]
+    pub(crate) fn with_anchor(mut self, anchor: FloatAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Compute the area of the float including its border within `area`.
+    /// The float is moved up and left to fit if necessary, and is truncated if it's still too big.
+    /// Cursor anchors must be resolved to editor anchors before this is called.
+    pub(crate) fn area(&self, area: Rect) -> Rect {
+        let border = if self.border { 2 } else { 0 };
+        let width = self.width.saturating_add(border).min(area.width);
+        let height = self.height.saturating_add(border).min(area.height);
+        let (x, y) = match self.anchor {
+            FloatAnchor::Editor { x, y } => (area.x.saturating_add(x), area.y.saturating_add(y)),
+            FloatAnchor::Center => {
+                (area.x + (area.width - width) / 2, area.y + (area.height - height) / 2)
+            }
+            FloatAnchor::Cursor { .. } => unreachable!("cursor anchors should have been resolved"),
+        };
+
+        Rect { x: x.min(area.right() - width), y: y.min(area.bottom() - height), width, height }
+    }
+
+    /// The area of the content of the float with the given outer area.
+    fn inner(&self, area: Rect) -> Rect {
+        if self.border { tui::Block::bordered().inner(area) } else { area }
+    }
+}
+
+struct Float {
+    view: ViewId,
+    config: FloatConfig,
+}
+
+/// A mapping from special buffer kinds to their placement.
+/// Kinds without a rule use their built-in placement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlacementRules {
+    rules: BTreeMap<BufferKind, Placement>,
+}
+
+impl PlacementRules {
+    pub fn get(&self, kind: BufferKind) -> Option<Placement> {
+        self.rules.get(&kind).copied()
+    }
+
+    pub fn set(&mut self, kind: BufferKind, placement: Placement) -> Option<Placement> {
+        self.rules.insert(kind, placement)
+    }
+
+    pub fn remove(&mut self, kind: BufferKind) -> Option<Placement> {
+        self.rules.remove(&kind)
+    }
+
+    #[must_use]
+    pub fn with(mut self, kind: BufferKind, placement: Placement) -> Self {
+        self.set(kind, placement);
+        self
+    }
+}
+
+/// A dimension along which a view can be resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Width,
+    Height,
+}
+
+impl From<Dimension> for tui::Direction {
+    fn from(dimension: Dimension) -> Self {
+        match dimension {
+            Dimension::Width => tui::Direction::Horizontal,
+            Dimension::Height => tui::Direction::Vertical,
+        }
+    }
+}
+
 pub(crate) struct ViewTree {
     size: Size,
     layers: Vec<Layer>,
+    /// Floating windows above all layers, sorted by z-index.
+    floats: Vec<Float>,
+    /// The focused float, which takes precedence over the active view of the top layer.
+    focused_float: Option<ViewId>,
     last_known_area: RefCell<HashMap<ViewId, Rect>>,
 }
 
 impl ViewTree {
     pub fn new(size: Size, view: ViewId) -> Self {
-        ViewTree { size, layers: vec![Layer::new(view)], last_known_area: Default::default() }
+        ViewTree {
+            size,
+            layers: vec![Layer::new(view)],
+            floats: vec![],
+            focused_float: None,
+            last_known_area: Default::default(),
+        }
     }
 
     pub fn size(&self) -> Size {
@@ -26,6 +224,12 @@ impl ViewTree {
 
     /// Get the area of a view in the tree, returns the last known area if the view is no longer in the tree
     pub fn view_area(&self, id: ViewId) -> Rect {
+        if let Some(float) = self.float(id) {
+            let area = float.config.inner(float.config.area(self.area()));
+            self.last_known_area.borrow_mut().insert(id, area);
+            return area;
+        }
+
         for layer in self.layers.iter().rev() {
             if let Some(area) = layer.view_area(self.area(), id) {
                 self.last_known_area.borrow_mut().insert(id, area);
@@ -44,19 +248,59 @@ impl ViewTree {
         self.layers.is_empty()
     }
 
+    /// The focusable float or the view of the top layer at the position, if any.
+    pub fn view_at(&self, x: u16, y: u16) -> Option<ViewId> {
+        let float = self.floats.iter().rev().find(|float| {
+            let area = float.config.area(self.area());
+            float.config.focusable
+                && (area.left()..area.right()).contains(&x)
+                && (area.top()..area.bottom()).contains(&y)
+        });
+
+        match float {
+            Some(float) => Some(float.view),
+            None => self.layers.last()?.view_at(self.area(), x, y),
+        }
+    }
+
     pub fn push(&mut self, layer: Layer) {
+        self.focused_float = None;
         self.layers.push(layer);
     }
 
+    /// Add a floating window above all layers and floats with a lower or equal z-index.
+    pub fn push_float(&mut self, view: ViewId, config: FloatConfig) {
+        assert!(self.views().all(|v| v != view), "cannot float an existing view");
+        let idx = self.floats.partition_point(|float| float.config.z_index <= config.z_index);
+        self.floats.insert(idx, Float { view, config });
+        if config.focusable {
+            self.focused_float = Some(view);
+        }
+    }
+
+    fn float(&self, view: ViewId) -> Option<&Float> {
+        self.floats.iter().find(|float| float.view == view)
+    }
+
     pub fn pop(&mut self) -> Layer {
         self.layers.pop().expect("no layers to pop")
     }
 
     pub fn active(&self) -> ViewId {
-        self.top().active_view()
+        self.focused_float.unwrap_or_else(|| self.top().active_view())
     }
 
     pub fn close_view(&mut self, view: ViewId) -> Result<(), ()> {
+        if let Some(idx) = self.floats.iter().position(|float| float.view == view) {
+            self.floats.remove(idx);
+            if self.focused_float == Some(view) {
+                // Focus the most recently opened focusable float, if any.
+                self.focused_float =
+                    self.floats.iter().rev().find(|float| float.config.focusable).map(|f| f.view);
+            }
+            return Ok(());
+        }
+
         if self.is_empty() {
             return Err(());
         }
@@ -73,10 +317,26 @@ impl ViewTree {
         }
     }
 
+    /// The areas of the floats including their borders, bottom-most first.
+    pub fn float_areas(&self) -> impl Iterator<Item = tui::FloatArea> + '_ {
+        self.floats
+            .iter()
+            .map(|float| tui::FloatArea::new(float.config.area(self.area()), float.config.z_index))
+    }
+
     pub fn render(&self, editor: &Editor, surface: &mut tui::Buffer) {
         for layer in &self.layers {
             layer.render(editor, self.area(), surface);
         }
+
+        for float in &self.floats {
+            let area = float.config.area(self.area());
+            tui::Clear.render(area, surface);
+            if float.config.border {
+                tui::Block::bordered().render(area, surface);
+            }
+            editor.render_view(float.config.inner(area), surface, float.view);
+        }
     }
 
     pub fn split(
@@ -88,19 +348,42 @@ impl ViewTree {
     ) {
         assert_ne!(view, new, "cannot split a view into itself");
         assert!(self.views().all(|v| v != new), "cannot split into an existing view");
+        // Floats can't be split, split the view below them instead.
+        let view = if self.float(view).is_some() { self.top().active_view() } else { view };
+        self.focused_float = None;
         self.top_mut().split(view, new, direction, constraint)
     }
 
     pub fn focus_direction(&mut self, direction: Direction) -> ViewId {
+        self.focused_float = None;
         self.top_mut().focus_direction(direction)
     }
 
     pub fn focus(&mut self, view: ViewId) {
-        self.top_mut().focus(view)
+        match self.float(view) {
+            Some(float) => {
+                assert!(float.config.focusable, "cannot focus on an unfocusable float");
+                self.focused_float = Some(view);
+            }
+            None => {
+                self.focused_float = None;
+                self.top_mut().focus(view)
+            }
+        }
     }
 
     pub fn views(&self) -> impl Iterator<Item = ViewId> + '_ {
-        self.layers.iter().flat_map(|layer| layer.views())
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.views())
+            .chain(self.floats.iter().map(|float| float.view))
+    }
+
+    /// Whether closing the view would leave the tree empty, i.e. quit the editor.
+    pub fn is_last_view(&self, view: ViewId) -> bool {
+        self.layers.len() == 1
+            && self.floats.iter().all(|float| float.view != view)
+            && self.top().views().eq([view])
     }
 
     pub fn top(&self) -> &Layer {
@@ -115,8 +398,120 @@ impl ViewTree {
         self.size = size;
     }
 
+    /// Resize the view along the dimension to `size(current_size)` by taking from or giving to its
+    /// neighbours in the innermost container split along that dimension.
+    /// Does nothing if the view is a float or there is no such container.
+    pub(crate) fn resize_view(
+        &mut self,
+        view: ViewId,
+        dimension: Dimension,
+        size: impl Fn(u16) -> u16,
+    ) {
+        if self.float(view).is_some() || self.is_empty() {
+            return;
+        }
+
+        let area = self.area();
+        let layer = self.top_mut();
+        let area = (layer.compute_area)(area);
+        let _ = layer.root.resize(area, view, dimension.into(), &size);
+    }
+
+    /// Rotate the views in the split containing the view forwards (or backwards), `<C-w>r`.
+    /// Fails if any of the views in the split are split themselves.
+    pub(crate) fn rotate(&mut self, view: ViewId, forward: bool) -> Result<(), ()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let Some((container, _)) = self.top_mut().root.parent(view) else { return Ok(()) };
+        if container.children.iter().any(|child| matches!(child, Node::Container(_))) {
+            return Err(());
+        }
+
+        if forward {
+            container.children.rotate_right(1);
+        } else {
+            container.children.rotate_left(1);
+        }
+        Ok(())
+    }
+
+    /// Exchange the view with the `n`th view of its split, or the next one (the previous one if
+    /// it's last) if `None`, `<C-w>x`. Returns the view it was exchanged with, if any.
+    /// Fails if that view is split.
+    pub(crate) fn exchange(
+        &mut self,
+        view: ViewId,
+        n: Option<usize>,
+    ) -> Result<Option<ViewId>, ()> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        let Some((container, i)) = self.top_mut().root.parent(view) else { return Ok(None) };
+        let len = container.children.len();
+        let j = match n {
+            Some(n) if n < len => n,
+            Some(_) => return Ok(None),
+            None if i + 1 < len => i + 1,
+            None => i.checked_sub(1).ok_or(())?,
+        };
+
+        let Node::View(other) = container.children[j] else { return Err(()) };
+        container.children.swap(i, j);
+        Ok(Some(other))
+    }
+
+    /// Move the view to the far edge of its layer in the direction where it spans the full width
+    /// or height, `<C-w>H`, `<C-w>J`, `<C-w>K`, and `<C-w>L`.
+    pub(crate) fn move_to_edge(&mut self, view: ViewId, direction: Direction) {
+        if self.is_empty() || self.float(view).is_some() {
+            return;
+        }
+
+        let layer = self.top_mut();
+        if !layer.views().any(|v| v == view) || layer.views().eq([view]) {
+            return;
+        }
+
+        let _ = layer.root.close_view(view);
+        let root = std::mem::replace(&mut layer.root, Node::View(view));
+        layer.root = match root {
+            // Extend the root if it's already split in the direction rather than nesting it.
+            Node::Container(mut container) if container.direction == direction.into() => {
+                let node = Node::View(view);
+                match direction {
+                    Direction::Left | Direction::Up => container.children.insert(0, node),
+                    Direction::Right | Direction::Down => container.children.push(node),
+                }
+                container.constraints = vec![Constraint::Fill(1); container.children.len()];
+                Node::Container(container)
+            }
+            root => {
+                let (view, root) =
+                    ((Constraint::Fill(1), Node::View(view)), (Constraint::Fill(1), root));
+                let children = match direction {
+                    Direction::Left | Direction::Up => [view, root],
+                    Direction::Right | Direction::Down => [root, view],
+                };
+                Node::Container(Container::new(direction, children))
+            }
+        };
+        layer.active = view;
+    }
+
+    /// Make all views in each split equally sized, `<C-w>=`.
+    pub(crate) fn equalize(&mut self) {
+        for layer in &mut self.layers {
+            layer.root.equalize();
+        }
+    }
+
     pub(crate) fn view_only(&mut self, view: ViewId) {
         self.layers = vec![Layer::new(view)];
+        self.floats.clear();
+        self.focused_float = None;
     }
 }
 
@@ -143,6 +538,14 @@ impl Layer {
         self.root.view_area((self.compute_area)(area), view)
     }
 
+    pub fn view_at(&self, area: Rect, x: u16, y: u16) -> Option<ViewId> {
+        self.views().find(|&view| {
+            self.view_area(area, view).is_some_and(|area| {
+                (area.left()..area.right()).contains(&x) && (area.top()..area.bottom()).contains(&y)
+            })
+        })
+    }
+
     pub fn split(
         &mut self,
         view: ViewId,
@@ -251,6 +654,40 @@ impl Node {
         }
     }
 
+    fn resize(
+        &mut self,
+        area: Rect,
+        view: ViewId,
+        direction: tui::Direction,
+        size: &dyn Fn(u16) -> u16,
+    ) -> TraverseResult<()> {
+        match self {
+            Node::View(v) if *v == view => TraverseResult::Propagate,
+            Node::Container(c) => c.resize(area, view, direction, size),
+            _ => TraverseResult::Continue,
+        }
+    }
+
+    /// The container the view is a direct child of and its index within it.
+    fn parent(&mut self, view: ViewId) -> Option<(&mut Container, usize)> {
+        let Node::Container(container) = self else { return None };
+        match container
+            .children
+            .iter()
+            .position(|child| matches!(child, Node::View(v) if *v == view))
+        {
+            Some(idx) => Some((container, idx)),
+            None => container.children.iter_mut().find_map(|child| child.parent(view)),
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let Node::Container(c) = self {
+            c.constraints.fill(Constraint::Fill(1));
+            c.children.iter_mut().for_each(Node::equalize);
+        }
+    }
+
     fn first_view(&self) -> ViewId {
         match self {
             Node::View(id) => *id,
@@ -393,6 +830,60 @@ impl Container {
         TraverseResult::Continue
     }
 
+    fn resize(
+        &mut self,
+        area: Rect,
+        view: ViewId,
+        direction: tui::Direction,
+        size: &dyn Fn(u16) -> u16,
+    ) -> TraverseResult<()> {
+        let areas = self.layout().split(area);
+        for i in 0..self.children.len() {
+            match self.children[i].resize(areas[i], view, direction, size) {
+                TraverseResult::Continue => continue,
+                TraverseResult::Done(()) => return TraverseResult::Done(()),
+                TraverseResult::Propagate => {
+                    if self.direction != direction || self.children.len() == 1 {
+                        // Try again in the parent container
+                        return TraverseResult::Propagate;
+                    }
+
+                    let extent = |area: &Rect| match direction {
+                        tui::Direction::Horizontal => area.width,
+                        tui::Direction::Vertical => area.height,
+                    };
+
+                    let mut sizes = areas.iter().map(extent).collect::<Vec<_>>();
+                    let total = sizes.iter().sum::<u16>();
+                    // Every other child keeps at least one cell.
+                    let max = total.saturating_sub(sizes.len() as u16 - 1).max(1);
+                    let new = size(sizes[i]).clamp(1, max);
+                    let mut delta = new as i32 - sizes[i] as i32;
+                    sizes[i] = new;
+
+                    // Take from or give to the following children first, then the preceding ones.
+                    for j in (i + 1..sizes.len()).chain((0..i).rev()) {
+                        if delta == 0 {
+                            break;
+                        }
+
+                        let available =
+                            if delta > 0 { sizes[j].saturating_sub(1) as i32 } else { i32::MAX };
+                        let change = delta.min(available);
+                        sizes[j] = (sizes[j] as i32 - change) as u16;
+                        delta -= change;
+                    }
+
+                    // Proportional rather than fixed sizes so the split still scales with the editor.
+                    self.constraints = sizes.into_iter().map(Constraint::Fill).collect();
+                    return TraverseResult::Done(());
+                }
+            }
+        }
+
+        TraverseResult::Continue
+    }
+
     fn first_view(&self) -> ViewId {
         self.children.first().expect("container was empty").first_view()
     }
@@ -1,12 +1,16 @@
 pub use zi_input as input;
 
 pub mod buffer;
+mod clipboard;
 pub mod command;
 mod completion;
 mod config;
 pub mod dirs;
 mod editor;
+mod editorconfig;
 pub mod event;
+mod expr;
+mod fuzzy;
 mod jump;
 mod keymap;
 mod language;
@@ -17,41 +21,54 @@ mod namespace;
 mod operator;
 pub mod plugin;
 mod private;
+mod snippet;
+mod spell;
+mod startup;
 mod syntax;
+mod terminal;
 mod undo;
 pub mod view;
 
+pub use encoding_rs;
 pub use location::Location;
 pub use tokio::sync::Notify;
 pub use tree_sitter;
-pub use tui::{Constraint, LineNumberStyle};
+pub use tui::{Constraint, LineNumberStyle, ListChars, Rect};
 pub use url::Url;
 pub use zi_core::{
-    BufferId, Col, CompletionItem, Direction, Line, Mode, NamespaceId, Offset, Operator, Point,
-    PointRange, Size, ViewGroupId, ViewId,
+    BufferId, Col, CompletionItem, CompletionItemKind, Direction, Line, Mode, NamespaceId, Offset,
+    Operator, Point, PointRange, Size, ViewGroupId, ViewId,
 };
 pub use zi_text::{
-    AnyText, AnyTextMut, AnyTextSlice, Delta, Deltas, Rope, RopeBuilder, Text, TextBase, TextMut,
-    TextSlice, deltas,
+    AnyText, AnyTextMut, AnyTextSlice, ContentHash, Delta, Deltas, LineDiff, LineEnding, Rope,
+    RopeBuilder, Text, TextBase, TextMut, TextSlice, deltas,
 };
 pub use zi_textobject::motion;
 
-pub use self::buffer::{BufferFlags, Mark, MarkBuilder, MarkId, PickerBuffer, TextBuffer};
+pub use self::buffer::{
+    BufferFlags, Conceal, FoldMethod, Mark, MarkBuilder, MarkId, PickerBuffer, Sign, TextBuffer,
+    VirtualText,
+};
+pub use self::clipboard::ClipboardSelection;
 pub use self::command::{Command, Commands};
-pub use self::completion::CompletionProvider;
+pub use self::completion::{BufferWordProvider, CompletionProvider, PathProvider, SnippetProvider};
 pub use self::config::Setting;
 pub use self::editor::visual::Selection;
 pub use self::editor::{
-    Active, Backend, Client, DummyBackend, EditError, Editor, Match, OpenFlags, Register,
-    RegisterKind, Resource, SaveFlags, Tasks,
+    Active, Backend, Client, DummyBackend, EditError, Editor, GuiCursor, Hunk, HunkKind, Match,
+    Message, MessageLevel, OpenFlags, PasteFlags, PickerItem, QuickfixEntry, Register,
+    RegisterKind, RenderStats, Resource, SaveFlags, Segment, StatusLine, Tasks,
 };
 pub(crate) use self::jump::JumpList;
-pub use self::language::{FileType, LanguageConfig, LanguageServiceId};
+pub use self::language::{CommentTokens, FileType, LanguageConfig, LanguageServiceId};
 pub use self::language_service::{LanguageClient, LanguageService, LanguageServiceConfig, lstypes};
+pub use self::layout::{BufferKind, Dimension, FloatAnchor, FloatConfig, Placement, PlacementRules};
 pub use self::namespace::Namespace;
+pub use self::startup::{StartupPhase, StartupProfile};
 #[doc(hidden)]
 pub use self::syntax::HighlightName;
 pub use self::syntax::{Syntax, Theme};
+pub use self::terminal::{Capabilities, TerminalRequest};
 pub use self::view::{VerticalAlignment, View};
 
 pub type Error = anyhow::Error;
@@ -0,0 +1,358 @@
+//! User-defined snippets and the snippet syntax of the language server protocol.
+//!
+//! Snippets are loaded from `{filetype}.json` files in the format of VSCode or `{filetype}.toml`
+//! files with the same structure, where snippets in `all.{json,toml}` apply to every filetype.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context as _, bail};
+
+use crate::Result;
+
+/// The filetype of snippets that apply to every filetype.
+const ALL: &str = "all";
+
+/// A snippet definition, e.g. from `rust.toml`:
+///
+/// ```toml
+/// [function]
+/// prefix = "fn"
+/// body = ["fn ${1:name}($2) {", "\t$0", "}"]
+/// description = "A function"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SnippetDefinition {
+    pub name: String,
+    pub prefixes: Vec<String>,
+    pub body: String,
+    pub description: Option<String>,
+}
+
+/// The snippets of each filetype.
+#[derive(Debug, Default)]
+pub(crate) struct Snippets {
+    by_filetype: HashMap<String, Vec<SnippetDefinition>>,
+}
+
+impl Snippets {
+    /// Load the snippets of every file in `dir`, a missing directory has no snippets.
+    pub fn load(&mut self, dir: &Path) -> Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let Some(filetype) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+            let content = || std::fs::read_to_string(&path);
+            let snippets = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => parse_json(&content()?),
+                Some("toml") => parse_toml(&content()?),
+                _ => continue,
+            }
+            .with_context(|| format!("failed to load snippets from `{}`", path.display()))?;
+            self.by_filetype.entry(filetype.to_string()).or_default().extend(snippets);
+        }
+
+        Ok(())
+    }
+
+    /// The snippets of the filetype, including those of every filetype.
+    pub fn of(&self, filetype: &str) -> impl Iterator<Item = &SnippetDefinition> {
+        let snippets = self.by_filetype.get(filetype);
+        snippets.into_iter().chain(self.by_filetype.get(ALL)).flatten()
+    }
+}
+
+fn parse_json(s: &str) -> Result<Vec<SnippetDefinition>> {
+    let serde_json::Value::Object(snippets) = serde_json::from_str(s)? else {
+        bail!("expected an object of snippets")
+    };
+
+    let string = |value: &serde_json::Value| value.as_str().map(str::to_string);
+    let strings = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => Some(vec![s.clone()]),
+        serde_json::Value::Array(values) => values.iter().map(string).collect(),
+        _ => None,
+    };
+
+    snippets
+        .iter()
+        .map(|(name, snippet)| {
+            definition(
+                name,
+                snippet.get("prefix").and_then(strings),
+                snippet.get("body").and_then(strings),
+                snippet.get("description").and_then(string),
+            )
+        })
+        .collect()
+}
+
+fn parse_toml(s: &str) -> Result<Vec<SnippetDefinition>> {
+    let string = |value: &toml::Value| value.as_str().map(str::to_string);
+    let strings = |value: &toml::Value| match value {
+        toml::Value::String(s) => Some(vec![s.clone()]),
+        toml::Value::Array(values) => values.iter().map(string).collect(),
+        _ => None,
+    };
+
+    s.parse::<toml::Table>()?
+        .iter()
+        .map(|(name, snippet)| {
+            definition(
+                name,
+                snippet.get("prefix").and_then(strings),
+                snippet.get("body").and_then(strings),
+                snippet.get("description").and_then(string),
+            )
+        })
+        .collect()
+}
+
+fn definition(
+    name: &str,
+    prefixes: Option<Vec<String>>,
+    body: Option<Vec<String>>,
+    description: Option<String>,
+) -> Result<SnippetDefinition> {
+    let Some(prefixes) = prefixes else { bail!("snippet `{name}` has no prefix") };
+    let Some(body) = body else { bail!("snippet `{name}` has no body") };
+    Ok(SnippetDefinition { name: name.to_string(), prefixes, body: body.join("\n"), description })
+}
+
+/// A parsed snippet, the text to insert and the byte ranges of its tabstops within it.
+/// The syntax is that of the language server protocol, e.g. `fn ${1:name}($2) {\n\t$0\n}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Snippet {
+    text: String,
+    /// The tabstops in the order they are visited, with the final tabstop `$0` last.
+    tabstops: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// Parse the snippet, looking up the value of variables such as `$TM_FILENAME` with
+    /// `variable`. Anything that isn't valid snippet syntax is inserted as is.
+    pub fn parse(s: &str, variable: impl Fn(&str) -> Option<String>) -> Self {
+        let mut parser = Parser { s, pos: 0, text: String::new(), tabstops: vec![], variable };
+        parser.parse(false);
+
+        let Parser { text, mut tabstops, .. } = parser;
+        // Visit the tabstops in order, with `$0` last. Repeated tabstops are only visited once.
+        tabstops.sort_by_key(|&(idx, _)| (idx == 0, idx));
+        tabstops.dedup_by_key(|(idx, _)| *idx);
+        if tabstops.last().is_none_or(|&(idx, _)| idx != 0) {
+            tabstops.push((0, text.len()..text.len()));
+        }
+
+        Self { text, tabstops: tabstops.into_iter().map(|(_, range)| range).collect() }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn tabstops(&self) -> &[Range<usize>] {
+        &self.tabstops
+    }
+
+    /// Indent the lines after the first with `indent` and replace tabs with `tab`.
+    pub fn indent(&self, indent: &str, tab: &str) -> Self {
+        let mut text = String::with_capacity(self.text.len());
+        // The offset in the new text of each byte of the old text.
+        let mut offsets = Vec::with_capacity(self.text.len() + 1);
+        for (i, c) in self.text.char_indices() {
+            offsets.resize(i + 1, text.len());
+            match c {
+                '\t' => text.push_str(tab),
+                '\n' => {
+                    text.push('\n');
+                    text.push_str(indent);
+                }
+                c => text.push(c),
+            }
+        }
+        offsets.resize(self.text.len() + 1, text.len());
+
+        let tabstops =
+            self.tabstops.iter().map(|range| offsets[range.start]..offsets[range.end]).collect();
+        Self { text, tabstops }
+    }
+}
+
+struct Parser<'a, F> {
+    s: &'a str,
+    pos: usize,
+    text: String,
+    tabstops: Vec<(u32, Range<usize>)>,
+    variable: F,
+}
+
+impl<'a, F: Fn(&str) -> Option<String>> Parser<'a, F> {
+    fn rest(&self) -> &str {
+        &self.s[self.pos..]
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let ate = self.rest().starts_with(c);
+        if ate {
+            self.pos += c.len_utf8();
+        }
+        ate
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        let len = self.rest().find(|c| !f(c)).unwrap_or(self.rest().len());
+        self.pos += len;
+        &self.s[start..self.pos]
+    }
+
+    /// Parse until the end, or an unescaped `}` if `nested`.
+    fn parse(&mut self, nested: bool) {
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => match self.rest().chars().next() {
+                    Some(c @ ('$' | '}' | '\\')) => {
+                        self.pos += 1;
+                        self.text.push(c);
+                    }
+                    _ => self.text.push('\\'),
+                },
+                '}' if nested => return,
+                '$' => self.parse_dollar(),
+                c => self.text.push(c),
+            }
+        }
+    }
+
+    /// Parse a tabstop, placeholder, choice or variable after a `$`.
+    fn parse_dollar(&mut self) {
+        let start = self.pos;
+        let braced = self.eat('{');
+        if let Ok(idx) = self.eat_while(|c| c.is_ascii_digit()).parse::<u32>() {
+            let at = self.text.len();
+            if !braced {
+                self.tabstops.push((idx, at..at));
+                return;
+            }
+
+            if self.eat('}') {
+                self.tabstops.push((idx, at..at));
+                return;
+            }
+
+            if self.eat(':') {
+                self.parse(true);
+                self.tabstops.push((idx, at..self.text.len()));
+                return;
+            }
+
+            if self.eat('|') {
+                if let Some(end) = self.rest().find("|}") {
+                    // Only the first choice is inserted.
+                    let choices = &self.s[self.pos..self.pos + end];
+                    self.text.push_str(choices.split(',').next().unwrap_or_default());
+                    self.pos += end + 2;
+                    self.tabstops.push((idx, at..self.text.len()));
+                    return;
+                }
+            }
+        } else {
+            let name = self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()) {
+                let value = (self.variable)(name);
+                if !braced || self.eat('}') {
+                    self.text.push_str(&value.unwrap_or_default());
+                    return;
+                }
+
+                if self.eat(':') {
+                    // The default is only used if the variable has no value.
+                    let at = self.text.len();
+                    let n = self.tabstops.len();
+                    self.parse(true);
+                    if let Some(value) = value {
+                        self.text.truncate(at);
+                        self.tabstops.truncate(n);
+                        self.text.push_str(&value);
+                    }
+                    return;
+                }
+            }
+        }
+
+        // Not valid snippet syntax, insert the `$` literally.
+        self.pos = start;
+        self.text.push('$');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_snippet() {
+        let check = |s: &str, text: &str, tabstops: &[&str]| {
+            let snippet = Snippet::parse(s, |name| (name == "TM_FILENAME").then(|| "a.rs".into()));
+            assert_eq!(snippet.text, text);
+            let actual = snippet.tabstops.iter().map(|range| &snippet.text[range.clone()]);
+            assert_eq!(actual.collect::<Vec<_>>(), tabstops);
+        };
+
+        check("foo", "foo", &[""]);
+        check("$2 $1 $0 x", "   x", &["", "", ""]);
+        check("fn ${1:name}($2) {}", "fn name() {}", &["name", "", ""]);
+        check("${1:a ${2:b}}", "a b", &["a b", "b", ""]);
+        check("${1|x,y|}", "x", &["x", ""]);
+        check("$TM_FILENAME ${TM_FILENAME}", "a.rs a.rs", &[""]);
+        check("${FOO:${1:x}} ${TM_FILENAME:y}", "x a.rs", &["x", ""]);
+        check(r"\$1 \} $ ${x", "$1 } $ ${x", &[""]);
+    }
+
+    #[test]
+    fn indent_snippet() {
+        let snippet = Snippet::parse("{\n\t${1:x}\n}", |_| None).indent("  ", "    ");
+        assert_eq!(snippet.text(), "{\n      x\n  }");
+        assert_eq!(&snippet.text()[snippet.tabstops()[0].clone()], "x");
+    }
+
+    #[test]
+    fn parse_definitions() {
+        let expected = vec![SnippetDefinition {
+            name: "function".into(),
+            prefixes: vec!["fn".into()],
+            body: "fn $1() {\n\t$0\n}".into(),
+            description: Some("A function".into()),
+        }];
+
+        let json = r#"{
+            "function": {
+                "prefix": "fn",
+                "body": ["fn $1() {", "\t$0", "}"],
+                "description": "A function"
+            }
+        }"#;
+        assert_eq!(parse_json(json).unwrap(), expected);
+
+        let toml = r#"
+[function]
+prefix = ["fn"]
+body = "fn $1() {\n\t$0\n}"
+description = "A function"
+"#;
+        assert_eq!(parse_toml(toml).unwrap(), expected);
+        assert!(parse_toml("[function]\nprefix = \"fn\"").is_err());
+    }
+}
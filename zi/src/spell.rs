@@ -0,0 +1,506 @@
+//! Spell checking with Hunspell dictionaries.
+//!
+//! A dictionary is a `{lang}.dic` file of words with flags, and a `{lang}.aff` file of the prefix
+//! and suffix rules the flags refer to. Every form of every word is expanded when the dictionary
+//! is loaded, compounding and the other less common features of Hunspell are not supported.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context as _, bail};
+
+use crate::Result;
+
+/// The most suggestions to offer for a misspelled word.
+const MAX_SUGGESTIONS: usize = 20;
+
+/// The most candidates two edits away from a misspelled word to check. There are quadratically
+/// many in the length of the word so only the first are checked.
+const MAX_CANDIDATES: usize = 100_000;
+
+/// A dictionary of correctly spelled words.
+#[derive(Debug, Default)]
+pub(crate) struct Dictionary {
+    words: HashSet<String>,
+    /// The characters to try when suggesting corrections, the most common first.
+    try_chars: Vec<char>,
+}
+
+impl Dictionary {
+    /// Load the dictionary of the language from `{lang}.dic` and `{lang}.aff` in `dir`, `None` if
+    /// there is no such dictionary.
+    pub fn load(dir: &Path, lang: &str) -> Result<Option<Self>> {
+        let dic = dir.join(format!("{lang}.dic"));
+        let aff = dir.join(format!("{lang}.aff"));
+        if !dic.is_file() {
+            return Ok(None);
+        }
+
+        let context = || format!("failed to load dictionary `{}`", dic.display());
+        let aff = match std::fs::read(&aff) {
+            Ok(aff) => aff,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err).with_context(context),
+        };
+        let dic = std::fs::read(&dic).with_context(context)?;
+
+        // The encoding of both files is declared in the affix file, e.g. `SET ISO8859-1`.
+        let encoding = String::from_utf8_lossy(&aff)
+            .lines()
+            .find_map(|line| line.strip_prefix("SET "))
+            .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+        let (aff, ..) = encoding.decode(&aff);
+        let (dic, ..) = encoding.decode(&dic);
+        Self::parse(&dic, &aff).with_context(context).map(Some)
+    }
+
+    /// Parse the contents of a `.dic` and `.aff` file.
+    pub fn parse(dic: &str, aff: &str) -> Result<Self> {
+        let aff = Affixes::parse(aff)?;
+        let mut dictionary = Self::default();
+
+        // The first line is the (approximate) number of words.
+        let mut lines = dic.lines().peekable();
+        if lines.peek().is_some_and(|line| line.trim().parse::<usize>().is_ok()) {
+            lines.next();
+        }
+
+        for line in lines {
+            // Anything after the word and its flags is morphological information.
+            let Some(entry) = line.split_whitespace().next() else { continue };
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            let flags = aff.flags(flags);
+
+            dictionary.insert(word);
+            let classes = flags.iter().filter_map(|flag| aff.classes.get(flag));
+            let (prefixes, suffixes) = classes.partition::<Vec<_>, _>(|class| class.prefix);
+
+            let mut cross = vec![];
+            for class in suffixes {
+                for form in class.rules.iter().filter_map(|rule| rule.apply_suffix(word)) {
+                    if class.cross {
+                        cross.push(form.clone());
+                    }
+                    dictionary.words.insert(form);
+                }
+            }
+
+            for class in prefixes {
+                for rule in &class.rules {
+                    let forms = std::iter::once(word)
+                        .chain(cross.iter().map(String::as_str).filter(|_| class.cross));
+                    for form in forms.filter_map(|form| rule.apply_prefix(form)) {
+                        dictionary.words.insert(form);
+                    }
+                }
+            }
+        }
+
+        dictionary.try_chars = match aff.try_chars {
+            Some(chars) => chars.chars().collect(),
+            // Without a `TRY` line try every character of the dictionary.
+            None => {
+                let chars = dictionary.words.iter().flat_map(|word| word.chars());
+                let mut chars = chars.collect::<HashSet<_>>().into_iter().collect::<Vec<_>>();
+                chars.sort_unstable();
+                chars
+            }
+        };
+
+        Ok(dictionary)
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.words.insert(word.to_string());
+    }
+
+    /// Whether the word is spelled correctly. A capitalized or uppercase word is also correct if
+    /// its lowercase form is.
+    pub fn check(&self, word: &str) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+
+        match Case::of(word) {
+            Case::Lower | Case::Mixed => false,
+            Case::Capitalized => self.words.contains(&word.to_lowercase()),
+            Case::Upper => {
+                let lower = word.to_lowercase();
+                self.words.contains(&lower) || self.words.contains(&capitalize(&lower))
+            }
+        }
+    }
+
+    /// Suggest corrections of a misspelled word, the closest first.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let case = Case::of(word);
+        let word = match case {
+            Case::Capitalized | Case::Upper => word.to_lowercase(),
+            Case::Lower | Case::Mixed => word.to_string(),
+        };
+
+        let edits = self.edits(&word);
+        let splits = split_words(&word).map(|(first, second)| format!("{first} {second}"));
+        let mut suggestions = self.known(case, splits.chain(edits.iter().cloned()));
+        // Only look further if there is nothing one edit away.
+        if suggestions.is_empty() {
+            let candidates = edits.iter().flat_map(|edit| self.edits(edit)).take(MAX_CANDIDATES);
+            suggestions = self.known(case, candidates);
+        }
+
+        suggestions
+    }
+
+    /// The first `MAX_SUGGESTIONS` correctly spelled candidates without duplicates, in the case of
+    /// the misspelled word.
+    fn known(&self, case: Case, candidates: impl Iterator<Item = String>) -> Vec<String> {
+        let mut seen = HashSet::new();
+        candidates
+            .filter_map(|candidate| {
+                // Proper nouns are suggested capitalized, e.g. `pariss` is corrected to `Paris`.
+                if candidate.split(' ').all(|word| self.check(word)) {
+                    Some(candidate)
+                } else {
+                    Some(capitalize(&candidate)).filter(|candidate| self.check(candidate))
+                }
+            })
+            .map(|candidate| match case {
+                Case::Capitalized => capitalize(&candidate),
+                Case::Upper => candidate.to_uppercase(),
+                Case::Lower | Case::Mixed => candidate,
+            })
+            .filter(|candidate| seen.insert(candidate.clone()))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+
+    /// Every string one edit away from `word`: a swapped, replaced, removed or inserted character.
+    fn edits(&self, word: &str) -> Vec<String> {
+        let chars = word.chars().collect::<Vec<_>>();
+        let string = |chars: &[char]| chars.iter().collect::<String>();
+        let mut edits = vec![];
+
+        for i in 0..chars.len().saturating_sub(1) {
+            let mut swapped = chars.clone();
+            swapped.swap(i, i + 1);
+            edits.push(string(&swapped));
+        }
+
+        for i in 0..chars.len() {
+            for &c in self.try_chars.iter().filter(|&&c| c != chars[i]) {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                edits.push(string(&replaced));
+            }
+        }
+
+        for i in 0..chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            edits.push(string(&removed));
+        }
+
+        for i in 0..=chars.len() {
+            for &c in &self.try_chars {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                edits.push(string(&inserted));
+            }
+        }
+
+        edits
+    }
+}
+
+/// The ways to split `word` into two non-empty words.
+fn split_words(word: &str) -> impl Iterator<Item = (&str, &str)> {
+    word.char_indices().skip(1).map(|(i, _)| word.split_at(i))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Lower,
+    Capitalized,
+    Upper,
+    Mixed,
+}
+
+impl Case {
+    fn of(word: &str) -> Self {
+        let mut chars = word.chars();
+        let first = chars.next().is_some_and(char::is_uppercase);
+        let (mut upper, mut lower) = (false, false);
+        for c in chars {
+            upper |= c.is_uppercase();
+            lower |= c.is_lowercase();
+        }
+
+        match (first, upper, lower) {
+            (false, false, _) => Case::Lower,
+            (true, false, true) => Case::Capitalized,
+            (true, _, false) => Case::Upper,
+            _ => Case::Mixed,
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// The words of `text` to spell check with their byte offsets. Words with digits, underscores or
+/// uppercase letters after the first are skipped as they are likely identifiers or acronyms.
+pub(crate) fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let mut offset = 0;
+    text.split(move |c: char| !is_word_char(c))
+        .map(move |s| {
+            let start = offset;
+            offset += s.len() + 1;
+            (start, s)
+        })
+        .filter_map(|(start, s)| {
+            let word = s.trim_start_matches('\'');
+            let start = start + s.len() - word.len();
+            let word = word.trim_end_matches('\'');
+            let identifier = word.chars().any(|c| c.is_numeric() || c == '_')
+                || word.chars().skip(1).any(char::is_uppercase);
+            (!word.is_empty() && !identifier).then_some((start, word))
+        })
+}
+
+/// The flags, prefix and suffix rules of an affix file.
+#[derive(Debug, Default)]
+struct Affixes {
+    flag_format: FlagFormat,
+    /// The flag sets that flags in the dictionary can refer to by number, from `AF` lines.
+    aliases: Vec<String>,
+    classes: HashMap<String, AffixClass>,
+    try_chars: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum FlagFormat {
+    /// Each character is a flag.
+    #[default]
+    Char,
+    /// Each pair of characters is a flag.
+    Long,
+    /// Flags are comma-separated numbers.
+    Num,
+}
+
+/// The prefix or suffix rules of a flag.
+#[derive(Debug)]
+struct AffixClass {
+    prefix: bool,
+    /// Whether the prefixes and suffixes can be combined.
+    cross: bool,
+    rules: Vec<AffixRule>,
+}
+
+#[derive(Debug)]
+struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Vec<Condition>,
+}
+
+/// A character of the condition of an affix rule.
+#[derive(Debug)]
+enum Condition {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl Affixes {
+    fn parse(aff: &str) -> Result<Self> {
+        let mut affixes = Self::default();
+        for (i, line) in aff.lines().enumerate() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match fields[..] {
+                ["FLAG", "long"] => affixes.flag_format = FlagFormat::Long,
+                ["FLAG", "num"] => affixes.flag_format = FlagFormat::Num,
+                ["TRY", chars] => affixes.try_chars = Some(chars.to_string()),
+                ["AF", flags, ..] if flags.parse::<usize>().is_err() => {
+                    affixes.aliases.push(flags.to_string())
+                }
+                [kind @ ("PFX" | "SFX"), flag, ref rest @ ..] => affixes
+                    .parse_affix(kind == "PFX", flag, rest)
+                    .with_context(|| format!("invalid affix on line {}: `{line}`", i + 1))?,
+                _ => {}
+            }
+        }
+        Ok(affixes)
+    }
+
+    fn parse_affix(&mut self, prefix: bool, flag: &str, fields: &[&str]) -> Result<()> {
+        // The first line of a flag is a header of whether it combines and the number of rules.
+        let Some(class) = self.classes.get_mut(flag) else {
+            let [cross, _count, ..] = fields else { bail!("expected a cross product and count") };
+            let class = AffixClass { prefix, cross: *cross == "Y", rules: vec![] };
+            self.classes.insert(flag.to_string(), class);
+            return Ok(());
+        };
+
+        let (strip, add, condition) = match fields {
+            [strip, add] => (strip, add, "."),
+            [strip, add, condition, ..] => (strip, add, *condition),
+            _ => bail!("expected the text to strip and add"),
+        };
+        let zero = |s: &str| if s == "0" { String::new() } else { s.to_string() };
+        // Flags of the affix itself (`add/flags`) are not supported.
+        let add = add.split_once('/').map_or(*add, |(add, _)| add);
+        class.rules.push(AffixRule {
+            strip: zero(strip),
+            add: zero(add),
+            condition: parse_condition(condition)?,
+        });
+        Ok(())
+    }
+
+    /// The flags of a word of the dictionary.
+    fn flags(&self, flags: &str) -> Vec<String> {
+        let flags = match flags.parse::<usize>() {
+            Ok(n) if !self.aliases.is_empty() => {
+                self.aliases.get(n.wrapping_sub(1)).map_or("", String::as_str)
+            }
+            _ => flags,
+        };
+
+        match self.flag_format {
+            FlagFormat::Char => flags.chars().map(String::from).collect(),
+            FlagFormat::Long => {
+                let chars = flags.chars().collect::<Vec<_>>();
+                chars.chunks(2).map(|chunk| chunk.iter().collect()).collect()
+            }
+            FlagFormat::Num => flags.split(',').map(str::to_string).collect(),
+        }
+    }
+}
+
+fn parse_condition(s: &str) -> Result<Vec<Condition>> {
+    if s == "." {
+        return Ok(vec![]);
+    }
+
+    let mut condition = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        condition.push(match c {
+            '.' => Condition::Any,
+            '[' => {
+                let mut set = chars.by_ref().take_while(|&c| c != ']').collect::<Vec<_>>();
+                let negated = set.first() == Some(&'^');
+                if negated {
+                    set.remove(0);
+                }
+                Condition::Set { chars: set, negated }
+            }
+            ']' => bail!("unmatched `]` in condition `{s}`"),
+            c => Condition::Char(c),
+        });
+    }
+    Ok(condition)
+}
+
+impl Condition {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Condition::Any => true,
+            Condition::Char(expected) => c == *expected,
+            Condition::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+impl AffixRule {
+    fn apply_suffix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_suffix(self.strip.as_str())?;
+        let mut chars = word.chars().rev();
+        let matches =
+            self.condition.iter().rev().all(|cond| chars.next().is_some_and(|c| cond.matches(c)));
+        (matches && !stem.is_empty()).then(|| format!("{stem}{}", self.add))
+    }
+
+    fn apply_prefix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_prefix(self.strip.as_str())?;
+        let mut chars = word.chars();
+        let matches =
+            self.condition.iter().all(|cond| chars.next().is_some_and(|c| cond.matches(c)));
+        (matches && !stem.is_empty()).then(|| format!("{}{stem}", self.add))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "\
+SET UTF-8
+TRY esianrtolcdugmphbyfvkwz
+
+PFX A Y 1
+PFX A   0     re         .
+
+SFX D Y 3
+SFX D   0     d          e
+SFX D   y     ied        [^aeiou]y
+SFX D   0     ed         [^ey]
+
+SFX S N 1
+SFX S   0     s          .
+";
+
+    const DIC: &str = "\
+3
+try/D
+work/ADS
+Paris
+";
+
+    #[test]
+    fn check_words() {
+        let dictionary = Dictionary::parse(DIC, AFF).unwrap();
+        for word in ["try", "tried", "work", "worked", "works", "rework", "reworked", "Paris"] {
+            assert!(dictionary.check(word), "{word}");
+        }
+
+        for word in ["Worked", "WORKED", "PARIS"] {
+            assert!(dictionary.check(word), "{word}");
+        }
+
+        // `S` does not combine with prefixes.
+        for word in ["tryed", "retried", "reworks", "wrk", "wOrked"] {
+            assert!(!dictionary.check(word), "{word}");
+        }
+    }
+
+    #[test]
+    fn suggest_words() {
+        let dictionary = Dictionary::parse(DIC, AFF).unwrap();
+        assert_eq!(dictionary.suggest("wrok"), ["work"]);
+        assert_eq!(dictionary.suggest("Wroked"), ["Worked"]);
+        assert_eq!(dictionary.suggest("TRIDE"), ["TRIED"]);
+        assert_eq!(dictionary.suggest("trywork"), ["try work"]);
+        assert_eq!(dictionary.suggest("rwrk"), ["work", "rework"]);
+        assert!(dictionary.suggest("zzzzzzzz").is_empty());
+    }
+
+    #[test]
+    fn long_flags() {
+        let aff = "FLAG long\nSFX Aa Y 1\nSFX Aa 0 ing .\nSFX Bb Y 1\nSFX Bb 0 s .";
+        let dictionary = Dictionary::parse("walk/AaBb", aff).unwrap();
+        assert!(dictionary.check("walking"));
+        assert!(dictionary.check("walks"));
+    }
+
+    #[test]
+    fn tokenize() {
+        let words = words("don't 'quoted' foo_bar x86 HashMap Word, TODO ok").collect::<Vec<_>>();
+        assert_eq!(words, [(0, "don't"), (7, "quoted"), (35, "Word"), (46, "ok")]);
+    }
+}
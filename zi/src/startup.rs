@@ -0,0 +1,83 @@
+use std::fmt;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long each phase of startup took, see `zi --startuptime`.
+/// Phases are recorded as they end, so a phase that runs in the background takes the time since
+/// the phase recorded before it.
+#[derive(Debug, Clone)]
+pub struct StartupProfile {
+    start: Instant,
+    phases: Vec<StartupPhase>,
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupPhase {
+    pub name: String,
+    /// The time since startup that the phase ended.
+    pub end: Duration,
+    /// The time since the previous phase ended.
+    pub duration: Duration,
+}
+
+impl StartupProfile {
+    /// Start timing startup from now.
+    pub fn new() -> Self {
+        Self { start: Instant::now(), phases: vec![], output: None }
+    }
+
+    /// Write the phases recorded so far to `path` and append each phase as it ends, so the file is
+    /// complete once startup is rather than only if the editor exits cleanly.
+    pub fn write_to(&mut self, path: impl Into<PathBuf>) -> std::io::Result<()> {
+        let path = path.into();
+        std::fs::write(&path, self.to_string())?;
+        self.output = Some(path);
+        Ok(())
+    }
+
+    /// Record that the phase `name` has ended.
+    pub fn record(&mut self, name: impl Into<String>) {
+        let end = self.start.elapsed();
+        let prev = self.phases.last().map_or(Duration::ZERO, |phase| phase.end);
+        let phase = StartupPhase { name: name.into(), end, duration: end - prev };
+        if let Some(path) = &self.output {
+            let res = std::fs::OpenOptions::new()
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(phase.to_string().as_bytes()));
+            if let Err(err) = res {
+                tracing::error!(?path, %err, "failed to write startup profile");
+            }
+        }
+        self.phases.push(phase);
+    }
+
+    #[inline]
+    pub fn phases(&self) -> &[StartupPhase] {
+        &self.phases
+    }
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for StartupProfile {
+    /// The phases in order with the times in milliseconds, similar to vim's `--startuptime`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>10} {:>10}  phase", "clock", "self")?;
+        self.phases.iter().try_for_each(|phase| write!(f, "{phase}"))
+    }
+}
+
+impl fmt::Display for StartupPhase {
+    /// A line of the profile, see [`StartupProfile`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(f, "{:>10.3} {:>10.3}  {}", ms(self.end), ms(self.duration), self.name)
+    }
+}
@@ -1,28 +1,29 @@
 mod highlight;
 
-use tree_sitter::{Query, QueryCapture, QueryCursor, Tree};
+use std::ops::Range;
+
+use dyn_clone::DynClone;
+use tree_sitter::{Query, QueryCapture, QueryCursor};
 use zi_core::PointRange;
-use zi_text::{AnyText, AnyTextMut, Deltas};
+use zi_text::{AnyText, Deltas};
 
 pub use self::highlight::{HighlightId, HighlightMap, HighlightName, Theme};
-use crate::FileType;
+use crate::{Conceal, FileType};
 
-pub trait Syntax: Send + Sync + 'static {
+pub trait Syntax: DynClone + Send + Sync + 'static {
     fn file_type(&self) -> FileType;
 
     fn highlights_query(&self) -> &Query;
 
-    /// Set the text of the syntax tree.
-    /// Prefer using `edit` if there is a delta available.
-    fn set(&mut self, text: &dyn AnyText);
+    /// Parse the text, reusing the current syntax tree incrementally if there is one.
+    /// This can be slow for large texts, so it is called on a clone of the syntax off the main
+    /// thread unless the tree is needed immediately.
+    /// Returns the 0-indexed line ranges whose syntax changed from the previous tree.
+    fn parse(&mut self, text: &dyn AnyText) -> Vec<Range<usize>>;
 
-    /// Apply the given deltas to the text and update the syntax tree.
-    /// Returns the inverse deltas and the previous syntax tree.
-    fn edit(
-        &mut self,
-        text: &mut dyn AnyTextMut,
-        deltas: &Deltas<'_>,
-    ) -> (Deltas<'static>, Option<Tree>);
+    /// Update the syntax tree for the deltas about to be applied to `text` without reparsing.
+    /// The tree is stale until the next [`Syntax::parse`], but its nodes are moved to match.
+    fn edit(&mut self, text: &dyn AnyText, deltas: &Deltas<'_>);
 
     fn highlights<'a, 'tree: 'a>(
         &'tree self,
@@ -31,6 +32,63 @@ pub trait Syntax: Send + Sync + 'static {
         range: PointRange,
     ) -> Box<dyn Iterator<Item = QueryCapture<'tree>> + 'a>;
 
+    /// The 0-indexed line ranges that can be folded according to the language's fold query.
+    /// Returns `None` if the language has no fold query.
+    fn folds(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+    ) -> Option<Vec<Range<usize>>> {
+        let _ = (query_cursor, source);
+        None
+    }
+
+    /// The ranges within `range` concealed by the highlights query, i.e. by patterns with the
+    /// `(#set! conceal "<substitute>")` property.
+    fn conceals(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        range: PointRange,
+    ) -> Vec<(PointRange, Conceal)> {
+        let _ = (query_cursor, source, range);
+        vec![]
+    }
+
+    /// The syntaxes of the languages injected into this one by its `injections.scm` query, e.g.
+    /// code blocks in markdown. Each is parsed over only the injected ranges of the same text, so
+    /// its highlights are positioned relative to the whole text.
+    fn injections(&self) -> Vec<&dyn Syntax> {
+        vec![]
+    }
+
+    /// The delimiters within `range` captured by `@rainbow.bracket` in the language's rainbows
+    /// query, with how deeply each is nested in `@rainbow.scope` captures.
+    /// Returns an empty list if the language has no rainbows query.
+    fn rainbows(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        range: PointRange,
+    ) -> Vec<(PointRange, usize)> {
+        let _ = (query_cursor, source, range);
+        vec![]
+    }
+
+    /// The indentation level of `line` according to the language's indents query, i.e. the
+    /// number of distinct lines before it that start an `@indent` capture spanning it, less one if
+    /// the line starts with an `@outdent` capture.
+    /// Returns `None` if the language has no indents query or the text has not been parsed.
+    fn indent(
+        &self,
+        query_cursor: &mut QueryCursor,
+        source: &dyn AnyText,
+        line: usize,
+    ) -> Option<usize> {
+        let _ = (query_cursor, source, line);
+        None
+    }
+
     fn capture_names(&self) -> &[&str] {
         self.highlights_query().capture_names()
     }
@@ -1,10 +1,14 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
-use zi_core::style::{Style, style};
+use anyhow::{anyhow, bail};
+use zi_core::style::{Style, parse_modifier, style};
 
 #[derive(Clone)]
 pub struct Theme {
     highlights: Vec<(HighlightName, Style)>,
+    /// Highlights that take their style from another highlight.
+    links: Vec<(HighlightName, HighlightName)>,
     default_style: Style,
 }
 
@@ -25,6 +29,13 @@ impl From<&'static str> for HighlightName {
     }
 }
 
+impl HighlightName {
+    /// Intern a highlight name that is not known statically, e.g. from a theme file.
+    pub(crate) fn intern(name: &str) -> Self {
+        Self(ustr::ustr(name).as_str())
+    }
+}
+
 macro_rules! declare_highlights {
     ($($name:ident = $value:literal,)*) => {
         $( pub const $name: Self = Self($value); )*
@@ -35,16 +46,43 @@ impl HighlightName {
     declare_highlights! {
         BACKGROUND = "background",
         CURSORLINE = "cursorline",
+        CURSORCOLUMN = "cursorcolumn",
+        COLORCOLUMN = "colorcolumn",
         DIRECTORY = "directory",
         CURRENT_SEARCH = "search.current",
         SEARCH = "search",
+        PICKER_MATCH = "picker.match",
         VISUAL = "visual",
+        FOLDED = "folded",
+        CONCEAL = "conceal",
+        WHITESPACE = "whitespace",
+        SPELL_BAD = "spell.bad",
+        STATUSLINE = "statusline",
+        MESSAGE_ERROR = "message.error",
+        MESSAGE_WARNING = "message.warning",
+        MESSAGE_INFO = "message.info",
 
         ERROR = "error",
         WARNING = "warning",
         INFO = "info",
         HINT = "hint",
 
+        GIT_ADDED = "git.added",
+        GIT_CHANGED = "git.changed",
+        GIT_DELETED = "git.deleted",
+        GIT_BLAME = "git.blame",
+
+        DIFF_ADDED = "diff.added",
+        DIFF_CHANGED = "diff.changed",
+        DIFF_DELETED = "diff.deleted",
+
+        RAINBOW_1 = "rainbow.1",
+        RAINBOW_2 = "rainbow.2",
+        RAINBOW_3 = "rainbow.3",
+        RAINBOW_4 = "rainbow.4",
+        RAINBOW_5 = "rainbow.5",
+        RAINBOW_6 = "rainbow.6",
+
         NAMESPACE = "namespace",
         MODULE = "module",
         MACRO = "function.macro",
@@ -74,6 +112,18 @@ impl HighlightName {
     }
 }
 
+impl HighlightName {
+    /// The highlights of nested delimiters, cycled through by depth.
+    pub const RAINBOW: [Self; 6] = [
+        Self::RAINBOW_1,
+        Self::RAINBOW_2,
+        Self::RAINBOW_3,
+        Self::RAINBOW_4,
+        Self::RAINBOW_5,
+        Self::RAINBOW_6,
+    ];
+}
+
 impl Theme {
     pub fn new() -> Self {
         Self::default()
@@ -91,6 +141,125 @@ impl Theme {
             .map(|i| HighlightId(i as u32))
             .unwrap_or_default()
     }
+
+    /// Set the style of the highlight `name`, adding it if it does not exist.
+    /// This replaces any link of the highlight.
+    pub fn set_highlight(&mut self, name: &str, style: Style) {
+        self.links.retain(|(from, _)| from.as_ref() != name);
+        match self.highlights.iter_mut().find(|(key, _)| key.as_ref() == name) {
+            Some(hl) => hl.1 = style,
+            None => self.highlights.push((HighlightName::intern(name), style)),
+        }
+    }
+
+    /// Make the highlight `from` use the style of the highlight `to`, adding `from` if it does not exist.
+    /// Linking to a highlight that does not exist uses the default style.
+    pub fn link_highlight(&mut self, from: &str, to: &str) {
+        if !self.highlights.iter().any(|(key, _)| key.as_ref() == from) {
+            self.highlights.push((HighlightName::intern(from), Style::none()));
+        }
+
+        let to = HighlightName::intern(to);
+        match self.links.iter_mut().find(|(key, _)| key.as_ref() == from) {
+            Some(link) => link.1 = to,
+            None => self.links.push((HighlightName::intern(from), to)),
+        }
+    }
+
+    /// The highlight `from` is linked to, if any.
+    pub fn highlight_link(&self, from: impl AsRef<str>) -> Option<&HighlightName> {
+        let from = from.as_ref();
+        self.links.iter().find(|(key, _)| key.as_ref() == from).map(|(_, to)| to)
+    }
+
+    /// Follow the links of the highlight to the highlight that provides its style.
+    fn resolve(&self, mut id: HighlightId) -> HighlightId {
+        // Following at most as many links as there are highlights guards against cycles.
+        for _ in 0..self.highlights.len() {
+            let Some(to) = id.name(self).and_then(|name| self.highlight_link(name)) else { break };
+            id = self.highlight_id_by_name(to);
+        }
+        id
+    }
+
+    /// Reorder the highlights so the ids of the highlights of `prev` are still valid for this theme.
+    /// Highlights of `prev` that this theme does not style are kept with no style.
+    pub(crate) fn with_ids_of(self, prev: &Theme) -> Theme {
+        let mut highlights = prev
+            .highlights
+            .iter()
+            .map(|(name, _)| (name.clone(), Style::none()))
+            .collect::<Vec<_>>();
+        for (name, style) in self.highlights {
+            match highlights.iter_mut().find(|(key, _)| *key == name) {
+                Some(hl) => hl.1 = style,
+                None => highlights.push((name, style)),
+            }
+        }
+        Theme { highlights, links: self.links, default_style: self.default_style }
+    }
+}
+
+/// Parse a theme from TOML mapping highlight names to styles.
+/// A style is either a foreground color or a table with optional `fg`, `bg`, and `modifiers`.
+/// A table with only a `link` makes the highlight use the style of another highlight.
+/// The `default` key is the style of text without a highlight.
+///
+/// ```toml
+/// default = { fg = "#839496" }
+/// keyword = "#527bd2"
+/// "git.blame" = { fg = "#586e75", modifiers = ["italic"] }
+/// "function.macro" = { link = "function" }
+/// ```
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table = s.parse::<toml::Table>()?;
+        let mut theme = Theme { highlights: vec![], links: vec![], default_style: Style::none() };
+        for (name, value) in &table {
+            if let Some(link) = value.get("link") {
+                match link.as_str() {
+                    Some(to) if name != "default" => theme.link_highlight(name, to),
+                    _ => bail!("invalid link for `{name}`"),
+                }
+                continue;
+            }
+
+            let style =
+                parse_style(value).map_err(|err| anyhow!("invalid style for `{name}`: {err}"))?;
+            match name.as_str() {
+                "default" => theme.default_style = style,
+                _ => theme.highlights.push((HighlightName::intern(name), style)),
+            }
+        }
+        Ok(theme)
+    }
+}
+
+fn parse_style(value: &toml::Value) -> anyhow::Result<Style> {
+    let table = match value {
+        toml::Value::String(fg) => return Ok(Style::none().with_fg(fg.parse()?)),
+        toml::Value::Table(table) => table,
+        _ => bail!("expected a color or a table"),
+    };
+
+    let mut style = Style::none();
+    for (key, value) in table {
+        style = match (key.as_str(), value) {
+            ("fg", toml::Value::String(fg)) => style.with_fg(fg.parse()?),
+            ("bg", toml::Value::String(bg)) => style.with_bg(bg.parse()?),
+            ("modifiers", toml::Value::Array(modifiers)) => {
+                let modifiers = modifiers.iter().map(|modifier| match modifier.as_str() {
+                    Some(modifier) => parse_modifier(modifier),
+                    None => bail!("expected a modifier name"),
+                });
+                style.with_modifier(modifiers.collect::<anyhow::Result<_>>()?)
+            }
+            _ => bail!("unexpected field `{key}`"),
+        };
+    }
+    Ok(style)
 }
 
 macro_rules! hi {
@@ -105,17 +274,41 @@ impl Default for Theme {
         use HighlightName as Hl;
         Self {
             default_style: style!(fg = 0x83949600),
+            links: vec![],
             highlights: [
                 hi!(Hl::BACKGROUND => bg=0x002b3600),
                 hi!(Hl::CURSORLINE => bg=0x07364200),
+                hi!(Hl::CURSORCOLUMN => bg=0x07364200),
+                hi!(Hl::COLORCOLUMN => bg=0x07364200),
                 hi!(Hl::DIRECTORY => fg=0x268bd200),
                 hi!(Hl::SEARCH => bg=0x00445400),
                 hi!(Hl::CURRENT_SEARCH => fg=0xeb773400 bg=0x00445400),
+                hi!(Hl::PICKER_MATCH => fg=0xb5890000 bold),
                 hi!(Hl::VISUAL => bg=0x28485800),
+                hi!(Hl::FOLDED => fg=0x586e7500),
+                hi!(Hl::CONCEAL => fg=0x83949600),
+                hi!(Hl::WHITESPACE => fg=0x586e7500),
+                hi!(Hl::SPELL_BAD => underline),
+                hi!(Hl::MESSAGE_ERROR => fg=0xdc322f00),
+                hi!(Hl::MESSAGE_WARNING => fg=0xb5890000),
+                hi!(Hl::MESSAGE_INFO => fg=0x268bd200),
                 hi!(Hl::ERROR => underline),
                 hi!(Hl::WARNING => underline),
                 hi!(Hl::INFO => underline),
                 hi!(Hl::HINT => underline),
+                hi!(Hl::GIT_ADDED => fg=0x85990000),
+                hi!(Hl::GIT_CHANGED => fg=0xb5890000),
+                hi!(Hl::GIT_DELETED => fg=0xdc322f00),
+                hi!(Hl::GIT_BLAME => fg=0x586e7500 italic),
+                hi!(Hl::DIFF_ADDED => bg=0x0f3d2e00),
+                hi!(Hl::DIFF_CHANGED => bg=0x2f3a1500),
+                hi!(Hl::DIFF_DELETED => fg=0xdc322f00),
+                hi!(Hl::RAINBOW_1 => fg=0xb5890000),
+                hi!(Hl::RAINBOW_2 => fg=0xd3368200),
+                hi!(Hl::RAINBOW_3 => fg=0x268bd200),
+                hi!(Hl::RAINBOW_4 => fg=0x2aa19800),
+                hi!(Hl::RAINBOW_5 => fg=0x85990000),
+                hi!(Hl::RAINBOW_6 => fg=0xcb4b1600),
                 hi!(Hl::NAMESPACE => fg=0x39a6b900),
                 hi!(Hl::MODULE => fg=0x39a6b900),
                 hi!(Hl::MACRO => fg=0x298cba00),
@@ -162,11 +355,12 @@ impl HighlightId {
     }
 
     pub fn style(self, theme: &Theme) -> Option<Style> {
-        if self.is_default() {
+        let id = theme.resolve(self);
+        if id.is_default() {
             return Some(theme.default_style);
         }
 
-        theme.highlights.get(self.0 as usize).map(|(_, style)| style).copied()
+        theme.highlights.get(id.0 as usize).map(|(_, style)| style).copied()
     }
 
     pub fn name(self, theme: &Theme) -> Option<&str> {
@@ -233,6 +427,7 @@ mod tests {
     fn highlight_map() {
         let theme = Theme {
             default_style: Style::none(),
+            links: vec![],
             highlights: [
                 ("function", Style::none()),
                 ("function.method", Style::none()),
@@ -253,4 +448,76 @@ mod tests {
         assert_eq!(map.get(1).name(&theme), Some("function.async"));
         assert_eq!(map.get(2).name(&theme), Some("variable.builtin"));
     }
+
+    #[test]
+    fn parse_theme() {
+        let theme = r##"
+            default = { fg = "#112233" }
+            keyword = "#445566"
+            "string.special" = { bg = "#778899", modifiers = ["bold", "italic"] }
+        "##
+        .parse::<Theme>()
+        .unwrap();
+
+        assert_eq!(theme.default_style(), style!(fg = 0x11223300));
+        let style = |name| theme.highlight_id_by_name(name).style(&theme);
+        assert_eq!(style("keyword"), Some(style!(fg = 0x44556600)));
+        assert_eq!(style("string.special"), Some(style!(bg=0x77889900 bold italic)));
+
+        assert!("keyword = 1".parse::<Theme>().is_err());
+        assert!(
+            r##"keyword = { fg = "#445566", modifiers = ["shiny"] }"##.parse::<Theme>().is_err()
+        );
+    }
+
+    #[test]
+    fn theme_with_ids_of() {
+        let prev = Theme::default();
+        let theme = r##"
+            keyword = "#445566"
+            custom = "#778899"
+        "##
+        .parse::<Theme>()
+        .unwrap()
+        .with_ids_of(&prev);
+
+        let keyword = prev.highlight_id_by_name(HighlightName::KEYWORD);
+        assert_eq!(theme.highlight_id_by_name(HighlightName::KEYWORD), keyword);
+        assert_eq!(keyword.style(&theme), Some(style!(fg = 0x44556600)));
+        assert_eq!(
+            prev.highlight_id_by_name(HighlightName::STRING).style(&theme),
+            Some(Style::none()),
+            "highlights missing from the new theme are unstyled"
+        );
+        assert!(!theme.highlight_id_by_name("custom").is_default());
+    }
+
+    #[test]
+    fn highlight_links() {
+        let mut theme = r##"
+            function = "#112233"
+            "function.macro" = { link = "function" }
+            "function.method" = { link = "missing" }
+        "##
+        .parse::<Theme>()
+        .unwrap();
+
+        let style = |theme: &Theme, name| theme.highlight_id_by_name(name).style(theme);
+        assert_eq!(style(&theme, "function.macro"), Some(style!(fg = 0x11223300)));
+        assert_eq!(style(&theme, "function.method"), Some(theme.default_style()));
+
+        theme.set_highlight("function", style!(fg = 0x44556600));
+        assert_eq!(style(&theme, "function.macro"), Some(style!(fg = 0x44556600)));
+
+        theme.set_highlight("function.macro", style!(bold));
+        assert_eq!(theme.highlight_link("function.macro"), None);
+        assert_eq!(style(&theme, "function.macro"), Some(style!(bold)));
+
+        // Cycles must not loop forever.
+        theme.link_highlight("a", "b");
+        theme.link_highlight("b", "a");
+        assert!(style(&theme, "a").is_some());
+
+        assert!(r#"keyword = { link = 1 }"#.parse::<Theme>().is_err());
+    }
 }
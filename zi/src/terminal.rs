@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use crate::ClipboardSelection;
+
+/// Features supported by the terminal (or other frontend) the editor is running in.
+/// The frontend is responsible for detecting these at startup and setting them via
+/// [`Editor::set_capabilities`](crate::Editor::set_capabilities).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// 24-bit colors are supported. If not, colors are approximated with the 256 color palette.
+    pub truecolor: bool,
+    /// The kitty keyboard protocol is supported.
+    pub kitty_keyboard: bool,
+    /// Synchronized output (mode 2026) is supported.
+    pub synchronized_output: bool,
+    /// Setting the clipboard via OSC 52 is supported.
+    pub osc52: bool,
+    /// Changing the cursor shape is supported.
+    pub cursor_shape: bool,
+}
+
+impl Default for Capabilities {
+    /// The capabilities assumed when nothing has been detected.
+    fn default() -> Self {
+        Self {
+            truecolor: true,
+            kitty_keyboard: false,
+            synchronized_output: false,
+            osc52: false,
+            cursor_shape: true,
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool)> {
+        [
+            ("truecolor", self.truecolor),
+            ("kitty keyboard protocol", self.kitty_keyboard),
+            ("synchronized output", self.synchronized_output),
+            ("clipboard (osc 52)", self.osc52),
+            ("cursor shape", self.cursor_shape),
+        ]
+        .into_iter()
+    }
+}
+
+/// Requests from the editor to the terminal that can't be expressed by rendering.
+/// These are drained by the frontend via [`Editor::take_terminal_requests`](crate::Editor::take_terminal_requests).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalRequest {
+    /// Set a system clipboard (via OSC 52).
+    SetClipboard(ClipboardSelection, String),
+    /// Record the terminal to an asciicast file, or stop recording if `None`.
+    Record(Option<PathBuf>),
+    /// Set the window title (via OSC 0).
+    SetTitle(String),
+    /// Report the working directory (via OSC 7), e.g. so new terminal tabs open in it.
+    SetCwd(PathBuf),
+}
@@ -1,7 +1,8 @@
 use std::cell::Cell;
+use std::path::{Path, PathBuf};
 
 use slotmap::Key;
-use tui::LineNumberStyle;
+use tui::{LineNumberStyle, ListChars};
 use zi_core::{Offset, Size, ViewGroupId, ViewId};
 use zi_text::{self, Text as _, TextSlice};
 
@@ -16,6 +17,17 @@ pub struct Settings {
     /// The width of the line numbers column including a space between the number and the text
     pub line_number_width: Setting<u8>,
     pub line_number_style: Setting<LineNumberStyle>,
+    /// How concealed text is displayed from 0 (not concealed) to 3 (hidden), see [`Conceal`](crate::Conceal).
+    pub conceal_level: Setting<u8>,
+    /// Highlight the line the cursor is on.
+    pub cursor_line: Setting<bool>,
+    /// Highlight the column the cursor is on.
+    pub cursor_column: Setting<bool>,
+    /// The 1-indexed columns to highlight as guides, e.g. to mark a maximum line length.
+    pub color_columns: Setting<Vec<u16>>,
+    /// Display whitespace with the glyphs of `list_chars`.
+    pub list: Setting<bool>,
+    pub list_chars: Setting<ListChars>,
 }
 
 impl Default for Settings {
@@ -23,6 +35,12 @@ impl Default for Settings {
         Self {
             line_number_width: Setting::new(4),
             line_number_style: Setting::new(LineNumberStyle::Absolute),
+            conceal_level: Setting::new(0),
+            cursor_line: Setting::new(true),
+            cursor_column: Setting::new(false),
+            color_columns: Setting::new(vec![]),
+            list: Setting::new(false),
+            list_chars: Setting::new(ListChars::default()),
         }
     }
 }
@@ -73,6 +91,8 @@ pub struct View {
     url: Url,
     jumps: JumpList<Location>,
     settings: Settings,
+    /// The working directory of the view set with `:lcd`, if any.
+    local_dir: Option<PathBuf>,
 
     /// The actual width of the line numbers column including a space between the number and the text.
     /// This should be at least `config.line_number_width` but can be larger if the line numbers are wider.
@@ -229,6 +249,12 @@ impl View {
         self.offset = Offset::new(line, 0);
     }
 
+    /// Scroll so `line` is the top line, as far as the cursor stays in bounds.
+    pub(crate) fn set_offset_line(&mut self, size: impl Into<Size>, line: usize) {
+        self.offset.line = line;
+        self.ensure_scroll_in_bounds(size);
+    }
+
     /// Returns the cursor coordinates in the buffer in cells (not characters) relative to the viewport.
     /// For example, '\t' is one character but is 4 cells wide (by default).
     #[inline]
@@ -246,17 +272,13 @@ impl View {
         );
 
         let line_idx = self.cursor.point.line();
-        let text = buf.text();
-        let line = text.line(line_idx).unwrap_or_else(|| Box::new(""));
-        let byte =
-            line.chars().take(self.cursor.point.col()).map(|c| buf.char_width(c)).sum::<usize>();
         // TODO need tests for the column adjustment
-        let x = byte - self.offset.col;
+        let x = buf.display_col(self.cursor.point) - self.offset.col;
         let y = line_idx - self.offset.line;
         (x.try_into().unwrap(), y.try_into().unwrap())
     }
 
-    /// `amt` is measured in characters or lines depending on the direction.
+    /// `amt` is measured in grapheme clusters or lines depending on the direction.
     pub(crate) fn move_cursor(
         &mut self,
         mode: Mode,
@@ -268,15 +290,30 @@ impl View {
         assert_eq!(buf.id(), self.buf);
 
         let pos = match direction {
-            Direction::Left => match buf.text().char_before_point(self.cursor.point) {
-                // this is wrong, can't assume all characters are the same width
-                Some(c) => self.cursor.point.left(c.len_utf8() * amt),
-                None => return self.cursor.point,
-            },
-            Direction::Right => match buf.text().char_at_point(self.cursor.point) {
-                Some(c) => self.cursor.point.right(c.len_utf8() * amt),
-                None => return self.cursor.point,
-            },
+            // Horizontal movements move by grapheme clusters within the line.
+            Direction::Left => {
+                let Some(line) = buf.text().line(self.cursor.point.line()) else {
+                    return self.cursor.point;
+                };
+                let col = self.cursor.point.col();
+                let before = line.graphemes().take_while(|&(i, _)| i < col).count();
+                let col = line.graphemes().nth(before.saturating_sub(amt)).map_or(col, |(i, _)| i);
+                self.cursor.point.with_col(col)
+            }
+            Direction::Right => {
+                let Some(line) = buf.text().line(self.cursor.point.line()) else {
+                    return self.cursor.point;
+                };
+                let col = self.cursor.point.col();
+                let col = line
+                    .graphemes()
+                    .map(|(i, g)| i + g.len())
+                    .filter(|&end| end > col)
+                    .take(amt)
+                    .last()
+                    .unwrap_or(col);
+                self.cursor.point.with_col(col)
+            }
             // Horizontal movements set the target column.
             // Vertical movements try to keep moving to the target column.
             Direction::Up => self.cursor.point.up(amt),
@@ -393,7 +430,7 @@ impl View {
             | Mode::VisualLine
             | Mode::VisualBlock
             | Mode::OperatorPending(..)
-            | Mode::ReplacePending => line.chars().next_back().map_or(0, |c| c.len_utf8()),
+            | Mode::ReplacePending => line.graphemes().last().map_or(0, |(_, g)| g.len()),
         };
 
         let max_col = Col::from(line_len.saturating_sub(k));
@@ -507,6 +544,7 @@ impl View {
             cursor: Default::default(),
             offset: Default::default(),
             jumps: Default::default(),
+            local_dir: None,
         }
     }
 
@@ -518,6 +556,16 @@ impl View {
         Self { id, group: None, ..view.clone() }
     }
 
+    /// The working directory of the view if it was set with `:lcd`, see [`Editor::current_dir`].
+    #[inline]
+    pub fn local_dir(&self) -> Option<&Path> {
+        self.local_dir.as_deref()
+    }
+
+    pub(crate) fn set_local_dir(&mut self, dir: Option<PathBuf>) {
+        self.local_dir = dir;
+    }
+
     pub fn jump_list(&self) -> &JumpList<Location> {
         &self.jumps
     }
@@ -1,5 +1,8 @@
+use std::path::Path;
 use std::sync::OnceLock;
 
+use proptest::prop_assume;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
 use tokio::sync::OnceCell;
 use zi_nvim::{CompareFlags, Fixture, Nvim, TestCase, spawn};
 
@@ -23,24 +26,43 @@ macro_rules! t {
         t!($text, $inputs, $name, ::zi_nvim::CompareFlags::empty());
     };
     ( $text:expr, $inputs:tt, $name:ident, $flags:expr) => {
-        ::proptest::proptest! {
-            #[test]
-            fn $name(inputs in $inputs, text in $text) {
-                // `:help cw`
-                // stupid special case in neovim that I can't find a good workaround for.
-                // Also don't want to implement it as it's not a good default behaviour.
-                proptest::prop_assume!(!inputs.contains("cw") && !inputs.contains("cW"));
-
-                // `cb` and `db` have some interesting (undocumented?) behaviour (repro with text="a\nb" input="wcb") where the first newline is not removed.
-                // Not sure how to go about matching this behaviour so skip these cases for now
-                proptest::prop_assume!(!inputs.contains("cb") && !inputs.contains("cB"));
-
-                run(text, &inputs, $flags)
-            }
+        #[test]
+        fn $name() {
+            prop(stringify!($name), $text, $inputs, $flags);
         }
     };
 }
 
+/// Run the property test with proptest's shrinking, only the case it shrinks to is minimized and
+/// traced by [`run`] as that reruns it many times.
+#[track_caller]
+fn prop(name: &str, text: &'static str, inputs: &'static str, flags: CompareFlags) {
+    let mut runner = TestRunner::new(Config { source_file: Some(file!()), ..Config::default() });
+    let res = runner.run(&(inputs, text), |(inputs, text)| {
+        // `:help cw`
+        // stupid special case in neovim that I can't find a good workaround for.
+        // Also don't want to implement it as it's not a good default behaviour.
+        prop_assume!(!inputs.contains("cw") && !inputs.contains("cW"));
+
+        // `cb` and `db` have some interesting (undocumented?) behaviour (repro with text="a\nb" input="wcb") where the first newline is not removed.
+        // Not sure how to go about matching this behaviour so skip these cases for now
+        prop_assume!(!inputs.contains("cb") && !inputs.contains("cB"));
+
+        check(text, &inputs, flags).map_err(|err| TestCaseError::fail(format!("{err:#}")))
+    });
+
+    match res {
+        Ok(()) => {}
+        Err(TestError::Fail(reason, (inputs, text))) => {
+            run(name, text.as_str(), &inputs, flags);
+            panic!(
+                "{name} failed with inputs={inputs:?} text={text:?} but passed when rerun: {reason}"
+            );
+        }
+        Err(err) => panic!("{name}: {err}"),
+    }
+}
+
 // Too many annoying cases here for word motions :)
 // Also the default behaviour is not great anyway so leave this for later.
 // We probably want to implement something closer to vim-wordmotion by default
@@ -57,19 +79,25 @@ t!(I, "[dWBhjkl]+", nvim_delete_operator);
 t!(I, "d([uWB]|(<ESC>))+<ESC>", nvim_undo_delete_word);
 t!(I, "([ucdWB]|(<ESC>))+<ESC>", nvim_undo);
 t!(I, "[CDWBhjkl]", nvim_big_cd);
-t!(I, "v[jk]+y", nvim_visual_yank);
+t!(I, "v[jk]+y", nvim_visual_yank, CompareFlags::REGISTERS | CompareFlags::VISUAL);
 t!(I, "v[jk]+d", nvim_visual_delete);
 t!(I_NO_TRAILING_SPACE, "v[jk]+c<ESC>", nvim_visual_change);
 t!(I, "V[jk]*d", nvim_visual_line_delete);
-t!(I, "V[jk]*y", nvim_visual_line_yank);
+t!(I, "V[jk]*y", nvim_visual_line_yank, CompareFlags::REGISTERS | CompareFlags::VISUAL);
 t!(I_NO_TRAILING_SPACE, "V[jk]*c<ESC>", nvim_visual_line_change);
+// End in visual mode so the anchor is compared after the last key too.
+t!(I, "v[hjklWB]+", nvim_visual_motions, CompareFlags::VISUAL);
+t!(I, "V[hjklWB]+", nvim_visual_line_motions, CompareFlags::VISUAL);
+// Patterns of letters only so they mean the same to both regex engines.
+t!(I, "/[a-z]{1,3}<CR>[nN]*", nvim_search, CompareFlags::SEARCH);
+t!(I, "/[a-z]{1,3}<CR>v[nN]+", nvim_visual_search, CompareFlags::SEARCH | CompareFlags::VISUAL);
 
 /// Useful to test a particular case
 #[test]
 fn scratch() {
     #[allow(unused)]
     fn test(text: &str, inputs: &str) {
-        run(text, inputs, CompareFlags::empty())
+        run("scratch", text, inputs, CompareFlags::empty())
     }
 
     test("abcde", "Vy");
@@ -77,11 +105,23 @@ fn scratch() {
     test("first\nsecond\nthird", "Vjc<ESC>");
 }
 
+fn check(text: String, inputs: &str, flags: CompareFlags) -> zi::Result<()> {
+    rt().block_on(async move {
+        let fixture = Fixture::new([TestCase::new(text, inputs)]);
+        let nvim = nvim(&fixture).await;
+        fixture.nvim_vs_zi_with(nvim, flags).await
+    })
+}
+
+/// Failing cases are minimized and a trace of the minimized case is written to
+/// `$CARGO_TARGET_TMPDIR/nvim-prop/<name>.test`, which can be copied into `tests/nvim/testdata`.
 #[track_caller]
-fn run(text: impl Into<String>, inputs: &str, flags: CompareFlags) {
+fn run(name: &str, text: impl Into<String>, inputs: &str, flags: CompareFlags) {
+    let trace_path =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join("nvim-prop").join(format!("{name}.test"));
     rt().block_on(async move {
         let fixture = Fixture::new([TestCase::new(text, inputs)]);
         let nvim = nvim(&fixture).await;
-        fixture.nvim_vs_zi_with(nvim, flags).await.unwrap();
+        fixture.nvim_vs_zi_traced(nvim, flags, &trace_path).await.unwrap();
     })
 }
@@ -2,6 +2,7 @@ use std::path::Path;
 
 use arbitrary::{Arbitrary, Unstructured};
 use datatest_stable::{Result, harness};
+use futures_util::StreamExt;
 use zi::input::{KeyCode, KeyEvent, KeySequence};
 
 #[global_allocator]
@@ -35,7 +36,13 @@ async fn run(seq: KeySequence) -> Result<()> {
     .into_iter()
     .map(KeyEvent::from);
     let inputs = seq.into_iter().chain(quit_sequence);
-    let inputs = futures_util::stream::iter(inputs.into_iter().map(zi::input::Event::Key).map(Ok));
+    // Yield between keys so that the editor renders after each one rather than coalescing them.
+    let inputs = futures_util::stream::iter(inputs.into_iter().map(zi::input::Event::Key).map(Ok))
+        .then(|event| async move {
+            tokio::task::yield_now().await;
+            event
+        });
+    editor.settings().redraw_interval.write(std::time::Duration::ZERO);
 
     editor
         .run(inputs, tasks, |editor| {
@@ -1,17 +1,41 @@
+mod case;
 mod command;
+mod comment;
 mod completion;
 mod config;
 mod cursor;
+mod cwd;
+mod diff;
 mod dot;
 mod edit;
+mod eval;
+mod fold;
+mod git;
+mod global;
+mod increment;
+mod indent;
+mod join;
+mod mapping;
 mod marks;
+mod messages;
 mod motion;
+mod mouse;
+mod normal;
 mod open;
+mod paste;
 mod picker;
+mod quit;
+mod register;
+mod report;
 mod save;
 mod scroll;
 mod search;
+mod snippet;
+mod spell;
+mod surround;
+mod swap;
 mod tab;
+mod theme;
 mod undo;
 mod view;
 mod visual;
@@ -0,0 +1,47 @@
+use crate::new;
+
+#[tokio::test]
+async fn case_operators() {
+    let cx = new("hello World\n").await;
+    cx.with(|editor| {
+        editor.input("gUw").unwrap();
+        assert_eq!(editor.text(zi::Active), "HELLO World\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 0));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("g~~").unwrap();
+        assert_eq!(editor.text(zi::Active), "hello wORLD\n");
+
+        editor.input("guu").unwrap();
+        assert_eq!(editor.text(zi::Active), "hello world\n");
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "hello wORLD\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn toggle_case() {
+    let cx = new("hello world\n").await;
+    cx.with(|editor| {
+        editor.input("~").unwrap();
+        assert_eq!(editor.text(zi::Active), "Hello world\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 1));
+
+        editor.input("3~").unwrap();
+        assert_eq!(editor.text(zi::Active), "HELLo world\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 4));
+
+        editor.input("vlU").unwrap();
+        assert_eq!(editor.text(zi::Active), "HELLO world\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 4));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("Vu").unwrap();
+        assert_eq!(editor.text(zi::Active), "hello world\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
@@ -28,3 +28,379 @@ async fn cmd_unknown() {
     cx.render().await;
     cx.cleanup().await;
 }
+
+#[tokio::test]
+async fn cmd_capabilities() {
+    let cx = new("").await;
+
+    cx.with(|editor| {
+        let capabilities = zi::Capabilities { truecolor: false, ..*editor.capabilities() };
+        editor.set_capabilities(capabilities);
+        editor.execute("capabilities").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        let report = editor.buffer(zi::Active).text().to_string();
+        assert!(report.contains("truecolor: no"), "{report}");
+        assert!(report.contains("cursor shape: yes"), "{report}");
+    })
+    .await;
+
+    // The renderer should handle the lack of truecolor support
+    cx.render().await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn cmd_checkhealth() {
+    let cx = new("").await;
+
+    cx.with(|editor| {
+        editor.execute("checkhealth").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        let report = editor.buffer(zi::Active).text().to_string();
+        for section in [
+            "language services",
+            "grammars",
+            "terminal",
+            "clipboard",
+            "config",
+            "plugins",
+            "startup",
+        ] {
+            assert!(report.lines().any(|line| line == section), "missing `{section}`:\n{report}");
+        }
+        assert!(report.contains("  rust: "), "{report}");
+        assert!(report.contains("  first render: "), "{report}");
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[test]
+fn startup_profile() {
+    let mut profile = zi::StartupProfile::new();
+    profile.record("a");
+    profile.record("b");
+    let phases = profile.phases();
+    assert_eq!(phases.iter().map(|phase| phase.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(phases[1].duration, phases[1].end - phases[0].end);
+
+    let report = profile.to_string();
+    assert_eq!(report.lines().count(), 3, "{report}");
+    assert!(report.lines().last().unwrap().ends_with("  b"), "{report}");
+
+    // The phases are written as they end, not only once the profile is done with.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startuptime");
+    profile.write_to(&path).unwrap();
+    profile.record("c");
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, profile.to_string());
+}
+
+#[tokio::test]
+async fn cmd_buffers() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let a = cx.open_tmp("a", zi::OpenFlags::empty()).await?;
+    let b = cx.open_tmp("b", zi::OpenFlags::empty()).await?;
+
+    let number = cx
+        .with(move |editor| {
+            assert_eq!(editor.listed_buffers().collect::<Vec<_>>(), [a, b]);
+            assert_eq!(editor.buffer(zi::Active).id(), b);
+            let list = editor.buffer_list();
+            assert_eq!(list.lines().count(), 2, "{list}");
+            assert!(list.lines().nth(1).unwrap().contains(" % "), "{list}");
+            assert_eq!(editor.buffer_number(b), Some(editor.buffer_number(a).unwrap() + 1));
+            editor.buffer_number(a).unwrap()
+        })
+        .await;
+
+    cx.with(move |editor| editor.execute(format!("b {number}").as_str()).unwrap()).await;
+    cx.with(move |editor| {
+        assert_eq!(editor.buffer(zi::Active).id(), a);
+        editor.edit(a, &zi::Deltas::insert_at(0, "x")).unwrap();
+        assert!(editor.delete_buffer(a, false).is_err(), "buffer has unsaved changes");
+
+        editor.delete_buffer(a, true).unwrap();
+        assert_eq!(editor.buffer(zi::Active).id(), b, "deleted buffer should be replaced");
+        assert_eq!(editor.listed_buffers().collect::<Vec<_>>(), [b]);
+        assert!(editor.delete_buffer(b, false).is_err(), "cannot delete the last buffer");
+        assert_eq!(editor.buffer_number(a), None);
+    })
+    .await;
+
+    // Numbers are not reused after a buffer is deleted.
+    let c = cx.open_tmp("c", zi::OpenFlags::empty()).await?;
+    cx.with(move |editor| {
+        assert_eq!(editor.buffer_number(b), Some(number + 1));
+        assert_eq!(editor.buffer_number(c), Some(number + 2));
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cmd_explorer() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+
+    cx.with({
+        let dir = dir.clone();
+        move |editor| {
+            editor.open_file_explorer(&dir);
+            editor.execute("create a").unwrap();
+            editor.execute("create b/c/").unwrap();
+        }
+    })
+    .await;
+
+    cx.with({
+        let dir = dir.clone();
+        move |editor| {
+            assert!(dir.join("a").is_file());
+            assert!(dir.join("b/c").is_dir());
+            assert!(editor.create_file("a").is_err(), "file already exists");
+        }
+    })
+    .await;
+
+    let fut = cx
+        .with({
+            let dir = dir.clone();
+            move |editor| {
+                assert!(editor.rename_file(dir.join("a"), dir.join("b")).is_err(), "target exists");
+                editor.rename_file(dir.join("a"), dir.join("b/a")).unwrap()
+            }
+        })
+        .await;
+    fut.await?;
+    assert!(!dir.join("a").exists());
+    assert!(dir.join("b/a").is_file());
+
+    // Refreshing picks up changes made outside of the editor
+    std::fs::write(dir.join("new"), "")?;
+    cx.with(|editor| editor.refresh_explorer().unwrap()).await;
+    let mut text = String::new();
+    for _ in 0..100 {
+        cx.render().await;
+        text = cx.with(|editor| editor.buffer(zi::Active).text().to_string()).await;
+        if text.lines().any(|line| line == "new") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(text.lines().any(|line| line == "new"), "{text}");
+
+    // The entry under the cursor is trashed
+    cx.with(|editor| {
+        let text = editor.buffer(zi::Active).text().to_string();
+        let line = text.lines().position(|line| line == "new").unwrap();
+        editor.set_cursor(zi::Active, (line, 0));
+        editor.execute("trash").unwrap();
+    })
+    .await;
+    cx.with(move |_| assert!(!dir.join("new").exists())).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cmd_file_operations_outside_explorer() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    std::fs::write(dir.join("a"), "abc\n")?;
+    let buf = cx.open(dir.join("a"), zi::OpenFlags::empty()).await?;
+
+    // Outside of an explorer, paths are relative to the directory of the active buffer
+    cx.with(|editor| editor.execute("create b").unwrap()).await;
+    cx.with({
+        let dir = dir.clone();
+        move |_| assert!(dir.join("b").is_file())
+    })
+    .await;
+
+    let fut = cx.with(|editor| editor.rename_active_file("c").unwrap()).await;
+    fut.await?;
+    assert!(!dir.join("a").exists());
+    assert_eq!(std::fs::read_to_string(dir.join("c"))?, "abc\n");
+
+    // The open buffer now refers to the renamed file
+    let path = dir.canonicalize()?.join("c");
+    cx.with(move |editor| {
+        assert_eq!(editor.buffer(buf).file_path(), Some(path));
+        editor.execute("trash").unwrap();
+    })
+    .await;
+    cx.with(move |_| assert!(!dir.join("c").exists())).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn apply_workspace_edit() -> zi::Result<()> {
+    use zi::lstypes::{EncodedRange, TextEdit, WorkspaceEdit};
+
+    let cx = new("").await;
+    let open = cx.open_tmp("abc\ndef\n", zi::OpenFlags::empty()).await?;
+    let closed = cx.tempfile("ghi\n")?;
+
+    let url = cx.with(move |editor| editor.buffer(open).file_url().cloned().unwrap()).await;
+    let edit = |range: zi::PointRange, text: &str| TextEdit {
+        range: EncodedRange::from(range),
+        new_text: text.to_string(),
+    };
+    let workspace_edit = WorkspaceEdit {
+        changes: vec![
+            (
+                url,
+                vec![
+                    edit(zi::PointRange::new((1, 0), (1, 3)), "x"),
+                    edit(zi::PointRange::new((0, 0), (0, 0)), "y"),
+                ],
+            ),
+            (
+                zi::Url::from_file_path(&closed).unwrap(),
+                vec![edit(zi::PointRange::new((0, 1), (0, 2)), "")],
+            ),
+        ],
+    };
+
+    let fut = cx.with(move |editor| editor.apply_workspace_edit(workspace_edit)).await;
+    fut.await?;
+    cx.with(move |editor| {
+        assert_eq!(editor[open].text().to_string(), "yabc\nx\n");
+        // Files that weren't open are opened in the background
+        let buf = editor.buffers().find(|buf| buf.file_path().as_deref() == Some(&*closed));
+        assert_eq!(buf.unwrap().text().to_string(), "gi\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cmd_filter() -> zi::Result<()> {
+    let cx = new("c\nb\na\nd\n").await;
+
+    cx.with(|editor| {
+        editor.input("!j").unwrap();
+        assert_eq!(editor.command_buffer(), Some(":1,2!"));
+        editor.input("<ESC>").unwrap();
+    })
+    .await;
+
+    let fut = cx.with(|editor| editor.filter(zi::Active, 0..3, "sort").unwrap()).await;
+    fut.await?;
+    cx.with(|editor| {
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a\nb\nc\nd\n");
+        // The whole filter is undone at once
+        editor.undo(zi::Active).unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "c\nb\na\nd\n");
+    })
+    .await;
+
+    // The text is left alone if the command fails, stderr is the error
+    let fut =
+        cx.with(|editor| editor.filter(zi::Active, 0..4, "echo oops >&2; exit 1").unwrap()).await;
+    assert_eq!(fut.await.unwrap_err().to_string(), "oops");
+    cx.with(|editor| {
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "c\nb\na\nd\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cmd_shell() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let fut = cx.with(|editor| editor.shell("echo out; echo err >&2; exit 3").unwrap()).await;
+    fut.await?;
+    cx.render().await;
+    cx.with(|editor| {
+        // The output is shown in a new split
+        assert_eq!(editor.views().count(), 2);
+        let output = editor.buffer(zi::Active).text().to_string();
+        assert!(output.contains("out\n"), "{output}");
+        assert!(output.contains("err\n"), "{output}");
+        assert!(output.ends_with("exit status: 3]\n"), "{output}");
+    })
+    .await;
+
+    // `<C-c>` kills the command
+    let fut = cx.with(|editor| editor.shell("sleep 10; echo done").unwrap()).await;
+    cx.with(|editor| editor.input("<C-c>").unwrap()).await;
+    fut.await?;
+    cx.render().await;
+    cx.with(|editor| {
+        let output = editor.buffer(zi::Active).text().to_string();
+        assert!(!output.contains("done"), "{output}");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cmd_ranges() {
+    let cx = new("a\n\tb\n\tc\n\td\n\te\n").await;
+    cx.with(|editor| editor.execute("2mark x").unwrap()).await;
+    cx.with(|editor| editor.execute("'x,/d/-1retab").unwrap()).await;
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "a\n    b\n    c\n\td\n\te\n");
+
+        let resolve = |editor: &zi::Editor, range: &str| {
+            let cmd = format!("{range}retab").parse::<zi::Command>().unwrap();
+            editor.resolve_range(zi::Active, cmd.range().unwrap()).map_err(|err| err.to_string())
+        };
+        assert_eq!(resolve(editor, "?b?,.+2"), Ok(1..3));
+        assert_eq!(resolve(editor, "/nope/"), Err("pattern not found: `nope`".to_string()));
+        assert_eq!(resolve(editor, "'z"), Err("mark not set: `z`".to_string()));
+
+        // Marks move with edits
+        editor.input("Onew<Esc>").unwrap();
+        assert_eq!(resolve(editor, "'x"), Ok(2..3));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn cmd_window() {
+    let cx = new("\ta\n").await;
+    cx.with(|editor| editor.input(":%retab<CR>").unwrap()).await;
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "    a\n");
+        assert_eq!(editor.command_history().collect::<Vec<_>>(), ["%retab"]);
+        editor.execute("set expandtab false").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("q:").unwrap();
+        assert_eq!(editor.views().count(), 2);
+        assert_eq!(editor.text(zi::Active), "%retab\n");
+        editor.input("<CR>").unwrap();
+        assert_eq!(editor.views().count(), 1);
+    })
+    .await;
+
+    cx.with(|editor| assert_eq!(editor.text(zi::Active), "\ta\n")).await;
+    cx.cleanup().await;
+}
@@ -0,0 +1,69 @@
+use zi::{CommentTokens, LanguageConfig};
+
+use crate::new;
+
+#[tokio::test]
+async fn toggle_line_comments() {
+    let cx = new("fn f() {\n    foo\n\n    bar\n}\n").await;
+    cx.with(|editor| {
+        let ft = editor.buffer(zi::Active).file_type();
+        editor.language_config_mut().add_language(
+            ft,
+            LanguageConfig::default().with_comment_tokens(CommentTokens::line("//")),
+        );
+
+        editor.input("jgc2j").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    // foo\n\n    // bar\n}\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 4));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("gcc").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    foo\n\n    // bar\n}\n");
+
+        // A partially commented range is commented at the smallest indentation.
+        editor.input("ggVGgc").unwrap();
+        assert_eq!(editor.text(zi::Active), "// fn f() {\n//     foo\n\n//     // bar\n// }\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    foo\n\n    // bar\n}\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn toggle_block_comment() {
+    let cx = new("a\n  b\n").await;
+    cx.with(|editor| {
+        let ft = editor.buffer(zi::Active).file_type();
+        editor.language_config_mut().add_language(
+            ft,
+            LanguageConfig::default().with_comment_tokens(CommentTokens::block("/*", "*/")),
+        );
+
+        editor.input("Vjgc").unwrap();
+        assert_eq!(editor.text(zi::Active), "/* a\n  b */\n");
+
+        editor.input("gcj").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\n  b\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn no_comment_tokens() {
+    let cx = new("a\n").await;
+    cx.with(|editor| {
+        assert!(editor.comment_tokens(zi::filetype!(text)).is_none());
+        assert_eq!(
+            editor.comment_tokens(zi::filetype!(rust)),
+            Some(CommentTokens::line("//").with_block("/*", "*/"))
+        );
+        editor.input("gcc").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
@@ -12,7 +12,12 @@ fn res() -> lstypes::CompletionResponse {
         items: vec![
             zi::CompletionItem { label: "foo".to_string(), ..Default::default() },
             zi::CompletionItem { label: "bar".to_string(), ..Default::default() },
-            zi::CompletionItem { label: "bazz".to_string(), ..Default::default() },
+            zi::CompletionItem {
+                label: "bazz".to_string(),
+                kind: Some(zi::CompletionItemKind::Function),
+                deprecated: true,
+                ..Default::default()
+            },
         ],
     }
 }
@@ -25,6 +30,15 @@ impl zi::CompletionProvider for Completions {
     ) -> BoxFuture<'static, zi::Result<lstypes::CompletionResponse>> {
         Box::pin(async move { Ok(res()) })
     }
+
+    fn resolve(
+        &self,
+        _editor: &mut zi::Editor,
+        item: zi::CompletionItem,
+    ) -> BoxFuture<'static, zi::Result<zi::CompletionItem>> {
+        let documentation = Some(format!("{} docs", item.label));
+        Box::pin(async move { Ok(zi::CompletionItem { documentation, ..item }) })
+    }
 }
 
 #[tokio::test]
@@ -45,6 +59,48 @@ async fn request_completion() -> zi::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn completion_sources() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    std::fs::create_dir_all(dir.join("src/main"))?;
+    std::fs::write(dir.join("src/lib.rs"), "")?;
+    std::fs::write(dir.join("a.txt"), "foo quux src/\n")?;
+
+    cx.open(dir.join("a.txt"), zi::OpenFlags::empty()).await?;
+    let completions = cx
+        .with(|editor| {
+            editor.register_completion_provider(Completions);
+            editor.set_mode(zi::Mode::Insert);
+            editor.set_cursor(zi::Active, (0, 13));
+            editor.request_completions(zi::Active)
+        })
+        .await
+        .await?;
+
+    let item = |label: &str, kind| zi::CompletionItem {
+        label: label.to_string(),
+        kind: Some(kind),
+        ..Default::default()
+    };
+
+    // Items are ordered by the priority of their provider and the duplicate `foo` from the buffer
+    // words is dropped.
+    let mut expected = vec![
+        item("lib.rs", zi::CompletionItemKind::File),
+        item("main/", zi::CompletionItemKind::Folder),
+    ];
+    expected.extend(res().items);
+    expected.extend([
+        item("quux", zi::CompletionItemKind::Text),
+        item("src", zi::CompletionItemKind::Text),
+    ]);
+    assert_eq!(completions, expected);
+
+    cx.cleanup().await;
+    Ok(())
+}
+
 fn completions(editor: &mut zi::Editor) -> Vec<zi::CompletionItem> {
     editor.completions().unwrap().cloned().collect()
 }
@@ -120,3 +176,46 @@ async fn tab_completion() -> zi::Result<()> {
     cx.cleanup().await;
     Ok(())
 }
+
+#[tokio::test]
+async fn confirm_completion() -> zi::Result<()> {
+    let cx = new("").await;
+    cx.open_tmp("", zi::OpenFlags::empty()).await?;
+    cx.with(|editor| {
+        editor.register_completion_provider(Completions);
+        editor.input("i<C-n>").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("<C-n><C-n>").unwrap();
+        assert_eq!(editor.text(zi::Active), "bar\n");
+        editor.input("<C-p>").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo\n");
+    })
+    .await;
+
+    cx.with(|editor| {
+        // The selected item is resolved lazily.
+        let selected = editor.selected_completion().unwrap();
+        assert_eq!(selected.documentation.as_deref(), Some("foo docs"));
+
+        editor.input("<C-y>").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo\n");
+        assert_eq!(editor.completions().unwrap().len(), 0);
+
+        editor.execute("set completeconfirm <CR>").unwrap();
+        editor.input(" b").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        // Confirming without a selection accepts the best match instead of inserting a newline.
+        editor.input("<CR>").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
@@ -68,10 +68,40 @@ async fn buffer_set_command() {
     check_buf(&cx, "set ts 4", 4, |buf| *buf.tab_width.read()).await;
     check_buf(&cx, "set tabstop 2", 2, |buf| *buf.tab_width.read()).await;
 
+    // :set fileformat
+    check_buf(&cx, "set fileformat dos", zi::LineEnding::CrLf, |buf| *buf.line_ending.read()).await;
+    check_buf(&cx, "set ff unix", zi::LineEnding::Lf, |buf| *buf.line_ending.read()).await;
+
     // :set numberwidth
     cx.with(|editor| assert_eq!(view(editor).line_number_width, 4)).await;
     check_view(&cx, "set numberwidth 6", 6, |view| *view.line_number_width.read()).await;
     check_view(&cx, "set nuw 3", 3, |view| *view.line_number_width.read()).await;
 
+    // :set cursorline, cursorcolumn, colorcolumn
+    check_view(&cx, "set cursorline false", false, |view| *view.cursor_line.read()).await;
+    check_view(&cx, "set cuc true", true, |view| *view.cursor_column.read()).await;
+    check_view(&cx, "set colorcolumn 80,100", vec![80, 100], |view| {
+        view.color_columns.read().clone()
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn rainbow_delimiters() {
+    let cx = new("").await;
+    let ft = cx
+        .with(|editor| {
+            let ft = editor.buffer(zi::Active).file_type();
+            assert!(!editor.rainbow_delimiters(ft));
+            editor.toggle_rainbow_delimiters(ft);
+            assert!(editor.rainbow_delimiters(ft));
+            editor.execute("RainbowToggle").unwrap();
+            ft
+        })
+        .await;
+
+    cx.with(move |editor| assert!(!editor.rainbow_delimiters(ft))).await;
     cx.cleanup().await;
 }
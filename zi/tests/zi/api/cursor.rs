@@ -1,4 +1,5 @@
 use zi::Direction::*;
+use zi::Point;
 
 use crate::new;
 
@@ -48,6 +49,64 @@ async fn cursor_viewport_coords_tabs() {
     cx.cleanup().await;
 }
 
+#[tokio::test]
+async fn cursor_viewport_coords_graphemes() {
+    let cx = new("你好e\u{301}x\n").await;
+    cx.with(|editor| {
+        editor.set_cursor(zi::Active, (0, 0));
+        editor.move_cursor(zi::Active, Right, 1);
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+        assert_eq!(editor.cursor_viewport_coords(), (2, 0), "wide characters are 2 cells");
+
+        editor.move_cursor(zi::Active, Right, 2);
+        assert_eq!(editor.cursor(zi::Active), (0, 9), "the accent is part of the grapheme");
+        assert_eq!(editor.cursor_viewport_coords(), (5, 0));
+
+        editor.move_cursor(zi::Active, Left, 1);
+        assert_eq!(editor.cursor(zi::Active), (0, 6));
+        assert_eq!(editor.view_point_at(zi::Active, (3, 0)), Some(Point::new(0, 3)));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn virtual_col_tab_stops() {
+    let cx = new("ab\tc\n\t\tx\n").await;
+    cx.with(|editor| {
+        // The tab extends to the next multiple of the tab width.
+        editor.set_cursor(zi::Active, (0, 3));
+        assert_eq!(editor.virtual_col(zi::Active), 4);
+        assert_eq!(editor.cursor_viewport_coords(), (4, 0));
+
+        editor.set_cursor(zi::Active, (1, 2));
+        assert_eq!(editor.virtual_col(zi::Active), 8);
+
+        editor.execute("set tabstop 3").unwrap();
+        editor.set_cursor(zi::Active, (0, 3));
+        assert_eq!(editor.virtual_col(zi::Active), 3);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn view_point_at_tabs() {
+    let cx = new("ab\tc\n").await;
+    cx.with(|editor| {
+        assert_eq!(editor.view_point_at(zi::Active, (1, 0)), Some(Point::new(0, 1)));
+        // Any cell of the tab is the tab.
+        assert_eq!(editor.view_point_at(zi::Active, (2, 0)), Some(Point::new(0, 2)));
+        assert_eq!(editor.view_point_at(zi::Active, (3, 0)), Some(Point::new(0, 2)));
+        assert_eq!(editor.view_point_at(zi::Active, (4, 0)), Some(Point::new(0, 3)));
+        // Past the end of the line is the last char.
+        assert_eq!(editor.view_point_at(zi::Active, (10, 0)), Some(Point::new(0, 3)));
+        assert_eq!(editor.view_point_at(zi::Active, (0, 1)), Some(Point::new(1, 0)));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
 #[tokio::test]
 async fn cursor_viewport_coords_scroll() {
     let cx = new("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n").await;
@@ -0,0 +1,55 @@
+use zi::Active;
+use zi::Constraint::*;
+use zi::Direction::*;
+
+use crate::new;
+
+#[tokio::test]
+async fn working_directories() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?.canonicalize()?;
+    std::fs::create_dir(dir.join("sub"))?;
+    std::fs::write(dir.join("sub/a.txt"), "a\n")?;
+
+    cx.with({
+        let dir = dir.clone();
+        move |editor| {
+            let cwd = std::env::current_dir().unwrap();
+            assert_eq!(editor.current_dir(Active), cwd);
+            editor.change_dir(&dir).unwrap();
+            assert_eq!(editor.current_dir(Active), dir);
+            assert_eq!(std::env::current_dir().unwrap(), cwd, "the process is unaffected");
+            assert!(editor.change_dir("sub/a.txt").is_err(), "not a directory");
+            assert!(editor.change_dir("missing").is_err());
+
+            editor.change_local_dir(Active, "sub").unwrap();
+            assert_eq!(editor.current_dir(Active), dir.join("sub"));
+            assert_eq!(editor.resolve_path("a.txt"), dir.join("sub/a.txt"));
+            let split = editor.split(Active, Right, Fill(1));
+            assert_eq!(editor.current_dir(split), dir.join("sub"), "splits inherit the directory");
+        }
+    })
+    .await;
+
+    // Relative paths are opened relative to the local directory.
+    let buf = cx.open("a.txt", zi::OpenFlags::empty()).await?;
+    cx.with({
+        let dir = dir.clone();
+        move |editor| {
+            assert_eq!(editor.buffer(buf).file_path(), Some(dir.join("sub/a.txt")));
+            editor.execute("cd %:h/..").unwrap();
+        }
+    })
+    .await;
+
+    cx.with(move |editor| {
+        assert_eq!(editor.current_dir(Active), dir, "`:cd` clears the local directory");
+        assert_eq!(editor.view(Active).local_dir(), None);
+        assert!(editor.execute("cd %:x").is_err());
+        assert!(editor.execute("cd %:é").is_err(), "modifiers are characters, not bytes");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
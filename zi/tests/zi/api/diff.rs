@@ -0,0 +1,59 @@
+use zi::{Active, LineDiff, OpenFlags};
+
+use crate::new;
+
+#[tokio::test]
+async fn diff_split() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let dir = cx.tempdir()?;
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    std::fs::write(&a, "a\nb\nc\nd\n")?;
+    std::fs::write(&b, "a\nx\nc\n")?;
+
+    let a_buf = cx.open(&a, OpenFlags::empty()).await?;
+    let a_view = cx.with(|editor| editor.view(Active).id()).await;
+    cx.with(move |editor| editor.diff_split(Active, b)).await?.await?;
+
+    cx.with(move |editor| {
+        assert!(editor.is_diffing(a_view));
+        assert_eq!(
+            editor.diff_hunks(Active).unwrap(),
+            [LineDiff { old: 1..2, new: 1..2 }, LineDiff { old: 3..4, new: 3..3 }]
+        );
+        assert_eq!(
+            editor.diff_hunks(a_view).unwrap(),
+            [LineDiff { old: 1..2, new: 1..2 }, LineDiff { old: 3..3, new: 3..4 }]
+        );
+
+        editor.input("]c").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 1);
+        // The line above the lines missing from this buffer.
+        editor.input("]c").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 2);
+        editor.input("[c").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 1);
+
+        editor.diff_get(Active).unwrap();
+        assert_eq!(editor.text(Active).to_string(), "a\nb\nc\n");
+        assert_eq!(editor.diff_hunks(Active).unwrap(), [LineDiff { old: 3..4, new: 3..3 }]);
+
+        editor.input("]c").unwrap();
+        editor.diff_put(Active).unwrap();
+        assert_eq!(editor.text(a_buf).to_string(), "a\nb\nc\n");
+        assert_eq!(editor.diff_hunks(Active).unwrap(), []);
+    })
+    .await;
+
+    cx.render().await;
+
+    cx.with(move |editor| {
+        editor.diff_off();
+        assert!(!editor.is_diffing(a_view));
+        assert!(editor.diff_hunks(Active).is_none());
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
@@ -340,17 +340,17 @@ async fn dot_repeat_paste() {
     .await;
 
     cx.with(|editor| {
-        // Move to "two" and paste after current position
+        // Move to "two" and paste after the cursor
         editor.input("wp").unwrap();
-        // Paste inserts "one" (the yanked word) at the beginning
-        assert_eq!(editor.cursor_line(), "one one two three");
+        // Paste inserts "one " (the yanked word) after the `t`
+        assert_eq!(editor.cursor_line(), "one tone wo three");
     })
     .await;
 
     cx.with(|editor| {
-        // Use dot to paste again - should paste another "one"
+        // Use dot to paste again - should paste another "one " after the end of the last paste
         editor.input(".").unwrap();
-        assert_eq!(editor.cursor_line(), "one one one two three");
+        assert_eq!(editor.cursor_line(), "one tone one wo three");
     })
     .await;
 
@@ -447,21 +447,21 @@ async fn dot_after_paste_then_insert() {
     cx.with(|editor| {
         // Paste it
         editor.input("p").unwrap();
-        assert_eq!(editor.cursor_line(), "wordword");
+        assert_eq!(editor.cursor_line(), "wwordord");
     })
     .await;
 
     cx.with(|editor| {
         // Now do an insert (different change)
         editor.input("i!<ESC>").unwrap();
-        assert_eq!(editor.cursor_line(), "word!word");
+        assert_eq!(editor.cursor_line(), "wwor!dord");
     })
     .await;
 
     cx.with(|editor| {
         // Dot should repeat the insert, not the paste
         editor.input(".").unwrap();
-        assert_eq!(editor.cursor_line(), "word!!word");
+        assert_eq!(editor.cursor_line(), "wwor!!dord");
     })
     .await;
 
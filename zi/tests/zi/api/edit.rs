@@ -195,6 +195,18 @@ async fn enter_normal_mode_on_last_line() {
     cx.cleanup().await;
 }
 
+#[tokio::test]
+async fn open_line_above_indents() {
+    let cx = new("fn f() {\n}\n").await;
+    cx.with(|editor| {
+        editor.input("jO").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    \n}\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 4));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
 #[tokio::test]
 async fn delete_last_line() {
     let cx = new("").await;
@@ -208,3 +220,20 @@ async fn delete_last_line() {
     .await;
     cx.cleanup().await;
 }
+
+#[tokio::test]
+async fn content_hash() {
+    let cx = new("abc\ndef\n").await;
+    cx.with(|editor| {
+        let buf = editor.buffer(zi::Active).id();
+        let before = editor.buffer(buf).content_hash();
+        assert_eq!(before, zi::ContentHash::new("abc\ndef\n"));
+
+        editor.edit(buf, &zi::deltas![1..2 => "xy", 5..5 => "z"]).unwrap();
+        let after = editor.buffer(buf).content_hash();
+        assert_ne!(before, after);
+        assert_eq!(after, zi::ContentHash::new("axyc\ndzef\n"));
+        assert_eq!(after, zi::ContentHash::new(editor.buffer(buf).text()));
+    })
+    .await;
+}
@@ -0,0 +1,55 @@
+use crate::new;
+
+#[tokio::test]
+async fn eval() -> zi::Result<()> {
+    let cx = new("abc\ndef\n").await;
+    cx.with(|editor| {
+        editor.set_cursor(zi::Active, (1, 2));
+        let eval = |src| editor.eval(src).map_err(|err| err.to_string());
+        assert_eq!(eval("line('.') . ':' . col('.') . '/' . col('$')"), Ok("2:3/4".into()));
+        assert_eq!(eval("toupper(getline(1)) . getline('$')"), Ok("ABCdef".into()));
+        assert_eq!(eval("line(3) + len(getline(3))"), Ok("0".into()));
+        assert_eq!(eval("nope(1)"), Err("unknown function: `nope`".into()));
+        assert_eq!(eval("line()"), Err("`line` expects 1 argument, got 0".into()));
+    })
+    .await;
+
+    // Arguments of the form `` `=expr` `` are replaced by their value
+    cx.with(|editor| editor.command_future("set tabstop `=line('$') * 3`")).await?.await?;
+    cx.with(|editor| editor.command_future("echo 'tabstop: ' . (2 + 4)")).await?.await?;
+    cx.with(|editor| {
+        assert_eq!(*editor.buffer(zi::Active).settings().tab_width.read(), 6);
+        assert_eq!(editor.messages().last().unwrap().text, "tabstop: 6");
+    })
+    .await;
+
+    // Whitespace within strings is kept
+    cx.with(|editor| editor.command_future("echo 'a  b'")).await?.await?;
+    cx.with(|editor| assert_eq!(editor.messages().last().unwrap().text, "a  b")).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn expression_register() {
+    let cx = new("a\nb\nc\n").await;
+    cx.with(|editor| {
+        editor.input("A<C-r>=line('$')*10<CR>!<ESC>").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a30!\nb\nc\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        // An empty expression reuses the previous one
+        editor.input("jA<C-r>=<CR><ESC>").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a30!\nb30\nc\n");
+
+        // Escaping the prompt returns to insert mode
+        editor.input("jA<C-r>=1<ESC>").unwrap();
+        assert_eq!(editor.mode(), zi::Mode::Insert);
+        editor.input("x<ESC>").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a30!\nb30\ncx\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+}
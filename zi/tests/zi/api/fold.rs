@@ -0,0 +1,106 @@
+use zi::{Active, FoldMethod, Mode, OpenFlags};
+
+use crate::new;
+
+#[tokio::test]
+async fn indent_folds() {
+    let cx = new("fn a() {\n    b;\n\n    if c {\n        d;\n    }\n}\ne\n").await;
+
+    cx.with(|editor| {
+        editor.buffer(Active).settings().fold_method.write(FoldMethod::Indent);
+        assert_eq!(editor.update_folds(Active), [0..6, 3..5]);
+        assert!(editor.closed_folds(Active).is_empty());
+
+        editor.set_cursor(Active, (4, 0));
+        editor.input("zc").unwrap();
+        assert_eq!(editor.closed_folds(Active), [3..5]);
+        assert_eq!(editor.cursor(Active), (3, 0));
+        assert_eq!(editor.cursor_viewport_coords().1, 3);
+
+        // Moving down skips over the closed fold.
+        editor.input("j").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 5);
+        assert_eq!(editor.cursor_viewport_coords().1, 4);
+        editor.input("k").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 3);
+
+        // Closing the enclosing fold hides the closed one inside it.
+        editor.input("zc").unwrap();
+        assert_eq!(editor.closed_folds(Active), [0..6]);
+        assert_eq!(editor.cursor(Active), (0, 0));
+        editor.input("j").unwrap();
+        assert_eq!(editor.cursor(Active).line(), 6);
+        assert_eq!(editor.cursor_viewport_coords().1, 1);
+        editor.input("k").unwrap();
+
+        editor.input("zo").unwrap();
+        assert_eq!(editor.closed_folds(Active), [3..5]);
+        editor.input("za").unwrap();
+        assert_eq!(editor.closed_folds(Active), [0..6]);
+
+        editor.input("zR").unwrap();
+        assert!(editor.closed_folds(Active).is_empty());
+        editor.input("zM").unwrap();
+        assert_eq!(editor.closed_folds(Active), [0..6]);
+    })
+    .await;
+
+    cx.render().await;
+
+    // The folds track edits to the buffer.
+    cx.with(|editor| {
+        editor.input("zR").unwrap();
+        editor.set_cursor(Active, (4, 0));
+        editor.input("zc").unwrap();
+        editor.set_cursor(Active, (0, 0));
+        editor.input("Ox<ESC>").unwrap();
+        assert_eq!(editor.closed_folds(Active), [4..6]);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn manual_folds() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let path = cx.tempfile("a\nb\nc\nd\ne\n")?;
+    let persisted = cx.tempfile("")?;
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+    cx.with({
+        let persisted = persisted.clone();
+        move |editor| {
+            editor.buffer(buf).settings().fold_method.write(FoldMethod::Indent);
+            assert!(editor.update_folds(buf).is_empty());
+
+            editor.set_cursor(Active, (1, 0));
+            editor.input("zfj").unwrap();
+            assert_eq!(editor.mode(), Mode::Normal);
+            assert_eq!(editor.closed_folds(buf), [1..3]);
+            assert_eq!(editor.update_folds(buf), [1..3]);
+            editor.input("zo").unwrap();
+
+            editor.set_cursor(Active, (3, 0));
+            editor.input("Vjzf").unwrap();
+            assert_eq!(editor.mode(), Mode::Normal);
+            assert_eq!(editor.closed_folds(buf), [3..5]);
+            editor.save_folds(&persisted)
+        }
+    })
+    .await?;
+
+    // Reopening the file in a new session restores the folds.
+    let cx2 = new("").await;
+    cx2.with(move |editor| editor.load_folds(&persisted)).await?;
+    let buf = cx2.open(&path, OpenFlags::empty()).await?;
+    cx2.with(move |editor| {
+        assert_eq!(editor.update_folds(buf), [1..3, 3..5]);
+        assert_eq!(editor.closed_folds(buf), [3..5]);
+    })
+    .await;
+
+    cx2.cleanup().await;
+    cx.cleanup().await;
+    Ok(())
+}
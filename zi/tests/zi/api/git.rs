@@ -0,0 +1,175 @@
+use zi::{Delta, Deltas, Hunk, HunkKind, OpenFlags};
+
+use crate::new;
+
+#[tokio::test]
+async fn git_hunks() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let dir = cx.tempdir()?;
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "a\nb\nc\nd\n")?;
+    duct::cmd!("git", "init", "-q").dir(&dir).run()?;
+    duct::cmd!("git", "add", "a.txt").dir(&dir).run()?;
+    std::fs::write(&path, "a\nc!\nd\ne\n")?;
+
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+    cx.with(move |editor| editor.refresh_git_diff(buf)).await.await?;
+    cx.with(move |editor| {
+        assert_eq!(
+            editor.git_hunks(buf),
+            [
+                Hunk { kind: HunkKind::Changed, base: 1..3, lines: 1..2 },
+                Hunk { kind: HunkKind::Added, base: 4..4, lines: 3..4 },
+            ]
+        );
+
+        let signs = editor.marks(buf, ..).filter_map(|(_, _, mark)| mark.sign()).count();
+        assert_eq!(signs, 2);
+
+        // Back to the index contents apart from the first line
+        editor.edit(buf, &Deltas::new([Delta::new(0..9, "b\nc\nd\n")])).unwrap();
+    })
+    .await;
+
+    cx.with(move |editor| editor.refresh_git_diff(buf)).await.await?;
+    cx.with(move |editor| {
+        assert_eq!(
+            editor.git_hunks(buf),
+            [Hunk { kind: HunkKind::Deleted, base: 0..1, lines: 0..0 }]
+        );
+    })
+    .await;
+
+    cx.render().await;
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn git_hunk_workflow() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let dir = cx.tempdir()?;
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "a\nb\nc\nd\n")?;
+    duct::cmd!("git", "init", "-q").dir(&dir).run()?;
+    duct::cmd!("git", "add", "a.txt").dir(&dir).run()?;
+    std::fs::write(&path, "a\nc!\nd\ne\n")?;
+
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+    cx.with(move |editor| editor.refresh_git_diff(buf)).await.await?;
+    cx.with(|editor| {
+        editor.input("]c").unwrap();
+        assert_eq!(editor.cursor(zi::Active).line(), 1);
+        editor.input("]c").unwrap();
+        assert_eq!(editor.cursor(zi::Active).line(), 3);
+        editor.input("[c").unwrap();
+        assert_eq!(editor.cursor(zi::Active).line(), 1);
+    })
+    .await;
+
+    cx.with(|editor| editor.stage_hunk(zi::Active)).await?.await?;
+    assert_eq!(duct::cmd!("git", "show", ":a.txt").dir(&dir).read()?, "a\nc!\nd");
+    cx.with(move |editor| {
+        assert_eq!(
+            editor.git_hunks(buf),
+            [Hunk { kind: HunkKind::Added, base: 3..3, lines: 3..4 }]
+        );
+
+        editor.input("Gdih").unwrap();
+        assert_eq!(editor[buf].text().to_string(), "a\nc!\nd\n");
+        editor.undo(buf).unwrap();
+
+        editor.set_cursor(zi::Active, zi::Point::new(3, 0));
+        editor.reset_hunk(zi::Active).unwrap();
+        assert_eq!(editor[buf].text().to_string(), "a\nc!\nd\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn git_hunks_crlf() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let dir = cx.tempdir()?;
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "a\r\nb\r\nc\r\n")?;
+    duct::cmd!("git", "init", "-q").dir(&dir).run()?;
+    duct::cmd!("git", "-c", "core.autocrlf=false", "add", "a.txt").dir(&dir).run()?;
+    std::fs::write(&path, "a\r\nb!\r\nc\r\n")?;
+
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+    cx.with(move |editor| editor.refresh_git_diff(buf)).await.await?;
+    cx.with(move |editor| {
+        // The buffer text has `\n` line endings, that alone is not a change.
+        assert_eq!(
+            editor.git_hunks(buf),
+            [Hunk { kind: HunkKind::Changed, base: 1..2, lines: 1..2 }]
+        );
+        editor.set_cursor(zi::Active, zi::Point::new(1, 0));
+    })
+    .await;
+
+    cx.with(|editor| editor.stage_hunk(zi::Active)).await?.await?;
+    let index = duct::cmd!("git", "show", ":a.txt").dir(&dir).stdout_capture().run()?.stdout;
+    assert_eq!(index, b"a\r\nb!\r\nc\r\n");
+    cx.with(move |editor| assert!(editor.git_hunks(buf).is_empty())).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn git_blame() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let dir = cx.tempdir()?;
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "a\nb\n")?;
+    duct::cmd!("git", "init", "-q").dir(&dir).run()?;
+    duct::cmd!("git", "add", "a.txt").dir(&dir).run()?;
+    duct::cmd!(
+        "git",
+        "-c",
+        "user.name=zi",
+        "-c",
+        "user.email=zi@example.com",
+        "commit",
+        "-qm",
+        "msg"
+    )
+    .dir(&dir)
+    .run()?;
+
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+    cx.with(|editor| assert!(editor.request_git_blame(zi::Active).is_none())).await;
+
+    cx.with(move |editor| editor.toggle_git_blame(buf)).await;
+    cx.with(|editor| editor.request_git_blame(zi::Active).unwrap()).await.await?;
+    cx.with(move |editor| {
+        let blame = editor.git_blame(buf, 0).unwrap();
+        assert!(blame.starts_with("zi, "), "{blame}");
+        assert!(blame.ends_with("msg"), "{blame}");
+        // Already blamed
+        assert!(editor.request_git_blame(zi::Active).is_none());
+
+        editor.edit(buf, &Deltas::new([Delta::new(0..1, "c")])).unwrap();
+        assert!(editor.git_blame(buf, 0).is_none());
+    })
+    .await;
+
+    cx.with(|editor| editor.request_git_blame(zi::Active).unwrap()).await.await?;
+    cx.with(move |editor| {
+        assert_eq!(editor.git_blame(buf, 0), Some("Not committed yet"));
+        assert!(editor.git_blame(buf, 1).unwrap().ends_with("msg"));
+    })
+    .await;
+
+    cx.render().await;
+    cx.cleanup().await;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::new;
+
+#[tokio::test]
+async fn global_delete() -> zi::Result<()> {
+    let cx = new("a1\nb\na2\na3\nc\n").await;
+    cx.with(|editor| editor.command_future("g/^a/d")).await?.await?;
+    cx.with(|editor| {
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "b\nc\n");
+        // The whole `:global` is undone at once
+        editor.input("u").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a1\nb\na2\na3\nc\n");
+    })
+    .await;
+
+    cx.with(|editor| editor.command_future("v/a/d")).await?.await?;
+    cx.with(|editor| assert_eq!(editor.buffer(zi::Active).text().to_string(), "a1\na2\na3\n"))
+        .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn global_skips_deleted_lines() -> zi::Result<()> {
+    let cx = new("a1\nb\na2\na3\nc\n").await;
+    // `a3` is deleted along with `a2` so it's skipped
+    cx.with(|editor| editor.command_future("g/^a/.,+1d")).await?.await?;
+    cx.with(|editor| assert_eq!(editor.buffer(zi::Active).text().to_string(), "c\n")).await;
+
+    let err = cx.with(|editor| editor.command_future("g/x/d").err()).await;
+    assert_eq!(err.unwrap().to_string(), "pattern not found: `x`");
+
+    cx.cleanup().await;
+    Ok(())
+}
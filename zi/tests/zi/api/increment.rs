@@ -0,0 +1,43 @@
+use crate::new;
+
+#[tokio::test]
+async fn increment() {
+    let cx = new("x = 9\n0x0f -3\n").await;
+    cx.with(|editor| {
+        editor.input("<C-a>").unwrap();
+        assert_eq!(editor.text(zi::Active), "x = 10\n0x0f -3\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 5));
+
+        editor.input("5<C-x>").unwrap();
+        assert_eq!(editor.text(zi::Active), "x = 5\n0x0f -3\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 4));
+
+        editor.input("j<C-a>").unwrap();
+        assert_eq!(editor.text(zi::Active), "x = 5\n0x0f -2\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 6));
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "x = 5\n0x0f -3\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn visual_increment() {
+    let cx = new("1\n1\n1\n").await;
+    cx.with(|editor| {
+        editor.input("VG<C-a>").unwrap();
+        assert_eq!(editor.text(zi::Active), "2\n2\n2\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+        assert_eq!(editor.cursor(zi::Active), (0, 0));
+
+        editor.input("Vjjg<C-a>").unwrap();
+        assert_eq!(editor.text(zi::Active), "3\n4\n5\n");
+
+        editor.input("Vj2g<C-x>").unwrap();
+        assert_eq!(editor.text(zi::Active), "1\n0\n5\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
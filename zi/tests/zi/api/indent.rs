@@ -0,0 +1,45 @@
+use zi::buffer::IndentSettings;
+
+use crate::new;
+
+#[tokio::test]
+async fn reindent_operator() {
+    let cx = new("fn f() {\nfoo\n        bar\n  \n}\n").await;
+    cx.with(|editor| {
+        editor.input("=G").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    foo\n    bar\n\n}\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 0));
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\nfoo\n        bar\n  \n}\n");
+
+        editor.input("ggj==").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    foo\n        bar\n  \n}\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 4));
+
+        editor.input("jVj=").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    foo\n    bar\n\n}\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn retab() {
+    let cx = new("a\n\tb\n  \t c\n").await;
+    cx.with(|editor| editor.execute("retab").unwrap()).await;
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "a\n    b\n     c\n");
+        editor.execute("set expandtab false").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        assert_eq!(*editor.buffer(zi::Active).settings().indent.read(), IndentSettings::Tabs);
+        editor.execute("2,2retab").unwrap();
+    })
+    .await;
+    cx.with(|editor| assert_eq!(editor.text(zi::Active), "a\n\tb\n     c\n")).await;
+    cx.cleanup().await;
+}
@@ -0,0 +1,62 @@
+use zi::{CommentTokens, LanguageConfig};
+
+use crate::new;
+
+#[tokio::test]
+async fn join_lines() {
+    let cx = new("foo\n    bar\n\nbaz)\n)\n").await;
+    cx.with(|editor| {
+        editor.input("J").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar\n\nbaz)\n)\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+
+        editor.input("3J").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar baz)\n)\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 7));
+
+        editor.input("J").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar baz))\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 12));
+
+        // Joining is a single change
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar baz)\n)\n");
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "foo bar\n\nbaz)\n)\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn join_lines_raw() {
+    let cx = new("a\n  b\nc\n").await;
+    cx.with(|editor| {
+        editor.input("gJ").unwrap();
+        assert_eq!(editor.text(zi::Active), "a  b\nc\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 1));
+
+        editor.input("VjJ").unwrap();
+        assert_eq!(editor.text(zi::Active), "a  b c\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn join_comments() {
+    let cx = new("    // foo\n    // bar\n").await;
+    cx.with(|editor| {
+        let ft = editor.buffer(zi::Active).file_type();
+        editor.language_config_mut().add_language(
+            ft,
+            LanguageConfig::default().with_comment_tokens(CommentTokens::line("//")),
+        );
+
+        editor.input("J").unwrap();
+        assert_eq!(editor.text(zi::Active), "    // foo bar\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
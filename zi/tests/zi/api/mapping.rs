@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use zi::Mode;
+
+use crate::new;
+
+#[tokio::test]
+async fn user_mappings() {
+    let cx = new("abc\ndef\nghi\n").await;
+    cx.with(|editor| {
+        editor.execute("nnoremap <leader>d dd").unwrap();
+        editor.execute("inoremap jk <Esc>").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("\\d").unwrap();
+        assert_eq!(editor.text(zi::Active), "def\nghi\n");
+
+        editor.input("ixjkx").unwrap();
+        assert_eq!(editor.mode(), Mode::Normal);
+        assert_eq!(editor.text(zi::Active), "def\nghi\n");
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.execute("set mapleader <space>").unwrap();
+        editor.execute("nnoremap x dd").unwrap();
+        editor.execute("nnoremap Y x").unwrap();
+        editor.execute("nmap Z x").unwrap();
+    })
+    .await;
+
+    // `<leader>` is expanded when the mapping is defined.
+    cx.with(|editor| editor.execute("nnoremap <leader>D x").unwrap()).await;
+
+    cx.with(|editor| {
+        // Non-recursive mappings use the builtin `x`, recursive ones the user's.
+        editor.input("Y").unwrap();
+        assert_eq!(editor.text(zi::Active), "ef\nghi\n");
+        editor.input("<space>D").unwrap();
+        assert_eq!(editor.text(zi::Active), "f\nghi\n");
+        editor.input("Z").unwrap();
+        assert_eq!(editor.text(zi::Active), "ghi\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn recursive_mapping() {
+    let cx = new("abc\n").await;
+    cx.with(|editor| editor.execute("nmap x lx").unwrap()).await;
+    cx.with(|editor| {
+        editor.input("x").unwrap();
+        assert_eq!(editor.text(zi::Active), "abc\n");
+        assert_eq!(editor.mode(), Mode::Normal);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn exponential_recursive_mapping() {
+    let cx = new("abc\n").await;
+    cx.with(|editor| editor.execute("nmap a aa").unwrap()).await;
+    cx.with(|editor| {
+        // Each expansion doubles the keys, this must not expand all 2^100 of them.
+        editor.input("a").unwrap();
+        assert_eq!(editor.text(zi::Active), "abc\n");
+        assert_eq!(editor.mode(), Mode::Normal);
+        assert_eq!(editor.messages().last().unwrap().text, "recursive mapping");
+
+        // The keys work as usual afterwards.
+        editor.input("ggx").unwrap();
+        assert_eq!(editor.text(zi::Active), "bc\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn mapping_timeout() {
+    let cx = new("abc\n").await;
+    cx.with(|editor| {
+        editor.execute("set timeoutlen 10").unwrap();
+        editor.execute("inoremap j J").unwrap();
+        editor.execute("inoremap jk <Esc>").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("ij").unwrap();
+        // `j` is also the start of `jk` so it's not used until the timeout elapses.
+        assert_eq!(editor.text(zi::Active), "abc\n");
+    })
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "Jabc\n");
+        editor.input("jk").unwrap();
+        assert_eq!(editor.mode(), Mode::Normal);
+
+        // A key that doesn't continue the longer mapping uses the shorter one immediately.
+        editor.input("ijx").unwrap();
+        assert_eq!(editor.text(zi::Active), "JxJabc\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn mapping_timeout_simulated() {
+    let mut driver = new("abc\n").with_size((20, 4)).driver();
+    driver
+        .with(|editor| {
+            editor.execute("set timeoutlen 100").unwrap();
+            editor.execute("inoremap j J").unwrap();
+            editor.execute("inoremap jk <Esc>").unwrap();
+        })
+        .await;
+
+    driver.input("ij").await;
+    driver.wait(Duration::from_millis(99)).await;
+    driver.with(|editor| assert_eq!(editor.text(zi::Active), "abc\n")).await;
+    driver.capture("pending").await;
+
+    driver.wait(Duration::from_millis(1)).await;
+    driver.with(|editor| assert_eq!(editor.text(zi::Active), "Jabc\n")).await;
+    driver.capture("timed out").await;
+
+    driver.resize(12, 3).await;
+    driver.capture("resized").await;
+
+    let [(_, pending), (_, timed_out), (_, resized)] = driver.frames() else { unreachable!() };
+    assert_ne!(pending, timed_out);
+    assert_eq!(resized.lines().count(), 3);
+    driver.cleanup().await;
+}
+
+#[tokio::test]
+async fn key_hints() {
+    let cx = new("abc\n").await;
+    cx.with(|editor| {
+        editor.execute("set keyhintsdelay 10").unwrap();
+        editor.execute("nnoremap <space>x dd").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("<space>").unwrap();
+        assert!(editor.key_hints().is_empty(), "hints are only shown after a delay");
+    })
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cx.with(|editor| {
+        let hints = editor
+            .key_hints()
+            .iter()
+            .map(|(key, desc)| format!("{key} {desc}"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hints,
+            [
+                "/ open global search",
+                "b open buffer picker",
+                "e open file explorer",
+                "f open file picker here",
+                "j open jump list",
+                "l open diagnostics",
+                "m open marks",
+                "o open file picker",
+                "x dd",
+            ]
+        );
+
+        editor.input("<Esc>").unwrap();
+        assert!(editor.key_hints().is_empty());
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn command_mappings() {
+    let cx = new("abc\ndef\n").await;
+    cx.with(|editor| {
+        editor.map_command(&[Mode::Normal], "Q", "d").unwrap();
+        // Builtin sequences, their prefixes and sequences they are a prefix of all conflict.
+        for lhs in ["dd", "g", "x<space>"] {
+            assert!(editor.map_command(&[Mode::Normal], lhs, "d").is_err(), "{lhs}");
+        }
+        editor.input("Q").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "def\n");
+        editor.unmap(&[Mode::Normal], "Q").unwrap();
+        editor.input("Q").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| assert_eq!(editor.text(zi::Active), "def\n")).await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn sub_mode() {
+    let cx = new("abc\ndef\nghi\n").await;
+    cx.with(|editor| {
+        assert!(editor.enter_sub_mode("lines").is_err());
+        editor.map_sub_mode("lines", "x", "d").unwrap();
+        editor.enter_sub_mode("lines").unwrap();
+        assert_eq!(editor.sub_mode(), Some("lines"));
+        editor.input("x").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "def\nghi\n");
+        assert_eq!(editor.sub_mode(), Some("lines"));
+        editor.input("x").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        assert_eq!(editor.text(zi::Active), "ghi\n");
+        // A key that isn't mapped leaves the sub-mode and is handled as usual.
+        editor.input("i").unwrap();
+        assert_eq!(editor.sub_mode(), None);
+        assert_eq!(editor.mode(), Mode::Insert);
+        editor.input("<Esc>").unwrap();
+
+        // `<Esc>` only leaves the sub-mode.
+        editor.enter_sub_mode("lines").unwrap();
+        editor.input("<Esc>").unwrap();
+        assert_eq!(editor.sub_mode(), None);
+        assert_eq!(editor.mode(), Mode::Normal);
+
+        editor.unmap_sub_mode("lines", "x").unwrap();
+        assert!(editor.enter_sub_mode("lines").is_err());
+    })
+    .await;
+    cx.cleanup().await;
+}
@@ -33,3 +33,30 @@ async fn marks() {
 
     cx.cleanup().await;
 }
+
+#[tokio::test]
+async fn mark_ids_are_not_reused() {
+    let cx = new("abc\ndef\n").await;
+
+    cx.with(|editor| {
+        let ns = editor.default_namespace();
+        let a = editor.create_mark(zi::Active, ns, zi::Mark::builder(2));
+        editor.delete_mark(zi::Active, ns, a);
+
+        // Deleting the mark again doesn't delete the mark created after it.
+        let b = editor.create_mark(zi::Active, ns, zi::Mark::builder(4));
+        assert_ne!(a, b);
+        editor.delete_mark(zi::Active, ns, a);
+        check_marks_eq(editor.marks(zi::Active, ..), [(ns, 4..4, b)]);
+
+        // Creating and deleting marks doesn't run out of ids.
+        for _ in 0..1 << 16 {
+            let id = editor.create_mark(zi::Active, ns, zi::Mark::builder(0));
+            editor.delete_mark(zi::Active, ns, id);
+        }
+        check_marks_eq(editor.marks(zi::Active, ..), [(ns, 4..4, b)]);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,46 @@
+use zi::{Active, Message, MessageLevel};
+
+use crate::new;
+
+#[tokio::test]
+async fn message_log() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        editor.set_error("first");
+        editor.log(MessageLevel::Info, "second");
+        assert_eq!(editor.get_error(), Some("first"));
+
+        let messages = editor.messages().cloned().collect::<Vec<_>>();
+        assert_eq!(messages, [
+            Message { level: MessageLevel::Error, text: "first".into() },
+            Message { level: MessageLevel::Info, text: "second".into() },
+        ]);
+
+        editor.open_messages();
+        assert_eq!(editor.text(Active), "first\nsecond\n");
+        assert_eq!(editor.cursor(Active), (1, 0));
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn message_history() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        editor.settings().message_history.write(2);
+        for i in 0..5 {
+            editor.notify(MessageLevel::Warning, i);
+        }
+        assert_eq!(editor.get_error(), Some("4"));
+        let texts = editor.messages().map(|message| message.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["3", "4"]);
+
+        editor.clear_messages();
+        assert_eq!(editor.messages().len(), 0);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,68 @@
+use zi::Constraint::*;
+use zi::Direction::*;
+use zi::input::{Event, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::new;
+
+fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent::new(kind, column, row))
+}
+
+#[tokio::test]
+async fn mouse_click_and_drag() {
+    let cx = new("abc\ndef\nghi\n").await;
+    cx.with(|editor| {
+        editor.handle_input(mouse(MouseEventKind::Down(MouseButton::Left), 2, 1));
+        editor.handle_input(mouse(MouseEventKind::Up(MouseButton::Left), 2, 1));
+        assert_eq!(editor.cursor(zi::Active), (1, 2));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.handle_input(mouse(MouseEventKind::Down(MouseButton::Left), 2, 1));
+        editor.handle_input(mouse(MouseEventKind::Drag(MouseButton::Left), 1, 2));
+        assert_eq!(editor.mode(), zi::Mode::Visual);
+        assert_eq!(editor.cursor(zi::Active), (2, 1));
+
+        editor.input("y").unwrap();
+        assert_eq!(editor.register('"').unwrap().content, "f\ngh");
+
+        // Clicking past the end of a line moves to its last character
+        editor.handle_input(mouse(MouseEventKind::Down(MouseButton::Left), 50, 0));
+        assert_eq!(editor.cursor(zi::Active), (0, 2));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn mouse_click_focuses_view() {
+    let cx = new("abc\n").await;
+    cx.with(|editor| {
+        let left = editor.view(zi::Active).id();
+        let right = editor.split(zi::Active, Right, Fill(1));
+        assert_eq!(editor.view(zi::Active).id(), right);
+
+        editor.handle_input(mouse(MouseEventKind::Down(MouseButton::Left), 1, 0));
+        assert_eq!(editor.view(zi::Active).id(), left);
+        assert_eq!(editor.cursor(zi::Active), (0, 1));
+
+        editor.handle_input(mouse(MouseEventKind::Down(MouseButton::Left), 78, 0));
+        assert_eq!(editor.view(zi::Active).id(), right);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn mouse_scroll() {
+    let cx = new("x\n".repeat(100)).await;
+    cx.with(|editor| {
+        editor.input("gg").unwrap();
+        editor.handle_input(mouse(MouseEventKind::ScrollDown, 0, 0));
+        assert_eq!(editor.view(zi::Active).offset().line, 3);
+
+        editor.handle_input(mouse(MouseEventKind::ScrollUp, 0, 0));
+        assert_eq!(editor.view(zi::Active).offset().line, 0);
+    })
+    .await;
+    cx.cleanup().await;
+}
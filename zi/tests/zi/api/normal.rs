@@ -0,0 +1,40 @@
+use crate::new;
+
+#[tokio::test]
+async fn normal() -> zi::Result<()> {
+    let cx = new("a\nb\nc\n").await;
+    cx.with(|editor| editor.command_future("%normal A;")).await?.await?;
+    cx.with(|editor| {
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a;\nb;\nc;\n");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+        // The whole `:normal` is undone at once
+        editor.input("u").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "a\nb\nc\n");
+
+        // Spaces are typed as is and the incomplete `d` is aborted
+        editor.set_cursor(zi::Active, (0, 0));
+        editor.normal(None, "Ix y<Esc>d", true).unwrap();
+        editor.input("x").unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "x a\nb\nc\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn normal_noremap() -> zi::Result<()> {
+    let cx = new("a\nb\nc\n").await;
+    cx.with(|editor| {
+        editor.map(&[zi::Mode::Normal], "x", "dd", false).unwrap();
+        editor.normal(None, "x", false).unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "\nb\nc\n");
+        editor.normal(None, "x", true).unwrap();
+        assert_eq!(editor.buffer(zi::Active).text().to_string(), "b\nc\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use std::env;
 
+use zi::buffer::IndentSettings;
+
 use crate::new;
 
 #[tokio::test]
@@ -69,3 +71,153 @@ async fn test_open_focus() -> zi::Result<()> {
     cx.cleanup().await;
     Ok(())
 }
+
+#[tokio::test]
+async fn oldfiles() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let a = cx.tempfile("a")?;
+    let b = cx.tempfile("b")?;
+    let persisted = cx.tempfile("")?;
+    cx.open(&a, zi::OpenFlags::empty()).await?;
+    cx.open(&b, zi::OpenFlags::empty()).await?;
+    // Background opens (e.g. previews) are not recorded
+    cx.open(&persisted, zi::OpenFlags::BACKGROUND).await?;
+
+    let expected = vec![b.canonicalize()?, a.canonicalize()?];
+    cx.with({
+        let expected = expected.clone();
+        let persisted = persisted.clone();
+        move |editor| {
+            assert_eq!(
+                editor.oldfiles().map(|path| path.to_path_buf()).collect::<Vec<_>>(),
+                expected
+            );
+            editor.save_oldfiles(&persisted)
+        }
+    })
+    .await?;
+
+    let cx2 = new("").await;
+    let c = cx2.tempfile("c")?;
+    cx2.open(&c, zi::OpenFlags::empty()).await?;
+    let expected = [vec![c.canonicalize()?], expected].concat();
+    cx2.with(move |editor| {
+        editor.load_oldfiles(&persisted)?;
+        assert_eq!(editor.oldfiles().map(|path| path.to_path_buf()).collect::<Vec<_>>(), expected);
+        editor.execute("browse oldfiles")
+    })
+    .await?;
+
+    cx2.cleanup().await;
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn editorconfig() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    std::fs::create_dir(dir.join("sub"))?;
+    std::fs::write(
+        dir.join(".editorconfig"),
+        "root = true\n\n[*]\nindent_style = tab\ntab_width = 8\n\n[*.md]\ntrim_trailing_whitespace = false\n",
+    )?;
+    std::fs::write(
+        dir.join("sub/.editorconfig"),
+        "[*.rs]\nindent_style = space\nindent_size = 2\nend_of_line = crlf\nmax_line_length = 100\n",
+    )?;
+
+    let rs = cx.open(dir.join("sub/a.rs"), zi::OpenFlags::empty()).await?;
+    let md = cx.open(dir.join("b.md"), zi::OpenFlags::empty()).await?;
+    cx.with(move |editor| {
+        let settings = editor.buffer(rs).settings();
+        assert_eq!(*settings.indent.read(), IndentSettings::Spaces(2));
+        assert_eq!(*settings.tab_width.read(), 8);
+        assert_eq!(*settings.line_ending.read(), zi::LineEnding::CrLf);
+        assert_eq!(*settings.max_line_length.read(), Some(100));
+        assert!(*settings.trim_trailing_whitespace.read());
+
+        let settings = editor.buffer(md).settings();
+        assert_eq!(*settings.indent.read(), IndentSettings::Tabs);
+        assert!(!*settings.trim_trailing_whitespace.read());
+        assert_eq!(*settings.max_line_length.read(), None);
+
+        // Settings changed after opening are not overridden by opening the buffer again.
+        settings.tab_width.write(4);
+    })
+    .await;
+
+    cx.open(dir.join("b.md"), zi::OpenFlags::empty()).await?;
+    cx.with(move |editor| assert_eq!(*editor.buffer(md).settings().tab_width.read(), 4)).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn reload_in_place() -> zi::Result<()> {
+    let cx = new("").await;
+    let path = cx.tempfile("abc\ndef\n")?;
+    let buf = cx.open(&path, zi::OpenFlags::empty()).await?;
+
+    let mark = cx
+        .with(move |editor| {
+            let ns = editor.default_namespace();
+            editor.create_mark(buf, ns, zi::Mark::builder(4).width(3))
+        })
+        .await;
+
+    std::fs::write(&path, "xbc\ndef\nghi\n")?;
+    cx.open(&path, zi::OpenFlags::FORCE).await?;
+    cx.with(move |editor| {
+        assert_eq!(editor[buf].text().to_string(), "xbc\ndef\nghi\n");
+        assert!(!editor[buf].flags().contains(zi::BufferFlags::DIRTY));
+        let marks = editor.marks(buf, ..).map(|(_, range, m)| (range, m.id())).collect::<Vec<_>>();
+        assert_eq!(marks, [(4..7, mark)]);
+
+        // The reload can be undone.
+        assert!(editor.undo(buf).unwrap());
+        assert_eq!(editor[buf].text().to_string(), "abc\ndef\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn make_writable() -> zi::Result<()> {
+    let cx = new("").await;
+    let path = cx.tempfile("abc\r\ndef\r\n")?;
+    let buf = cx.open(&path, zi::OpenFlags::READONLY).await?;
+
+    cx.with(move |editor| {
+        let ns = editor.default_namespace();
+        let mark = editor.create_mark(buf, ns, zi::Mark::builder(5).width(3));
+        editor.set_cursor(zi::Active, (1, 1));
+
+        editor.make_writable(buf).unwrap();
+        assert_eq!(editor.buffer(zi::Active).id(), buf);
+        assert!(!editor[buf].flags().contains(zi::BufferFlags::READONLY));
+        assert!(!editor[buf].flags().contains(zi::BufferFlags::DIRTY));
+        assert_eq!(*editor[buf].settings().line_ending.read(), zi::LineEnding::CrLf);
+
+        // The line endings are normalized with the marks and cursor kept in place.
+        assert_eq!(editor[buf].text().to_string(), "abc\ndef\n");
+        let marks = editor.marks(buf, ..).map(|(_, range, m)| (range, m.id())).collect::<Vec<_>>();
+        assert_eq!(marks, [(4..7, mark)]);
+        assert_eq!(editor.cursor(zi::Active), (1, 1));
+
+        editor.insert(zi::Active, "x").unwrap();
+        assert_eq!(editor[buf].text().to_string(), "abc\ndxef\n");
+        assert!(editor[buf].flags().contains(zi::BufferFlags::DIRTY));
+
+        // Already writable buffers are left alone.
+        editor.make_writable(buf).unwrap();
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
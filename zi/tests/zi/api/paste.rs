@@ -0,0 +1,138 @@
+use crate::new;
+
+#[tokio::test]
+async fn paste_charwise() {
+    let cx = new("ab\n").await;
+    cx.with(|editor| {
+        editor.input("yl3p").unwrap();
+        assert_eq!(editor.text(zi::Active), "aaaab\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+
+        editor.input("P").unwrap();
+        assert_eq!(editor.text(zi::Active), "aaaaab\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn paste_linewise() {
+    let cx = new("a\nb\n").await;
+    cx.with(|editor| {
+        editor.input("yyp").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\na\nb\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 0));
+
+        editor.input("2P").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\na\na\na\nb\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 0));
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\na\nb\n");
+
+        // Deleted lines are put too
+        editor.input("ggjjddggP").unwrap();
+        assert_eq!(editor.text(zi::Active), "b\na\na\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn paste_linewise_without_final_newline() {
+    let cx = new("a\nb").await;
+    cx.with(|editor| {
+        editor.input("jyyp").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\nb\nb");
+        assert_eq!(editor.cursor(zi::Active), (2, 0));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn paste_blockwise() {
+    let cx = new("abc\ndef\nghi\n").await;
+    cx.with(|editor| {
+        editor.input("<C-v>jly").unwrap();
+        assert_eq!(editor.register('"').unwrap().kind, zi::RegisterKind::Blockwise);
+
+        editor.input("jp").unwrap();
+        assert_eq!(editor.text(zi::Active), "abc\ndabef\ngdehi\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 1));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn paste_indented() {
+    let cx = new("fn f() {\n    a\n}\n").await;
+    cx.with(|editor| {
+        editor.input("yyj]p").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    a\n    fn f() {\n}\n");
+        assert_eq!(editor.cursor(zi::Active), (2, 4));
+
+        editor.input("j[p").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn f() {\n    a\n    fn f() {\nfn f() {\n}\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn bracketed_paste() {
+    let cx = new("ab\n").await;
+    cx.with(|editor| {
+        editor.handle_input(zi::input::Event::Paste("x\r\ny".into()));
+        assert_eq!(editor.text(zi::Active), "ax\nyb\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 1));
+
+        // Inserted literally without indentation or mappings
+        editor.input("jA").unwrap();
+        editor.handle_input(zi::input::Event::Paste("\n  fn<ESC>\n\tjk".into()));
+        assert_eq!(editor.text(zi::Active), "ax\nyb\n  fn<ESC>\n\tjk\n");
+        assert_eq!(editor.mode(), zi::Mode::Insert);
+        assert_eq!(editor.cursor(zi::Active), (3, 3));
+
+        editor.input("<ESC>u").unwrap();
+        assert_eq!(editor.text(zi::Active), "ax\nyb\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn composed_text() {
+    use zi::input::{Event, KeyCode, KeyEvent, KeyModifiers, compose};
+
+    let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    let text = |s: &str| Event::Text(s.into());
+    // Runs of keys including a non-ASCII character are merged
+    assert_eq!(
+        compose([key('e'), key('\u{301}'), Event::Resize(80, 10), key('a'), key('b'), key('é')]),
+        [text("e\u{301}"), Event::Resize(80, 10), text("abé")]
+    );
+    assert_eq!(compose([key('a'), key('b')]), [key('a'), key('b')]);
+    assert_eq!(compose([key('é')]), [key('é')]);
+
+    let cx = new("ab\n").await;
+    cx.with(move |editor| {
+        editor.input("i").unwrap();
+        editor.handle_input(text("日本"));
+        assert_eq!(editor.text(zi::Active), "日本ab\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 6));
+
+        editor.input("<ESC>.").unwrap();
+        assert_eq!(editor.text(zi::Active), "日日本本ab\n");
+
+        // Typed as keys outside of insert mode or with a pending key sequence
+        editor.handle_input(text("xA"));
+        editor.input("f").unwrap();
+        editor.handle_input(text("é"));
+        assert_eq!(editor.text(zi::Active), "日日本abfé\n");
+    })
+    .await;
+    cx.cleanup().await;
+}
@@ -57,3 +57,68 @@ async fn picker_input() {
     .await;
     cx.cleanup().await;
 }
+
+#[tokio::test]
+async fn quickfix() -> zi::Result<()> {
+    let cx = new("").await;
+    let path = cx.tempfile("abc\ndef\n").unwrap();
+    cx.with(move |editor| {
+        assert!(editor.quickfix().is_empty());
+        assert!(editor.quickfix_next().is_err(), "the quickfix list is empty");
+
+        let entries = [(0, "abc"), (1, "def")].map(|(line, text)| zi::QuickfixEntry {
+            path: path.clone(),
+            point: Some(zi::Point::new(line, 1).into()),
+            range: Some(zi::PointRange::new((line, 1), (line, 3)).into()),
+            text: text.to_string(),
+        });
+        editor.set_quickfix(entries.clone());
+        assert_eq!(editor.quickfix(), &entries);
+
+        editor.execute("copen").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        assert_eq!(editor.views().count(), 4, "quickfix list should be shown in a picker");
+        editor.close_view(zi::Active);
+        assert_eq!(editor.views().count(), 1);
+    })
+    .await;
+
+    // The list starts at the first entry, so the first `cnext` jumps to the second one.
+    for line in [1, 0, 1] {
+        cx.with(|editor| editor.quickfix_next()).await?.await?;
+        cx.with(move |editor| {
+            assert_eq!(editor.text(zi::Active), "abc\ndef\n");
+            assert_eq!(editor.cursor(zi::Active), (line, 1));
+        })
+        .await;
+    }
+
+    cx.with(|editor| editor.quickfix_prev()).await?.await?;
+    cx.with(|editor| assert_eq!(editor.cursor(zi::Active), (0, 1))).await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn item_picker() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        let picker = editor.open_item_picker("items").unwrap();
+        assert_eq!(editor.views().count(), 4);
+        assert!(editor.open_item_picker("items").is_err(), "the picker is already open");
+
+        let items = [(1, "foo"), (2, "bar")]
+            .map(|(id, text)| zi::PickerItem { id, text: text.to_string() });
+        editor.push_picker_items(picker, items).unwrap();
+
+        editor.close_view(zi::Active);
+        assert_eq!(editor.views().count(), 1);
+        assert!(editor.push_picker_items(picker, []).is_err(), "the picker is closed");
+    })
+    .await;
+    cx.cleanup().await;
+}
@@ -0,0 +1,48 @@
+use zi::Constraint::*;
+use zi::Direction::*;
+
+use crate::new;
+
+#[tokio::test]
+async fn quit_dirty() -> zi::Result<()> {
+    let cx = new("").await;
+    let buf = cx.open_tmp("abc", zi::OpenFlags::empty()).await?;
+    cx.with(move |editor| {
+        editor.edit(buf, &zi::Deltas::insert_at(0, "x".to_string())).unwrap();
+        assert_eq!(editor.dirty_buffers().collect::<Vec<_>>(), [buf]);
+
+        // Closing a view that doesn't quit the editor can't lose any changes.
+        let right = editor.split(zi::Active, Right, Fill(1));
+        editor.close_window(right).unwrap();
+        assert_eq!(editor.views().count(), 1);
+
+        let err = editor.close_window(zi::Active).unwrap_err();
+        assert_eq!(err.to_string(), "cannot close the last window");
+
+        let err = editor.quit(zi::Active, false).unwrap_err();
+        assert!(err.to_string().ends_with("has unsaved changes (add ! to override)"), "{err}");
+        assert!(editor.quit_all(false).is_err());
+        assert_eq!(editor.views().count(), 1);
+
+        editor.quit_all(true).unwrap();
+        assert_eq!(editor.views().count(), 0);
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn quit_split() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        editor.edit(zi::Active, &zi::Deltas::insert_at(0, "x".to_string())).unwrap();
+        editor.split(zi::Active, Right, Fill(1));
+        editor.quit(zi::Active, false).unwrap();
+        assert_eq!(editor.views().count(), 1);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,48 @@
+use zi::{ClipboardSelection, TerminalRequest};
+
+use crate::new;
+
+#[tokio::test]
+async fn clipboard_registers() {
+    let cx = new("a\nb\n").await;
+    cx.with(|editor| {
+        // These work without a system clipboard by falling back to the stored register.
+        editor.input("\"+yyj\"+p").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\nb\na\n");
+        assert_eq!(editor.register('+').unwrap().kind, zi::RegisterKind::Linewise);
+        assert_eq!(editor.register('"').unwrap().content, "a\n");
+
+        // The register only applies to the next command.
+        editor.input("\"*ylggyyjj\"\"p").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\nb\na\na\n");
+        assert_eq!(editor.register('*').unwrap().content, "a");
+
+        let capabilities = zi::Capabilities { osc52: true, ..*editor.capabilities() };
+        editor.set_capabilities(capabilities);
+        editor.take_terminal_requests();
+        editor.input("gg\"*yy").unwrap();
+        // Only set via OSC 52 if there is no other clipboard provider.
+        assert!(editor.take_terminal_requests().iter().all(|request| matches!(
+            request,
+            TerminalRequest::SetClipboard(ClipboardSelection::Primary, text) if text == "a\n"
+        )));
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn clipboard_setting() {
+    let cx = new("a\n").await;
+    cx.with(|editor| editor.execute("set clipboard unnamedplus").unwrap()).await;
+    cx.with(|editor| {
+        assert_eq!(*editor.settings().clipboard.read(), Some(ClipboardSelection::Clipboard));
+        editor.input("yyp").unwrap();
+        assert_eq!(editor.text(zi::Active), "a\na\n");
+    })
+    .await;
+
+    cx.with(|editor| editor.execute("set clipboard none").unwrap()).await;
+    cx.with(|editor| assert_eq!(*editor.settings().clipboard.read(), None)).await;
+    cx.cleanup().await;
+}
@@ -0,0 +1,33 @@
+use zi::TerminalRequest;
+
+use crate::new;
+
+#[tokio::test]
+async fn report_title_and_cwd() {
+    let cx = new("abc").await;
+    cx.with(|editor| {
+        assert!(editor.take_terminal_requests().is_empty(), "nothing is reported by default");
+
+        editor.execute("set title true").unwrap();
+        editor.execute("set reportcwd true").unwrap();
+        let title = editor.terminal_title();
+        assert!(title.ends_with("(NORMAL) - zi"), "{title}");
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(editor.take_terminal_requests(), [
+            TerminalRequest::SetTitle(title),
+            TerminalRequest::SetCwd(cwd)
+        ]);
+        // Only changes are reported.
+        assert!(editor.take_terminal_requests().is_empty());
+
+        editor.input("ix").unwrap();
+        let requests = editor.take_terminal_requests();
+        let [TerminalRequest::SetTitle(title)] = &requests[..] else { panic!("{requests:?}") };
+        assert!(title.ends_with("[+] (INSERT) - zi"), "{title}");
+
+        assert!(editor.change_dir("/nonexistent/zi").is_err());
+    })
+    .await;
+
+    cx.cleanup().await;
+}
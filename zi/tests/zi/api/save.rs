@@ -26,3 +26,98 @@ async fn save() -> zi::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn save_preserves_line_endings() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let path = cx.tempfile("abc\r\ndef\r\n").unwrap();
+    let buf = cx.open(&path, zi::OpenFlags::empty()).await?;
+
+    cx.with(move |editor| {
+        assert_eq!(editor[buf].text().to_string(), "abc\ndef\n");
+        assert_eq!(*editor[buf].settings().line_ending.read(), zi::LineEnding::CrLf);
+        editor.edit(buf, &zi::Deltas::insert_at(3, "x".to_string())).unwrap();
+    })
+    .await;
+
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::empty())).await.await?;
+    assert_eq!(std::fs::read_to_string(&path)?, "abcx\r\ndef\r\n");
+
+    cx.with(|editor| editor.execute("set ff unix").unwrap()).await;
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::FORCE)).await.await?;
+    assert_eq!(std::fs::read_to_string(&path)?, "abcx\ndef\n");
+
+    cx.cleanup().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn save_preserves_encoding() -> zi::Result<()> {
+    let cx = new("").await;
+
+    // latin-1 `é` is not valid utf-8
+    let path = cx.tempfile("").unwrap();
+    std::fs::write(&path, b"caf\xe9\n")?;
+    let buf = cx.open(&path, zi::OpenFlags::empty()).await?;
+
+    cx.with(move |editor| {
+        assert_eq!(editor[buf].text().to_string(), "café\n");
+        assert_eq!(*editor[buf].settings().encoding.read(), zi::encoding_rs::WINDOWS_1252);
+        editor.edit(buf, &zi::Deltas::insert_at(0, "à ".to_string())).unwrap();
+    })
+    .await;
+
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::empty())).await.await?;
+    assert_eq!(std::fs::read(&path)?, b"\xe0 caf\xe9\n");
+
+    // utf-16 is detected via the byte order mark
+    let bytes =
+        [&[0xFF, 0xFE][..], &"abc\n".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>()]
+            .concat();
+    std::fs::write(&path, &bytes)?;
+    let buf = cx.open(&path, zi::OpenFlags::FORCE).await?;
+    cx.with(move |editor| {
+        assert_eq!(editor[buf].text().to_string(), "abc\n");
+        assert_eq!(*editor[buf].settings().encoding.read(), zi::encoding_rs::UTF_16LE);
+    })
+    .await;
+
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::FORCE)).await.await?;
+    assert_eq!(std::fs::read(&path)?, bytes);
+
+    cx.cleanup().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn save_trims_whitespace_and_fixes_eol() -> zi::Result<()> {
+    let cx = new("").await;
+
+    let path = cx.tempfile("abc  \n\t\ndef \t").unwrap();
+    let buf = cx.open(&path, zi::OpenFlags::empty()).await?;
+
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::FORCE)).await.await?;
+    assert_eq!(std::fs::read_to_string(&path)?, "abc\n\ndef\n");
+
+    // The changes are undone as a single step
+    cx.with(move |editor| {
+        editor.undo(buf).unwrap();
+        assert_eq!(editor[buf].text().to_string(), "abc  \n\t\ndef \t");
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.execute("set ttw false").unwrap();
+        editor.execute("set fixeol false").unwrap();
+    })
+    .await;
+    cx.with(move |editor| editor.save(buf, zi::SaveFlags::FORCE)).await.await?;
+    assert_eq!(std::fs::read_to_string(&path)?, "abc  \n\t\ndef \t");
+
+    cx.cleanup().await;
+
+    Ok(())
+}
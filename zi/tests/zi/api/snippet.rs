@@ -0,0 +1,80 @@
+use crate::new;
+
+#[tokio::test]
+async fn snippet_tabstops() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        editor.set_mode(zi::Mode::Insert);
+        editor.insert_snippet(zi::Active, "fn ${1:name}($2) {\n\t$0\n}").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn name() {\n    \n}\n");
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+
+        // Typing replaces the placeholder.
+        editor.insert(zi::Active, "foo").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn foo() {\n    \n}\n");
+
+        editor.tab().unwrap();
+        assert_eq!(editor.cursor(zi::Active), (0, 7));
+        editor.insert(zi::Active, "a").unwrap();
+
+        editor.backtab().unwrap();
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+        editor.insert(zi::Active, "bar").unwrap();
+        assert_eq!(editor.text(zi::Active), "fn bar(a) {\n    \n}\n");
+
+        editor.tab().unwrap();
+        assert_eq!(editor.cursor(zi::Active), (0, 8));
+        editor.tab().unwrap();
+        assert_eq!(editor.cursor(zi::Active), (1, 4));
+
+        // The snippet is done once the final tabstop is reached.
+        editor.tab().unwrap();
+        assert_eq!(editor.text(zi::Active), "fn bar(a) {\n        \n}\n");
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn snippet_completion() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    std::fs::create_dir(dir.join("snippets"))?;
+    std::fs::write(
+        dir.join("snippets/text.toml"),
+        "[function]\nprefix = \"fn\"\nbody = [\"fn ${1:name}() {\", \"\\t$0\", \"}\"]\n",
+    )?;
+    std::fs::write(
+        dir.join("snippets/all.json"),
+        r#"{ "file": { "prefix": "file", "body": "$TM_FILENAME" } }"#,
+    )?;
+
+    cx.open(dir.join("a.txt"), zi::OpenFlags::empty()).await?;
+    cx.with(move |editor| {
+        editor.load_snippets(dir.join("snippets")).unwrap();
+        editor.set_mode(zi::Mode::Insert);
+        editor.insert(zi::Active, "fi").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("<C-y>").unwrap();
+        assert_eq!(editor.text(zi::Active), "a.txt\n");
+        editor.insert(zi::Active, " fn").unwrap();
+    })
+    .await;
+
+    cx.with(|editor| {
+        editor.input("<C-y>").unwrap();
+        assert_eq!(editor.text(zi::Active), "a.txt fn name() {\n    \n}\n");
+        editor.insert(zi::Active, "x").unwrap();
+        editor.tab().unwrap();
+        assert_eq!(editor.text(zi::Active), "a.txt fn x() {\n    \n}\n");
+        assert_eq!(editor.cursor(zi::Active), (1, 4));
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
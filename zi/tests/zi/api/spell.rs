@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use zi::Active;
+
+use crate::new;
+
+const AFF: &str = "SFX S Y 1\nSFX S 0 s .\n";
+const DIC: &str = "3\nword/S\nthe\nthis\n";
+
+#[tokio::test]
+async fn spell_navigation() -> zi::Result<()> {
+    let cx = new("this wrod\nthe wordz words").await;
+    let dir = cx.tempdir()?;
+    cx.with(move |editor| {
+        editor.add_spell_dictionary("en_US", DIC, AFF).unwrap();
+        let settings = editor.buffer(Active).settings();
+        settings.spell.write(true);
+        settings.spell_file.write(Some(dir.join("en.add")));
+
+        assert_eq!(editor.goto_next_misspelling(Active), Some(5..9));
+        assert_eq!(editor.cursor(Active), (0, 5));
+        assert_eq!(editor.goto_next_misspelling(Active), Some(14..19));
+        assert_eq!(editor.cursor(Active), (1, 4));
+        assert_eq!(editor.goto_next_misspelling(Active), None);
+        assert_eq!(editor.goto_prev_misspelling(Active), Some(5..9));
+
+        assert_eq!(editor.spell_suggestions(Active), ["word"]);
+
+        // Words added to the spell file are no longer misspelled.
+        editor.add_good_word(Active).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("en.add")).unwrap(), "wrod\n");
+        assert_eq!(editor.goto_prev_misspelling(Active), None);
+        assert_eq!(editor.goto_next_misspelling(Active), Some(14..19));
+    })
+    .await;
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn spell_marks() -> zi::Result<()> {
+    let cx = new("this wrod").await;
+    let dir = cx.tempdir()?;
+    cx.with(move |editor| {
+        editor.add_spell_dictionary("en_US", DIC, AFF).unwrap();
+        let settings = editor.buffer(Active).settings();
+        settings.spell.write(true);
+        settings.spell_file.write(Some(dir.join("en.add")));
+    })
+    .await;
+
+    // The misspelled words are marked in the background once the buffer stops changing.
+    let mut marks = vec![];
+    for _ in 0..100 {
+        marks = cx
+            .with(|editor| editor.marks(Active, ..).map(|(_, range, _)| range).collect::<Vec<_>>())
+            .await;
+        if !marks.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(marks, [5..9]);
+
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn spell_disabled() {
+    let cx = new("wrod").await;
+    cx.with(|editor| {
+        editor.add_spell_dictionary("en_US", DIC, AFF).unwrap();
+        assert_eq!(editor.goto_next_misspelling(Active), None);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,66 @@
+use crate::new;
+
+#[tokio::test]
+async fn surround() {
+    let cx = new("foo bar").await;
+    cx.with(|editor| {
+        editor.input("ys3l)").unwrap();
+        assert_eq!(editor.cursor_line(), "(foo) bar");
+        assert_eq!(editor.cursor(zi::Active), (0, 0));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("cs)]").unwrap();
+        assert_eq!(editor.cursor_line(), "[foo] bar");
+
+        editor.input("ds]").unwrap();
+        assert_eq!(editor.cursor_line(), "foo bar");
+
+        editor.input("yss\"").unwrap();
+        assert_eq!(editor.cursor_line(), "\"foo bar\"");
+
+        editor.input("ds\"").unwrap();
+        assert_eq!(editor.cursor_line(), "foo bar");
+
+        editor.input("ys3l(").unwrap();
+        assert_eq!(editor.cursor_line(), "( foo ) bar");
+
+        // Nothing to delete
+        editor.input("ds]").unwrap();
+        assert_eq!(editor.cursor_line(), "( foo ) bar");
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+
+        editor.input("u").unwrap();
+        assert_eq!(editor.cursor_line(), "foo bar");
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn autopairs() {
+    let cx = new("").await;
+    cx.with(|editor| editor.execute("set autopairs true").unwrap()).await;
+    cx.with(|editor| {
+        editor.input("i(").unwrap();
+        assert_eq!(editor.cursor_line(), "()");
+        assert_eq!(editor.cursor(zi::Active), (0, 1));
+
+        // Typing the closing character moves over it
+        editor.input("a)").unwrap();
+        assert_eq!(editor.cursor_line(), "(a)");
+        assert_eq!(editor.cursor(zi::Active), (0, 3));
+
+        // Deleting the opening character of an empty pair deletes both
+        editor.input("[<BS>").unwrap();
+        assert_eq!(editor.cursor_line(), "(a)");
+
+        // Quotes are not paired after a word
+        editor.input("x'").unwrap();
+        assert_eq!(editor.cursor_line(), "(a)x'");
+
+        editor.input(" \"").unwrap();
+        assert_eq!(editor.cursor_line(), "(a)x' \"\"");
+    })
+    .await;
+    cx.cleanup().await;
+}
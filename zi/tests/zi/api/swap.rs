@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use zi::{Deltas, OpenFlags};
+
+use crate::new;
+
+#[tokio::test]
+async fn swap_recover() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    let path = cx.tempfile("abc\n")?;
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+
+    let swap_dir = dir.clone();
+    cx.with(move |editor| {
+        editor.settings().swap_dir.write(Some(swap_dir));
+        editor.edit(buf, &Deltas::insert_at(3, "def".to_string())).unwrap();
+        editor.write_swap(buf).expect("dirty buffer should have a swap file")
+    })
+    .await
+    .await?;
+
+    // Open the file in another editor as if the first one had crashed.
+    let recovery = new("").await;
+    recovery.with(move |editor| editor.settings().swap_dir.write(Some(dir))).await;
+    let recovered = recovery.open(&path, OpenFlags::empty()).await?;
+    recovery
+        .with(move |editor| {
+            assert!(editor.get_error().unwrap().contains(":recover"));
+            assert_eq!(editor[recovered].text().to_string(), "abc\n");
+            editor.recover(recovered).unwrap();
+            assert_eq!(editor[recovered].text().to_string(), "abcdef\n");
+            assert!(editor[recovered].flags().contains(zi::BufferFlags::DIRTY));
+        })
+        .await;
+
+    recovery.cleanup().await;
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn stale_swap() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    let path = cx.tempfile("abc\n")?;
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+
+    let swap_dir = dir.clone();
+    cx.with(move |editor| {
+        editor.settings().swap_dir.write(Some(swap_dir));
+        editor.edit(buf, &Deltas::insert_at(3, "def".to_string())).unwrap();
+        editor.write_swap(buf).unwrap()
+    })
+    .await
+    .await?;
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+    // The file was changed after the swap file was written.
+    std::fs::write(&path, "xyz\n")?;
+
+    let recovery = new("").await;
+    let swap_dir = dir.clone();
+    recovery.with(move |editor| editor.settings().swap_dir.write(Some(swap_dir))).await;
+    let recovered = recovery.open(&path, OpenFlags::empty()).await?;
+    recovery
+        .with(move |editor| {
+            assert_eq!(editor.get_error(), None);
+            assert!(editor.recover(recovered).is_err());
+        })
+        .await;
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 0);
+
+    recovery.cleanup().await;
+    cx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn swap_debounced() -> zi::Result<()> {
+    let cx = new("").await;
+    let dir = cx.tempdir()?;
+    let path = cx.tempfile("abc\n")?;
+    let buf = cx.open(&path, OpenFlags::empty()).await?;
+
+    let swap_dir = dir.clone();
+    cx.with(move |editor| {
+        editor.settings().swap_dir.write(Some(swap_dir));
+        editor.settings().update_time.write(Duration::from_millis(500));
+    })
+    .await;
+
+    // Each change restarts the wait, so the swap file isn't written while the buffer keeps changing.
+    for _ in 0..2 {
+        cx.with(move |editor| editor.edit(buf, &Deltas::insert_at(0, "x".to_string())).unwrap())
+            .await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 0);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+    cx.cleanup().await;
+    Ok(())
+}
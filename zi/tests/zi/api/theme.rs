@@ -0,0 +1,66 @@
+use zi::{Editor, HighlightName};
+
+use crate::new;
+
+#[tokio::test]
+async fn colorscheme() {
+    let cx = new("").await;
+
+    fn background(editor: &Editor) -> Option<String> {
+        let id = editor.highlight_id_by_name(HighlightName::BACKGROUND);
+        id.style(&editor.theme().read()).map(|style| style.to_string())
+    }
+
+    let (id, dark) = cx
+        .with(|editor| {
+            let names = editor.colorschemes();
+            assert!(names.iter().any(|name| name == "default"));
+            assert!(names.iter().any(|name| name == "solarized-light"));
+            assert!(editor.colorscheme("nonexistent").is_err());
+
+            editor.execute("colorscheme solarized-light").unwrap();
+            (editor.highlight_id_by_name(HighlightName::BACKGROUND), background(editor))
+        })
+        .await;
+
+    cx.with(move |editor| {
+        // Existing highlight ids remain valid for the new theme.
+        assert_eq!(editor.highlight_id_by_name(HighlightName::BACKGROUND), id);
+        assert_ne!(background(editor), dark);
+
+        editor.colorscheme("default").unwrap();
+        assert_eq!(background(editor), dark);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn highlight_command() {
+    let cx = new("").await;
+
+    fn style(editor: &Editor, name: &str) -> Option<String> {
+        let id = editor.highlight_id_by_name(name);
+        id.style(&editor.theme().read()).map(|style| style.to_string())
+    }
+
+    let keyword = cx
+        .with(|editor| {
+            editor.execute("highlight keyword fg=#ff0000 bold").unwrap();
+            editor.execute("highlight link search keyword").unwrap();
+            editor.execute("highlight custom bg=#00ff00").unwrap();
+            editor.highlight_id_by_name(HighlightName::KEYWORD)
+        })
+        .await;
+
+    cx.with(move |editor| {
+        assert_eq!(editor.highlight_id_by_name(HighlightName::KEYWORD), keyword);
+        assert_eq!(style(editor, "keyword").as_deref(), Some("fg=#ff0000"));
+        assert_eq!(style(editor, "search").as_deref(), Some("fg=#ff0000"));
+        assert_eq!(style(editor, "custom").as_deref(), Some("bg=#00ff00"));
+    })
+    .await;
+
+    cx.cleanup().await;
+}
@@ -149,3 +149,173 @@ async fn test_directional_focus() {
     .await;
     cx.cleanup().await;
 }
+
+#[tokio::test]
+async fn resize_views() {
+    let cx = new("").await;
+    let (left, right) = cx
+        .with(|editor| {
+            let left = editor.view(zi::Active).id();
+            let right = editor.split(zi::Active, Right, Fill(1));
+            assert_eq!(editor.view_area(left).width, 40);
+            assert_eq!(editor.view_area(right).width, 40);
+
+            editor.resize_view(right, zi::Dimension::Width, 10);
+            assert_eq!(editor.view_area(left).width, 30);
+            assert_eq!(editor.view_area(right).width, 50);
+
+            // There is no split to resize along
+            let height = editor.view_area(right).height;
+            editor.resize_view(right, zi::Dimension::Height, 1);
+            assert_eq!(editor.view_area(right).height, height);
+
+            // Neighbours keep at least one cell
+            editor.set_view_size(left, zi::Dimension::Width, u16::MAX);
+            assert_eq!(editor.view_area(left).width, 79);
+            assert_eq!(editor.view_area(right).width, 1);
+
+            editor.input("<C-w>=").unwrap();
+            assert_eq!(editor.view_area(left).width, 40);
+
+            editor.input("5<C-w>>").unwrap();
+            assert_eq!(editor.view_area(right).width, 45);
+            editor.input("<C-w><").unwrap();
+            assert_eq!(editor.view_area(right).width, 44);
+
+            editor.execute("vertical resize 20").unwrap();
+            (left, right)
+        })
+        .await;
+
+    cx.with(move |editor| {
+        assert_eq!(editor.view_area(left).width, 60);
+        assert_eq!(editor.view_area(right).width, 20);
+        editor.execute("vertical resize -5").unwrap();
+    })
+    .await;
+
+    cx.with(move |editor| assert_eq!(editor.view_area(right).width, 15)).await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn rotate_and_exchange_views() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        let a = editor.view(zi::Active).id();
+        let b = editor.split(zi::Active, Right, Fill(1));
+        let c = editor.split(zi::Active, Right, Fill(1));
+        let x = |editor: &zi::Editor, view| editor.view_area(view).x;
+        assert!(x(editor, a) < x(editor, b) && x(editor, b) < x(editor, c));
+
+        editor.input("<C-w>r").unwrap();
+        assert!(x(editor, c) < x(editor, a) && x(editor, a) < x(editor, b));
+        assert_eq!(editor.view(zi::Active).id(), c);
+
+        editor.input("<C-w>R").unwrap();
+        assert!(x(editor, a) < x(editor, b) && x(editor, b) < x(editor, c));
+
+        // Exchanging with the previous view as there is no next one, the focus stays in place
+        editor.input("<C-w>x").unwrap();
+        assert!(x(editor, a) < x(editor, c) && x(editor, c) < x(editor, b));
+        assert_eq!(editor.view(zi::Active).id(), b);
+
+        editor.input("1<C-w>x").unwrap();
+        assert!(x(editor, b) < x(editor, c) && x(editor, c) < x(editor, a));
+        assert_eq!(editor.view(zi::Active).id(), a);
+
+        editor.input("<C-w>J").unwrap();
+        assert_eq!(editor.view(zi::Active).id(), a);
+        assert_eq!(editor.view_area(a).width, 80);
+        assert_eq!(editor.view_area(a).x, 0);
+        assert!(editor.view_area(a).y > editor.view_area(b).y);
+        assert_eq!(editor.view_area(b).y, editor.view_area(c).y);
+
+        assert!(editor.rotate_views(a, true).is_err());
+        assert!(editor.exchange_view(a, None).is_err());
+
+        editor.input("<C-w>H").unwrap();
+        assert_eq!(editor.view_area(a).x, 0);
+        assert_eq!(editor.view_area(a).y, 0);
+        assert_eq!(editor.view_area(a).height, editor.view_area(b).height);
+    })
+    .await;
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn placement_rules() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        let rules = zi::PlacementRules::default()
+            .with(zi::BufferKind::Explorer, zi::Placement::Dock { edge: Left, size: Length(40) });
+        editor.settings().placement_rules.write(rules);
+
+        let prev = editor.view(zi::Active).id();
+        let prev_buf = editor.buffer(zi::Active).id();
+        editor.open_file_explorer(".");
+        assert_eq!(editor.views().count(), 2);
+        assert_ne!(editor.view(zi::Active).id(), prev);
+        assert_eq!(editor.buffer(zi::Active).file_type(), zi::filetype!(explorer));
+        assert_eq!(editor.view(prev).buffer(), prev_buf);
+
+        // Navigating within the explorer should reuse the docked view
+        editor.open_file_explorer("..");
+        assert_eq!(editor.views().count(), 2);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn placement_rules_quickfix_and_help() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        let dock = zi::Placement::Dock { edge: Down, size: Length(10) };
+        let float = zi::Placement::Float { width: Percentage(50), height: Percentage(50) };
+        let rules = zi::PlacementRules::default()
+            .with(zi::BufferKind::Quickfix, dock)
+            .with(zi::BufferKind::Help, float);
+        editor.settings().placement_rules.write(rules);
+        let size = editor.size();
+        let area = zi::Rect::new(0, 0, size.width, size.height);
+
+        let main = editor.view(zi::Active).id();
+        editor.open_quickfix();
+        let docked = dock.area(area);
+        let picker_views =
+            editor.views().map(|view| view.id()).filter(|&view| view != main).collect::<Vec<_>>();
+        assert_eq!(picker_views.len(), 3);
+        for view in picker_views {
+            let view_area = editor.view_area(view);
+            assert_eq!(docked.intersection(view_area), view_area, "quickfix should be docked");
+        }
+
+        editor.close_view(zi::Active);
+        assert_eq!(editor.views().count(), 1);
+
+        let help = editor.open_help();
+        assert_eq!(editor.view(zi::Active).id(), help);
+        assert_eq!(editor.view_area(help), float.area(area), "help should float");
+        assert!(editor.text(help).to_string().contains(":help"));
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[test]
+fn placement_area() {
+    let area = zi::Rect::new(0, 0, 100, 50);
+    assert_eq!(
+        zi::Placement::Dock { edge: Down, size: Percentage(30) }.area(area),
+        zi::Rect::new(0, 35, 100, 15)
+    );
+    assert_eq!(
+        zi::Placement::Dock { edge: Left, size: Length(40) }.area(area),
+        zi::Rect::new(0, 0, 40, 50)
+    );
+    let float = zi::Placement::Float { width: Percentage(50), height: Percentage(50) }.area(area);
+    assert_eq!((float.width, float.height), (50, 25));
+}
@@ -54,7 +54,7 @@ async fn visual_block_yank() {
         assert_eq!(editor.mode(), zi::Mode::Normal);
         let reg = editor.register('"').unwrap();
         assert_eq!(reg.content, "ab\nde\ngh");
-        assert_eq!(reg.kind, zi::RegisterKind::Charwise);
+        assert_eq!(reg.kind, zi::RegisterKind::Blockwise);
     })
     .await;
     cx.cleanup().await;
@@ -126,7 +126,7 @@ async fn visual_block_yank_with_empty_lines() {
         assert_eq!(editor.mode(), zi::Mode::Normal);
         let reg = editor.register('"').unwrap();
         assert_eq!(reg.content, "ab\n\ngh");
-        assert_eq!(reg.kind, zi::RegisterKind::Charwise);
+        assert_eq!(reg.kind, zi::RegisterKind::Blockwise);
     })
     .await;
     cx.cleanup().await;
@@ -141,7 +141,7 @@ async fn visual_block_yank_with_short_lines() {
         assert_eq!(editor.mode(), zi::Mode::Normal);
         let reg = editor.register('"').unwrap();
         assert_eq!(reg.content, "bcde\nb\nbcde");
-        assert_eq!(reg.kind, zi::RegisterKind::Charwise);
+        assert_eq!(reg.kind, zi::RegisterKind::Blockwise);
     })
     .await;
     cx.cleanup().await;
@@ -23,3 +23,32 @@ async fn composite_escape() {
     check("iffd", "f").await;
     check("ifffx", "fffx").await;
 }
+
+#[test]
+fn parse_enhanced_keys() {
+    use zi::input::{KeyCode, KeyEvent, KeyModifiers};
+
+    let key = "<D-s>".parse::<KeyEvent>().unwrap();
+    assert_eq!(key, KeyEvent::new(KeyCode::Char('s'), KeyModifiers::SUPER));
+    assert_eq!(key.to_string(), "<D-s>");
+
+    let key = "<C-H-x>".parse::<KeyEvent>().unwrap();
+    assert_eq!(key.modifiers(), KeyModifiers::CONTROL | KeyModifiers::HYPER);
+    assert_eq!(key.to_string(), "<C-H-x>");
+
+    // These are only distinguishable with the kitty keyboard protocol
+    assert_ne!("<C-i>".parse::<KeyEvent>().unwrap(), "<Tab>".parse::<KeyEvent>().unwrap());
+    assert_ne!("<C-m>".parse::<KeyEvent>().unwrap(), "<CR>".parse::<KeyEvent>().unwrap());
+}
+
+#[tokio::test]
+async fn key_release_is_ignored() {
+    let cx = new("").await;
+    cx.with(|editor| {
+        let key = "i".parse::<zi::input::KeyEvent>().unwrap();
+        editor.handle_input(zi::input::Event::KeyRelease(key));
+        assert_eq!(editor.mode(), zi::Mode::Normal);
+    })
+    .await;
+    cx.cleanup().await;
+}
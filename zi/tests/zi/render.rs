@@ -1,6 +1,15 @@
 use super::*;
 
+mod conceal;
+mod damage;
+mod diff;
 mod file_picker;
+mod float;
+mod frame_rate;
 mod insert;
 mod line_number;
+mod list;
 mod split;
+mod statusline;
+mod surface;
+mod virtual_text;
@@ -0,0 +1,65 @@
+use expect_test::expect;
+use zi::{Active, Conceal, LineNumberStyle, Mark};
+
+use crate::new;
+
+#[tokio::test]
+async fn conceal_marks() {
+    let cx = new("a **b** c\nd **e** f\n").with_size((24, 5)).await;
+
+    cx.with(|editor| {
+        let settings = editor.view(Active).settings();
+        settings.line_number_style.write(LineNumberStyle::None);
+        settings.conceal_level.write(2);
+
+        let ns = editor.create_namespace("test");
+        let buf = editor.buffer(Active).id();
+        for (byte, substitute) in [(2, None), (5, None), (12, None), (15, Some('~'))] {
+            let conceal = Conceal { substitute };
+            editor.create_mark(buf, ns, Mark::builder(byte).width(2).conceal(conceal));
+        }
+    })
+    .await;
+
+    cx.snapshot(expect![[r#"
+        "  a b c                 "
+        "  d e~ f                "
+        "  |                     "
+        "buffer://scratch:3:0    "
+        "                        "
+    "#]])
+        .await;
+
+    // The cursor line is not concealed.
+    cx.with(|editor| editor.set_cursor(Active, (0, 0))).await;
+    cx.snapshot(expect![[r#"
+        "  | **b** c             "
+        "  d e~ f                "
+        "                        "
+        "buffer://scratch:1:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.with(|editor| editor.view(Active).settings().conceal_level.write(1)).await;
+    cx.snapshot(expect![[r#"
+        "  | **b** c             "
+        "  d  e~ f               "
+        "                        "
+        "buffer://scratch:1:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.with(|editor| editor.view(Active).settings().conceal_level.write(3)).await;
+    cx.snapshot(expect![[r#"
+        "  | **b** c             "
+        "  d e f                 "
+        "                        "
+        "buffer://scratch:1:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,79 @@
+use zi::Constraint::*;
+use zi::Direction::*;
+
+use crate::new;
+
+#[tokio::test]
+async fn damage_on_typing() {
+    let cx = new("abc\n").with_size((40, 8)).await;
+    cx.with(|editor| {
+        let main = editor.view(zi::Active).id();
+        let other = editor.split(zi::Active, Right, Fill(1));
+        let buf = editor.create_readonly_buffer("other", "other".as_bytes());
+        editor.set_buffer(other, buf);
+        editor.focus(main);
+
+        // The views rendered, the views reused, and the rows rendered.
+        let render = |editor: &mut zi::Editor| {
+            let before = editor.render_stats();
+            editor.render(&mut tui::TestFrame::new(40, 8));
+            let after = editor.render_stats();
+            (
+                after.views_rendered - before.views_rendered,
+                after.views_reused - before.views_reused,
+                after.rows_rendered - before.rows_rendered,
+            )
+        };
+
+        assert_eq!(render(editor), (2, 0, 12), "the first frame renders everything");
+        assert_eq!(render(editor), (0, 2, 0), "nothing changed so everything is reused");
+
+        editor.input("ix").unwrap();
+        assert_eq!(render(editor), (2, 0, 12), "changing mode may change any view");
+        editor.input("y").unwrap();
+        assert_eq!(render(editor), (1, 1, 1), "only the edited line is rendered");
+        assert_eq!(editor.text(zi::Active), "xyabc\n");
+
+        // Commands may change anything even when typed.
+        editor.input("<Esc>").unwrap();
+        assert_eq!(render(editor), (2, 0, 12));
+        editor.input(":set list true<CR>").unwrap();
+        assert_eq!(render(editor), (2, 0, 12), "commands may change any view");
+        assert_eq!(render(editor), (0, 2, 0));
+    })
+    .await;
+
+    // Requests may change anything, so nothing is reused after them.
+    let reused = cx.with(|editor| editor.render_stats().views_reused).await;
+    cx.with(|editor| editor.settings().render_stats.write(true)).await;
+    cx.with(move |editor| {
+        editor.render(&mut tui::TestFrame::new(40, 8));
+        assert_eq!(editor.render_stats().views_reused, reused);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn damage_moving_cursor() {
+    let cx = new("0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n").with_size((40, 8)).await;
+    cx.with(|editor| {
+        let rows = |editor: &mut zi::Editor| {
+            let before = editor.render_stats().rows_rendered;
+            editor.render(&mut tui::TestFrame::new(40, 8));
+            editor.render_stats().rows_rendered - before
+        };
+
+        assert_eq!(rows(editor), 6);
+        // The rows of the lines the cursor moved between, for the cursor line highlight.
+        editor.input("jj").unwrap();
+        assert_eq!(rows(editor), 2);
+        // Joining lines moves the lines below up, so the rows below are rendered too.
+        editor.input("J").unwrap();
+        assert_eq!(rows(editor), 4);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
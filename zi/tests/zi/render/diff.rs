@@ -0,0 +1,35 @@
+use expect_test::expect;
+use zi::Constraint::*;
+use zi::Direction::*;
+
+use crate::new;
+
+#[tokio::test]
+async fn diff_filler_rows() {
+    let cx = new("a\nb\nc\nd\n").with_size((51, 8)).await;
+
+    cx.with(|editor| {
+        let a = editor.view(zi::Active).id();
+        let b = editor.split(a, Right, Fill(1));
+        let buf = editor.create_readonly_buffer("b.txt", "a\nx\nc\n".as_bytes());
+        editor.set_buffer(b, buf);
+        editor.focus(a);
+        editor.diff_views(a, b);
+    })
+    .await;
+
+    // The line only the left buffer has is aligned with a filler row on the right.
+    cx.snapshot(expect![[r#"
+        "   1 a                       1 a                   "
+        "   2 b                       2 x                   "
+        "   3 c                    _  3 c                   "
+        "   4 d                         --------------------"
+        "   5 |                       4                     "
+        "                                                   "
+        "buffer://scratch:5:0                               "
+        "                                                   "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}
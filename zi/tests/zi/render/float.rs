@@ -0,0 +1,64 @@
+use expect_test::expect;
+use zi::{Active, FloatAnchor, FloatConfig, LineNumberStyle};
+
+use crate::new;
+
+#[tokio::test]
+async fn float_at_cursor() {
+    let cx = new("a\nb\nc\nd\n").with_size((20, 7)).await;
+    let main = cx
+        .with(|editor| {
+            editor.view(Active).settings().line_number_style.write(LineNumberStyle::None);
+            editor.view(Active).id()
+        })
+        .await;
+    cx.render().await;
+
+    cx.with(move |editor| {
+        let buf = editor.create_readonly_buffer("hover", "hello".as_bytes());
+        let config = FloatConfig::new(FloatAnchor::Cursor { x: 0, y: 1 }, 7, 1)
+            .with_border(true)
+            .with_focusable(false);
+        editor.open_float(buf, config);
+        assert_eq!(editor.view(Active).id(), main, "unfocusable floats don't take focus");
+    })
+    .await;
+
+    cx.snapshot(expect![[r#"
+        "  |                 "
+        "  ┌───────┐         "
+        "  │  hello│         "
+        "  └───────┘         "
+        "                    "
+        "buffer://scratch:1:0"
+        "                    "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn float_focus() {
+    let cx = new("a\n").await;
+    cx.with(|editor| {
+        let main = editor.view(Active).id();
+        let buf = editor.create_readonly_buffer("float", "float".as_bytes());
+        let below = editor.open_float(buf, FloatConfig::new(FloatAnchor::Center, 10, 1));
+        assert_eq!(editor.view(Active).id(), below);
+
+        let buf = editor.create_readonly_buffer("above", "above".as_bytes());
+        let config = FloatConfig::new(FloatAnchor::Center, 10, 1).with_z_index(1);
+        let above = editor.open_float(buf, config);
+        assert_eq!(editor.view(Active).id(), above);
+
+        // Closing a float focuses the previous focusable float, and then the split tree.
+        editor.close_view(above);
+        assert_eq!(editor.view(Active).id(), below);
+        editor.close_view(below);
+        assert_eq!(editor.view(Active).id(), main);
+    })
+    .await;
+
+    cx.cleanup().await;
+}
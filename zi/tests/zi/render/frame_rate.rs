@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use futures_util::{StreamExt, stream};
+use zi::input::{Event, KeyCode, KeyEvent};
+
+#[tokio::test(start_paused = true)]
+async fn coalesce_input() {
+    let (mut editor, tasks) = zi::Editor::new(zi::DummyBackend, zi::Size::new(40, 8));
+    let keys = "ihello".chars().map(KeyCode::Char).chain([KeyCode::Esc]);
+    let events =
+        stream::iter(keys.map(KeyEvent::from).map(Event::Key).map(Ok)).chain(stream::pending());
+
+    let mut frames = vec![];
+    let run = editor.run(events, tasks, |editor| {
+        frames.push(editor.text(zi::Active).to_string());
+        Ok(())
+    });
+    // The editor runs until the timeout as there is no more input.
+    let _ = tokio::time::timeout(Duration::from_secs(1), run).await;
+
+    // The keys available at once are handled together and rendered once the redraw interval
+    // since the first frame has passed.
+    assert_eq!(frames.len(), 2, "{frames:?}");
+    assert_eq!(frames[1].trim_end(), "hello");
+}
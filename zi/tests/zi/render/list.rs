@@ -0,0 +1,39 @@
+use expect_test::expect;
+use zi::{Active, LineNumberStyle};
+
+use crate::new;
+
+#[tokio::test]
+async fn list_chars() {
+    let cx = new("a\tb  \nc\u{a0}d\n  \n").with_size((24, 6)).await;
+
+    cx.with(|editor| {
+        let settings = editor.view(Active).settings();
+        settings.line_number_style.write(LineNumberStyle::None);
+        settings.list.write(true);
+    })
+    .await;
+
+    cx.snapshot(expect![[r#"
+        "  a>  b--               "
+        "  c+d                   "
+        "  --                    "
+        "  |                     "
+        "buffer://scratch:4:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.with(|editor| editor.execute("set listchars tab:»·,trail:~,nbsp:_").unwrap()).await;
+    cx.snapshot(expect![[r#"
+        "  a»··b~~               "
+        "  c_d                   "
+        "  ~~                    "
+        "  |                     "
+        "buffer://scratch:4:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}
@@ -0,0 +1,33 @@
+use expect_test::expect;
+use zi::{Active, LineNumberStyle};
+
+use crate::new;
+
+#[tokio::test]
+async fn statusline_sections() {
+    let cx = new("a\nb\nc\nd\n").with_size((60, 4)).await;
+    cx.with(|editor| {
+        editor.view(Active).settings().line_number_style.write(LineNumberStyle::None);
+        editor.execute("set statusline {mode} {file}{dirty} %= {line}:{col} %= {percent}").unwrap();
+    })
+    .await;
+
+    cx.snapshot(expect![[r#"
+        "  |                                                         "
+        "  b                                                         "
+        "NORMAL buffer://scratch     1:0                          25%"
+        "                                                            "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}
+
+#[test]
+fn statusline_parse_errors() {
+    let err = |s: &str| s.parse::<zi::StatusLine>().unwrap_err().to_string();
+    assert_eq!(err("{unknown}"), "unknown status line segment: `unknown`");
+    assert_eq!(err("{file"), "unclosed `{` in status line");
+    assert_eq!(err("file}"), "unmatched `}` in status line");
+    assert_eq!(err("a %= b %= c %= d"), "expected at most 3 sections separated by `%=`");
+}
@@ -0,0 +1,66 @@
+use zi::{Active, FloatAnchor, FloatConfig, LineNumberStyle};
+
+use crate::new;
+
+#[tokio::test]
+async fn render_surface() {
+    let cx = new("日本\n").with_size((20, 5)).await;
+    cx.with(|editor| {
+        editor.view(Active).settings().line_number_style.write(LineNumberStyle::None);
+        let surface = editor.render_surface(20, 5);
+        assert_eq!((surface.width(), surface.height()), (20, 5));
+        let symbols = surface.row(0).iter().map(|cell| cell.symbol.as_str()).collect::<Vec<_>>();
+        // The cells covered by wide characters are empty.
+        assert_eq!(symbols[..7], [" ", " ", "日", "", "本", "", " "]);
+        assert_eq!(surface.cursor().unwrap().shape, tui::CursorShape::Block);
+
+        // Drawing from scratch resizes and then draws everything.
+        let ops = tui::Surface::default().diff(&surface);
+        assert_eq!(ops[0], tui::RenderOp::Resize { width: 20, height: 5 });
+        assert!(surface.diff(&surface).is_empty());
+
+        editor.input("ix").unwrap();
+        let next = editor.render_surface(20, 5);
+        assert_eq!(next.cursor().unwrap().shape, tui::CursorShape::Bar);
+        let ops = surface.diff(&next);
+        assert!(ops.iter().all(|op| !matches!(op, tui::RenderOp::Resize { .. })));
+        assert!(ops.iter().any(|op| matches!(op, tui::RenderOp::Draw { y: 0, .. })));
+        assert!(ops.iter().any(|op| matches!(op, tui::RenderOp::Cursor(Some(_)))));
+
+        let buf = editor.create_readonly_buffer("float", "float".as_bytes());
+        let config = FloatConfig::new(FloatAnchor::Center, 10, 1).with_border(true);
+        editor.open_float(buf, config.with_z_index(2));
+        let floats = editor.render_surface(20, 5).floats().to_vec();
+        assert_eq!(floats.len(), 1);
+        assert_eq!((floats[0].width, floats[0].height, floats[0].z_index), (12, 3, 2));
+
+        // Rendering at another size resizes the editor first.
+        let surface = editor.render_surface(30, 8);
+        assert_eq!((surface.width(), surface.height()), (30, 8));
+        assert_eq!(editor.size(), zi::Size::new(30, 6));
+    })
+    .await;
+
+    cx.cleanup().await;
+}
+
+#[tokio::test]
+async fn guicursor() {
+    let cx = new("abc\n").with_size((20, 5)).await;
+    cx.with(|editor| {
+        editor.execute("set guicursor a:hor20-blinkon0,i:block-blinkon500").unwrap();
+        let style = editor.cursor_style();
+        assert_eq!((style.shape, style.blink), (tui::CursorShape::Underline, false));
+
+        editor.input("i").unwrap();
+        let cursor = editor.render_surface(20, 5).cursor().unwrap();
+        assert_eq!((cursor.shape, cursor.blink), (tui::CursorShape::Block, true));
+
+        assert!(editor.execute("set guicursor x:block").is_err());
+        assert!(editor.execute("set guicursor n:ver101").is_err());
+        assert!(editor.execute("set guicursor n:block-Cursor").is_err());
+    })
+    .await;
+
+    cx.cleanup().await;
+}
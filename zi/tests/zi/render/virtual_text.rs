@@ -0,0 +1,33 @@
+use expect_test::expect;
+use zi::{Active, LineNumberStyle, Mark, VirtualText};
+
+use crate::new;
+
+#[tokio::test]
+async fn virtual_text_marks() {
+    let cx = new("abc\ndef\n").with_size((24, 5)).await;
+
+    cx.with(|editor| {
+        editor.view(Active).settings().line_number_style.write(LineNumberStyle::None);
+
+        let ns = editor.create_namespace("test");
+        let buf = editor.buffer(Active).id();
+        let hl = editor.highlight_id_by_name("Comment");
+        for (byte, text) in [(0, "error"), (5, "warning")] {
+            let text = VirtualText { text: text.into(), hl };
+            editor.create_mark(buf, ns, Mark::builder(byte).virtual_text(text));
+        }
+    })
+    .await;
+
+    cx.snapshot(expect![[r#"
+        "  abc    error          "
+        "  def    warning        "
+        "  |                     "
+        "buffer://scratch:3:0    "
+        "                        "
+    "#]])
+        .await;
+
+    cx.cleanup().await;
+}